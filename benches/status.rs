@@ -0,0 +1,169 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use home_control::{
+    api::Status,
+    config::HomeControlConfig,
+    forecast::{ForecastCache, ForecastCacheConfig},
+    home_assistant::{self, Client, State},
+};
+use serde_json::json;
+use tokio::{runtime::Runtime, sync::mpsc};
+
+/// The number of entities of the fixture, like a large Home Assistant
+/// instance.
+const ENTITIES: usize = 2000;
+
+const WEATHER_ENTITY: &str = "weather.home";
+
+fn config() -> HomeControlConfig {
+    serde_json::from_value(json!({
+        "location": "Home",
+        "weather_entity": WEATHER_ENTITY,
+        "windows": [
+            {
+                "room": "Living room",
+                "climate_entity": "climate.living_room",
+                "sensors": ["sensor.sensor_1", "sensor.sensor_2"],
+            },
+        ],
+    }))
+    .expect("valid config")
+}
+
+fn states() -> Vec<State> {
+    let now = Utc::now();
+    let state = |entity_id: String, state: &str, attributes: serde_json::Value| State {
+        entity_id,
+        attributes: attributes.into(),
+        context: Default::default(),
+        last_changed: now,
+        last_updated: now,
+        state: state.to_string(),
+    };
+
+    let mut states: Vec<_> = (0..ENTITIES - 1)
+        .map(|i| {
+            state(
+                format!("sensor.sensor_{}", i),
+                "21.5",
+                json!({
+                    "friendly_name": format!("Sensor {}", i),
+                    "unit_of_measurement": "°C",
+                    "device_class": "temperature",
+                    "state_class": "measurement",
+                }),
+            )
+        })
+        .collect();
+
+    states.push(state(
+        WEATHER_ENTITY.to_string(),
+        "sunny",
+        json!({
+            "friendly_name": "Home",
+            "humidity": 45.0,
+            "pressure": 1013.0,
+            "temperature": 21.0,
+            "wind_bearing": 180.0,
+            "wind_speed": 10.0,
+            "forecast": (0..48).map(|hour| json!({
+                "condition": "sunny",
+                "datetime": now + chrono::Duration::hours(hour),
+                "precipitation": 0.0,
+                "temperature": 21.0,
+                "templow": 12.0,
+                "wind_bearing": 180.0,
+                "wind_speed": 10.0,
+            })).collect::<Vec<_>>(),
+        }),
+    ));
+
+    states
+}
+
+/// Get a controller whose entity cache holds the fixture.
+fn controller(runtime: &Runtime) -> home_assistant::Controller {
+    runtime.block_on(async {
+        let client = Client::new("localhost", String::new(), Default::default(), None, false)
+            .await
+            .expect("valid client");
+        let controller = client.new_controller();
+        let (tx, rx) = mpsc::channel(ENTITIES);
+
+        for state in states() {
+            tx.send(state).await.expect("running client");
+        }
+
+        tokio::spawn(client.run_simulated(rx));
+
+        let mut watch = controller.watch_states();
+
+        while controller.entity(WEATHER_ENTITY).await.is_none() {
+            watch.changed().await.expect("running client");
+        }
+
+        controller
+    })
+}
+
+fn entity_cache(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let controller = controller(&runtime);
+
+    c.bench_function("entity cache status", |b| {
+        b.iter(|| runtime.block_on(controller.status()))
+    });
+    c.bench_function("entity cache entity", |b| {
+        b.iter(|| runtime.block_on(controller.entity("sensor.sensor_1000")))
+    });
+}
+
+fn status(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let controller = controller(&runtime);
+    let config = config();
+
+    // Not caching, so that the forecast is computed on every iteration.
+    let forecast_cache = ForecastCache::new(ForecastCacheConfig {
+        ttl: Duration::ZERO,
+        ..Default::default()
+    });
+
+    c.bench_function("Status::new", |b| {
+        b.iter_batched(
+            || runtime.block_on(controller.status()),
+            |ha_status| {
+                Status::new(
+                    ha_status,
+                    &config,
+                    Vec::new(),
+                    None,
+                    None,
+                    Vec::new(),
+                    &forecast_cache,
+                )
+                .unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("status serialization", |b| {
+        let status = Status::new(
+            runtime.block_on(controller.status()),
+            &config,
+            Vec::new(),
+            None,
+            None,
+            Vec::new(),
+            &forecast_cache,
+        )
+        .unwrap();
+
+        b.iter(|| serde_json::to_vec(&status).unwrap())
+    });
+}
+
+criterion_group!(benches, entity_cache, status);
+criterion_main!(benches);
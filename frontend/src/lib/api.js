@@ -1,11 +1,26 @@
 import { writable, readable, derived, get } from "svelte/store";
 
+// The prefix of the API can be configured: the server tells it in the
+// document it serves.
+export const apiPrefix =
+	(typeof document !== 'undefined' &&
+		document.querySelector('meta[name="home-control-api-prefix"]')?.getAttribute('content')) ||
+	'/api/v1';
+
+export function apiUrl(path) {
+	return apiPrefix + path;
+}
+
 async function apiGetStatus() {
-	return await (await fetch('/api/v1/status')).json();
+	return await (await fetch(apiUrl('/status'))).json();
 }
 
 const initialState = {
 	status: {},
+	hazards: [],
+	voice: null,
+	announcement: null,
+	wakeup: null,
 	isLoading: false,
 	error: ""
 };
@@ -15,11 +30,21 @@ function createApiStore() {
 
 	const api = {
 		subscribe,
+		dismissAnnouncement: () => {
+			update(state => (state = { ...state, announcement: null }));
+		},
+		cancelWakeup: async () => {
+			try {
+				await fetch(apiUrl('/wakeup/cancel'), { method: 'POST' });
+			} catch (e) {
+				console.error(e);
+			}
+		},
 		init: async () => {
 			update(state => (state = { ...state, isLoading: true }));
 
 			try {
-				const status = await (await fetch('/api/v1/status')).json();
+				const status = await (await fetch(apiUrl('/status'))).json();
 				update(state => (state = { ...state, status: status }));
 			} catch (e) {
 				update(state => (state = { ...state, error: e.message }));
@@ -29,17 +54,111 @@ function createApiStore() {
 		},
 	};
 
-	let apiStatusPoller;
+	// Long-poll the status, which only returns once the states changed from
+	// the generation we have, or after a while.
+	async function waitForStatus() {
+		let generation;
+
+		for (;;) {
+			try {
+				const query = generation === undefined ? '' : `?since=${generation}`;
+				const response = await fetch(apiUrl(`/status/wait${query}`));
+
+				if (!response.ok) {
+					throw new Error(`status ${response.status}`);
+				}
+
+				const statusUpdate = await response.json();
+
+				generation = statusUpdate.generation;
+				update(state => (state = { ...state, status: statusUpdate.status, error: '' }));
+			} catch (e) {
+				update(state => (state = { ...state, error: e.message }));
+				await new Promise(resolve => setTimeout(resolve, 1000));
+			}
+		}
+	}
 
-	function setupApiStatusPoller() {
-		if (apiStatusPoller) {
-			clearInterval(apiStatusPoller);
+	// Listen to the status pushed by the server, and fall back to the long
+	// poll if the browser or the server can't.
+	function listenForStatus() {
+		if (typeof EventSource === 'undefined') {
+			waitForStatus();
+			return;
 		}
 
-		apiStatusPoller = setInterval(api.init, 1000);
+		const events = new EventSource(apiUrl('/events'));
+		let received = false;
+
+		events.addEventListener('status', event => {
+			const statusUpdate = JSON.parse(event.data);
+
+			received = true;
+			update(state => (state = { ...state, status: statusUpdate.status, error: '' }));
+		});
+
+		// The leaks and smoke detected, pushed as soon as they are.
+		events.addEventListener('hazards', event => {
+			const hazards = JSON.parse(event.data);
+
+			update(state => (state = { ...state, hazards: hazards }));
+		});
+
+		// The message to show full-screen, or null once taken down.
+		events.addEventListener('announcement', event => {
+			const announcement = JSON.parse(event.data);
+
+			update(state => (state = { ...state, announcement: announcement }));
+		});
+
+		// The wake-up in progress, or null once over.
+		events.addEventListener('wakeup', event => {
+			const wakeup = JSON.parse(event.data);
+
+			update(state => (state = { ...state, wakeup: wakeup }));
+		});
+
+		// The progress of the Assist sessions opened by the wake word, shown
+		// for a while once they end.
+		let voiceTimeout;
+
+		events.addEventListener('voice', event => {
+			const voiceEvent = JSON.parse(event.data);
+
+			clearTimeout(voiceTimeout);
+			update(state => {
+				const voice = voiceEvent.type === 'wake' ? { transcript: '' } : { ...state.voice };
+
+				switch (voiceEvent.type) {
+					case 'transcript':
+						voice.transcript = voiceEvent.text;
+						break;
+					case 'response':
+						voice.response = voiceEvent.text;
+						break;
+					case 'error':
+						voice.error = voiceEvent.message;
+						break;
+				}
+
+				return { ...state, voice: voice };
+			});
+
+			if (voiceEvent.type === 'response' || voiceEvent.type === 'error') {
+				voiceTimeout = setTimeout(() => update(state => (state = { ...state, voice: null })), 8000);
+			}
+		});
+
+		events.onerror = () => {
+			// The browser reconnects by itself once the stream worked.
+			if (!received) {
+				events.close();
+				waitForStatus();
+			}
+		};
 	}
 
-	setupApiStatusPoller();
+	listenForStatus();
 
 	return api;
 }
@@ -8,7 +8,14 @@ const config = {
 	preprocess: preprocess(),
 
 	kit: {
-		adapter: adapter(),
+		// The assets are embedded pre-compressed in the binary.
+		adapter: adapter({ precompress: true }),
+
+		// The frontend is served under the static prefix of the server, which
+		// it must be built for.
+		paths: {
+			base: (process.env.STATIC_PREFIX ?? '').replace(/\/+$/, '')
+		},
 
 		// hydrate the <div id="svelte"> element in src/app.html
 		target: '#svelte'
@@ -0,0 +1,223 @@
+//! The compressed states of the `subscribe_entities` subscription.
+//!
+//! Home-Assistant first sends the full states of the subscribed entities, then
+//! only the differences with the previous states.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{Attributes, Context, State};
+
+/// The changes to the subscribed entities.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct EntitiesEvent {
+    /// The entities added to the subscription, with their full states.
+    #[serde(rename = "a", default, skip_serializing_if = "HashMap::is_empty")]
+    pub added: HashMap<String, CompressedState>,
+
+    /// The changed entities, with the differences of their states.
+    #[serde(rename = "c", default, skip_serializing_if = "HashMap::is_empty")]
+    pub changed: HashMap<String, StateDiff>,
+
+    /// The removed entities.
+    #[serde(rename = "r", default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+}
+
+impl EntitiesEvent {
+    /// Apply the changes to the entity states.
+    pub fn apply(&self, entities: &mut HashMap<String, State>) {
+        for (entity_id, state) in &self.added {
+            entities.insert(entity_id.clone(), state.to_state(entity_id));
+        }
+
+        for (entity_id, diff) in &self.changed {
+            match entities.get_mut(entity_id) {
+                Some(state) => diff.apply(state),
+                None => debug!("Ignoring changes of unknown entity `{}`.", entity_id),
+            }
+        }
+
+        for entity_id in &self.removed {
+            entities.remove(entity_id);
+        }
+    }
+
+    /// Get the latest update of the changed entities, if any.
+    ///
+    /// The added entities are left out, as their states may be old.
+    pub fn last_updated(&self) -> Option<DateTime<Utc>> {
+        self.changed
+            .values()
+            .filter_map(|diff| diff.additions.as_ref())
+            .filter_map(|additions| additions.last_updated.or(additions.last_changed))
+            .reduce(f64::max)
+            .map(timestamp)
+    }
+
+    /// Describe the changes, for tracing.
+    pub fn describe(&self) -> String {
+        format!(
+            "{} added, {} changed, {} removed",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len()
+        )
+    }
+}
+
+/// A full entity state, with abbreviated field names and timestamps.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompressedState {
+    #[serde(rename = "s")]
+    pub state: String,
+
+    #[serde(rename = "a", default)]
+    pub attributes: Attributes,
+
+    #[serde(rename = "c", default)]
+    pub context: Option<CompressedContext>,
+
+    #[serde(rename = "lc")]
+    pub last_changed: f64,
+
+    /// Only set when it differs from the last change.
+    #[serde(rename = "lu", default)]
+    pub last_updated: Option<f64>,
+}
+
+impl CompressedState {
+    fn to_state(&self, entity_id: &str) -> State {
+        State {
+            entity_id: entity_id.to_string(),
+            attributes: self.attributes.clone(),
+            context: self
+                .context
+                .clone()
+                .map(CompressedContext::into_context)
+                .unwrap_or_default(),
+            last_changed: timestamp(self.last_changed),
+            last_updated: timestamp(self.last_updated.unwrap_or(self.last_changed)),
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// A context, reduced to its identifier when it has no parent nor user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum CompressedContext {
+    Id(String),
+    Full(Context),
+}
+
+impl CompressedContext {
+    fn into_context(self) -> Context {
+        match self {
+            Self::Id(id) => Context {
+                id,
+                ..Default::default()
+            },
+            Self::Full(context) => context,
+        }
+    }
+}
+
+/// The differences between two states of an entity.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StateDiff {
+    #[serde(rename = "+", default, skip_serializing_if = "Option::is_none")]
+    pub additions: Option<StateAdditions>,
+
+    #[serde(rename = "-", default, skip_serializing_if = "Option::is_none")]
+    pub removals: Option<StateRemovals>,
+}
+
+impl StateDiff {
+    fn apply(&self, state: &mut State) {
+        let mut attributes = match state
+            .attributes
+            .parse::<serde_json::Map<String, serde_json::Value>>()
+        {
+            Ok(attributes) => attributes,
+            Err(err) => {
+                warn!(
+                    "Failed to parse the attributes of `{}`: {}",
+                    state.entity_id, err
+                );
+
+                Default::default()
+            }
+        };
+
+        if let Some(additions) = &self.additions {
+            if let Some(value) = &additions.state {
+                state.state = value.clone();
+            }
+
+            if let Some(context) = &additions.context {
+                state.context = context.clone().into_context();
+            }
+
+            // The last update is implied by the last change when both changed.
+            if let Some(last_changed) = additions.last_changed {
+                state.last_changed = timestamp(last_changed);
+                state.last_updated = timestamp(last_changed);
+            }
+
+            if let Some(last_updated) = additions.last_updated {
+                state.last_updated = timestamp(last_updated);
+            }
+
+            attributes.extend(additions.attributes.clone());
+        }
+
+        if let Some(removals) = &self.removals {
+            for name in &removals.attributes {
+                attributes.remove(name);
+            }
+        }
+
+        state.attributes = serde_json::Value::Object(attributes).into();
+    }
+}
+
+/// The changed fields of a state.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StateAdditions {
+    #[serde(rename = "s", default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+
+    /// The changed attributes only.
+    #[serde(
+        rename = "a",
+        default,
+        skip_serializing_if = "serde_json::Map::is_empty"
+    )]
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+
+    #[serde(rename = "c", default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<CompressedContext>,
+
+    #[serde(rename = "lc", default, skip_serializing_if = "Option::is_none")]
+    pub last_changed: Option<f64>,
+
+    #[serde(rename = "lu", default, skip_serializing_if = "Option::is_none")]
+    pub last_updated: Option<f64>,
+}
+
+/// The removed fields of a state.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StateRemovals {
+    /// The names of the removed attributes.
+    #[serde(rename = "a", default)]
+    pub attributes: Vec<String>,
+}
+
+/// Convert a timestamp in seconds, as sent by Home-Assistant.
+fn timestamp(seconds: f64) -> DateTime<Utc> {
+    Utc.timestamp_nanos((seconds * 1e9).round() as i64)
+}
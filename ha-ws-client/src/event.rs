@@ -0,0 +1,308 @@
+//! The events and the entity states of the Home-Assistant web-socket API.
+
+use std::{borrow::Cow, fmt::Display, marker::PhantomData};
+
+use chrono::{DateTime, Utc};
+use serde::{
+    de::{DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Serialize,
+};
+use serde_json::{json, value::RawValue};
+
+use crate::{message::tag, EntitiesEvent};
+
+// Events are boxed in messages already.
+#[allow(clippy::large_enum_variant)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "event_type", rename_all = "snake_case", remote = "Self")]
+pub enum Event {
+    StateChanged {
+        context: Context,
+        data: StateChangedData,
+        origin: String,
+        time_fired: DateTime<Utc>,
+    },
+    HomeassistantStarted {
+        context: Context,
+        origin: String,
+        time_fired: DateTime<Utc>,
+    },
+    CoreConfigUpdated {
+        context: Context,
+        origin: String,
+        time_fired: DateTime<Utc>,
+    },
+    ComponentLoaded {
+        context: Context,
+        data: ComponentLoadedData,
+        origin: String,
+        time_fired: DateTime<Utc>,
+    },
+
+    /// An event type this client does not model, like the ones of custom
+    /// subscriptions.
+    #[serde(skip)]
+    Other {
+        event_type: String,
+        data: serde_json::Value,
+    },
+
+    /// The changes of the entities subscribed to with `subscribe_entities`,
+    /// which are not tagged with an event type.
+    #[serde(skip)]
+    Entities(EntitiesEvent),
+}
+
+impl Event {
+    /// The event types this client models.
+    const KNOWN_TYPES: &'static [&'static str] = &[
+        "state_changed",
+        "homeassistant_started",
+        "core_config_updated",
+        "component_loaded",
+    ];
+
+    /// Get the entities whose state the event changes.
+    pub fn entity_ids(&self) -> Vec<&str> {
+        match self {
+            Self::StateChanged { data, .. } => vec![&data.entity_id],
+            Self::Entities(changes) => changes
+                .added
+                .keys()
+                .chain(changes.changed.keys())
+                .chain(&changes.removed)
+                .map(String::as_str)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Get when Home-Assistant fired the event, if known.
+    pub fn time_fired(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::StateChanged { time_fired, .. }
+            | Self::HomeassistantStarted { time_fired, .. }
+            | Self::CoreConfigUpdated { time_fired, .. }
+            | Self::ComponentLoaded { time_fired, .. } => Some(*time_fired),
+            Self::Entities(changes) => changes.last_updated(),
+            Self::Other { .. } => None,
+        }
+    }
+}
+
+impl Serialize for Event {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Self::Other { event_type, data } => json!({
+                "event_type": event_type,
+                "data": data,
+            })
+            .serialize(serializer),
+            Self::Entities(changes) => changes.serialize(serializer),
+            _ => Self::serialize(self, serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Event {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let raw = serde_json::Value::deserialize(deserializer)?;
+
+        Self::deserialize(&raw).or_else(|err| match tag(&raw, "event_type") {
+            Some(event_type) if !Self::KNOWN_TYPES.contains(&event_type.as_str()) => {
+                Ok(Self::Other {
+                    event_type,
+                    data: raw.get("data").cloned().unwrap_or_default(),
+                })
+            }
+            None if ["a", "c", "r"].iter().any(|key| raw.get(key).is_some()) => {
+                EntitiesEvent::deserialize(&raw)
+                    .map(Self::Entities)
+                    .map_err(serde::de::Error::custom)
+            }
+            _ => Err(serde::de::Error::custom(err)),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ComponentLoadedData {
+    pub component: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Context {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub user_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StateChangedData {
+    pub entity_id: String,
+    pub old_state: Option<State>,
+    pub new_state: Option<State>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct State {
+    pub entity_id: String,
+    #[serde(default)]
+    pub attributes: Attributes,
+
+    /// Some integrations report states without a context.
+    #[serde(default)]
+    pub context: Context,
+    pub last_changed: DateTime<Utc>,
+    pub last_updated: DateTime<Utc>,
+    pub state: String,
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            Self::StateChanged {
+                context: _,
+                data,
+                origin: _,
+                time_fired: _,
+            } => write!(
+                f,
+                "{}: {} -> {}",
+                data.entity_id,
+                data.old_state
+                    .as_ref()
+                    .map(|s| s.state.as_str())
+                    .unwrap_or_default(),
+                data.new_state
+                    .as_ref()
+                    .map(|s| s.state.as_str())
+                    .unwrap_or_default(),
+            ),
+            Self::HomeassistantStarted { .. } => write!(f, "homeassistant_started"),
+            Self::CoreConfigUpdated { .. } => write!(f, "core_config_updated"),
+            Self::ComponentLoaded { data, .. } => write!(f, "component_loaded: {}", data.component),
+            Self::Other { event_type, .. } => write!(f, "{}", event_type),
+            Self::Entities(changes) => write!(f, "entities: {}", changes.describe()),
+        }
+    }
+}
+
+impl State {
+    /// Check whether the entity is available: Home-Assistant reports
+    /// `unavailable` or `unknown` when the device cannot be reached.
+    pub fn is_available(&self) -> bool {
+        !matches!(self.state.as_str(), "unavailable" | "unknown")
+    }
+}
+
+/// The attributes of a state.
+///
+/// They are kept as raw JSON, which takes a fraction of the memory of a
+/// parsed `serde_json::Value` on large instances, and are parsed on access.
+#[derive(Debug, Clone)]
+pub struct Attributes(Box<RawValue>);
+
+impl Attributes {
+    /// Get an attribute, or `None` if it is missing or not a `T`.
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        let mut deserializer = serde_json::Deserializer::from_str(self.0.get());
+
+        AttributeSeed {
+            name,
+            ty: PhantomData,
+        }
+        .deserialize(&mut deserializer)
+        .ok()
+        .flatten()
+    }
+
+    /// Parse all the attributes.
+    pub fn parse<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(self.0.get())
+    }
+
+    /// Get the size of the raw attributes, in bytes.
+    pub fn size(&self) -> usize {
+        self.0.get().len()
+    }
+}
+
+impl Default for Attributes {
+    fn default() -> Self {
+        Self::from(serde_json::Value::Object(Default::default()))
+    }
+}
+
+impl From<serde_json::Value> for Attributes {
+    fn from(value: serde_json::Value) -> Self {
+        Self(serde_json::value::to_raw_value(&value).expect("JSON values serialize"))
+    }
+}
+
+impl Serialize for Attributes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Attributes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // States are nested in tagged messages, which raw values cannot be
+        // borrowed from.
+        serde_json::Value::deserialize(deserializer).map(Self::from)
+    }
+}
+
+/// Looks up a single attribute, skipping over the others.
+struct AttributeSeed<'a, T> {
+    name: &'a str,
+    ty: PhantomData<T>,
+}
+
+impl<'de, T: DeserializeOwned> DeserializeSeed<'de> for AttributeSeed<'_, T> {
+    type Value = Option<T>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, T: DeserializeOwned> Visitor<'de> for AttributeSeed<'_, T> {
+    type Value = Option<T>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a map of attributes")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut value = None;
+
+        // The whole map must be consumed for the parsing to succeed.
+        while let Some(key) = map.next_key::<Cow<str>>()? {
+            if value.is_none() && key == self.name {
+                value = Some(map.next_value()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+/// The on/off status of an entity, or `None` if it is unavailable.
+impl From<State> for Option<bool> {
+    fn from(s: State) -> Self {
+        s.is_available()
+            .then_some(matches!(s.state.as_str(), "on" | "1" | "true"))
+    }
+}
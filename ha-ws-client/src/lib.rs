@@ -0,0 +1,21 @@
+//! The protocol of the Home-Assistant web-socket API: its messages, its
+//! events and the entity states, and the tracking of the requests waiting for
+//! their result.
+//!
+//! The connection itself, the authentication and what to do with the events
+//! are left to the clients.
+
+pub mod entities;
+mod event;
+mod message;
+mod pending;
+
+pub use self::{
+    entities::EntitiesEvent,
+    event::{Attributes, ComponentLoadedData, Context, Event, State, StateChangedData},
+    message::{parse_messages, Error, Features, Message},
+    pending::Pending,
+};
+
+#[cfg(test)]
+mod tests;
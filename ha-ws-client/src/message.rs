@@ -0,0 +1,283 @@
+//! The messages of the Home-Assistant web-socket API.
+
+use std::fmt::Display;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::Event;
+
+/// A message of the web-socket API, in either direction.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case", remote = "Self")]
+pub enum Message {
+    AuthRequired {
+        ha_version: String,
+    },
+    Auth {
+        access_token: String,
+    },
+    AuthOk {
+        ha_version: String,
+    },
+    AuthInvalid {
+        message: String,
+    },
+    CallService {
+        id: u64,
+        domain: String,
+        service: String,
+        service_data: Option<serde_json::Value>,
+        target: Option<serde_json::Value>,
+    },
+    Result {
+        id: u64,
+        success: bool,
+        #[serde(default)]
+        result: serde_json::Value,
+        error: Option<Error>,
+    },
+    SubscribeEvents {
+        id: u64,
+        event_type: Option<String>,
+    },
+    SubscribeTrigger {
+        id: u64,
+        trigger: serde_json::Value,
+    },
+    SubscribeEntities {
+        id: u64,
+        entity_ids: Vec<String>,
+    },
+    FireEvent {
+        id: u64,
+        event_type: String,
+        event_data: Option<serde_json::Value>,
+    },
+    SupportedFeatures {
+        id: u64,
+        features: Features,
+    },
+    Ping {
+        id: u64,
+    },
+    Pong {
+        id: u64,
+    },
+    Event {
+        id: u64,
+        event: Box<Event>,
+    },
+    GetStates {
+        id: u64,
+    },
+    GetConfig {
+        id: u64,
+    },
+    #[serde(rename = "config/entity_registry/list")]
+    EntityRegistryList {
+        id: u64,
+    },
+    #[serde(rename = "config/device_registry/list")]
+    DeviceRegistryList {
+        id: u64,
+    },
+    #[serde(rename = "config/area_registry/list")]
+    AreaRegistryList {
+        id: u64,
+    },
+
+    /// A message type this client does not model, like ones introduced by
+    /// newer Home-Assistant versions.
+    #[serde(skip)]
+    Other {
+        message_type: String,
+        raw: serde_json::Value,
+    },
+}
+
+impl Serialize for Message {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Self::Other { raw, .. } => raw.serialize(serializer),
+            _ => Self::serialize(self, serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let raw = serde_json::Value::deserialize(deserializer)?;
+
+        Self::deserialize(&raw).or_else(|err| match tag(&raw, "type") {
+            Some(message_type) if !Self::KNOWN_TYPES.contains(&message_type.as_str()) => {
+                Ok(Self::Other { message_type, raw })
+            }
+            _ => Err(serde::de::Error::custom(err)),
+        })
+    }
+}
+
+/// Parse the messages of a web-socket text frame, which holds a batch of them
+/// once Home-Assistant coalesces its messages.
+pub fn parse_messages(text: &str) -> Vec<Message> {
+    if !text.trim_start().starts_with('[') {
+        return match serde_json::from_str::<Message>(text) {
+            Ok(message) => vec![message],
+            Err(err) => {
+                warn!("Failed to parse message `{:?}`: {}", text, err);
+
+                Vec::new()
+            }
+        };
+    }
+
+    match serde_json::from_str::<Vec<serde_json::Value>>(text) {
+        Ok(values) => values
+            .into_iter()
+            .filter_map(|value| match serde_json::from_value::<Message>(value) {
+                Ok(message) => Some(message),
+                Err(err) => {
+                    warn!("Failed to parse coalesced message in `{:?}`: {}", text, err);
+
+                    None
+                }
+            })
+            .collect(),
+        Err(err) => {
+            warn!("Failed to parse coalesced messages `{:?}`: {}", text, err);
+
+            Vec::new()
+        }
+    }
+}
+
+/// Get the tag of an internally tagged value.
+pub(crate) fn tag(raw: &serde_json::Value, name: &str) -> Option<String> {
+    raw.get(name)?.as_str().map(ToString::to_string)
+}
+
+/// The protocol features this client supports, negotiated after the
+/// authentication.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Features {
+    /// Whether Home-Assistant may send batches of messages in a single frame.
+    pub coalesce_messages: u8,
+}
+
+/// The error of a failed request.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Error {
+    pub code: String,
+    pub message: String,
+}
+
+impl Default for Error {
+    fn default() -> Self {
+        Self {
+            code: "unspecified".to_string(),
+            message: "no error information was present".to_string(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "E{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Message {
+    /// The message types this client models.
+    const KNOWN_TYPES: &'static [&'static str] = &[
+        "auth_required",
+        "auth",
+        "auth_ok",
+        "auth_invalid",
+        "call_service",
+        "result",
+        "subscribe_events",
+        "subscribe_trigger",
+        "subscribe_entities",
+        "fire_event",
+        "supported_features",
+        "ping",
+        "pong",
+        "event",
+        "get_states",
+        "get_config",
+        "config/entity_registry/list",
+        "config/device_registry/list",
+        "config/area_registry/list",
+    ];
+
+    /// Describe the message, for tracing.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::CallService {
+                domain, service, ..
+            } => format!("call_service {}.{}", domain, service),
+            Self::SubscribeEvents { event_type, .. } => {
+                format!("subscribe_events {}", event_type.as_deref().unwrap_or("*"))
+            }
+            Self::FireEvent { event_type, .. } => format!("fire_event {}", event_type),
+            Self::AuthRequired { .. } => "auth_required".to_string(),
+            Self::Auth { .. } => "auth".to_string(),
+            Self::AuthOk { .. } => "auth_ok".to_string(),
+            Self::AuthInvalid { .. } => "auth_invalid".to_string(),
+            Self::Result { .. } => "result".to_string(),
+            Self::SubscribeTrigger { .. } => "subscribe_trigger".to_string(),
+            Self::SubscribeEntities { entity_ids, .. } => {
+                format!("subscribe_entities ({} entities)", entity_ids.len())
+            }
+            Self::SupportedFeatures { .. } => "supported_features".to_string(),
+            Self::Ping { .. } => "ping".to_string(),
+            Self::Pong { .. } => "pong".to_string(),
+            Self::Event { .. } => "event".to_string(),
+            Self::GetStates { .. } => "get_states".to_string(),
+            Self::GetConfig { .. } => "get_config".to_string(),
+            Self::EntityRegistryList { .. } => "config/entity_registry/list".to_string(),
+            Self::DeviceRegistryList { .. } => "config/device_registry/list".to_string(),
+            Self::AreaRegistryList { .. } => "config/area_registry/list".to_string(),
+            Self::Other { message_type, .. } => message_type.clone(),
+        }
+    }
+
+    /// Set the id of the message, and tell whether it has one: the
+    /// authentication messages do not.
+    pub fn inject_id(&mut self, new_id: u64) -> bool {
+        match self {
+            Self::AuthRequired { .. }
+            | Self::Auth { .. }
+            | Self::AuthOk { .. }
+            | Self::AuthInvalid { .. }
+            | Self::Other { .. } => false,
+            Self::CallService { id, .. }
+            | Self::Result { id, .. }
+            | Self::SubscribeEvents { id, .. }
+            | Self::SubscribeTrigger { id, .. }
+            | Self::SubscribeEntities { id, .. }
+            | Self::FireEvent { id, .. }
+            | Self::SupportedFeatures { id, .. }
+            | Self::Ping { id }
+            | Self::Pong { id }
+            | Self::Event { id, .. }
+            | Self::GetStates { id }
+            | Self::GetConfig { id }
+            | Self::EntityRegistryList { id }
+            | Self::DeviceRegistryList { id }
+            | Self::AreaRegistryList { id } => {
+                *id = new_id;
+
+                true
+            }
+        }
+    }
+}
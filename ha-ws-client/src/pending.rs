@@ -0,0 +1,62 @@
+//! The requests waiting for their result.
+
+use std::collections::HashMap;
+
+/// Allocates the message ids of a connection, and keeps the requests waiting
+/// for their result by id.
+///
+/// Ids are unique for the connection: every message, pings included, gets
+/// its own.
+#[derive(Debug)]
+pub struct Pending<T> {
+    next_id: u64,
+    requests: HashMap<u64, T>,
+}
+
+impl<T> Default for Pending<T> {
+    fn default() -> Self {
+        Self {
+            next_id: 1,
+            requests: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Pending<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate the id of a message.
+    pub fn allocate(&mut self) -> u64 {
+        let id = self.next_id;
+
+        // A u64 does not overflow in the lifetime of a connection.
+        self.next_id += 1;
+
+        id
+    }
+
+    /// Keep a request until its result, with the id it was sent with.
+    pub fn insert(&mut self, id: u64, request: T) {
+        self.requests.insert(id, request);
+    }
+
+    /// Get the request a result is for, if it is waiting for one.
+    pub fn complete(&mut self, id: u64) -> Option<T> {
+        self.requests.remove(&id)
+    }
+
+    /// Get the number of requests waiting for their result.
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u64, &mut T)> {
+        self.requests.iter_mut().map(|(id, request)| (*id, request))
+    }
+}
@@ -0,0 +1,578 @@
+//! Golden and round-trip tests of the Home-Assistant web-socket messages.
+//!
+//! The fixtures follow the messages sent by Home-Assistant 2023.x to 2025.x:
+//! newer versions add fields, like `last_reported` on states, which must
+//! keep being accepted.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::*;
+
+/// The number of random cases of each round-trip test.
+const CASES: usize = 500;
+
+macro_rules! fixture {
+    ($name:literal) => {
+        serde_json::from_str::<Value>(include_str!(concat!("fixtures/", $name, ".json")))
+            .expect(concat!("invalid fixture `", $name, "`"))
+    };
+}
+
+fn parse<T: serde::de::DeserializeOwned>(value: &Value) -> T {
+    serde_json::from_value(value.clone()).expect("failed to parse")
+}
+
+/// Check that a value survives serializing and parsing again unchanged.
+fn assert_round_trip<T: Serialize + serde::de::DeserializeOwned>(value: &T) {
+    let serialized = serde_json::to_value(value).expect("failed to serialize");
+    let reserialized =
+        serde_json::to_value(parse::<T>(&serialized)).expect("failed to serialize again");
+
+    assert_eq!(serialized, reserialized);
+}
+
+fn state_changed(message: Message) -> StateChangedData {
+    match message {
+        Message::Event { event, .. } => match *event {
+            Event::StateChanged { data, .. } => data,
+            event => panic!("unexpected event: {:?}", event),
+        },
+        message => panic!("unexpected message: {:?}", message),
+    }
+}
+
+#[test]
+fn auth_messages() {
+    assert!(matches!(
+        parse(&fixture!("auth_required")),
+        Message::AuthRequired { ha_version } if ha_version == "2023.1.7"
+    ));
+    assert!(matches!(
+        parse(&fixture!("auth_ok")),
+        Message::AuthOk { ha_version } if ha_version == "2024.6.4"
+    ));
+    assert!(matches!(
+        parse(&fixture!("auth_invalid")),
+        Message::AuthInvalid { message } if message.contains("Invalid access token")
+    ));
+    assert_eq!(
+        serde_json::to_value(Message::Auth {
+            access_token: "secret".to_string(),
+        })
+        .unwrap(),
+        json!({"type": "auth", "access_token": "secret"}),
+    );
+}
+
+#[test]
+fn result_messages() {
+    match parse(&fixture!("result_success")) {
+        Message::Result {
+            id,
+            success,
+            result,
+            error,
+        } => {
+            assert_eq!(id, 12);
+            assert!(success);
+            assert!(result.get("context").is_some());
+            assert!(error.is_none());
+        }
+        message => panic!("unexpected message: {:?}", message),
+    }
+
+    match parse(&fixture!("result_error")) {
+        Message::Result {
+            id,
+            success,
+            error: Some(error),
+            ..
+        } => {
+            assert_eq!(id, 13);
+            assert!(!success);
+            assert_eq!(error.code, "not_found");
+        }
+        message => panic!("unexpected message: {:?}", message),
+    }
+
+    assert!(matches!(parse(&fixture!("pong")), Message::Pong { id: 14 }));
+}
+
+#[test]
+fn state_changed_events() {
+    let data = state_changed(parse(&fixture!("event_state_changed_2023")));
+    let new_state = data.new_state.expect("missing new state");
+
+    assert_eq!(data.entity_id, "sensor.living_room_temperature");
+    assert_eq!(data.old_state.expect("missing old state").state, "21.4");
+    assert_eq!(new_state.state, "21.6");
+    assert_eq!(
+        new_state.attributes.get::<String>("unit_of_measurement"),
+        Some("°C".to_string())
+    );
+
+    // 2024.3 added `last_reported`.
+    let data = state_changed(parse(&fixture!("event_state_changed_2024")));
+    let new_state = data.new_state.expect("missing new state");
+
+    assert_eq!(new_state.state, "on");
+    assert_eq!(new_state.attributes.get::<u64>("brightness"), Some(180));
+    assert_eq!(
+        new_state.context.user_id.as_deref(),
+        Some("b2b4e3d1f4c34a43a7c5b8a3f9d2e1c0")
+    );
+
+    // Removed entities have no new state.
+    let data = state_changed(parse(&fixture!("event_state_removed_2025")));
+
+    assert!(data.new_state.is_none());
+    assert_eq!(
+        data.old_state.expect("missing old state").state,
+        "unavailable"
+    );
+}
+
+#[test]
+fn lifecycle_events() {
+    let event = |message| match message {
+        Message::Event { event, .. } => *event,
+        message => panic!("unexpected message: {:?}", message),
+    };
+
+    assert!(matches!(
+        event(parse(&fixture!("event_homeassistant_started"))),
+        Event::HomeassistantStarted { .. }
+    ));
+    assert!(matches!(
+        event(parse(&fixture!("event_core_config_updated"))),
+        Event::CoreConfigUpdated { .. }
+    ));
+    assert!(matches!(
+        event(parse(&fixture!("event_component_loaded"))),
+        Event::ComponentLoaded { data, .. } if data.component == "hue.light"
+    ));
+}
+
+#[test]
+fn unknown_types() {
+    match parse(&fixture!("event_other")) {
+        Message::Event { event, .. } => match *event {
+            Event::Other { event_type, data } => {
+                assert_eq!(event_type, "call_service");
+                assert_eq!(data["service"], "turn_on");
+            }
+            event => panic!("unexpected event: {:?}", event),
+        },
+        message => panic!("unexpected message: {:?}", message),
+    }
+
+    let raw = fixture!("message_other");
+
+    match parse(&raw) {
+        Message::Other { message_type, .. } => assert_eq!(message_type, "render_template"),
+        message => panic!("unexpected message: {:?}", message),
+    }
+
+    // Unknown messages are forwarded as they are.
+    assert_eq!(serde_json::to_value(parse::<Message>(&raw)).unwrap(), raw);
+}
+
+#[test]
+fn known_types_must_be_valid() {
+    // A known type with missing fields is an error, not an unknown message.
+    assert!(serde_json::from_value::<Message>(json!({"type": "auth_ok"})).is_err());
+    assert!(serde_json::from_value::<Event>(json!({"event_type": "state_changed"})).is_err());
+}
+
+#[test]
+fn states() {
+    let states: Vec<State> = parse(&fixture!("get_states"));
+
+    assert_eq!(states.len(), 3);
+    assert_eq!(states[1].entity_id, "weather.home");
+    assert_eq!(states[1].attributes.get::<f64>("temperature"), Some(17.2));
+
+    // Some integrations report states without a context.
+    assert_eq!(states[2].context.id, "");
+}
+
+#[test]
+fn attributes() {
+    let attributes: Attributes = serde_json::from_str(
+        r#"{
+            "friendly_name": "Living \"room\"",
+            "hvac_modes": ["off", "heat"],
+            "temperature": 20,
+            "escaped\u0020key": true
+        }"#,
+    )
+    .expect("failed to parse");
+
+    assert_eq!(
+        attributes.get::<String>("friendly_name"),
+        Some("Living \"room\"".to_string())
+    );
+    assert_eq!(
+        attributes.get::<Vec<String>>("hvac_modes"),
+        Some(vec!["off".to_string(), "heat".to_string()])
+    );
+    assert_eq!(attributes.get::<f64>("temperature"), Some(20.0));
+    assert_eq!(attributes.get::<bool>("escaped key"), Some(true));
+    assert_eq!(attributes.get::<String>("temperature"), None);
+    assert_eq!(attributes.get::<f64>("missing"), None);
+    assert_eq!(Attributes::default().get::<f64>("temperature"), None);
+}
+
+#[test]
+fn outgoing_messages() {
+    assert_eq!(
+        serde_json::to_value(Message::CallService {
+            id: 24,
+            domain: "light".to_string(),
+            service: "turn_on".to_string(),
+            service_data: Some(json!({"brightness_pct": 60})),
+            target: Some(json!({"entity_id": "light.kitchen"})),
+        })
+        .unwrap(),
+        fixture!("call_service"),
+    );
+    assert_eq!(
+        serde_json::to_value(Message::SubscribeEvents {
+            id: 18,
+            event_type: Some("state_changed".to_string()),
+        })
+        .unwrap(),
+        fixture!("subscribe_events"),
+    );
+    assert_eq!(
+        serde_json::to_value(Message::GetStates { id: 19 }).unwrap(),
+        fixture!("get_states_request"),
+    );
+    assert_eq!(
+        serde_json::to_value(Message::EntityRegistryList { id: 20 }).unwrap(),
+        fixture!("entity_registry_list"),
+    );
+    assert_eq!(
+        serde_json::to_value(Message::SupportedFeatures {
+            id: 1,
+            features: Features {
+                coalesce_messages: 1
+            },
+        })
+        .unwrap(),
+        fixture!("supported_features"),
+    );
+    assert_eq!(
+        serde_json::to_value(Message::SubscribeEntities {
+            id: 21,
+            entity_ids: vec![
+                "light.kitchen".to_string(),
+                "sensor.outdoor_temperature".to_string()
+            ],
+        })
+        .unwrap(),
+        fixture!("subscribe_entities"),
+    );
+}
+
+#[test]
+fn entities_events() {
+    /// Apply the changes, and get the sorted ids of the changed entities.
+    fn apply(message: Message, entities: &mut HashMap<String, State>) -> Vec<String> {
+        match message {
+            Message::Event { event, .. } => {
+                let mut entity_ids: Vec<_> =
+                    event.entity_ids().into_iter().map(str::to_string).collect();
+
+                entity_ids.sort();
+
+                match *event {
+                    Event::Entities(changes) => changes.apply(entities),
+                    event => panic!("unexpected event: {:?}", event),
+                }
+
+                entity_ids
+            }
+            message => panic!("unexpected message: {:?}", message),
+        }
+    }
+
+    let mut entities = HashMap::new();
+
+    apply(parse(&fixture!("event_entities_added")), &mut entities);
+
+    let light = &entities["light.kitchen"];
+
+    assert_eq!(light.state, "on");
+    assert_eq!(light.attributes.get::<u8>("brightness"), Some(153));
+    assert_eq!(light.context.id, "01J06Y2Q4G8ZK3M5N7P9R1T3V5");
+    assert_eq!(light.last_changed, light.last_updated);
+
+    let sensor = &entities["sensor.outdoor_temperature"];
+
+    assert_eq!(
+        sensor.context.user_id.as_deref(),
+        Some("9f8e7d6c5b4a39281706f5e4d3c2b1a0")
+    );
+    assert_eq!(
+        sensor.last_changed,
+        Utc.with_ymd_and_hms(2024, 6, 12, 6, 40, 0).unwrap()
+    );
+    assert_eq!(
+        sensor.last_updated,
+        Utc.with_ymd_and_hms(2024, 6, 12, 7, 0, 0).unwrap() + chrono::Duration::milliseconds(500)
+    );
+
+    assert_eq!(
+        apply(parse(&fixture!("event_entities_changed")), &mut entities),
+        ["light.kitchen", "sensor.outdoor_temperature"]
+    );
+
+    let light = &entities["light.kitchen"];
+
+    assert_eq!(light.state, "off");
+    assert_eq!(light.attributes.get::<u8>("brightness"), None);
+    assert_eq!(
+        light.attributes.get::<String>("friendly_name").as_deref(),
+        Some("Kitchen")
+    );
+    assert_eq!(light.context.id, "01J06Y4M2N4P6Q8R0S2T4V6W8X");
+    assert_eq!(light.last_changed, light.last_updated);
+
+    let sensor = &entities["sensor.outdoor_temperature"];
+
+    assert_eq!(sensor.state, "18.4");
+    assert_eq!(
+        sensor
+            .attributes
+            .get::<String>("unit_of_measurement")
+            .as_deref(),
+        Some("°F")
+    );
+    assert_eq!(
+        sensor.last_changed,
+        Utc.with_ymd_and_hms(2024, 6, 12, 6, 40, 0).unwrap()
+    );
+    assert_eq!(
+        sensor.last_updated,
+        Utc.with_ymd_and_hms(2024, 6, 12, 7, 6, 0).unwrap()
+    );
+
+    assert_eq!(
+        apply(parse(&fixture!("event_entities_removed")), &mut entities),
+        ["light.kitchen"]
+    );
+
+    assert!(!entities.contains_key("light.kitchen"));
+    assert!(entities.contains_key("sensor.outdoor_temperature"));
+
+    // An event without a type that is not about entities is still an error.
+    assert!(serde_json::from_value::<Event>(json!({"data": {}})).is_err());
+}
+
+#[test]
+fn coalesced_messages() {
+    let messages = parse_messages(&fixture!("coalesced").to_string());
+
+    assert_eq!(messages.len(), 3);
+    assert!(matches!(
+        messages[0],
+        Message::Result {
+            id: 1,
+            success: true,
+            ..
+        }
+    ));
+    assert!(matches!(messages[1], Message::Event { id: 2, .. }));
+    assert!(matches!(messages[2], Message::Pong { id: 3 }));
+
+    // A single message is still accepted, and an invalid one in a batch only
+    // drops that one.
+    assert_eq!(parse_messages(&fixture!("pong").to_string()).len(), 1);
+    assert!(matches!(
+        parse_messages(r#"[{"type": "pong"}, {"id": 4, "type": "pong"}]"#)[..],
+        [Message::Pong { id: 4 }]
+    ));
+}
+
+#[test]
+fn fixtures_round_trip() {
+    for fixture in [
+        fixture!("auth_required"),
+        fixture!("auth_ok"),
+        fixture!("auth_invalid"),
+        fixture!("result_success"),
+        fixture!("result_error"),
+        fixture!("pong"),
+        fixture!("event_state_changed_2023"),
+        fixture!("event_state_changed_2024"),
+        fixture!("event_state_removed_2025"),
+        fixture!("event_homeassistant_started"),
+        fixture!("event_core_config_updated"),
+        fixture!("event_component_loaded"),
+        fixture!("event_other"),
+        fixture!("message_other"),
+        fixture!("call_service"),
+        fixture!("subscribe_events"),
+        fixture!("get_states_request"),
+        fixture!("entity_registry_list"),
+        fixture!("supported_features"),
+        fixture!("subscribe_entities"),
+        fixture!("event_entities_added"),
+        fixture!("event_entities_changed"),
+        fixture!("event_entities_removed"),
+    ] {
+        assert_round_trip(&parse::<Message>(&fixture));
+    }
+}
+
+fn random_string(rng: &mut StdRng) -> String {
+    const CHARS: &[char] = &[
+        'a', 'z', 'A', '0', '9', '_', '.', ' ', '"', '\\', '\n', '°', 'é', '€', '😀',
+    ];
+
+    (0..rng.gen_range(0..16))
+        .map(|_| CHARS[rng.gen_range(0..CHARS.len())])
+        .collect()
+}
+
+fn random_json(rng: &mut StdRng, depth: usize) -> Value {
+    match rng.gen_range(0..if depth == 0 { 5 } else { 7 }) {
+        0 => Value::Null,
+        1 => Value::Bool(rng.gen()),
+        2 => json!(rng.gen::<i64>()),
+        3 => json!(rng.gen_range(-1e6..1e6)),
+        4 => Value::String(random_string(rng)),
+        5 => (0..rng.gen_range(0..4))
+            .map(|_| random_json(rng, depth - 1))
+            .collect(),
+        _ => Value::Object(
+            (0..rng.gen_range(0..4))
+                .map(|_| (random_string(rng), random_json(rng, depth - 1)))
+                .collect(),
+        ),
+    }
+}
+
+fn random_time(rng: &mut StdRng) -> DateTime<Utc> {
+    Utc.timestamp_opt(
+        rng.gen_range(0..4_000_000_000),
+        rng.gen_range(0..1_000_000_000),
+    )
+    .unwrap()
+}
+
+fn random_context(rng: &mut StdRng) -> Context {
+    Context {
+        id: random_string(rng),
+        parent_id: rng.gen::<bool>().then(|| random_string(rng)),
+        user_id: rng.gen::<bool>().then(|| random_string(rng)),
+    }
+}
+
+fn random_state(rng: &mut StdRng) -> State {
+    State {
+        entity_id: format!("{}.{}", random_string(rng), random_string(rng)),
+        attributes: Value::Object(
+            (0..rng.gen_range(0..6))
+                .map(|_| (random_string(rng), random_json(rng, 2)))
+                .collect(),
+        )
+        .into(),
+        context: random_context(rng),
+        last_changed: random_time(rng),
+        last_updated: random_time(rng),
+        state: random_string(rng),
+    }
+}
+
+#[test]
+fn states_round_trip() {
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for _ in 0..CASES {
+        assert_round_trip(&random_state(&mut rng));
+    }
+}
+
+#[test]
+fn state_changed_events_round_trip() {
+    let mut rng = StdRng::seed_from_u64(1);
+
+    for _ in 0..CASES {
+        let message = Message::Event {
+            id: rng.gen(),
+            event: Box::new(Event::StateChanged {
+                context: random_context(&mut rng),
+                data: StateChangedData {
+                    entity_id: random_string(&mut rng),
+                    old_state: rng.gen::<bool>().then(|| random_state(&mut rng)),
+                    new_state: rng.gen::<bool>().then(|| random_state(&mut rng)),
+                },
+                origin: random_string(&mut rng),
+                time_fired: random_time(&mut rng),
+            }),
+        };
+
+        assert_round_trip(&message);
+    }
+}
+
+#[test]
+fn call_service_round_trip() {
+    let mut rng = StdRng::seed_from_u64(2);
+
+    for _ in 0..CASES {
+        let message = Message::CallService {
+            id: rng.gen(),
+            domain: random_string(&mut rng),
+            service: random_string(&mut rng),
+            service_data: rng.gen::<bool>().then(|| random_json(&mut rng, 3)),
+            target: rng.gen::<bool>().then(|| random_json(&mut rng, 3)),
+        };
+
+        assert_round_trip(&message);
+    }
+}
+
+#[test]
+fn unknown_types_round_trip() {
+    let mut rng = StdRng::seed_from_u64(3);
+
+    for _ in 0..CASES {
+        let message_type = format!("x_{}", random_string(&mut rng));
+        let mut raw = json!({"type": message_type, "id": rng.gen::<u32>()});
+
+        raw["payload"] = random_json(&mut rng, 3);
+
+        match parse::<Message>(&raw) {
+            Message::Other {
+                message_type: parsed,
+                ..
+            } => assert_eq!(parsed, message_type),
+            message => panic!("unexpected message: {:?}", message),
+        }
+
+        assert_eq!(serde_json::to_value(parse::<Message>(&raw)).unwrap(), raw);
+    }
+}
+
+#[test]
+fn ids_are_allocated_in_sequence() {
+    let mut pending = Pending::new();
+    let first = pending.allocate();
+    let second = pending.allocate();
+
+    assert_eq!((first, second), (1, 2));
+
+    pending.insert(second, "get_states");
+
+    assert_eq!(pending.complete(first), None);
+    assert_eq!(pending.complete(second), Some("get_states"));
+    assert!(pending.is_empty());
+}
@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use futures_util::future::{join_all, BoxFuture, FutureExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::{context::AppContext, home_assistant::Status, rules::engine::Condition, templates};
+
+/// How many times a `repeat` runs its actions at most, for a condition that
+/// never matches not to repeat them forever.
+const MAX_REPEATS: u32 = 100;
+
+/// An action triggered by a gesture, an input, like a button, or a rule.
+///
+/// The sequences, delays and branches mirror those of the Home-Assistant
+/// scripts.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// Toggle an entity, like `light.hallway`.
+    Toggle { entity_id: String },
+
+    /// Call a service, like `script.snooze_alarm`.
+    CallService {
+        service: String,
+
+        /// The service data, whose strings may contain templates, like
+        /// `{{ states('sensor.indoor') }}`.
+        #[serde(default)]
+        data: Option<serde_json::Value>,
+    },
+
+    /// Wait before running the next action.
+    Delay {
+        /// In seconds.
+        #[serde_as(as = "DurationSeconds<f64>")]
+        #[schemars(with = "f64")]
+        duration: Duration,
+    },
+
+    /// Run actions one after the other, stopping at the first failure.
+    Sequence { actions: Vec<Action> },
+
+    /// Run actions at the same time, and wait for all of them.
+    Parallel { actions: Vec<Action> },
+
+    /// Run the actions of the first choice whose conditions all match, or
+    /// the default ones if none does.
+    Choose {
+        choices: Vec<Choice>,
+
+        #[serde(default)]
+        default: Vec<Action>,
+    },
+
+    /// Run actions again until their conditions all match, checked after
+    /// each run, or for a number of times.
+    ///
+    /// The actions run once with neither, and at most 100 times.
+    Repeat {
+        actions: Vec<Action>,
+
+        #[serde(default)]
+        count: Option<u32>,
+
+        #[serde(default)]
+        until: Vec<Condition>,
+    },
+}
+
+/// A branch of a `choose` action.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Choice {
+    pub conditions: Vec<Condition>,
+    pub actions: Vec<Action>,
+}
+
+/// Run actions one after the other, stopping at the first failure.
+pub async fn run_all(actions: &[Action], context: &AppContext) -> anyhow::Result<()> {
+    for action in actions {
+        action.run(context).await?;
+    }
+
+    Ok(())
+}
+
+impl Action {
+    /// Run the action, boxed as the combinators run their actions
+    /// recursively.
+    pub fn run<'a>(&'a self, context: &'a AppContext) -> BoxFuture<'a, anyhow::Result<()>> {
+        self.run_inner(context).boxed()
+    }
+
+    async fn run_inner(&self, context: &AppContext) -> anyhow::Result<()> {
+        match self {
+            Self::Toggle { entity_id } => {
+                context
+                    .home_assistant
+                    .call_service(
+                        "homeassistant",
+                        "toggle",
+                        None,
+                        Some(&json!({ "entity_id": entity_id })),
+                    )
+                    .await?
+            }
+            Self::CallService { service, data } => {
+                let (domain, service) = service
+                    .split_once('.')
+                    .ok_or_else(|| anyhow::anyhow!("the service `{}` has no domain", service))?;
+
+                let data = match data {
+                    Some(data) if templates::is_template(data) => {
+                        match context.home_assistant.status().await {
+                            Status::Connected { entities } => {
+                                Some(templates::render_value(data, &entities)?)
+                            }
+                            Status::Disconnected => {
+                                anyhow::bail!("Home-Assistant is disconnected")
+                            }
+                        }
+                    }
+                    data => data.clone(),
+                };
+
+                context
+                    .home_assistant
+                    .call_service(domain, service, data.as_ref(), None)
+                    .await?
+            }
+            Self::Delay { duration } => tokio::time::sleep(*duration).await,
+            Self::Sequence { actions } => run_all(actions, context).await?,
+            Self::Parallel { actions } => {
+                let errors: Vec<String> =
+                    join_all(actions.iter().map(|action| action.run(context)))
+                        .await
+                        .into_iter()
+                        .filter_map(|result| result.err().map(|err| err.to_string()))
+                        .collect();
+
+                if !errors.is_empty() {
+                    anyhow::bail!("{}", errors.join("; "));
+                }
+            }
+            Self::Choose { choices, default } => {
+                let mut actions = default;
+
+                for choice in choices {
+                    if crate::rules::engine::check(&choice.conditions, context).await? {
+                        actions = &choice.actions;
+                        break;
+                    }
+                }
+
+                run_all(actions, context).await?
+            }
+            Self::Repeat {
+                actions,
+                count,
+                until,
+            } => {
+                let count = match (count, until.is_empty()) {
+                    (Some(count), _) => *count,
+                    (None, true) => 1,
+                    (None, false) => MAX_REPEATS,
+                };
+
+                for _ in 0..count.min(MAX_REPEATS) {
+                    run_all(actions, context).await?;
+
+                    if !until.is_empty() && crate::rules::engine::check(until, context).await? {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::home_assistant::State;
+
+/// The air quality configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AirQualityConfig {
+    /// The entity reporting the CO2 concentration, in ppm.
+    #[serde(default)]
+    pub co2_entity: Option<String>,
+
+    /// The entity reporting the PM2.5 concentration, in µg/m³.
+    #[serde(default)]
+    pub pm25_entity: Option<String>,
+
+    /// The CO2 concentrations from which the level is moderate, poor and bad.
+    #[serde(default = "AirQualityConfig::default_co2_thresholds")]
+    pub co2_thresholds: [f64; 3],
+
+    /// The PM2.5 concentrations from which the level is moderate, poor and bad.
+    #[serde(default = "AirQualityConfig::default_pm25_thresholds")]
+    pub pm25_thresholds: [f64; 3],
+}
+
+impl AirQualityConfig {
+    fn default_co2_thresholds() -> [f64; 3] {
+        [800.0, 1200.0, 2000.0]
+    }
+
+    fn default_pm25_thresholds() -> [f64; 3] {
+        [12.0, 35.5, 55.5]
+    }
+
+    /// Compute the air quality status from the current entities.
+    pub fn status(&self, entities: &HashMap<String, State>) -> AirQualityStatus {
+        let reading = |entity_id: &Option<String>, thresholds: &[f64; 3]| {
+            entity_id
+                .as_ref()
+                .and_then(|entity_id| entities.get(entity_id))
+                .and_then(|state| state.state.parse::<f64>().ok())
+                .map(|value| AirQualityReading {
+                    value,
+                    level: AirQualityLevel::from_thresholds(value, thresholds),
+                })
+        };
+
+        let co2 = reading(&self.co2_entity, &self.co2_thresholds);
+        let pm25 = reading(&self.pm25_entity, &self.pm25_thresholds);
+
+        // The overall level is the worst level among the available readings.
+        let level = [&co2, &pm25]
+            .into_iter()
+            .flatten()
+            .map(|reading| reading.level)
+            .max();
+
+        AirQualityStatus { co2, pm25, level }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum AirQualityLevel {
+    Good,
+    Moderate,
+    Poor,
+    Bad,
+}
+
+impl AirQualityLevel {
+    fn from_thresholds(value: f64, thresholds: &[f64; 3]) -> Self {
+        match thresholds.iter().filter(|&&t| value >= t).count() {
+            0 => Self::Good,
+            1 => Self::Moderate,
+            2 => Self::Poor,
+            _ => Self::Bad,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AirQualityReading {
+    pub value: f64,
+    pub level: AirQualityLevel,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AirQualityStatus {
+    pub co2: Option<AirQualityReading>,
+    pub pm25: Option<AirQualityReading>,
+    pub level: Option<AirQualityLevel>,
+}
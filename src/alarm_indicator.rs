@@ -0,0 +1,160 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use futures_util::future::try_join_all;
+use log::info;
+use serde::Deserialize;
+
+use crate::{
+    context::AppContext,
+    outputs::{Output, OutputPattern},
+    red_led::RedLedOwner,
+    tasks,
+};
+
+/// The configuration of the local alarm indicators, which reflect the state of
+/// an `alarm_control_panel` entity on the LEDs and the buzzer.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlarmIndicatorConfig {
+    /// The alarm entity, like `alarm_control_panel.home`.
+    pub entity_id: String,
+
+    /// The output patterns by alarm state, like `armed_away` or `pending`.
+    /// The outputs are off in the other states.
+    ///
+    /// Defaults to the green LED when disarmed, the red LED when armed, and
+    /// the buzzer while pending or triggered.
+    #[serde(default = "AlarmIndicatorConfig::default_states")]
+    pub states: HashMap<String, HashMap<Output, OutputPattern>>,
+}
+
+impl AlarmIndicatorConfig {
+    /// Reflect an alarm with the default patterns.
+    pub fn new(entity_id: String) -> Self {
+        Self {
+            entity_id,
+            states: Self::default_states(),
+        }
+    }
+
+    fn default_states() -> HashMap<String, HashMap<Output, OutputPattern>> {
+        let armed = HashMap::from([(Output::RedLed, OutputPattern::On)]);
+        let mut states: HashMap<_, _> = [
+            "armed_away",
+            "armed_home",
+            "armed_night",
+            "armed_vacation",
+            "armed_custom_bypass",
+        ]
+        .into_iter()
+        .map(|state| (state.to_string(), armed.clone()))
+        .collect();
+
+        states.insert(
+            "disarmed".to_string(),
+            HashMap::from([(Output::GreenLed, OutputPattern::On)]),
+        );
+        states.insert(
+            "arming".to_string(),
+            HashMap::from([(
+                Output::RedLed,
+                OutputPattern::Blink {
+                    period: Duration::from_secs(1),
+                },
+            )]),
+        );
+        states.insert(
+            "pending".to_string(),
+            HashMap::from([
+                (Output::RedLed, OutputPattern::On),
+                (
+                    Output::Buzzer,
+                    OutputPattern::Beep {
+                        interval: Duration::from_secs(1),
+                    },
+                ),
+            ]),
+        );
+        states.insert(
+            "triggered".to_string(),
+            HashMap::from([
+                (
+                    Output::RedLed,
+                    OutputPattern::Blink {
+                        period: Duration::from_millis(500),
+                    },
+                ),
+                (
+                    Output::Buzzer,
+                    OutputPattern::Blink {
+                        period: Duration::from_millis(500),
+                    },
+                ),
+            ]),
+        );
+
+        states
+    }
+
+    /// Reflect the alarm state on the outputs forever.
+    pub async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        let mut states = context.home_assistant.watch_states();
+        let outputs: HashSet<Output> = self
+            .states
+            .values()
+            .flat_map(|patterns| patterns.keys().copied())
+            .collect();
+
+        loop {
+            let state = self.state(context).await;
+
+            info!(
+                "Alarm `{}` is `{}`.",
+                self.entity_id,
+                state.as_deref().unwrap_or("unknown")
+            );
+
+            let patterns = state.as_ref().and_then(|state| self.states.get(state));
+            let drive = try_join_all(outputs.iter().map(|output| {
+                let pattern = patterns
+                    .and_then(|patterns| patterns.get(output))
+                    .copied()
+                    .unwrap_or_default();
+
+                pattern.drive_for(&context.gpio, *output, RedLedOwner::Alarm)
+            }));
+
+            // Keep the steady patterns until the state changes.
+            let drive = async {
+                drive.await?;
+                futures_util::future::pending::<anyhow::Result<()>>().await
+            };
+
+            let changed = async {
+                loop {
+                    states.changed().await?;
+                    tasks::heartbeat();
+
+                    if self.state(context).await != state {
+                        return anyhow::Ok(());
+                    }
+                }
+            };
+
+            tokio::select! {
+                r = drive => r?,
+                r = changed => r?,
+            }
+        }
+    }
+
+    async fn state(&self, context: &AppContext) -> Option<String> {
+        context
+            .home_assistant
+            .entity(&self.entity_id)
+            .await
+            .map(|state| state.state)
+    }
+}
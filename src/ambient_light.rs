@@ -0,0 +1,257 @@
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::Deserialize;
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::{screen::Screen, tasks};
+
+#[cfg(feature = "gpio")]
+use rppal::i2c::I2c;
+
+/// The supported I2C lux sensors.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LuxSensor {
+    Bh1750,
+    Tsl2561,
+}
+
+impl LuxSensor {
+    fn default_address(self) -> u16 {
+        match self {
+            Self::Bh1750 => 0x23,
+            Self::Tsl2561 => 0x39,
+        }
+    }
+}
+
+/// A point of the lux to brightness curve.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct BrightnessPoint {
+    pub lux: f64,
+
+    /// The screen brightness, from 0 to 255.
+    pub brightness: u8,
+}
+
+/// The ambient light sensor configuration, which drives the screen brightness.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct AmbientLightConfig {
+    pub sensor: LuxSensor,
+
+    /// The I2C bus the sensor is connected to.
+    #[serde(default = "AmbientLightConfig::default_bus")]
+    pub bus: u8,
+
+    /// The I2C address of the sensor. The default one of the sensor if
+    /// unspecified.
+    #[serde(default)]
+    pub address: Option<u16>,
+
+    /// The brightness for each level of ambient light, interpolated linearly
+    /// between the points and constant beyond them.
+    #[serde(default = "AmbientLightConfig::default_curve")]
+    pub curve: Vec<BrightnessPoint>,
+
+    /// The smallest brightness change to apply, so that the screen does not
+    /// flicker with the sensor noise.
+    #[serde(default = "AmbientLightConfig::default_min_change")]
+    pub min_change: u8,
+
+    /// The interval between readings.
+    #[serde(default = "AmbientLightConfig::default_poll_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub poll_interval: Duration,
+}
+
+impl AmbientLightConfig {
+    fn default_bus() -> u8 {
+        1
+    }
+
+    fn default_curve() -> Vec<BrightnessPoint> {
+        [(0.0, 15), (10.0, 60), (100.0, 140), (1000.0, 255)]
+            .into_iter()
+            .map(|(lux, brightness)| BrightnessPoint { lux, brightness })
+            .collect()
+    }
+
+    fn default_min_change() -> u8 {
+        8
+    }
+
+    fn default_poll_interval() -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn address(&self) -> u16 {
+        self.address
+            .unwrap_or_else(|| self.sensor.default_address())
+    }
+
+    /// Get the brightness for an ambient light level.
+    pub fn brightness(&self, lux: f64) -> Option<u8> {
+        let mut curve = self.curve.clone();
+        curve.sort_by(|a, b| a.lux.total_cmp(&b.lux));
+
+        let first = curve.first()?;
+        let last = curve.last()?;
+
+        if lux <= first.lux {
+            return Some(first.brightness);
+        }
+
+        let brightness = curve
+            .windows(2)
+            .find(|points| lux <= points[1].lux)
+            .map(|points| {
+                let (low, high) = (points[0], points[1]);
+                let ratio = (lux - low.lux) / (high.lux - low.lux);
+
+                f64::from(low.brightness)
+                    + ratio * (f64::from(high.brightness) - f64::from(low.brightness))
+            })
+            .unwrap_or_else(|| f64::from(last.brightness));
+
+        Some(brightness.round() as u8)
+    }
+
+    /// Adjust the screen brightness to the ambient light forever.
+    ///
+    /// The brightness set through MQTT is overridden on the next significant
+    /// change of the ambient light.
+    pub async fn run(&self, screen: &Screen) -> anyhow::Result<()> {
+        info!(
+            "Adjusting the screen brightness with the {:?} at address {:#04x} on I2C bus {}.",
+            self.sensor,
+            self.address(),
+            self.bus
+        );
+
+        loop {
+            match self.read().await {
+                Ok(lux) => {
+                    let current = screen.state().brightness;
+
+                    if let Some(brightness) = self.brightness(lux) {
+                        // Small changes are still applied to reach the ends of
+                        // the range.
+                        let extreme = brightness == 0 || brightness == u8::MAX;
+
+                        if brightness.abs_diff(current) >= self.min_change
+                            || (extreme && brightness != current)
+                        {
+                            screen.set_brightness(brightness)?;
+                        }
+                    }
+                }
+                Err(err) => warn!("Failed to read the ambient light: {}", err),
+            }
+
+            tokio::time::sleep(self.poll_interval).await;
+            tasks::heartbeat();
+        }
+    }
+
+    async fn read(&self) -> anyhow::Result<f64> {
+        let (sensor, bus, address) = (self.sensor, self.bus, self.address());
+
+        tokio::task::spawn_blocking(move || match sensor {
+            LuxSensor::Bh1750 => read_bh1750(bus, address),
+            LuxSensor::Tsl2561 => read_tsl2561(bus, address),
+        })
+        .await?
+    }
+}
+
+/// Read the ambient light of a BH1750, in lux.
+#[cfg(feature = "gpio")]
+fn read_bh1750(bus: u8, address: u16) -> anyhow::Result<f64> {
+    const POWER_ON: u8 = 0x01;
+    const ONE_TIME_HIGH_RESOLUTION: u8 = 0x20;
+
+    let mut i2c = I2c::with_bus(bus)?;
+    i2c.set_slave_address(address)?;
+
+    i2c.write(&[POWER_ON])?;
+    i2c.write(&[ONE_TIME_HIGH_RESOLUTION])?;
+
+    // The measurement takes at most 180 ms.
+    std::thread::sleep(Duration::from_millis(180));
+
+    let mut buffer = [0u8; 2];
+    i2c.read(&mut buffer)?;
+
+    Ok(f64::from(u16::from_be_bytes(buffer)) / 1.2)
+}
+
+/// Read the ambient light of a TSL2561, in lux.
+#[cfg(feature = "gpio")]
+fn read_tsl2561(bus: u8, address: u16) -> anyhow::Result<f64> {
+    const COMMAND: u8 = 0x80;
+    const WORD: u8 = 0x20;
+    const REGISTER_CONTROL: u8 = 0x00;
+    const REGISTER_TIMING: u8 = 0x01;
+    const REGISTER_DATA0: u8 = 0x0C;
+    const REGISTER_DATA1: u8 = 0x0E;
+
+    let mut i2c = I2c::with_bus(bus)?;
+    i2c.set_slave_address(address)?;
+
+    // Power on, with the low gain and the 402 ms integration, which do not
+    // saturate in daylight.
+    i2c.write(&[COMMAND | REGISTER_CONTROL, 0x03])?;
+    i2c.write(&[COMMAND | REGISTER_TIMING, 0x02])?;
+
+    std::thread::sleep(Duration::from_millis(450));
+
+    // Registers are little-endian.
+    let read_register = |register: u8| -> anyhow::Result<u16> {
+        let mut buffer = [0u8; 2];
+        i2c.write_read(&[COMMAND | WORD | register], &mut buffer)?;
+
+        Ok(u16::from_le_bytes(buffer))
+    };
+
+    // The lux formula expects the high gain, which is 16 times the low one.
+    let broadband = f64::from(read_register(REGISTER_DATA0)?) * 16.0;
+    let infrared = f64::from(read_register(REGISTER_DATA1)?) * 16.0;
+
+    Ok(tsl2561_lux(broadband, infrared))
+}
+
+/// Compute the lux from the TSL2561 channels, with the empirical formula of
+/// its datasheet.
+#[cfg(feature = "gpio")]
+fn tsl2561_lux(broadband: f64, infrared: f64) -> f64 {
+    if broadband <= 0.0 {
+        return 0.0;
+    }
+
+    let ratio = infrared / broadband;
+    let lux = match ratio {
+        r if r <= 0.5 => 0.0304 * broadband - 0.062 * broadband * r.powf(1.4),
+        r if r <= 0.61 => 0.0224 * broadband - 0.031 * infrared,
+        r if r <= 0.8 => 0.0128 * broadband - 0.0153 * infrared,
+        r if r <= 1.3 => 0.00146 * broadband - 0.00112 * infrared,
+        _ => 0.0,
+    };
+
+    lux.max(0.0)
+}
+
+#[cfg(not(feature = "gpio"))]
+fn read_bh1750(_bus: u8, _address: u16) -> anyhow::Result<f64> {
+    Err(anyhow::anyhow!(
+        "ambient light support requires the `gpio` feature"
+    ))
+}
+
+#[cfg(not(feature = "gpio"))]
+fn read_tsl2561(_bus: u8, _address: u16) -> anyhow::Result<f64> {
+    Err(anyhow::anyhow!(
+        "ambient light support requires the `gpio` feature"
+    ))
+}
@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use log::info;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::sync::watch;
+
+/// A message shown full-screen on the panel, to reach whoever walks by.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Announcement {
+    pub title: Option<String>,
+    pub message: String,
+    pub shown_at: DateTime<Utc>,
+
+    /// When the message goes away, unless replaced by another one first.
+    pub until: DateTime<Utc>,
+}
+
+/// The announcement currently shown, if any.
+pub struct Announcements {
+    current: watch::Sender<Option<Announcement>>,
+}
+
+impl Default for Announcements {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Announcements {
+    pub fn new() -> Self {
+        Self {
+            current: watch::channel(None).0,
+        }
+    }
+
+    pub fn current(&self) -> Option<Announcement> {
+        self.current.borrow().clone()
+    }
+
+    /// Watch the changes of the current announcement.
+    pub fn watch(&self) -> watch::Receiver<Option<Announcement>> {
+        self.current.subscribe()
+    }
+
+    /// Show an announcement, replacing the current one.
+    pub fn show(&self, announcement: Announcement) {
+        info!("Announcing `{}`.", announcement.message);
+
+        self.current.send_replace(Some(announcement));
+    }
+
+    /// Take the announcements down once they expire, forever.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let mut changes = self.current.subscribe();
+
+        loop {
+            let until = changes
+                .borrow_and_update()
+                .as_ref()
+                .map(|announcement| announcement.until);
+
+            match until {
+                Some(until) => {
+                    let delay = (until - Utc::now()).to_std().unwrap_or_default();
+
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {
+                            // Unless it was replaced meanwhile.
+                            self.current.send_if_modified(|current| {
+                                let expired = current
+                                    .as_ref()
+                                    .is_some_and(|announcement| announcement.until <= Utc::now());
+
+                                if expired {
+                                    *current = None;
+                                }
+
+                                expired
+                            });
+                        }
+                        r = changes.changed() => r?,
+                    }
+                }
+                None => changes.changed().await?,
+            }
+        }
+    }
+}
@@ -1,106 +1,187 @@
 use std::{
-    sync::Arc,
-    time::{Duration, Instant},
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
+#[cfg(feature = "presence")]
+use std::{sync::atomic::Ordering, time::Instant};
 
-use chrono::{DateTime, Utc};
-use log::{error, info};
+use chrono::Local;
+#[cfg(feature = "presence")]
+use chrono::Utc;
+use log::{info, warn};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use tokio::time::sleep;
-use warp::{Filter, Rejection, Reply};
+use tokio::{
+    sync::{broadcast, RwLock},
+    time::sleep,
+};
+use warp::{http::StatusCode, Filter, Rejection, Reply};
 
+#[cfg(feature = "presence")]
+use crate::presence::Proximity;
 use crate::{
-    config::HomeControlConfig,
-    gpio_controller::GpioController,
-    home_assistant::{self, Controller},
-    Result,
+    alarm_indicator::AlarmIndicatorConfig,
+    announcements::Announcements,
+    appliances,
+    artwork::Artwork,
+    audio::Audio,
+    auth::Sessions,
+    barcode::Barcodes,
+    camera::Cameras,
+    changelog::Changelog,
+    circadian::Circadian,
+    climate::ClimateBooster,
+    clock_skew::ClockSkew,
+    context::AppContext,
+    control_action,
+    debounce::Debouncer,
+    departures::Departures,
+    digest::Digest,
+    disk::Disk,
+    energy_meter::EnergyMeter,
+    esphome::Esphome,
+    fan::Fan,
+    forecast::ForecastCache,
+    frost,
+    gestures::DetectedGesture,
+    hazards::Hazards,
+    home_assistant,
+    indoor::IndoorConfig,
+    inputs,
+    intercom::Intercom,
+    kiosk::Kiosk,
+    lockout::Lockout,
+    media_groups::MediaGroups,
+    melody::{Melody, MelodyPlayer},
+    mirrors,
+    mqtt::zigbee2mqtt::Zigbee2Mqtt,
+    network::Network,
+    notifications::{Notifications, Severity},
+    nowcast::Nowcast,
+    outputs::OutputPattern,
+    panic::Panic,
+    polling,
+    presence::DistanceReading,
+    red_led::RedLedOwner,
+    rfid::Rfid,
+    screensaver::Screensaver,
+    self_check::{self, SelfCheckReport},
+    serial_devices::SerialDevices,
+    shutdown::ShutdownController,
+    sleep_timer::SleepTimer,
+    sound_level::SoundLevelSensor,
+    tasks,
+    thermostat::Thermostats,
+    ups::Ups,
+    usage,
+    voice::Voice,
 };
 
-pub struct Api {
-    gpio_controller: Arc<GpioController>,
-    ha_controller: Controller,
-    home_control_config: HomeControlConfig,
-}
+mod alarm;
+mod announce;
+mod auth;
+mod barcode;
+mod chores;
+mod climate;
+mod config;
+mod debug;
+mod esphome;
+mod events;
+mod filters;
+mod gpio;
+mod ha;
+mod hazards;
+mod intercom;
+mod irrigation;
+mod lights;
+mod limits;
+mod media;
+mod openings;
+mod panic;
+mod rules;
+mod schema;
+mod screen;
+mod serial;
+mod status;
+mod system;
+#[cfg(test)]
+mod testing;
+mod theme;
+mod thermostats;
+mod timers;
+mod users;
+mod versions;
+mod wakeup;
+mod weather;
+mod zigbee;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(tag = "status", rename_all = "camelCase")]
-pub enum Status {
-    Disconnected,
-    #[serde(rename_all = "camelCase")]
-    Connected {
-        location: String,
-        weather_current: Box<WeatherStatus>,
-        weather_forecast: Box<WeatherStatus>,
+pub use self::{
+    auth::SessionStatus,
+    filters::{path_prefix, ErrorResponse},
+    lights::LightStatus,
+    limits::RouteLimitsConfig,
+    status::{
+        ConnectedStatus, GroupedStatus, Status, StatusBlock, StatusConfig, StatusVersion,
+        WeatherBlock, WeatherStatus,
     },
-}
+    versions::{ApiClientUsage, ApiVersion, ApiVersionsConfig},
+};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct WeatherStatus {
-    pub timestamp: DateTime<Utc>,
-    pub state: String,
-    pub humidity: Option<f64>,
-    pub pressure: Option<f64>,
-    pub temperature: f64,
-    pub wind_speed: f64,
-    pub wind_bearing: f64,
-}
+use self::{
+    filters::{handle_api_rejection, handle_rejection, under_prefixes, Context},
+    versions::ApiUsage,
+};
 
-impl Status {
-    fn new(
-        ha_status: home_assistant::Status,
-        home_control_config: &HomeControlConfig,
-    ) -> Result<Self> {
-        Ok(match ha_status {
-            home_assistant::Status::Disconnected => Status::Disconnected,
-            home_assistant::Status::Connected { mut entities } => {
-                let weather_state: home_assistant::WeatherState = entities
-                    .remove(&home_control_config.weather_entity)
-                    .ok_or_else(|| {
-                        anyhow::anyhow!(
-                            "Weather entity `{}` was not found",
-                            home_control_config.weather_entity
-                        )
-                    })?
-                    .try_into()?;
-
-                let first_forecast = weather_state
-                    .attributes
-                    .forecast
-                    .into_iter()
-                    .next()
-                    .ok_or_else(|| anyhow::anyhow!("No forecast found"))?;
-
-                let weather_current = Box::new(WeatherStatus {
-                    timestamp: weather_state.last_changed,
-                    state: weather_state.state,
-                    humidity: Some(weather_state.attributes.humidity),
-                    pressure: Some(weather_state.attributes.pressure),
-                    temperature: weather_state.attributes.temperature,
-                    wind_speed: weather_state.attributes.wind_speed,
-                    wind_bearing: weather_state.attributes.wind_bearing,
-                });
-                let weather_forecast = Box::new(WeatherStatus {
-                    timestamp: first_forecast.datetime,
-                    state: first_forecast.condition,
-                    humidity: None,
-                    pressure: None,
-                    temperature: first_forecast.temperature,
-                    wind_speed: first_forecast.wind_speed,
-                    wind_bearing: first_forecast.wind_bearing,
-                });
-
-                Status::Connected {
-                    location: home_control_config.location.clone(),
-                    weather_current,
-                    weather_forecast,
-                }
-            }
-        })
-    }
+pub struct Api {
+    context: AppContext,
+    circadian: Option<Circadian>,
+    climate_booster: ClimateBooster,
+    notifications: Notifications,
+    announcements: Announcements,
+    digest: Option<Digest>,
+    cameras: Cameras,
+    departures: Option<Departures>,
+    nowcast: Option<Nowcast>,
+    sleep_timer: Option<SleepTimer>,
+    thermostats: Thermostats,
+    audio: Option<Audio>,
+    artwork: Artwork,
+    media_groups: MediaGroups,
+    sound_level: Option<SoundLevelSensor>,
+    rfid: Option<Rfid>,
+    barcodes: Option<Barcodes>,
+    ups: Option<Ups>,
+    fan: Option<Fan>,
+    disk: Disk,
+    forecast_cache: ForecastCache<WeatherBlock>,
+    energy_meter: Option<EnergyMeter>,
+    serial_devices: SerialDevices,
+    kiosk: Option<Kiosk>,
+    arm_confirmations: Mutex<Vec<alarm::ArmConfirmation>>,
+    panic: Option<Panic>,
+    hazards: Option<Hazards>,
+    voice: Option<Voice>,
+    changelog: Option<Changelog>,
+    shutdown_controller: ShutdownController,
+    network: Option<Network>,
+    screensaver: Option<Screensaver>,
+    intercom: Option<Intercom>,
+    zigbee: Option<Zigbee2Mqtt>,
+    esphome: Esphome,
+    clock_skew: ClockSkew,
+    sessions: Option<Sessions>,
+    settings_sessions: Option<Sessions>,
+    lockout: Lockout,
+    debouncer: Debouncer,
+    distance_readings: broadcast::Sender<DistanceReading>,
+    gestures: broadcast::Sender<DetectedGesture>,
+    self_check: RwLock<Option<SelfCheckReport>>,
+    melody_player: Arc<MelodyPlayer>,
+    api_usage: ApiUsage,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Copy, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum ApiBool {
     Bool(bool),
@@ -117,133 +198,1011 @@ impl From<ApiBool> for bool {
 }
 
 impl Api {
-    pub fn new(
-        gpio_controller: Arc<GpioController>,
-        ha_controller: Controller,
-        home_control_config: HomeControlConfig,
-    ) -> anyhow::Result<Arc<Self>> {
+    pub fn new(context: AppContext) -> anyhow::Result<Arc<Self>> {
+        let home_control_config = &context.config;
+        let circadian = home_control_config.circadian.clone().map(Circadian::new);
+        let departures = home_control_config.departures.clone().map(Departures::new);
+        let nowcast = home_control_config.nowcast.clone().map(Nowcast::new);
+        let sleep_timer = home_control_config.sleep_timer.clone().map(SleepTimer::new);
+        let thermostats = Thermostats::new(home_control_config.thermostats.clone());
+        let audio = home_control_config.audio.clone().map(Audio::new);
+        let sound_level = home_control_config
+            .sound_level
+            .clone()
+            .map(SoundLevelSensor::new);
+        let cameras = Cameras::new(home_control_config.cameras.clone());
+        let artwork = Artwork::new(home_control_config.artwork.clone());
+        let media_groups = MediaGroups::new(home_control_config.media_groups.clone());
+        let rfid = home_control_config.rfid.clone().map(Rfid::new);
+        let barcodes = home_control_config.barcode.clone().map(Barcodes::new);
+        let ups = home_control_config.ups.clone().map(Ups::new);
+        let fan = home_control_config.fan.clone().map(Fan::new);
+        let disk = Disk::new(home_control_config.disk.clone());
+        let forecast_cache = ForecastCache::new(home_control_config.forecast_cache.clone());
+        let energy_meter = home_control_config
+            .energy_meter
+            .clone()
+            .map(EnergyMeter::new);
+        let serial_devices = SerialDevices::new(home_control_config.serial_devices.clone())?;
+        let kiosk = home_control_config.kiosk.clone().map(Kiosk::new);
+        let changelog = home_control_config.changelog.clone().map(Changelog::new);
+        let panic = home_control_config.panic.clone().map(Panic::new);
+        let digest = home_control_config.digest.clone().map(Digest::new);
+        let hazards = home_control_config.hazards.clone().map(Hazards::new);
+        let voice = home_control_config.voice.clone().map(Voice::new);
+        let shutdown_controller = ShutdownController::new(home_control_config.shutdown.clone());
+        let network = home_control_config.network.clone().map(Network::new);
+        let screensaver = home_control_config
+            .screensaver
+            .clone()
+            .map(Screensaver::new);
+        let intercom = home_control_config.intercom.clone().map(Intercom::new);
+        let zigbee = home_control_config
+            .mqtt
+            .as_ref()
+            .and_then(|mqtt| mqtt.zigbee2mqtt.clone())
+            .map(Zigbee2Mqtt::new);
+        let esphome = Esphome::new(home_control_config.esphome.clone());
+        let clock_skew = ClockSkew::new(home_control_config.clock_skew.clone());
+        let sessions = home_control_config.auth.clone().map(Sessions::new);
+        let settings_sessions = home_control_config.settings_auth.clone().map(Sessions::new);
+        let lockout = Lockout::new(home_control_config.pin_lockout.clone());
+        let debouncer = Debouncer::new(home_control_config.command_debounce_window);
+        let melody_player = Arc::new(MelodyPlayer::new(Arc::clone(&context.gpio)));
+        let climate_booster = ClimateBooster::new(
+            home_control_config.climate_boost.clone(),
+            context.home_assistant.clone(),
+        );
+
         Ok(Arc::new(Self {
-            gpio_controller,
-            ha_controller,
-            home_control_config,
+            context,
+            circadian,
+            climate_booster,
+            notifications: Notifications::new(),
+            announcements: Announcements::new(),
+            digest,
+            cameras,
+            departures,
+            nowcast,
+            sleep_timer,
+            thermostats,
+            audio,
+            artwork,
+            media_groups,
+            sound_level,
+            rfid,
+            barcodes,
+            ups,
+            fan,
+            disk,
+            forecast_cache,
+            energy_meter,
+            serial_devices,
+            kiosk,
+            arm_confirmations: Mutex::new(Vec::new()),
+            panic,
+            hazards,
+            voice,
+            changelog,
+            shutdown_controller,
+            network,
+            screensaver,
+            intercom,
+            zigbee,
+            esphome,
+            clock_skew,
+            sessions,
+            settings_sessions,
+            lockout,
+            debouncer,
+            distance_readings: broadcast::channel(16).0,
+            gestures: broadcast::channel(4).0,
+            self_check: RwLock::new(None),
+            melody_player,
+            api_usage: ApiUsage::default(),
         }))
     }
 
-    pub async fn run(self: Arc<Self>) -> anyhow::Result<()> {
-        let period = Duration::from_secs(1);
+    /// Spawn the background tasks, each restarted whenever it fails.
+    pub fn run(self: &Arc<Self>) {
+        let tasks = &self.context.tasks;
+
+        tokio::spawn({
+            let tasks = tasks.clone();
+            let api = Arc::clone(self);
+
+            async move { tasks.run("self_check", api.run_self_check()).await }
+        });
+
+        // A display left unrotated is still usable: only warn.
+        tokio::spawn({
+            let screen = Arc::clone(&self.context.screen);
+
+            async move {
+                if let Err(err) = screen.apply_rotation().await {
+                    warn!("Failed to rotate the display: {}", err);
+                }
+            }
+        });
+
+        self.spawn("presence", Self::run_presence_detection);
+        self.spawn("windows", Self::run_window_watcher);
+        self.spawn("indoor", Self::run_indoor_watcher);
+        self.spawn("auth_failure", Self::run_auth_failure_watcher);
+        self.spawn("departures", Self::run_departures);
+        self.spawn("nowcast", Self::run_nowcast);
+        self.spawn("weather_alerts", Self::run_weather_alerts);
+        self.spawn("irrigation", Self::run_irrigation);
+        self.spawn("wakeup", Self::run_wakeup);
+        self.spawn("sleep_timer", Self::run_sleep_timer);
+        self.spawn("thermostats", Self::run_thermostats);
+        self.spawn("sound_level", Self::run_sound_level);
+        self.spawn("rfid", Self::run_rfid);
+        self.spawn("barcode", Self::run_barcode);
+        self.spawn("ups", Self::run_ups);
+        self.spawn("fan", Self::run_fan);
+        self.spawn("disk", Self::run_disk);
+        self.spawn("latency", Self::run_latency);
+        self.spawn("energy_meter", Self::run_energy_meter);
+        self.spawn("serial_devices", Self::run_serial_devices);
+        self.spawn("kiosk", Self::run_kiosk);
+        self.spawn("changelog", Self::run_changelog);
+        self.spawn("network", Self::run_network);
+        self.spawn("clock_skew", Self::run_clock_skew);
+        self.spawn("alarm_indicator", Self::run_alarm_indicator);
+        self.spawn("panic", Self::run_panic);
+        self.spawn("hazards", Self::run_hazards);
+        self.spawn("ambient_light", Self::run_ambient_light);
+        self.spawn("mirrors", Self::run_mirrors);
+        self.spawn("polling", Self::run_polling);
+        self.spawn("inputs", Self::run_inputs);
+        self.spawn("usb_inputs", Self::run_usb_inputs);
+        self.spawn("appliances", Self::run_appliances);
+        self.spawn("rules", Self::run_rules);
+        self.spawn("voice", Self::run_voice);
+        self.spawn("announcements", Self::run_announcements);
+        self.spawn("digest", Self::run_digest);
+        self.spawn("frost", Self::run_frost);
+        self.spawn("mqtt", Self::run_mqtt);
+        self.spawn("esphome", Self::run_esphome);
+        self.spawn("error_policy", Self::run_error_policy);
+        self.spawn("red_led", |api| async move {
+            api.context.gpio.red_led().run(&api.context.gpio).await
+        });
+        self.spawn("usage", |api| async move {
+            usage::run(&api.context.config.usage).await
+        });
+    }
+
+    /// Spawn a task, restarted whenever it fails.
+    fn spawn<F>(self: &Arc<Self>, name: &'static str, f: fn(Arc<Self>) -> F)
+    where
+        F: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let api = Arc::clone(self);
+
+        self.context.tasks.spawn(name, move || f(Arc::clone(&api)));
+    }
+
+    async fn run_self_check(self: Arc<Self>) -> anyhow::Result<()> {
+        if let Some(config) = &self.context.config.self_check {
+            let report = self_check::run(&self.context, config).await;
+
+            *self.self_check.write().await = Some(report);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "presence"))]
+    async fn run_presence_detection(self: Arc<Self>) -> anyhow::Result<()> {
+        tasks::idle().await
+    }
+
+    #[cfg(feature = "presence")]
+    async fn run_presence_detection(self: Arc<Self>) -> anyhow::Result<()> {
+        use crate::{
+            gestures::GestureDetector,
+            presence::{DistanceUnit, PresenceFilter},
+        };
+
+        const NOTIFICATION_ID: &str = "presence-sensor";
+
+        // Isolated failures are common with ultrasonic sensors.
+        const FAILURE_THRESHOLD: u64 = 5;
+
+        let mut filter = PresenceFilter::new(self.context.config.presence.clone());
+        let mut gestures = self
+            .context
+            .config
+            .gestures
+            .clone()
+            .map(GestureDetector::new);
         let mut last_seen = Instant::now();
-        let mut screen_status = false;
+
+        loop {
+            let poll_interval = match &gestures {
+                Some(gestures) => filter.poll_interval().min(gestures.config().poll_interval),
+                None => filter.poll_interval(),
+            };
+
+            sleep(poll_interval).await;
+            tasks::heartbeat();
+
+            let sound_presence = match &self.sound_level {
+                Some(sound_level) => sound_level.presence_detected().await,
+                None => false,
+            };
+
+            // The presence is unknown while the distance sensor fails.
+            let temperature_c = match &self.context.config.presence.temperature_entity {
+                Some(entity_id) => self
+                    .context
+                    .home_assistant
+                    .entity(entity_id)
+                    .await
+                    .and_then(|state| state.state.parse::<f64>().ok()),
+                None => None,
+            };
+            let distance = self.context.gpio.get_distance(temperature_c).await;
+
+            // Nobody may be listening.
+            let _ = self.distance_readings.send(DistanceReading {
+                at: Utc::now(),
+                unit: DistanceUnit::Cm,
+                distance: distance.as_ref().ok().map(|distance| distance.cm),
+                activation_distance: self.context.config.sensor_activation_distance_cm,
+                echo_us: distance
+                    .as_ref()
+                    .ok()
+                    .map(|distance| distance.echo.as_secs_f64() * 1e6),
+                temperature_c: distance
+                    .as_ref()
+                    .ok()
+                    .map(|distance| distance.temperature_c),
+            });
+
+            let distance_presence = match distance {
+                Ok(distance) => {
+                    if self.notifications.clear(NOTIFICATION_ID).await {
+                        info!("The presence sensor recovered.");
+                    }
+
+                    if let Some(gesture) = gestures
+                        .as_mut()
+                        .and_then(|gestures| gestures.update(distance.cm, Instant::now()))
+                    {
+                        self.gesture_detected(gesture);
+                    }
+
+                    let previous = *self.context.presence.proximity.borrow();
+
+                    self.set_proximity(
+                        self.context
+                            .config
+                            .presence
+                            .proximity
+                            .bucket(distance.cm, previous),
+                    );
+
+                    Some(distance.cm <= self.context.config.sensor_activation_distance_cm)
+                }
+                Err(err) => {
+                    let health = self.context.gpio.health();
+
+                    if health.consecutive_failures >= FAILURE_THRESHOLD {
+                        self.notifications
+                            .raise(
+                                NOTIFICATION_ID,
+                                Severity::Warning,
+                                "Presence sensor failing",
+                                format!(
+                                    "The presence sensor failed {} times in a row: {}",
+                                    health.consecutive_failures, err
+                                ),
+                            )
+                            .await;
+                    }
+
+                    None
+                }
+            };
+
+            let presence = match distance_presence {
+                _ if sound_presence => Some(true),
+                presence => presence,
+            }
+            .map(|presence| filter.update(presence));
+
+            if let Some(presence) = presence {
+                self.context
+                    .presence
+                    .presence
+                    .send_if_modified(|current| std::mem::replace(current, presence) != presence);
+            }
+
+            // The screen forced from the API is left as it is.
+            let screen = &self.context.screen;
+            let screen_status = screen.state().on;
+            let overridden = screen.overridden();
+
+            match presence {
+                Some(true) => {
+                    last_seen = Instant::now();
+
+                    if !screen_status && !overridden && filter.wakes_screen(Local::now().time()) {
+                        info!("Presence detected: turning on screen.");
+                        self.set_screen(true);
+                    }
+                }
+                Some(false)
+                    if last_seen.elapsed() > self.context.config.presence_inactivity_timeout
+                        && screen_status
+                        && !overridden =>
+                {
+                    info!(
+                        "Presence not detected for {:.2}s: turning off screen.",
+                        self.context
+                            .config
+                            .presence_inactivity_timeout
+                            .as_secs_f64()
+                    );
+                    self.set_screen(false);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[cfg(feature = "presence")]
+    fn gesture_detected(&self, gesture: crate::gestures::Gesture) {
+        // Nobody may be listening.
+        let _ = self.gestures.send(DetectedGesture {
+            gesture,
+            at: Utc::now(),
+        });
+
+        let context = self.context.clone();
+
+        tokio::spawn(async move {
+            if let Some(gestures) = &context.config.gestures {
+                gestures.trigger(gesture, &context).await;
+            }
+        });
+    }
+
+    #[cfg(feature = "presence")]
+    fn set_proximity(&self, proximity: Proximity) {
+        self.context.presence.proximity.send_if_modified(|current| {
+            if *current == Some(proximity) {
+                return false;
+            }
+
+            // Counted before the watchers are woken up.
+            self.context
+                .presence
+                .proximity_changes
+                .fetch_add(1, Ordering::Relaxed);
+            *current = Some(proximity);
+
+            true
+        });
+    }
+
+    #[cfg(feature = "presence")]
+    fn set_screen(&self, on: bool) {
+        if let Err(err) = self.context.screen.set_on(on) {
+            warn!(
+                "Failed to turn the screen {}: {}",
+                if on { "on" } else { "off" },
+                err
+            );
+        }
+    }
+
+    /// Claim the red LED for an alert, or release it.
+    fn set_red_led(&self, owner: RedLedOwner, on: bool) {
+        let red_led = self.context.gpio.red_led();
+
+        if on {
+            red_led.claim(owner, OutputPattern::On);
+        } else {
+            red_led.release(owner);
+        }
+    }
+
+    async fn run_window_watcher(self: Arc<Self>) -> anyhow::Result<()> {
+        let period = Duration::from_secs(10);
 
         loop {
             sleep(period).await;
+            tasks::heartbeat();
 
-            if self.gpio_controller.get_distance_cm().await?
-                <= self.home_control_config.sensor_activation_distance_cm
-            {
-                last_seen = Instant::now();
+            let entities = match self.context.home_assistant.status().await {
+                home_assistant::Status::Connected { entities } => entities,
+                home_assistant::Status::Disconnected => continue,
+            };
 
-                if !screen_status {
-                    info!("Presence detected: turning on screen.");
-                    screen_status = true;
+            for room in &self.context.config.windows {
+                let notification_id = room.notification_id();
+
+                if room.is_heating_with_window_open(&entities) {
+                    self.notifications
+                        .raise(
+                            notification_id,
+                            Severity::Warning,
+                            "Window open",
+                            format!(
+                                "A window is open in the {} while the heating is on.",
+                                room.room
+                            ),
+                        )
+                        .await;
+                } else if self.notifications.clear(&notification_id).await {
+                    info!("Window no longer open while heating in the {}.", room.room);
                 }
-            } else if last_seen.elapsed() > self.home_control_config.presence_inactivity_timeout
-                && screen_status
-            {
-                info!(
-                    "Presence not detected for {:.2}s: turning off screen.",
-                    self.home_control_config
-                        .presence_inactivity_timeout
-                        .as_secs_f64()
-                );
-                screen_status = false;
             }
         }
     }
 
-    pub fn routes(
-        self: &Arc<Self>,
-    ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-        let api = Arc::clone(self);
-        let api_filter = warp::any().map(move || Arc::clone(&api));
-
-        // Status.
-        let api_status_get = warp::path!("api" / "v1" / "status")
-            .and(warp::get())
-            .and(api_filter.clone())
-            .and_then(Self::api_status_get);
-
-        // Alarm.
-        let api_alarm_get = warp::path!("api" / "v1" / "alarm")
-            .and(warp::get())
-            .and(api_filter.clone())
-            .and_then(Self::api_alarm_get);
-
-        // Light control.
-        let api_light = warp::path!("api" / "v1" / "light" / String);
-
-        let api_light_get = api_light
-            .and(warp::get())
-            .and(api_filter.clone())
-            .and_then(|name, api: Arc<Api>| async move { api.api_light_get(name).await });
-
-        let api_light_set = api_light
-            .and(warp::post())
-            .and(warp::body::content_length_limit(8))
-            .and(api_filter)
-            .and(warp::body::json())
-            .and_then(|light: String, api: Arc<Api>, status| async move {
-                Self::api_light_set(api, light, status).await
-            });
+    async fn run_indoor_watcher(self: Arc<Self>) -> anyhow::Result<()> {
+        const NOTIFICATION_ID: &str = "mold-risk";
+
+        let (indoor_config, alert_level) = match &self.context.config.indoor {
+            Some(
+                config @ IndoorConfig {
+                    alert_level: Some(alert_level),
+                    ..
+                },
+            ) => (config, *alert_level),
+            _ => return tasks::idle().await,
+        };
+
+        let period = Duration::from_secs(30);
+
+        loop {
+            sleep(period).await;
+            tasks::heartbeat();
+
+            let indoor = match self.context.home_assistant.status().await {
+                home_assistant::Status::Connected { entities } => indoor_config.status(&entities),
+                home_assistant::Status::Disconnected => continue,
+            };
+
+            match indoor {
+                Some(indoor) if indoor.mold_risk >= alert_level => {
+                    let raised = self
+                        .notifications
+                        .raise(
+                            NOTIFICATION_ID,
+                            Severity::Warning,
+                            "Mold risk",
+                            format!(
+                                "Indoor humidity is {:.0}% (dew point {:.1}°C): consider airing the room.",
+                                indoor.humidity, indoor.dew_point
+                            ),
+                        )
+                        .await;
+
+                    if raised {
+                        self.set_red_led(RedLedOwner::MoldRisk, true);
+                    }
+                }
+                _ => {
+                    if self.notifications.clear(NOTIFICATION_ID).await {
+                        self.set_red_led(RedLedOwner::MoldRisk, false);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn run_auth_failure_watcher(self: Arc<Self>) -> anyhow::Result<()> {
+        const NOTIFICATION_ID: &str = "home_assistant_auth";
+
+        let period = Duration::from_secs(10);
+
+        loop {
+            sleep(period).await;
+            tasks::heartbeat();
+
+            match self.context.home_assistant.info().await.auth_failure {
+                Some(reason) => {
+                    let raised = self
+                        .notifications
+                        .raise(
+                            NOTIFICATION_ID,
+                            Severity::Critical,
+                            "Home-Assistant token rejected",
+                            format!(
+                                "Home-Assistant rejects the token ({}): it may have expired or been revoked.",
+                                reason
+                            ),
+                        )
+                        .await;
+
+                    if raised {
+                        self.set_red_led(RedLedOwner::AuthFailure, true);
+                    }
+                }
+                None => {
+                    if self.notifications.clear(NOTIFICATION_ID).await {
+                        self.set_red_led(RedLedOwner::AuthFailure, false);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn run_departures(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.departures {
+            Some(departures) => departures.run().await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_nowcast(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.nowcast {
+            #[cfg(feature = "weather")]
+            Some(nowcast) => nowcast.run().await,
+            #[cfg(not(feature = "weather"))]
+            Some(_) => tasks::unsupported("The nowcast", "weather").await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_weather_alerts(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.context.config.weather_alerts {
+            #[cfg(feature = "weather")]
+            Some(weather_alerts) => weather_alerts.run(&self.context, &self.notifications).await,
+            #[cfg(not(feature = "weather"))]
+            Some(_) => tasks::unsupported("Weather alerts", "weather").await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_irrigation(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.context.scheduler.irrigation {
+            #[cfg(feature = "scheduler")]
+            Some(irrigation) => irrigation.run(&self.context).await,
+            #[cfg(not(feature = "scheduler"))]
+            Some(_) => tasks::unsupported("Irrigation", "scheduler").await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_wakeup(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.context.scheduler.wakeup {
+            #[cfg(feature = "scheduler")]
+            Some(wakeup) => wakeup.run(&self.context, &self.melody_player).await,
+            #[cfg(not(feature = "scheduler"))]
+            Some(_) => tasks::unsupported("The wake-up light", "scheduler").await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_sleep_timer(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.sleep_timer {
+            Some(sleep_timer) => sleep_timer.run(&self.context).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_thermostats(self: Arc<Self>) -> anyhow::Result<()> {
+        self.thermostats.run(&self.context).await
+    }
 
-        // Final path organization.
-        api_status_get
-            .or(api_alarm_get)
-            .or(api_light_get)
-            .or(api_light_set)
+    async fn run_esphome(self: Arc<Self>) -> anyhow::Result<()> {
+        self.esphome.run().await
     }
 
-    async fn api_status_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
-        let ha_status = self.ha_controller.status().await;
+    async fn run_sound_level(self: Arc<Self>) -> anyhow::Result<()> {
+        let sound_level = match &self.sound_level {
+            Some(sound_level) => sound_level,
+            None => return tasks::idle().await,
+        };
+
+        let report = async {
+            let input_number = match &sound_level.config().ha_input_number {
+                Some(input_number) => input_number,
+                None => return futures_util::future::pending().await,
+            };
+            let period = Duration::from_secs(30);
 
-        let status = match Status::new(ha_status, &self.home_control_config) {
-            Ok(status) => status,
-            Err(err) => {
-                error!("failed to get status: {}", err);
-                return Err(err.into());
+            loop {
+                sleep(period).await;
+                tasks::heartbeat();
+
+                if let Some(level) = sound_level.level().await {
+                    if let Err(err) = self
+                        .context
+                        .home_assistant
+                        .input_number_set_value(input_number, level.level_db.round())
+                        .await
+                    {
+                        warn!(
+                            "Failed to report the sound level to `{}`: {}",
+                            input_number, err
+                        );
+                    }
+                }
             }
         };
 
-        Ok(warp::reply::json(&status))
+        tokio::select! {
+            r = sound_level.run() => r,
+            r = report => r,
+        }
+    }
+
+    async fn run_rfid(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.rfid {
+            Some(rfid) => rfid.run(&self.context.home_assistant).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_barcode(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.barcodes {
+            Some(barcodes) => barcodes.run(&self.context.home_assistant).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_ups(self: Arc<Self>) -> anyhow::Result<()> {
+        let ups = match &self.ups {
+            Some(ups) => ups,
+            None => return tasks::idle().await,
+        };
+
+        ups.run(&self.shutdown_controller).await
+    }
+
+    async fn run_fan(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.fan {
+            Some(fan) => fan.run(&self.context.gpio).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_disk(self: Arc<Self>) -> anyhow::Result<()> {
+        self.disk.run(&self.notifications).await
     }
 
-    async fn api_alarm_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
-        // TODO: Implement.
-        //let status = self
-        //    .ha_controller
-        //    .get_light(GpioPin::RedLed)
-        //    .map_err(|_| warp::reject::reject())?;
-        let status = true;
+    async fn run_latency(self: Arc<Self>) -> anyhow::Result<()> {
+        self.context.metrics.latency.run(&self.notifications).await
+    }
+
+    async fn run_energy_meter(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.energy_meter {
+            Some(energy_meter) => energy_meter.run(&self.context).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_serial_devices(self: Arc<Self>) -> anyhow::Result<()> {
+        self.serial_devices.run().await
+    }
+
+    async fn run_kiosk(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.kiosk {
+            Some(kiosk) => kiosk.run().await,
+            None => tasks::idle().await,
+        }
+    }
 
-        Ok(warp::reply::json(&status))
+    async fn run_changelog(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.changelog {
+            Some(changelog) => changelog.run(&self.context).await,
+            None => tasks::idle().await,
+        }
     }
 
-    async fn api_light_get(self: Arc<Self>, _light: String) -> Result<impl Reply, Rejection> {
-        let status = false;
+    async fn run_network(self: Arc<Self>) -> anyhow::Result<()> {
+        let network = match &self.network {
+            Some(network) => network,
+            None => return tasks::idle().await,
+        };
+
+        let report = async {
+            let input_number = match &network.config().ha_input_number {
+                Some(input_number) => input_number,
+                None => return futures_util::future::pending().await,
+            };
+
+            loop {
+                sleep(network.config().refresh_interval).await;
+
+                let signal_dbm = network.status().await.and_then(|status| status.signal_dbm);
+
+                if let Some(signal_dbm) = signal_dbm {
+                    if let Err(err) = self
+                        .context
+                        .home_assistant
+                        .input_number_set_value(input_number, signal_dbm)
+                        .await
+                    {
+                        warn!(
+                            "Failed to report the Wi-Fi signal strength to `{}`: {}",
+                            input_number, err
+                        );
+                    }
+                }
+            }
+        };
 
-        Ok(warp::reply::json(&status))
+        tokio::select! {
+            r = network.run() => r,
+            r = report => r,
+        }
     }
 
-    async fn api_light_set(
-        self: Arc<Self>,
-        light: String,
-        status: ApiBool,
-    ) -> Result<impl Reply, Rejection> {
-        let status: bool = status.into();
-        self.ha_controller
-            .light_set(&format!("light.{}", light), status)
+    async fn run_clock_skew(self: Arc<Self>) -> anyhow::Result<()> {
+        self.clock_skew
+            .run(&self.context.home_assistant, &self.notifications)
             .await
-            .map_err(warp::reject::custom)?;
+    }
+
+    async fn run_alarm_indicator(self: Arc<Self>) -> anyhow::Result<()> {
+        let config = &self.context.config;
+
+        match (&config.alarm_indicator, &config.alarm_entity) {
+            (Some(alarm_indicator), _) => alarm_indicator.run(&self.context).await,
+            (None, Some(alarm_entity)) => {
+                AlarmIndicatorConfig::new(alarm_entity.clone())
+                    .run(&self.context)
+                    .await
+            }
+            (None, None) => tasks::idle().await,
+        }
+    }
+
+    async fn run_panic(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.panic {
+            Some(panic) => panic.run(&self.context).await,
+            None => tasks::idle().await,
+        }
+    }
 
-        Ok(warp::reply::json(&status))
+    async fn run_hazards(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.hazards {
+            Some(hazards) => hazards.run(&self.context).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_error_policy(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.context.config.error_policy {
+            Some(error_policy) => error_policy.run(&self.context).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_ambient_light(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.context.config.ambient_light {
+            Some(ambient_light) => ambient_light.run(&self.context.screen).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_mirrors(self: Arc<Self>) -> anyhow::Result<()> {
+        match self.context.config.mirrors.as_slice() {
+            [] => tasks::idle().await,
+            configured => mirrors::run(configured, &self.context).await,
+        }
+    }
+
+    async fn run_polling(self: Arc<Self>) -> anyhow::Result<()> {
+        match self.context.config.polling.as_slice() {
+            [] => tasks::idle().await,
+            configured => polling::run(configured, &self.context).await,
+        }
+    }
+
+    async fn run_inputs(self: Arc<Self>) -> anyhow::Result<()> {
+        match self.context.config.inputs.as_slice() {
+            [] => tasks::idle().await,
+            configured => inputs::run(configured, &self.context).await,
+        }
+    }
+
+    async fn run_usb_inputs(self: Arc<Self>) -> anyhow::Result<()> {
+        match self.context.config.usb_inputs.as_slice() {
+            [] => tasks::idle().await,
+            configured => inputs::usb::run(configured, &self.context).await,
+        }
+    }
+
+    async fn run_appliances(self: Arc<Self>) -> anyhow::Result<()> {
+        match self.context.config.appliances.as_slice() {
+            [] => tasks::idle().await,
+            configured => {
+                appliances::run(
+                    configured,
+                    &self.context,
+                    &self.notifications,
+                    &self.melody_player,
+                )
+                .await
+            }
+        }
+    }
+
+    async fn run_rules(self: Arc<Self>) -> anyhow::Result<()> {
+        match self.context.config.rules.as_slice() {
+            [] => tasks::idle().await,
+            configured => crate::rules::engine::run(configured, &self.context).await,
+        }
+    }
+
+    async fn run_voice(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.voice {
+            Some(voice) => voice.run(&self.context).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_announcements(self: Arc<Self>) -> anyhow::Result<()> {
+        self.announcements.run().await
+    }
+
+    async fn run_digest(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.digest {
+            Some(digest) => digest.run(&self.context, &self.notifications).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    async fn run_frost(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.context.config.frost {
+            Some(frost) => frost::run(frost, &self.context).await,
+            None => tasks::idle().await,
+        }
+    }
+
+    /// Add an event to the digest, if enabled.
+    async fn add_to_digest(&self, text: impl Into<String>) {
+        if let Some(digest) = &self.digest {
+            digest.push(text).await;
+        }
+    }
+
+    async fn run_mqtt(self: Arc<Self>) -> anyhow::Result<()> {
+        match &self.context.config.mqtt {
+            Some(mqtt) => {
+                mqtt.run(
+                    &self.context,
+                    self.context.presence.presence.subscribe(),
+                    self.zigbee.as_ref(),
+                )
+                .await
+            }
+            None => tasks::idle().await,
+        }
+    }
+
+    /// Chirp the buzzer for the outcome of a control action, if enabled.
+    fn feedback(&self, status: StatusCode) {
+        let config = &self.context.config;
+        let now = Local::now().time();
+
+        if !config.buzzer_feedback
+            || config
+                .presence
+                .quiet_hours
+                .iter()
+                .any(|quiet_hours| quiet_hours.contains(now))
+        {
+            return;
+        }
+
+        let melody = if status.is_success() {
+            Melody::SUCCESS
+        } else {
+            Melody::FAILURE
+        };
+        let melody_player = Arc::clone(&self.melody_player);
+
+        tokio::spawn(async move {
+            if let Err(err) = melody_player.play(melody).await {
+                warn!("Failed to play the feedback melody: {}", err);
+            }
+        });
+    }
+
+    /// Subscribe to the readings of the distance sensor.
+    pub fn distance_readings(&self) -> broadcast::Receiver<DistanceReading> {
+        self.distance_readings.subscribe()
+    }
+
+    /// Get the API routes, mounted under the specified prefixes of each
+    /// version, like `/api/v1` and `/api/v2`.
+    ///
+    /// The version 2 only differs in the structure of some responses, and
+    /// the responses of the version 1 are marked as deprecated. The liveness
+    /// and readiness probes are mounted at `/healthz` and `/readyz`
+    /// regardless.
+    pub fn routes(
+        self: &Arc<Self>,
+        prefix: &str,
+        v2_prefix: &str,
+    ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let ctx = Context::new(self);
+        let sunset = self.context.config.api_versions.v1_sunset;
+
+        // The routes that are the same in all the versions, boxed in halves
+        // to keep their type within the compiler limits.
+        let common = alarm::routes(&ctx)
+            .or(auth::routes(&ctx))
+            .or(barcode::routes(&ctx))
+            .or(system::routes(&ctx))
+            .or(versions::routes(&ctx))
+            .or(chores::routes(&ctx))
+            .or(media::routes(&ctx))
+            .or(screen::routes(&ctx))
+            .or(users::routes(&ctx))
+            .or(gpio::routes(&ctx))
+            .or(lights::routes(&ctx))
+            .or(openings::routes(&ctx))
+            .or(hazards::routes(&ctx))
+            .or(panic::routes(&ctx))
+            .or(announce::routes(&ctx))
+            .or(intercom::routes(&ctx))
+            .or(climate::routes(&ctx))
+            .or(ha::routes(&ctx))
+            .boxed()
+            .or(debug::routes(&ctx))
+            .or(config::routes(&ctx))
+            .or(weather::routes(&ctx))
+            .or(irrigation::routes(&ctx))
+            .or(wakeup::routes(&ctx))
+            .or(timers::routes(&ctx))
+            .or(rules::routes(&ctx))
+            .or(thermostats::routes(&ctx))
+            .or(theme::routes(&ctx))
+            .or(zigbee::routes(&ctx))
+            .or(esphome::routes(&ctx))
+            .or(serial::routes(&ctx))
+            .or(schema::routes(&ctx))
+            .boxed();
+
+        let v1 = path_prefix(prefix)
+            .and(versions::track(&ctx, ApiVersion::V1))
+            .and(
+                status::routes(&ctx)
+                    .or(events::routes(&ctx))
+                    .or(common.clone()),
+            )
+            .map(move |reply| versions::deprecate(reply, sunset));
+        let v2 = path_prefix(v2_prefix)
+            .and(versions::track(&ctx, ApiVersion::V2))
+            .and(
+                status::routes_v2(&ctx)
+                    .or(events::routes_v2(&ctx))
+                    .or(ha::routes_v2(&ctx))
+                    .or(status::routes(&ctx))
+                    .or(common),
+            );
+
+        // The paths under the prefixes are the API's, even when unknown or
+        // requested with the wrong method, rather than the frontend's.
+        let routes = system::healthz(&ctx)
+            .or(system::readyz(&ctx))
+            .or(under_prefixes(&[prefix, v2_prefix])
+                .and(v2.or(v1).recover(handle_api_rejection))
+                .boxed())
+            .recover(handle_rejection);
+
+        // The control routes flag their requests, as
+        // [`Context::control_route`].
+        ctx.api().and(routes).map(|api: Arc<Api>, reply| {
+            let response = warp::Reply::into_response(reply);
+
+            if control_action::is_flagged() {
+                api.feedback(response.status());
+            }
+
+            response
+        })
     }
 }
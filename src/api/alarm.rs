@@ -0,0 +1,302 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use log::info;
+use rand::{distributions::Alphanumeric, Rng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warp::{
+    http::{Method, StatusCode},
+    Filter, Rejection, Reply,
+};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::windows::Blocker;
+
+/// For how long the confirmation of a pre-arm check allows forcing.
+const CONFIRMATION_LIFETIME: Duration = Duration::from_secs(60);
+
+/// The brute-force protection scope of the alarm codes.
+const ALARM_SCOPE: &str = "alarm";
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlarmStatus {
+    pub entity_id: String,
+
+    /// The state of the alarm, like `armed_away` or `pending`, if known.
+    pub state: Option<String>,
+    pub last_changed: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum AlarmAction {
+    ArmAway,
+    ArmHome,
+    Disarm,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub(super) struct AlarmRequest {
+    action: AlarmAction,
+
+    /// The code of the alarm, if it requires one.
+    #[serde(default)]
+    code: Option<String>,
+
+    /// The confirmation of the failed pre-arm check, to arm despite its
+    /// blockers with the same action.
+    #[serde(default)]
+    confirmation: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct AlarmCheckQuery {
+    /// The arming action to check, which the confirmation is for.
+    #[serde(default = "AlarmCheckQuery::default_action")]
+    action: AlarmAction,
+}
+
+impl AlarmCheckQuery {
+    fn default_action() -> AlarmAction {
+        AlarmAction::ArmAway
+    }
+}
+
+/// The outcome of the pre-arm check.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PreArmCheck {
+    /// Whether the alarm can be armed right away.
+    pub ready: bool,
+    pub blockers: Vec<Blocker>,
+
+    /// The confirmation to force arming despite the blockers, with the
+    /// checked action, valid for a minute.
+    pub confirmation: Option<String>,
+}
+
+/// A confirmation of a failed pre-arm check, which allows forcing an action
+/// once.
+pub(super) struct ArmConfirmation {
+    token: String,
+    action: AlarmAction,
+    expires_at: Instant,
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_alarm_get = warp::path!("alarm")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_alarm_get);
+
+    let api_alarm_set = warp::path!("alarm")
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "alarm", 256))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, request| permit.hold(Api::api_alarm_set(api, request)));
+
+    let api_alarm_check_get = warp::path!("alarm" / "check")
+        .and(ctx.route(Method::GET, Access::Session))
+        .and(warp::query())
+        .and_then(Api::api_alarm_check_get);
+
+    api_alarm_get.or(api_alarm_set).or(api_alarm_check_get)
+}
+
+impl Api {
+    fn alarm_entity(&self) -> Result<&str, Rejection> {
+        self.context
+            .config
+            .alarm_entity
+            .as_deref()
+            .ok_or_else(warp::reject::not_found)
+    }
+
+    /// Check that the openings are closed and locked before arming, if
+    /// configured.
+    ///
+    /// A failed check comes with a new confirmation of the action.
+    async fn pre_arm_check(&self, action: AlarmAction) -> PreArmCheck {
+        let blockers = match &self.context.config.openings {
+            Some(openings) if openings.block_arming => self
+                .openings()
+                .await
+                .map(|openings| openings.blockers)
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        if blockers.is_empty() {
+            return PreArmCheck {
+                ready: true,
+                blockers,
+                confirmation: None,
+            };
+        }
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect();
+
+        let mut confirmations = self.arm_confirmations.lock().unwrap();
+        let now = Instant::now();
+
+        confirmations.retain(|confirmation| now < confirmation.expires_at);
+        confirmations.push(ArmConfirmation {
+            token: token.clone(),
+            action,
+            expires_at: now + CONFIRMATION_LIFETIME,
+        });
+
+        PreArmCheck {
+            ready: false,
+            blockers,
+            confirmation: Some(token),
+        }
+    }
+
+    /// Take the confirmation of an action out, so that it is only used once,
+    /// if it is valid.
+    ///
+    /// It is given back with [`Api::restore_confirmation`] if the action
+    /// fails, to be retried.
+    fn take_confirmation(&self, token: &str, action: AlarmAction) -> Option<ArmConfirmation> {
+        let mut confirmations = self.arm_confirmations.lock().unwrap();
+        let now = Instant::now();
+
+        confirmations.retain(|confirmation| now < confirmation.expires_at);
+
+        let index = confirmations.iter().position(|confirmation| {
+            confirmation.token == token && confirmation.action == action
+        })?;
+
+        Some(confirmations.swap_remove(index))
+    }
+
+    fn restore_confirmation(&self, confirmation: ArmConfirmation) {
+        self.arm_confirmations.lock().unwrap().push(confirmation);
+    }
+
+    async fn api_alarm_check_get(
+        self: Arc<Self>,
+        query: AlarmCheckQuery,
+    ) -> Result<impl Reply, Rejection> {
+        self.alarm_entity()?;
+
+        Ok(warp::reply::json(&self.pre_arm_check(query.action).await))
+    }
+
+    async fn api_alarm_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let entity_id = self.alarm_entity()?;
+        let state = self.context.home_assistant.entity(entity_id).await;
+
+        Ok(warp::reply::json(&AlarmStatus {
+            entity_id: entity_id.to_string(),
+            last_changed: state.as_ref().map(|state| state.last_changed),
+            state: state.map(|state| state.state),
+        }))
+    }
+
+    /// Arm or disarm the alarm. The code is checked by Home-Assistant, and its
+    /// rejections counted for the brute-force protection of the `alarm` scope.
+    ///
+    /// Arming runs the pre-arm check first: if it fails, the check is
+    /// returned with a conflict status, and arming again with its
+    /// confirmation and the same action forces it. The confirmation is only
+    /// used up once Home-Assistant accepted the action.
+    async fn api_alarm_set(
+        self: Arc<Self>,
+        request: AlarmRequest,
+    ) -> Result<warp::reply::Response, Rejection> {
+        let entity_id = self.alarm_entity()?;
+        let code = request.code.as_deref();
+        let home_assistant = &self.context.home_assistant;
+        let confirmation = request
+            .confirmation
+            .as_deref()
+            .and_then(|token| self.take_confirmation(token, request.action));
+        let forced = confirmation.is_some();
+
+        if !matches!(request.action, AlarmAction::Disarm) && !forced {
+            let check = self.pre_arm_check(request.action).await;
+
+            if !check.ready {
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&check),
+                    StatusCode::CONFLICT,
+                )
+                .into_response());
+            }
+        }
+
+        if forced {
+            info!(target: "audit", "Alarm `{}` forced despite the pre-arm check.", entity_id);
+        }
+
+        if code.is_some() {
+            if let Err(rejection) = self.check_pin_lockout(ALARM_SCOPE).await {
+                if let Some(confirmation) = confirmation {
+                    self.restore_confirmation(confirmation);
+                }
+
+                return Err(rejection);
+            }
+        }
+
+        info!(target: "audit", "Alarm `{}` action: {:?}.", entity_id, request.action);
+
+        let result = match request.action {
+            AlarmAction::ArmAway => home_assistant.alarm_arm_away(entity_id, code).await,
+            AlarmAction::ArmHome => home_assistant.alarm_arm_home(entity_id, code).await,
+            AlarmAction::Disarm => home_assistant.alarm_disarm(entity_id, code).await,
+        };
+
+        if let (Err(_), Some(confirmation)) = (&result, confirmation) {
+            self.restore_confirmation(confirmation);
+        }
+
+        match result {
+            Err(err) if code.is_some() && is_invalid_code(&err) => {
+                self.record_pin_attempt(ALARM_SCOPE, false).await?;
+
+                Err(warp::reject::custom(err))
+            }
+            Err(err) => Err(warp::reject::custom(err)),
+            Ok(()) => {
+                if code.is_some() {
+                    self.record_pin_attempt(ALARM_SCOPE, true).await?;
+                }
+
+                Ok(warp::reply::json(&true).into_response())
+            }
+        }
+    }
+}
+
+/// Whether Home-Assistant rejected the call for an invalid alarm code, rather
+/// than for being unreachable or the panel being unavailable.
+fn is_invalid_code(err: &crate::Error) -> bool {
+    match err {
+        crate::Error::HomeAssistantError { error } => {
+            let message = error.message.to_lowercase();
+
+            error.code == "invalid_code"
+                || (message.contains("invalid") && message.contains("code"))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,111 @@
+//! Tests of the alarm routes.
+
+use super::*;
+use crate::{api::testing, home_assistant::Client};
+
+fn home_assistant_error(code: &str, message: &str) -> crate::Error {
+    crate::home_assistant::Error {
+        code: code.to_string(),
+        message: message.to_string(),
+    }
+    .into()
+}
+
+#[test]
+fn only_the_invalid_codes_count_as_pin_attempts() {
+    assert!(is_invalid_code(&home_assistant_error(
+        "service_validation_error",
+        "Invalid alarm code provided"
+    )));
+    assert!(!is_invalid_code(&home_assistant_error(
+        "not_found",
+        "Entity alarm_control_panel.home not found"
+    )));
+    assert!(!is_invalid_code(&crate::Error::Busy));
+}
+
+/// An API with an alarm, which the unknown front door blocks from arming.
+async fn api() -> (Arc<Api>, Client) {
+    testing::api(serde_json::json!({
+        "alarm_entity": "alarm_control_panel.home",
+        "openings": {"sensors": ["binary_sensor.front_door"], "block_arming": true},
+    }))
+    .await
+}
+
+fn request(action: AlarmAction, confirmation: Option<&str>) -> AlarmRequest {
+    AlarmRequest {
+        action,
+        code: None,
+        confirmation: confirmation.map(ToString::to_string),
+    }
+}
+
+/// Arm the alarm, returning the confirmation of a failed pre-arm check if
+/// any.
+async fn arm(
+    api: &Arc<Api>,
+    action: AlarmAction,
+    confirmation: Option<&str>,
+) -> Result<Option<String>, Rejection> {
+    let response = Arc::clone(api)
+        .api_alarm_set(request(action, confirmation))
+        .await?;
+
+    if response.status() != StatusCode::CONFLICT {
+        return Ok(None);
+    }
+
+    let body = warp::hyper::body::to_bytes(response.into_body())
+        .await
+        .unwrap();
+    let check: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(check["ready"], false);
+
+    Ok(Some(check["confirmation"].as_str().unwrap().to_string()))
+}
+
+#[tokio::test]
+async fn blocked_arming_is_forced_with_the_confirmation_of_the_same_action() {
+    let (api, client) = api().await;
+    let (_states, states) = tokio::sync::mpsc::channel(1);
+
+    tokio::spawn(client.run_simulated(states));
+
+    let confirmation = arm(&api, AlarmAction::ArmAway, None)
+        .await
+        .unwrap()
+        .expect("a failed pre-arm check");
+
+    assert!(arm(&api, AlarmAction::ArmHome, Some(&confirmation))
+        .await
+        .unwrap()
+        .is_some());
+    assert_eq!(
+        arm(&api, AlarmAction::ArmAway, Some(&confirmation))
+            .await
+            .unwrap(),
+        None
+    );
+    assert!(arm(&api, AlarmAction::ArmAway, Some(&confirmation))
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test(start_paused = true)]
+async fn confirmations_are_kept_when_home_assistant_fails() {
+    let (api, _client) = api().await;
+    let confirmation = arm(&api, AlarmAction::ArmAway, None)
+        .await
+        .unwrap()
+        .expect("a failed pre-arm check");
+
+    assert!(arm(&api, AlarmAction::ArmAway, Some(&confirmation))
+        .await
+        .is_err());
+    assert!(api
+        .take_confirmation(&confirmation, AlarmAction::ArmAway)
+        .is_some());
+}
@@ -0,0 +1,132 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use log::warn;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use warp::{
+    http::{Method, StatusCode},
+    Filter, Rejection, Reply,
+};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::{announcements::Announcement, melody::Melody};
+
+/// For how long an announcement can be shown at most.
+const MAX_DURATION: Duration = Duration::from_secs(24 * 3600);
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub(super) struct AnnounceRequest {
+    #[serde(default)]
+    title: Option<String>,
+    message: String,
+
+    /// For how long to show the message, in seconds, up to a day.
+    #[serde(default = "AnnounceRequest::default_duration")]
+    duration: f64,
+
+    /// Whether to chime the buzzer first.
+    #[serde(default)]
+    chime: bool,
+
+    /// Whether to speak the message with the local text-to-speech.
+    #[serde(default)]
+    speak: bool,
+}
+
+impl AnnounceRequest {
+    fn default_duration() -> f64 {
+        60.0
+    }
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_announcement_get = warp::path!("announce")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_announcement_get);
+
+    let api_announce = warp::path!("announce")
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "announce", 4 * 1024))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, request| permit.hold(Api::api_announce(api, request)));
+
+    api_announcement_get.or(api_announce)
+}
+
+impl Api {
+    async fn api_announcement_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.announcements.current()))
+    }
+
+    /// Show a message full-screen, and optionally chime and speak it.
+    ///
+    /// The chime and the speech are not waited for.
+    async fn api_announce(
+        self: Arc<Self>,
+        request: AnnounceRequest,
+    ) -> Result<impl Reply, Rejection> {
+        if request.message.trim().is_empty() {
+            return Err(warp::reject::custom(crate::Error::InvalidConfig(
+                "the message is empty".to_string(),
+            )));
+        }
+
+        if !request.duration.is_finite() || request.duration <= 0.0 {
+            return Err(warp::reject::custom(crate::Error::InvalidConfig(format!(
+                "invalid duration: {}",
+                request.duration
+            ))));
+        }
+
+        if request.speak && self.audio.is_none() {
+            return Err(warp::reject::custom(crate::Error::InvalidConfig(
+                "the audio playback is not configured".to_string(),
+            )));
+        }
+
+        if let Err(err) = self.context.screen.force_on(true) {
+            warn!("Failed to turn the screen on: {}", err);
+        }
+
+        let shown_at = Utc::now();
+        let duration = Duration::from_secs_f64(request.duration.min(MAX_DURATION.as_secs_f64()));
+        let announcement = Announcement {
+            title: request.title,
+            message: request.message,
+            shown_at,
+            until: shown_at + chrono::Duration::from_std(duration).unwrap_or_default(),
+        };
+
+        self.announcements.show(announcement.clone());
+
+        if request.chime || request.speak {
+            let api = Arc::clone(&self);
+            let message = announcement.message.clone();
+
+            tokio::spawn(async move {
+                if request.chime {
+                    if let Err(err) = api.melody_player.play(Melody::CHIME).await {
+                        warn!("Failed to play the chime: {}", err);
+                    }
+                }
+
+                if let Some(audio) = api.audio.as_ref().filter(|_| request.speak) {
+                    if let Err(err) = audio.speak(&message).await {
+                        warn!("Failed to speak the announcement: {}", err);
+                    }
+                }
+            });
+        }
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&announcement),
+            StatusCode::ACCEPTED,
+        ))
+    }
+}
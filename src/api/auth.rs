@@ -0,0 +1,322 @@
+use std::sync::Arc;
+
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::auth::{Credentials, Sessions, SESSION_COOKIE, SETTINGS_SESSION_COOKIE};
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStatus {
+    /// Whether mutating routes require a session.
+    pub required: bool,
+    pub authenticated: bool,
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_login = warp::path!("login")
+        .and(ctx.limited_route(Method::POST, Access::Public, "auth", 256))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, credentials| permit.hold(Api::api_login(api, credentials)));
+
+    let api_logout = warp::path!("logout")
+        .and(ctx.route(Method::POST, Access::Public))
+        .and(warp::cookie::optional::<String>(SESSION_COOKIE))
+        .and_then(Api::api_logout);
+
+    let api_session_get = warp::path!("session")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::cookie::optional::<String>(SESSION_COOKIE))
+        .and_then(Api::api_session_get);
+
+    let api_settings_login = warp::path!("settings" / "login")
+        .and(ctx.limited_route(Method::POST, Access::Public, "auth", 256))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, credentials| {
+            permit.hold(Api::api_settings_login(api, credentials))
+        });
+
+    let api_settings_logout = warp::path!("settings" / "logout")
+        .and(ctx.route(Method::POST, Access::Public))
+        .and(warp::cookie::optional::<String>(SETTINGS_SESSION_COOKIE))
+        .and_then(Api::api_settings_logout);
+
+    let api_settings_session_get = warp::path!("settings" / "session")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::cookie::optional::<String>(SETTINGS_SESSION_COOKIE))
+        .and_then(Api::api_settings_session_get);
+
+    api_login
+        .or(api_logout)
+        .or(api_session_get)
+        .or(api_settings_login)
+        .or(api_settings_logout)
+        .or(api_settings_session_get)
+}
+
+impl Api {
+    /// Check a PIN with brute-force protection.
+    ///
+    /// Attempts are counted per scope, like `login` or `alarm`, and every
+    /// attempt is written to the audit log.
+    pub(super) async fn check_pin(
+        &self,
+        scope: &str,
+        valid: impl FnOnce() -> bool,
+    ) -> Result<(), Rejection> {
+        self.check_pin_lockout(scope).await?;
+        self.record_pin_attempt(scope, valid()).await
+    }
+
+    /// Reject the PIN attempts of a scope while it is locked out, for the PINs
+    /// checked elsewhere, like the alarm codes checked by Home-Assistant.
+    pub(super) async fn check_pin_lockout(&self, scope: &str) -> Result<(), Rejection> {
+        if let Some(retry_after) = self.lockout.locked_for(scope).await {
+            warn!(
+                target: "audit",
+                "Rejected `{}` PIN attempt: locked out for {}s.",
+                scope,
+                retry_after.as_secs()
+            );
+
+            return Err(warp::reject::custom(crate::Error::LockedOut {
+                retry_after,
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Count a PIN attempt of a scope, checked once not locked out, and reject
+    /// it if invalid.
+    pub(super) async fn record_pin_attempt(
+        &self,
+        scope: &str,
+        valid: bool,
+    ) -> Result<(), Rejection> {
+        if valid {
+            info!(target: "audit", "Successful `{}` PIN attempt.", scope);
+            self.lockout.succeeded(scope).await;
+
+            return Ok(());
+        }
+
+        let failure = self.lockout.failed(scope).await;
+
+        warn!(
+            target: "audit",
+            "Failed `{}` PIN attempt ({} in a row).",
+            scope, failure.failures
+        );
+
+        if failure.notify {
+            let message = format!(
+                "{} failed `{}` PIN attempts in a row on the panel.",
+                failure.failures, scope
+            );
+
+            if let Err(err) = self
+                .context
+                .home_assistant
+                .persistent_notification_create("Panel PIN attempts", &message)
+                .await
+            {
+                warn!(
+                    "Failed to notify Home Assistant of failed PIN attempts: {}",
+                    err
+                );
+            }
+        }
+
+        match failure.locked_for {
+            Some(retry_after) => {
+                warn!(
+                    target: "audit",
+                    "Locking out `{}` PIN attempts for {}s.",
+                    scope,
+                    retry_after.as_secs()
+                );
+
+                Err(warp::reject::custom(crate::Error::LockedOut {
+                    retry_after,
+                }))
+            }
+            None => Err(warp::reject::custom(crate::Error::Unauthorized)),
+        }
+    }
+
+    /// Authenticate a change of the settings, and write it to the audit log.
+    pub(super) async fn authenticate_settings(
+        &self,
+        token: Option<String>,
+        settings_token: Option<String>,
+        change: &str,
+    ) -> Result<(), Rejection> {
+        let authenticated = match &self.settings_sessions {
+            Some(sessions) => match settings_token {
+                Some(token) => sessions.is_valid(&token).await,
+                None => false,
+            },
+            None => self.authenticate(token).await.is_ok(),
+        };
+
+        if !authenticated {
+            warn!(target: "audit", "Rejected settings change `{}`.", change);
+
+            return Err(warp::reject::custom(crate::Error::Unauthorized));
+        }
+
+        info!(target: "audit", "Settings change `{}`.", change);
+
+        Ok(())
+    }
+
+    pub(super) async fn authenticate(&self, token: Option<String>) -> Result<(), Rejection> {
+        let sessions = match &self.sessions {
+            Some(sessions) => sessions,
+            None => return Ok(()),
+        };
+
+        match token {
+            Some(token) if sessions.is_valid(&token).await => Ok(()),
+            _ => Err(warp::reject::custom(crate::Error::Unauthorized)),
+        }
+    }
+
+    async fn api_login(self: Arc<Self>, credentials: Credentials) -> Result<impl Reply, Rejection> {
+        self.open_session(&self.sessions, "login", SESSION_COOKIE, credentials)
+            .await
+    }
+
+    async fn api_logout(self: Arc<Self>, token: Option<String>) -> Result<impl Reply, Rejection> {
+        Ok(self
+            .close_session(&self.sessions, SESSION_COOKIE, token)
+            .await)
+    }
+
+    async fn api_session_get(
+        self: Arc<Self>,
+        token: Option<String>,
+    ) -> Result<impl Reply, Rejection> {
+        let required = self.sessions.is_some();
+        let authenticated = self.authenticate(token).await.is_ok();
+
+        Ok(warp::reply::json(&SessionStatus {
+            required,
+            authenticated,
+        }))
+    }
+
+    async fn api_settings_login(
+        self: Arc<Self>,
+        credentials: Credentials,
+    ) -> Result<impl Reply, Rejection> {
+        self.open_session(
+            &self.settings_sessions,
+            "settings",
+            SETTINGS_SESSION_COOKIE,
+            credentials,
+        )
+        .await
+    }
+
+    async fn api_settings_logout(
+        self: Arc<Self>,
+        token: Option<String>,
+    ) -> Result<impl Reply, Rejection> {
+        Ok(self
+            .close_session(&self.settings_sessions, SETTINGS_SESSION_COOKIE, token)
+            .await)
+    }
+
+    async fn api_settings_session_get(
+        self: Arc<Self>,
+        token: Option<String>,
+    ) -> Result<impl Reply, Rejection> {
+        let sessions = self
+            .settings_sessions
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+        let authenticated = match token {
+            Some(token) => sessions.is_valid(&token).await,
+            None => false,
+        };
+
+        Ok(warp::reply::json(&SessionStatus {
+            required: true,
+            authenticated,
+        }))
+    }
+
+    /// Open a session with the credentials, as a cookie.
+    async fn open_session(
+        &self,
+        sessions: &Option<Sessions>,
+        scope: &str,
+        cookie: &str,
+        credentials: Credentials,
+    ) -> Result<impl Reply, Rejection> {
+        let sessions = sessions.as_ref().ok_or_else(warp::reject::not_found)?;
+
+        self.check_pin(scope, || sessions.config().check(&credentials))
+            .await?;
+
+        let token = sessions.create().await;
+
+        info!("Frontend `{}` session opened.", scope);
+
+        Ok(warp::reply::with_header(
+            warp::reply::json(&SessionStatus {
+                required: true,
+                authenticated: true,
+            }),
+            "set-cookie",
+            self.session_cookie(cookie, &token, sessions.config().session_lifetime.as_secs()),
+        ))
+    }
+
+    /// Close a session, and clear its cookie.
+    async fn close_session(
+        &self,
+        sessions: &Option<Sessions>,
+        cookie: &str,
+        token: Option<String>,
+    ) -> impl Reply {
+        if let (Some(sessions), Some(token)) = (sessions, token) {
+            sessions.remove(&token).await;
+        }
+
+        warp::reply::with_header(
+            warp::reply::json(&SessionStatus {
+                required: sessions.is_some(),
+                authenticated: false,
+            }),
+            "set-cookie",
+            self.session_cookie(cookie, "", 0),
+        )
+    }
+
+    /// The `set-cookie` value of a session cookie, only sent back over TLS
+    /// when the panel serves it.
+    fn session_cookie(&self, cookie: &str, token: &str, max_age: u64) -> String {
+        let secure = if self.context.config.server.tls.is_some() {
+            "; Secure"
+        } else {
+            ""
+        };
+
+        format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}{}",
+            cookie, token, max_age, secure
+        )
+    }
+}
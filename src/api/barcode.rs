@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::barcode::Barcodes;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub(super) struct BarcodeRequest {
+    /// The barcode, as typed by the scanner.
+    barcode: String,
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("barcode")
+        .and(ctx.limited_route(Method::POST, Access::Session, "barcode", 256))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, scan| permit.hold(Api::api_barcode_scan(api, scan)))
+}
+
+impl Api {
+    /// Add the product of a scanned barcode to the shopping list.
+    async fn api_barcode_scan(
+        self: Arc<Self>,
+        request: BarcodeRequest,
+    ) -> Result<impl Reply, Rejection> {
+        let barcodes = self.barcodes.as_ref().ok_or_else(warp::reject::not_found)?;
+        let barcode = request.barcode.trim();
+
+        if !Barcodes::is_valid(barcode) {
+            return Err(warp::reject::custom(crate::Error::InvalidConfig(format!(
+                "invalid barcode `{}`",
+                barcode
+            ))));
+        }
+
+        let product = barcodes
+            .scan(barcode, &self.context.home_assistant)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&product))
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use log::info;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::chores::{ChoreUser, Chores, NewChore};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_chores = warp::path!("chores");
+
+    let api_chore = warp::path!("chores" / i64);
+
+    let api_chores_get = api_chores
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_chores_get);
+
+    let api_chores_create = api_chores
+        .and(ctx.limited_route(Method::POST, Access::Session, "chores", 1024))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, chore| permit.hold(Api::api_chores_create(api, chore)));
+
+    let api_chores_delete = api_chore
+        .and(ctx.route(Method::DELETE, Access::Session))
+        .and_then(|id, api: Arc<Api>| async move { api.api_chores_delete(id).await });
+
+    let api_chores_claim = warp::path!("chores" / i64 / "claim")
+        .and(ctx.limited_route(Method::POST, Access::Session, "chores", 256))
+        .and(warp::body::json())
+        .and_then(|id, permit: Permit, api: Arc<Api>, user| {
+            permit.hold(Api::api_chores_claim(api, id, user))
+        });
+
+    let api_chores_complete = warp::path!("chores" / i64 / "complete")
+        .and(ctx.limited_route(Method::POST, Access::Session, "chores", 256))
+        .and(warp::body::json())
+        .and_then(|id, permit: Permit, api: Arc<Api>, user| {
+            permit.hold(Api::api_chores_complete(api, id, user))
+        });
+
+    let api_chores_stats_get = warp::path!("chores" / "stats")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_chores_stats_get);
+
+    api_chores_get
+        .or(api_chores_create)
+        .or(api_chores_delete)
+        .or(api_chores_claim)
+        .or(api_chores_complete)
+        .or(api_chores_stats_get)
+}
+
+impl Api {
+    fn chores(&self) -> Result<&Chores, Rejection> {
+        self.context
+            .storage
+            .chores
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)
+    }
+
+    async fn api_chores_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let chores = self.chores()?.list().await.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&chores))
+    }
+
+    async fn api_chores_create(self: Arc<Self>, chore: NewChore) -> Result<impl Reply, Rejection> {
+        info!("Creating chore `{}`.", chore.name);
+
+        let id = self
+            .chores()?
+            .create(chore)
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&id))
+    }
+
+    async fn api_chores_delete(self: Arc<Self>, id: i64) -> Result<impl Reply, Rejection> {
+        if !self
+            .chores()?
+            .delete(id)
+            .await
+            .map_err(warp::reject::custom)?
+        {
+            return Err(warp::reject::not_found());
+        }
+
+        Ok(warp::reply::json(&id))
+    }
+
+    async fn api_chores_claim(
+        self: Arc<Self>,
+        id: i64,
+        user: ChoreUser,
+    ) -> Result<impl Reply, Rejection> {
+        if !self
+            .chores()?
+            .claim(id, user.user)
+            .await
+            .map_err(warp::reject::custom)?
+        {
+            return Err(warp::reject::not_found());
+        }
+
+        Ok(warp::reply::json(&id))
+    }
+
+    async fn api_chores_complete(
+        self: Arc<Self>,
+        id: i64,
+        user: ChoreUser,
+    ) -> Result<impl Reply, Rejection> {
+        if !self
+            .chores()?
+            .complete(id, user.user)
+            .await
+            .map_err(warp::reject::custom)?
+        {
+            return Err(warp::reject::not_found());
+        }
+
+        Ok(warp::reply::json(&id))
+    }
+
+    async fn api_chores_stats_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let stats = self
+            .chores()?
+            .weekly_stats()
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&stats))
+    }
+}
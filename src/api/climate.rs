@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use log::error;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+use crate::{climate::HeatingSummary, home_assistant};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_climate_boost = warp::path!("climate" / String / "boost");
+
+    let api_climate_boost_get = api_climate_boost
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|name, api: Arc<Api>| async move { api.api_climate_boost_get(name).await });
+
+    let api_climate_boost_set = api_climate_boost
+        .and(ctx.control_route(Method::POST, Access::Session))
+        .and_then(|name, api: Arc<Api>| async move { api.api_climate_boost_set(name).await });
+
+    let api_heating_get = warp::path!("heating")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_heating_get);
+
+    api_climate_boost_get
+        .or(api_climate_boost_set)
+        .or(api_heating_get)
+}
+
+impl Api {
+    async fn api_climate_boost_get(self: Arc<Self>, name: String) -> Result<impl Reply, Rejection> {
+        let status = self
+            .climate_booster
+            .status(&format!("climate.{}", name))
+            .await;
+
+        Ok(warp::reply::json(&status))
+    }
+
+    async fn api_climate_boost_set(self: Arc<Self>, name: String) -> Result<impl Reply, Rejection> {
+        let status = self
+            .climate_booster
+            .boost(&format!("climate.{}", name))
+            .await
+            .map_err(|err| {
+                error!("failed to boost `{}`: {}", name, err);
+                warp::reject::custom(err)
+            })?;
+
+        Ok(warp::reply::json(&status))
+    }
+
+    async fn api_heating_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let entities = match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => entities,
+            home_assistant::Status::Disconnected => Default::default(),
+        };
+
+        Ok(warp::reply::json(&HeatingSummary::new(&entities)))
+    }
+}
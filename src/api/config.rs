@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::dashboard::{DashboardConfig, LightConfig};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_config_dashboard = warp::path!("config" / "dashboard");
+
+    let api_config_dashboard_get = api_config_dashboard
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_config_dashboard_get);
+
+    let api_config_dashboard_set = api_config_dashboard
+        .and(ctx.limited_route(Method::PUT, Access::Settings, "config", 64 * 1024))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, dashboard| {
+            permit.hold(Api::api_config_dashboard_set(api, dashboard))
+        });
+
+    let api_config_lights = warp::path!("config" / "lights");
+
+    let api_config_lights_get = api_config_lights
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_config_lights_get);
+
+    let api_config_lights_set = api_config_lights
+        .and(ctx.limited_route(Method::PUT, Access::Settings, "config", 16 * 1024))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, lights| {
+            permit.hold(Api::api_config_lights_set(api, lights))
+        });
+
+    api_config_dashboard_get
+        .or(api_config_dashboard_set)
+        .or(api_config_lights_get)
+        .or(api_config_lights_set)
+}
+
+impl Api {
+    async fn api_config_dashboard_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.context.editable.dashboard().await))
+    }
+
+    async fn api_config_dashboard_set(
+        self: Arc<Self>,
+        dashboard: DashboardConfig,
+    ) -> Result<impl Reply, Rejection> {
+        let result = self.context.editable.set_dashboard(dashboard.clone()).await;
+
+        self.add_to_digest(match &result {
+            Ok(()) => "The dashboard configuration was updated.".to_string(),
+            Err(err) => format!("Failed to update the dashboard configuration: {}", err),
+        })
+        .await;
+
+        result.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&dashboard))
+    }
+
+    async fn api_config_lights_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.context.editable.lights().await))
+    }
+
+    async fn api_config_lights_set(
+        self: Arc<Self>,
+        lights: Vec<LightConfig>,
+    ) -> Result<impl Reply, Rejection> {
+        let result = self.context.editable.set_lights(lights.clone()).await;
+
+        self.add_to_digest(match &result {
+            Ok(()) => "The lights configuration was updated.".to_string(),
+            Err(err) => format!("Failed to update the lights configuration: {}", err),
+        })
+        .await;
+
+        result.map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&lights))
+    }
+}
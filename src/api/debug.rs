@@ -0,0 +1,177 @@
+use std::{convert::Infallible, sync::Arc};
+
+use chrono::Utc;
+use futures_util::StreamExt;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use warp::{http::Method, sse, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    versions::VersionInfo,
+    Api,
+};
+use crate::{
+    bug_report::BugReport,
+    chaos::{self, Faults},
+    home_assistant::Status,
+    memory::MemoryStatus,
+};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_debug_ha_traffic = warp::path!("debug" / "ha-traffic")
+        .and(ctx.route(Method::GET, Access::Settings))
+        .and_then(Api::api_debug_ha_traffic);
+
+    let api_debug_bundle = warp::path!("debug" / "bundle")
+        .and(ctx.route(Method::GET, Access::Settings))
+        .and_then(Api::api_debug_bundle);
+
+    let api_debug_chaos_get = warp::path!("debug" / "chaos")
+        .and(ctx.route(Method::GET, Access::Settings))
+        .and_then(Api::api_debug_chaos_get);
+
+    let api_debug_chaos_set = warp::path!("debug" / "chaos")
+        .and(ctx.limited_route(Method::PUT, Access::Settings, "debug", 1024))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, faults| permit.hold(Api::api_debug_chaos_set(api, faults)));
+
+    let api_debug_chaos_ha_drop = warp::path!("debug" / "chaos" / "ha-drop")
+        .and(ctx.route(Method::POST, Access::Settings))
+        .and_then(Api::api_debug_chaos_ha_drop);
+
+    api_debug_ha_traffic
+        .or(api_debug_bundle)
+        .or(api_debug_chaos_get)
+        .or(api_debug_chaos_set)
+        .or(api_debug_chaos_ha_drop)
+}
+
+/// Reject the failure injection, unless built with the `debug` feature.
+fn chaos_enabled() -> Result<(), Rejection> {
+    if cfg!(feature = "debug") {
+        Ok(())
+    } else {
+        Err(warp::reject::not_found())
+    }
+}
+
+impl Api {
+    /// Stream the messages exchanged with Home-Assistant as server-sent
+    /// `message` events, the recent ones first.
+    ///
+    /// Only available in debug mode. A `lagged` event tells how many
+    /// messages were skipped, when the client reads too slowly.
+    async fn api_debug_ha_traffic(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let traffic = self.context.home_assistant.traffic();
+
+        if !traffic.enabled() {
+            return Err(warp::reject::not_found());
+        }
+
+        let (recent, rx) = traffic.subscribe();
+        let recent = futures_util::stream::iter(recent).map(|message| {
+            Ok::<_, Infallible>(
+                sse::Event::default()
+                    .event("message")
+                    .json_data(message)
+                    .unwrap_or_default(),
+            )
+        });
+        let live = futures_util::stream::unfold(rx, |mut rx| async move {
+            let event = match rx.recv().await {
+                Ok(message) => sse::Event::default()
+                    .event("message")
+                    .json_data(message)
+                    .unwrap_or_default(),
+                Err(RecvError::Lagged(skipped)) => sse::Event::default()
+                    .event("lagged")
+                    .data(skipped.to_string()),
+                Err(RecvError::Closed) => return None,
+            };
+
+            Some((Ok::<_, Infallible>(event), rx))
+        });
+
+        Ok(sse::reply(sse::keep_alive().stream(recent.chain(live))))
+    }
+
+    /// Get a zip of everything needed to diagnose the panel: the
+    /// configuration without its secrets, the recent logs and Home-Assistant
+    /// traffic, the tasks, the system information, and the current states as
+    /// an events file to replay with `--simulate`.
+    ///
+    /// The Home-Assistant traffic is only recorded in debug mode.
+    async fn api_debug_bundle(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let entities = match self.context.home_assistant.status().await {
+            Status::Connected { entities } => entities,
+            Status::Disconnected => Default::default(),
+        };
+        let system = json!({
+            "version": VersionInfo::new(),
+            "homeAssistant": self.context.home_assistant.info().await,
+            "homeAssistantCalls": self.context.home_assistant.call_stats(),
+            "memory": MemoryStatus::read(&self.context.home_assistant).await,
+            "gpio": self.context.gpio.health(),
+            "selfCheck": *self.self_check.read().await,
+        });
+        let bundle = (|| {
+            let mut bundle = BugReport::new();
+
+            bundle.add_json("config.json", &*self.context.redacted_config)?;
+            bundle.add_text("logs.txt", &crate::log::recent().join("\n"))?;
+            bundle.add_json(
+                "ha-traffic.json",
+                &self.context.home_assistant.traffic().subscribe().0,
+            )?;
+            bundle.add_json("tasks.json", &self.context.tasks.status())?;
+            bundle.add_json("system.json", &system)?;
+            bundle.add_text(
+                "states.jsonl",
+                &crate::bug_report::replayable_states(&entities)?,
+            )?;
+            bundle.finish()
+        })()
+        .map_err(|err| warp::reject::custom(crate::Error::from(err)))?;
+
+        Ok(warp::reply::with_header(
+            warp::reply::with_header(
+                warp::reply::Response::new(bundle.into()),
+                "content-type",
+                "application/zip",
+            ),
+            "content-disposition",
+            format!(
+                "attachment; filename=\"home-control-{}.zip\"",
+                Utc::now().format("%Y%m%d-%H%M%S")
+            ),
+        ))
+    }
+
+    async fn api_debug_chaos_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        chaos_enabled()?;
+
+        Ok(warp::reply::json(&chaos::faults()))
+    }
+
+    /// Replace the injected failures, like a delay of the service calls.
+    async fn api_debug_chaos_set(self: Arc<Self>, faults: Faults) -> Result<impl Reply, Rejection> {
+        chaos_enabled()?;
+        chaos::set_faults(faults);
+
+        Ok(warp::reply::json(&chaos::faults()))
+    }
+
+    /// Drop the web-socket to Home-Assistant, to exercise the reconnection.
+    ///
+    /// Has no effect in simulation, or while disconnected.
+    async fn api_debug_chaos_ha_drop(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        chaos_enabled()?;
+        chaos::drop_home_assistant();
+
+        Ok(warp::reply::json(&true))
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use log::error;
+use warp::{
+    http::{Method, StatusCode},
+    Filter, Rejection, Reply,
+};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api, ApiBool,
+};
+use crate::esphome::EsphomeEntityKind;
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_esphome_entities_get = warp::path!("esphome" / "entities")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_esphome_entities_get);
+
+    let api_esphome_entity_get = warp::path!("esphome" / "entities" / String)
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|id, api: Arc<Api>| async move { api.api_esphome_entity_get(id).await });
+
+    let api_esphome_entity_set = warp::path!("esphome" / "entities" / String)
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "esphome", 1024))
+        .and(warp::body::json())
+        .and_then(|id: String, permit: Permit, api: Arc<Api>, status| {
+            permit.hold(Api::api_esphome_entity_set(api, id, status))
+        });
+
+    api_esphome_entities_get
+        .or(api_esphome_entity_get)
+        .or(api_esphome_entity_set)
+}
+
+impl Api {
+    async fn api_esphome_entities_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.esphome.entities().await))
+    }
+
+    async fn api_esphome_entity_get(self: Arc<Self>, id: String) -> Result<impl Reply, Rejection> {
+        let entity = self
+            .esphome
+            .entity(&id)
+            .await
+            .ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&entity))
+    }
+
+    /// Turn a switch on or off.
+    ///
+    /// The new state is reported by the node once applied.
+    async fn api_esphome_entity_set(
+        self: Arc<Self>,
+        id: String,
+        status: ApiBool,
+    ) -> Result<impl Reply, Rejection> {
+        let on: bool = status.into();
+        let entity = self
+            .esphome
+            .entity(&id)
+            .await
+            .ok_or_else(warp::reject::not_found)?;
+
+        if entity.kind != EsphomeEntityKind::Switch {
+            return Err(warp::reject::custom(crate::Error::InvalidConfig(format!(
+                "`{}` is not a switch",
+                id
+            ))));
+        }
+
+        self.esphome.set(&id, on).await.map_err(|err| {
+            error!("failed to command the ESPHome entity `{}`: {:#}", id, err);
+            warp::reject::custom(crate::Error::from(err))
+        })?;
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&on),
+            StatusCode::ACCEPTED,
+        ))
+    }
+}
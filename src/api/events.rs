@@ -0,0 +1,273 @@
+use std::{convert::Infallible, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use warp::{http::Method, sse, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    status::StatusUpdate,
+    Api, StatusVersion,
+};
+use crate::{
+    hazards::Hazards,
+    home_assistant::{Event, Update},
+    voice::{Voice, VoiceEvent},
+    wakeup::Wakeup,
+};
+
+/// The new state of a configured entity, as pushed to the frontend.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityUpdate {
+    pub entity_id: String,
+
+    /// The new state, or none if the entity was removed.
+    pub state: Option<String>,
+    pub last_changed: Option<DateTime<Utc>>,
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("events")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|api: Arc<Api>| async move {
+            let version = api.context.config.status.version;
+
+            api.api_events_stream(version).await
+        })
+}
+
+/// The routes of the version 2, in which the status is grouped by block.
+pub(super) fn routes_v2(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("events")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|api: Arc<Api>| api.api_events_stream(StatusVersion::V2))
+}
+
+impl Api {
+    /// Stream the status and the states of the configured entities as
+    /// server-sent events, whenever they change.
+    ///
+    /// The status is sent first, then after every change, as `status` events
+    /// shaped like the long-polled ones. The changes of the configured
+    /// entities are also sent as `entity` events, the summary of the doors
+    /// and windows as `openings` events, the detected leaks and smoke as
+    /// `hazards` events, the announcements as `announcement` events, the
+    /// progress of the Assist sessions as `voice` events, and the wake-up in
+    /// progress as `wakeup` events.
+    async fn api_events_stream(
+        self: Arc<Self>,
+        version: StatusVersion,
+    ) -> Result<impl Reply, Rejection> {
+        let (tx, rx) = mpsc::channel(16);
+
+        tokio::spawn(async move { self.push_events(version, tx).await });
+
+        let events = futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv()
+                .await
+                .map(|event| (Ok::<_, Infallible>(event), rx))
+        });
+
+        Ok(sse::reply(sse::keep_alive().stream(events)))
+    }
+
+    /// Push the events until the client goes away.
+    async fn push_events(&self, version: StatusVersion, tx: mpsc::Sender<sse::Event>) {
+        let mut states = self.context.home_assistant.watch_states();
+        let mut proximity = self.context.presence.proximity.subscribe();
+        let mut notifications = self.notifications.watch();
+        let mut updates = self.context.home_assistant.subscribe_updates();
+        let mut hazards = self.hazards.as_ref().map(Hazards::watch);
+        let mut voice = self.voice.as_ref().map(Voice::subscribe);
+        let mut announcements = self.announcements.watch();
+        let mut wakeup = self.context.scheduler.wakeup.as_ref().map(Wakeup::watch);
+
+        // The status is sent right away, and so are the openings, the
+        // hazards, the announcement and the wake-up.
+        states.mark_changed();
+
+        let initial = self
+            .openings_event()
+            .await
+            .into_iter()
+            .chain(self.hazards_event())
+            .chain(self.announcement_event())
+            .chain(self.wakeup_event());
+
+        for event in initial {
+            if tx.send(event).await.is_err() {
+                return;
+            }
+        }
+
+        loop {
+            let events = tokio::select! {
+                // The senders only go away with the panel, which then closes
+                // the stream.
+                changed = states.changed() => match changed {
+                    Ok(()) => {
+                        states.mark_unchanged();
+                        self.status_event(version).await.into_iter().collect()
+                    }
+                    Err(_) => return,
+                },
+                changed = proximity.changed() => match changed {
+                    Ok(()) => self.status_event(version).await.into_iter().collect(),
+                    Err(_) => return,
+                },
+                changed = notifications.changed() => match changed {
+                    Ok(()) => self.status_event(version).await.into_iter().collect(),
+                    Err(_) => return,
+                },
+                Some(()) = async {
+                    match &mut hazards {
+                        Some(hazards) => hazards.changed().await.ok(),
+                        None => None,
+                    }
+                } => self.hazards_event().into_iter().collect(),
+                changed = announcements.changed() => match changed {
+                    Ok(()) => self.announcement_event().into_iter().collect(),
+                    Err(_) => return,
+                },
+                Some(()) = async {
+                    match &mut wakeup {
+                        Some(wakeup) => wakeup.changed().await.ok(),
+                        None => None,
+                    }
+                } => self.wakeup_event().into_iter().collect(),
+                Some(event) = async {
+                    match &mut voice {
+                        // The sessions are short-lived: the missed steps are
+                        // not worth catching up with.
+                        Some(voice) => voice.recv().await.ok(),
+                        None => None,
+                    }
+                } => voice_event(event).into_iter().collect(),
+                update = updates.recv() => match update {
+                    Ok(Update::Event(event)) => {
+                        let mut events = self.entity_events(&event).await;
+
+                        if self.changes_openings(&event) {
+                            events.extend(self.openings_event().await);
+                        }
+
+                        events
+                    }
+                    Ok(Update::Connected | Update::Disconnected) => continue,
+
+                    // The status catches up with the missed changes anyway.
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                },
+                _ = tx.closed() => return,
+            };
+
+            for event in events {
+                if tx.send(event).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn status_event(&self, version: StatusVersion) -> Option<sse::Event> {
+        let generation = self.status_generation();
+        let status = self.status().await.ok()?;
+        let event = sse::Event::default().event("status");
+
+        match version {
+            StatusVersion::V1 => event.json_data(StatusUpdate { generation, status }),
+            StatusVersion::V2 => event.json_data(StatusUpdate {
+                generation,
+                status: status.grouped(),
+            }),
+        }
+        .ok()
+    }
+
+    async fn openings_event(&self) -> Option<sse::Event> {
+        let openings = self.openings().await?;
+
+        sse::Event::default()
+            .event("openings")
+            .json_data(openings)
+            .ok()
+    }
+
+    fn hazards_event(&self) -> Option<sse::Event> {
+        let hazards = self.hazards.as_ref()?;
+
+        sse::Event::default()
+            .event("hazards")
+            .json_data(hazards.alerts())
+            .ok()
+    }
+
+    /// The current announcement, or `null` once taken down.
+    fn announcement_event(&self) -> Option<sse::Event> {
+        sse::Event::default()
+            .event("announcement")
+            .json_data(self.announcements.current())
+            .ok()
+    }
+
+    /// The wake-up in progress, or `null` once over.
+    fn wakeup_event(&self) -> Option<sse::Event> {
+        let wakeup = self.context.scheduler.wakeup.as_ref()?;
+
+        sse::Event::default()
+            .event("wakeup")
+            .json_data(&*wakeup.watch().borrow())
+            .ok()
+    }
+
+    fn changes_openings(&self, event: &Event) -> bool {
+        match &self.context.config.openings {
+            Some(openings) => event
+                .entity_ids()
+                .into_iter()
+                .any(|entity_id| openings.sensors.iter().any(|sensor| sensor == entity_id)),
+            None => false,
+        }
+    }
+
+    /// Get the events of the configured entities changed by an event.
+    async fn entity_events(&self, event: &Event) -> Vec<sse::Event> {
+        let mut events = Vec::new();
+        let entity_ids: Vec<_> = {
+            let configured = self.context.editable.entity_ids().await;
+
+            event
+                .entity_ids()
+                .into_iter()
+                .filter(|entity_id| configured.contains(*entity_id))
+                .collect()
+        };
+
+        for entity_id in entity_ids {
+            let state = self.context.home_assistant.entity(entity_id).await;
+            let update = EntityUpdate {
+                entity_id: entity_id.to_string(),
+                last_changed: state.as_ref().map(|state| state.last_changed),
+                state: state.map(|state| state.state),
+            };
+
+            if let Ok(event) = sse::Event::default().event("entity").json_data(update) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+}
+
+fn voice_event(event: VoiceEvent) -> Option<sse::Event> {
+    sse::Event::default().event("voice").json_data(event).ok()
+}
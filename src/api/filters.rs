@@ -0,0 +1,317 @@
+use std::{collections::HashMap, convert::Infallible, sync::Arc};
+
+use log::error;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warp::{
+    filters::{body::BodyDeserializeError, path::FullPath, BoxedFilter},
+    http::{HeaderValue, Method, StatusCode},
+    reject::{
+        InvalidHeader, InvalidQuery, LengthRequired, MethodNotAllowed, MissingHeader,
+        PayloadTooLarge, UnsupportedMediaType,
+    },
+    Filter, Rejection, Reply,
+};
+
+use super::{
+    limits::{Permit, RouteLimits},
+    Api,
+};
+use crate::{
+    auth::{SESSION_COOKIE, SETTINGS_SESSION_COOKIE},
+    control_action, request_id,
+};
+
+/// Who may call a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Access {
+    /// Anyone, like the routes reading the state of the panel.
+    Public,
+
+    /// The holders of a session, if logging in is configured: the routes
+    /// controlling the home.
+    Session,
+
+    /// The holders of a settings session, or of a session if logging in to
+    /// change the settings is not configured: the routes changing the
+    /// configuration, the rules or the system, which are audited.
+    Settings,
+}
+
+/// The filters shared by the route modules.
+#[derive(Clone)]
+pub(super) struct Context {
+    pub(super) api: Arc<Api>,
+    limits: Arc<HashMap<String, RouteLimits>>,
+}
+
+impl Context {
+    pub(super) fn new(api: &Arc<Api>) -> Self {
+        Self {
+            api: Arc::clone(api),
+            limits: Arc::new(RouteLimits::by_group(&api.context.config.route_limits)),
+        }
+    }
+
+    /// Extract the API.
+    pub(super) fn api(&self) -> impl Filter<Extract = (Arc<Api>,), Error = Infallible> + Clone {
+        let api = Arc::clone(&self.api);
+
+        warp::any().map(move || Arc::clone(&api))
+    }
+
+    /// The filters of every route after its path: its method, the access it
+    /// requires, then the API for its handler, which takes the values
+    /// extracted after it, like the body.
+    ///
+    /// What applies to all the routes goes here rather than to each of them.
+    pub(super) fn route(&self, method: Method, access: Access) -> BoxedFilter<(Arc<Api>,)> {
+        self.access(method, access).and(self.api()).boxed()
+    }
+
+    /// Like [`Context::route`], for the routes controlling the home, like
+    /// turning a light on: flags their requests as control actions, which
+    /// the buzzer chirps for once answered.
+    pub(super) fn control_route(&self, method: Method, access: Access) -> BoxedFilter<(Arc<Api>,)> {
+        self.access(method, access)
+            .and(control())
+            .and(self.api())
+            .boxed()
+    }
+
+    /// Like [`Context::route`], for the routes with a body: limits its size
+    /// and the number of those handled at once, as [`Context::limits`].
+    pub(super) fn limited_route(
+        &self,
+        method: Method,
+        access: Access,
+        group: &str,
+        default_body_size: u64,
+    ) -> BoxedFilter<(Permit, Arc<Api>)> {
+        self.access(method, access)
+            .and(self.limits(group, default_body_size))
+            .and(self.api())
+            .boxed()
+    }
+
+    /// Like [`Context::limited_route`], for the routes controlling the home,
+    /// as [`Context::control_route`].
+    pub(super) fn limited_control_route(
+        &self,
+        method: Method,
+        access: Access,
+        group: &str,
+        default_body_size: u64,
+    ) -> BoxedFilter<(Permit, Arc<Api>)> {
+        self.access(method, access)
+            .and(control())
+            .and(self.limits(group, default_body_size))
+            .and(self.api())
+            .boxed()
+    }
+
+    fn access(&self, method: Method, access: Access) -> BoxedFilter<()> {
+        let method = match method {
+            Method::GET => warp::get().boxed(),
+            Method::POST => warp::post().boxed(),
+            Method::PUT => warp::put().boxed(),
+            Method::PATCH => warp::patch().boxed(),
+            Method::DELETE => warp::delete().boxed(),
+            method => panic!("unsupported method `{}`", method),
+        };
+
+        match access {
+            Access::Public => method,
+            Access::Session => method.and(self.authenticated()).boxed(),
+            Access::Settings => method.and(self.settings()).boxed(),
+        }
+    }
+
+    /// Require a session, if logging in is configured.
+    fn authenticated(&self) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+        warp::cookie::optional::<String>(SESSION_COOKIE)
+            .and(self.api())
+            .and_then(|token, api: Arc<Api>| async move { api.authenticate(token).await })
+            .untuple_one()
+    }
+
+    /// Require a settings session, if logging in to change the settings is
+    /// configured, or a session otherwise.
+    fn settings(&self) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+        warp::cookie::optional::<String>(SESSION_COOKIE)
+            .and(warp::cookie::optional::<String>(SETTINGS_SESSION_COOKIE))
+            .and(warp::method())
+            .and(warp::path::full())
+            .and(self.api())
+            .and_then(
+                |token, settings_token, method: Method, path: FullPath, api: Arc<Api>| async move {
+                    api.authenticate_settings(
+                        token,
+                        settings_token,
+                        &format!("{} {}", method, path.as_str()),
+                    )
+                    .await
+                },
+            )
+            .untuple_one()
+    }
+
+    /// Limit the size of the request bodies, to the configured size of the
+    /// group of routes or to the default one, and the number of those handled
+    /// at once. Used by the routes with a body, which hold the permit until
+    /// handled.
+    fn limits(
+        &self,
+        group: &str,
+        default_body_size: u64,
+    ) -> impl Filter<Extract = (Permit,), Error = Rejection> + Clone {
+        let limits = self.limits.get(group).cloned().unwrap_or_default();
+
+        warp::body::content_length_limit(limits.max_body_size.unwrap_or(default_body_size))
+            .and_then(move || {
+                let permit =
+                    Permit::acquire(limits.requests.as_ref()).map_err(warp::reject::custom);
+
+                async move { permit }
+            })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorResponse {
+    pub error: String,
+
+    /// The id of the failed request, to find it in the logs.
+    pub request_id: Option<String>,
+}
+
+/// Flag the request as a control action.
+fn control() -> impl Filter<Extract = (), Error = Infallible> + Clone {
+    warp::any().map(control_action::flag).untuple_one()
+}
+
+/// Match the segments of a path prefix, like `/api/v1`.
+///
+/// An empty prefix, or `/`, matches any path.
+pub fn path_prefix(prefix: &str) -> BoxedFilter<()> {
+    prefix
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .fold(warp::any().boxed(), |filter, segment| {
+            filter.and(warp::path(segment.to_string())).boxed()
+        })
+}
+
+/// Match the paths under any of the prefixes, without consuming them.
+pub(super) fn under_prefixes(prefixes: &[&str]) -> BoxedFilter<()> {
+    let prefixes: Vec<String> = prefixes.iter().map(ToString::to_string).collect();
+
+    warp::path::full()
+        .and_then(move |path: FullPath| {
+            let under = prefixes
+                .iter()
+                .any(|prefix| is_under_prefix(path.as_str(), prefix));
+
+            async move {
+                if under {
+                    Ok(())
+                } else {
+                    Err(warp::reject::not_found())
+                }
+            }
+        })
+        .untuple_one()
+        .boxed()
+}
+
+fn is_under_prefix(path: &str, prefix: &str) -> bool {
+    let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+
+    prefix
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .all(|segment| segments.next() == Some(segment))
+}
+
+/// Answer the requests no route of the API accepts with a JSON error, like
+/// the other errors of the API, so that they never reach the frontend.
+pub(super) async fn handle_api_rejection(
+    err: Rejection,
+) -> Result<warp::reply::Response, Rejection> {
+    if err.find::<crate::Error>().is_some() {
+        return handle_rejection(err).await;
+    }
+
+    // From the most specific rejection, as the routes a request does not
+    // match at all also reject it.
+    let (status, error) = if let Some(err) = err.find::<BodyDeserializeError>() {
+        (StatusCode::BAD_REQUEST, err.to_string())
+    } else if let Some(err) = err.find::<InvalidQuery>() {
+        (StatusCode::BAD_REQUEST, err.to_string())
+    } else if let Some(err) = err.find::<MissingHeader>() {
+        (StatusCode::BAD_REQUEST, err.to_string())
+    } else if let Some(err) = err.find::<InvalidHeader>() {
+        (StatusCode::BAD_REQUEST, err.to_string())
+    } else if let Some(err) = err.find::<LengthRequired>() {
+        (StatusCode::LENGTH_REQUIRED, err.to_string())
+    } else if let Some(err) = err.find::<PayloadTooLarge>() {
+        (StatusCode::PAYLOAD_TOO_LARGE, err.to_string())
+    } else if let Some(err) = err.find::<UnsupportedMediaType>() {
+        (StatusCode::UNSUPPORTED_MEDIA_TYPE, err.to_string())
+    } else if let Some(err) = err.find::<MethodNotAllowed>() {
+        (StatusCode::METHOD_NOT_ALLOWED, err.to_string())
+    } else {
+        (StatusCode::NOT_FOUND, "not found".to_string())
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ErrorResponse {
+            error,
+            request_id: request_id::current(),
+        }),
+        status,
+    )
+    .into_response())
+}
+
+pub(super) async fn handle_rejection(err: Rejection) -> Result<warp::reply::Response, Rejection> {
+    let error = match err.find::<crate::Error>() {
+        Some(error) => error,
+        None => return Err(err),
+    };
+
+    let request_id = request_id::current();
+    let status = match error {
+        crate::Error::InvalidConfig(_) => StatusCode::BAD_REQUEST,
+        crate::Error::Unauthorized => StatusCode::UNAUTHORIZED,
+        crate::Error::LockedOut { .. } => StatusCode::TOO_MANY_REQUESTS,
+        crate::Error::Busy => StatusCode::SERVICE_UNAVAILABLE,
+        _ => {
+            error!("[{}] {}", request_id.as_deref().unwrap_or("-"), error);
+
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+
+    let mut response = warp::reply::with_status(
+        warp::reply::json(&ErrorResponse {
+            error: error.to_string(),
+            request_id,
+        }),
+        status,
+    )
+    .into_response();
+
+    if let crate::Error::LockedOut { retry_after } = error {
+        response.headers_mut().insert(
+            "retry-after",
+            HeaderValue::from(retry_after.as_secs().max(1)),
+        );
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,143 @@
+//! Tests of the API filters.
+
+use serde_json::json;
+
+use super::*;
+use crate::api::testing;
+
+/// The routes under the prefixes, before a frontend answering everything
+/// else, like `Api::routes` and the static files.
+fn routes() -> impl Filter<Extract = (warp::reply::Response,), Error = Infallible> + Clone {
+    let login = warp::path!("api" / "v1" / "login")
+        .and(warp::post())
+        .map(|| warp::reply::json(&true).into_response());
+    let frontend = warp::any().map(|| warp::reply::html("<html></html>").into_response());
+
+    under_prefixes(&["/api/v1"])
+        .and(login.recover(handle_api_rejection))
+        .unify()
+        .or(frontend)
+        .unify()
+}
+
+async fn error(method: &str, path: &str) -> (StatusCode, ErrorResponse) {
+    let response = warp::test::request()
+        .method(method)
+        .path(path)
+        .reply(&routes())
+        .await;
+
+    (
+        response.status(),
+        serde_json::from_slice(response.body()).expect("a JSON error"),
+    )
+}
+
+#[test]
+fn prefixes_match_whole_segments() {
+    assert!(is_under_prefix("/api/v1/login", "/api/v1"));
+    assert!(is_under_prefix("/api/v1", "/api/v1/"));
+    assert!(!is_under_prefix("/api/v10/login", "/api/v1"));
+    assert!(!is_under_prefix("/settings", "/api/v1"));
+}
+
+#[tokio::test]
+async fn unknown_api_paths_are_not_found() {
+    let (status, response) = error("GET", "/api/v1/nope").await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(response.error, "not found");
+}
+
+#[tokio::test]
+async fn wrong_methods_are_not_allowed() {
+    let (status, _) = error("GET", "/api/v1/login").await;
+
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn the_frontend_answers_outside_of_the_prefixes() {
+    let response = warp::test::request()
+        .path("/settings")
+        .reply(&routes())
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body(), "<html></html>");
+}
+
+async fn is_control_action(path: &str) -> bool {
+    let light = warp::path!("light")
+        .and(warp::post())
+        .and(control())
+        .map(warp::reply);
+    let login = warp::path!("login").and(warp::post()).map(warp::reply);
+
+    control_action::scope(async {
+        warp::test::request()
+            .method("POST")
+            .path(path)
+            .reply(&light.or(login))
+            .await;
+
+        control_action::is_flagged()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn only_the_control_routes_flag_their_requests() {
+    assert!(is_control_action("/light").await);
+    assert!(!is_control_action("/login").await);
+}
+
+/// An API with the login sessions, and the settings ones if `settings_pin`.
+async fn api(settings_pin: Option<&str>) -> Arc<Api> {
+    testing::api(json!({
+        "auth": {"pin": "1234"},
+        "settings_auth": settings_pin.map(|pin| json!({"pin": pin})),
+    }))
+    .await
+    .0
+}
+
+/// Whether a settings route accepts a request with the cookies.
+async fn changes_settings(api: &Arc<Api>, cookies: &[(&str, &str)]) -> bool {
+    let route = warp::path!("settings").and(Context::new(api).route(Method::PUT, Access::Settings));
+    let cookie = cookies
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    warp::test::request()
+        .method("PUT")
+        .path("/settings")
+        .header("cookie", cookie)
+        .filter(&route)
+        .await
+        .is_ok()
+}
+
+#[tokio::test]
+async fn settings_routes_require_a_settings_session_when_configured() {
+    let api = api(Some("0000")).await;
+    let session = api.sessions.as_ref().unwrap().create().await;
+    let settings_session = api.settings_sessions.as_ref().unwrap().create().await;
+
+    assert!(!changes_settings(&api, &[]).await);
+    assert!(!changes_settings(&api, &[(SESSION_COOKIE, &session)]).await);
+    assert!(!changes_settings(&api, &[(SETTINGS_SESSION_COOKIE, &session)]).await);
+    assert!(changes_settings(&api, &[(SETTINGS_SESSION_COOKIE, &settings_session)]).await);
+}
+
+#[tokio::test]
+async fn settings_routes_fall_back_to_the_session_otherwise() {
+    let api = api(None).await;
+    let session = api.sessions.as_ref().unwrap().create().await;
+
+    assert!(!changes_settings(&api, &[]).await);
+    assert!(!changes_settings(&api, &[(SESSION_COOKIE, "nope")]).await);
+    assert!(changes_settings(&api, &[(SESSION_COOKIE, &session)]).await);
+}
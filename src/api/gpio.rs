@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use log::{error, info};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+use crate::{
+    gpio_controller::{Carrier, PinMode},
+    ir,
+    outputs::Relay,
+    presence::DistanceUnit,
+};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub(super) struct DistanceQuery {
+    /// Defaults to the unit system of Home-Assistant.
+    #[serde(default)]
+    unit: Option<DistanceUnit>,
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_ir_send = warp::path!("ir" / "send" / String)
+        .and(ctx.control_route(Method::POST, Access::Session))
+        .and_then(|name, api: Arc<Api>| async move { api.api_ir_send(name).await });
+
+    let api_rf_send = warp::path!("rf" / String)
+        .and(ctx.control_route(Method::POST, Access::Session))
+        .and_then(|name, api: Arc<Api>| async move { api.api_rf_send(name).await });
+
+    let api_gpio_distance_stream = warp::path!("gpio" / "distance" / "stream")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::query())
+        .and_then(Api::api_gpio_distance_stream);
+
+    let api_gestures_stream = warp::path!("gestures" / "stream")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_gestures_stream);
+
+    let api_gpio_get = warp::path!("gpio")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_gpio_get);
+
+    api_ir_send
+        .or(api_rf_send)
+        .or(api_gpio_distance_stream)
+        .or(api_gestures_stream)
+        .or(api_gpio_get)
+}
+
+impl Api {
+    async fn api_ir_send(self: Arc<Self>, name: String) -> Result<impl Reply, Rejection> {
+        let ir_config = self
+            .context
+            .config
+            .ir
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+        let code = ir_config.code(&name).ok_or_else(warp::reject::not_found)?;
+
+        info!("Sending IR code `{}`.", name);
+
+        let code_timings = code.code.timings();
+        let mut timings = Vec::new();
+
+        for i in 0..code.repeat.max(1) {
+            if i > 0 {
+                // Timings must alternate: the gap is a space after the final
+                // mark, or extends the final space if there is one.
+                if timings.len() % 2 == 0 {
+                    if let Some(last) = timings.last_mut() {
+                        *last += ir::REPEAT_GAP_US;
+                    }
+                } else {
+                    timings.push(ir::REPEAT_GAP_US);
+                }
+            }
+
+            timings.extend_from_slice(&code_timings);
+        }
+
+        self.context
+            .gpio
+            .send_pulses_async(
+                ir_config.pin,
+                Some(Carrier {
+                    frequency: ir_config.carrier_frequency,
+                    duty_cycle: ir_config.duty_cycle,
+                }),
+                timings,
+            )
+            .await
+            .map_err(|err| {
+                error!("failed to send IR code `{}`: {}", name, err);
+                warp::reject::custom(crate::Error::from(err))
+            })?;
+
+        Ok(warp::reply::json(&name))
+    }
+
+    async fn api_rf_send(self: Arc<Self>, name: String) -> Result<impl Reply, Rejection> {
+        let rf_config = self
+            .context
+            .config
+            .rf
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+        let code = rf_config.code(&name).ok_or_else(warp::reject::not_found)?;
+
+        info!("Sending RF code `{}`.", name);
+
+        let result = match code.timings() {
+            Ok(timings) => {
+                self.context
+                    .gpio
+                    .send_pulses_async(rf_config.pin, None, timings)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+
+        result.map_err(|err| {
+            error!("failed to send RF code `{}`: {}", name, err);
+            warp::reject::custom(crate::Error::from(err))
+        })?;
+
+        Ok(warp::reply::json(&name))
+    }
+
+    async fn api_gpio_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let gpio = &self.context.gpio;
+        let mut pins = gpio.pins();
+
+        if let Some(ir) = &self.context.config.ir {
+            pins.push(gpio.pin_status("ir", ir.pin, PinMode::Output));
+        }
+
+        if let Some(rf) = &self.context.config.rf {
+            pins.push(gpio.pin_status("rf", rf.pin, PinMode::Output));
+        }
+
+        if let Some(fan) = &self.context.config.fan {
+            pins.push(gpio.pin_status("fan", fan.pin, PinMode::Output));
+        }
+
+        if let Some(irrigation) = &self.context.config.irrigation {
+            pins.extend(irrigation.zones.iter().filter_map(|zone| match zone.relay {
+                Relay::Pin(pin) => Some(gpio.pin_status(&zone.name, pin, PinMode::Output)),
+                Relay::Switch(_) => None,
+            }));
+        }
+
+        pins.extend(
+            self.context
+                .config
+                .thermostats
+                .iter()
+                .filter_map(|thermostat| match thermostat.relay {
+                    Relay::Pin(pin) => {
+                        Some(gpio.pin_status(&thermostat.name, pin, PinMode::Output))
+                    }
+                    Relay::Switch(_) => None,
+                }),
+        );
+
+        pins.extend(
+            self.context
+                .config
+                .inputs
+                .iter()
+                .map(|input| gpio.pin_status(&input.name, input.pin, PinMode::Input)),
+        );
+
+        Ok(warp::reply::json(&pins))
+    }
+
+    /// Stream the distance sensor readings as server-sent events, to
+    /// calibrate the sensor.
+    async fn api_gpio_distance_stream(
+        self: Arc<Self>,
+        query: DistanceQuery,
+    ) -> Result<impl Reply, Rejection> {
+        let unit = match query.unit {
+            Some(unit) => unit,
+            None => match self.context.home_assistant.info().await.unit_system {
+                Some(unit_system) if unit_system.is_imperial() => DistanceUnit::In,
+                _ => DistanceUnit::Cm,
+            },
+        };
+        let readings = futures_util::stream::unfold(
+            self.distance_readings.subscribe(),
+            move |mut readings| async move {
+                loop {
+                    match readings.recv().await {
+                        Ok(reading) => {
+                            let reading = reading.in_unit(unit);
+
+                            return Some((
+                                warp::sse::Event::default().json_data(&reading),
+                                readings,
+                            ));
+                        }
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            },
+        );
+
+        Ok(warp::sse::reply(warp::sse::keep_alive().stream(readings)))
+    }
+
+    /// Stream the detected gestures as server-sent events.
+    async fn api_gestures_stream(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        if self.context.config.gestures.is_none() {
+            return Err(warp::reject::not_found());
+        }
+
+        let gestures =
+            futures_util::stream::unfold(self.gestures.subscribe(), |mut gestures| async move {
+                loop {
+                    match gestures.recv().await {
+                        Ok(gesture) => {
+                            return Some((
+                                warp::sse::Event::default().json_data(&gesture),
+                                gestures,
+                            ));
+                        }
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            });
+
+        Ok(warp::sse::reply(warp::sse::keep_alive().stream(gestures)))
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+use crate::home_assistant::DiscoveredEntity;
+
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct DiscoverQuery {
+    #[serde(default)]
+    domain: Option<String>,
+
+    /// The area id or name.
+    #[serde(default)]
+    area: Option<String>,
+}
+
+/// The discovered entities grouped by domain, as of the version 2.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredDomains {
+    pub domains: BTreeMap<String, Vec<DiscoveredEntity>>,
+}
+
+/// The routes of the version 2, in which the entities are grouped by domain.
+pub(super) fn routes_v2(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("discover")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::query())
+        .and_then(Api::api_v2_discover_get)
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_ha_info_get = warp::path!("ha" / "info")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_ha_info_get);
+
+    let api_discover_get = warp::path!("discover")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::query())
+        .and_then(Api::api_discover_get);
+
+    let api_area_recent_get = warp::path!("areas" / String / "recent")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|area, api: Arc<Api>| async move { api.api_area_recent_get(area).await });
+
+    api_ha_info_get.or(api_discover_get).or(api_area_recent_get)
+}
+
+impl Api {
+    async fn api_ha_info_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.context.home_assistant.info().await))
+    }
+
+    /// Suggest entities for the dashboard, so that they do not have to be
+    /// looked up in Home-Assistant.
+    async fn api_discover_get(
+        self: Arc<Self>,
+        query: DiscoverQuery,
+    ) -> Result<impl Reply, Rejection> {
+        let entities = self
+            .context
+            .home_assistant
+            .discover(query.domain.as_deref(), query.area.as_deref())
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&entities))
+    }
+
+    /// Get what changed recently in an area, by id or name, the latest
+    /// first.
+    async fn api_area_recent_get(self: Arc<Self>, area: String) -> Result<impl Reply, Rejection> {
+        let changelog = self
+            .changelog
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+        let changes = changelog
+            .recent(&area)
+            .await
+            .ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&changes))
+    }
+
+    async fn api_v2_discover_get(
+        self: Arc<Self>,
+        query: DiscoverQuery,
+    ) -> Result<impl Reply, Rejection> {
+        let entities = self
+            .context
+            .home_assistant
+            .discover(query.domain.as_deref(), query.area.as_deref())
+            .await
+            .map_err(warp::reject::custom)?;
+        let mut domains = BTreeMap::<_, Vec<_>>::new();
+
+        for entity in entities {
+            domains
+                .entry(entity.domain.clone())
+                .or_default()
+                .push(entity);
+        }
+
+        Ok(warp::reply::json(&DiscoveredDomains { domains }))
+    }
+}
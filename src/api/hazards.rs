@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("hazards")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_hazards_get)
+}
+
+impl Api {
+    async fn api_hazards_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let hazards = self.hazards.as_ref().ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&hazards.alerts()))
+    }
+}
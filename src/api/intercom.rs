@@ -0,0 +1,164 @@
+use std::{sync::Arc, time::Duration};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use log::{info, warn};
+use warp::{
+    http::{Method, StatusCode},
+    Filter, Rejection, Reply,
+};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::{
+    announcements::Announcement,
+    audio::Audio,
+    intercom::{IntercomAck, IntercomMessage},
+    melody::Melody,
+};
+
+/// For how long a message of another panel is shown.
+const MESSAGE_DURATION: Duration = Duration::from_secs(60);
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    // The clips are sent in base64, a third larger.
+    let max_body_size = ctx
+        .api
+        .audio
+        .as_ref()
+        .map(|audio| Audio::max_clip_size(audio) / 3 * 4)
+        .unwrap_or_default()
+        + 4 * 1024;
+
+    let api_intercom_send = warp::path!("intercom" / String)
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "intercom", max_body_size))
+        .and(warp::body::json())
+        .and_then(|target: String, permit: Permit, api: Arc<Api>, message| {
+            permit.hold(Api::api_intercom_send(api, target, message))
+        });
+
+    // The other panels authenticate with the shared token instead of a
+    // session.
+    let api_intercom_receive = warp::path!("intercom")
+        .and(ctx.limited_route(Method::POST, Access::Public, "intercom", max_body_size))
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, authorization, message| {
+            permit.hold(Api::api_intercom_receive(api, authorization, message))
+        });
+
+    api_intercom_send.or(api_intercom_receive)
+}
+
+impl Api {
+    /// Forward a message to another panel, answering once it acknowledged
+    /// it, or with a `502 Bad Gateway` if it could not be delivered.
+    async fn api_intercom_send(
+        self: Arc<Self>,
+        target: String,
+        message: IntercomMessage,
+    ) -> Result<impl Reply, Rejection> {
+        let intercom = self.intercom.as_ref().ok_or_else(warp::reject::not_found)?;
+
+        if message.message.trim().is_empty() {
+            return Err(warp::reject::custom(crate::Error::InvalidConfig(
+                "the message is empty".to_string(),
+            )));
+        }
+
+        let delivery = intercom
+            .send(&target, message)
+            .await
+            .ok_or_else(warp::reject::not_found)?;
+
+        if let Some(error) = &delivery.error {
+            warn!("Failed to send the message to `{}`: {}", target, error);
+        }
+
+        let status = if delivery.delivered {
+            StatusCode::OK
+        } else {
+            StatusCode::BAD_GATEWAY
+        };
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&delivery),
+            status,
+        ))
+    }
+
+    /// Show a message of another panel, and acknowledge it.
+    ///
+    /// The chime and the clip are not waited for.
+    async fn api_intercom_receive(
+        self: Arc<Self>,
+        authorization: Option<String>,
+        message: IntercomMessage,
+    ) -> Result<impl Reply, Rejection> {
+        let intercom = self.intercom.as_ref().ok_or_else(warp::reject::not_found)?;
+
+        if !intercom.is_authorized(authorization.as_deref()) {
+            return Err(warp::reject::custom(crate::Error::Unauthorized));
+        }
+
+        let clip = message
+            .audio
+            .as_ref()
+            .map(|audio| STANDARD.decode(audio))
+            .transpose()
+            .map_err(|err| {
+                warp::reject::custom(crate::Error::InvalidConfig(format!(
+                    "the audio clip is not in base64: {}",
+                    err
+                )))
+            })?;
+
+        info!("Received a message from the panel `{}`.", message.from);
+
+        if let Err(err) = self.context.screen.force_on(true) {
+            warn!("Failed to turn the screen on: {}", err);
+        }
+
+        let received_at = Utc::now();
+
+        self.announcements.show(Announcement {
+            title: Some(message.from),
+            message: message.message,
+            shown_at: received_at,
+            until: received_at + chrono::Duration::from_std(MESSAGE_DURATION).unwrap_or_default(),
+        });
+
+        if message.chime || clip.is_some() {
+            let api = Arc::clone(&self);
+
+            tokio::spawn(async move {
+                if message.chime {
+                    if let Err(err) = api.melody_player.play(Melody::CHIME).await {
+                        warn!("Failed to play the chime: {}", err);
+                    }
+                }
+
+                if let Some(clip) = clip {
+                    match &api.audio {
+                        Some(audio) => {
+                            if let Err(err) = audio.play_clip(&clip).await {
+                                warn!("Failed to play the intercom clip: {}", err);
+                            }
+                        }
+                        None => warn!("Ignoring the intercom clip: no audio playback."),
+                    }
+                }
+            });
+        }
+
+        Ok(warp::reply::json(&IntercomAck {
+            panel: intercom.name().to_string(),
+            received_at,
+        }))
+    }
+}
@@ -0,0 +1,112 @@
+use std::{sync::Arc, time::Duration};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::irrigation::{Irrigation, IrrigationSchedule};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub(super) struct StartRequest {
+    /// The watering duration in seconds. Defaults to the run time of the
+    /// zone.
+    #[serde(default)]
+    duration: Option<f64>,
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_irrigation_get = warp::path!("irrigation")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_irrigation_get);
+
+    let api_irrigation_start = warp::path!("irrigation" / String / "start")
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "irrigation", 64))
+        .and(warp::body::json())
+        .and_then(|zone: String, permit: Permit, api: Arc<Api>, request| {
+            permit.hold(api.api_irrigation_start(zone, request))
+        });
+
+    let api_irrigation_stop = warp::path!("irrigation" / "stop")
+        .and(ctx.control_route(Method::POST, Access::Session))
+        .and_then(Api::api_irrigation_stop);
+
+    let api_irrigation_schedule_set = warp::path!("irrigation" / "schedule")
+        .and(ctx.limited_control_route(Method::PUT, Access::Settings, "irrigation", 16 * 1024))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, schedule| {
+            permit.hold(Api::api_irrigation_schedule_set(api, schedule))
+        });
+
+    api_irrigation_get
+        .or(api_irrigation_stop)
+        .or(api_irrigation_start)
+        .or(api_irrigation_schedule_set)
+}
+
+impl Api {
+    fn irrigation(&self) -> Result<&Irrigation, Rejection> {
+        self.context
+            .scheduler
+            .irrigation
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)
+    }
+
+    async fn api_irrigation_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(
+            &self.irrigation()?.status(&self.context).await,
+        ))
+    }
+
+    async fn api_irrigation_start(
+        self: Arc<Self>,
+        zone: String,
+        request: StartRequest,
+    ) -> Result<impl Reply, Rejection> {
+        let duration = request
+            .duration
+            .filter(|duration| duration.is_finite() && *duration > 0.0)
+            .map(Duration::from_secs_f64);
+
+        if !self.irrigation()?.start(&zone, duration).await {
+            return Err(warp::reject::not_found());
+        }
+
+        Ok(warp::reply::json(&zone))
+    }
+
+    async fn api_irrigation_stop(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        self.irrigation()?.stop().await;
+
+        Ok(warp::reply::json(&true))
+    }
+
+    async fn api_irrigation_schedule_set(
+        self: Arc<Self>,
+        schedules: Vec<IrrigationSchedule>,
+    ) -> Result<impl Reply, Rejection> {
+        let irrigation = self.irrigation()?;
+
+        irrigation
+            .validate_schedules(&schedules)
+            .map_err(warp::reject::custom)?;
+        self.context
+            .editable
+            .save_schedules(Some(schedules.clone()), None)
+            .await
+            .map_err(warp::reject::custom)?;
+        irrigation
+            .set_schedules(schedules.clone())
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&schedules))
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use log::{debug, info};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warp::{
+    http::{Method, StatusCode},
+    Filter, Rejection, Reply,
+};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api, ApiBool,
+};
+use crate::{circadian::Circadian, mqtt::zigbee2mqtt::ZigbeeDevice};
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LightStatus {
+    pub on: bool,
+
+    /// Whether the light is reachable. Unavailable lights are reported off.
+    pub available: bool,
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_light = warp::path!("light" / String);
+
+    let api_light_get = api_light
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|name, api: Arc<Api>| async move { api.api_light_get(name).await });
+
+    let api_light_set = api_light
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "lights", 256))
+        .and(warp::body::json())
+        .and_then(|light: String, permit: Permit, api: Arc<Api>, status| {
+            permit.hold(Api::api_light_set(api, light, status))
+        });
+
+    let api_light_brightness_set = warp::path!("light" / String / "brightness")
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "lights", 256))
+        .and(warp::body::json())
+        .and_then(|light: String, permit: Permit, api: Arc<Api>, brightness| {
+            permit.hold(Api::api_light_brightness_set(api, light, brightness))
+        });
+
+    let api_circadian = warp::path!("circadian");
+
+    let api_circadian_get = api_circadian
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_circadian_get);
+
+    let api_circadian_set = api_circadian
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "lights", 8))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, enabled| permit.hold(Api::api_circadian_set(api, enabled)));
+
+    api_light_get
+        .or(api_light_set)
+        .or(api_light_brightness_set)
+        .or(api_circadian_get)
+        .or(api_circadian_set)
+}
+
+impl Api {
+    /// Get the Zigbee2MQTT device standing in for a light Home-Assistant
+    /// does not know of, like while it is down.
+    async fn zigbee_light(&self, light: &str) -> Option<ZigbeeDevice> {
+        if self
+            .context
+            .home_assistant
+            .entity(&format!("light.{}", light))
+            .await
+            .is_some()
+        {
+            return None;
+        }
+
+        self.zigbee.as_ref()?.device(light).await
+    }
+
+    async fn api_light_get(self: Arc<Self>, light: String) -> Result<impl Reply, Rejection> {
+        let entity_id = format!("light.{}", light);
+        let on: Option<bool> = match self.context.home_assistant.entity(&entity_id).await {
+            Some(state) => state.into(),
+            None => self
+                .zigbee_light(&light)
+                .await
+                .ok_or_else(warp::reject::not_found)?
+                .is_on(),
+        };
+
+        Ok(warp::reply::json(&LightStatus {
+            on: on.unwrap_or_default(),
+            available: on.is_some(),
+        }))
+    }
+
+    async fn api_light_set(
+        self: Arc<Self>,
+        light: String,
+        status: ApiBool,
+    ) -> Result<impl Reply, Rejection> {
+        let status: bool = status.into();
+        let entity_id = format!("light.{}", light);
+
+        if let (Some(zigbee), Some(device)) = (&self.zigbee, self.zigbee_light(&light).await) {
+            zigbee
+                .set_on(&device.ieee_address, status)
+                .await
+                .map_err(|err| warp::reject::custom(crate::Error::from(err)))?;
+
+            return Ok(warp::reply::json(&LightStatus {
+                on: status,
+                available: true,
+            }));
+        }
+
+        let circadian_settings = self
+            .circadian
+            .as_ref()
+            .and_then(|circadian| circadian.settings_for(&light))
+            .filter(|_| status);
+
+        if let Some(settings) = circadian_settings {
+            debug!(
+                "Applying circadian settings to `{}`: {:?}",
+                entity_id, settings
+            );
+
+            self.context
+                .home_assistant
+                .light_turn_on_with(&entity_id, &settings.to_service_data())
+                .await
+        } else {
+            self.context
+                .home_assistant
+                .light_set(&entity_id, status)
+                .await
+        }
+        .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&LightStatus {
+            on: status,
+            available: true,
+        }))
+    }
+
+    async fn api_light_brightness_set(
+        self: Arc<Self>,
+        light: String,
+        brightness_pct: f64,
+    ) -> Result<impl Reply, Rejection> {
+        let entity_id = format!("light.{}", light);
+        let brightness_pct = brightness_pct.clamp(0.0, 100.0);
+        let ha_controller = self.context.home_assistant.clone();
+
+        self.debouncer
+            .submit(format!("{}/brightness", entity_id), async move {
+                ha_controller
+                    .light_set_brightness(&entity_id, brightness_pct)
+                    .await
+            })
+            .await;
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&brightness_pct),
+            StatusCode::ACCEPTED,
+        ))
+    }
+
+    async fn api_circadian_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let status = self
+            .circadian
+            .as_ref()
+            .map(Circadian::enabled)
+            .unwrap_or_default();
+
+        Ok(warp::reply::json(&status))
+    }
+
+    async fn api_circadian_set(self: Arc<Self>, status: ApiBool) -> Result<impl Reply, Rejection> {
+        let circadian = self
+            .circadian
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+        let status: bool = status.into();
+
+        info!(
+            "{} circadian lighting.",
+            if status { "Enabling" } else { "Disabling" }
+        );
+        circadian.set_enabled(status);
+
+        Ok(warp::reply::json(&status))
+    }
+}
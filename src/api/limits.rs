@@ -0,0 +1,72 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use serde::Deserialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// The limits of a group of routes, by the name of the group, like `lights`
+/// or `rules`, to protect the panel from a misbehaving client.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RouteLimitsConfig {
+    /// The maximum size of the request bodies, in bytes, instead of the
+    /// default size of each route.
+    #[serde(default)]
+    pub max_body_size: Option<u64>,
+
+    /// The maximum number of requests with a body handled at once. Further
+    /// requests are rejected until one completes.
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+}
+
+/// The limits of a group of routes, as enforced.
+#[derive(Debug, Clone, Default)]
+pub(super) struct RouteLimits {
+    pub(super) max_body_size: Option<u64>,
+    pub(super) requests: Option<Arc<Semaphore>>,
+}
+
+impl RouteLimits {
+    pub(super) fn by_group(
+        config: &HashMap<String, RouteLimitsConfig>,
+    ) -> HashMap<String, RouteLimits> {
+        config
+            .iter()
+            .map(|(group, config)| {
+                let limits = RouteLimits {
+                    max_body_size: config.max_body_size,
+                    requests: config
+                        .max_concurrency
+                        .map(|max| Arc::new(Semaphore::new(max))),
+                };
+
+                (group.clone(), limits)
+            })
+            .collect()
+    }
+}
+
+/// The permission to handle a request of a group of routes, to hold until it
+/// is handled.
+#[derive(Debug)]
+pub(super) struct Permit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Permit {
+    pub(super) fn acquire(requests: Option<&Arc<Semaphore>>) -> crate::Result<Self> {
+        match requests {
+            Some(requests) => Arc::clone(requests)
+                .try_acquire_owned()
+                .map(|permit| Self {
+                    _permit: Some(permit),
+                })
+                .map_err(|_| crate::Error::Busy),
+            None => Ok(Self { _permit: None }),
+        }
+    }
+
+    /// Hold the permit while handling the request.
+    pub(super) async fn hold<F: Future>(self, handle: F) -> F::Output {
+        handle.await
+    }
+}
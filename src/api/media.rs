@@ -0,0 +1,265 @@
+use std::sync::Arc;
+
+use log::error;
+use serde::Deserialize;
+use warp::{
+    http::{Method, StatusCode},
+    hyper::body::Bytes,
+    Filter, Rejection, Reply,
+};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::{
+    audio::{Audio, PlaySound},
+    camera::MJPEG_BOUNDARY,
+};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub(super) struct ArtQuery {
+    /// The size of the square the artwork must fit in, in pixels, or the
+    /// original size.
+    #[serde(default)]
+    size: Option<u32>,
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let max_clip_size = ctx
+        .api
+        .audio
+        .as_ref()
+        .map(Audio::max_clip_size)
+        .unwrap_or_default();
+
+    let api_audio_play = warp::path!("audio" / "play")
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "media", max_clip_size))
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::body::bytes())
+        .and_then(|permit: Permit, api, content_type, clip| {
+            permit.hold(Api::api_audio_play(api, content_type, clip))
+        });
+
+    let api_camera_stream = warp::path!("camera" / String / "stream")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|name, api: Arc<Api>| async move { api.api_camera_stream(name).await });
+
+    let api_media_volume_set = warp::path!("media" / String / "volume")
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "media", 32))
+        .and(warp::body::json())
+        .and_then(|name: String, permit: Permit, api: Arc<Api>, volume| {
+            permit.hold(Api::api_media_volume_set(api, name, volume))
+        });
+
+    let api_media_art = warp::path!("media" / String / "art")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::query())
+        .and_then(|name, api: Arc<Api>, query| async move { api.api_media_art(name, query).await });
+
+    let api_media_groups_get = warp::path!("media" / "groups")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_media_groups_get);
+
+    let api_media_group_join = warp::path!("media" / "groups" / String / "join")
+        .and(ctx.control_route(Method::POST, Access::Session))
+        .and_then(|name, api: Arc<Api>| async move { api.api_media_group_join(name).await });
+
+    let api_media_group_unjoin = warp::path!("media" / "groups" / String / "unjoin")
+        .and(ctx.control_route(Method::POST, Access::Session))
+        .and_then(|name, api: Arc<Api>| async move { api.api_media_group_unjoin(name).await });
+
+    let api_media_group_volume_set = warp::path!("media" / "groups" / String / "volume")
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "media", 32))
+        .and(warp::body::json())
+        .and_then(|name: String, permit: Permit, api: Arc<Api>, volume| {
+            permit.hold(api.api_media_group_volume_set(name, volume))
+        });
+
+    api_audio_play
+        .or(api_camera_stream)
+        .or(api_media_volume_set)
+        .or(api_media_art)
+        .or(api_media_groups_get)
+        .or(api_media_group_join)
+        .or(api_media_group_unjoin)
+        .or(api_media_group_volume_set)
+}
+
+impl Api {
+    /// Play a local sound if the body is JSON, or the body itself otherwise.
+    async fn api_audio_play(
+        self: Arc<Self>,
+        content_type: Option<String>,
+        body: Bytes,
+    ) -> Result<impl Reply, Rejection> {
+        let audio = self.audio.as_ref().ok_or_else(warp::reject::not_found)?;
+
+        let result = match content_type {
+            Some(content_type) if content_type.starts_with("application/json") => {
+                let play_sound: PlaySound = serde_json::from_slice(&body)
+                    .map_err(|err| warp::reject::custom(crate::Error::from(err)))?;
+
+                audio.play_sound(&play_sound.sound).await
+            }
+            _ => audio.play_clip(&body).await,
+        };
+
+        result.map_err(|err| {
+            error!("failed to play audio: {}", err);
+            warp::reject::custom(err)
+        })?;
+
+        Ok(warp::reply::json(&true))
+    }
+
+    async fn api_camera_stream(self: Arc<Self>, name: String) -> Result<impl Reply, Rejection> {
+        let stream = self
+            .cameras
+            .stream(&name)
+            .await
+            .ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::with_header(
+            warp::reply::Response::new(warp::hyper::Body::wrap_stream(stream)),
+            "content-type",
+            format!("multipart/x-mixed-replace; boundary={}", MJPEG_BOUNDARY),
+        ))
+    }
+
+    async fn api_media_volume_set(
+        self: Arc<Self>,
+        name: String,
+        volume_level: f64,
+    ) -> Result<impl Reply, Rejection> {
+        let entity_id = format!("media_player.{}", name);
+        let volume_level = volume_level.clamp(0.0, 1.0);
+        let ha_controller = self.context.home_assistant.clone();
+
+        self.debouncer
+            .submit(format!("{}/volume", entity_id), async move {
+                ha_controller
+                    .media_player_volume_set(&entity_id, volume_level)
+                    .await
+            })
+            .await;
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&volume_level),
+            StatusCode::ACCEPTED,
+        ))
+    }
+
+    /// Serve the artwork of a media player, from the cache if possible.
+    async fn api_media_art(
+        self: Arc<Self>,
+        name: String,
+        query: ArtQuery,
+    ) -> Result<impl Reply, Rejection> {
+        if let Some(size) = query.size {
+            if !self.artwork.is_supported_size(size) {
+                return Err(warp::reject::custom(crate::Error::InvalidConfig(format!(
+                    "unsupported artwork size: {}",
+                    size
+                ))));
+            }
+        }
+
+        let entity_id = format!("media_player.{}", name);
+        let url = self
+            .context
+            .home_assistant
+            .entity(&entity_id)
+            .await
+            .and_then(|state| state.attributes.get::<String>("entity_picture"))
+            .ok_or_else(warp::reject::not_found)?;
+        let picture = self
+            .artwork
+            .get(&url, query.size, &self.context.home_assistant)
+            .await
+            .map_err(|err| {
+                error!("failed to get the artwork of `{}`: {:#}", entity_id, err);
+                warp::reject::custom(crate::Error::from(err))
+            })?;
+
+        Ok(warp::reply::with_header(
+            warp::reply::Response::new(picture.data.into()),
+            "content-type",
+            picture.content_type,
+        ))
+    }
+
+    async fn api_media_groups_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(
+            &self.media_groups.status(&self.context.home_assistant).await,
+        ))
+    }
+
+    async fn api_media_group_join(self: Arc<Self>, name: String) -> Result<impl Reply, Rejection> {
+        let joined = self
+            .media_groups
+            .join(&name, &self.context.home_assistant)
+            .await
+            .map_err(|err| {
+                error!("failed to join the speakers of `{}`: {}", name, err);
+                warp::reject::custom(err)
+            })?;
+
+        if !joined {
+            return Err(warp::reject::not_found());
+        }
+
+        Ok(warp::reply::json(&true))
+    }
+
+    async fn api_media_group_unjoin(
+        self: Arc<Self>,
+        name: String,
+    ) -> Result<impl Reply, Rejection> {
+        let unjoined = self
+            .media_groups
+            .unjoin(&name, &self.context.home_assistant)
+            .await
+            .map_err(|err| {
+                error!("failed to unjoin the speakers of `{}`: {}", name, err);
+                warp::reject::custom(err)
+            })?;
+
+        if !unjoined {
+            return Err(warp::reject::not_found());
+        }
+
+        Ok(warp::reply::json(&true))
+    }
+
+    /// Set the volume of all the speakers of a group at once.
+    async fn api_media_group_volume_set(
+        self: Arc<Self>,
+        name: String,
+        volume_level: f64,
+    ) -> Result<impl Reply, Rejection> {
+        let entity_ids = self
+            .media_groups
+            .get(&name)
+            .ok_or_else(warp::reject::not_found)?
+            .entity_ids();
+        let volume_level = volume_level.clamp(0.0, 1.0);
+        let ha_controller = self.context.home_assistant.clone();
+
+        self.debouncer
+            .submit(format!("media_groups/{}/volume", name), async move {
+                ha_controller
+                    .media_players_volume_set(&entity_ids, volume_level)
+                    .await
+            })
+            .await;
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&volume_level),
+            StatusCode::ACCEPTED,
+        ))
+    }
+}
@@ -0,0 +1,37 @@
+use std::{collections::HashMap, sync::Arc};
+
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+use crate::{home_assistant, windows::OpeningsStatus};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("openings")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_openings_get)
+}
+
+impl Api {
+    /// Summarize the configured doors and windows, if any.
+    ///
+    /// While Home-Assistant is disconnected, all the sensors are unavailable.
+    pub(super) async fn openings(&self) -> Option<OpeningsStatus> {
+        let openings = self.context.config.openings.as_ref()?;
+
+        Some(match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => openings.status(&entities),
+            home_assistant::Status::Disconnected => openings.status(&HashMap::new()),
+        })
+    }
+
+    async fn api_openings_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let openings = self.openings().await.ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&openings))
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::panic::{Panic, PanicSource};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub(super) struct PanicCancelRequest {
+    pin: String,
+}
+
+/// Raising a panic does not require a session, so that it works from a
+/// locked panel: cancelling it requires the PIN instead.
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_panic_get = warp::path!("panic")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_panic_get);
+
+    let api_panic_raise = warp::path!("panic")
+        .and(ctx.route(Method::POST, Access::Public))
+        .and_then(Api::api_panic_raise);
+
+    let api_panic_cancel = warp::path!("panic" / "cancel")
+        .and(ctx.limited_route(Method::POST, Access::Public, "panic", 256))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, request| permit.hold(Api::api_panic_cancel(api, request)));
+
+    api_panic_get.or(api_panic_raise).or(api_panic_cancel)
+}
+
+impl Api {
+    fn panic(&self) -> Result<&Panic, Rejection> {
+        self.panic.as_ref().ok_or_else(warp::reject::not_found)
+    }
+
+    async fn api_panic_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.panic()?.status()))
+    }
+
+    async fn api_panic_raise(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let panic = self.panic()?;
+
+        panic.raise(PanicSource::Api);
+
+        Ok(warp::reply::json(&panic.status()))
+    }
+
+    /// Cancel the panic, with brute-force protection of the PIN.
+    async fn api_panic_cancel(
+        self: Arc<Self>,
+        request: PanicCancelRequest,
+    ) -> Result<impl Reply, Rejection> {
+        let panic = self.panic()?;
+
+        self.check_pin("panic", || panic.config().check_pin(&request.pin))
+            .await?;
+        panic.cancel();
+
+        Ok(warp::reply::json(&panic.status()))
+    }
+}
@@ -0,0 +1,460 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+use log::info;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::{
+    home_assistant::{Attributes, Status},
+    irrigation::IrrigationSchedule,
+    rules::{
+        engine::{EntitySnapshot, Snapshot},
+        preview::{Firing, Schedules},
+    },
+    wakeup::WakeupAlarm,
+};
+
+/// The version of the exported documents, bumped when their shape changes.
+const RULES_VERSION: u32 = 1;
+
+/// How many executions the history lists at most.
+const MAX_HISTORY_LIMIT: usize = 500;
+
+/// How far ahead the schedules are previewed at most, in hours.
+const MAX_PREVIEW_HOURS: u32 = 14 * 24;
+
+/// The local schedules, as a single document to version-control or to copy to
+/// another panel.
+///
+/// The sections are only exported for the configured features, and the
+/// missing ones are left untouched on import. The imported sections are saved
+/// with the configuration edited from the frontend, and so survive restarts.
+///
+/// The declarative `rules` of the configuration are excluded: they are only
+/// read from the configuration file.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RulesDocument {
+    pub version: u32,
+
+    /// When the document was exported. Ignored on import.
+    #[serde(default)]
+    pub exported_at: Option<DateTime<Utc>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub irrigation_schedules: Option<Vec<IrrigationSchedule>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wakeup_alarms: Option<Vec<WakeupAlarm>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub(super) struct ImportQuery {
+    /// Only validate the document and compare it to the current rules.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RuleTestRequest {
+    /// The states to evaluate against, by entity id, on top of the current
+    /// ones.
+    #[serde(default)]
+    states: HashMap<String, SuppliedState>,
+
+    /// The local date and time to evaluate against, like
+    /// `2024-05-04T23:30:00`. Defaults to now.
+    #[serde(default)]
+    at: Option<NaiveDateTime>,
+}
+
+/// A state to evaluate a rule against.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub(super) enum SuppliedState {
+    /// Only the state, which keeps the current attributes and is held for
+    /// long enough.
+    State(String),
+
+    #[serde(rename_all = "camelCase")]
+    Entity {
+        state: String,
+
+        /// Defaults to long ago, for the state to be held for long enough.
+        #[serde(default)]
+        last_changed: Option<DateTime<Utc>>,
+
+        #[serde(default)]
+        attributes: Map<String, Value>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub(super) struct HistoryQuery {
+    /// How many of the latest executions to list, up to 500.
+    #[serde(default = "HistoryQuery::default_limit")]
+    limit: usize,
+}
+
+impl HistoryQuery {
+    fn default_limit() -> usize {
+        50
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub(super) struct PreviewQuery {
+    /// How far ahead to preview the schedules, in hours, up to two weeks.
+    #[serde(default = "PreviewQuery::default_hours")]
+    hours: u32,
+}
+
+impl PreviewQuery {
+    fn default_hours() -> u32 {
+        48
+    }
+}
+
+/// The upcoming firings of the schedules and the rules.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulesPreview {
+    pub from: DateTime<FixedOffset>,
+    pub until: DateTime<FixedOffset>,
+    pub firings: Vec<Firing>,
+}
+
+/// The changes of a section of the rules.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RulesSectionDiff {
+    /// The section, like `irrigationSchedules`.
+    pub section: String,
+    pub added: Vec<Value>,
+    pub removed: Vec<Value>,
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RulesImportResult {
+    /// Whether the rules were left untouched.
+    pub dry_run: bool,
+
+    /// The changes of the imported sections.
+    pub sections: Vec<RulesSectionDiff>,
+}
+
+impl RulesSectionDiff {
+    fn new<T: Serialize>(section: &str, current: &[T], imported: &[T]) -> Self {
+        let current: Vec<Value> = current
+            .iter()
+            .filter_map(|entry| serde_json::to_value(entry).ok())
+            .collect();
+        let imported: Vec<Value> = imported
+            .iter()
+            .filter_map(|entry| serde_json::to_value(entry).ok())
+            .collect();
+
+        Self {
+            section: section.to_string(),
+            added: imported
+                .iter()
+                .filter(|entry| !current.contains(entry))
+                .cloned()
+                .collect(),
+            removed: current
+                .iter()
+                .filter(|entry| !imported.contains(entry))
+                .cloned()
+                .collect(),
+            unchanged: imported
+                .iter()
+                .filter(|entry| current.contains(entry))
+                .count(),
+        }
+    }
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_rules_export = warp::path!("rules" / "export")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_rules_export);
+
+    let api_rules_import = warp::path!("rules" / "import")
+        .and(ctx.limited_route(Method::POST, Access::Settings, "rules", 64 * 1024))
+        .and(warp::query())
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, query, rules| {
+            permit.hold(Api::api_rules_import(api, query, rules))
+        });
+
+    let api_rule_test = warp::path!("rules" / String / "test")
+        .and(ctx.limited_route(Method::POST, Access::Public, "rules", 64 * 1024))
+        .and(warp::body::json())
+        .and_then(|id: String, permit: Permit, api: Arc<Api>, request| {
+            permit.hold(api.api_rule_test(id, request))
+        });
+
+    let api_rule_history = warp::path!("rules" / String / "history")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::query())
+        .and_then(|id: String, api: Arc<Api>, query| async move {
+            api.api_rule_history(id, query).await
+        });
+
+    let api_schedules_preview = warp::path!("schedules" / "preview")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::query())
+        .and_then(Api::api_schedules_preview);
+
+    api_rules_export
+        .or(api_rules_import)
+        .or(api_rule_test)
+        .or(api_rule_history)
+        .or(api_schedules_preview)
+}
+
+impl Api {
+    async fn api_rules_export(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let irrigation_schedules = match &self.context.scheduler.irrigation {
+            Some(irrigation) => Some(irrigation.schedules().await),
+            None => None,
+        };
+        let wakeup_alarms = match &self.context.scheduler.wakeup {
+            Some(wakeup) => Some(wakeup.alarms().await),
+            None => None,
+        };
+
+        Ok(warp::reply::json(&RulesDocument {
+            version: RULES_VERSION,
+            exported_at: Some(Utc::now()),
+            irrigation_schedules,
+            wakeup_alarms,
+        }))
+    }
+
+    /// Replace the rules of the sections of the document, once they are all
+    /// valid.
+    async fn api_rules_import(
+        self: Arc<Self>,
+        query: ImportQuery,
+        document: RulesDocument,
+    ) -> Result<impl Reply, Rejection> {
+        self.validate_rules(&document)
+            .map_err(warp::reject::custom)?;
+
+        let mut sections = Vec::new();
+
+        if let (Some(irrigation), Some(schedules)) = (
+            &self.context.scheduler.irrigation,
+            &document.irrigation_schedules,
+        ) {
+            sections.push(RulesSectionDiff::new(
+                "irrigationSchedules",
+                &irrigation.schedules().await,
+                schedules,
+            ));
+        }
+
+        if let (Some(wakeup), Some(alarms)) =
+            (&self.context.scheduler.wakeup, &document.wakeup_alarms)
+        {
+            sections.push(RulesSectionDiff::new(
+                "wakeupAlarms",
+                &wakeup.alarms().await,
+                alarms,
+            ));
+        }
+
+        if !query.dry_run {
+            self.context
+                .editable
+                .save_schedules(
+                    document.irrigation_schedules.clone(),
+                    document.wakeup_alarms.clone(),
+                )
+                .await
+                .map_err(warp::reject::custom)?;
+
+            if let (Some(irrigation), Some(schedules)) = (
+                &self.context.scheduler.irrigation,
+                document.irrigation_schedules,
+            ) {
+                irrigation
+                    .set_schedules(schedules)
+                    .await
+                    .map_err(warp::reject::custom)?;
+            }
+
+            if let (Some(wakeup), Some(alarms)) =
+                (&self.context.scheduler.wakeup, document.wakeup_alarms)
+            {
+                wakeup.set_alarms(alarms).await;
+            }
+
+            info!("Imported the rules.");
+        }
+
+        Ok(warp::reply::json(&RulesImportResult {
+            dry_run: query.dry_run,
+            sections,
+        }))
+    }
+
+    /// Evaluate a rule and tell which of its actions would run, without
+    /// running them.
+    async fn api_rule_test(
+        self: Arc<Self>,
+        id: String,
+        request: RuleTestRequest,
+    ) -> Result<impl Reply, Rejection> {
+        let rule = self
+            .context
+            .config
+            .rules
+            .iter()
+            .find(|rule| rule.id == id)
+            .ok_or_else(warp::reject::not_found)?;
+        let now = match request.at {
+            Some(at) => Local.from_local_datetime(&at).earliest().ok_or_else(|| {
+                warp::reject::custom(crate::Error::InvalidConfig(format!(
+                    "`{}` does not exist in the local time zone",
+                    at
+                )))
+            })?,
+            None => Local::now(),
+        };
+        let entities = match self.context.home_assistant.status().await {
+            Status::Connected { entities } => entities,
+            Status::Disconnected => Default::default(),
+        };
+        let mut snapshot = Snapshot::new(
+            &entities,
+            rule.entity_ids(),
+            now,
+            self.context.config.holidays.as_ref(),
+        );
+
+        for (entity_id, supplied) in request.states {
+            let entity = match supplied {
+                SuppliedState::State(state) => EntitySnapshot {
+                    state,
+                    last_changed: DateTime::<Utc>::MIN_UTC,
+                    attributes: entities
+                        .get(&entity_id)
+                        .map(|current| current.attributes.clone())
+                        .unwrap_or_default(),
+                },
+                SuppliedState::Entity {
+                    state,
+                    last_changed,
+                    attributes,
+                } => EntitySnapshot {
+                    state,
+                    last_changed: last_changed.unwrap_or(DateTime::<Utc>::MIN_UTC),
+                    attributes: Attributes::from(Value::Object(attributes)),
+                },
+            };
+
+            snapshot.states.insert(entity_id, entity);
+        }
+
+        // The conditions are evaluated as if they did not match before.
+        Ok(warp::reply::json(&rule.trace(&snapshot, &[])))
+    }
+
+    /// List the latest executions of a rule, or of the `irrigation` and
+    /// `wakeup` schedules, the latest first.
+    async fn api_rule_history(
+        self: Arc<Self>,
+        id: String,
+        query: HistoryQuery,
+    ) -> Result<impl Reply, Rejection> {
+        let history = self
+            .context
+            .storage
+            .history
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+        let executions = history
+            .list(id, query.limit.min(MAX_HISTORY_LIMIT))
+            .await
+            .map_err(warp::reject::custom)?;
+
+        Ok(warp::reply::json(&executions))
+    }
+
+    /// List the upcoming firings of the schedules and the rules, in local
+    /// time, to check them over the changes of the UTC offset.
+    async fn api_schedules_preview(
+        self: Arc<Self>,
+        query: PreviewQuery,
+    ) -> Result<impl Reply, Rejection> {
+        let irrigation = match &self.context.scheduler.irrigation {
+            Some(irrigation) => irrigation.schedules().await,
+            None => Vec::new(),
+        };
+        let wakeup = match &self.context.scheduler.wakeup {
+            Some(wakeup) => wakeup.alarms().await,
+            None => Vec::new(),
+        };
+        let from = Local::now();
+        let until = from + Duration::hours(query.hours.min(MAX_PREVIEW_HOURS).into());
+        let firings = Schedules {
+            irrigation: &irrigation,
+            wakeup: &wakeup,
+            rules: &self.context.config.rules,
+            astronomy: self.context.config.astronomy.as_ref(),
+            holidays: self.context.config.holidays.as_ref(),
+        }
+        .preview(&Local, &from, &until);
+
+        Ok(warp::reply::json(&SchedulesPreview {
+            from: from.fixed_offset(),
+            until: until.fixed_offset(),
+            firings,
+        }))
+    }
+
+    /// Check the whole document before touching any rule, so that an import
+    /// is all or nothing.
+    fn validate_rules(&self, document: &RulesDocument) -> crate::Result<()> {
+        if document.version != RULES_VERSION {
+            return Err(crate::Error::InvalidConfig(format!(
+                "unsupported rules version {}, expected {}",
+                document.version, RULES_VERSION
+            )));
+        }
+
+        if let Some(schedules) = &document.irrigation_schedules {
+            self.context
+                .scheduler
+                .irrigation
+                .as_ref()
+                .ok_or_else(|| {
+                    crate::Error::InvalidConfig("irrigation is not configured".to_string())
+                })?
+                .validate_schedules(schedules)?;
+        }
+
+        if document.wakeup_alarms.is_some() && self.context.scheduler.wakeup.is_none() {
+            return Err(crate::Error::InvalidConfig(
+                "the wake-up light is not configured".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
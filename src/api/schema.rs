@@ -0,0 +1,243 @@
+use std::sync::Arc;
+
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Value};
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    alarm::AlarmRequest,
+    alarm::AlarmStatus,
+    alarm::PreArmCheck,
+    announce::AnnounceRequest,
+    auth::SessionStatus,
+    barcode::BarcodeRequest,
+    events::EntityUpdate,
+    filters::ErrorResponse,
+    filters::{Access, Context},
+    ha::DiscoveredDomains,
+    irrigation::StartRequest,
+    lights::LightStatus,
+    panic::PanicCancelRequest,
+    rules::RuleTestRequest,
+    rules::RulesDocument,
+    rules::RulesImportResult,
+    rules::RulesSectionDiff,
+    rules::SchedulesPreview,
+    status::GroupedStatus,
+    status::Status,
+    status::StatusUpdate,
+    system::Liveness,
+    system::Readiness,
+    timers::SleepTimerRequest,
+    versions::ApiClientUsage,
+    versions::VersionInfo,
+    Api, ApiBool,
+};
+use crate::{
+    air_quality::AirQualityStatus,
+    announcements::Announcement,
+    audio::PlaySound,
+    auth::Credentials,
+    barcode::ScannedProduct,
+    changelog::EntityChange,
+    chaos::Faults,
+    chores::{Chore, ChoreUser, NewChore, WeeklyStats},
+    climate::{ClimateBoostStatus, HeatingSummary},
+    clock_skew::ClockSkewStatus,
+    dashboard::{DashboardConfig, LightConfig},
+    departures::StopDepartures,
+    disk::DiskStatus,
+    energy_meter::EnergyReadings,
+    esphome::EsphomeEntity,
+    fan::FanStatus,
+    gestures::DetectedGesture,
+    gpio_controller::{GpioHealth, PinStatus},
+    hazards::HazardAlert,
+    history::Execution,
+    home_assistant::{CallStats, DiscoveredEntity, Info, TrafficMessage},
+    indoor::IndoorStatus,
+    intercom::{IntercomAck, IntercomDelivery, IntercomMessage},
+    irrigation::{IrrigationSchedule, IrrigationStatus},
+    latency::LatencyStatus,
+    media_groups::MediaGroupStatus,
+    memory::MemoryStatus,
+    mqtt::zigbee2mqtt::ZigbeeDevice,
+    net_check::NetCheckReport,
+    network::NetworkStatus,
+    notifications::Notification,
+    nowcast::NowcastStatus,
+    panic::PanicStatus,
+    reminders::UpcomingReminder,
+    rules::engine::RuleTrace,
+    screen::{ScreenInfo, ScreenStatus},
+    screensaver::ScreensaverImage,
+    serial_devices::SerialDeviceStatus,
+    sleep_timer::Timer,
+    sound_level::SoundLevel,
+    tasks::TaskStatus,
+    theme::Theme,
+    thermostat::ThermostatStatus,
+    ups::UpsStatus,
+    users::{Favorite, User},
+    voice::VoiceEvent,
+    wakeup::{ActiveWakeup, WakeupAlarm, WakeupStatus},
+    weather_alerts::WeatherAlert,
+    windows::OpeningsStatus,
+};
+
+/// Register the request and response types of the API, by name.
+macro_rules! schemas {
+    ($gen:ident, $($ty:ty),* $(,)?) => {
+        $(
+            $gen.subschema_for::<$ty>();
+        )*
+    };
+}
+
+/// Get the JSON schema of the request and response types of the API, for
+/// the frontend to check its own types against.
+pub fn schema() -> Value {
+    let mut gen = SchemaSettings::draft07().into_generator();
+
+    schemas!(
+        gen,
+        // Requests.
+        AlarmRequest,
+        AnnounceRequest,
+        ApiBool,
+        BarcodeRequest,
+        ChoreUser,
+        Credentials,
+        DashboardConfig,
+        Faults,
+        IntercomMessage,
+        IrrigationSchedule,
+        LightConfig,
+        NewChore,
+        PanicCancelRequest,
+        PlaySound,
+        RuleTestRequest,
+        RulesDocument,
+        SleepTimerRequest,
+        StartRequest,
+        WakeupAlarm,
+        // Responses.
+        ActiveWakeup,
+        AirQualityStatus,
+        AlarmStatus,
+        Announcement,
+        ApiClientUsage,
+        CallStats,
+        Chore,
+        ClimateBoostStatus,
+        ClockSkewStatus,
+        DetectedGesture,
+        DiscoveredDomains,
+        DiscoveredEntity,
+        DiskStatus,
+        EnergyReadings,
+        EntityChange,
+        EntityUpdate,
+        ErrorResponse,
+        EsphomeEntity,
+        Execution,
+        FanStatus,
+        Favorite,
+        GpioHealth,
+        GroupedStatus,
+        HazardAlert,
+        HeatingSummary,
+        IndoorStatus,
+        Info,
+        IntercomAck,
+        IntercomDelivery,
+        IrrigationStatus,
+        LatencyStatus,
+        LightStatus,
+        Liveness,
+        MediaGroupStatus,
+        MemoryStatus,
+        NetCheckReport,
+        NetworkStatus,
+        Notification,
+        NowcastStatus,
+        OpeningsStatus,
+        PanicStatus,
+        PinStatus,
+        PreArmCheck,
+        Readiness,
+        RuleTrace,
+        RulesImportResult,
+        RulesSectionDiff,
+        ScannedProduct,
+        SchedulesPreview,
+        ScreenInfo,
+        ScreenStatus,
+        ScreensaverImage,
+        SerialDeviceStatus,
+        SessionStatus,
+        SoundLevel,
+        Status,
+        StatusUpdate<Status>,
+        StatusUpdate<GroupedStatus>,
+        StopDepartures,
+        TaskStatus,
+        Theme,
+        ThermostatStatus,
+        Timer,
+        TrafficMessage,
+        UpcomingReminder,
+        UpsStatus,
+        User,
+        VersionInfo,
+        VoiceEvent,
+        WakeupStatus,
+        WeatherAlert,
+        WeeklyStats,
+        ZigbeeDevice,
+    );
+
+    json!({
+        "$schema": gen.settings().meta_schema,
+        "definitions": gen.take_definitions(),
+    })
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("schema")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_schema_get)
+}
+
+impl Api {
+    async fn api_schema_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&schema()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_matches_snapshot() {
+        let schema = serde_json::to_string_pretty(&schema()).unwrap() + "\n";
+
+        // Regenerate the snapshot with `UPDATE_SCHEMA=1 cargo test`, and
+        // update the frontend types accordingly.
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::write(
+                std::path::Path::new(file!()).with_file_name("schema.json"),
+                &schema,
+            )
+            .unwrap();
+        } else {
+            assert!(
+                include_str!("schema.json") == schema,
+                "the API schema changed: run `UPDATE_SCHEMA=1 cargo test` and update the frontend"
+            );
+        }
+    }
+}
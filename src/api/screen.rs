@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use log::error;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api, ApiBool,
+};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_screen_get = warp::path!("screen")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_screen_get);
+
+    let api_screen_set = warp::path!("screen")
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "screen", 8))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, on| permit.hold(Api::api_screen_set(api, on)));
+
+    let api_screen_info_get = warp::path!("screen" / "info")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_screen_info_get);
+
+    let api_screensaver_next = warp::path!("screensaver" / "next")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_screensaver_next);
+
+    let api_screensaver_image = warp::path!("screensaver" / "images" / String)
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|name, api: Arc<Api>| async move { api.api_screensaver_image(name).await });
+
+    api_screen_get
+        .or(api_screen_set)
+        .or(api_screen_info_get)
+        .or(api_screensaver_next)
+        .or(api_screensaver_image)
+}
+
+impl Api {
+    async fn api_screen_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.context.screen.status()))
+    }
+
+    /// Force the screen on or off, until the override times out.
+    async fn api_screen_set(self: Arc<Self>, status: ApiBool) -> Result<impl Reply, Rejection> {
+        let screen = &self.context.screen;
+
+        screen.force_on(status.into()).map_err(|err| {
+            error!("failed to set the screen: {}", err);
+            warp::reject::custom(crate::Error::from(err))
+        })?;
+
+        Ok(warp::reply::json(&screen.status()))
+    }
+
+    async fn api_screen_info_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let info = self.context.screen.info().map_err(|err| {
+            error!("failed to read the display information: {}", err);
+            warp::reject::custom(crate::Error::from(err))
+        })?;
+
+        Ok(warp::reply::json(
+            &info.ok_or_else(warp::reject::not_found)?,
+        ))
+    }
+
+    /// Get the next image of the photo frame, or nothing if the directory
+    /// has none.
+    async fn api_screensaver_next(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let screensaver = self
+            .screensaver
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+        let image = screensaver.next().await.map_err(|err| {
+            error!("failed to get the next screensaver image: {:#}", err);
+            warp::reject::custom(crate::Error::from(err))
+        })?;
+
+        Ok(warp::reply::json(&image))
+    }
+
+    async fn api_screensaver_image(self: Arc<Self>, name: String) -> Result<impl Reply, Rejection> {
+        let screensaver = self
+            .screensaver
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+        let (data, content_type) = screensaver
+            .image(&name)
+            .await
+            .map_err(|err| {
+                error!("failed to read the screensaver image `{}`: {:#}", name, err);
+                warp::reject::custom(crate::Error::from(err))
+            })?
+            .ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::with_header(
+            warp::reply::Response::new(data.into()),
+            "content-type",
+            content_type,
+        ))
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use log::error;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_sensors_serial_get = warp::path!("sensors" / "serial")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_sensors_serial_get);
+
+    let api_sensors_serial_device_get = warp::path!("sensors" / "serial" / String)
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(
+            |name, api: Arc<Api>| async move { api.api_sensors_serial_device_get(name).await },
+        );
+
+    let api_serial_send = warp::path!("serial" / String / String)
+        .and(ctx.control_route(Method::POST, Access::Session))
+        .and_then(|name, command, api: Arc<Api>| async move {
+            api.api_serial_send(name, command).await
+        });
+
+    api_sensors_serial_get
+        .or(api_sensors_serial_device_get)
+        .or(api_serial_send)
+}
+
+impl Api {
+    async fn api_sensors_serial_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.serial_devices.statuses().await))
+    }
+
+    async fn api_sensors_serial_device_get(
+        self: Arc<Self>,
+        name: String,
+    ) -> Result<impl Reply, Rejection> {
+        let status = self
+            .serial_devices
+            .status(&name)
+            .await
+            .ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&status))
+    }
+
+    /// Send a command to a serial device, by name.
+    async fn api_serial_send(
+        self: Arc<Self>,
+        name: String,
+        command: String,
+    ) -> Result<impl Reply, Rejection> {
+        self.serial_devices
+            .send(&name, &command)
+            .await
+            .ok_or_else(warp::reject::not_found)?
+            .map_err(|err| {
+                error!("failed to send `{}` to `{}`: {:#}", command, name, err);
+                warp::reject::custom(crate::Error::from(err))
+            })?;
+
+        Ok(warp::reply::json(&command))
+    }
+}
@@ -0,0 +1,831 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, Utc};
+use log::error;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+use crate::{
+    air_quality::AirQualityStatus,
+    apparent_temperature::{apparent_temperature, WeatherUnits},
+    astronomy::AstronomyStatus,
+    comfort::ComfortStatus,
+    config::HomeControlConfig,
+    extra_sensors::ExtraSensorStatus,
+    forecast::{ForecastCache, TodaySummary},
+    frost::FrostForecast,
+    home_assistant::{self, IntegrationStatus},
+    indoor::IndoorStatus,
+    notifications::Notification,
+    presence::Proximity,
+    reminders::{self, UpcomingReminder},
+    rfid::IdentifiedUser,
+    weather_conditions::WeatherCondition,
+    windows, Result,
+};
+
+/// The blocks the status may contain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusBlock {
+    /// The current weather, the forecast and the astronomy.
+    Weather,
+
+    /// The open windows, the indoor and air quality readings and the comfort.
+    Climate,
+
+    /// The extra sensors.
+    Sensors,
+
+    /// The due reminders.
+    Agenda,
+
+    /// The user identified by the RFID reader.
+    Presence,
+    Notifications,
+
+    /// The integrations providing the configured entities.
+    System,
+}
+
+impl StatusBlock {
+    const ALL: &'static [Self] = &[
+        Self::Weather,
+        Self::Climate,
+        Self::Sensors,
+        Self::Agenda,
+        Self::Presence,
+        Self::Notifications,
+        Self::System,
+    ];
+}
+
+/// The structure of the status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "u8")]
+pub enum StatusVersion {
+    /// All the fields at the top level.
+    #[default]
+    V1,
+
+    /// The fields grouped by block.
+    V2,
+}
+
+impl TryFrom<u8> for StatusVersion {
+    type Error = String;
+
+    fn try_from(value: u8) -> std::result::Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            _ => Err(format!("unsupported status version {}", value)),
+        }
+    }
+}
+
+/// The composition of the status, which slim panels can reduce to what they
+/// display.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StatusConfig {
+    /// The blocks of the status. All of them if unspecified.
+    #[serde(default)]
+    pub blocks: Option<BTreeSet<StatusBlock>>,
+
+    #[serde(default)]
+    pub version: StatusVersion,
+}
+
+impl StatusConfig {
+    pub fn has(&self, block: StatusBlock) -> bool {
+        match &self.blocks {
+            Some(blocks) => blocks.contains(&block),
+            None => true,
+        }
+    }
+
+    fn blocks(&self) -> impl Iterator<Item = StatusBlock> + '_ {
+        StatusBlock::ALL
+            .iter()
+            .copied()
+            .filter(move |block| self.has(*block))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum Status {
+    Disconnected(Box<DisconnectedStatus>),
+    Connected(Box<ConnectedStatus>),
+}
+
+/// The status while disconnected from Home-Assistant, with the last known
+/// weather so that it does not blank out, if recent enough and configured.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DisconnectedStatus {
+    #[serde(flatten)]
+    pub weather: Option<WeatherBlock>,
+}
+
+/// The status with the fields of the blocks at the top level, omitting the
+/// blocks that are not configured.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectedStatus {
+    pub location: String,
+    #[serde(flatten)]
+    pub weather: Option<WeatherBlock>,
+    #[serde(flatten)]
+    pub climate: Option<ClimateBlock>,
+    #[serde(flatten)]
+    pub sensors: Option<SensorsBlock>,
+    #[serde(flatten)]
+    pub agenda: Option<AgendaBlock>,
+    #[serde(flatten)]
+    pub presence: Option<PresenceBlock>,
+    #[serde(flatten)]
+    pub notifications: Option<NotificationsBlock>,
+    #[serde(flatten)]
+    pub system: Option<SystemBlock>,
+}
+
+/// The status with the fields grouped by block, as of the version 2.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum GroupedStatus {
+    Disconnected(Box<GroupedDisconnectedStatus>),
+    Connected(Box<GroupedConnectedStatus>),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupedDisconnectedStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weather: Option<WeatherBlock>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupedConnectedStatus {
+    pub location: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weather: Option<WeatherBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub climate: Option<ClimateBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sensors: Option<SensorsBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agenda: Option<AgendaBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence: Option<PresenceBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<NotificationsBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<SystemBlock>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherBlock {
+    pub weather_current: WeatherStatus,
+    pub weather_forecast: WeatherStatus,
+
+    /// The forecast for the rest of the day, if there is any.
+    pub weather_today: Option<TodaySummary>,
+
+    /// Whether frost is likely on the windshields over the coming night, if
+    /// enabled.
+    pub frost: Option<FrostForecast>,
+    pub astronomy: Option<AstronomyStatus>,
+
+    /// Whether this is the last known weather, served while it cannot be
+    /// computed again, like while the weather entity is unavailable or
+    /// Home-Assistant is disconnected.
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClimateBlock {
+    pub window_open_rooms: Vec<String>,
+    pub indoor: Option<IndoorStatus>,
+    pub air_quality: Option<AirQualityStatus>,
+
+    /// The combined comfort of the indoor readings.
+    pub comfort: Option<ComfortStatus>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SensorsBlock {
+    pub extra_sensors: Vec<ExtraSensorStatus>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AgendaBlock {
+    pub due_reminders: Vec<UpcomingReminder>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceBlock {
+    pub identified_user: Option<IdentifiedUser>,
+
+    /// How far the user is from the screen, if the distance sensor works.
+    pub proximity: Option<Proximity>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationsBlock {
+    pub notifications: Vec<Notification>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemBlock {
+    /// The integrations providing the configured entities.
+    pub integrations: Vec<IntegrationStatus>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherStatus {
+    pub timestamp: DateTime<Utc>,
+    pub state: String,
+
+    /// How to display the state.
+    pub condition: WeatherCondition,
+    pub humidity: Option<f64>,
+    pub pressure: Option<f64>,
+    pub temperature: f64,
+
+    /// The "feels like" temperature, from the wind chill or the heat index.
+    pub apparent_temperature: f64,
+    pub wind_speed: f64,
+    pub wind_bearing: f64,
+}
+
+impl Status {
+    /// Compute the status, with only the configured blocks.
+    ///
+    /// The notifications, the identified user and the integrations are only
+    /// used when their blocks are configured.
+    pub fn new(
+        ha_status: home_assistant::Status,
+        home_control_config: &HomeControlConfig,
+        notifications: Vec<Notification>,
+        identified_user: Option<IdentifiedUser>,
+        proximity: Option<Proximity>,
+        integrations: Vec<IntegrationStatus>,
+        forecast_cache: &ForecastCache<WeatherBlock>,
+    ) -> Result<Self> {
+        let entities = match ha_status {
+            home_assistant::Status::Disconnected => {
+                let weather = home_control_config
+                    .status
+                    .has(StatusBlock::Weather)
+                    .then(|| forecast_cache.last())
+                    .flatten()
+                    .map(|forecast| WeatherBlock {
+                        stale: forecast.stale,
+                        ..forecast.value
+                    });
+
+                return Ok(Status::Disconnected(Box::new(DisconnectedStatus {
+                    weather,
+                })));
+            }
+            home_assistant::Status::Connected { entities } => entities,
+        };
+        let mut status = ConnectedStatus {
+            location: home_control_config.location.clone(),
+            weather: None,
+            climate: None,
+            sensors: None,
+            agenda: None,
+            presence: None,
+            notifications: None,
+            system: None,
+        };
+
+        for block in home_control_config.status.blocks() {
+            match block {
+                StatusBlock::Weather => {
+                    let forecast = forecast_cache
+                        .get(entities.get(&home_control_config.weather_entity), || {
+                            WeatherBlock::new(&entities, home_control_config)
+                        })?;
+
+                    status.weather = Some(WeatherBlock {
+                        stale: forecast.stale,
+                        ..forecast.value
+                    })
+                }
+                StatusBlock::Climate => {
+                    let indoor = home_control_config
+                        .indoor
+                        .as_ref()
+                        .and_then(|indoor| indoor.status(&entities));
+                    let air_quality = home_control_config
+                        .air_quality
+                        .as_ref()
+                        .map(|air_quality| air_quality.status(&entities));
+
+                    status.climate = Some(ClimateBlock {
+                        window_open_rooms: windows::rooms_with_window_open(
+                            &home_control_config.windows,
+                            &entities,
+                        ),
+                        comfort: home_control_config
+                            .comfort
+                            .status(indoor.as_ref(), air_quality.as_ref()),
+                        indoor,
+                        air_quality,
+                    });
+                }
+                StatusBlock::Sensors => {
+                    status.sensors = Some(SensorsBlock {
+                        extra_sensors: home_control_config
+                            .extra_sensors
+                            .iter()
+                            .map(|sensor| sensor.status(&entities))
+                            .collect(),
+                    })
+                }
+                StatusBlock::Agenda => {
+                    status.agenda = Some(AgendaBlock {
+                        due_reminders: reminders::upcoming(
+                            &home_control_config.reminders,
+                            &entities,
+                        )
+                        .into_iter()
+                        .filter(|reminder| reminder.due)
+                        .collect(),
+                    })
+                }
+                StatusBlock::Presence => {
+                    status.presence = Some(PresenceBlock {
+                        identified_user: identified_user.clone(),
+                        proximity,
+                    })
+                }
+                StatusBlock::Notifications => {
+                    status.notifications = Some(NotificationsBlock {
+                        notifications: notifications.clone(),
+                    })
+                }
+                StatusBlock::System => {
+                    status.system = Some(SystemBlock {
+                        integrations: integrations.clone(),
+                    })
+                }
+            }
+        }
+
+        Ok(Status::Connected(Box::new(status)))
+    }
+
+    /// Group the fields of the status by block.
+    pub fn grouped(self) -> GroupedStatus {
+        match self {
+            Self::Disconnected(status) => {
+                GroupedStatus::Disconnected(Box::new(GroupedDisconnectedStatus {
+                    weather: status.weather,
+                }))
+            }
+            Self::Connected(status) => {
+                let ConnectedStatus {
+                    location,
+                    weather,
+                    climate,
+                    sensors,
+                    agenda,
+                    presence,
+                    notifications,
+                    system,
+                } = *status;
+
+                GroupedStatus::Connected(Box::new(GroupedConnectedStatus {
+                    location,
+                    weather,
+                    climate,
+                    sensors,
+                    agenda,
+                    presence,
+                    notifications,
+                    system,
+                }))
+            }
+        }
+    }
+}
+
+impl WeatherBlock {
+    pub(super) fn new(
+        entities: &HashMap<String, home_assistant::State>,
+        home_control_config: &HomeControlConfig,
+    ) -> Result<Self> {
+        // Only the weather entity is copied: the others are borrowed.
+        let weather_state: home_assistant::WeatherState = entities
+            .get(&home_control_config.weather_entity)
+            .cloned()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Weather entity `{}` was not found",
+                    home_control_config.weather_entity
+                )
+            })?
+            .try_into()?;
+
+        let weather_today = TodaySummary::new(&weather_state.attributes.forecast, Local::now());
+        let frost = home_control_config
+            .frost
+            .as_ref()
+            .and_then(|frost| FrostForecast::new(frost, &weather_state, Local::now()));
+        let first_forecast = weather_state
+            .attributes
+            .forecast
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No forecast found"))?;
+
+        let conditions = &home_control_config.weather_conditions;
+        let units = WeatherUnits {
+            temperature: weather_state.attributes.temperature_unit.as_deref(),
+            wind_speed: weather_state.attributes.wind_speed_unit.as_deref(),
+        };
+        let feels_like = |temperature, humidity, wind_speed| {
+            let apparent = apparent_temperature(temperature, humidity, wind_speed, units);
+
+            (apparent * 10.0).round() / 10.0
+        };
+        let weather_current = WeatherStatus {
+            timestamp: weather_state.last_changed,
+            condition: conditions.display(&weather_state.state),
+            state: weather_state.state,
+            humidity: Some(weather_state.attributes.humidity),
+            pressure: Some(weather_state.attributes.pressure),
+            temperature: weather_state.attributes.temperature,
+            apparent_temperature: feels_like(
+                weather_state.attributes.temperature,
+                Some(weather_state.attributes.humidity),
+                weather_state.attributes.wind_speed,
+            ),
+            wind_speed: weather_state.attributes.wind_speed,
+            wind_bearing: weather_state.attributes.wind_bearing,
+        };
+        let weather_forecast = WeatherStatus {
+            timestamp: first_forecast.datetime,
+            condition: conditions.display(&first_forecast.condition),
+            state: first_forecast.condition,
+            humidity: None,
+            pressure: None,
+            temperature: first_forecast.temperature,
+            apparent_temperature: feels_like(
+                first_forecast.temperature,
+                None,
+                first_forecast.wind_speed,
+            ),
+            wind_speed: first_forecast.wind_speed,
+            wind_bearing: first_forecast.wind_bearing,
+        };
+
+        Ok(Self {
+            weather_current,
+            weather_forecast,
+            weather_today,
+            frost,
+            astronomy: home_control_config
+                .astronomy
+                .as_ref()
+                .map(|astronomy| astronomy.status(Utc::now())),
+            stale: false,
+        })
+    }
+}
+
+/// How long a status wait lasts at most.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct WaitQuery {
+    /// The generation of the status the client has, if any.
+    #[serde(default)]
+    since: Option<u64>,
+
+    /// How long to wait for a change, in seconds.
+    #[serde(default = "WaitQuery::default_timeout")]
+    timeout: f64,
+}
+
+impl WaitQuery {
+    fn default_timeout() -> f64 {
+        30.0
+    }
+}
+
+/// The status, with its generation.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusUpdate<S> {
+    pub generation: u64,
+    pub status: S,
+}
+
+/// The routes of the version 2, in which the status is grouped by block.
+pub(super) fn routes_v2(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_v2_status_get = warp::path!("status")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_v2_status_get);
+
+    let api_v2_status_wait_get = warp::path!("status" / "wait")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::query())
+        .and_then(Api::api_v2_status_wait_get);
+
+    api_v2_status_get.or(api_v2_status_wait_get)
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_status_get = warp::path!("status")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_status_get);
+
+    let api_status_wait_get = warp::path!("status" / "wait")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::query())
+        .and_then(Api::api_status_wait_get);
+
+    let api_notifications_get = warp::path!("notifications")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_notifications_get);
+
+    let api_sensors_indoor_get = warp::path!("sensors" / "indoor")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_sensors_indoor_get);
+
+    let api_sensors_sound_get = warp::path!("sensors" / "sound")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_sensors_sound_get);
+
+    let api_sensors_ups_get = warp::path!("sensors" / "ups")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_sensors_ups_get);
+
+    let api_energy_get = warp::path!("energy")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_energy_get);
+
+    let api_air_get = warp::path!("air")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_air_get);
+
+    let api_reminders_upcoming_get = warp::path!("reminders" / "upcoming")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_reminders_upcoming_get);
+
+    let api_departures_get = warp::path!("departures")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_departures_get);
+
+    api_status_get
+        .or(api_status_wait_get)
+        .or(api_notifications_get)
+        .or(api_sensors_indoor_get)
+        .or(api_sensors_sound_get)
+        .or(api_sensors_ups_get)
+        .or(api_energy_get)
+        .or(api_air_get)
+        .or(api_reminders_upcoming_get)
+        .or(api_departures_get)
+}
+
+impl Api {
+    async fn api_status_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let status = self.status().await?;
+
+        Ok(match self.context.config.status.version {
+            StatusVersion::V1 => warp::reply::json(&status),
+            StatusVersion::V2 => warp::reply::json(&status.grouped()),
+        })
+    }
+
+    async fn api_v2_status_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.status().await?.grouped()))
+    }
+
+    /// Wait for the status to change from the generation the client has,
+    /// as a fallback for the clients that cannot use the server-sent events.
+    async fn api_status_wait_get(
+        self: Arc<Self>,
+        query: WaitQuery,
+    ) -> Result<impl Reply, Rejection> {
+        let generation = self.wait_for_changes(&query).await;
+        let status = self.status().await?;
+
+        Ok(match self.context.config.status.version {
+            StatusVersion::V1 => warp::reply::json(&StatusUpdate { generation, status }),
+            StatusVersion::V2 => warp::reply::json(&StatusUpdate {
+                generation,
+                status: status.grouped(),
+            }),
+        })
+    }
+
+    async fn api_v2_status_wait_get(
+        self: Arc<Self>,
+        query: WaitQuery,
+    ) -> Result<impl Reply, Rejection> {
+        let generation = self.wait_for_changes(&query).await;
+
+        Ok(warp::reply::json(&StatusUpdate {
+            generation,
+            status: self.status().await?.grouped(),
+        }))
+    }
+
+    /// Wait for the generation of the status to differ from the one of the
+    /// client, or for the timeout, and get the current generation.
+    async fn wait_for_changes(&self, query: &WaitQuery) -> u64 {
+        let mut states = self.context.home_assistant.watch_states();
+        let mut proximity = self.context.presence.proximity.subscribe();
+        let mut notifications = self.notifications.watch();
+
+        states.mark_unchanged();
+
+        let timeout = Duration::try_from_secs_f64(query.timeout)
+            .unwrap_or_default()
+            .min(MAX_WAIT);
+
+        if query.since == Some(self.status_generation()) {
+            let _ = tokio::time::timeout(timeout, async {
+                tokio::select! {
+                    _ = states.changed() => {}
+                    _ = proximity.changed() => {}
+                    _ = notifications.changed() => {}
+                }
+            })
+            .await;
+        }
+
+        self.status_generation()
+    }
+
+    /// The generation of the status, which changes with the states, the
+    /// proximity and the notifications.
+    pub(super) fn status_generation(&self) -> u64 {
+        self.context.home_assistant.states_generation()
+            + self
+                .context
+                .presence
+                .proximity_changes
+                .load(Ordering::Relaxed)
+            + self.notifications.generation()
+    }
+
+    pub(super) async fn status(&self) -> Result<Status, Rejection> {
+        let config = &self.context.config.status;
+        let ha_status = self.context.home_assistant.status().await;
+        let notifications = if config.has(StatusBlock::Notifications) {
+            self.notifications.list().await
+        } else {
+            Vec::new()
+        };
+        let identified_user = match (&self.rfid, config.has(StatusBlock::Presence)) {
+            (Some(rfid), true) => rfid.identified_user().await,
+            _ => None,
+        };
+        let integrations = if config.has(StatusBlock::System) {
+            let entity_ids = self.context.editable.entity_ids().await.clone();
+
+            self.context
+                .home_assistant
+                .integrations(entity_ids.iter().map(String::as_str))
+                .await
+        } else {
+            Vec::new()
+        };
+        let proximity = *self.context.presence.proximity.borrow();
+
+        Status::new(
+            ha_status,
+            &self.context.config,
+            notifications,
+            identified_user,
+            proximity,
+            integrations,
+            &self.forecast_cache,
+        )
+        .map_err(|err| {
+            error!("failed to get status: {}", err);
+
+            err.into()
+        })
+    }
+
+    async fn api_notifications_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let notifications = self.notifications.list().await;
+
+        Ok(warp::reply::json(&notifications))
+    }
+
+    async fn api_sensors_indoor_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let indoor_config = self
+            .context
+            .config
+            .indoor
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+
+        let status = match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => indoor_config.status(&entities),
+            home_assistant::Status::Disconnected => None,
+        };
+
+        Ok(warp::reply::json(&status))
+    }
+
+    async fn api_sensors_sound_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let sound_level = self
+            .sound_level
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&sound_level.level().await))
+    }
+
+    async fn api_sensors_ups_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let ups = self.ups.as_ref().ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&ups.status().await))
+    }
+
+    async fn api_energy_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let energy_meter = self
+            .energy_meter
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&energy_meter.readings().await))
+    }
+
+    async fn api_air_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let air_quality_config = self
+            .context
+            .config
+            .air_quality
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+
+        let status = match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => {
+                Some(air_quality_config.status(&entities))
+            }
+            home_assistant::Status::Disconnected => None,
+        };
+
+        Ok(warp::reply::json(&status))
+    }
+
+    async fn api_reminders_upcoming_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let entities = match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => entities,
+            home_assistant::Status::Disconnected => Default::default(),
+        };
+        let upcoming = reminders::upcoming(&self.context.config.reminders, &entities);
+
+        Ok(warp::reply::json(&upcoming))
+    }
+
+    async fn api_departures_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let departures = self
+            .departures
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+        let entities = match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => entities,
+            home_assistant::Status::Disconnected => Default::default(),
+        };
+
+        Ok(warp::reply::json(&departures.next(&entities).await))
+    }
+}
+
+#[cfg(test)]
+mod tests;
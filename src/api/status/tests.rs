@@ -0,0 +1,127 @@
+//! Tests of the status.
+
+use serde_json::json;
+
+use super::*;
+use crate::forecast::ForecastCacheConfig;
+
+fn config() -> HomeControlConfig {
+    serde_json::from_value(json!({
+        "location": "Home",
+        "weather_entity": "weather.home",
+        "status": {"blocks": ["weather"]},
+    }))
+    .unwrap()
+}
+
+fn connected() -> home_assistant::Status {
+    let now = Utc::now();
+    let weather: home_assistant::State = serde_json::from_value(json!({
+        "entity_id": "weather.home",
+        "state": "sunny",
+        "last_changed": now,
+        "last_updated": now,
+        "attributes": {
+            "friendly_name": "Home",
+            "humidity": 45.0,
+            "pressure": 1013.0,
+            "temperature": 21.0,
+            "wind_bearing": 180.0,
+            "wind_speed": 10.0,
+            "forecast": [{
+                "condition": "sunny",
+                "datetime": now + chrono::Duration::hours(1),
+                "precipitation": 0.0,
+                "temperature": 22.0,
+                "templow": 12.0,
+                "wind_bearing": 180.0,
+                "wind_speed": 10.0,
+            }],
+        },
+    }))
+    .unwrap();
+
+    home_assistant::Status::Connected {
+        entities: Arc::new(HashMap::from([(weather.entity_id.clone(), weather)])),
+    }
+}
+
+fn status(
+    ha_status: home_assistant::Status,
+    forecast_cache: &ForecastCache<WeatherBlock>,
+) -> Status {
+    Status::new(
+        ha_status,
+        &config(),
+        Vec::new(),
+        None,
+        None,
+        Vec::new(),
+        forecast_cache,
+    )
+    .unwrap()
+}
+
+#[test]
+fn the_last_weather_is_served_while_disconnected() {
+    let forecast_cache = ForecastCache::new(ForecastCacheConfig::default());
+
+    match status(home_assistant::Status::Disconnected, &forecast_cache) {
+        Status::Disconnected(status) => assert!(status.weather.is_none()),
+        status => panic!("unexpected status: {:?}", status),
+    }
+
+    match status(connected(), &forecast_cache) {
+        Status::Connected(status) => assert!(!status.weather.unwrap().stale),
+        status => panic!("unexpected status: {:?}", status),
+    }
+
+    match status(home_assistant::Status::Disconnected, &forecast_cache) {
+        Status::Disconnected(status) => {
+            let weather = status.weather.unwrap();
+
+            assert!(weather.stale);
+            assert_eq!(weather.weather_current.temperature, 21.0);
+        }
+        status => panic!("unexpected status: {:?}", status),
+    }
+}
+
+#[test]
+fn the_last_weather_expires() {
+    let forecast_cache = ForecastCache::new(ForecastCacheConfig {
+        max_stale: Duration::ZERO,
+        ..Default::default()
+    });
+
+    status(connected(), &forecast_cache);
+
+    match status(home_assistant::Status::Disconnected, &forecast_cache) {
+        Status::Disconnected(status) => assert!(status.weather.is_none()),
+        status => panic!("unexpected status: {:?}", status),
+    }
+}
+
+#[test]
+fn the_disconnected_status_is_flat() {
+    let forecast_cache = ForecastCache::new(ForecastCacheConfig::default());
+
+    status(connected(), &forecast_cache);
+
+    let disconnected = status(home_assistant::Status::Disconnected, &forecast_cache);
+    let v1 = serde_json::to_value(&disconnected).unwrap();
+    let v2 = serde_json::to_value(disconnected.grouped()).unwrap();
+
+    assert_eq!(v1["status"], "disconnected");
+    assert_eq!(v1["weatherCurrent"]["temperature"], 21.0);
+    assert_eq!(v1["stale"], true);
+    assert_eq!(v2["weather"]["weatherCurrent"]["temperature"], 21.0);
+    assert_eq!(
+        serde_json::to_value(status(
+            home_assistant::Status::Disconnected,
+            &ForecastCache::new(ForecastCacheConfig::default())
+        ))
+        .unwrap(),
+        json!({"status": "disconnected"})
+    );
+}
@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use warp::{
+    http::{Method, StatusCode},
+    Filter, Rejection, Reply,
+};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+use crate::{
+    memory::MemoryStatus, net_check, network::NetworkConfig, self_check::SelfCheckReport,
+    tasks::TaskState,
+};
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Liveness {
+    /// Whether no task failed.
+    pub alive: bool,
+    pub failed_tasks: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Readiness {
+    /// Whether the self-check passed, or is disabled.
+    pub ready: bool,
+    pub self_check: Option<SelfCheckReport>,
+}
+
+/// The liveness probe, which fails once a task failed: the process should
+/// then be restarted.
+pub(super) fn healthz(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("healthz")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_healthz)
+}
+
+/// The readiness probe, which fails until the startup self-check passes.
+pub(super) fn readyz(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("readyz")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_readyz)
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_system_network_get = warp::path!("system" / "network")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_system_network_get);
+
+    let api_system_tasks_get = warp::path!("system" / "tasks")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_system_tasks_get);
+
+    let api_system_gpio_get = warp::path!("system" / "gpio")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_system_gpio_get);
+
+    let api_system_home_assistant_get = warp::path!("system" / "home_assistant")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_system_home_assistant_get);
+
+    let api_system_memory_get = warp::path!("system" / "memory")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_system_memory_get);
+
+    let api_system_clock_get = warp::path!("system" / "clock")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_system_clock_get);
+
+    let api_system_net_check = warp::path!("system" / "net-check")
+        .and(ctx.route(Method::POST, Access::Session))
+        .and_then(Api::api_system_net_check);
+
+    let api_system_fan_get = warp::path!("system" / "fan")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_system_fan_get);
+
+    let api_system_disk_get = warp::path!("system" / "disk")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_system_disk_get);
+
+    let api_system_latency_get = warp::path!("system" / "latency")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_system_latency_get);
+
+    let api_kiosk_reload = warp::path!("kiosk" / "reload")
+        .and(ctx.route(Method::POST, Access::Settings))
+        .and_then(Api::api_kiosk_reload);
+
+    api_system_network_get
+        .or(api_system_tasks_get)
+        .or(api_system_gpio_get)
+        .or(api_system_home_assistant_get)
+        .or(api_system_memory_get)
+        .or(api_system_clock_get)
+        .or(api_system_fan_get)
+        .or(api_system_disk_get)
+        .or(api_system_latency_get)
+        .or(api_system_net_check)
+        .or(api_kiosk_reload)
+}
+
+impl Api {
+    async fn api_system_network_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let network = self.network.as_ref().ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&network.status().await))
+    }
+
+    async fn api_system_tasks_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.context.tasks.status()))
+    }
+
+    async fn api_system_gpio_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.context.gpio.health()))
+    }
+
+    async fn api_system_home_assistant_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.context.home_assistant.call_stats()))
+    }
+
+    async fn api_system_memory_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(
+            &MemoryStatus::read(&self.context.home_assistant).await,
+        ))
+    }
+
+    async fn api_system_clock_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.clock_skew.status()))
+    }
+
+    async fn api_system_fan_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let fan = self.fan.as_ref().ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&fan.status().await))
+    }
+
+    async fn api_system_disk_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.disk.status().await))
+    }
+
+    /// The 95th percentiles of the latency of the API endpoints and of the
+    /// calls to Home-Assistant, against their budgets.
+    async fn api_system_latency_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.context.metrics.latency.statuses()))
+    }
+
+    /// Diagnose the connection to Home-Assistant, like when the panel is
+    /// disconnected.
+    async fn api_system_net_check(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let interface = match &self.network {
+            Some(network) => network.config().interface.clone(),
+            None => NetworkConfig::default_interface(),
+        };
+
+        Ok(warp::reply::json(
+            &net_check::run(
+                &self.context.config.net_check,
+                &interface,
+                &self.context.home_assistant,
+            )
+            .await,
+        ))
+    }
+
+    /// Restart the kiosk browser, like after a frontend update.
+    async fn api_kiosk_reload(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let kiosk = self.kiosk.as_ref().ok_or_else(warp::reject::not_found)?;
+
+        kiosk.reload();
+
+        Ok(warp::reply::json(&true))
+    }
+
+    async fn api_healthz(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let failed_tasks: Vec<_> = self
+            .context
+            .tasks
+            .status()
+            .into_iter()
+            .filter(|task| task.state == TaskState::Failed)
+            .map(|task| task.name)
+            .collect();
+        let alive = failed_tasks.is_empty();
+        let status = if alive {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&Liveness {
+                alive,
+                failed_tasks,
+            }),
+            status,
+        ))
+    }
+
+    async fn api_readyz(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let self_check = self.self_check.read().await.clone();
+        let ready = match &self_check {
+            Some(report) => report.passed,
+            None => self.context.config.self_check.is_none(),
+        };
+        let status = if ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&Readiness { ready, self_check }),
+            status,
+        ))
+    }
+}
@@ -0,0 +1,52 @@
+//! The API of the route tests, without Home-Assistant.
+
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use super::Api;
+use crate::{
+    config::{GpioConfig, HomeControlConfig},
+    context::AppContext,
+    gpio_controller::GpioController,
+    home_assistant::Client,
+    overrides::EditableConfig,
+};
+
+/// Create an API with the configuration sections, and its Home-Assistant
+/// client, which is not running.
+pub(super) async fn api(sections: Value) -> (Arc<Api>, Client) {
+    let mut config = json!({
+        "location": "Home",
+        "weather_entity": "weather.home",
+    });
+
+    config
+        .as_object_mut()
+        .unwrap()
+        .extend(sections.as_object().unwrap().clone());
+
+    let config: HomeControlConfig = serde_json::from_value(config).unwrap();
+    let editable = EditableConfig::new(None, &config, Default::default());
+    let gpio = GpioController::new(GpioConfig {
+        red_led_pin: 1,
+        green_led_pin: 2,
+        buzzer_pin: 3,
+        trigger_pin: 4,
+        echo_pin: 5,
+    })
+    .unwrap();
+    let client = Client::new("localhost", String::new(), Default::default(), None, false)
+        .await
+        .unwrap();
+    let context = AppContext::new(
+        config,
+        Value::Null,
+        editable,
+        Arc::new(gpio),
+        client.new_controller(),
+    )
+    .unwrap();
+
+    (Api::new(context).unwrap(), client)
+}
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use chrono::Local;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("theme")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_theme_get)
+}
+
+impl Api {
+    /// Get the theme of the panels, which may depend on the time of day.
+    async fn api_theme_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let config = &self.context.config;
+
+        Ok(warp::reply::json(
+            &config.theme.theme(&Local::now(), config.astronomy.as_ref()),
+        ))
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_thermostats_get = warp::path!("thermostats")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_thermostats_get);
+
+    let api_thermostat_get = warp::path!("thermostats" / String)
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|name: String, api: Arc<Api>| async move { api.api_thermostat_get(name).await });
+
+    let api_thermostat_setpoint_set = warp::path!("thermostats" / String / "setpoint")
+        .and(ctx.limited_control_route(Method::PUT, Access::Session, "thermostats", 64))
+        .and(warp::body::json())
+        .and_then(|name: String, permit: Permit, api: Arc<Api>, setpoint| {
+            permit.hold(api.api_thermostat_setpoint_set(name, setpoint))
+        });
+
+    api_thermostats_get
+        .or(api_thermostat_get)
+        .or(api_thermostat_setpoint_set)
+}
+
+impl Api {
+    async fn api_thermostats_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.thermostats.statuses().await))
+    }
+
+    async fn api_thermostat_get(self: Arc<Self>, name: String) -> Result<impl Reply, Rejection> {
+        match self.thermostats.status(&name).await {
+            Some(status) => Ok(warp::reply::json(&status)),
+            None => Err(warp::reject::not_found()),
+        }
+    }
+
+    /// Set the target temperature, in °C, clamped to the allowed range.
+    async fn api_thermostat_setpoint_set(
+        self: Arc<Self>,
+        name: String,
+        setpoint: f64,
+    ) -> Result<impl Reply, Rejection> {
+        if !setpoint.is_finite() {
+            return Err(warp::reject::custom(crate::Error::InvalidConfig(
+                "the setpoint must be a number".to_string(),
+            )));
+        }
+
+        match self.thermostats.set_setpoint(&name, setpoint).await {
+            Some(status) => Ok(warp::reply::json(&status)),
+            None => Err(warp::reject::not_found()),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::{sync::Arc, time::Duration};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::sleep_timer::{SleepTimer, Timer};
+
+/// For how long a sleep timer can count down at most.
+const MAX_DURATION: Duration = Duration::from_secs(12 * 3600);
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub(super) struct SleepTimerRequest {
+    /// The countdown in seconds, up to 12 hours. Defaults to the configured
+    /// one.
+    #[serde(default)]
+    duration: Option<f64>,
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_timers_get = warp::path!("timers")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_timers_get);
+
+    let api_sleep_timer_start = warp::path!("sleep-timer")
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "timers", 64))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, request| {
+            permit.hold(Api::api_sleep_timer_start(api, request))
+        });
+
+    let api_sleep_timer_cancel = warp::path!("sleep-timer")
+        .and(ctx.control_route(Method::DELETE, Access::Session))
+        .and_then(Api::api_sleep_timer_cancel);
+
+    api_timers_get
+        .or(api_sleep_timer_start)
+        .or(api_sleep_timer_cancel)
+}
+
+impl Api {
+    fn sleep_timer(&self) -> Result<&SleepTimer, Rejection> {
+        self.sleep_timer
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)
+    }
+
+    /// List the countdowns in progress.
+    async fn api_timers_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let timers: Vec<Timer> = self
+            .sleep_timer
+            .as_ref()
+            .and_then(SleepTimer::current)
+            .into_iter()
+            .collect();
+
+        Ok(warp::reply::json(&timers))
+    }
+
+    /// Start the sleep timer, replacing the one in progress.
+    async fn api_sleep_timer_start(
+        self: Arc<Self>,
+        request: SleepTimerRequest,
+    ) -> Result<impl Reply, Rejection> {
+        let sleep_timer = self.sleep_timer()?;
+        let duration = match request.duration {
+            Some(duration) if !duration.is_finite() || duration <= 0.0 => {
+                return Err(warp::reject::custom(crate::Error::InvalidConfig(format!(
+                    "invalid duration: {}",
+                    duration
+                ))));
+            }
+            Some(duration) => Some(Duration::from_secs_f64(duration).min(MAX_DURATION)),
+            None => None,
+        };
+
+        Ok(warp::reply::json(&sleep_timer.start(duration)))
+    }
+
+    async fn api_sleep_timer_cancel(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        if !self.sleep_timer()?.cancel() {
+            return Err(warp::reject::not_found());
+        }
+
+        Ok(warp::reply::json(&true))
+    }
+}
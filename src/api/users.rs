@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::Deserialize;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+use crate::{
+    client_ip, home_assistant, usage,
+    users::{Favorite, User},
+};
+
+/// The default number of automatic favorites.
+const DEFAULT_AUTO_FAVORITES: usize = 8;
+
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct AutoFavoritesQuery {
+    /// The maximum number of favorites, 8 by default.
+    #[serde(default)]
+    limit: Option<usize>,
+
+    /// The user to keep the entities they may control of, if any.
+    #[serde(default)]
+    user: Option<String>,
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_users_get = warp::path!("users")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_users_get);
+
+    let api_user_favorites_get = warp::path!("users" / String / "favorites")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|id, api: Arc<Api>| async move { api.api_user_favorites_get(id).await });
+
+    let api_favorites_auto_get = warp::path!("favorites" / "auto")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and(warp::query())
+        .and_then(Api::api_favorites_auto_get);
+
+    api_users_get
+        .or(api_user_favorites_get)
+        .or(api_favorites_auto_get)
+}
+
+impl Api {
+    async fn api_users_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let users: Vec<User> = self.context.config.users.iter().map(Into::into).collect();
+
+        Ok(warp::reply::json(&users))
+    }
+
+    async fn api_user_favorites_get(self: Arc<Self>, id: String) -> Result<impl Reply, Rejection> {
+        let user = self
+            .context
+            .config
+            .users
+            .iter()
+            .find(|user| user.id == id)
+            .ok_or_else(warp::reject::not_found)?;
+        let entities = match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => entities,
+            home_assistant::Status::Disconnected => Default::default(),
+        };
+
+        Ok(warp::reply::json(&user.favorites(&entities)))
+    }
+
+    /// Get the entities most used through the panel, or through all of them
+    /// when it was not used yet, as favorites.
+    async fn api_favorites_auto_get(
+        self: Arc<Self>,
+        query: AutoFavoritesQuery,
+    ) -> Result<impl Reply, Rejection> {
+        let user = match &query.user {
+            Some(id) => Some(
+                self.context
+                    .config
+                    .users
+                    .iter()
+                    .find(|user| &user.id == id)
+                    .ok_or_else(warp::reject::not_found)?,
+            ),
+            None => None,
+        };
+        let entities = match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => entities,
+            home_assistant::Status::Disconnected => Default::default(),
+        };
+        let panel = client_ip::current().map(|client_ip| client_ip.to_string());
+        let favorites: Vec<Favorite> =
+            usage::most_used(panel.as_deref(), &self.context.config.usage, Utc::now())
+                .into_iter()
+                .filter(|(entity_id, _)| user.is_none_or(|user| user.is_allowed(entity_id)))
+                .take(query.limit.unwrap_or(DEFAULT_AUTO_FAVORITES))
+                .map(|(entity_id, _)| Favorite::new(&entity_id, &entities))
+                .collect();
+
+        Ok(warp::reply::json(&favorites))
+    }
+}
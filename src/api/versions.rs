@@ -0,0 +1,223 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use log::info;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use warp::{
+    http::{HeaderValue, Method},
+    Filter, Rejection, Reply,
+};
+
+use super::{
+    filters::{Access, Context},
+    Api,
+};
+
+/// The number of clients tracked, over all the versions, after which the
+/// requests of the others are counted together, as anyone can make up user
+/// agents.
+const MAX_CLIENTS: usize = 64;
+
+/// The number of characters of the user agents kept.
+const MAX_CLIENT_LENGTH: usize = 128;
+
+/// The client of the requests of the clients beyond [`MAX_CLIENTS`].
+const OTHER_CLIENTS: &str = "other";
+
+/// The versions of the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    /// Deprecated: the status is flat, and the entities are bare lists.
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    /// All the versions served.
+    const ALL: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+}
+
+/// The build of the panel, for the fleet tooling to check which panel runs
+/// what.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionInfo {
+    /// The version of the crate.
+    pub version: &'static str,
+
+    /// The git commit of the build, if it was built from a checkout.
+    pub git_commit: Option<&'static str>,
+    pub build_date: Option<DateTime<Utc>>,
+
+    /// The enabled cargo features.
+    pub features: Vec<&'static str>,
+    pub api_versions: Vec<ApiVersion>,
+}
+
+impl VersionInfo {
+    pub(super) fn new() -> Self {
+        let features = [
+            ("barcode", cfg!(feature = "barcode")),
+            ("debug", cfg!(feature = "debug")),
+            ("frontend", cfg!(feature = "frontend")),
+            ("gpio", cfg!(feature = "gpio")),
+            ("hid", cfg!(feature = "hid")),
+            ("mqtt", cfg!(feature = "mqtt")),
+            ("presence", cfg!(feature = "presence")),
+            ("rfid", cfg!(feature = "rfid")),
+            ("scheduler", cfg!(feature = "scheduler")),
+            ("storage", cfg!(feature = "storage")),
+            ("weather", cfg!(feature = "weather")),
+        ];
+
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_commit: Some(env!("HOME_CONTROL_GIT_COMMIT")).filter(|commit| !commit.is_empty()),
+            build_date: env!("HOME_CONTROL_BUILD_TIMESTAMP")
+                .parse()
+                .ok()
+                .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single()),
+            features: features
+                .into_iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(feature, _)| feature)
+                .collect(),
+            api_versions: ApiVersion::ALL.to_vec(),
+        }
+    }
+}
+
+/// The deprecation of the previous API versions.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiVersionsConfig {
+    /// When the version 1 will stop being served, announced in the `Sunset`
+    /// header of its responses.
+    #[serde(default)]
+    pub v1_sunset: Option<DateTime<Utc>>,
+
+    /// Whether to count the requests of each version by client, to find the
+    /// clients still to migrate.
+    #[serde(default)]
+    pub track_usage: bool,
+}
+
+/// The requests of each version by client, when tracked.
+#[derive(Debug, Default)]
+pub(super) struct ApiUsage {
+    requests: Mutex<BTreeMap<(ApiVersion, String), u64>>,
+}
+
+impl ApiUsage {
+    fn record(&self, version: ApiVersion, mut client: String) {
+        let mut requests = self.requests.lock().unwrap();
+
+        if let Some((index, _)) = client.char_indices().nth(MAX_CLIENT_LENGTH) {
+            client.truncate(index);
+        }
+
+        let key = (version, client);
+        let key = if requests.contains_key(&key) || requests.len() < MAX_CLIENTS {
+            key
+        } else {
+            (version, OTHER_CLIENTS.to_string())
+        };
+        let count = requests.entry(key).or_default();
+
+        if *count == 0 && version == ApiVersion::V1 {
+            info!("A client uses the deprecated API version 1.");
+        }
+
+        *count += 1;
+    }
+
+    fn report(&self) -> Vec<ApiClientUsage> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((version, client), requests)| ApiClientUsage {
+                version: *version,
+                client: client.clone(),
+                requests: *requests,
+            })
+            .collect()
+    }
+}
+
+/// The requests of a client to a version of the API.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiClientUsage {
+    pub version: ApiVersion,
+
+    /// The user agent of the client, truncated, or `other` for the requests
+    /// of the clients beyond the tracked ones.
+    pub client: String,
+    pub requests: u64,
+}
+
+/// Track the requests to a version of the API, if configured.
+pub(super) fn track(
+    ctx: &Context,
+    version: ApiVersion,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("user-agent")
+        .and(ctx.api())
+        .map(move |user_agent: Option<String>, api: Arc<Api>| {
+            if api.context.config.api_versions.track_usage {
+                api.api_usage
+                    .record(version, user_agent.unwrap_or_else(|| "unknown".to_string()));
+            }
+        })
+        .untuple_one()
+}
+
+/// Mark a response of a deprecated version of the API.
+pub(super) fn deprecate(reply: impl Reply, sunset: Option<DateTime<Utc>>) -> warp::reply::Response {
+    let mut response = reply.into_response();
+    let headers = response.headers_mut();
+
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+
+    if let Some(sunset) = sunset {
+        let sunset = sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+
+        if let Ok(value) = HeaderValue::from_str(&sunset) {
+            headers.insert("sunset", value);
+        }
+    }
+
+    response
+}
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_system_api_usage_get = warp::path!("system" / "api_usage")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_system_api_usage_get);
+
+    let api_version_get = warp::path!("version")
+        .and(ctx.route(Method::GET, Access::Public))
+        .map(|_| warp::reply::json(&VersionInfo::new()));
+
+    api_system_api_usage_get.or(api_version_get)
+}
+
+impl Api {
+    async fn api_system_api_usage_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        if !self.context.config.api_versions.track_usage {
+            return Err(warp::reject::not_found());
+        }
+
+        Ok(warp::reply::json(&self.api_usage.report()))
+    }
+}
+
+#[cfg(test)]
+mod tests;
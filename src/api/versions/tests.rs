@@ -0,0 +1,56 @@
+//! Tests of the API versions.
+
+use super::*;
+
+#[test]
+fn requests_are_counted_by_version_and_client() {
+    let usage = ApiUsage::default();
+
+    usage.record(ApiVersion::V1, "panel".to_string());
+    usage.record(ApiVersion::V1, "panel".to_string());
+    usage.record(ApiVersion::V2, "panel".to_string());
+
+    let report: Vec<_> = usage
+        .report()
+        .into_iter()
+        .map(|usage| (usage.version, usage.client, usage.requests))
+        .collect();
+
+    assert_eq!(
+        report,
+        [
+            (ApiVersion::V1, "panel".to_string(), 2),
+            (ApiVersion::V2, "panel".to_string(), 1),
+        ]
+    );
+}
+
+#[test]
+fn long_user_agents_are_truncated() {
+    let usage = ApiUsage::default();
+
+    usage.record(ApiVersion::V2, "é".repeat(MAX_CLIENT_LENGTH + 1));
+
+    assert_eq!(usage.report()[0].client, "é".repeat(MAX_CLIENT_LENGTH));
+}
+
+#[test]
+fn the_clients_beyond_the_tracked_ones_are_counted_together() {
+    let usage = ApiUsage::default();
+
+    for client in 0..MAX_CLIENTS + 2 {
+        usage.record(ApiVersion::V2, client.to_string());
+    }
+
+    usage.record(ApiVersion::V2, "0".to_string());
+
+    let report = usage.report();
+
+    assert_eq!(report.len(), MAX_CLIENTS + 1);
+    assert!(report
+        .iter()
+        .any(|usage| usage.client == "0" && usage.requests == 2));
+    assert!(report
+        .iter()
+        .any(|usage| usage.client == OTHER_CLIENTS && usage.requests == 2));
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::wakeup::{Wakeup, WakeupAlarm};
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_wakeup_get = warp::path!("wakeup")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_wakeup_get);
+
+    let api_wakeup_alarms_set = warp::path!("wakeup" / "alarms")
+        .and(ctx.limited_control_route(Method::PUT, Access::Settings, "wakeup", 16 * 1024))
+        .and(warp::body::json())
+        .and_then(|permit: Permit, api, alarms| {
+            permit.hold(Api::api_wakeup_alarms_set(api, alarms))
+        });
+
+    let api_wakeup_cancel = warp::path!("wakeup" / "cancel")
+        .and(ctx.control_route(Method::POST, Access::Session))
+        .and_then(Api::api_wakeup_cancel);
+
+    api_wakeup_get
+        .or(api_wakeup_alarms_set)
+        .or(api_wakeup_cancel)
+}
+
+impl Api {
+    fn wakeup(&self) -> Result<&Wakeup, Rejection> {
+        self.context
+            .scheduler
+            .wakeup
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)
+    }
+
+    async fn api_wakeup_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.wakeup()?.status().await))
+    }
+
+    async fn api_wakeup_alarms_set(
+        self: Arc<Self>,
+        alarms: Vec<WakeupAlarm>,
+    ) -> Result<impl Reply, Rejection> {
+        let wakeup = self.wakeup()?;
+
+        self.context
+            .editable
+            .save_schedules(None, Some(alarms.clone()))
+            .await
+            .map_err(warp::reject::custom)?;
+        wakeup.set_alarms(alarms).await;
+
+        Ok(warp::reply::json(&self.wakeup()?.status().await))
+    }
+
+    /// Stop the wake-up in progress, leaving the lights as they are.
+    async fn api_wakeup_cancel(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        if !self.wakeup()?.cancel() {
+            return Err(warp::reject::not_found());
+        }
+
+        Ok(warp::reply::json(&true))
+    }
+}
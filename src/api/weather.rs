@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use log::error;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+use super::{
+    filters::{Access, Context},
+    status::WeatherBlock,
+    Api,
+};
+use crate::home_assistant;
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_weather_nowcast_get = warp::path!("weather" / "nowcast")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_weather_nowcast_get);
+
+    let api_weather_radar_get = warp::path!("weather" / "radar")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_weather_radar_get);
+
+    let api_weather_alerts_get = warp::path!("weather" / "alerts")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_weather_alerts_get);
+
+    let api_weather_today_get = warp::path!("weather" / "today")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_weather_today_get);
+
+    api_weather_nowcast_get
+        .or(api_weather_radar_get)
+        .or(api_weather_alerts_get)
+        .or(api_weather_today_get)
+}
+
+impl Api {
+    async fn api_weather_nowcast_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let nowcast = self.nowcast.as_ref().ok_or_else(warp::reject::not_found)?;
+        let entities = match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => entities,
+            home_assistant::Status::Disconnected => Default::default(),
+        };
+
+        Ok(warp::reply::json(&nowcast.next_hour(&entities).await))
+    }
+
+    /// Proxy the radar image, which is cached so that panels do not hammer
+    /// the provider.
+    async fn api_weather_radar_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let nowcast = self.nowcast.as_ref().ok_or_else(warp::reject::not_found)?;
+        let image = nowcast
+            .radar()
+            .await
+            .map_err(|err| {
+                error!("failed to fetch the radar image: {}", err);
+                warp::reject::custom(crate::Error::from(err))
+            })?
+            .ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::with_header(
+            warp::reply::Response::new(image.data.into()),
+            "content-type",
+            image.content_type,
+        ))
+    }
+
+    async fn api_weather_alerts_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let weather_alerts = self
+            .context
+            .config
+            .weather_alerts
+            .as_ref()
+            .ok_or_else(warp::reject::not_found)?;
+        let entities = match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => entities,
+            home_assistant::Status::Disconnected => Default::default(),
+        };
+
+        Ok(warp::reply::json(&weather_alerts.alerts(&entities)))
+    }
+
+    /// Summarize the forecast for the rest of the day.
+    ///
+    /// The last known summary is served while Home-Assistant is unavailable,
+    /// with a `Warning` header telling it is stale.
+    async fn api_weather_today_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        let entities = match self.context.home_assistant.status().await {
+            home_assistant::Status::Connected { entities } => entities,
+            home_assistant::Status::Disconnected => Default::default(),
+        };
+        let config = &self.context.config;
+        let (summary, stale) = match self
+            .forecast_cache
+            .get(entities.get(&config.weather_entity), || {
+                WeatherBlock::new(&entities, config)
+            }) {
+            Ok(forecast) => (forecast.value.weather_today, forecast.stale),
+            Err(_) => (None, false),
+        };
+        let reply = warp::reply::json(&summary);
+
+        Ok(if stale {
+            warp::reply::with_header(reply, "warning", "110 - \"Response is Stale\"")
+                .into_response()
+        } else {
+            reply.into_response()
+        })
+    }
+}
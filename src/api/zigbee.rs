@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use log::error;
+use serde_json::Value;
+use warp::{
+    http::{Method, StatusCode},
+    Filter, Rejection, Reply,
+};
+
+use super::{
+    filters::{Access, Context},
+    limits::Permit,
+    Api,
+};
+use crate::mqtt::zigbee2mqtt::Zigbee2Mqtt;
+
+pub(super) fn routes(
+    ctx: &Context,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let api_zigbee_devices_get = warp::path!("zigbee" / "devices")
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(Api::api_zigbee_devices_get);
+
+    let api_zigbee_device_get = warp::path!("zigbee" / "devices" / String)
+        .and(ctx.route(Method::GET, Access::Public))
+        .and_then(|id, api: Arc<Api>| async move { api.api_zigbee_device_get(id).await });
+
+    let api_zigbee_device_set = warp::path!("zigbee" / "devices" / String / "set")
+        .and(ctx.limited_control_route(Method::POST, Access::Session, "zigbee", 1024))
+        .and(warp::body::json())
+        .and_then(|id: String, permit: Permit, api: Arc<Api>, command| {
+            permit.hold(Api::api_zigbee_device_set(api, id, command))
+        });
+
+    api_zigbee_devices_get
+        .or(api_zigbee_device_get)
+        .or(api_zigbee_device_set)
+}
+
+impl Api {
+    fn zigbee(&self) -> Result<&Zigbee2Mqtt, Rejection> {
+        self.zigbee.as_ref().ok_or_else(warp::reject::not_found)
+    }
+
+    async fn api_zigbee_devices_get(self: Arc<Self>) -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::json(&self.zigbee()?.devices().await))
+    }
+
+    async fn api_zigbee_device_get(self: Arc<Self>, id: String) -> Result<impl Reply, Rejection> {
+        let device = self
+            .zigbee()?
+            .device(&id)
+            .await
+            .ok_or_else(warp::reject::not_found)?;
+
+        Ok(warp::reply::json(&device))
+    }
+
+    /// Send a command to a device, like `{"state": "ON"}`, as Zigbee2MQTT
+    /// expects it.
+    ///
+    /// The new state is reported by the device once applied.
+    async fn api_zigbee_device_set(
+        self: Arc<Self>,
+        id: String,
+        command: Value,
+    ) -> Result<impl Reply, Rejection> {
+        let zigbee = self.zigbee()?;
+
+        if !command.is_object() {
+            return Err(warp::reject::custom(crate::Error::InvalidConfig(
+                "the command is not an object".to_string(),
+            )));
+        }
+
+        if zigbee.device(&id).await.is_none() {
+            return Err(warp::reject::not_found());
+        }
+
+        zigbee.set(&id, &command).await.map_err(|err| {
+            error!("failed to command the Zigbee device `{}`: {:#}", id, err);
+            warp::reject::custom(crate::Error::from(err))
+        })?;
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&command),
+            StatusCode::ACCEPTED,
+        ))
+    }
+}
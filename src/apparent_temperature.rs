@@ -0,0 +1,83 @@
+//! The "feels like" temperature, from the wind chill in the cold and the heat
+//! index in the heat.
+
+/// The units of the weather entity, which default to the metric ones.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WeatherUnits<'a> {
+    /// Like `°C` or `°F`.
+    pub temperature: Option<&'a str>,
+
+    /// Like `km/h`, `m/s`, `mph` or `kn`.
+    pub wind_speed: Option<&'a str>,
+}
+
+impl WeatherUnits<'_> {
+    fn is_fahrenheit(&self) -> bool {
+        self.temperature == Some("°F")
+    }
+
+    /// Convert a wind speed to km/h.
+    fn kmh(&self, wind_speed: f64) -> f64 {
+        wind_speed
+            * match self.wind_speed {
+                Some("m/s") => 3.6,
+                Some("mph") => 1.609_344,
+                Some("kn") => 1.852,
+                Some("ft/s") => 1.097_28,
+                _ => 1.0,
+            }
+    }
+}
+
+/// Compute the apparent temperature, in the unit of the temperature.
+///
+/// Without a humidity, like in forecasts, only the wind chill is accounted
+/// for.
+pub fn apparent_temperature(
+    temperature: f64,
+    humidity: Option<f64>,
+    wind_speed: f64,
+    units: WeatherUnits,
+) -> f64 {
+    let celsius = if units.is_fahrenheit() {
+        (temperature - 32.0) * 5.0 / 9.0
+    } else {
+        temperature
+    };
+    let wind_speed = units.kmh(wind_speed);
+
+    let apparent = match humidity {
+        _ if celsius <= 10.0 && wind_speed > 4.8 => wind_chill(celsius, wind_speed),
+        Some(humidity) if celsius >= 26.7 && humidity >= 40.0 => heat_index(celsius, humidity),
+        _ => return temperature,
+    };
+
+    if units.is_fahrenheit() {
+        apparent * 9.0 / 5.0 + 32.0
+    } else {
+        apparent
+    }
+}
+
+/// The wind chill index of Environment Canada and the NWS, in °C, for a wind
+/// speed in km/h.
+fn wind_chill(celsius: f64, wind_speed: f64) -> f64 {
+    let wind = wind_speed.powf(0.16);
+
+    13.12 + 0.6215 * celsius - 11.37 * wind + 0.3965 * celsius * wind
+}
+
+/// The heat index regression of Rothfusz, used by the NWS, in °C.
+fn heat_index(celsius: f64, humidity: f64) -> f64 {
+    let t = celsius * 9.0 / 5.0 + 32.0;
+    let rh = humidity;
+    let fahrenheit = -42.379 + 2.049_015_23 * t + 10.143_331_27 * rh
+        - 0.224_755_41 * t * rh
+        - 0.006_837_83 * t * t
+        - 0.054_817_17 * rh * rh
+        + 0.001_228_74 * t * t * rh
+        + 0.000_852_82 * t * rh * rh
+        - 0.000_001_99 * t * t * rh * rh;
+
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
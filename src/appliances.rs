@@ -0,0 +1,132 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    context::AppContext,
+    home_assistant::Update,
+    melody::{Melody, MelodyPlayer},
+    notifications::{Notifications, Severity},
+    rules::cycle_detector::{CycleDetector, CycleDetectorConfig, CycleEvent},
+    tasks,
+};
+
+/// The interval between two checks of the power readings, for the cycles to
+/// finish even when the sensors stop reporting.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// An appliance whose cycles are watched, like a washing machine.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApplianceConfig {
+    /// The name of the appliance, used in the notifications.
+    pub name: String,
+
+    /// The power sensor of the appliance, in watts, like
+    /// `sensor.washing_machine_power`.
+    pub power_entity: String,
+
+    #[serde(flatten)]
+    pub cycle: CycleDetectorConfig,
+
+    /// Whether to play the chime when a cycle finishes.
+    #[serde(default = "ApplianceConfig::default_chime")]
+    pub chime: bool,
+}
+
+impl ApplianceConfig {
+    fn default_chime() -> bool {
+        true
+    }
+
+    fn notification_id(&self) -> String {
+        format!("appliance-finished-{}", self.power_entity)
+    }
+}
+
+struct Appliance<'a> {
+    config: &'a ApplianceConfig,
+    detector: CycleDetector,
+}
+
+/// Watch the appliances forever, and notify when their cycles finish.
+pub async fn run(
+    appliances: &[ApplianceConfig],
+    context: &AppContext,
+    notifications: &Notifications,
+    melody_player: &MelodyPlayer,
+) -> anyhow::Result<()> {
+    let mut appliances: Vec<_> = appliances
+        .iter()
+        .map(|config| Appliance {
+            config,
+            detector: CycleDetector::new(config.cycle.clone()),
+        })
+        .collect();
+    let power_entities: HashSet<&str> = appliances
+        .iter()
+        .map(|appliance| appliance.config.power_entity.as_str())
+        .collect();
+    let mut updates = context.home_assistant.subscribe_updates();
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            update = updates.recv() => match update {
+                Ok(Update::Event(event)) => {
+                    if !event.entity_ids().iter().any(|entity_id| power_entities.contains(entity_id)) {
+                        continue;
+                    }
+                }
+                Ok(Update::Connected | Update::Disconnected) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+
+        tasks::heartbeat();
+
+        for appliance in &mut appliances {
+            let config = appliance.config;
+            let power = match context.home_assistant.entity(&config.power_entity).await {
+                Some(state) => match state.state.parse::<f64>() {
+                    Ok(power) => power,
+
+                    // The cycle is kept as it is while the sensor is unavailable.
+                    Err(_) => continue,
+                },
+                None => continue,
+            };
+
+            match appliance.detector.update(power, Instant::now()) {
+                Some(CycleEvent::Started) => {
+                    info!("{} started.", config.name);
+                    notifications.clear(&config.notification_id()).await;
+                }
+                Some(CycleEvent::Finished { duration }) => {
+                    let minutes = duration.as_secs() / 60;
+
+                    notifications
+                        .raise(
+                            config.notification_id(),
+                            Severity::Info,
+                            format!("{} finished", config.name),
+                            format!("The cycle lasted {}h{:02}.", minutes / 60, minutes % 60),
+                        )
+                        .await;
+
+                    if config.chime {
+                        if let Err(err) = melody_player.play(Melody::CHIME).await {
+                            warn!("Failed to play the chime: {}", err);
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+}
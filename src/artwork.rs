@@ -0,0 +1,211 @@
+use std::{collections::HashMap, process::Stdio, time::Instant};
+
+use anyhow::Context;
+use log::debug;
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    process::Command,
+    sync::Mutex,
+};
+use warp::hyper::body::Bytes;
+
+use crate::home_assistant::Controller;
+
+/// The media artwork cache configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArtworkConfig {
+    /// The sizes the artwork can be resized to, in pixels, which bound the
+    /// variants cached per cover.
+    #[serde(default = "ArtworkConfig::default_sizes")]
+    pub sizes: Vec<u32>,
+
+    /// How many pictures to keep, across the covers and their sizes.
+    #[serde(default = "ArtworkConfig::default_max_entries")]
+    pub max_entries: usize,
+
+    /// The ffmpeg command, used to resize the artwork.
+    #[serde(default = "ArtworkConfig::default_ffmpeg")]
+    pub ffmpeg: String,
+}
+
+impl Default for ArtworkConfig {
+    fn default() -> Self {
+        Self {
+            sizes: Self::default_sizes(),
+            max_entries: Self::default_max_entries(),
+            ffmpeg: Self::default_ffmpeg(),
+        }
+    }
+}
+
+impl ArtworkConfig {
+    fn default_sizes() -> Vec<u32> {
+        vec![128, 256, 512]
+    }
+
+    fn default_max_entries() -> usize {
+        64
+    }
+
+    fn default_ffmpeg() -> String {
+        "ffmpeg".to_string()
+    }
+}
+
+/// A cached picture.
+#[derive(Debug, Clone)]
+pub struct Picture {
+    pub content_type: String,
+    pub data: Bytes,
+}
+
+struct CachedPicture {
+    picture: Picture,
+    used_at: Instant,
+}
+
+/// Caches the artwork of the media players, by URL and size.
+///
+/// The URL of the artwork changes with the cover, so the cached pictures
+/// never go stale: the least recently used ones are evicted instead.
+pub struct Artwork {
+    config: ArtworkConfig,
+    cache: Mutex<HashMap<(String, Option<u32>), CachedPicture>>,
+}
+
+impl Artwork {
+    pub fn new(config: ArtworkConfig) -> Self {
+        Self {
+            config,
+            cache: Default::default(),
+        }
+    }
+
+    /// Check that the artwork can be resized to a size.
+    pub fn is_supported_size(&self, size: u32) -> bool {
+        self.config.sizes.contains(&size)
+    }
+
+    /// Get a picture, resized to fit a square of the size if any, fetching
+    /// it if it is not cached.
+    pub async fn get(
+        &self,
+        url: &str,
+        size: Option<u32>,
+        ha_controller: &Controller,
+    ) -> anyhow::Result<Picture> {
+        let key = (url.to_string(), size);
+
+        if let Some(cached) = self.cache.lock().await.get_mut(&key) {
+            cached.used_at = Instant::now();
+
+            return Ok(cached.picture.clone());
+        }
+
+        let original = match size {
+            Some(_) => self.original(url, ha_controller).await?,
+            None => self.fetch(url, ha_controller).await?,
+        };
+        let picture = match size {
+            Some(size) => self.resize(&original, size).await?,
+            None => original,
+        };
+
+        self.insert(key, picture.clone()).await;
+
+        Ok(picture)
+    }
+
+    /// Get the original picture, caching it too as the other sizes are
+    /// likely to follow.
+    async fn original(&self, url: &str, ha_controller: &Controller) -> anyhow::Result<Picture> {
+        let key = (url.to_string(), None);
+
+        if let Some(cached) = self.cache.lock().await.get(&key) {
+            return Ok(cached.picture.clone());
+        }
+
+        let picture = self.fetch(url, ha_controller).await?;
+
+        self.insert(key, picture.clone()).await;
+
+        Ok(picture)
+    }
+
+    async fn fetch(&self, url: &str, ha_controller: &Controller) -> anyhow::Result<Picture> {
+        debug!("Fetching the artwork `{}`.", url);
+
+        let (content_type, data) = ha_controller.fetch_picture(url).await?;
+
+        Ok(Picture { content_type, data })
+    }
+
+    async fn insert(&self, key: (String, Option<u32>), picture: Picture) {
+        let mut cache = self.cache.lock().await;
+
+        while cache.len() >= self.config.max_entries {
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, cached)| cached.used_at)
+                .map(|(key, _)| key.clone());
+
+            match oldest {
+                Some(oldest) => cache.remove(&oldest),
+                None => break,
+            };
+        }
+
+        cache.insert(
+            key,
+            CachedPicture {
+                picture,
+                used_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Resize a picture to fit a square, as a JPEG.
+    async fn resize(&self, picture: &Picture, size: u32) -> anyhow::Result<Picture> {
+        let mut child = Command::new(&self.config.ffmpeg)
+            .args(["-loglevel", "error", "-i", "pipe:0", "-vf"])
+            .arg(format!(
+                "scale={0}:{0}:force_original_aspect_ratio=decrease",
+                size
+            ))
+            .args(["-frames:v", "1", "-f", "image2", "-c:v", "mjpeg", "pipe:1"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .context("failed to run ffmpeg")?;
+        let mut stdin = child.stdin.take().context("failed to write to ffmpeg")?;
+        let mut stdout = child.stdout.take().context("failed to read from ffmpeg")?;
+        let mut data = Vec::new();
+
+        // The picture is written while reading, for ffmpeg not to block on
+        // a full pipe.
+        let write = async {
+            stdin.write_all(&picture.data).await?;
+
+            // Closing the input lets ffmpeg finish.
+            drop(stdin);
+
+            Ok::<_, std::io::Error>(())
+        };
+
+        tokio::try_join!(write, stdout.read_to_end(&mut data))
+            .context("failed to resize the picture")?;
+
+        let status = child.wait().await?;
+
+        anyhow::ensure!(status.success(), "ffmpeg exited with {}", status);
+        anyhow::ensure!(!data.is_empty(), "ffmpeg produced no picture");
+
+        Ok(Picture {
+            content_type: "image/jpeg".to_string(),
+            data: data.into(),
+        })
+    }
+}
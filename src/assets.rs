@@ -0,0 +1,246 @@
+use std::{borrow::Cow, collections::hash_map::DefaultHasher, hash::Hasher, io::Read, sync::Arc};
+
+use flate2::read::GzDecoder;
+use log::warn;
+use rust_embed::RustEmbed;
+use warp::{
+    filters::{path::Tail, BoxedFilter},
+    http::{header, response::Builder, Response, StatusCode},
+    hyper::Body,
+    Filter,
+};
+
+/// The pre-compressed variants of the assets, by order of preference.
+const ENCODINGS: &[(&str, &str)] = &[("br", "br"), ("gzip", "gz")];
+
+/// Where the frontend build puts the bundles with a content hash in their
+/// name, which never change.
+const IMMUTABLE_PREFIX: &str = "_app/immutable/";
+
+/// Serve the embedded frontend assets.
+///
+/// The frontend build pre-compresses the text assets, of which only the
+/// compressed variants are embedded: they are served as is to the clients
+/// that accept them, and decompressed on the fly for the others.
+///
+/// The hashed bundles are cached forever, while the other assets, like
+/// `index.html`, are revalidated with their `ETag` so that the updates of the
+/// frontend reach the panels.
+///
+/// The unknown paths without an extension are the routes of the frontend,
+/// like after a reload on a deep link: they are served `index.html`, for the
+/// frontend to route them.
+///
+/// The HTML documents are told the prefix of the API, which can be
+/// configured, in a `home-control-api-prefix` meta tag: they are served
+/// decompressed, with it added.
+pub fn embedded<A: RustEmbed>(api_prefix: &str) -> BoxedFilter<(Response<Body>,)> {
+    let head: Arc<str> = format!(
+        "<meta name=\"home-control-api-prefix\" content=\"{}\">",
+        escape_attribute(api_prefix.trim_end_matches('/'))
+    )
+    .into();
+
+    warp::get()
+        .and(warp::path::tail())
+        .and(warp::header::optional::<String>("accept-encoding"))
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and_then(
+            move |tail: Tail, accept_encoding: Option<String>, if_none_match: Option<String>| {
+                let head = Arc::clone(&head);
+
+                async move {
+                    let accept_encoding = accept_encoding.as_deref().unwrap_or_default();
+                    let if_none_match = if_none_match.as_deref();
+
+                    serve::<A>(tail.as_str(), accept_encoding, if_none_match, &head)
+                        .or_else(|| {
+                            is_frontend_route(tail.as_str())
+                                .then(|| serve::<A>("", accept_encoding, if_none_match, &head))?
+                        })
+                        .ok_or_else(warp::reject::not_found)
+                }
+            },
+        )
+        .boxed()
+}
+
+fn serve<A: RustEmbed>(
+    path: &str,
+    accept_encoding: &str,
+    if_none_match: Option<&str>,
+    head: &str,
+) -> Option<Response<Body>> {
+    let path = if path.is_empty() || path.ends_with('/') {
+        format!("{}index.html", path)
+    } else {
+        path.to_string()
+    };
+    let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+    let cache_control = if path.starts_with(IMMUTABLE_PREFIX) {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    let response = Response::builder()
+        .header(header::CONTENT_TYPE, content_type.as_ref())
+        .header(header::VARY, "accept-encoding")
+        .header(header::CACHE_CONTROL, cache_control);
+
+    if content_type.essence_str() == "text/html" {
+        return serve_document::<A>(&path, if_none_match, head, response);
+    }
+
+    let compressed = ENCODINGS
+        .iter()
+        .filter(|(encoding, _)| accepts(accept_encoding, encoding))
+        .find_map(|(encoding, extension)| {
+            Some((*encoding, A::get(&format!("{}.{}", path, extension))?))
+        });
+
+    // The decompressed variants are tagged after the compressed file they
+    // come from, distinctly.
+    let (response, file, decompressed) = match compressed {
+        Some((encoding, file)) => (
+            response.header(header::CONTENT_ENCODING, encoding),
+            file,
+            false,
+        ),
+        None => match A::get(&path) {
+            Some(file) => (response, file, false),
+            None => (response, A::get(&format!("{}.gz", path))?, true),
+        },
+    };
+    let etag = etag(&file.metadata.sha256_hash(), decompressed);
+    let response = response.header(header::ETAG, &etag);
+
+    if matches!(if_none_match, Some(tags) if etag_matches(tags, &etag)) {
+        return response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .ok();
+    }
+
+    let data = if decompressed {
+        decompress(&file.data)?
+    } else {
+        file.data
+    };
+
+    response
+        .body(match data {
+            Cow::Borrowed(data) => Body::from(data),
+            Cow::Owned(data) => Body::from(data),
+        })
+        .ok()
+}
+
+/// Serve an HTML document, decompressed, with some markup added to its head.
+fn serve_document<A: RustEmbed>(
+    path: &str,
+    if_none_match: Option<&str>,
+    head: &str,
+    response: Builder,
+) -> Option<Response<Body>> {
+    let (file, compressed) = match A::get(path) {
+        Some(file) => (file, false),
+        None => (A::get(&format!("{}.gz", path))?, true),
+    };
+
+    // The document changes with the markup added to it.
+    let mut hasher = DefaultHasher::new();
+
+    hasher.write(&file.metadata.sha256_hash());
+    hasher.write(head.as_bytes());
+
+    let etag = format!("\"{:016x}-document\"", hasher.finish());
+    let response = response.header(header::ETAG, &etag);
+
+    if matches!(if_none_match, Some(tags) if etag_matches(tags, &etag)) {
+        return response
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .ok();
+    }
+
+    let data = if compressed {
+        decompress(&file.data)?
+    } else {
+        file.data
+    };
+    let document = String::from_utf8_lossy(&data);
+    let document = match document.find("</head>") {
+        Some(index) => format!("{}{}{}", &document[..index], head, &document[index..]),
+        None => format!("{}{}", head, document),
+    };
+
+    response.body(Body::from(document)).ok()
+}
+
+/// Escape a value for an HTML attribute.
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Check whether a path is a route of the frontend rather than an asset,
+/// which all have an extension.
+fn is_frontend_route(path: &str) -> bool {
+    !path.rsplit('/').next().unwrap_or_default().contains('.')
+}
+
+/// Get the entity tag of some content, from its hash.
+fn etag(hash: &[u8; 32], decompressed: bool) -> String {
+    let hash: String = hash[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    if decompressed {
+        format!("\"{}-identity\"", hash)
+    } else {
+        format!("\"{}\"", hash)
+    }
+}
+
+/// Check whether an `If-None-Match` header matches an entity tag.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// Check whether an `Accept-Encoding` header accepts an encoding.
+fn accepts(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut parameters = item.split(';').map(str::trim);
+
+        parameters.next() == Some(encoding)
+            && !parameters.any(|parameter| {
+                matches!(
+                    parameter.strip_prefix("q=").map(str::parse::<f64>),
+                    Some(Ok(q)) if q <= 0.0
+                )
+            })
+    })
+}
+
+fn decompress(data: &[u8]) -> Option<Cow<'static, [u8]>> {
+    let mut decompressed = Vec::new();
+
+    match GzDecoder::new(data).read_to_end(&mut decompressed) {
+        Ok(_) => Some(Cow::Owned(decompressed)),
+        Err(err) => {
+            warn!("Failed to decompress an embedded asset: {}", err);
+
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
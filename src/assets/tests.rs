@@ -0,0 +1,61 @@
+//! Tests of the embedded assets.
+
+use super::*;
+
+/// A frontend, of which the document is only embedded pre-compressed, like
+/// the built one.
+#[derive(rust_embed::RustEmbed)]
+#[folder = "src/assets/fixtures"]
+struct Fixtures;
+
+async fn document(path: &str, api_prefix: &str) -> String {
+    let response = warp::test::request()
+        .path(path)
+        .header(header::ACCEPT_ENCODING, "gzip")
+        .reply(&embedded::<Fixtures>(api_prefix))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+
+    String::from_utf8(response.body().to_vec()).expect("a document")
+}
+
+#[tokio::test]
+async fn documents_are_told_the_api_prefix() {
+    let expected = "<meta name=\"home-control-api-prefix\" content=\"/panel-api\"></head>";
+
+    assert!(document("/", "/panel-api/").await.contains(expected));
+    assert!(document("/settings", "/panel-api").await.contains(expected));
+}
+
+#[tokio::test]
+async fn the_api_prefix_is_escaped() {
+    assert!(document("/", "/\"api\"")
+        .await
+        .contains("content=\"/&quot;api&quot;\""));
+}
+
+#[tokio::test]
+async fn documents_are_tagged_after_the_api_prefix() {
+    let etag = |api_prefix: &'static str| async move {
+        warp::test::request()
+            .path("/")
+            .reply(&embedded::<Fixtures>(api_prefix))
+            .await
+            .headers()[header::ETAG]
+            .clone()
+    };
+    let default = etag("/api/v1").await;
+
+    assert_eq!(default, etag("/api/v1").await);
+    assert_ne!(default, etag("/panel-api").await);
+
+    let response = warp::test::request()
+        .path("/")
+        .header(header::IF_NONE_MATCH, default)
+        .reply(&embedded::<Fixtures>("/api/v1"))
+        .await;
+
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
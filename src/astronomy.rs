@@ -0,0 +1,203 @@
+use std::f64::consts::PI;
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The length of a lunar cycle, in days.
+const SYNODIC_MONTH_DAYS: f64 = 29.530_588_853;
+
+/// The Julian date of the 2000-01-06 18:14 UTC new moon.
+const REFERENCE_NEW_MOON_JD: f64 = 2_451_550.26;
+
+/// The Julian date of the Unix epoch.
+const UNIX_EPOCH_JD: f64 = 2_440_587.5;
+
+/// The Julian date of the J2000.0 epoch.
+const J2000_JD: f64 = 2_451_545.0;
+
+/// The astronomy configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AstronomyConfig {
+    /// The latitude, in degrees north.
+    pub latitude: f64,
+
+    /// The longitude, in degrees east.
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MoonPhase {
+    NewMoon,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    FullMoon,
+    WaningGibbous,
+    LastQuarter,
+    WaningCrescent,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AstronomyStatus {
+    pub moon_phase: MoonPhase,
+
+    /// The illuminated fraction of the moon, from 0 to 1.
+    pub moon_illumination: f64,
+
+    /// The days since the last new moon.
+    pub moon_age_days: f64,
+
+    /// The sun events of the local day, missing during polar days and
+    /// nights.
+    pub civil_dawn: Option<DateTime<Utc>>,
+    pub sunrise: Option<DateTime<Utc>>,
+    pub sunset: Option<DateTime<Utc>>,
+    pub civil_dusk: Option<DateTime<Utc>>,
+
+    /// The time between sunrise and sunset, in seconds.
+    pub day_length: f64,
+}
+
+impl AstronomyConfig {
+    /// Compute the astronomical data at the specified time.
+    pub fn status(&self, now: DateTime<Utc>) -> AstronomyStatus {
+        let moon_age_days =
+            (julian_date(now) - REFERENCE_NEW_MOON_JD).rem_euclid(SYNODIC_MONTH_DAYS);
+        let moon_fraction = moon_age_days / SYNODIC_MONTH_DAYS;
+        let date = now.with_timezone(&Local).date_naive();
+        let sun = |altitude| self.sun_crossings(date, altitude);
+
+        let (sunrise, sunset, day_length) = match sun(-0.833) {
+            SunCrossings::Both { rise, set } => {
+                (Some(rise), Some(set), (set - rise).num_seconds() as f64)
+            }
+            SunCrossings::AlwaysAbove => (None, None, 86400.0),
+            SunCrossings::AlwaysBelow => (None, None, 0.0),
+        };
+        let (civil_dawn, civil_dusk) = match sun(-6.0) {
+            SunCrossings::Both { rise, set } => (Some(rise), Some(set)),
+            _ => (None, None),
+        };
+
+        AstronomyStatus {
+            moon_phase: MoonPhase::from_fraction(moon_fraction),
+            moon_illumination: (1.0 - (2.0 * PI * moon_fraction).cos()) / 2.0,
+            moon_age_days,
+            civil_dawn,
+            sunrise,
+            sunset,
+            civil_dusk,
+            day_length,
+        }
+    }
+
+    /// Compute when the sun rises above, or sets below, the specified
+    /// altitude on a day, if it does.
+    pub fn sun_crossing(
+        &self,
+        date: NaiveDate,
+        altitude: f64,
+        rising: bool,
+    ) -> Option<DateTime<Utc>> {
+        match self.sun_crossings(date, altitude) {
+            SunCrossings::Both { rise, .. } if rising => Some(rise),
+            SunCrossings::Both { set, .. } => Some(set),
+            _ => None,
+        }
+    }
+
+    /// Check whether the sun is above the specified altitude at a time.
+    pub fn sun_above(&self, time: DateTime<Utc>, altitude: f64) -> bool {
+        match self.sun_crossings(self.solar_date(time), altitude) {
+            SunCrossings::Both { rise, set } => rise <= time && time < set,
+            SunCrossings::AlwaysAbove => true,
+            SunCrossings::AlwaysBelow => false,
+        }
+    }
+
+    /// Get the day of a time in mean solar time, which the sun crossings of
+    /// the day surround.
+    fn solar_date(&self, time: DateTime<Utc>) -> NaiveDate {
+        (time + chrono::Duration::seconds((self.longitude / 360.0 * 86400.0) as i64)).date_naive()
+    }
+
+    /// Compute when the sun crosses the specified altitude, in degrees, on
+    /// a day.
+    ///
+    /// Uses the sunrise equation, which is accurate to a minute or so.
+    fn sun_crossings(&self, date: NaiveDate, altitude: f64) -> SunCrossings {
+        let days =
+            (date - NaiveDate::from_ymd_opt(2000, 1, 1).unwrap_or_default()).num_days() as f64;
+        let mean_solar_noon = days - self.longitude / 360.0;
+        let mean_anomaly = (357.5291 + 0.985_600_28 * mean_solar_noon)
+            .rem_euclid(360.0)
+            .to_radians();
+        let center = 1.9148 * mean_anomaly.sin()
+            + 0.02 * (2.0 * mean_anomaly).sin()
+            + 0.0003 * (3.0 * mean_anomaly).sin();
+        let ecliptic_longitude = (mean_anomaly.to_degrees() + center + 180.0 + 102.9372)
+            .rem_euclid(360.0)
+            .to_radians();
+        let transit = J2000_JD + mean_solar_noon + 0.0053 * mean_anomaly.sin()
+            - 0.0069 * (2.0 * ecliptic_longitude).sin();
+        let declination = (ecliptic_longitude.sin() * 23.4397_f64.to_radians().sin()).asin();
+        let latitude = self.latitude.to_radians();
+        let cos_hour_angle = (altitude.to_radians().sin() - latitude.sin() * declination.sin())
+            / (latitude.cos() * declination.cos());
+
+        if cos_hour_angle < -1.0 {
+            SunCrossings::AlwaysAbove
+        } else if cos_hour_angle > 1.0 {
+            SunCrossings::AlwaysBelow
+        } else {
+            let hour_angle = cos_hour_angle.acos().to_degrees() / 360.0;
+
+            SunCrossings::Both {
+                rise: from_julian_date(transit - hour_angle),
+                set: from_julian_date(transit + hour_angle),
+            }
+        }
+    }
+}
+
+enum SunCrossings {
+    Both {
+        rise: DateTime<Utc>,
+        set: DateTime<Utc>,
+    },
+    AlwaysAbove,
+    AlwaysBelow,
+}
+
+impl MoonPhase {
+    /// Get the phase from the fraction of the lunar cycle elapsed since the
+    /// new moon.
+    fn from_fraction(fraction: f64) -> Self {
+        const PHASES: [MoonPhase; 8] = [
+            MoonPhase::NewMoon,
+            MoonPhase::WaxingCrescent,
+            MoonPhase::FirstQuarter,
+            MoonPhase::WaxingGibbous,
+            MoonPhase::FullMoon,
+            MoonPhase::WaningGibbous,
+            MoonPhase::LastQuarter,
+            MoonPhase::WaningCrescent,
+        ];
+
+        // Each phase is centered on its nominal fraction.
+        PHASES[((fraction * 8.0).round() as usize) % PHASES.len()]
+    }
+}
+
+fn julian_date(time: DateTime<Utc>) -> f64 {
+    time.timestamp_millis() as f64 / 86_400_000.0 + UNIX_EPOCH_JD
+}
+
+fn from_julian_date(julian_date: f64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(((julian_date - UNIX_EPOCH_JD) * 86_400_000.0) as i64)
+        .single()
+        .unwrap_or_default()
+}
@@ -0,0 +1,213 @@
+use std::{path::PathBuf, process::Stdio};
+
+use anyhow::Context;
+use log::{debug, info};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    process::Command,
+    sync::Mutex,
+};
+
+use crate::Result;
+
+/// The audio playback configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AudioConfig {
+    /// The directory containing the local sound files.
+    #[serde(default = "AudioConfig::default_sounds_dir")]
+    pub sounds_dir: PathBuf,
+
+    /// The ALSA device to play to, like `plughw:0,0`. Uses the default device
+    /// if unspecified.
+    #[serde(default)]
+    pub device: Option<String>,
+
+    /// The ALSA player command.
+    #[serde(default = "AudioConfig::default_player")]
+    pub player: String,
+
+    /// The maximum size of an uploaded clip, in bytes.
+    #[serde(default = "AudioConfig::default_max_clip_size")]
+    pub max_clip_size: u64,
+
+    /// The local text-to-speech command and its arguments, like
+    /// `[espeak-ng, --stdout]`, if any. The text is written to its input, and
+    /// it must write a WAV clip to its output.
+    #[serde(default)]
+    pub tts_command: Vec<String>,
+}
+
+impl AudioConfig {
+    fn default_sounds_dir() -> PathBuf {
+        "/usr/share/home-control/sounds".into()
+    }
+
+    fn default_player() -> String {
+        "aplay".to_string()
+    }
+
+    fn default_max_clip_size() -> u64 {
+        1024 * 1024
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaySound {
+    /// The name of a local sound file, without its `.wav` extension.
+    pub sound: String,
+}
+
+/// Plays sounds through ALSA.
+///
+/// Playbacks are serialized, so that announcements never overlap.
+pub struct Audio {
+    config: AudioConfig,
+    lock: Mutex<()>,
+}
+
+impl Audio {
+    pub fn new(config: AudioConfig) -> Self {
+        Self {
+            config,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn max_clip_size(&self) -> u64 {
+        self.config.max_clip_size
+    }
+
+    /// Play a local sound file.
+    pub async fn play_sound(&self, sound: &str) -> Result<()> {
+        if sound.is_empty() || sound.contains(['/', '\\']) || sound.starts_with('.') {
+            return Err(anyhow::anyhow!("invalid sound name `{}`", sound).into());
+        }
+
+        let path = self.config.sounds_dir.join(format!("{}.wav", sound));
+
+        if !path.is_file() {
+            return Err(anyhow::anyhow!("sound `{}` was not found", sound).into());
+        }
+
+        info!("Playing sound `{}`.", sound);
+
+        let _guard = self.lock.lock().await;
+        let status = self
+            .player()
+            .arg(&path)
+            .status()
+            .await
+            .context("failed to run the audio player")?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("the audio player failed: {}", status).into());
+        }
+
+        Ok(())
+    }
+
+    /// Play an audio clip, like a WAV file.
+    pub async fn play_clip(&self, clip: &[u8]) -> Result<()> {
+        info!("Playing a {} bytes audio clip.", clip.len());
+
+        let _guard = self.lock.lock().await;
+        let mut child = self
+            .player()
+            .arg("-")
+            .stdin(Stdio::piped())
+            .spawn()
+            .context("failed to run the audio player")?;
+
+        // Dropping stdin once the clip was written lets the player terminate.
+        {
+            let mut stdin = child
+                .stdin
+                .take()
+                .context("failed to open the audio player input")?;
+
+            stdin
+                .write_all(clip)
+                .await
+                .context("failed to send the clip to the audio player")?;
+        }
+
+        let status = child
+            .wait()
+            .await
+            .context("failed to wait for the audio player")?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("the audio player failed: {}", status).into());
+        }
+
+        Ok(())
+    }
+
+    /// Speak a text with the local text-to-speech command.
+    pub async fn speak(&self, text: &str) -> Result<()> {
+        let (program, args) = self
+            .config
+            .tts_command
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("no text-to-speech command is configured"))?;
+
+        info!("Speaking `{}`.", text);
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("failed to run the text-to-speech command")?;
+        let mut stdin = child
+            .stdin
+            .take()
+            .context("failed to open the text-to-speech input")?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .context("failed to open the text-to-speech output")?;
+        let mut clip = Vec::new();
+
+        // The text is written while reading the clip, for the command not to
+        // block on a full pipe.
+        let write = async {
+            stdin.write_all(text.as_bytes()).await?;
+            drop(stdin);
+
+            Ok::<_, std::io::Error>(())
+        };
+
+        tokio::try_join!(write, stdout.read_to_end(&mut clip))
+            .context("failed to synthesize the speech")?;
+
+        let status = child
+            .wait()
+            .await
+            .context("failed to wait for the text-to-speech command")?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("the text-to-speech command failed: {}", status).into());
+        }
+
+        self.play_clip(&clip).await
+    }
+
+    fn player(&self) -> Command {
+        let mut command = Command::new(&self.config.player);
+
+        command.arg("-q").stdout(Stdio::null());
+
+        if let Some(device) = &self.config.device {
+            command.arg("-D").arg(device);
+        }
+
+        debug!("Audio player command: {:?}", command);
+
+        command
+    }
+}
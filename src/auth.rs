@@ -0,0 +1,127 @@
+use std::{collections::HashMap, time::Duration};
+
+use rand::{distributions::Alphanumeric, Rng};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_with::{serde_as, DurationSeconds};
+use tokio::{sync::Mutex, time::Instant};
+
+/// The name of the session cookie.
+pub const SESSION_COOKIE: &str = "home_control_session";
+
+/// The name of the cookie of the sessions changing the settings.
+pub const SETTINGS_SESSION_COOKIE: &str = "home_control_settings_session";
+
+/// The frontend login configuration.
+///
+/// When set, mutating API routes require a session, obtained by logging in
+/// with either the PIN or the password.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthConfig {
+    /// The PIN to log in with, if any.
+    #[serde(default)]
+    pub pin: Option<String>,
+
+    /// The password to log in with, if any.
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// The lifetime of a session.
+    #[serde(default = "AuthConfig::default_session_lifetime")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub session_lifetime: Duration,
+}
+
+impl AuthConfig {
+    fn default_session_lifetime() -> Duration {
+        Duration::from_secs(12 * 60 * 60)
+    }
+
+    /// Check whether the credentials match the configured PIN or password.
+    pub fn check(&self, credentials: &Credentials) -> bool {
+        fn matches(expected: &Option<String>, actual: &Option<String>) -> bool {
+            match (expected, actual) {
+                (Some(expected), Some(actual)) => constant_time_eq(expected, actual),
+                _ => false,
+            }
+        }
+
+        matches(&self.pin, &credentials.pin) || matches(&self.password, &credentials.password)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Credentials {
+    #[serde(default)]
+    pub pin: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// Tracks the active login sessions.
+pub struct Sessions {
+    config: AuthConfig,
+    sessions: Mutex<HashMap<String, Instant>>,
+}
+
+impl Sessions {
+    pub fn new(config: AuthConfig) -> Self {
+        Self {
+            config,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &AuthConfig {
+        &self.config
+    }
+
+    /// Create a new session, returning its token.
+    pub async fn create(&self) -> String {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(48)
+            .map(char::from)
+            .collect();
+
+        let mut sessions = self.sessions.lock().await;
+
+        sessions.retain(|_, expires_at| *expires_at > Instant::now());
+        sessions.insert(token.clone(), Instant::now() + self.config.session_lifetime);
+
+        token
+    }
+
+    /// Check whether the token belongs to an active session.
+    pub async fn is_valid(&self, token: &str) -> bool {
+        let mut sessions = self.sessions.lock().await;
+
+        match sessions.get(token) {
+            Some(expires_at) if *expires_at > Instant::now() => true,
+            Some(_) => {
+                sessions.remove(token);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// End a session.
+    pub async fn remove(&self, token: &str) {
+        self.sessions.lock().await.remove(token);
+    }
+}
+
+/// Compare two strings in a time that does not depend on where they differ.
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests;
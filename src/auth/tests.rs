@@ -0,0 +1,75 @@
+//! Tests of the login sessions.
+
+use super::*;
+
+fn sessions() -> Sessions {
+    Sessions::new(AuthConfig {
+        pin: Some("1234".to_string()),
+        password: None,
+        session_lifetime: Duration::from_secs(60),
+    })
+}
+
+#[tokio::test(start_paused = true)]
+async fn created_sessions_are_valid() {
+    let sessions = sessions();
+    let token = sessions.create().await;
+
+    assert_eq!(token.len(), 48);
+    assert!(sessions.is_valid(&token).await);
+    assert_ne!(sessions.create().await, token);
+}
+
+#[tokio::test(start_paused = true)]
+async fn sessions_expire_after_their_lifetime() {
+    let sessions = sessions();
+    let token = sessions.create().await;
+
+    tokio::time::advance(Duration::from_secs(59)).await;
+    assert!(sessions.is_valid(&token).await);
+
+    tokio::time::advance(Duration::from_secs(1)).await;
+    assert!(!sessions.is_valid(&token).await);
+    assert!(sessions.sessions.lock().await.is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn unknown_tokens_are_invalid() {
+    let sessions = sessions();
+    let token = sessions.create().await;
+
+    assert!(!sessions.is_valid("").await);
+    assert!(!sessions.is_valid(&token[1..]).await);
+    assert!(!sessions.is_valid(&format!("{}x", token)).await);
+}
+
+#[tokio::test(start_paused = true)]
+async fn logging_out_ends_the_session_only() {
+    let sessions = sessions();
+    let token = sessions.create().await;
+    let other = sessions.create().await;
+
+    sessions.remove(&token).await;
+
+    assert!(!sessions.is_valid(&token).await);
+    assert!(sessions.is_valid(&other).await);
+}
+
+#[test]
+fn credentials_match_the_pin_or_the_password() {
+    let config = AuthConfig {
+        pin: Some("1234".to_string()),
+        password: Some("secret".to_string()),
+        session_lifetime: Duration::from_secs(60),
+    };
+    let credentials = |pin: Option<&str>, password: Option<&str>| Credentials {
+        pin: pin.map(ToString::to_string),
+        password: password.map(ToString::to_string),
+    };
+
+    assert!(config.check(&credentials(Some("1234"), None)));
+    assert!(config.check(&credentials(None, Some("secret"))));
+    assert!(!config.check(&credentials(Some("123"), None)));
+    assert!(!config.check(&credentials(Some("secret"), None)));
+    assert!(!config.check(&credentials(None, None)));
+}
@@ -0,0 +1,308 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::RwLock;
+
+#[cfg(feature = "barcode")]
+use evdev::{Device, InputEventKind, Key};
+
+use crate::{files::write_atomic, home_assistant::Controller};
+
+/// The product lookup, with the barcode appended.
+const LOOKUP_URL: &str = "https://world.openfoodfacts.org/api/v2/product/";
+
+/// The grocery barcode scanner configuration.
+///
+/// Scanned products are added to the Home-Assistant shopping list.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BarcodeConfig {
+    /// The input device of a USB scanner that emulates a keyboard, like
+    /// `/dev/input/by-id/usb-...-event-kbd`, if read directly rather than
+    /// through the API.
+    #[serde(default)]
+    pub device: Option<PathBuf>,
+
+    /// The names of the known products, by barcode, which take precedence
+    /// over the lookups.
+    #[serde(default)]
+    pub products: HashMap<String, String>,
+
+    /// Whether to look up the unknown barcodes on Open Food Facts.
+    #[serde(default = "BarcodeConfig::default_lookup")]
+    pub lookup: bool,
+
+    /// The file in which the looked up products are cached, if any.
+    #[serde(default = "BarcodeConfig::default_cache_path")]
+    pub cache_path: Option<PathBuf>,
+}
+
+impl BarcodeConfig {
+    fn default_lookup() -> bool {
+        true
+    }
+
+    fn default_cache_path() -> Option<PathBuf> {
+        Some("/var/lib/home-control/barcodes.yaml".into())
+    }
+}
+
+/// The outcome of a scan.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScannedProduct {
+    pub barcode: String,
+
+    /// The name of the product, if it was resolved.
+    pub name: Option<String>,
+
+    /// Whether the product was added to the shopping list.
+    pub added: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupResponse {
+    #[serde(default)]
+    product: Option<LookupProduct>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupProduct {
+    #[serde(default)]
+    product_name: Option<String>,
+}
+
+pub struct Barcodes {
+    config: BarcodeConfig,
+    http_client: reqwest::Client,
+
+    /// The products looked up so far, by barcode.
+    cache: RwLock<HashMap<String, String>>,
+}
+
+impl Barcodes {
+    pub fn new(config: BarcodeConfig) -> Self {
+        let cache = match config.cache_path.as_deref().map(load_cache) {
+            None => HashMap::new(),
+            Some(Ok(cache)) => cache,
+            Some(Err(err)) => {
+                warn!("Failed to load the barcodes cache: {:#}", err);
+                HashMap::new()
+            }
+        };
+
+        Self {
+            config,
+            http_client: reqwest::Client::new(),
+            cache: RwLock::new(cache),
+        }
+    }
+
+    /// Check that a barcode looks like a product code, like an EAN-13.
+    pub fn is_valid(barcode: &str) -> bool {
+        (8..=14).contains(&barcode.len()) && barcode.bytes().all(|b| b.is_ascii_digit())
+    }
+
+    /// Resolve a scanned barcode, and add the product to the shopping list.
+    pub async fn scan(
+        &self,
+        barcode: &str,
+        ha_controller: &Controller,
+    ) -> crate::Result<ScannedProduct> {
+        let name = self.resolve(barcode).await;
+
+        let name = match name {
+            Some(name) => name,
+            None => {
+                warn!("Unknown barcode `{}` was scanned.", barcode);
+
+                return Ok(ScannedProduct {
+                    barcode: barcode.to_string(),
+                    name: None,
+                    added: false,
+                });
+            }
+        };
+
+        info!("Adding `{}` to the shopping list.", name);
+
+        ha_controller
+            .call_service(
+                "shopping_list",
+                "add_item",
+                Some(&json!({ "name": name })),
+                None,
+            )
+            .await?;
+
+        Ok(ScannedProduct {
+            barcode: barcode.to_string(),
+            name: Some(name),
+            added: true,
+        })
+    }
+
+    async fn resolve(&self, barcode: &str) -> Option<String> {
+        if let Some(name) = self.config.products.get(barcode) {
+            return Some(name.clone());
+        }
+
+        if let Some(name) = self.cache.read().await.get(barcode) {
+            return Some(name.clone());
+        }
+
+        if !self.config.lookup {
+            return None;
+        }
+
+        let name = match self.lookup(barcode).await {
+            Ok(name) => name?,
+            Err(err) => {
+                warn!("Failed to look up the barcode `{}`: {:#}", barcode, err);
+                return None;
+            }
+        };
+
+        let mut cache = self.cache.write().await;
+
+        cache.insert(barcode.to_string(), name.clone());
+
+        if let Some(cache_path) = &self.config.cache_path {
+            if let Err(err) = save_cache(cache_path, &cache) {
+                warn!("Failed to save the barcodes cache: {:#}", err);
+            }
+        }
+
+        Some(name)
+    }
+
+    async fn lookup(&self, barcode: &str) -> anyhow::Result<Option<String>> {
+        let response: LookupResponse = self
+            .http_client
+            .get(format!("{}{}", LOOKUP_URL, barcode))
+            .query(&[("fields", "product_name")])
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("failed to query the product")?
+            .json()
+            .await
+            .context("failed to parse the product")?;
+
+        Ok(response
+            .product
+            .and_then(|product| product.product_name)
+            .filter(|name| !name.is_empty()))
+    }
+}
+
+fn load_cache(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_yaml::from_str(&content)
+            .with_context(|| format!("failed to parse `{}`", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(err) => Err(err).with_context(|| format!("failed to read `{}`", path.display())),
+    }
+}
+
+/// Save the cache, replacing the file atomically.
+fn save_cache(path: &Path, cache: &HashMap<String, String>) -> anyhow::Result<()> {
+    let content = serde_yaml::to_string(cache).context("failed to serialize the cache")?;
+
+    write_atomic(path, content)
+}
+
+#[cfg(feature = "barcode")]
+impl Barcodes {
+    /// Read barcodes from the scanner forever, if configured.
+    pub async fn run(&self, ha_controller: &Controller) -> anyhow::Result<()> {
+        let path = match &self.config.device {
+            Some(path) => path,
+            None => return crate::tasks::idle().await,
+        };
+
+        let mut device = Device::open(path)
+            .with_context(|| format!("failed to open the barcode scanner `{}`", path.display()))?;
+
+        // Prevent the barcodes from being typed into the kiosk browser.
+        device
+            .grab()
+            .context("failed to grab the barcode scanner")?;
+
+        let mut events = device
+            .into_event_stream()
+            .context("failed to read from the barcode scanner")?;
+        let mut barcode = String::new();
+
+        info!("Reading barcodes from `{}`.", path.display());
+
+        loop {
+            let event = events
+                .next_event()
+                .await
+                .context("failed to read from the barcode scanner")?;
+
+            // Only key presses matter.
+            if event.value() != 1 {
+                continue;
+            }
+
+            if let InputEventKind::Key(key) = event.kind() {
+                match key {
+                    Key::KEY_ENTER | Key::KEY_KPENTER => {
+                        let scanned = std::mem::take(&mut barcode);
+
+                        if !Self::is_valid(&scanned) {
+                            warn!("Ignoring the invalid barcode `{}`.", scanned);
+                            continue;
+                        }
+
+                        if let Err(err) = self.scan(&scanned, ha_controller).await {
+                            warn!("Failed to add the barcode `{}`: {}", scanned, err);
+                        }
+                    }
+                    key => {
+                        if let Some(digit) = key_to_digit(key) {
+                            barcode.push(digit);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "barcode")]
+fn key_to_digit(key: Key) -> Option<char> {
+    Some(match key {
+        Key::KEY_0 | Key::KEY_KP0 => '0',
+        Key::KEY_1 | Key::KEY_KP1 => '1',
+        Key::KEY_2 | Key::KEY_KP2 => '2',
+        Key::KEY_3 | Key::KEY_KP3 => '3',
+        Key::KEY_4 | Key::KEY_KP4 => '4',
+        Key::KEY_5 | Key::KEY_KP5 => '5',
+        Key::KEY_6 | Key::KEY_KP6 => '6',
+        Key::KEY_7 | Key::KEY_KP7 => '7',
+        Key::KEY_8 | Key::KEY_KP8 => '8',
+        Key::KEY_9 | Key::KEY_KP9 => '9',
+        _ => return None,
+    })
+}
+
+#[cfg(not(feature = "barcode"))]
+impl Barcodes {
+    pub async fn run(&self, _ha_controller: &Controller) -> anyhow::Result<()> {
+        match &self.config.device {
+            Some(_) => crate::tasks::unsupported("The barcode scanner", "barcode").await,
+            None => crate::tasks::idle().await,
+        }
+    }
+}
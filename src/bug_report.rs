@@ -0,0 +1,74 @@
+//! The bug report bundles, which gather everything needed to diagnose a
+//! panel into a single zip file.
+
+use std::{collections::HashMap, io::Write};
+
+use serde::Serialize;
+use serde_json::json;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{home_assistant::State, simulation::SimulatedEventKind};
+
+/// Get the states as the lines of an events file, for them to be replayed
+/// with `--simulate`.
+pub fn replayable_states(entities: &HashMap<String, State>) -> anyhow::Result<String> {
+    let mut entity_ids: Vec<_> = entities.keys().collect();
+    let mut lines = String::new();
+
+    entity_ids.sort();
+
+    for entity_id in entity_ids {
+        let state = &entities[entity_id];
+        let mut line = serde_json::to_value(SimulatedEventKind::State {
+            entity_id: state.entity_id.clone(),
+            state: state.state.clone(),
+            attributes: state.attributes.parse()?,
+        })?;
+
+        line["at"] = json!(0);
+        lines.push_str(&line.to_string());
+        lines.push('\n');
+    }
+
+    Ok(lines)
+}
+
+/// A bundle being written.
+pub struct BugReport {
+    zip: ZipWriter<std::io::Cursor<Vec<u8>>>,
+}
+
+impl BugReport {
+    pub fn new() -> Self {
+        Self {
+            zip: ZipWriter::new(std::io::Cursor::new(Vec::new())),
+        }
+    }
+
+    /// Add a text file.
+    pub fn add_text(&mut self, name: &str, text: &str) -> anyhow::Result<()> {
+        self.zip.start_file(
+            name,
+            FileOptions::default().compression_method(CompressionMethod::Deflated),
+        )?;
+        self.zip.write_all(text.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Add a value as a pretty JSON file.
+    pub fn add_json(&mut self, name: &str, value: &impl Serialize) -> anyhow::Result<()> {
+        self.add_text(name, &serde_json::to_string_pretty(value)?)
+    }
+
+    /// Get the zip file.
+    pub fn finish(mut self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.zip.finish()?.into_inner())
+    }
+}
+
+impl Default for BugReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
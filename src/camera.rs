@@ -0,0 +1,212 @@
+use std::{collections::HashMap, process::Stdio, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use futures_util::Stream;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use tokio::{
+    io::AsyncReadExt,
+    process::Command,
+    sync::{broadcast, Mutex},
+};
+use warp::hyper::body::Bytes;
+
+/// The multipart boundary used for the MJPEG streams.
+pub const MJPEG_BOUNDARY: &str = "frame";
+
+/// The configuration of a camera that is not integrated into Home Assistant.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CameraConfig {
+    /// The name of the camera, as used in the API path.
+    pub name: String,
+
+    /// The RTSP URL of the camera stream.
+    pub url: String,
+
+    /// The frame rate of the MJPEG stream.
+    #[serde(default = "CameraConfig::default_fps")]
+    pub fps: u32,
+
+    /// The ffmpeg command.
+    #[serde(default = "CameraConfig::default_ffmpeg")]
+    pub ffmpeg: String,
+}
+
+impl CameraConfig {
+    fn default_fps() -> u32 {
+        5
+    }
+
+    fn default_ffmpeg() -> String {
+        "ffmpeg".to_string()
+    }
+}
+
+struct Camera {
+    config: CameraConfig,
+    frames: broadcast::Sender<Bytes>,
+    running: Mutex<bool>,
+}
+
+/// Re-serves RTSP camera streams as MJPEG.
+///
+/// The ffmpeg transcoder of a camera only runs while it has viewers.
+pub struct Cameras {
+    cameras: HashMap<String, Arc<Camera>>,
+}
+
+impl Cameras {
+    pub fn new(configs: Vec<CameraConfig>) -> Self {
+        let cameras = configs
+            .into_iter()
+            .map(|config| {
+                let (frames, _) = broadcast::channel(4);
+
+                (
+                    config.name.clone(),
+                    Arc::new(Camera {
+                        config,
+                        frames,
+                        running: Mutex::new(false),
+                    }),
+                )
+            })
+            .collect();
+
+        Self { cameras }
+    }
+
+    /// Get a stream of multipart MJPEG parts for the specified camera.
+    ///
+    /// Returns `None` if the camera does not exist.
+    pub async fn stream(
+        &self,
+        name: &str,
+    ) -> Option<impl Stream<Item = Result<Bytes, std::convert::Infallible>>> {
+        let camera = Arc::clone(self.cameras.get(name)?);
+
+        // Subscribing under the lock guarantees the transcoder cannot stop
+        // without noticing the new viewer.
+        let receiver = {
+            let mut running = camera.running.lock().await;
+            let receiver = camera.frames.subscribe();
+
+            if !*running {
+                *running = true;
+                tokio::spawn(Camera::transcode(Arc::clone(&camera)));
+            }
+
+            receiver
+        };
+
+        Some(futures_util::stream::unfold(
+            receiver,
+            |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(frame) => {
+                            let mut part = format!(
+                                "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+                                MJPEG_BOUNDARY,
+                                frame.len()
+                            )
+                            .into_bytes();
+
+                            part.extend_from_slice(&frame);
+                            part.extend_from_slice(b"\r\n");
+
+                            return Some((Ok(Bytes::from(part)), receiver));
+                        }
+                        // Slow viewers simply skip frames.
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            },
+        ))
+    }
+}
+
+impl Camera {
+    async fn transcode(self: Arc<Self>) {
+        let retry_delay = Duration::from_secs(5);
+
+        loop {
+            info!("Starting the stream of camera `{}`.", self.config.name);
+
+            if let Err(err) = self.run_ffmpeg().await {
+                warn!("Camera `{}` stream failed: {}", self.config.name, err);
+
+                tokio::time::sleep(retry_delay).await;
+            }
+
+            let mut running = self.running.lock().await;
+
+            if self.frames.receiver_count() == 0 {
+                info!("Stopped the stream of camera `{}`.", self.config.name);
+
+                *running = false;
+
+                return;
+            }
+        }
+    }
+
+    async fn run_ffmpeg(&self) -> anyhow::Result<()> {
+        let mut child = Command::new(&self.config.ffmpeg)
+            .args(["-loglevel", "error", "-rtsp_transport", "tcp", "-i"])
+            .arg(&self.config.url)
+            .args(["-f", "mjpeg", "-q:v", "5", "-r"])
+            .arg(self.config.fps.to_string())
+            .arg("-")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("failed to run ffmpeg")?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .context("failed to open the ffmpeg output")?;
+
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 16 * 1024];
+
+        loop {
+            let read = stdout
+                .read(&mut chunk)
+                .await
+                .context("failed to read from ffmpeg")?;
+
+            if read == 0 {
+                anyhow::bail!("ffmpeg terminated");
+            }
+
+            buffer.extend_from_slice(&chunk[..read]);
+
+            while let Some(frame) = split_jpeg(&mut buffer) {
+                // Sending fails once the last viewer is gone, which stops the
+                // transcoder (and kills ffmpeg on drop).
+                if self.frames.send(frame).is_err() {
+                    debug!("No more viewers for camera `{}`.", self.config.name);
+
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Extract the first complete JPEG image from the buffer, if any.
+fn split_jpeg(buffer: &mut Vec<u8>) -> Option<Bytes> {
+    let start = buffer.windows(2).position(|w| w == [0xFF, 0xD8])?;
+    let end = buffer[start..]
+        .windows(2)
+        .position(|w| w == [0xFF, 0xD9])
+        .map(|end| start + end + 2)?;
+
+    let frame = Bytes::copy_from_slice(&buffer[start..end]);
+    buffer.drain(..end);
+
+    Some(frame)
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::{broadcast::error::RecvError, RwLock};
+
+use crate::{
+    context::AppContext,
+    home_assistant::{EntityAreas, Status, Update},
+    tasks,
+};
+
+/// The recent changes of the entities, by area.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChangelogConfig {
+    /// The domains of the entities whose changes are kept.
+    #[serde(default = "ChangelogConfig::default_domains")]
+    pub domains: Vec<String>,
+
+    /// The number of changes kept by area.
+    #[serde(default = "ChangelogConfig::default_capacity")]
+    pub capacity: usize,
+
+    /// The interval between two refreshes of the areas of the entities.
+    #[serde(default = "ChangelogConfig::default_areas_refresh")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub areas_refresh: Duration,
+}
+
+impl ChangelogConfig {
+    fn default_domains() -> Vec<String> {
+        [
+            "alarm_control_panel",
+            "binary_sensor",
+            "climate",
+            "cover",
+            "light",
+            "lock",
+            "media_player",
+            "switch",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    fn default_capacity() -> usize {
+        50
+    }
+
+    fn default_areas_refresh() -> Duration {
+        Duration::from_secs(600)
+    }
+
+    fn tracks(&self, entity_id: &str) -> bool {
+        matches!(entity_id.split_once('.'), Some((domain, _)) if self.domains.iter().any(|d| d == domain))
+    }
+}
+
+/// A change of the state of an entity.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityChange {
+    pub entity_id: String,
+    pub friendly_name: Option<String>,
+
+    /// The previous state, if it was known.
+    pub old_state: Option<String>,
+    pub state: String,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Keeps the recent changes of the entities, by area.
+pub struct Changelog {
+    config: ChangelogConfig,
+    areas: RwLock<EntityAreas>,
+    changes: RwLock<HashMap<String, VecDeque<EntityChange>>>,
+}
+
+impl Changelog {
+    pub fn new(config: ChangelogConfig) -> Self {
+        Self {
+            config,
+            areas: RwLock::new(EntityAreas::default()),
+            changes: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Get the recent changes in an area, by id or name, the latest first.
+    ///
+    /// Returns `None` if the area is unknown.
+    pub async fn recent(&self, area: &str) -> Option<Vec<EntityChange>> {
+        let area_id = self.areas.read().await.find(area)?.to_string();
+
+        Some(
+            self.changes
+                .read()
+                .await
+                .get(&area_id)
+                .map(|changes| changes.iter().rev().cloned().collect())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Record the changes forever.
+    pub async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        let home_assistant = &context.home_assistant;
+        let mut updates = home_assistant.subscribe_updates();
+        let mut refresh = tokio::time::interval(self.config.areas_refresh);
+
+        // The last known states, to tell the changes from the attribute
+        // updates.
+        let mut states = self.snapshot(context).await;
+
+        loop {
+            tokio::select! {
+                _ = refresh.tick() => {
+                    match home_assistant.entity_areas().await {
+                        Ok(areas) => *self.areas.write().await = areas,
+                        Err(err) => warn!("Failed to refresh the areas of the entities: {}", err),
+                    }
+                }
+                update = updates.recv() => match update {
+                    Ok(Update::Event(event)) => {
+                        for entity_id in event.entity_ids() {
+                            self.record(context, &mut states, entity_id).await;
+                        }
+                    }
+                    Ok(Update::Connected) => states = self.snapshot(context).await,
+                    Ok(Update::Disconnected) => {}
+                    Err(RecvError::Lagged(missed)) => {
+                        debug!("Missed {} updates: taking a new snapshot.", missed);
+                        states = self.snapshot(context).await;
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+            }
+
+            tasks::heartbeat();
+        }
+    }
+
+    /// Get the states of the tracked entities.
+    async fn snapshot(&self, context: &AppContext) -> HashMap<String, String> {
+        match context.home_assistant.status().await {
+            Status::Connected { entities } => entities
+                .values()
+                .filter(|state| self.config.tracks(&state.entity_id))
+                .map(|state| (state.entity_id.clone(), state.state.clone()))
+                .collect(),
+            Status::Disconnected => HashMap::new(),
+        }
+    }
+
+    async fn record(
+        &self,
+        context: &AppContext,
+        states: &mut HashMap<String, String>,
+        entity_id: &str,
+    ) {
+        if !self.config.tracks(entity_id) {
+            return;
+        }
+
+        let state = match context.home_assistant.entity(entity_id).await {
+            Some(state) => state,
+            None => {
+                states.remove(entity_id);
+                return;
+            }
+        };
+        let old_state = states.insert(entity_id.to_string(), state.state.clone());
+
+        if old_state.as_ref() == Some(&state.state) {
+            return;
+        }
+
+        let area_id = match self.areas.read().await.entities.get(entity_id) {
+            Some(area_id) => area_id.clone(),
+            None => return,
+        };
+        let mut changes = self.changes.write().await;
+        let changes = changes.entry(area_id).or_default();
+
+        changes.push_back(EntityChange {
+            entity_id: entity_id.to_string(),
+            friendly_name: state.attributes.get("friendly_name"),
+            old_state,
+            state: state.state,
+            changed_at: state.last_changed,
+        });
+
+        while changes.len() > self.config.capacity {
+            changes.pop_front();
+        }
+    }
+}
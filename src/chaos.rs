@@ -0,0 +1,81 @@
+//! Failure injection, to exercise the reconnections, the degraded modes and
+//! the supervisor deliberately rather than waiting for real outages.
+//!
+//! The failures can only be injected through the API of the builds with the
+//! `debug` feature: otherwise, none is ever set and the checks do nothing.
+
+use std::{sync::Mutex, time::Duration};
+
+use log::warn;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::Notify;
+
+static FAULTS: Mutex<Faults> = Mutex::new(Faults {
+    call_delay: Duration::ZERO,
+    gpio_reads_fail: false,
+});
+
+static HOME_ASSISTANT_DROP: Notify = Notify::const_new();
+
+/// The failures currently injected.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Faults {
+    /// The delay added to the responses of the service calls, in seconds.
+    #[serde(default)]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    #[schemars(with = "f64")]
+    pub call_delay: Duration,
+
+    /// Whether the readings of the GPIO inputs and of the distance sensor
+    /// fail.
+    #[serde(default)]
+    pub gpio_reads_fail: bool,
+}
+
+pub fn faults() -> Faults {
+    FAULTS.lock().unwrap().clone()
+}
+
+/// Replace the injected failures.
+pub fn set_faults(faults: Faults) {
+    if faults != Faults::default() {
+        warn!("Injecting failures: {:?}", faults);
+    }
+
+    *FAULTS.lock().unwrap() = faults;
+}
+
+/// Drop the web-socket to Home-Assistant, which is then reconnected as after
+/// an outage.
+pub fn drop_home_assistant() {
+    warn!("Dropping the Home-Assistant web-socket on purpose.");
+
+    HOME_ASSISTANT_DROP.notify_waiters();
+}
+
+/// Wait for the web-socket to Home-Assistant to be dropped on purpose.
+pub async fn home_assistant_dropped() {
+    HOME_ASSISTANT_DROP.notified().await
+}
+
+/// Delay the response of a service call, if requested.
+pub async fn delay_call() {
+    let delay = FAULTS.lock().unwrap().call_delay;
+
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Fail a GPIO reading, if requested.
+pub fn check_gpio_read() -> anyhow::Result<()> {
+    if FAULTS.lock().unwrap().gpio_reads_fail {
+        anyhow::bail!("the GPIO reading failed on purpose");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,358 @@
+use std::path::PathBuf;
+#[cfg(feature = "storage")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "storage")]
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "storage")]
+use chrono::{Datelike, Duration, Local, TimeZone};
+#[cfg(feature = "storage")]
+use rusqlite::{params, Connection, OptionalExtension};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+#[cfg(feature = "storage")]
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS chores (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    assignee TEXT,
+    points INTEGER NOT NULL DEFAULT 1,
+    recurrence_days INTEGER,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS chore_completions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chore_id INTEGER NOT NULL REFERENCES chores(id) ON DELETE CASCADE,
+    completed_by TEXT NOT NULL,
+    completed_at TEXT NOT NULL,
+    points INTEGER NOT NULL
+);
+";
+
+/// The chores board configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChoresConfig {
+    /// The path to the SQLite database.
+    #[serde(default = "ChoresConfig::default_database_path")]
+    pub database_path: PathBuf,
+}
+
+impl ChoresConfig {
+    fn default_database_path() -> PathBuf {
+        "/var/lib/home-control/chores.sqlite".into()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewChore {
+    pub name: String,
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[serde(default = "NewChore::default_points")]
+    pub points: u32,
+
+    /// The number of days after which a completed chore is due again.
+    ///
+    /// One-off chores have no recurrence.
+    #[serde(default)]
+    pub recurrence_days: Option<u32>,
+}
+
+impl NewChore {
+    fn default_points() -> u32 {
+        1
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Chore {
+    pub id: i64,
+    pub name: String,
+    pub assignee: Option<String>,
+    pub points: u32,
+    pub recurrence_days: Option<u32>,
+    pub last_completed_at: Option<DateTime<Utc>>,
+    pub last_completed_by: Option<String>,
+    pub due: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChoreUser {
+    pub user: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStats {
+    pub user: String,
+    pub completed: u32,
+    pub points: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyStats {
+    pub since: DateTime<Utc>,
+    pub users: Vec<UserStats>,
+}
+
+/// A chores board, stored locally in SQLite.
+#[cfg(feature = "storage")]
+#[derive(Clone)]
+pub struct Chores {
+    connection: Arc<Mutex<Connection>>,
+}
+
+/// A chores board, which cannot exist without storage support.
+#[cfg(not(feature = "storage"))]
+#[derive(Clone)]
+pub enum Chores {}
+
+#[cfg(feature = "storage")]
+impl Chores {
+    pub fn new(config: &ChoresConfig) -> anyhow::Result<Self> {
+        if let Some(parent) = config.database_path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!(
+                    "failed to create the chores directory `{}`",
+                    parent.display()
+                )
+            })?;
+        }
+
+        let connection = Connection::open(&config.database_path).with_context(|| {
+            format!(
+                "failed to open the chores database `{}`",
+                config.database_path.display()
+            )
+        })?;
+
+        connection
+            .execute_batch(SCHEMA)
+            .context("failed to initialize the chores database")?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Run a function on the connection in a blocking task.
+    async fn with_connection<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> anyhow::Result<T> + Send + 'static,
+    {
+        let connection = Arc::clone(&self.connection);
+
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|_| anyhow::anyhow!("the chores database lock is poisoned"))?;
+
+            f(&mut connection)
+        })
+        .await
+        .context("the chores database task failed")?
+        .map_err(Into::into)
+    }
+
+    pub async fn list(&self) -> Result<Vec<Chore>> {
+        self.with_connection(|connection| {
+            let now = Utc::now();
+            let mut statement = connection.prepare(
+                "SELECT c.id, c.name, c.assignee, c.points, c.recurrence_days,
+                        l.completed_at, l.completed_by
+                 FROM chores c
+                 LEFT JOIN chore_completions l ON l.id = (
+                     SELECT id FROM chore_completions
+                     WHERE chore_id = c.id
+                     ORDER BY completed_at DESC
+                     LIMIT 1
+                 )
+                 ORDER BY c.id",
+            )?;
+
+            let chores = statement
+                .query_map([], |row| {
+                    let recurrence_days: Option<u32> = row.get(4)?;
+                    let last_completed_at: Option<DateTime<Utc>> = row.get(5)?;
+                    let due = match (last_completed_at, recurrence_days) {
+                        (None, _) => true,
+                        (Some(_), None) => false,
+                        (Some(at), Some(days)) => at + Duration::days(days.into()) <= now,
+                    };
+
+                    Ok(Chore {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        assignee: row.get(2)?,
+                        points: row.get(3)?,
+                        recurrence_days,
+                        last_completed_at,
+                        last_completed_by: row.get(6)?,
+                        due,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            Ok(chores)
+        })
+        .await
+    }
+
+    pub async fn create(&self, chore: NewChore) -> Result<i64> {
+        self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT INTO chores (name, assignee, points, recurrence_days, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    chore.name,
+                    chore.assignee,
+                    chore.points,
+                    chore.recurrence_days,
+                    Utc::now()
+                ],
+            )?;
+
+            Ok(connection.last_insert_rowid())
+        })
+        .await
+    }
+
+    /// Delete a chore and its history.
+    ///
+    /// Returns `false` if the chore does not exist.
+    pub async fn delete(&self, id: i64) -> Result<bool> {
+        self.with_connection(move |connection| {
+            let transaction = connection.transaction()?;
+
+            transaction.execute(
+                "DELETE FROM chore_completions WHERE chore_id = ?1",
+                params![id],
+            )?;
+            let deleted = transaction.execute("DELETE FROM chores WHERE id = ?1", params![id])?;
+
+            transaction.commit()?;
+
+            Ok(deleted > 0)
+        })
+        .await
+    }
+
+    /// Assign a chore to a user.
+    ///
+    /// Returns `false` if the chore does not exist.
+    pub async fn claim(&self, id: i64, user: String) -> Result<bool> {
+        self.with_connection(move |connection| {
+            let updated = connection.execute(
+                "UPDATE chores SET assignee = ?1 WHERE id = ?2",
+                params![user, id],
+            )?;
+
+            Ok(updated > 0)
+        })
+        .await
+    }
+
+    /// Record the completion of a chore by a user.
+    ///
+    /// Returns `false` if the chore does not exist.
+    pub async fn complete(&self, id: i64, user: String) -> Result<bool> {
+        self.with_connection(move |connection| {
+            let points: Option<u32> = connection
+                .query_row(
+                    "SELECT points FROM chores WHERE id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            match points {
+                Some(points) => {
+                    connection.execute(
+                        "INSERT INTO chore_completions (chore_id, completed_by, completed_at, points)
+                         VALUES (?1, ?2, ?3, ?4)",
+                        params![id, user, Utc::now(), points],
+                    )?;
+
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+        .await
+    }
+
+    /// Get the points per user since the start of the current week.
+    pub async fn weekly_stats(&self) -> Result<WeeklyStats> {
+        let today = Local::now().date_naive();
+        let monday = today - Duration::days(today.weekday().num_days_from_monday().into());
+        let since = monday
+            .and_hms_opt(0, 0, 0)
+            .and_then(|dt| Local.from_local_datetime(&dt).earliest())
+            .context("failed to compute the start of the week")?
+            .with_timezone(&Utc);
+
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT completed_by, COUNT(*), SUM(points)
+                 FROM chore_completions
+                 WHERE completed_at >= ?1
+                 GROUP BY completed_by
+                 ORDER BY SUM(points) DESC",
+            )?;
+
+            let users = statement
+                .query_map(params![since], |row| {
+                    Ok(UserStats {
+                        user: row.get(0)?,
+                        completed: row.get(1)?,
+                        points: row.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            Ok(WeeklyStats { since, users })
+        })
+        .await
+    }
+}
+
+#[cfg(not(feature = "storage"))]
+impl Chores {
+    pub fn new(_config: &ChoresConfig) -> anyhow::Result<Self> {
+        anyhow::bail!("chores are configured but storage support was not compiled in")
+    }
+
+    pub async fn list(&self) -> Result<Vec<Chore>> {
+        match *self {}
+    }
+
+    pub async fn create(&self, _chore: NewChore) -> Result<i64> {
+        match *self {}
+    }
+
+    pub async fn delete(&self, _id: i64) -> Result<bool> {
+        match *self {}
+    }
+
+    pub async fn claim(&self, _id: i64, _user: String) -> Result<bool> {
+        match *self {}
+    }
+
+    pub async fn complete(&self, _id: i64, _user: String) -> Result<bool> {
+        match *self {}
+    }
+
+    pub async fn weekly_stats(&self) -> Result<WeeklyStats> {
+        match *self {}
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    f64::consts::PI,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use chrono::{Local, NaiveTime, Timelike};
+use serde::Deserialize;
+use serde_json::json;
+
+/// The configuration for the adaptive/circadian lighting helper.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CircadianConfig {
+    /// The lights to apply circadian settings to, without the `light.` prefix.
+    pub lights: Vec<String>,
+
+    /// Whether the helper is enabled at startup.
+    #[serde(default = "CircadianConfig::default_enabled")]
+    pub enabled: bool,
+
+    /// The warmest color temperature, used at night.
+    #[serde(default = "CircadianConfig::default_min_color_temp_kelvin")]
+    pub min_color_temp_kelvin: u16,
+
+    /// The coldest color temperature, used at solar noon.
+    #[serde(default = "CircadianConfig::default_max_color_temp_kelvin")]
+    pub max_color_temp_kelvin: u16,
+
+    /// The lowest brightness, used at night.
+    #[serde(default = "CircadianConfig::default_min_brightness_pct")]
+    pub min_brightness_pct: u8,
+
+    /// The highest brightness, used at solar noon.
+    #[serde(default = "CircadianConfig::default_max_brightness_pct")]
+    pub max_brightness_pct: u8,
+}
+
+impl CircadianConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_min_color_temp_kelvin() -> u16 {
+        2200
+    }
+
+    fn default_max_color_temp_kelvin() -> u16 {
+        5500
+    }
+
+    fn default_min_brightness_pct() -> u8 {
+        30
+    }
+
+    fn default_max_brightness_pct() -> u8 {
+        100
+    }
+}
+
+/// The light settings computed for a given time of day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircadianSettings {
+    pub color_temp_kelvin: u16,
+    pub brightness_pct: u8,
+}
+
+impl CircadianSettings {
+    /// Get the `light.turn_on` service data for these settings.
+    pub fn to_service_data(self) -> serde_json::Value {
+        json!({
+            "color_temp_kelvin": self.color_temp_kelvin,
+            "brightness_pct": self.brightness_pct,
+        })
+    }
+}
+
+/// Computes light settings from the time of day.
+pub struct Circadian {
+    config: CircadianConfig,
+    enabled: AtomicBool,
+}
+
+impl Circadian {
+    pub fn new(config: CircadianConfig) -> Self {
+        let enabled = AtomicBool::new(config.enabled);
+
+        Self { config, enabled }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Check whether the specified light is managed by the helper.
+    pub fn manages(&self, light: &str) -> bool {
+        self.config.lights.iter().any(|l| l == light)
+    }
+
+    /// Get the settings to apply to the specified light right now, if any.
+    pub fn settings_for(&self, light: &str) -> Option<CircadianSettings> {
+        if self.enabled() && self.manages(light) {
+            Some(self.settings_at(Local::now().time()))
+        } else {
+            None
+        }
+    }
+
+    /// Compute the settings for the specified time of day.
+    ///
+    /// The curve follows a cosine that peaks at noon and bottoms out at midnight.
+    pub fn settings_at(&self, time: NaiveTime) -> CircadianSettings {
+        let hours = time.num_seconds_from_midnight() as f64 / 3600.0;
+        let factor = (((hours - 12.0) / 12.0 * PI).cos() + 1.0) / 2.0;
+
+        let lerp = |min: f64, max: f64| min + (max - min) * factor;
+
+        CircadianSettings {
+            color_temp_kelvin: lerp(
+                self.config.min_color_temp_kelvin.into(),
+                self.config.max_color_temp_kelvin.into(),
+            )
+            .round() as u16,
+            brightness_pct: lerp(
+                self.config.min_brightness_pct.into(),
+                self.config.max_brightness_pct.into(),
+            )
+            .round() as u8,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::{future::Future, net::IpAddr};
+
+use ipnet::IpNet;
+use warp::http::HeaderMap;
+
+/// The header listing the clients and the proxies a request went through.
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// The header carrying the client, as set by nginx.
+const REAL_IP_HEADER: &str = "x-real-ip";
+
+tokio::task_local! {
+    static CLIENT_IP: IpAddr;
+}
+
+/// Get the address of the client of the API request being handled, if any.
+pub fn current() -> Option<IpAddr> {
+    CLIENT_IP.try_with(Clone::clone).ok()
+}
+
+/// Run a future within the scope of a client address.
+pub async fn scope<F: Future>(client_ip: IpAddr, f: F) -> F::Output {
+    CLIENT_IP.scope(client_ip, f).await
+}
+
+/// Get the address of the client of a request, from the peer address.
+///
+/// The `x-forwarded-for` and `x-real-ip` headers are only honored when the
+/// peer is a trusted proxy, as anyone else could forge them: the client is
+/// then the last address of `x-forwarded-for` that is not a trusted proxy,
+/// or `x-real-ip` without one.
+pub fn resolve(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+
+    if !is_trusted(&peer) {
+        return peer;
+    }
+
+    let forwarded_for: Vec<&str> = headers
+        .get_all(FORWARDED_FOR_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+
+    if forwarded_for.is_empty() {
+        return headers
+            .get(REAL_IP_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(peer);
+    }
+
+    // The addresses are appended by each proxy: walk them back from the
+    // peer, through the trusted proxies only.
+    let mut client_ip = peer;
+
+    for ip in forwarded_for.iter().rev() {
+        match ip.parse() {
+            Ok(ip) if is_trusted(&ip) => client_ip = ip,
+            Ok(ip) => return ip,
+            Err(_) => break,
+        }
+    }
+
+    client_ip
+}
+
+#[cfg(test)]
+mod tests;
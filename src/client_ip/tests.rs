@@ -0,0 +1,94 @@
+//! Tests of the resolution of the client addresses behind proxies.
+
+use warp::http::HeaderValue;
+
+use super::*;
+
+fn ip(ip: &str) -> IpAddr {
+    ip.parse().unwrap()
+}
+
+fn header_map(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    for (name, value) in pairs {
+        headers.append(*name, HeaderValue::from_static(value));
+    }
+
+    headers
+}
+
+fn trusted_proxies() -> Vec<IpNet> {
+    vec![
+        "127.0.0.1/32".parse().unwrap(),
+        "10.0.0.0/8".parse().unwrap(),
+    ]
+}
+
+#[test]
+fn resolve_ignores_the_headers_of_untrusted_peers() {
+    let headers = header_map(&[
+        ("x-forwarded-for", "192.168.1.10"),
+        ("x-real-ip", "192.168.1.10"),
+    ]);
+
+    assert_eq!(
+        resolve(ip("192.168.1.20"), &headers, &trusted_proxies()),
+        ip("192.168.1.20")
+    );
+    assert_eq!(resolve(ip("127.0.0.1"), &headers, &[]), ip("127.0.0.1"));
+}
+
+#[test]
+fn resolve_walks_back_the_trusted_proxies() {
+    let trusted_proxies = trusted_proxies();
+
+    // A forged first address is skipped, as it was not added by a proxy.
+    let headers = header_map(&[("x-forwarded-for", "1.2.3.4, 192.168.1.10, 10.0.0.2")]);
+
+    assert_eq!(
+        resolve(ip("127.0.0.1"), &headers, &trusted_proxies),
+        ip("192.168.1.10")
+    );
+
+    // The proxies can also append their own header.
+    let headers = header_map(&[
+        ("x-forwarded-for", "192.168.1.10"),
+        ("x-forwarded-for", "10.0.0.2"),
+    ]);
+
+    assert_eq!(
+        resolve(ip("127.0.0.1"), &headers, &trusted_proxies),
+        ip("192.168.1.10")
+    );
+
+    // Only proxies: the first one is the client.
+    let headers = header_map(&[("x-forwarded-for", "10.0.0.3, 10.0.0.2")]);
+
+    assert_eq!(
+        resolve(ip("127.0.0.1"), &headers, &trusted_proxies),
+        ip("10.0.0.3")
+    );
+
+    // An invalid address stops the walk.
+    let headers = header_map(&[("x-forwarded-for", "192.168.1.10, unknown, 10.0.0.2")]);
+
+    assert_eq!(
+        resolve(ip("127.0.0.1"), &headers, &trusted_proxies),
+        ip("10.0.0.2")
+    );
+}
+
+#[test]
+fn resolve_falls_back_to_the_real_ip() {
+    let headers = header_map(&[("x-real-ip", "192.168.1.10")]);
+
+    assert_eq!(
+        resolve(ip("127.0.0.1"), &headers, &trusted_proxies()),
+        ip("192.168.1.10")
+    );
+    assert_eq!(
+        resolve(ip("127.0.0.1"), &HeaderMap::new(), &trusted_proxies()),
+        ip("127.0.0.1")
+    );
+}
@@ -0,0 +1,324 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::{sync::Mutex, task::JoinHandle};
+
+use crate::{
+    home_assistant::{Controller, State},
+    Result,
+};
+
+/// The configuration for the thermostat boost override.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClimateBoostConfig {
+    /// The amount of degrees to add to the current setpoint.
+    #[serde(default = "ClimateBoostConfig::default_delta")]
+    pub delta: f64,
+
+    /// The duration of the boost, in seconds.
+    #[serde(default = "ClimateBoostConfig::default_duration")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub duration: Duration,
+}
+
+impl Default for ClimateBoostConfig {
+    fn default() -> Self {
+        Self {
+            delta: Self::default_delta(),
+            duration: Self::default_duration(),
+        }
+    }
+}
+
+impl ClimateBoostConfig {
+    fn default_delta() -> f64 {
+        2.0
+    }
+
+    fn default_duration() -> Duration {
+        Duration::from_secs(30 * 60)
+    }
+}
+
+/// The status of an active boost.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClimateBoostStatus {
+    pub entity_id: String,
+    pub previous_setpoint: f64,
+    pub boosted_setpoint: f64,
+    pub until: DateTime<Utc>,
+}
+
+/// The delay before retrying a failed restore, doubled on every failure in a
+/// row.
+const RESTORE_RETRY_DELAY: Duration = Duration::from_secs(5);
+const MAX_RESTORE_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// The setpoints of the climate entities, as read and written by the
+/// booster.
+pub trait Setpoints: Clone + Send + Sync + 'static {
+    fn setpoint(&self, entity_id: &str) -> impl Future<Output = Result<f64>> + Send;
+
+    fn set_setpoint(
+        &self,
+        entity_id: &str,
+        setpoint: f64,
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl Setpoints for Controller {
+    async fn setpoint(&self, entity_id: &str) -> Result<f64> {
+        let state = self
+            .entity(entity_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("climate entity `{}` was not found", entity_id))?;
+
+        state.attributes.get("temperature").ok_or_else(|| {
+            anyhow::anyhow!("climate entity `{}` has no target temperature", entity_id).into()
+        })
+    }
+
+    async fn set_setpoint(&self, entity_id: &str, setpoint: f64) -> Result<()> {
+        self.climate_set_temperature(entity_id, setpoint).await
+    }
+}
+
+struct ClimateBoost {
+    /// The boost, told apart from the next ones of the same entity.
+    id: u64,
+    status: ClimateBoostStatus,
+    restore_task: JoinHandle<()>,
+}
+
+type Boosts = Arc<Mutex<HashMap<String, ClimateBoost>>>;
+
+/// Raises climate setpoints temporarily and restores them afterwards.
+///
+/// The previous setpoints are tracked locally, so a restart during a boost
+/// leaves the boosted setpoint in place.
+pub struct ClimateBooster<S = Controller> {
+    config: ClimateBoostConfig,
+    setpoints: S,
+    boosts: Boosts,
+    next_id: AtomicU64,
+}
+
+impl<S: Setpoints> ClimateBooster<S> {
+    pub fn new(config: ClimateBoostConfig, setpoints: S) -> Self {
+        Self {
+            config,
+            setpoints,
+            boosts: Default::default(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Boost the specified climate entity.
+    ///
+    /// Boosting an already boosted entity extends the boost but does not
+    /// stack the deltas. A failed boost leaves the previous one, if any, as
+    /// it was.
+    pub async fn boost(&self, entity_id: &str) -> Result<ClimateBoostStatus> {
+        let boosted = self
+            .boosts
+            .lock()
+            .await
+            .get(entity_id)
+            .map(|boost| boost.status.previous_setpoint);
+
+        let previous_setpoint = match boosted {
+            Some(previous_setpoint) => previous_setpoint,
+            None => self.setpoints.setpoint(entity_id).await?,
+        };
+
+        let boosted_setpoint = previous_setpoint + self.config.delta;
+
+        info!(
+            "Boosting `{}` from {} to {} for {:.0}s.",
+            entity_id,
+            previous_setpoint,
+            boosted_setpoint,
+            self.config.duration.as_secs_f64()
+        );
+
+        self.setpoints
+            .set_setpoint(entity_id, boosted_setpoint)
+            .await?;
+
+        let status = ClimateBoostStatus {
+            entity_id: entity_id.to_string(),
+            previous_setpoint,
+            boosted_setpoint,
+            until: Utc::now()
+                + chrono::Duration::from_std(self.config.duration)
+                    .unwrap_or_else(|_| chrono::Duration::zero()),
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut boosts = self.boosts.lock().await;
+        let restore_task = tokio::spawn(Self::restore_after(
+            self.setpoints.clone(),
+            Arc::clone(&self.boosts),
+            entity_id.to_string(),
+            id,
+            previous_setpoint,
+            self.config.duration,
+        ));
+
+        if let Some(boost) = boosts.insert(
+            entity_id.to_string(),
+            ClimateBoost {
+                id,
+                status: status.clone(),
+                restore_task,
+            },
+        ) {
+            boost.restore_task.abort();
+        }
+
+        Ok(status)
+    }
+
+    /// Get the active boost for the specified climate entity, if any.
+    pub async fn status(&self, entity_id: &str) -> Option<ClimateBoostStatus> {
+        self.boosts
+            .lock()
+            .await
+            .get(entity_id)
+            .map(|boost| boost.status.clone())
+    }
+
+    /// Restore the setpoint once the boost expires, retrying until it
+    /// succeeds or a newer boost replaces this one.
+    async fn restore_after(
+        setpoints: S,
+        boosts: Boosts,
+        entity_id: String,
+        id: u64,
+        previous_setpoint: f64,
+        duration: Duration,
+    ) {
+        tokio::time::sleep(duration).await;
+
+        info!(
+            "Boost of `{}` expired: restoring setpoint to {}.",
+            entity_id, previous_setpoint
+        );
+
+        let mut delay = RESTORE_RETRY_DELAY;
+
+        while let Err(err) = setpoints.set_setpoint(&entity_id, previous_setpoint).await {
+            warn!(
+                "Failed to restore the setpoint of `{}` to {}, retrying in {:?}: {}",
+                entity_id, previous_setpoint, delay, err
+            );
+
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RESTORE_RETRY_DELAY);
+        }
+
+        let mut boosts = boosts.lock().await;
+
+        if boosts.get(&entity_id).is_some_and(|boost| boost.id == id) {
+            boosts.remove(&entity_id);
+        }
+    }
+}
+
+/// The heating state of a climate entity.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatingRoom {
+    pub entity_id: String,
+    pub name: Option<String>,
+    pub hvac_mode: String,
+    pub hvac_action: Option<String>,
+    pub setpoint: Option<f64>,
+    pub current_temperature: Option<f64>,
+    pub calling_for_heat: bool,
+}
+
+/// The heating state of all the climate entities.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatingSummary {
+    pub rooms: Vec<HeatingRoom>,
+
+    /// The number of rooms calling for heat.
+    pub calling: usize,
+
+    /// The sum of the degrees missing to reach the setpoints, over the rooms
+    /// calling for heat.
+    pub demand: f64,
+}
+
+impl HeatingRoom {
+    fn from_state(state: &State) -> Self {
+        let number = |name| state.attributes.get::<f64>(name);
+        let hvac_action = state.attributes.get::<String>("hvac_action");
+        let setpoint = number("temperature");
+        let current_temperature = number("current_temperature");
+
+        // Not all thermostats report what they are doing.
+        let calling_for_heat = match hvac_action.as_deref() {
+            Some(hvac_action) => hvac_action == "heating",
+            None => {
+                matches!(state.state.as_str(), "heat" | "heat_cool" | "auto")
+                    && matches!((current_temperature, setpoint), (Some(current), Some(setpoint)) if current < setpoint)
+            }
+        };
+
+        Self {
+            entity_id: state.entity_id.clone(),
+            name: state.attributes.get("friendly_name"),
+            hvac_mode: state.state.clone(),
+            hvac_action,
+            setpoint,
+            current_temperature,
+            calling_for_heat,
+        }
+    }
+}
+
+impl HeatingSummary {
+    /// Summarize the heating state of the available `climate` entities.
+    pub fn new(entities: &HashMap<String, State>) -> Self {
+        let mut rooms: Vec<_> = entities
+            .values()
+            .filter(|state| state.entity_id.starts_with("climate.") && state.is_available())
+            .map(HeatingRoom::from_state)
+            .collect();
+
+        rooms.sort_by(|a, b| a.entity_id.cmp(&b.entity_id));
+
+        let calling = rooms.iter().filter(|room| room.calling_for_heat).count();
+        let demand = rooms
+            .iter()
+            .filter(|room| room.calling_for_heat)
+            .filter_map(|room| Some((room.setpoint? - room.current_temperature?).max(0.0)))
+            .sum();
+
+        Self {
+            rooms,
+            calling,
+            demand,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,135 @@
+//! Tests of the climate boosts.
+
+use std::sync::Mutex as StdMutex;
+
+use super::*;
+
+const ENTITY_ID: &str = "climate.living_room";
+
+/// The setpoints of a thermostat whose calls fail on demand.
+#[derive(Clone, Default)]
+struct FakeSetpoints {
+    setpoint: Arc<StdMutex<f64>>,
+    failing: Arc<StdMutex<bool>>,
+}
+
+impl FakeSetpoints {
+    fn new(setpoint: f64) -> Self {
+        let setpoints = Self::default();
+
+        *setpoints.setpoint.lock().unwrap() = setpoint;
+
+        setpoints
+    }
+
+    fn get(&self) -> f64 {
+        *self.setpoint.lock().unwrap()
+    }
+
+    fn fail(&self, failing: bool) {
+        *self.failing.lock().unwrap() = failing;
+    }
+}
+
+impl Setpoints for FakeSetpoints {
+    async fn setpoint(&self, _entity_id: &str) -> Result<f64> {
+        Ok(self.get())
+    }
+
+    async fn set_setpoint(&self, _entity_id: &str, setpoint: f64) -> Result<()> {
+        if *self.failing.lock().unwrap() {
+            return Err(anyhow::anyhow!("Wi-Fi blip").into());
+        }
+
+        *self.setpoint.lock().unwrap() = setpoint;
+
+        Ok(())
+    }
+}
+
+fn booster(setpoints: &FakeSetpoints) -> ClimateBooster<FakeSetpoints> {
+    ClimateBooster::new(
+        ClimateBoostConfig {
+            delta: 2.0,
+            duration: Duration::from_secs(60),
+        },
+        setpoints.clone(),
+    )
+}
+
+/// Let the restore tasks run for a while.
+async fn wait(secs: u64) {
+    tokio::time::sleep(Duration::from_secs(secs)).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_setpoint_is_restored_after_the_boost() {
+    let setpoints = FakeSetpoints::new(19.0);
+    let booster = booster(&setpoints);
+
+    booster.boost(ENTITY_ID).await.unwrap();
+
+    assert_eq!(setpoints.get(), 21.0);
+
+    wait(61).await;
+
+    assert_eq!(setpoints.get(), 19.0);
+    assert!(booster.status(ENTITY_ID).await.is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_failed_reboost_keeps_the_previous_restore() {
+    let setpoints = FakeSetpoints::new(19.0);
+    let booster = booster(&setpoints);
+
+    booster.boost(ENTITY_ID).await.unwrap();
+    setpoints.fail(true);
+
+    assert!(booster.boost(ENTITY_ID).await.is_err());
+
+    setpoints.fail(false);
+    wait(61).await;
+
+    assert_eq!(setpoints.get(), 19.0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn failed_restores_are_retried() {
+    let setpoints = FakeSetpoints::new(19.0);
+    let booster = booster(&setpoints);
+
+    booster.boost(ENTITY_ID).await.unwrap();
+    setpoints.fail(true);
+    wait(120).await;
+
+    assert_eq!(setpoints.get(), 21.0);
+    assert!(booster.status(ENTITY_ID).await.is_some());
+
+    setpoints.fail(false);
+    wait(300).await;
+
+    assert_eq!(setpoints.get(), 19.0);
+    assert!(booster.status(ENTITY_ID).await.is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn reboosting_extends_without_stacking() {
+    let setpoints = FakeSetpoints::new(19.0);
+    let booster = booster(&setpoints);
+
+    booster.boost(ENTITY_ID).await.unwrap();
+    wait(30).await;
+
+    let status = booster.boost(ENTITY_ID).await.unwrap();
+
+    assert_eq!(status.previous_setpoint, 19.0);
+    assert_eq!(setpoints.get(), 21.0);
+
+    wait(45).await;
+
+    assert_eq!(setpoints.get(), 21.0);
+
+    wait(30).await;
+
+    assert_eq!(setpoints.get(), 19.0);
+}
@@ -0,0 +1,174 @@
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    home_assistant::{Controller, Update},
+    notifications::{Notifications, Severity},
+    tasks,
+};
+
+const NOTIFICATION_ID: &str = "clock-skew";
+
+/// The detection of the skew of the local clock with the one of
+/// Home-Assistant.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClockSkewConfig {
+    /// The skew above which a notification is raised.
+    #[serde(default = "ClockSkewConfig::default_threshold")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub threshold: Duration,
+
+    /// The number of recent events to estimate the skew from.
+    #[serde(default = "ClockSkewConfig::default_samples")]
+    pub samples: usize,
+}
+
+impl Default for ClockSkewConfig {
+    fn default() -> Self {
+        Self {
+            threshold: Self::default_threshold(),
+            samples: Self::default_samples(),
+        }
+    }
+}
+
+impl ClockSkewConfig {
+    fn default_threshold() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn default_samples() -> usize {
+        20
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockSkewStatus {
+    /// How far ahead of Home-Assistant the local clock is, in seconds, once
+    /// an event was received.
+    pub skew_seconds: Option<f64>,
+    pub threshold_seconds: f64,
+
+    /// Whether the skew exceeds the threshold.
+    pub skewed: bool,
+}
+
+/// Estimates the skew of the local clock from the events of Home-Assistant.
+///
+/// Events are received after they are fired, so the smallest delay between
+/// their firing and their reception over the recent events is the closest to
+/// the skew.
+pub struct ClockSkew {
+    config: ClockSkewConfig,
+    delays: Mutex<VecDeque<f64>>,
+}
+
+impl ClockSkew {
+    pub fn new(config: ClockSkewConfig) -> Self {
+        Self {
+            config,
+            delays: Mutex::default(),
+        }
+    }
+
+    /// Record an event fired and received at the specified times.
+    pub fn record(&self, fired: DateTime<Utc>, received: DateTime<Utc>) {
+        let delay = (received - fired).num_milliseconds() as f64 / 1000.0;
+        let mut delays = self.delays.lock().unwrap();
+
+        delays.push_back(delay);
+
+        while delays.len() > self.config.samples.max(1) {
+            delays.pop_front();
+        }
+    }
+
+    /// Get the estimated skew in seconds, positive if the local clock is
+    /// ahead.
+    pub fn skew(&self) -> Option<f64> {
+        self.delays.lock().unwrap().iter().copied().reduce(f64::min)
+    }
+
+    pub fn status(&self) -> ClockSkewStatus {
+        let skew_seconds = self.skew();
+        let threshold_seconds = self.config.threshold.as_secs_f64();
+
+        ClockSkewStatus {
+            skew_seconds,
+            threshold_seconds,
+            skewed: skew_seconds.is_some_and(|skew| skew.abs() > threshold_seconds),
+        }
+    }
+
+    /// Estimate the skew forever, raising a notification while it exceeds
+    /// the threshold.
+    pub async fn run(
+        &self,
+        home_assistant: &Controller,
+        notifications: &Notifications,
+    ) -> anyhow::Result<()> {
+        let mut updates = home_assistant.subscribe_updates();
+
+        loop {
+            match updates.recv().await {
+                Ok(Update::Event(event)) => {
+                    if let Some(fired) = event.time_fired() {
+                        self.record(fired, Utc::now());
+                        self.check(notifications).await;
+                    }
+                }
+
+                // Another instance may answer after a reconnection.
+                Ok(Update::Connected) => self.delays.lock().unwrap().clear(),
+                Ok(Update::Disconnected) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Ok(()),
+            }
+
+            tasks::heartbeat();
+        }
+    }
+
+    async fn check(&self, notifications: &Notifications) {
+        let status = self.status();
+
+        match status.skew_seconds {
+            Some(skew) if status.skewed => {
+                let raised = notifications
+                    .raise(
+                        NOTIFICATION_ID,
+                        Severity::Warning,
+                        "Clock skew",
+                        format!(
+                            "The clock of the panel is {:.0} seconds {} of Home-Assistant: the times and schedules may be wrong.",
+                            skew.abs(),
+                            if skew > 0.0 { "ahead" } else { "behind" }
+                        ),
+                    )
+                    .await;
+
+                if raised {
+                    warn!(
+                        "The local clock is off by {:.1}s from the one of Home-Assistant.",
+                        skew
+                    );
+                }
+            }
+            _ => {
+                if notifications.clear(NOTIFICATION_ID).await {
+                    info!("The local clock is back in sync with Home-Assistant.");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,38 @@
+//! Tests of the estimation of the clock skew.
+
+use chrono::TimeZone;
+
+use super::*;
+
+fn at(seconds: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(1_700_000_000 + seconds, 0).unwrap()
+}
+
+#[test]
+fn estimates_the_skew_from_the_smallest_delay() {
+    let clock_skew = ClockSkew::new(ClockSkewConfig::default());
+
+    assert_eq!(clock_skew.skew(), None);
+
+    clock_skew.record(at(0), at(42));
+    clock_skew.record(at(10), at(50));
+    clock_skew.record(at(20), at(65));
+
+    assert_eq!(clock_skew.skew(), Some(40.0));
+    assert!(clock_skew.status().skewed);
+}
+
+#[test]
+fn forgets_the_older_events() {
+    let clock_skew = ClockSkew::new(ClockSkewConfig {
+        samples: 2,
+        ..Default::default()
+    });
+
+    clock_skew.record(at(0), at(-60));
+    clock_skew.record(at(10), at(11));
+    clock_skew.record(at(20), at(22));
+
+    assert_eq!(clock_skew.skew(), Some(1.0));
+    assert!(!clock_skew.status().skewed);
+}
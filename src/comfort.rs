@@ -0,0 +1,162 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{air_quality::AirQualityStatus, indoor::IndoorStatus};
+
+/// The indoor comfort configuration.
+///
+/// The temperature and humidity come from the indoor sensors, and the CO2
+/// from the air quality sensors.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ComfortConfig {
+    /// The temperature comfort band, in °C.
+    #[serde(default = "ComfortConfig::default_temperature")]
+    pub temperature: ComfortBand,
+
+    /// The relative humidity comfort band, in %.
+    #[serde(default = "ComfortConfig::default_humidity")]
+    pub humidity: ComfortBand,
+
+    /// The CO2 comfort band, in ppm.
+    #[serde(default = "ComfortConfig::default_co2")]
+    pub co2: ComfortBand,
+}
+
+/// The ranges of comfortable values.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ComfortBand {
+    /// The range of ideal values.
+    pub ideal: [f64; 2],
+
+    /// The range of acceptable values, which contains the ideal range.
+    pub acceptable: [f64; 2],
+}
+
+impl Default for ComfortConfig {
+    fn default() -> Self {
+        Self {
+            temperature: Self::default_temperature(),
+            humidity: Self::default_humidity(),
+            co2: Self::default_co2(),
+        }
+    }
+}
+
+impl ComfortConfig {
+    fn default_temperature() -> ComfortBand {
+        ComfortBand {
+            ideal: [20.0, 23.0],
+            acceptable: [18.0, 25.0],
+        }
+    }
+
+    fn default_humidity() -> ComfortBand {
+        ComfortBand {
+            ideal: [40.0, 60.0],
+            acceptable: [30.0, 70.0],
+        }
+    }
+
+    fn default_co2() -> ComfortBand {
+        ComfortBand {
+            ideal: [0.0, 800.0],
+            acceptable: [0.0, 1200.0],
+        }
+    }
+
+    /// Compute the comfort from the indoor and air quality statuses.
+    ///
+    /// Returns `None` if no reading is available.
+    pub fn status(
+        &self,
+        indoor: Option<&IndoorStatus>,
+        air_quality: Option<&AirQualityStatus>,
+    ) -> Option<ComfortStatus> {
+        let temperature = indoor.map(|indoor| self.temperature.reading(indoor.temperature));
+        let humidity = indoor.map(|indoor| self.humidity.reading(indoor.humidity));
+        let co2 = air_quality
+            .and_then(|air_quality| air_quality.co2.as_ref())
+            .map(|co2| self.co2.reading(co2.value));
+
+        // The least comfortable reading is the one that gets noticed.
+        let worst = [&temperature, &humidity, &co2]
+            .into_iter()
+            .flatten()
+            .min_by(|a, b| a.score.total_cmp(&b.score))?;
+
+        Some(ComfortStatus {
+            score: worst.score,
+            level: worst.level,
+            temperature,
+            humidity,
+            co2,
+        })
+    }
+}
+
+impl ComfortBand {
+    /// Score a value from 0 to 100: 100 in the ideal range, 50 at the edges
+    /// of the acceptable range, and 0 as far again beyond them.
+    fn reading(&self, value: f64) -> ComfortReading {
+        let [ideal_min, ideal_max] = self.ideal;
+        let [acceptable_min, acceptable_max] = self.acceptable;
+
+        let (distance, margin) = if value < ideal_min {
+            (ideal_min - value, ideal_min - acceptable_min)
+        } else if value > ideal_max {
+            (value - ideal_max, acceptable_max - ideal_max)
+        } else {
+            (0.0, 0.0)
+        };
+
+        let score = if distance == 0.0 {
+            100.0
+        } else if margin <= 0.0 {
+            0.0
+        } else {
+            (100.0 - 50.0 * distance / margin).max(0.0)
+        };
+        let level = if distance == 0.0 {
+            ComfortLevel::Good
+        } else if distance <= margin {
+            ComfortLevel::Fair
+        } else {
+            ComfortLevel::Poor
+        };
+
+        ComfortReading {
+            value,
+            score,
+            level,
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum ComfortLevel {
+    Good,
+    Fair,
+    Poor,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComfortReading {
+    pub value: f64,
+    pub score: f64,
+    pub level: ComfortLevel,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComfortStatus {
+    /// The overall score, from 0 to 100.
+    pub score: f64,
+    pub level: ComfortLevel,
+    pub temperature: Option<ComfortReading>,
+    pub humidity: Option<ComfortReading>,
+    pub co2: Option<ComfortReading>,
+}
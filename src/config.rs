@@ -1,23 +1,139 @@
-use std::{net::SocketAddr, path::PathBuf, time::Duration};
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use anyhow::Context;
 use clap::Parser;
 use serde::Deserialize;
 use serde_with::{serde_as, DurationSeconds};
 
+use crate::{
+    air_quality::AirQualityConfig,
+    alarm_indicator::AlarmIndicatorConfig,
+    ambient_light::AmbientLightConfig,
+    api::{ApiVersionsConfig, RouteLimitsConfig, StatusConfig},
+    appliances::ApplianceConfig,
+    artwork::ArtworkConfig,
+    astronomy::AstronomyConfig,
+    audio::AudioConfig,
+    auth::AuthConfig,
+    barcode::BarcodeConfig,
+    camera::CameraConfig,
+    changelog::ChangelogConfig,
+    chores::ChoresConfig,
+    circadian::CircadianConfig,
+    climate::ClimateBoostConfig,
+    clock_skew::ClockSkewConfig,
+    comfort::ComfortConfig,
+    dashboard::{DashboardConfig, LightConfig},
+    departures::{DepartureSource, DeparturesConfig},
+    digest::DigestConfig,
+    disk::DiskConfig,
+    energy_meter::EnergyMeterConfig,
+    error_policy::ErrorPolicyConfig,
+    esphome::EsphomeNodeConfig,
+    extra_sensors::ExtraSensorConfig,
+    fan::FanConfig,
+    forecast::ForecastCacheConfig,
+    frost::FrostConfig,
+    gestures::GesturesConfig,
+    hazards::HazardsConfig,
+    history::HistoryConfig,
+    holidays::HolidaysConfig,
+    home_assistant::AuthFailurePolicy,
+    indoor::IndoorConfig,
+    inputs::{usb::UsbInputConfig, InputConfig},
+    intercom::IntercomConfig,
+    ir::IrConfig,
+    irrigation::IrrigationConfig,
+    kiosk::KioskConfig,
+    latency::LatencyConfig,
+    lockout::LockoutConfig,
+    media_groups::MediaGroupConfig,
+    migrations,
+    mirrors::MirrorConfig,
+    mqtt::MqttConfig,
+    net_check::NetCheckConfig,
+    network::NetworkConfig,
+    nowcast::{NowcastConfig, NowcastSource},
+    outputs::Relay,
+    overrides::ConfigOverrides,
+    panic::PanicConfig,
+    polling::PollConfig,
+    presence::PresenceConfig,
+    recording::Recorder,
+    redact,
+    reminders::{ReminderConfig, ReminderSchedule},
+    rf::RfConfig,
+    rfid::RfidConfig,
+    rules::engine::RuleConfig,
+    screen::ScreenConfig,
+    screensaver::ScreensaverConfig,
+    secrets::{self, KeySource},
+    self_check::SelfCheckConfig,
+    serial_devices::SerialDeviceConfig,
+    server::ServerConfig,
+    shutdown::ShutdownConfig,
+    simulation::Simulation,
+    sleep_timer::SleepTimerConfig,
+    sound_level::SoundLevelConfig,
+    theme::ThemeConfig,
+    thermostat::{TemperatureSensor, ThermostatConfig},
+    ups::UpsConfig,
+    usage::UsageConfig,
+    users::UserConfig,
+    voice::VoiceConfig,
+    wakeup::WakeupConfig,
+    weather_alerts::WeatherAlertsConfig,
+    weather_conditions::WeatherConditionsConfig,
+    windows::{OpeningsConfig, RoomWindowsConfig},
+};
+
 const DEFAULT_RED_LED_PIN: &str = "17";
 const DEFAULT_GREEN_LED_PIN: &str = "27";
 const DEFAULT_BUZZER_PIN: &str = "18";
 const DEFAULT_TRIGGER_PIN: &str = "24";
 const DEFAULT_ECHO_PIN: &str = "23";
 
+/// The systemd credential holding the Home Assistant token, as loaded by
+/// `LoadCredential=ha_token:<file>`.
+pub const HOME_ASSISTANT_TOKEN_CREDENTIAL: &str = "ha_token";
+
+/// The SQLite path of the databases kept in memory.
+const IN_MEMORY_DATABASE: &str = ":memory:";
+
 pub struct Config {
     pub debug: bool,
+
+    /// Whether running in a container, which logs JSON lines to stdout.
+    pub docker: bool,
     pub home_control_config: HomeControlConfig,
+
+    /// The configuration as loaded, without the secrets, for the bug
+    /// reports.
+    pub redacted_config: serde_json::Value,
+
+    /// Where the configuration was read from, the ones taking precedence
+    /// last.
+    pub config_sources: Vec<String>,
+    pub overrides: ConfigOverrides,
+
+    /// The file the configuration edited from the frontend is saved to, if
+    /// any.
+    pub overrides_file: Option<PathBuf>,
     pub listen_endpoint: SocketAddr,
     pub reverse_proxy_url: Option<String>,
+    pub api_prefix: String,
+    pub api_v2_prefix: String,
+    pub static_prefix: String,
     pub gpio_config: GpioConfig,
     pub home_assistant_endpoint: String,
     pub home_assistant_token: String,
+    pub simulation: Option<Simulation>,
+    pub recorder: Option<Recorder>,
 }
 
 pub struct GpioConfig {
@@ -32,12 +148,33 @@ pub struct GpioConfig {
 #[serde_as]
 #[derive(Debug, Clone, Deserialize)]
 pub struct HomeControlConfig {
+    /// The version of the configuration, none for the version 1. The older
+    /// configuration files are upgraded on startup.
+    #[serde(default)]
+    pub version: Option<u64>,
+
     /// The location to display in the UI.
     pub location: String,
 
     /// The entity to fetch the weather from.
     pub weather_entity: String,
 
+    /// The labels and icons of the weather conditions.
+    #[serde(default)]
+    pub weather_conditions: WeatherConditionsConfig,
+
+    /// The weather alerts configuration.
+    #[serde(default)]
+    pub weather_alerts: Option<WeatherAlertsConfig>,
+
+    /// The coordinates to compute the moon phase and the sun events for.
+    #[serde(default)]
+    pub astronomy: Option<AstronomyConfig>,
+
+    /// The windshield frost warning, from the forecast of the night.
+    #[serde(default)]
+    pub frost: Option<FrostConfig>,
+
     /// Sensor activation distance.
     #[serde(default = "HomeControlConfig::default_sensor_activation_distance")]
     pub sensor_activation_distance_cm: f64,
@@ -48,9 +185,385 @@ pub struct HomeControlConfig {
     #[serde(default = "HomeControlConfig::default_presence_inactivity_timeout")]
     #[serde_as(as = "DurationSeconds<f64>")]
     pub presence_inactivity_timeout: Duration,
+
+    /// The presence polling configuration.
+    #[serde(default)]
+    pub presence: PresenceConfig,
+
+    /// The gestures detected over the distance sensor, if any.
+    #[serde(default)]
+    pub gestures: Option<GesturesConfig>,
+
+    /// The window during which successive slider values for the same entity
+    /// are coalesced into the last one.
+    #[serde(default = "HomeControlConfig::default_command_debounce_window")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub command_debounce_window: Duration,
+
+    /// How long the connection to Home Assistant must be lost for before the
+    /// outputs apply their `on_disconnect` policy.
+    #[serde(default = "HomeControlConfig::default_disconnect_timeout")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub disconnect_timeout: Duration,
+
+    /// Whether control actions chirp the buzzer, distinctly on success and
+    /// failure. The buzzer stays silent during the presence quiet hours.
+    #[serde(default)]
+    pub buzzer_feedback: bool,
+
+    /// The dashboard panels. Can be edited from the frontend.
+    #[serde(default)]
+    pub dashboard: DashboardConfig,
+
+    /// The recent changes of the entities to keep by area, if any.
+    #[serde(default)]
+    pub changelog: Option<ChangelogConfig>,
+
+    /// The light buttons of the sidebar. Can be edited from the frontend.
+    #[serde(default)]
+    pub lights: Vec<LightConfig>,
+
+    /// The adaptive/circadian lighting configuration.
+    #[serde(default)]
+    pub circadian: Option<CircadianConfig>,
+
+    /// The thermostat boost configuration.
+    #[serde(default)]
+    pub climate_boost: ClimateBoostConfig,
+
+    /// The rooms to watch for open windows while heating.
+    #[serde(default)]
+    pub windows: Vec<RoomWindowsConfig>,
+
+    /// The doors and windows to summarize, if any.
+    #[serde(default)]
+    pub openings: Option<OpeningsConfig>,
+
+    /// The leak and smoke sensors, if any.
+    #[serde(default)]
+    pub hazards: Option<HazardsConfig>,
+
+    /// The indoor climate sensors configuration.
+    #[serde(default)]
+    pub indoor: Option<IndoorConfig>,
+
+    /// The air quality sensors configuration.
+    #[serde(default)]
+    pub air_quality: Option<AirQualityConfig>,
+
+    /// The comfort score configuration. The score is computed from the
+    /// indoor and air quality sensors, if any.
+    #[serde(default)]
+    pub comfort: ComfortConfig,
+
+    /// Arbitrary sensors to display in the UI.
+    #[serde(default)]
+    pub extra_sensors: Vec<ExtraSensorConfig>,
+
+    /// Recurring reminders, like the waste collection.
+    #[serde(default)]
+    pub reminders: Vec<ReminderConfig>,
+
+    /// The public transport departures configuration.
+    #[serde(default)]
+    pub departures: Option<DeparturesConfig>,
+
+    /// The precipitation nowcast configuration.
+    #[serde(default)]
+    pub nowcast: Option<NowcastConfig>,
+
+    /// The irrigation configuration.
+    #[serde(default)]
+    pub irrigation: Option<IrrigationConfig>,
+
+    /// The local thermostats, like for a pool or a boiler.
+    #[serde(default)]
+    pub thermostats: Vec<ThermostatConfig>,
+
+    /// The chores board configuration.
+    #[serde(default)]
+    pub chores: Option<ChoresConfig>,
+
+    /// The audio playback configuration.
+    #[serde(default)]
+    pub audio: Option<AudioConfig>,
+
+    /// The cache of the media players artwork.
+    #[serde(default)]
+    pub artwork: ArtworkConfig,
+
+    /// The speakers that can be grouped to play in sync.
+    #[serde(default)]
+    pub media_groups: Vec<MediaGroupConfig>,
+
+    /// The microphone-based sound level sensor configuration.
+    #[serde(default)]
+    pub sound_level: Option<SoundLevelConfig>,
+
+    /// The cameras that are not integrated into Home Assistant.
+    #[serde(default)]
+    pub cameras: Vec<CameraConfig>,
+
+    /// The RFID reader configuration.
+    #[serde(default)]
+    pub rfid: Option<RfidConfig>,
+
+    /// The grocery barcode scanner configuration.
+    #[serde(default)]
+    pub barcode: Option<BarcodeConfig>,
+
+    /// The users of the panel.
+    #[serde(default)]
+    pub users: Vec<UserConfig>,
+
+    /// The tracking of the entities used through the panels, for the
+    /// automatic favorites.
+    #[serde(default)]
+    pub usage: UsageConfig,
+
+    /// The IR blaster configuration.
+    #[serde(default)]
+    pub ir: Option<IrConfig>,
+
+    /// The 433 MHz RF transmitter configuration.
+    #[serde(default)]
+    pub rf: Option<RfConfig>,
+
+    /// The monitoring of the disks, usually an SD card.
+    #[serde(default)]
+    pub disk: DiskConfig,
+
+    /// The latency budgets of the API endpoints and of the calls to
+    /// Home-Assistant.
+    #[serde(default)]
+    pub latency: LatencyConfig,
+
+    /// The cache of the forecast, served while the weather entity is briefly
+    /// unavailable.
+    #[serde(default)]
+    pub forecast_cache: ForecastCacheConfig,
+
+    /// What to do when Home-Assistant rejects the token.
+    #[serde(default)]
+    pub auth_failure: AuthFailurePolicy,
+
+    /// The case fan, driven by the CPU temperature.
+    #[serde(default)]
+    pub fan: Option<FanConfig>,
+
+    /// The UPS HAT configuration.
+    #[serde(default)]
+    pub ups: Option<UpsConfig>,
+
+    /// The Modbus RTU energy meter configuration.
+    #[serde(default)]
+    pub energy_meter: Option<EnergyMeterConfig>,
+
+    /// The serial gadgets speaking a line protocol, like UART sensors.
+    #[serde(default)]
+    pub serial_devices: Vec<SerialDeviceConfig>,
+
+    /// The kiosk browser to supervise, if it is not run by the system.
+    #[serde(default)]
+    pub kiosk: Option<KioskConfig>,
+
+    /// The shutdown controller configuration.
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+
+    /// The network monitoring configuration.
+    #[serde(default)]
+    pub network: Option<NetworkConfig>,
+
+    /// The detection of the skew of the local clock with the one of
+    /// Home-Assistant.
+    #[serde(default)]
+    pub clock_skew: ClockSkewConfig,
+
+    /// The network diagnostics run from the troubleshooting page.
+    #[serde(default)]
+    pub net_check: NetCheckConfig,
+
+    /// The frontend login configuration. Mutating routes are unprotected if
+    /// unspecified.
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+
+    /// The login to change the settings, like the configuration, the rules
+    /// and the system, with its own PIN or password so that the guests who
+    /// may control the house cannot change the panel. The settings are
+    /// protected like the other mutating routes if unspecified.
+    #[serde(default)]
+    pub settings_auth: Option<AuthConfig>,
+
+    /// The brute-force protection of PIN checks.
+    #[serde(default)]
+    pub pin_lockout: LockoutConfig,
+
+    /// The HTTP server configuration.
+    #[serde(default)]
+    pub server: ServerConfig,
+
+    /// The limits of the groups of API routes, by group, like `lights`,
+    /// `media` or `rules`.
+    #[serde(default)]
+    pub route_limits: HashMap<String, RouteLimitsConfig>,
+
+    /// The deprecation of the previous API versions.
+    #[serde(default)]
+    pub api_versions: ApiVersionsConfig,
+
+    /// The blocks and the structure of the status.
+    #[serde(default)]
+    pub status: StatusConfig,
+
+    /// The alarm control panel entity, like `alarm_control_panel.home`, to
+    /// arm and disarm from the panel.
+    ///
+    /// Its state is reflected on the outputs with the default patterns,
+    /// unless the alarm indicators are configured.
+    #[serde(default)]
+    pub alarm_entity: Option<String>,
+
+    /// The alarm indicators configuration.
+    #[serde(default)]
+    pub alarm_indicator: Option<AlarmIndicatorConfig>,
+
+    /// The wake-up light, ramping up before the alarms.
+    #[serde(default)]
+    pub wakeup: Option<WakeupConfig>,
+
+    /// The sleep timer, turning the media players and the lights off.
+    #[serde(default)]
+    pub sleep_timer: Option<SleepTimerConfig>,
+
+    /// The periodic digest of the low-priority events.
+    #[serde(default)]
+    pub digest: Option<DigestConfig>,
+
+    /// The panic action configuration.
+    #[serde(default)]
+    pub panic: Option<PanicConfig>,
+
+    /// The entities to mirror on local outputs.
+    #[serde(default)]
+    pub mirrors: Vec<MirrorConfig>,
+
+    /// The entities to refresh periodically, for the integrations that only
+    /// update when polled.
+    #[serde(default)]
+    pub polling: Vec<PollConfig>,
+
+    /// The input pins to report to Home-Assistant.
+    #[serde(default)]
+    pub inputs: Vec<InputConfig>,
+
+    /// The USB input devices, like foot pedals, whose keys run actions.
+    #[serde(default)]
+    pub usb_inputs: Vec<UsbInputConfig>,
+
+    /// The appliances whose cycles are watched, like a washing machine.
+    #[serde(default)]
+    pub appliances: Vec<ApplianceConfig>,
+
+    /// The history of the executions of the rules and the schedules.
+    #[serde(default)]
+    pub history: Option<HistoryConfig>,
+
+    /// The holidays, for the rules and the schedules skipping them.
+    #[serde(default)]
+    pub holidays: Option<HolidaysConfig>,
+
+    /// The declarative rules, running actions when their conditions become
+    /// true.
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+
+    /// The local wake word integration, which opens Assist sessions.
+    #[serde(default)]
+    pub voice: Option<VoiceConfig>,
+
+    /// The screen backlight configuration.
+    #[serde(default)]
+    pub screen: ScreenConfig,
+
+    /// The intercom with the other panels of the household.
+    #[serde(default)]
+    pub intercom: Option<IntercomConfig>,
+
+    /// The photo frame shown by the idle panels.
+    #[serde(default)]
+    pub screensaver: Option<ScreensaverConfig>,
+
+    /// The colors and the font scale of the frontend.
+    #[serde(default)]
+    pub theme: ThemeConfig,
+
+    /// The ambient light sensor driving the screen brightness.
+    #[serde(default)]
+    pub ambient_light: Option<AmbientLightConfig>,
+
+    /// The actions to take when a subsystem keeps failing.
+    #[serde(default)]
+    pub error_policy: Option<ErrorPolicyConfig>,
+
+    /// The MQTT configuration, to register the screen, the buzzer and the
+    /// presence sensor to Home-Assistant.
+    #[serde(default)]
+    pub mqtt: Option<MqttConfig>,
+
+    /// The ESPHome nodes to read and control directly, over their native API.
+    #[serde(default)]
+    pub esphome: Vec<EsphomeNodeConfig>,
+
+    /// The startup self-check configuration. The self-check is skipped if
+    /// unspecified.
+    #[serde(default)]
+    pub self_check: Option<SelfCheckConfig>,
+    /// The Home Assistant entities to keep track of, besides the ones the
+    /// configuration refers to. When specified, only the changes of those
+    /// are subscribed to instead of all the state changes, which spares a
+    /// lot of traffic on large installations. Entities added from the
+    /// frontend need a restart to be tracked.
+    #[serde(default)]
+    pub entity_allowlist: Option<BTreeSet<String>>,
 }
 
 impl HomeControlConfig {
+    /// Move the state written at runtime to a data directory, or keep it in
+    /// memory without one, for the panels with a read-only root filesystem.
+    ///
+    /// The files keep their names, and the optional ones are disabled in
+    /// memory.
+    fn confine_state(&mut self, data_dir: Option<&Path>) {
+        let relocate = |path: &Path| {
+            data_dir
+                .zip(path.file_name())
+                .map(|(data_dir, file_name)| data_dir.join(file_name))
+        };
+        let relocate_database =
+            |path: &Path| relocate(path).unwrap_or_else(|| IN_MEMORY_DATABASE.into());
+
+        if let Some(history) = &mut self.history {
+            history.database_path = relocate_database(&history.database_path);
+        }
+
+        if let Some(chores) = &mut self.chores {
+            chores.database_path = relocate_database(&chores.database_path);
+        }
+
+        if let Some(barcode) = &mut self.barcode {
+            barcode.cache_path = barcode.cache_path.as_deref().and_then(relocate);
+        }
+
+        if let Some(screensaver) = &mut self.screensaver {
+            screensaver.state_path = screensaver.state_path.as_deref().and_then(relocate);
+        }
+
+        self.usage.path = self.usage.path.as_deref().and_then(relocate);
+        self.disk.log_file = self.disk.log_file.as_deref().and_then(relocate);
+    }
+
     fn default_sensor_activation_distance() -> f64 {
         40.0
     }
@@ -58,15 +571,199 @@ impl HomeControlConfig {
     fn default_presence_inactivity_timeout() -> Duration {
         Duration::from_secs(5)
     }
+
+    fn default_command_debounce_window() -> Duration {
+        Duration::from_millis(300)
+    }
+
+    fn default_disconnect_timeout() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    /// Get the Home Assistant entities the configuration refers to, besides
+    /// the sections edited from the frontend, which
+    /// [`EditableConfig::entity_ids`](crate::overrides::EditableConfig::entity_ids)
+    /// adds.
+    pub fn entity_ids(&self) -> BTreeSet<String> {
+        let mut entity_ids = BTreeSet::new();
+
+        entity_ids.insert(self.weather_entity.clone());
+
+        if let Some(circadian) = &self.circadian {
+            entity_ids.extend(
+                circadian
+                    .lights
+                    .iter()
+                    .map(|light| format!("light.{}", light)),
+            );
+        }
+
+        if let Some(weather_alerts) = &self.weather_alerts {
+            entity_ids.extend(weather_alerts.entities.iter().cloned());
+        }
+
+        for room in &self.windows {
+            entity_ids.insert(room.climate_entity.clone());
+            entity_ids.extend(room.sensors.iter().cloned());
+        }
+
+        if let Some(openings) = &self.openings {
+            entity_ids.extend(openings.sensors.iter().cloned());
+        }
+
+        entity_ids.extend(
+            self.appliances
+                .iter()
+                .map(|appliance| appliance.power_entity.clone()),
+        );
+
+        entity_ids.extend(
+            self.rules
+                .iter()
+                .flat_map(|rule| &rule.conditions)
+                .filter_map(|condition| condition.entity_id().map(str::to_string)),
+        );
+
+        if let Some(hazards) = &self.hazards {
+            entity_ids.extend(
+                hazards
+                    .sensors
+                    .iter()
+                    .map(|sensor| sensor.entity_id.clone()),
+            );
+        }
+
+        if let Some(panic) = &self.panic {
+            entity_ids.extend(panic.sirens.iter().cloned());
+            entity_ids.extend(panic.lights.iter().cloned());
+        }
+
+        if let Some(indoor) = &self.indoor {
+            entity_ids.insert(indoor.temperature_entity.clone());
+            entity_ids.insert(indoor.humidity_entity.clone());
+        }
+
+        if let Some(air_quality) = &self.air_quality {
+            entity_ids.extend(air_quality.co2_entity.iter().cloned());
+            entity_ids.extend(air_quality.pm25_entity.iter().cloned());
+        }
+
+        entity_ids.extend(
+            self.extra_sensors
+                .iter()
+                .map(|sensor| sensor.entity_id.clone()),
+        );
+
+        for reminder in &self.reminders {
+            if let ReminderSchedule::Calendar(calendar) = &reminder.schedule {
+                entity_ids.insert(calendar.clone());
+            }
+        }
+
+        if let Some(departures) = &self.departures {
+            for stop in &departures.stops {
+                if let DepartureSource::Sensors(sensors) = &stop.source {
+                    entity_ids.extend(sensors.iter().cloned());
+                }
+            }
+        }
+
+        if let Some(NowcastConfig {
+            source: NowcastSource::Entity(entity_id),
+            ..
+        }) = &self.nowcast
+        {
+            entity_ids.insert(entity_id.clone());
+        }
+
+        if let Some(irrigation) = &self.irrigation {
+            entity_ids.extend(
+                irrigation
+                    .zones
+                    .iter()
+                    .filter_map(|zone| match &zone.relay {
+                        Relay::Switch(entity_id) => Some(entity_id.clone()),
+                        Relay::Pin(_) => None,
+                    }),
+            );
+        }
+
+        for thermostat in &self.thermostats {
+            if let TemperatureSensor::Entity(entity_id) = &thermostat.sensor {
+                entity_ids.insert(entity_id.clone());
+            }
+
+            if let Relay::Switch(entity_id) = &thermostat.relay {
+                entity_ids.insert(entity_id.clone());
+            }
+        }
+
+        entity_ids.extend(self.mirrors.iter().map(|mirror| mirror.entity_id.clone()));
+        entity_ids.extend(self.polling.iter().map(|poll| poll.entity_id.clone()));
+
+        if let Some(wakeup) = &self.wakeup {
+            entity_ids.extend(wakeup.lights.iter().cloned());
+        }
+
+        if let Some(sleep_timer) = &self.sleep_timer {
+            entity_ids.extend(sleep_timer.entity_ids());
+        }
+
+        for group in &self.media_groups {
+            entity_ids.extend(group.entity_ids());
+        }
+
+        for user in &self.users {
+            entity_ids.extend(user.favorites.iter().cloned());
+        }
+
+        entity_ids.extend(
+            [
+                self.sound_level
+                    .as_ref()
+                    .and_then(|sound_level| sound_level.ha_input_number.as_ref()),
+                self.rfid
+                    .as_ref()
+                    .and_then(|rfid| rfid.alarm_entity.as_ref()),
+                self.network
+                    .as_ref()
+                    .and_then(|network| network.ha_input_number.as_ref()),
+                self.presence.temperature_entity.as_ref(),
+                self.alarm_entity.as_ref(),
+                self.holidays
+                    .as_ref()
+                    .and_then(|holidays| holidays.workday_entity.as_ref()),
+                self.alarm_indicator
+                    .as_ref()
+                    .map(|alarm_indicator| &alarm_indicator.entity_id),
+            ]
+            .into_iter()
+            .flatten()
+            .cloned(),
+        );
+
+        entity_ids
+    }
 }
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[clap(
+    author,
+    version,
+    about,
+    long_about = None,
+    after_help = "To run in a container, generate an example compose setup with `home-control init-docker`."
+)]
 struct Args {
     /// Enables debug output.
-    #[clap(long, short)]
+    #[clap(long, short, env)]
     pub debug: bool,
 
+    /// Runs in a container: the configuration file is optional, and the logs
+    /// are JSON lines on stdout.
+    #[clap(long, env = "HOME_CONTROL_DOCKER")]
+    pub docker: bool,
+
     #[clap(
         long,
         value_name = "CONFIG_FILE",
@@ -76,6 +773,39 @@ struct Args {
     )]
     pub config_file: PathBuf,
 
+    #[clap(
+        long,
+        env,
+        value_name = "CONFIG_YAML",
+        help = "The configuration, as YAML, read before the configuration file"
+    )]
+    pub config_yaml: Option<String>,
+
+    #[clap(
+        long,
+        env,
+        value_name = "OVERRIDES_FILE",
+        help = "The path to the file the configuration edited from the frontend is saved to. Defaults to `overrides.yaml` next to the configuration file"
+    )]
+    pub overrides_file: Option<PathBuf>,
+
+    #[clap(
+        long,
+        env,
+        value_name = "SECRETS_KEY_FILE",
+        help = "The path to the key decrypting the `enc:` secrets of the configuration. Defaults to `secrets.key` next to the configuration file"
+    )]
+    pub secrets_key_file: Option<PathBuf>,
+
+    #[clap(
+        long,
+        env,
+        value_name = "SECRETS_KEY_COMMAND",
+        conflicts_with = "secrets-key-file",
+        help = "A shell command printing the key decrypting the `enc:` secrets of the configuration, like `tpm2_unseal -c 0x81000001`"
+    )]
+    pub secrets_key_command: Option<String>,
+
     #[clap(
         value_name = "HOME_ASSISTANT_ENDPOINT",
         env,
@@ -88,23 +818,51 @@ struct Args {
         short = 't',
         env,
         value_name = "HOME_ASSISTANT_TOKEN",
-        help = "The Home Assistant API long-lived token"
+        help = "The Home Assistant API long-lived token. The `ha_token` systemd credential is preferred when present"
     )]
-    pub home_assistant_token: String,
+    pub home_assistant_token: Option<String>,
 
     #[clap(
         long,
         short,
+        env,
         default_value = "127.0.0.1:8000",
         value_name = "LISTEN_ENDPOINT"
     )]
     pub listen_endpoint: SocketAddr,
 
-    #[clap(long, short, value_name = "REVERSE_PROXY_URL")]
+    #[clap(long, short, env, value_name = "REVERSE_PROXY_URL")]
     pub reverse_proxy_url: Option<String>,
 
     #[clap(
         long,
+        env,
+        default_value = "/api/v1",
+        value_name = "API_PREFIX",
+        help = "The path the version 1 of the API is served under"
+    )]
+    pub api_prefix: String,
+
+    #[clap(
+        long,
+        env,
+        value_name = "API_V2_PREFIX",
+        help = "The path the version 2 of the API is served under. Defaults to the version 1 path with its `v1` segment replaced by `v2`, or suffixed with `-v2` without one"
+    )]
+    pub api_v2_prefix: Option<String>,
+
+    #[clap(
+        long,
+        env,
+        default_value = "/",
+        value_name = "STATIC_PREFIX",
+        help = "The path the frontend is served under, which it must be built for with the same `STATIC_PREFIX`. Example: `/panel`"
+    )]
+    pub static_prefix: String,
+
+    #[clap(
+        long,
+        env,
         default_value = DEFAULT_RED_LED_PIN,
         value_name = "RED_LED_PIN"
     )]
@@ -112,6 +870,7 @@ struct Args {
 
     #[clap(
         long,
+        env,
         default_value = DEFAULT_GREEN_LED_PIN,
         value_name = "GREEN_LED_PIN"
     )]
@@ -119,6 +878,7 @@ struct Args {
 
     #[clap(
         long,
+        env,
         default_value = DEFAULT_BUZZER_PIN,
         value_name = "BUZZER_PIN"
     )]
@@ -126,6 +886,7 @@ struct Args {
 
     #[clap(
         long,
+        env,
         default_value = DEFAULT_TRIGGER_PIN,
         value_name = "TRIGGER_PIN"
     )]
@@ -133,29 +894,191 @@ struct Args {
 
     #[clap(
         long,
+        env,
         default_value = DEFAULT_ECHO_PIN,
         value_name = "ECHO_PIN"
     )]
     pub echo_pin: u8,
+
+    #[clap(
+        long,
+        value_name = "EVENTS_FILE",
+        help = "Replay the recorded Home Assistant states and sensor readings of a JSON lines file, instead of connecting to Home Assistant"
+    )]
+    pub simulate: Option<PathBuf>,
+
+    #[clap(
+        long,
+        default_value = "1",
+        value_name = "SIMULATE_SPEED",
+        help = "The speed factor the simulated events are replayed at"
+    )]
+    pub simulate_speed: f64,
+
+    #[clap(
+        long,
+        value_name = "EVENTS_FILE",
+        help = "Append the received Home Assistant states and sensor readings to a JSON lines file, which can be replayed with `--simulate`. Relative to the data directory, if any"
+    )]
+    pub record: Option<PathBuf>,
+
+    #[clap(
+        long,
+        env,
+        value_name = "DATA_DIR",
+        help = "The directory to keep all the state written at runtime in, like the databases, the caches and the configuration edited from the frontend, for a read-only root filesystem"
+    )]
+    pub data_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        env,
+        conflicts_with_all = &["data-dir", "record"],
+        help = "Keep all the state written at runtime in memory, losing it on restart, for a read-only root filesystem without a data directory"
+    )]
+    pub stateless: bool,
 }
 
 impl Config {
     pub fn new() -> anyhow::Result<Self> {
         let args = Args::try_parse()?;
         let config_file = args.config_file;
-        let home_control_config = config::Config::builder()
-            .add_source(config::File::from(config_file))
-            .add_source(config::Environment::with_prefix("HOME_CONTROL"))
-            .build()?
-            .try_deserialize()?;
+        let api_v2_prefix = api_prefixes(&args.api_prefix, args.api_v2_prefix)?;
+
+        // With a data directory, or stateless, the root filesystem may be
+        // read-only: the configuration file is then only upgraded in memory.
+        let migrated_config =
+            migrations::migrate_file(&config_file, args.data_dir.is_none() && !args.stateless)?;
+
+        // Stateless, the overrides are still read, but never saved.
+        let overrides_file = args.overrides_file.unwrap_or_else(|| match &args.data_dir {
+            Some(data_dir) => data_dir.join("overrides.yaml"),
+            None => config_file.with_file_name("overrides.yaml"),
+        });
+        let key_source = match args.secrets_key_command {
+            Some(command) => KeySource::Command(command),
+            None => KeySource::File(
+                args.secrets_key_file
+                    .unwrap_or_else(|| config_file.with_file_name("secrets.key")),
+            ),
+        };
+        let overrides = ConfigOverrides::load(&overrides_file)?;
+
+        if let Some(data_dir) = &args.data_dir {
+            std::fs::create_dir_all(data_dir).with_context(|| {
+                format!(
+                    "failed to create the data directory `{}`",
+                    data_dir.display()
+                )
+            })?;
+        }
+
+        let simulation = args
+            .simulate
+            .map(|events_file| Simulation::load(&events_file, args.simulate_speed))
+            .transpose()?;
+        // Containers are typically configured through the environment only,
+        // with nested keys like `HOME_CONTROL_SERVER__PORT`.
+        let mut builder = config::Config::builder();
+        let mut config_sources = Vec::new();
+
+        if args.config_yaml.is_some() {
+            config_sources.push("--config-yaml".to_string());
+        }
+
+        if config_file.exists() {
+            config_sources.push(config_file.display().to_string());
+        }
+
+        if std::env::vars_os().any(|(name, _)| name.to_string_lossy().starts_with("HOME_CONTROL_"))
+        {
+            config_sources.push("environment".to_string());
+        }
+
+        if overrides_file.exists() {
+            config_sources.push(overrides_file.display().to_string());
+        }
+
+        if let Some(config_yaml) = &args.config_yaml {
+            builder = builder.add_source(config::File::from_str(
+                config_yaml,
+                config::FileFormat::Yaml,
+            ));
+        }
+
+        builder = match &migrated_config {
+            Some(migrated_config) => builder.add_source(config::File::from_str(
+                migrated_config,
+                config::FileFormat::Yaml,
+            )),
+            None => builder.add_source(
+                config::File::from(config_file)
+                    .required(!args.docker && args.config_yaml.is_none()),
+            ),
+        };
+        builder = builder.add_source(
+            config::Environment::with_prefix("HOME_CONTROL")
+                .prefix_separator("_")
+                .separator("__"),
+        );
+
+        let raw_config: serde_json::Value = builder.build_cloned()?.try_deserialize()?;
+        let mut redacted_config = raw_config.clone();
+
+        redact::redact(&mut redacted_config);
+
+        let home_assistant_token = match secrets::credential(HOME_ASSISTANT_TOKEN_CREDENTIAL)? {
+            Some(token) => token,
+            None => args.home_assistant_token.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "no Home Assistant token: pass `--home-assistant-token` or the `{}` credential",
+                    HOME_ASSISTANT_TOKEN_CREDENTIAL
+                )
+            })?,
+        };
+
+        // The key is only needed, and so required, when there are secrets to
+        // decrypt.
+        let encrypted = secrets::encrypted_values(&raw_config);
+        let secrets_key = if encrypted.is_empty() && !secrets::is_encrypted(&home_assistant_token) {
+            None
+        } else {
+            Some(key_source.load()?)
+        };
+
+        for (path, value) in encrypted {
+            let secret = secrets::decrypt(secrets_key.as_ref(), &value)
+                .with_context(|| format!("failed to decrypt `{}`", path))?;
+
+            builder = builder.set_override(path, secret)?;
+        }
+
+        let mut home_control_config: HomeControlConfig = builder.build()?.try_deserialize()?;
+
+        overrides.apply_schedules(&mut home_control_config);
+
+        if args.data_dir.is_some() || args.stateless {
+            home_control_config.confine_state(args.data_dir.as_deref());
+        }
+
+        let home_assistant_token = secrets::decrypt(secrets_key.as_ref(), &home_assistant_token)
+            .context("failed to decrypt the Home Assistant token")?;
 
         Ok(Self {
             debug: args.debug,
+            docker: args.docker,
             home_control_config,
+            redacted_config,
+            config_sources,
+            overrides,
+            overrides_file: (!args.stateless).then_some(overrides_file),
             home_assistant_endpoint: args.home_assistant_endpoint,
-            home_assistant_token: args.home_assistant_token,
+            home_assistant_token,
             listen_endpoint: args.listen_endpoint,
             reverse_proxy_url: args.reverse_proxy_url,
+            api_prefix: args.api_prefix,
+            api_v2_prefix,
+            static_prefix: args.static_prefix,
             gpio_config: GpioConfig {
                 red_led_pin: args.red_led_pin,
                 green_led_pin: args.green_led_pin,
@@ -163,6 +1086,49 @@ impl Config {
                 trigger_pin: args.trigger_pin,
                 echo_pin: args.echo_pin,
             },
+            simulation,
+            recorder: args.record.map(|path| match &args.data_dir {
+                Some(data_dir) => Recorder::new(data_dir.join(path)),
+                None => Recorder::new(path),
+            }),
         })
     }
 }
+
+/// Get the prefix of the version 2 of the API, derived from the version 1
+/// one if unspecified, and check that neither is empty nor contains the
+/// other, as the paths under both would then be ambiguous.
+fn api_prefixes(api_prefix: &str, api_v2_prefix: Option<String>) -> anyhow::Result<String> {
+    fn segments(prefix: &str) -> Vec<&str> {
+        prefix
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect()
+    }
+
+    let v1 = segments(api_prefix);
+    let api_v2_prefix = api_v2_prefix.unwrap_or_else(|| match v1.split_last() {
+        Some((&"v1", parents)) => format!("/{}", [parents, &["v2"]].concat().join("/")),
+        _ => format!("/{}-v2", v1.join("/")),
+    });
+    let v2 = segments(&api_v2_prefix);
+
+    for (name, segments) in [("--api-prefix", &v1), ("--api-v2-prefix", &v2)] {
+        if segments.is_empty() {
+            anyhow::bail!("`{}` cannot be empty or `/`", name);
+        }
+    }
+
+    if v1.starts_with(&v2) || v2.starts_with(&v1) {
+        anyhow::bail!(
+            "the API prefixes `{}` and `{}` cannot overlap",
+            api_prefix,
+            api_v2_prefix
+        );
+    }
+
+    Ok(api_v2_prefix)
+}
+
+#[cfg(test)]
+mod tests;
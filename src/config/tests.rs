@@ -0,0 +1,33 @@
+//! Tests of the configuration.
+
+use super::*;
+
+#[test]
+fn the_v2_prefix_defaults_to_the_v1_one() {
+    assert_eq!(api_prefixes("/api/v1", None).unwrap(), "/api/v2");
+    assert_eq!(
+        api_prefixes("/panel/api/v1/", None).unwrap(),
+        "/panel/api/v2"
+    );
+    assert_eq!(api_prefixes("/v1", None).unwrap(), "/v2");
+    assert_eq!(api_prefixes("/api", None).unwrap(), "/api-v2");
+    assert_eq!(
+        api_prefixes("/api/v1", Some("/next".to_string())).unwrap(),
+        "/next"
+    );
+}
+
+#[test]
+fn empty_api_prefixes_are_rejected() {
+    assert!(api_prefixes("", None).is_err());
+    assert!(api_prefixes("/", Some("/api/v2".to_string())).is_err());
+    assert!(api_prefixes("/api/v1", Some("//".to_string())).is_err());
+}
+
+#[test]
+fn overlapping_api_prefixes_are_rejected() {
+    assert!(api_prefixes("/api/v1", Some("/api/v1".to_string())).is_err());
+    assert!(api_prefixes("/api", Some("/api/v2".to_string())).is_err());
+    assert!(api_prefixes("/api/v1", Some("/api".to_string())).is_err());
+    assert!(api_prefixes("/api/v1", Some("/api/v10".to_string())).is_ok());
+}
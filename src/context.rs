@@ -0,0 +1,103 @@
+use std::sync::{atomic::AtomicU64, Arc};
+
+use tokio::sync::watch;
+
+use crate::{
+    chores::Chores, config::HomeControlConfig, gpio_controller::GpioController, history::History,
+    home_assistant::Controller, irrigation::Irrigation, latency::Latency,
+    overrides::EditableConfig, presence::Proximity, screen::Screen, tasks::Tasks, wakeup::Wakeup,
+};
+
+/// The handles shared by the API routes and the background tasks.
+///
+/// Subsystems take what they need from the context instead of having each
+/// handle threaded through their constructors.
+#[derive(Clone)]
+pub struct AppContext {
+    pub config: Arc<HomeControlConfig>,
+
+    /// The configuration as loaded, without the secrets.
+    pub redacted_config: Arc<serde_json::Value>,
+
+    /// The configuration sections edited from the frontend, which take
+    /// precedence over `config`.
+    pub editable: Arc<EditableConfig>,
+    pub gpio: Arc<GpioController>,
+    pub home_assistant: Controller,
+    pub screen: Arc<Screen>,
+    pub presence: Arc<PresenceState>,
+    pub scheduler: Arc<Scheduler>,
+    pub storage: Arc<Storage>,
+    pub metrics: Arc<Metrics>,
+    pub tasks: Tasks,
+}
+
+/// The presence in front of the panel, as detected by its sensors.
+pub struct PresenceState {
+    pub presence: watch::Sender<bool>,
+    pub proximity: watch::Sender<Option<Proximity>>,
+
+    /// The changes of the proximity, which count in the generation of the
+    /// status.
+    pub proximity_changes: AtomicU64,
+}
+
+/// The subsystems acting on a schedule, if configured.
+pub struct Scheduler {
+    pub irrigation: Option<Irrigation>,
+    pub wakeup: Option<Wakeup>,
+}
+
+/// The databases, if configured.
+pub struct Storage {
+    /// Where the rules, the irrigation programs and the wake-ups record their
+    /// executions.
+    pub history: Option<History>,
+    pub chores: Option<Chores>,
+}
+
+/// The measurements of the panel itself.
+pub struct Metrics {
+    pub latency: Latency,
+}
+
+impl AppContext {
+    pub fn new(
+        config: HomeControlConfig,
+        redacted_config: serde_json::Value,
+        editable: EditableConfig,
+        gpio: Arc<GpioController>,
+        home_assistant: Controller,
+    ) -> anyhow::Result<Self> {
+        let presence = PresenceState {
+            presence: watch::channel(false).0,
+            proximity: watch::channel(None).0,
+            proximity_changes: AtomicU64::new(0),
+        };
+        let scheduler = Scheduler {
+            irrigation: config.irrigation.clone().map(Irrigation::new),
+            wakeup: config.wakeup.clone().map(Wakeup::new),
+        };
+        let storage = Storage {
+            history: config.history.as_ref().map(History::new).transpose()?,
+            chores: config.chores.as_ref().map(Chores::new).transpose()?,
+        };
+        let metrics = Metrics {
+            latency: Latency::new(config.latency.clone()),
+        };
+
+        Ok(Self {
+            screen: Arc::new(Screen::new(config.screen.clone())),
+            config: Arc::new(config),
+            redacted_config: Arc::new(redacted_config),
+            editable: Arc::new(editable),
+            gpio,
+            home_assistant,
+            presence: Arc::new(presence),
+            scheduler: Arc::new(scheduler),
+            storage: Arc::new(storage),
+            metrics: Arc::new(metrics),
+            tasks: Tasks::default(),
+        })
+    }
+}
@@ -0,0 +1,25 @@
+//! The flag of the control actions, like turning a light on, which the routes
+//! set on the requests they handle so that the buzzer chirps for their
+//! outcome once answered.
+
+use std::{cell::Cell, future::Future};
+
+tokio::task_local! {
+    static CONTROL_ACTION: Cell<bool>;
+}
+
+/// Run a future within the scope of a request, which its route may flag as a
+/// control action.
+pub async fn scope<F: Future>(f: F) -> F::Output {
+    CONTROL_ACTION.scope(Cell::new(false), f).await
+}
+
+/// Flag the request being handled as a control action, if any.
+pub fn flag() {
+    let _ = CONTROL_ACTION.try_with(|control_action| control_action.set(true));
+}
+
+/// Whether the request being handled was flagged as a control action.
+pub fn is_flagged() -> bool {
+    CONTROL_ACTION.try_with(Cell::get).unwrap_or(false)
+}
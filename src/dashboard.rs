@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The panels of the dashboard.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct DashboardConfig {
+    /// The panels, in display order.
+    #[serde(default)]
+    pub panels: Vec<PanelConfig>,
+}
+
+/// A panel grouping entities, like the ones of a room.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct PanelConfig {
+    pub title: String,
+
+    /// The icon of the panel, like `noto:bed`.
+    #[serde(default)]
+    pub icon: Option<String>,
+
+    /// The entities to display, like `light.reading`.
+    #[serde(default)]
+    pub entities: Vec<String>,
+}
+
+/// A light button of the sidebar.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct LightConfig {
+    /// The name of the light, without the `light.` domain.
+    pub name: String,
+
+    /// The icon of the button, like `noto:books`.
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+impl DashboardConfig {
+    /// Check that the panels are well-formed.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut titles = HashSet::new();
+
+        for panel in &self.panels {
+            if panel.title.trim().is_empty() {
+                return Err("panel titles cannot be empty".to_string());
+            }
+
+            if !titles.insert(panel.title.as_str()) {
+                return Err(format!("duplicate panel `{}`", panel.title));
+            }
+
+            if let Some(entity_id) = panel
+                .entities
+                .iter()
+                .find(|entity_id| !is_entity_id(entity_id))
+            {
+                return Err(format!(
+                    "invalid entity `{}` in panel `{}`",
+                    entity_id, panel.title
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check that the lights are well-formed.
+pub fn validate_lights(lights: &[LightConfig]) -> Result<(), String> {
+    let mut names = HashSet::new();
+
+    for light in lights {
+        if !is_entity_id(&format!("light.{}", light.name)) {
+            return Err(format!("invalid light `{}`", light.name));
+        }
+
+        if !names.insert(light.name.as_str()) {
+            return Err(format!("duplicate light `{}`", light.name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that a string looks like an entity id, like `light.reading`.
+fn is_entity_id(entity_id: &str) -> bool {
+    match entity_id.split_once('.') {
+        Some((domain, object_id)) => [domain, object_id].iter().all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        }),
+        None => false,
+    }
+}
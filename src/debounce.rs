@@ -0,0 +1,64 @@
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use log::{debug, warn};
+use tokio::sync::Mutex;
+
+use crate::Result;
+
+type Command = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+/// Coalesces rapid successive commands for the same key, like the values of a
+/// brightness slider being dragged, so that only the last one is sent.
+#[derive(Clone)]
+pub struct Debouncer {
+    window: Duration,
+    pending: Arc<Mutex<HashMap<String, Command>>>,
+}
+
+impl Debouncer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: Default::default(),
+        }
+    }
+
+    /// Submit a command, replacing any command pending for the same key.
+    ///
+    /// The first command submitted for a key opens a window, at the end of
+    /// which the last command submitted for the key runs. Failures are logged.
+    pub async fn submit(
+        &self,
+        key: impl Into<String>,
+        command: impl Future<Output = Result<()>> + Send + 'static,
+    ) {
+        let key = key.into();
+
+        if self
+            .pending
+            .lock()
+            .await
+            .insert(key.clone(), Box::pin(command))
+            .is_some()
+        {
+            debug!("Coalesced pending command for `{}`.", key);
+
+            return;
+        }
+
+        let window = self.window;
+        let pending = Arc::clone(&self.pending);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+
+            let command = pending.lock().await.remove(&key);
+
+            if let Some(command) = command {
+                if let Err(err) = command.await {
+                    warn!("Debounced command for `{}` failed: {}", key, err);
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,192 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::RwLock;
+
+use crate::{home_assistant::State, tasks};
+
+/// The public transport departures configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeparturesConfig {
+    /// The stops to display departures for.
+    pub stops: Vec<StopConfig>,
+
+    /// The number of departures to display per stop.
+    #[serde(default = "DeparturesConfig::default_count")]
+    pub count: usize,
+
+    /// The interval at which remote departures are refreshed.
+    #[serde(default = "DeparturesConfig::default_refresh_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub refresh_interval: Duration,
+}
+
+impl DeparturesConfig {
+    fn default_count() -> usize {
+        3
+    }
+
+    fn default_refresh_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StopConfig {
+    /// The name of the stop, as displayed in the UI.
+    pub name: String,
+
+    /// Where the departures of the stop come from.
+    #[serde(flatten)]
+    pub source: DepartureSource,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepartureSource {
+    /// Home Assistant transit sensors, one per upcoming departure.
+    ///
+    /// The state is either a timestamp or a number of minutes until the
+    /// departure. The `line` (or `route`) and `destination` (or `headsign`)
+    /// attributes are used when present.
+    Sensors(Vec<String>),
+
+    /// A REST endpoint returning a JSON array of departures.
+    Url(String),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Departure {
+    #[serde(default)]
+    pub line: Option<String>,
+    #[serde(default)]
+    pub destination: Option<String>,
+    pub departure: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StopDepartures {
+    pub name: String,
+    pub departures: Vec<Departure>,
+}
+
+/// Tracks the next departures of the configured stops.
+pub struct Departures {
+    config: DeparturesConfig,
+    http_client: reqwest::Client,
+    remote: RwLock<HashMap<String, Vec<Departure>>>,
+}
+
+impl Departures {
+    pub fn new(config: DeparturesConfig) -> Self {
+        Self {
+            config,
+            http_client: reqwest::Client::new(),
+            remote: Default::default(),
+        }
+    }
+
+    /// Get the next departures of every stop.
+    pub async fn next(&self, entities: &HashMap<String, State>) -> Vec<StopDepartures> {
+        let now = Utc::now();
+        let remote = self.remote.read().await;
+
+        self.config
+            .stops
+            .iter()
+            .map(|stop| {
+                let mut departures = match &stop.source {
+                    DepartureSource::Sensors(sensors) => sensors
+                        .iter()
+                        .filter_map(|sensor| entities.get(sensor))
+                        .filter_map(|state| Self::departure_from_state(state, now))
+                        .collect(),
+                    DepartureSource::Url(_) => remote.get(&stop.name).cloned().unwrap_or_default(),
+                };
+
+                departures.retain(|departure| departure.departure >= now);
+                departures.sort_by_key(|departure| departure.departure);
+                departures.truncate(self.config.count);
+
+                StopDepartures {
+                    name: stop.name.clone(),
+                    departures,
+                }
+            })
+            .collect()
+    }
+
+    /// Refresh the remote departures periodically.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        loop {
+            for stop in &self.config.stops {
+                if let DepartureSource::Url(url) = &stop.source {
+                    match self.fetch(url).await {
+                        Ok(departures) => {
+                            debug!(
+                                "Fetched {} departures for `{}`",
+                                departures.len(),
+                                stop.name
+                            );
+
+                            self.remote
+                                .write()
+                                .await
+                                .insert(stop.name.clone(), departures);
+                        }
+                        Err(err) => {
+                            warn!("Failed to fetch departures for `{}`: {}", stop.name, err)
+                        }
+                    }
+                }
+            }
+
+            tokio::time::sleep(self.config.refresh_interval).await;
+            tasks::heartbeat();
+        }
+    }
+
+    async fn fetch(&self, url: &str) -> anyhow::Result<Vec<Departure>> {
+        self.http_client
+            .get(url)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .context("failed to query the departures endpoint")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("failed to parse the departures")
+    }
+
+    fn departure_from_state(state: &State, now: DateTime<Utc>) -> Option<Departure> {
+        let departure = match DateTime::parse_from_rfc3339(&state.state) {
+            Ok(departure) => departure.with_timezone(&Utc),
+            Err(_) => {
+                let minutes = state.state.parse::<f64>().ok()?;
+
+                now + chrono::Duration::seconds((minutes * 60.0) as i64)
+            }
+        };
+
+        let attribute = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| state.attributes.get::<String>(name))
+        };
+
+        Some(Departure {
+            line: attribute(&["line", "route"]),
+            destination: attribute(&["destination", "headsign"]),
+            departure,
+        })
+    }
+}
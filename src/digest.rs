@@ -0,0 +1,220 @@
+use std::{collections::HashSet, time::Duration};
+
+use chrono::{DateTime, Local, Utc};
+use log::{debug, warn};
+use serde::Deserialize;
+use serde_json::json;
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::{broadcast::error::RecvError, Mutex};
+
+use crate::{
+    context::AppContext,
+    home_assistant::Update,
+    notifications::{Notifications, Severity},
+    tasks,
+};
+
+/// The digest of the low-priority panel events, like the sensor warnings and
+/// the reconnections, sent periodically through Home-Assistant rather than
+/// one by one.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct DigestConfig {
+    /// The notification services to call, like `notify.mobile_app_phone`.
+    pub notify: Vec<String>,
+
+    /// The interval between two digests. Nothing is sent when nothing
+    /// happened.
+    #[serde(default = "DigestConfig::default_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub interval: Duration,
+
+    /// The title of the digests.
+    #[serde(default = "DigestConfig::default_title")]
+    pub title: String,
+
+    /// How many events a digest lists at most, the others being counted.
+    #[serde(default = "DigestConfig::default_max_entries")]
+    pub max_entries: usize,
+}
+
+impl DigestConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(3600)
+    }
+
+    fn default_title() -> String {
+        "Home control".to_string()
+    }
+
+    fn default_max_entries() -> usize {
+        20
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    at: DateTime<Utc>,
+    text: String,
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    entries: Vec<Entry>,
+
+    /// The events beyond the maximum.
+    dropped: usize,
+}
+
+/// Batches the low-priority events into periodic digests.
+pub struct Digest {
+    config: DigestConfig,
+    pending: Mutex<Pending>,
+}
+
+impl Digest {
+    pub fn new(config: DigestConfig) -> Self {
+        Self {
+            config,
+            pending: Default::default(),
+        }
+    }
+
+    /// Add an event to the next digest.
+    pub async fn push(&self, text: impl Into<String>) {
+        let text = text.into();
+        let mut pending = self.pending.lock().await;
+
+        debug!("Adding `{}` to the digest.", text);
+
+        if pending.entries.len() < self.config.max_entries {
+            pending.entries.push(Entry {
+                at: Utc::now(),
+                text,
+            });
+        } else {
+            pending.dropped += 1;
+        }
+    }
+
+    /// Collect the events and send the digests forever.
+    pub async fn run(
+        &self,
+        context: &AppContext,
+        notifications: &Notifications,
+    ) -> anyhow::Result<()> {
+        let mut updates = context.home_assistant.subscribe_updates();
+        let mut changes = notifications.watch();
+        let mut interval = tokio::time::interval(self.config.interval);
+
+        // The notifications already active are not news.
+        let mut seen: HashSet<String> = notifications
+            .list()
+            .await
+            .into_iter()
+            .map(|notification| notification.id)
+            .collect();
+
+        let mut disconnected = false;
+
+        // The first tick is immediate.
+        interval.tick().await;
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    tasks::heartbeat();
+                    self.send(context).await;
+                }
+                r = changes.changed() => {
+                    r?;
+
+                    let active = notifications.list().await;
+
+                    // The critical notifications have their own, immediate,
+                    // alerts.
+                    for notification in &active {
+                        if notification.severity < Severity::Critical && !seen.contains(&notification.id) {
+                            self.push(format!("{}: {}", notification.title, notification.message))
+                                .await;
+                        }
+                    }
+
+                    seen = active.into_iter().map(|notification| notification.id).collect();
+                }
+                update = updates.recv() => match update {
+                    // The first connection is not a reconnection.
+                    Ok(Update::Connected) if disconnected => {
+                        disconnected = false;
+                        self.push("Reconnected to Home-Assistant.").await;
+                    }
+                    Ok(Update::Connected) => {}
+                    Ok(Update::Disconnected) => {
+                        disconnected = true;
+                        self.push("Lost the connection to Home-Assistant.").await;
+                    }
+                    Ok(Update::Event(_)) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+            }
+        }
+    }
+
+    /// Send the pending events, if any, keeping them for the next digest if
+    /// that fails.
+    async fn send(&self, context: &AppContext) {
+        // The events are not locked while sending, as that may be slow.
+        let (mut lines, dropped) = {
+            let pending = self.pending.lock().await;
+
+            if pending.entries.is_empty() {
+                return;
+            }
+
+            let lines: Vec<_> = pending
+                .entries
+                .iter()
+                .map(|entry| {
+                    format!(
+                        "{} {}",
+                        entry.at.with_timezone(&Local).format("%H:%M"),
+                        entry.text
+                    )
+                })
+                .collect();
+
+            (lines, pending.dropped)
+        };
+        let count = lines.len();
+
+        if dropped > 0 {
+            lines.push(format!("And {} more.", dropped));
+        }
+
+        let data = json!({
+            "title": self.config.title,
+            "message": lines.join("\n"),
+        });
+        let mut sent = false;
+
+        for service in &self.config.notify {
+            let service = service.strip_prefix("notify.").unwrap_or(service);
+
+            match context
+                .home_assistant
+                .call_service("notify", service, Some(&data), None)
+                .await
+            {
+                Ok(()) => sent = true,
+                Err(err) => warn!("Failed to send the digest through `{}`: {}", service, err),
+            }
+        }
+
+        if sent {
+            let mut pending = self.pending.lock().await;
+
+            pending.entries.drain(..count);
+            pending.dropped -= dropped;
+        }
+    }
+}
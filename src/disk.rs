@@ -0,0 +1,260 @@
+//! The health of the local storage, usually an SD card: how much is written
+//! to it, and whether its filesystems were remounted read-only, which is how
+//! a failing card usually shows.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use log::debug;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::RwLock;
+
+use crate::{
+    notifications::{Notifications, Severity},
+    tasks,
+};
+
+/// The id of the notification raised while a filesystem is read-only.
+const READ_ONLY_NOTIFICATION_ID: &str = "disk_read_only";
+
+/// The size of the sectors counted by the kernel, whatever the device.
+const SECTOR_SIZE: u64 = 512;
+
+/// The disk monitoring configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiskConfig {
+    /// The block device to count the writes of, like `mmcblk0` for the SD
+    /// card of a Pi.
+    #[serde(default = "DiskConfig::default_device")]
+    pub device: String,
+
+    /// The mount points to watch for read-only remounts.
+    #[serde(default = "DiskConfig::default_mount_points")]
+    pub mount_points: Vec<PathBuf>,
+
+    /// The file to also write the logs to, if any, preferably on a tmpfs or
+    /// an external drive rather than on the SD card.
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+
+    /// The interval between checks.
+    #[serde(default = "DiskConfig::default_poll_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub poll_interval: Duration,
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        Self {
+            device: Self::default_device(),
+            mount_points: Self::default_mount_points(),
+            log_file: None,
+            poll_interval: Self::default_poll_interval(),
+        }
+    }
+}
+
+impl DiskConfig {
+    fn default_device() -> String {
+        "mmcblk0".to_string()
+    }
+
+    fn default_mount_points() -> Vec<PathBuf> {
+        vec![PathBuf::from("/")]
+    }
+
+    fn default_poll_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FilesystemStatus {
+    pub mount_point: PathBuf,
+    pub device: String,
+    pub fs_type: String,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskStatus {
+    pub device: String,
+
+    /// The bytes written to the device since the boot, by all the processes.
+    pub bytes_written: Option<u64>,
+
+    /// The bytes written to the disks by the panel itself since it started.
+    pub own_bytes_written: Option<u64>,
+
+    /// The write rate of the device over the last interval, in bytes per
+    /// second.
+    pub write_rate: Option<f64>,
+
+    /// The watched filesystems. Those that are not mounted are missing.
+    pub filesystems: Vec<FilesystemStatus>,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub struct Disk {
+    config: DiskConfig,
+    status: RwLock<Option<DiskStatus>>,
+}
+
+impl Disk {
+    pub fn new(config: DiskConfig) -> Self {
+        Self {
+            config,
+            status: RwLock::new(None),
+        }
+    }
+
+    /// Get the last status, if any.
+    pub async fn status(&self) -> Option<DiskStatus> {
+        self.status.read().await.clone()
+    }
+
+    /// Check the storage forever, raising a notification while a filesystem
+    /// is read-only.
+    pub async fn run(&self, notifications: &Notifications) -> anyhow::Result<()> {
+        loop {
+            let previous = self.status().await;
+            let status = self.check(previous.as_ref()).await;
+            let read_only: Vec<_> = status
+                .filesystems
+                .iter()
+                .filter(|filesystem| filesystem.read_only)
+                .map(|filesystem| format!("`{}`", filesystem.mount_point.display()))
+                .collect();
+
+            if read_only.is_empty() {
+                notifications.clear(READ_ONLY_NOTIFICATION_ID).await;
+            } else {
+                notifications
+                    .raise(
+                        READ_ONLY_NOTIFICATION_ID,
+                        Severity::Critical,
+                        "Read-only storage",
+                        format!(
+                            "{} got remounted read-only: the SD card may be failing.",
+                            read_only.join(", ")
+                        ),
+                    )
+                    .await;
+            }
+
+            *self.status.write().await = Some(status);
+
+            tokio::time::sleep(self.config.poll_interval).await;
+            tasks::heartbeat();
+        }
+    }
+
+    async fn check(&self, previous: Option<&DiskStatus>) -> DiskStatus {
+        let bytes_written = read(
+            &Path::new("/sys/block")
+                .join(&self.config.device)
+                .join("stat"),
+        )
+        .await
+        .and_then(|content| parse_block_stat(&content));
+        let own_bytes_written = read(Path::new("/proc/self/io"))
+            .await
+            .and_then(|content| parse_process_io(&content));
+        let filesystems = read(Path::new("/proc/mounts"))
+            .await
+            .map(|content| parse_mounts(&content, &self.config.mount_points))
+            .unwrap_or_default();
+        let updated_at = Utc::now();
+
+        let write_rate = previous.and_then(|previous| {
+            let elapsed = (updated_at - previous.updated_at).to_std().ok()?;
+            let written = bytes_written?.checked_sub(previous.bytes_written?)?;
+
+            Some(written as f64 / elapsed.as_secs_f64().max(1.0))
+        });
+
+        DiskStatus {
+            device: self.config.device.clone(),
+            bytes_written,
+            own_bytes_written,
+            write_rate,
+            filesystems,
+            updated_at,
+        }
+    }
+}
+
+async fn read(path: &Path) -> Option<String> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => Some(content),
+        // Like in containers, or on systems without the device.
+        Err(err) => {
+            debug!("Failed to read `{}`: {}", path.display(), err);
+
+            None
+        }
+    }
+}
+
+/// Get the bytes written to a block device, from its `stat` file.
+fn parse_block_stat(content: &str) -> Option<u64> {
+    let sectors: u64 = content.split_whitespace().nth(6)?.parse().ok()?;
+
+    Some(sectors * SECTOR_SIZE)
+}
+
+/// Get the bytes a process caused to be written to the storage, from its
+/// `io` file.
+fn parse_process_io(content: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("write_bytes:"))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Get the status of the watched filesystems, from `/proc/mounts`.
+fn parse_mounts(content: &str, mount_points: &[PathBuf]) -> Vec<FilesystemStatus> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            let mount_point = PathBuf::from(fields.next()?);
+            let fs_type = fields.next()?;
+            let options = fields.next()?;
+
+            mount_points
+                .contains(&mount_point)
+                .then(|| FilesystemStatus {
+                    mount_point,
+                    device: device.to_string(),
+                    fs_type: fs_type.to_string(),
+                    read_only: options.split(',').any(|option| option == "ro"),
+                })
+        })
+        // The last mount hides the previous ones at the same point.
+        .rev()
+        .fold(Vec::new(), |mut filesystems, filesystem| {
+            if !filesystems
+                .iter()
+                .any(|known: &FilesystemStatus| known.mount_point == filesystem.mount_point)
+            {
+                filesystems.push(filesystem);
+            }
+
+            filesystems
+        })
+}
+
+#[cfg(test)]
+mod tests;
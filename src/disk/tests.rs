@@ -0,0 +1,49 @@
+//! Tests of the disk health.
+
+use super::*;
+
+#[test]
+fn block_writes_are_in_sectors() {
+    let stat = "   14290     4587   987544    23940    52100    61830  1874584   412300        0    96640   436240";
+
+    assert_eq!(parse_block_stat(stat), Some(1874584 * 512));
+    assert_eq!(parse_block_stat("1 2 3"), None);
+}
+
+#[test]
+fn process_writes_are_read() {
+    let io = "rchar: 4812\nwchar: 1024\nsyscr: 12\nsyscw: 4\nread_bytes: 0\nwrite_bytes: 8192\ncancelled_write_bytes: 0\n";
+
+    assert_eq!(parse_process_io(io), Some(8192));
+}
+
+#[test]
+fn read_only_remounts_are_detected() {
+    let mounts = "\
+/dev/mmcblk0p2 / ext4 rw,noatime 0 0
+proc /proc proc rw,relatime 0 0
+/dev/mmcblk0p1 /boot vfat rw,relatime 0 0
+/dev/mmcblk0p2 / ext4 ro,noatime 0 0
+tmpfs /run tmpfs rw,nosuid,nodev,mode=755 0 0
+";
+
+    let filesystems = parse_mounts(mounts, &[PathBuf::from("/"), PathBuf::from("/boot")]);
+
+    assert_eq!(
+        filesystems,
+        vec![
+            FilesystemStatus {
+                mount_point: PathBuf::from("/"),
+                device: "/dev/mmcblk0p2".to_string(),
+                fs_type: "ext4".to_string(),
+                read_only: true,
+            },
+            FilesystemStatus {
+                mount_point: PathBuf::from("/boot"),
+                device: "/dev/mmcblk0p1".to_string(),
+                fs_type: "vfat".to_string(),
+                read_only: false,
+            },
+        ]
+    );
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::Parser;
+
+/// The example compose setup, by file name.
+const FILES: &[(&str, &str)] = &[
+    (
+        "docker-compose.yml",
+        include_str!("docker/docker-compose.yml"),
+    ),
+    ("home-control.env", include_str!("docker/home-control.env")),
+];
+
+/// Generates an example compose setup, to run home-control in a container.
+#[derive(Parser, Debug)]
+#[clap(name = "home-control init-docker")]
+pub struct InitDocker {
+    /// The directory to write the files to.
+    #[clap(long, short, default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// Overwrites the existing files.
+    #[clap(long, short)]
+    pub force: bool,
+}
+
+impl InitDocker {
+    /// Parse the arguments of the `init-docker` command, if it is the one
+    /// invoked.
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args_os().skip(1);
+
+        if args.next()? != "init-docker" {
+            return None;
+        }
+
+        Some(Self::parse_from(
+            std::iter::once("home-control init-docker".into()).chain(args),
+        ))
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.output_dir)
+            .with_context(|| format!("failed to create `{}`", self.output_dir.display()))?;
+
+        for (name, content) in FILES {
+            let path = self.output_dir.join(name);
+
+            if path.exists() && !self.force {
+                anyhow::bail!(
+                    "`{}` already exists: use `--force` to overwrite it",
+                    path.display()
+                );
+            }
+
+            std::fs::write(&path, content)
+                .with_context(|| format!("failed to write `{}`", path.display()))?;
+
+            println!("Wrote `{}`.", path.display());
+        }
+
+        println!("Fill in `home-control.env`, then run `docker compose up -d`.");
+
+        Ok(())
+    }
+}
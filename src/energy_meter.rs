@@ -0,0 +1,377 @@
+//! The DIN-rail energy meter, read over Modbus RTU through a USB-RS485
+//! dongle.
+
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_with::{serde_as, DurationSeconds};
+use tokio::{fs::File, io::AsyncWriteExt, sync::RwLock};
+
+use crate::{context::AppContext, serial, tasks};
+
+/// The energy meter configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnergyMeterConfig {
+    /// The serial port of the RS485 dongle.
+    #[serde(default = "EnergyMeterConfig::default_port")]
+    pub port: PathBuf,
+
+    #[serde(default = "EnergyMeterConfig::default_baud_rate")]
+    pub baud_rate: u32,
+
+    /// The Modbus address of the meter.
+    #[serde(default = "EnergyMeterConfig::default_slave")]
+    pub slave: u8,
+
+    /// The interval between readings.
+    #[serde(default = "EnergyMeterConfig::default_poll_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub poll_interval: Duration,
+
+    /// For how long to wait for the meter to answer.
+    #[serde(default = "EnergyMeterConfig::default_timeout")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub timeout: Duration,
+
+    /// The registers to read, like those of the voltage, the power and the
+    /// total energy.
+    pub registers: Vec<MeterRegisterConfig>,
+}
+
+impl EnergyMeterConfig {
+    fn default_port() -> PathBuf {
+        PathBuf::from("/dev/ttyUSB0")
+    }
+
+    fn default_baud_rate() -> u32 {
+        9600
+    }
+
+    fn default_slave() -> u8 {
+        1
+    }
+
+    fn default_poll_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_timeout() -> Duration {
+        Duration::from_secs(1)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MeterRegisterConfig {
+    /// The name of the value, like `power`.
+    pub name: String,
+
+    /// The address of the first register of the value.
+    pub address: u16,
+
+    #[serde(default)]
+    pub function: RegisterFunction,
+
+    #[serde(default)]
+    pub format: RegisterFormat,
+
+    /// The factor to apply to the raw value, like `0.01` for a value in
+    /// hundredths.
+    #[serde(default = "MeterRegisterConfig::default_scale")]
+    pub scale: f64,
+
+    #[serde(default)]
+    pub unit: Option<String>,
+
+    /// The Home-Assistant sensor to report the value to, if any, like
+    /// `sensor.panel_meter_power`.
+    #[serde(default)]
+    pub ha_sensor: Option<String>,
+}
+
+impl MeterRegisterConfig {
+    fn default_scale() -> f64 {
+        1.0
+    }
+}
+
+/// The kind of the registers, as read by a Modbus function.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterFunction {
+    Holding,
+    #[default]
+    Input,
+}
+
+impl RegisterFunction {
+    fn code(self) -> u8 {
+        match self {
+            Self::Holding => 0x03,
+            Self::Input => 0x04,
+        }
+    }
+}
+
+/// The format of a value, over one or two registers. The values over two
+/// registers have their high word first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegisterFormat {
+    U16,
+    I16,
+    U32,
+    I32,
+    #[default]
+    F32,
+}
+
+impl RegisterFormat {
+    fn registers(self) -> u16 {
+        match self {
+            Self::U16 | Self::I16 => 1,
+            Self::U32 | Self::I32 | Self::F32 => 2,
+        }
+    }
+
+    fn decode(self, data: &[u8]) -> Option<f64> {
+        Some(match self {
+            Self::U16 => u16::from_be_bytes(data.try_into().ok()?).into(),
+            Self::I16 => i16::from_be_bytes(data.try_into().ok()?).into(),
+            Self::U32 => u32::from_be_bytes(data.try_into().ok()?).into(),
+            Self::I32 => i32::from_be_bytes(data.try_into().ok()?).into(),
+            Self::F32 => f32::from_be_bytes(data.try_into().ok()?).into(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MeterValue {
+    pub value: f64,
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EnergyReadings {
+    /// The values read, by name. The values that failed to read are missing.
+    pub values: BTreeMap<String, MeterValue>,
+    pub timestamp: DateTime<Utc>,
+}
+
+pub struct EnergyMeter {
+    config: EnergyMeterConfig,
+    readings: RwLock<Option<EnergyReadings>>,
+}
+
+impl EnergyMeter {
+    pub fn new(config: EnergyMeterConfig) -> Self {
+        Self {
+            config,
+            readings: RwLock::new(None),
+        }
+    }
+
+    /// Get the last readings, if any.
+    pub async fn readings(&self) -> Option<EnergyReadings> {
+        self.readings.read().await.clone()
+    }
+
+    /// Poll the meter forever, reporting the values to Home-Assistant.
+    pub async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        info!(
+            "Reading the energy meter {} on `{}`.",
+            self.config.slave,
+            self.config.port.display()
+        );
+
+        let mut port = None;
+
+        loop {
+            let readings = match &mut port {
+                Some(port) => Some(self.read(port).await),
+                None => match serial::open(&self.config.port, self.config.baud_rate).await {
+                    Ok(opened) => Some(self.read(port.insert(opened)).await),
+                    Err(err) => {
+                        warn!("Failed to open the energy meter port: {:#}", err);
+
+                        None
+                    }
+                },
+            };
+
+            // The port is reopened after a read failure, in case the dongle
+            // was unplugged.
+            if readings
+                .as_ref()
+                .is_none_or(|readings| readings.values.is_empty())
+            {
+                port = None;
+            }
+
+            if let Some(readings) = &readings {
+                self.report(context, readings).await;
+            }
+
+            *self.readings.write().await = readings;
+
+            tokio::time::sleep(self.config.poll_interval).await;
+            tasks::heartbeat();
+        }
+    }
+
+    async fn read(&self, port: &mut File) -> EnergyReadings {
+        let mut values = BTreeMap::new();
+
+        for register in &self.config.registers {
+            match self.read_register(port, register).await {
+                Ok(value) => {
+                    values.insert(
+                        register.name.clone(),
+                        MeterValue {
+                            value,
+                            unit: register.unit.clone(),
+                        },
+                    );
+                }
+                Err(err) => warn!(
+                    "Failed to read `{}` from the meter: {:#}",
+                    register.name, err
+                ),
+            }
+        }
+
+        EnergyReadings {
+            values,
+            timestamp: Utc::now(),
+        }
+    }
+
+    async fn read_register(
+        &self,
+        port: &mut File,
+        register: &MeterRegisterConfig,
+    ) -> anyhow::Result<f64> {
+        let function = register.function.code();
+
+        port.write_all(&read_request(
+            self.config.slave,
+            function,
+            register.address,
+            register.format.registers(),
+        ))
+        .await?;
+
+        // The header tells the length of the rest, or that it is an
+        // exception.
+        let mut response = vec![0; 3];
+
+        serial::read_exact(port, &mut response, self.config.timeout).await?;
+
+        let length = if response[1] & 0x80 != 0 {
+            5
+        } else {
+            5 + usize::from(response[2])
+        };
+
+        response.resize(length, 0);
+        serial::read_exact(port, &mut response[3..], self.config.timeout).await?;
+
+        let data = parse_response(self.config.slave, function, &response)?;
+
+        register
+            .format
+            .decode(data)
+            .map(|value| value * register.scale)
+            .context("unexpected length of the value")
+    }
+
+    async fn report(&self, context: &AppContext, readings: &EnergyReadings) {
+        for register in &self.config.registers {
+            let (sensor, value) = match (&register.ha_sensor, readings.values.get(&register.name)) {
+                (Some(sensor), Some(value)) => (sensor, value),
+                _ => continue,
+            };
+
+            if let Err(err) = context
+                .home_assistant
+                .set_state(
+                    sensor,
+                    &value.value.to_string(),
+                    &json!({
+                        "friendly_name": register.name,
+                        "unit_of_measurement": register.unit,
+                    }),
+                )
+                .await
+            {
+                warn!(
+                    "Failed to report `{}` to `{}`: {}",
+                    register.name, sensor, err
+                );
+            }
+        }
+    }
+}
+
+/// The CRC of a Modbus RTU frame.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFF;
+
+    for byte in data {
+        crc ^= u16::from(*byte);
+
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// Build the request to read registers.
+fn read_request(slave: u8, function: u8, address: u16, count: u16) -> Vec<u8> {
+    let mut request = vec![slave, function];
+
+    request.extend_from_slice(&address.to_be_bytes());
+    request.extend_from_slice(&count.to_be_bytes());
+    request.extend_from_slice(&crc16(&request).to_le_bytes());
+    request
+}
+
+/// Check the response to a read request, and return its data.
+fn parse_response(slave: u8, function: u8, response: &[u8]) -> anyhow::Result<&[u8]> {
+    let (frame, crc) = response
+        .split_last_chunk::<2>()
+        .context("the response is too short")?;
+
+    if crc16(frame) != u16::from_le_bytes(*crc) {
+        anyhow::bail!("invalid CRC");
+    }
+
+    match frame {
+        [address, ..] if *address != slave => {
+            anyhow::bail!("unexpected answer from the slave {}", address)
+        }
+        [_, code, exception] if *code == function | 0x80 => {
+            anyhow::bail!("the meter answered with the exception {}", exception)
+        }
+        [_, code, length, data @ ..] if *code == function && usize::from(*length) == data.len() => {
+            Ok(data)
+        }
+        _ => anyhow::bail!("unexpected response"),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,53 @@
+//! Tests of the Modbus RTU energy meter.
+
+use super::*;
+
+#[test]
+fn read_requests_have_a_crc() {
+    // Reading the voltage of an SDM120, as documented by Eastron.
+    assert_eq!(
+        read_request(1, 0x04, 0x0000, 2),
+        vec![0x01, 0x04, 0x00, 0x00, 0x00, 0x02, 0x71, 0xCB]
+    );
+}
+
+#[test]
+fn responses_are_checked() {
+    let mut response = vec![0x01, 0x04, 0x04, 0x43, 0x66, 0x33, 0x33];
+
+    response.extend_from_slice(&crc16(&response).to_le_bytes());
+
+    let data = parse_response(1, 0x04, &response).unwrap();
+
+    assert_eq!(RegisterFormat::F32.decode(data), Some(230.2f32.into()));
+
+    // Another slave.
+    assert!(parse_response(2, 0x04, &response).is_err());
+
+    // A corrupted frame.
+    response[4] ^= 1;
+
+    assert!(parse_response(1, 0x04, &response).is_err());
+}
+
+#[test]
+fn exceptions_are_errors() {
+    let mut response = vec![0x01, 0x84, 0x02];
+
+    response.extend_from_slice(&crc16(&response).to_le_bytes());
+
+    let err = parse_response(1, 0x04, &response).unwrap_err();
+
+    assert!(err.to_string().contains("exception 2"));
+}
+
+#[test]
+fn formats_are_big_endian() {
+    assert_eq!(RegisterFormat::U16.decode(&[0x01, 0x02]), Some(258.0));
+    assert_eq!(RegisterFormat::I16.decode(&[0xFF, 0xFE]), Some(-2.0));
+    assert_eq!(
+        RegisterFormat::U32.decode(&[0x00, 0x01, 0x00, 0x00]),
+        Some(65536.0)
+    );
+    assert_eq!(RegisterFormat::I32.decode(&[0x00, 0x01]), None);
+}
@@ -10,6 +10,14 @@ pub enum Error {
         #[from]
         error: serde_json::Error,
     },
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+    #[error("unauthorized")]
+    Unauthorized,
+    #[error("locked out for {}s", retry_after.as_secs())]
+    LockedOut { retry_after: std::time::Duration },
+    #[error("too many requests at once")]
+    Busy,
     #[error("unknown error: {source}")]
     Unknown {
         #[from]
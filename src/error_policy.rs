@@ -0,0 +1,281 @@
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+use log::{info, warn};
+use serde::Deserialize;
+use serde_with::{serde_as, DurationSeconds};
+use tokio::{task::JoinHandle, time::Instant};
+
+use crate::{
+    context::AppContext,
+    gpio_controller::{GpioController, GpioHealth},
+    outputs::{Output, OutputPattern},
+    red_led::RedLedOwner,
+    tasks,
+};
+
+/// The actions to take when a subsystem keeps failing, instead of only
+/// logging its errors.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorPolicyConfig {
+    /// The policy when Home-Assistant is unreachable.
+    #[serde(default)]
+    pub home_assistant: Option<FailurePolicy>,
+
+    /// The policy when the GPIO operations keep failing.
+    #[serde(default)]
+    pub gpio: Option<FailurePolicy>,
+
+    /// The interval between two checks of the subsystems.
+    #[serde(default = "ErrorPolicyConfig::default_check_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub check_interval: Duration,
+}
+
+/// The actions to take when a subsystem keeps failing.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct FailurePolicy {
+    /// For how long the subsystem must keep failing before acting.
+    #[serde(default = "FailurePolicy::default_after")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub after: Duration,
+
+    /// How many operations in a row must fail for the subsystem to be
+    /// failing, for the subsystems that count them, like the GPIO: a single
+    /// failure of a rarely used pin stays uncleared until its next use.
+    #[serde(default = "FailurePolicy::default_failures")]
+    pub failures: u64,
+
+    /// The output patterns while the subsystem fails.
+    #[serde(default)]
+    pub outputs: HashMap<Output, OutputPattern>,
+
+    /// Whether to raise a Home-Assistant notification, once it is connected.
+    #[serde(default)]
+    pub notify: bool,
+
+    /// Whether to exit, for the supervisor to restart the process.
+    #[serde(default)]
+    pub restart: bool,
+}
+
+impl FailurePolicy {
+    fn default_after() -> Duration {
+        Duration::from_secs(600)
+    }
+
+    fn default_failures() -> u64 {
+        3
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Subsystem {
+    HomeAssistant,
+    Gpio,
+}
+
+impl Subsystem {
+    /// Get the current failure of the subsystem, if it fails.
+    fn failure(self, context: &AppContext, policy: &FailurePolicy) -> Option<String> {
+        match self {
+            Subsystem::HomeAssistant => context
+                .home_assistant
+                .disconnected_for()
+                .map(|_| "unreachable".to_string()),
+            Subsystem::Gpio => gpio_failure(&context.gpio.health(), policy.failures),
+        }
+    }
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subsystem::HomeAssistant => write!(f, "Home-Assistant"),
+            Subsystem::Gpio => write!(f, "GPIO"),
+        }
+    }
+}
+
+/// Get the failure of the GPIO, if enough operations failed in a row.
+fn gpio_failure(health: &GpioHealth, failures: u64) -> Option<String> {
+    (health.consecutive_failures >= failures.max(1)).then(|| {
+        format!(
+            "{} consecutive failures, last: {}",
+            health.consecutive_failures,
+            health.last_error.as_deref().unwrap_or("unknown")
+        )
+    })
+}
+
+/// The state of a subsystem under a policy.
+struct Watched<'a> {
+    subsystem: Subsystem,
+    policy: &'a FailurePolicy,
+    failing_since: Option<Instant>,
+    triggered: bool,
+    drive: Option<JoinHandle<()>>,
+}
+
+impl ErrorPolicyConfig {
+    fn default_check_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    /// Watch the subsystems forever.
+    ///
+    /// A policy asking for a restart ends the process through
+    /// [`Tasks::exit`](tasks::Tasks::exit), as failing would only restart this
+    /// task.
+    pub async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        let mut watched: Vec<_> = [
+            (Subsystem::HomeAssistant, &self.home_assistant),
+            (Subsystem::Gpio, &self.gpio),
+        ]
+        .into_iter()
+        .filter_map(|(subsystem, policy)| {
+            policy.as_ref().map(|policy| Watched {
+                subsystem,
+                policy,
+                failing_since: None,
+                triggered: false,
+                drive: None,
+            })
+        })
+        .collect();
+
+        // The notifications wait for Home-Assistant to be reachable.
+        let mut pending: Vec<String> = Vec::new();
+        let mut interval = tokio::time::interval(self.check_interval);
+
+        loop {
+            interval.tick().await;
+            tasks::heartbeat();
+
+            for watched in &mut watched {
+                if let Some(message) = watched.check(context) {
+                    pending.push(message);
+                }
+            }
+
+            if !pending.is_empty() && context.home_assistant.disconnected_for().is_none() {
+                let message = pending.join("\n\n");
+
+                match context
+                    .home_assistant
+                    .persistent_notification_create("Panel errors", &message)
+                    .await
+                {
+                    Ok(()) => pending.clear(),
+                    Err(err) => warn!("Failed to notify of the panel errors: {}", err),
+                }
+            }
+        }
+    }
+}
+
+impl Watched<'_> {
+    /// Check the subsystem, and get the notification to raise, if any.
+    fn check(&mut self, context: &AppContext) -> Option<String> {
+        let failure = self.subsystem.failure(context, self.policy);
+
+        self.apply(failure, &context.tasks, &context.gpio)
+    }
+
+    /// Apply the policy to the current failure of the subsystem, and get the
+    /// notification to raise, if any.
+    fn apply(
+        &mut self,
+        failure: Option<String>,
+        tasks: &tasks::Tasks,
+        gpio: &Arc<GpioController>,
+    ) -> Option<String> {
+        let failure = match failure {
+            Some(failure) => failure,
+            None => {
+                self.recover(gpio);
+
+                return None;
+            }
+        };
+
+        let since = *self.failing_since.get_or_insert_with(Instant::now);
+
+        if self.triggered || since.elapsed() < self.policy.after {
+            return None;
+        }
+
+        self.triggered = true;
+
+        warn!(
+            "{} failing for {:?} ({}): applying the error policy.",
+            self.subsystem,
+            since.elapsed(),
+            failure
+        );
+
+        if self.policy.restart {
+            tasks.exit(format!(
+                "{} failing for too long: {}",
+                self.subsystem, failure
+            ));
+
+            return None;
+        }
+
+        if !self.policy.outputs.is_empty() {
+            let gpio = Arc::clone(gpio);
+            let outputs = self.policy.outputs.clone();
+
+            self.drive = Some(tokio::spawn(async move {
+                let drives = outputs.into_iter().map(|(output, pattern)| {
+                    pattern.drive_for(&gpio, output, RedLedOwner::ErrorPolicy)
+                });
+
+                if let Err(err) = futures_util::future::try_join_all(drives).await {
+                    warn!("Failed to drive the error outputs: {}", err);
+                }
+            }));
+        }
+
+        self.policy.notify.then(|| {
+            format!(
+                "{} failed for {} min: {}.",
+                self.subsystem,
+                since.elapsed().as_secs() / 60,
+                failure
+            )
+        })
+    }
+
+    /// Undo the actions once the subsystem works again.
+    fn recover(&mut self, gpio: &GpioController) {
+        self.failing_since = None;
+
+        if !std::mem::take(&mut self.triggered) {
+            return;
+        }
+
+        info!("{} recovered.", self.subsystem);
+
+        // Aborting the drive releases the red LED.
+        if let Some(drive) = self.drive.take() {
+            drive.abort();
+
+            for output in self
+                .policy
+                .outputs
+                .keys()
+                .filter(|output| **output != Output::RedLed)
+            {
+                if let Err(err) = output.set(gpio, false) {
+                    warn!("Failed to turn off the error output {:?}: {}", output, err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
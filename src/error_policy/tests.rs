@@ -0,0 +1,74 @@
+//! Tests of the error policies.
+
+use super::*;
+use crate::config::GpioConfig;
+
+fn health(consecutive_failures: u64) -> GpioHealth {
+    GpioHealth {
+        failures: consecutive_failures,
+        consecutive_failures,
+        last_error: (consecutive_failures > 0).then(|| "I/O error".to_string()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn a_single_gpio_failure_is_not_a_failing_gpio() {
+    assert_eq!(gpio_failure(&health(0), 3), None);
+    assert_eq!(gpio_failure(&health(1), 3), None);
+    assert_eq!(gpio_failure(&health(2), 3), None);
+}
+
+#[test]
+fn repeated_gpio_failures_are_a_failing_gpio() {
+    assert_eq!(
+        gpio_failure(&health(3), 3).as_deref(),
+        Some("3 consecutive failures, last: I/O error")
+    );
+}
+
+#[test]
+fn the_failures_threshold_defaults_to_three() {
+    let policy: FailurePolicy = serde_json::from_str("{}").unwrap();
+
+    assert_eq!(policy.failures, 3);
+    assert_eq!(gpio_failure(&health(1), policy.failures), None);
+}
+
+#[tokio::test]
+async fn a_restart_policy_ends_the_process() {
+    let policy: FailurePolicy = serde_json::from_str(r#"{"after": 0, "restart": true}"#).unwrap();
+    let gpio = Arc::new(
+        GpioController::new(GpioConfig {
+            red_led_pin: 1,
+            green_led_pin: 2,
+            buzzer_pin: 3,
+            trigger_pin: 4,
+            echo_pin: 5,
+        })
+        .unwrap(),
+    );
+    let tasks = tasks::Tasks::default();
+    let mut watched = Watched {
+        subsystem: Subsystem::HomeAssistant,
+        policy: &policy,
+        failing_since: None,
+        triggered: false,
+        drive: None,
+    };
+
+    assert_eq!(watched.apply(None, &tasks, &gpio), None);
+    assert_eq!(
+        watched.apply(Some("unreachable".to_string()), &tasks, &gpio),
+        None
+    );
+
+    let err = tokio::time::timeout(Duration::from_secs(1), tasks.exited())
+        .await
+        .expect("the process should exit");
+
+    assert_eq!(
+        err.to_string(),
+        "Home-Assistant failing for too long: unreachable"
+    );
+}
@@ -0,0 +1,461 @@
+//! The ESPHome nodes nearby, read and controlled over their native API
+//! rather than through Home-Assistant, for a lower latency.
+//!
+//! Their entities are surfaced as `esphome.<node>.<object_id>`, apart from
+//! those of Home-Assistant. Only the sensors, binary sensors and switches are
+//! supported, over plaintext connections.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use anyhow::Context;
+use futures_util::future::try_join_all;
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{tcp::OwnedWriteHalf, TcpStream},
+    sync::{Mutex, RwLock},
+};
+
+use crate::tasks;
+
+use self::protocol::{Message, Value};
+
+mod protocol;
+
+/// The version of the native API spoken.
+const API_VERSION: (u64, u64) = (1, 9);
+
+/// For how long to wait for a node to connect or answer.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// For how long a node can stay silent before being pinged, and then before
+/// being considered gone.
+const KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// For how long to wait before reconnecting to a node.
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// An ESPHome node.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EsphomeNodeConfig {
+    /// The name of the node, as used in the entity ids, like `greenhouse`.
+    pub name: String,
+    pub host: String,
+
+    #[serde(default = "EsphomeNodeConfig::default_port")]
+    pub port: u16,
+
+    /// The password of the API, if the node has one. The nodes with an
+    /// encryption key are not supported.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+impl EsphomeNodeConfig {
+    fn default_port() -> u16 {
+        6053
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum EsphomeEntityKind {
+    BinarySensor,
+    Sensor,
+    Switch,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EsphomeEntity {
+    /// The id of the entity, like `esphome.greenhouse.temperature`.
+    pub id: String,
+    pub node: String,
+    pub name: String,
+    pub kind: EsphomeEntityKind,
+    pub unit: Option<String>,
+
+    /// The value of a sensor, if known.
+    pub value: Option<f64>,
+
+    /// The state of a binary sensor or a switch, if known.
+    pub on: Option<bool>,
+}
+
+/// A state reported by a node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Value(Option<f64>),
+    On(Option<bool>),
+}
+
+/// The ESPHome nodes.
+pub struct Esphome {
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    config: EsphomeNodeConfig,
+
+    /// The entities of the node, by key.
+    entities: RwLock<BTreeMap<u32, EsphomeEntity>>,
+
+    /// The connection to the node, while connected.
+    writer: Mutex<Option<OwnedWriteHalf>>,
+}
+
+impl Esphome {
+    pub fn new(configs: Vec<EsphomeNodeConfig>) -> Self {
+        Self {
+            nodes: configs
+                .into_iter()
+                .map(|config| Node {
+                    config,
+                    entities: RwLock::default(),
+                    writer: Mutex::default(),
+                })
+                .collect(),
+        }
+    }
+
+    pub async fn entities(&self) -> Vec<EsphomeEntity> {
+        let mut entities = Vec::new();
+
+        for node in &self.nodes {
+            entities.extend(node.entities.read().await.values().cloned());
+        }
+
+        entities
+    }
+
+    pub async fn entity(&self, id: &str) -> Option<EsphomeEntity> {
+        let (node, _) = self.find(id).await?;
+
+        node.entities
+            .read()
+            .await
+            .values()
+            .find(|entity| entity.id == id)
+            .cloned()
+    }
+
+    /// Turn a switch on or off.
+    ///
+    /// The new state is reported by the node once applied.
+    pub async fn set(&self, id: &str, on: bool) -> anyhow::Result<()> {
+        let (node, key) = self
+            .find(id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("unknown ESPHome entity `{}`", id))?;
+
+        info!("Turning `{}` {}.", id, if on { "on" } else { "off" });
+
+        node.send(
+            Message::default()
+                .fixed32(1, key)
+                .varint(2, on.into())
+                .frame(protocol::SWITCH_COMMAND_REQUEST),
+        )
+        .await
+    }
+
+    /// Stay connected to the nodes forever.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        if self.nodes.is_empty() {
+            return tasks::idle().await;
+        }
+
+        try_join_all(self.nodes.iter().map(Node::run)).await?;
+
+        Ok(())
+    }
+
+    /// Find the node and the key of an entity.
+    async fn find(&self, id: &str) -> Option<(&Node, u32)> {
+        for node in &self.nodes {
+            if let Some((key, _)) = node
+                .entities
+                .read()
+                .await
+                .iter()
+                .find(|(_, entity)| entity.id == id)
+            {
+                return Some((node, *key));
+            }
+        }
+
+        None
+    }
+}
+
+impl Node {
+    async fn run(&self) -> anyhow::Result<()> {
+        loop {
+            if let Err(err) = self.serve().await {
+                warn!(
+                    "Lost the connection to the ESPHome node `{}`: {:#}",
+                    self.config.name, err
+                );
+            }
+
+            *self.writer.lock().await = None;
+
+            // The entities are kept, for their ids to stay valid, but their
+            // states are no longer known.
+            for entity in self.entities.write().await.values_mut() {
+                entity.value = None;
+                entity.on = None;
+            }
+
+            tasks::heartbeat();
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    async fn send(&self, frame: Vec<u8>) -> anyhow::Result<()> {
+        let mut writer = self.writer.lock().await;
+        let writer = writer.as_mut().ok_or_else(|| {
+            anyhow::anyhow!("the ESPHome node `{}` is not connected", self.config.name)
+        })?;
+
+        writer.write_all(&frame).await?;
+
+        Ok(())
+    }
+
+    /// Connect to the node, list its entities and follow their states until
+    /// the connection is lost.
+    async fn serve(&self) -> anyhow::Result<()> {
+        let stream = tokio::time::timeout(
+            CONNECT_TIMEOUT,
+            TcpStream::connect((self.config.host.as_str(), self.config.port)),
+        )
+        .await
+        .context("timed out")??;
+        let (mut reader, mut writer) = stream.into_split();
+
+        let handshake = async {
+            writer
+                .write_all(
+                    &Message::default()
+                        .string(1, "home-control")
+                        .varint(2, API_VERSION.0)
+                        .varint(3, API_VERSION.1)
+                        .frame(protocol::HELLO_REQUEST),
+                )
+                .await?;
+            expect(&mut reader, protocol::HELLO_RESPONSE).await?;
+
+            writer
+                .write_all(
+                    &Message::default()
+                        .string(1, self.config.password.as_deref().unwrap_or_default())
+                        .frame(protocol::CONNECT_REQUEST),
+                )
+                .await?;
+
+            let response = expect(&mut reader, protocol::CONNECT_RESPONSE).await?;
+
+            if protocol::fields(&response)?.contains(&(1, Value::Varint(1))) {
+                anyhow::bail!("invalid password");
+            }
+
+            writer
+                .write_all(&Message::default().frame(protocol::LIST_ENTITIES_REQUEST))
+                .await?;
+
+            let mut entities = BTreeMap::new();
+
+            loop {
+                let (message_type, message) = protocol::read_frame(&mut reader).await?;
+
+                match message_type {
+                    protocol::LIST_ENTITIES_DONE_RESPONSE => break,
+                    _ => {
+                        if let Some((key, entity)) =
+                            parse_entity(&self.config.name, message_type, &message)?
+                        {
+                            entities.insert(key, entity);
+                        }
+                    }
+                }
+            }
+
+            writer
+                .write_all(&Message::default().frame(protocol::SUBSCRIBE_STATES_REQUEST))
+                .await?;
+
+            anyhow::Ok(entities)
+        };
+
+        let entities = tokio::time::timeout(CONNECT_TIMEOUT, handshake)
+            .await
+            .context("timed out")??;
+
+        info!(
+            "Connected to the ESPHome node `{}`, with {} entities.",
+            self.config.name,
+            entities.len()
+        );
+
+        *self.entities.write().await = entities;
+        *self.writer.lock().await = Some(writer);
+
+        let mut pinged = false;
+
+        loop {
+            let (message_type, message) =
+                match tokio::time::timeout(KEEPALIVE, protocol::read_frame(&mut reader)).await {
+                    Ok(frame) => frame?,
+                    Err(_) if pinged => anyhow::bail!("the node stopped answering"),
+                    Err(_) => {
+                        pinged = true;
+                        self.send(Message::default().frame(protocol::PING_REQUEST))
+                            .await?;
+
+                        continue;
+                    }
+                };
+
+            pinged = false;
+            tasks::heartbeat();
+
+            match message_type {
+                protocol::PING_REQUEST => {
+                    self.send(Message::default().frame(protocol::PING_RESPONSE))
+                        .await?;
+                }
+                protocol::DISCONNECT_REQUEST => {
+                    let _ = self
+                        .send(Message::default().frame(protocol::DISCONNECT_RESPONSE))
+                        .await;
+
+                    anyhow::bail!("the node disconnected");
+                }
+                _ => {
+                    if let Some((key, state)) = parse_state(message_type, &message)? {
+                        if let Some(entity) = self.entities.write().await.get_mut(&key) {
+                            match state {
+                                State::Value(value) => entity.value = value,
+                                State::On(on) => entity.on = on,
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Read the next frame, which must be of the specified type.
+async fn expect(
+    reader: &mut (impl tokio::io::AsyncRead + Unpin),
+    message_type: u32,
+) -> anyhow::Result<Vec<u8>> {
+    let (actual_type, message) = protocol::read_frame(reader).await?;
+
+    if actual_type != message_type {
+        anyhow::bail!(
+            "unexpected message of type {}, instead of {}",
+            actual_type,
+            message_type
+        );
+    }
+
+    Ok(message)
+}
+
+/// Parse the description of an entity of a node, if of a supported kind.
+fn parse_entity(
+    node: &str,
+    message_type: u32,
+    message: &[u8],
+) -> anyhow::Result<Option<(u32, EsphomeEntity)>> {
+    let kind = match message_type {
+        protocol::LIST_ENTITIES_BINARY_SENSOR_RESPONSE => EsphomeEntityKind::BinarySensor,
+        protocol::LIST_ENTITIES_SENSOR_RESPONSE => EsphomeEntityKind::Sensor,
+        protocol::LIST_ENTITIES_SWITCH_RESPONSE => EsphomeEntityKind::Switch,
+        _ => return Ok(None),
+    };
+
+    let mut key = None;
+    let mut object_id = None;
+    let mut name = String::new();
+    let mut unit = None;
+
+    for (field, value) in protocol::fields(message)? {
+        match (field, value) {
+            (1, Value::Bytes(bytes)) => {
+                object_id = Some(String::from_utf8_lossy(bytes).into_owned())
+            }
+            (2, Value::Fixed32(value)) => key = Some(value),
+            (3, Value::Bytes(bytes)) => name = String::from_utf8_lossy(bytes).into_owned(),
+            (6, Value::Bytes(bytes)) if kind == EsphomeEntityKind::Sensor && !bytes.is_empty() => {
+                unit = Some(String::from_utf8_lossy(bytes).into_owned())
+            }
+            _ => {}
+        }
+    }
+
+    let (key, object_id) = key
+        .zip(object_id)
+        .ok_or_else(|| anyhow::anyhow!("the entity has no key or object id"))?;
+
+    Ok(Some((
+        key,
+        EsphomeEntity {
+            id: format!("esphome.{}.{}", node, object_id),
+            node: node.to_string(),
+            name,
+            kind,
+            unit,
+            value: None,
+            on: None,
+        },
+    )))
+}
+
+/// Parse a state reported by a node, with the key of its entity, if of a
+/// supported kind.
+fn parse_state(message_type: u32, message: &[u8]) -> anyhow::Result<Option<(u32, State)>> {
+    let is_sensor = match message_type {
+        protocol::SENSOR_STATE_RESPONSE => true,
+        protocol::BINARY_SENSOR_STATE_RESPONSE | protocol::SWITCH_STATE_RESPONSE => false,
+        _ => return Ok(None),
+    };
+
+    let mut key = None;
+    let mut value = None;
+    let mut on = false;
+    let mut missing = false;
+
+    for (field, field_value) in protocol::fields(message)? {
+        match (field, field_value) {
+            (1, Value::Fixed32(field_value)) => key = Some(field_value),
+            (2, Value::Fixed32(field_value)) => value = Some(f32::from_bits(field_value)),
+            (2, Value::Varint(field_value)) => on = field_value != 0,
+            // The switches have no missing state.
+            (3, Value::Varint(field_value)) => missing = field_value != 0,
+            _ => {}
+        }
+    }
+
+    let key = key.ok_or_else(|| anyhow::anyhow!("the state has no key"))?;
+
+    let state = if is_sensor {
+        State::Value(
+            value
+                .filter(|value| !missing && value.is_finite())
+                .map(f64::from),
+        )
+    } else {
+        State::On((!missing).then_some(on))
+    };
+
+    Ok(Some((key, state)))
+}
+
+#[cfg(test)]
+mod tests;
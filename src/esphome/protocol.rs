@@ -0,0 +1,180 @@
+//! The plaintext framing and the few protobuf messages of the ESPHome native
+//! API the client uses.
+//!
+//! Each frame is a zero byte, the length of the message and its type as
+//! varints, then the message. The nodes with an encryption key expect Noise
+//! frames instead, starting with a one byte.
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+pub(super) const HELLO_REQUEST: u32 = 1;
+pub(super) const HELLO_RESPONSE: u32 = 2;
+pub(super) const CONNECT_REQUEST: u32 = 3;
+pub(super) const CONNECT_RESPONSE: u32 = 4;
+pub(super) const DISCONNECT_REQUEST: u32 = 5;
+pub(super) const DISCONNECT_RESPONSE: u32 = 6;
+pub(super) const PING_REQUEST: u32 = 7;
+pub(super) const PING_RESPONSE: u32 = 8;
+pub(super) const LIST_ENTITIES_REQUEST: u32 = 11;
+pub(super) const LIST_ENTITIES_BINARY_SENSOR_RESPONSE: u32 = 12;
+pub(super) const LIST_ENTITIES_SENSOR_RESPONSE: u32 = 16;
+pub(super) const LIST_ENTITIES_SWITCH_RESPONSE: u32 = 17;
+pub(super) const LIST_ENTITIES_DONE_RESPONSE: u32 = 19;
+pub(super) const SUBSCRIBE_STATES_REQUEST: u32 = 20;
+pub(super) const BINARY_SENSOR_STATE_RESPONSE: u32 = 21;
+pub(super) const SENSOR_STATE_RESPONSE: u32 = 25;
+pub(super) const SWITCH_STATE_RESPONSE: u32 = 26;
+pub(super) const SWITCH_COMMAND_REQUEST: u32 = 33;
+
+/// The largest message accepted, as the nodes only send small ones.
+const MAX_MESSAGE_SIZE: u64 = 64 * 1024;
+
+/// A field of a protobuf message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Value<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+/// Builds a protobuf message.
+#[derive(Debug, Default)]
+pub(super) struct Message(Vec<u8>);
+
+impl Message {
+    pub(super) fn string(mut self, field: u32, value: &str) -> Self {
+        write_varint(&mut self.0, (field << 3 | 2).into());
+        write_varint(&mut self.0, value.len() as u64);
+        self.0.extend_from_slice(value.as_bytes());
+        self
+    }
+
+    pub(super) fn varint(mut self, field: u32, value: u64) -> Self {
+        write_varint(&mut self.0, (field << 3).into());
+        write_varint(&mut self.0, value);
+        self
+    }
+
+    pub(super) fn fixed32(mut self, field: u32, value: u32) -> Self {
+        write_varint(&mut self.0, (field << 3 | 5).into());
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// Frame the message, with its type.
+    pub(super) fn frame(self, message_type: u32) -> Vec<u8> {
+        let mut frame = vec![0];
+
+        write_varint(&mut frame, self.0.len() as u64);
+        write_varint(&mut frame, message_type.into());
+        frame.extend(self.0);
+        frame
+    }
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    buf.push(value as u8);
+}
+
+fn read_varint(data: &[u8], offset: &mut usize) -> Option<u64> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*offset)?;
+
+        *offset += 1;
+        value |= u64::from(byte & 0x7F) << shift;
+
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+/// Decode the fields of a protobuf message, by number.
+pub(super) fn fields(data: &[u8]) -> anyhow::Result<Vec<(u32, Value<'_>)>> {
+    let invalid = || anyhow::anyhow!("invalid protobuf message");
+    let mut offset = 0;
+    let mut fields = Vec::new();
+
+    while offset < data.len() {
+        let tag = read_varint(data, &mut offset).ok_or_else(invalid)?;
+        let value = match tag & 7 {
+            0 => Value::Varint(read_varint(data, &mut offset).ok_or_else(invalid)?),
+            1 => {
+                let bytes = data.get(offset..offset + 8).ok_or_else(invalid)?;
+
+                offset += 8;
+                Value::Fixed64(u64::from_le_bytes(bytes.try_into()?))
+            }
+            2 => {
+                let length = read_varint(data, &mut offset).ok_or_else(invalid)? as usize;
+                let bytes = data
+                    .get(offset..offset.saturating_add(length))
+                    .ok_or_else(invalid)?;
+
+                offset += length;
+                Value::Bytes(bytes)
+            }
+            5 => {
+                let bytes = data.get(offset..offset + 4).ok_or_else(invalid)?;
+
+                offset += 4;
+                Value::Fixed32(u32::from_le_bytes(bytes.try_into()?))
+            }
+            _ => return Err(invalid()),
+        };
+
+        fields.push(((tag >> 3) as u32, value));
+    }
+
+    Ok(fields)
+}
+
+/// Read a frame, returning the type and the content of its message.
+pub(super) async fn read_frame(
+    reader: &mut (impl AsyncRead + Unpin),
+) -> anyhow::Result<(u32, Vec<u8>)> {
+    match reader.read_u8().await? {
+        0 => {}
+        1 => anyhow::bail!("the node expects an encrypted connection, which is not supported"),
+        indicator => anyhow::bail!("invalid frame indicator: {}", indicator),
+    }
+
+    let length = read_stream_varint(reader).await?;
+    let message_type = read_stream_varint(reader).await?;
+
+    if length > MAX_MESSAGE_SIZE {
+        anyhow::bail!("the message is too large: {} bytes", length);
+    }
+
+    let mut message = vec![0; length as usize];
+
+    reader.read_exact(&mut message).await?;
+
+    Ok((message_type as u32, message))
+}
+
+async fn read_stream_varint(reader: &mut (impl AsyncRead + Unpin)) -> anyhow::Result<u64> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        let byte = reader.read_u8().await?;
+
+        value |= u64::from(byte & 0x7F) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    anyhow::bail!("invalid varint")
+}
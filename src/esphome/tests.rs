@@ -0,0 +1,111 @@
+//! Tests of the ESPHome native API client.
+
+use super::*;
+
+fn sensor_description() -> Vec<u8> {
+    let frame = Message::default()
+        .string(1, "temperature")
+        .fixed32(2, 0xCAFE)
+        .string(3, "Greenhouse temperature")
+        .string(6, "°C")
+        .frame(protocol::LIST_ENTITIES_SENSOR_RESPONSE);
+
+    // Without the indicator, the length and the type.
+    frame[3..].to_vec()
+}
+
+#[tokio::test]
+async fn frames_round_trip() {
+    let frame = Message::default()
+        .string(1, "home-control")
+        .varint(2, 300)
+        .frame(protocol::HELLO_REQUEST);
+
+    let (message_type, message) = protocol::read_frame(&mut frame.as_slice()).await.unwrap();
+
+    assert_eq!(message_type, protocol::HELLO_REQUEST);
+    assert_eq!(
+        protocol::fields(&message).unwrap(),
+        vec![(1, Value::Bytes(b"home-control")), (2, Value::Varint(300))]
+    );
+}
+
+#[tokio::test]
+async fn encrypted_frames_are_rejected() {
+    let frame = [1, 0, 2];
+
+    assert!(protocol::read_frame(&mut frame.as_slice()).await.is_err());
+}
+
+#[test]
+fn truncated_messages_are_rejected() {
+    let message = sensor_description();
+
+    assert!(protocol::fields(&message[..message.len() - 1]).is_err());
+}
+
+#[test]
+fn entities_are_namespaced_by_node() {
+    let (key, entity) = parse_entity(
+        "greenhouse",
+        protocol::LIST_ENTITIES_SENSOR_RESPONSE,
+        &sensor_description(),
+    )
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(key, 0xCAFE);
+    assert_eq!(entity.id, "esphome.greenhouse.temperature");
+    assert_eq!(entity.name, "Greenhouse temperature");
+    assert_eq!(entity.kind, EsphomeEntityKind::Sensor);
+    assert_eq!(entity.unit.as_deref(), Some("°C"));
+    assert_eq!(entity.value, None);
+}
+
+#[test]
+fn unsupported_entities_are_ignored() {
+    // A light.
+    assert!(parse_entity("greenhouse", 15, &sensor_description())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn sensor_states_can_be_missing() {
+    let state = Message::default()
+        .fixed32(1, 0xCAFE)
+        .fixed32(2, 21.5f32.to_bits());
+
+    assert_eq!(
+        parse_state(protocol::SENSOR_STATE_RESPONSE, &state.frame(0)[3..]).unwrap(),
+        Some((0xCAFE, State::Value(Some(21.5))))
+    );
+
+    let state = Message::default()
+        .fixed32(1, 0xCAFE)
+        .fixed32(2, f32::NAN.to_bits())
+        .varint(3, 1);
+
+    assert_eq!(
+        parse_state(protocol::SENSOR_STATE_RESPONSE, &state.frame(0)[3..]).unwrap(),
+        Some((0xCAFE, State::Value(None)))
+    );
+}
+
+#[test]
+fn switch_states_are_booleans() {
+    let state = Message::default().fixed32(1, 7).varint(2, 1);
+
+    assert_eq!(
+        parse_state(protocol::SWITCH_STATE_RESPONSE, &state.frame(0)[3..]).unwrap(),
+        Some((7, State::On(Some(true))))
+    );
+
+    // The `false` values are omitted.
+    let state = Message::default().fixed32(1, 7);
+
+    assert_eq!(
+        parse_state(protocol::SWITCH_STATE_RESPONSE, &state.frame(0)[3..]).unwrap(),
+        Some((7, State::On(Some(false))))
+    );
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::home_assistant::State;
+
+/// An arbitrary sensor to display in the UI.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExtraSensorConfig {
+    /// The entity to read the value from.
+    pub entity_id: String,
+
+    /// The label to display in the UI.
+    pub label: String,
+
+    /// The unit to display. Defaults to the entity's `unit_of_measurement`.
+    #[serde(default)]
+    pub unit: Option<String>,
+
+    /// The icon to display. Defaults to the entity's `icon`.
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+impl ExtraSensorConfig {
+    /// Compute the sensor status from the current entities.
+    ///
+    /// Sensors whose entity is missing or unavailable are still reported,
+    /// without a value.
+    pub fn status(&self, entities: &HashMap<String, State>) -> ExtraSensorStatus {
+        let state = entities.get(&self.entity_id);
+        let attribute = |name: &str| state.and_then(|state| state.attributes.get(name));
+
+        ExtraSensorStatus {
+            entity_id: self.entity_id.clone(),
+            label: self.label.clone(),
+            value: state
+                .filter(|state| state.is_available())
+                .map(|state| state.state.clone()),
+            available: state.map(State::is_available).unwrap_or_default(),
+            unit: self
+                .unit
+                .clone()
+                .or_else(|| attribute("unit_of_measurement")),
+            icon: self.icon.clone().or_else(|| attribute("icon")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtraSensorStatus {
+    pub entity_id: String,
+    pub label: String,
+    pub value: Option<String>,
+
+    /// Whether the entity exists and is neither `unavailable` nor `unknown`.
+    pub available: bool,
+    pub unit: Option<String>,
+    pub icon: Option<String>,
+}
@@ -0,0 +1,221 @@
+//! The case fan of the panel, driven by the temperature of its CPU, for the
+//! enclosures that get too hot for a passive cooling.
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::RwLock;
+
+use crate::{gpio_controller::GpioController, tasks};
+
+/// The case fan configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct FanConfig {
+    /// The GPIO pin driving the fan, through a transistor.
+    pub pin: u8,
+
+    /// The frequency of the PWM, in Hz.
+    #[serde(default = "FanConfig::default_frequency")]
+    pub frequency: f64,
+
+    /// The speed of the fan by CPU temperature, in °C, interpolated between
+    /// the points. The speeds are duty cycles, between 0 and 1.
+    #[serde(default = "FanConfig::default_curve")]
+    pub curve: Vec<FanCurvePoint>,
+
+    /// By how much the temperature must drop before the fan slows down, in
+    /// °C, so that it does not keep changing speed.
+    #[serde(default = "FanConfig::default_hysteresis")]
+    pub hysteresis: f64,
+
+    /// The file to read the CPU temperature from, in thousandths of °C.
+    #[serde(default = "FanConfig::default_temperature_path")]
+    pub temperature_path: PathBuf,
+
+    /// The interval between readings.
+    #[serde(default = "FanConfig::default_poll_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub poll_interval: Duration,
+}
+
+impl FanConfig {
+    fn default_frequency() -> f64 {
+        100.0
+    }
+
+    fn default_curve() -> Vec<FanCurvePoint> {
+        vec![
+            FanCurvePoint {
+                temperature: 55.0,
+                speed: 0.0,
+            },
+            FanCurvePoint {
+                temperature: 60.0,
+                speed: 0.4,
+            },
+            FanCurvePoint {
+                temperature: 75.0,
+                speed: 1.0,
+            },
+        ]
+    }
+
+    fn default_hysteresis() -> f64 {
+        3.0
+    }
+
+    fn default_temperature_path() -> PathBuf {
+        PathBuf::from("/sys/class/thermal/thermal_zone0/temp")
+    }
+
+    fn default_poll_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct FanCurvePoint {
+    pub temperature: f64,
+    pub speed: f64,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FanStatus {
+    /// The CPU temperature, in °C, if it could be read.
+    pub cpu_temperature: Option<f64>,
+
+    /// The speed of the fan, as a duty cycle between 0 and 1.
+    pub speed: f64,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+pub struct Fan {
+    config: FanConfig,
+    status: RwLock<FanStatus>,
+}
+
+impl Fan {
+    pub fn new(config: FanConfig) -> Self {
+        Self {
+            config,
+            status: RwLock::new(FanStatus {
+                cpu_temperature: None,
+                speed: 0.0,
+                updated_at: None,
+            }),
+        }
+    }
+
+    pub async fn status(&self) -> FanStatus {
+        self.status.read().await.clone()
+    }
+
+    /// Drive the fan forever.
+    ///
+    /// The fan runs at full speed while the temperature cannot be read.
+    pub async fn run(&self, gpio: &Arc<GpioController>) -> anyhow::Result<()> {
+        info!("Driving the case fan on pin {}.", self.config.pin);
+
+        let mut speed = None;
+
+        loop {
+            let temperature = match self.read_temperature().await {
+                Ok(temperature) => Some(temperature),
+                Err(err) => {
+                    warn!("Failed to read the CPU temperature: {:#}", err);
+
+                    None
+                }
+            };
+            let new_speed = match temperature {
+                Some(temperature) => next_speed(
+                    &self.config.curve,
+                    self.config.hysteresis,
+                    speed.unwrap_or(0.0),
+                    temperature,
+                ),
+                None => 1.0,
+            };
+
+            if speed != Some(new_speed) {
+                info!(
+                    "Setting the case fan to {:.0}%, for {}.",
+                    new_speed * 100.0,
+                    temperature.map_or("an unknown temperature".to_string(), |temperature| {
+                        format!("{:.1}°C", temperature)
+                    })
+                );
+
+                match gpio.set_pwm(self.config.pin, self.config.frequency, new_speed) {
+                    Ok(()) => speed = Some(new_speed),
+                    Err(err) => warn!("Failed to drive the case fan: {}", err),
+                }
+            }
+
+            *self.status.write().await = FanStatus {
+                cpu_temperature: temperature,
+                speed: speed.unwrap_or_default(),
+                updated_at: Some(Utc::now()),
+            };
+
+            tokio::time::sleep(self.config.poll_interval).await;
+            tasks::heartbeat();
+        }
+    }
+
+    async fn read_temperature(&self) -> anyhow::Result<f64> {
+        let path = &self.config.temperature_path;
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        let millidegrees: f64 = content
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid temperature in `{}`", path.display()))?;
+
+        Ok(millidegrees / 1000.0)
+    }
+}
+
+/// Get the speed of the curve at a temperature.
+fn curve_speed(curve: &[FanCurvePoint], temperature: f64) -> f64 {
+    let speed = match curve
+        .iter()
+        .position(|point| point.temperature > temperature)
+    {
+        // Below the curve.
+        Some(0) => curve[0].speed,
+        Some(index) => {
+            let (low, high) = (curve[index - 1], curve[index]);
+            let ratio = (temperature - low.temperature) / (high.temperature - low.temperature);
+
+            low.speed + ratio * (high.speed - low.speed)
+        }
+        // Above the curve, or without curve.
+        None => curve.last().map_or(1.0, |point| point.speed),
+    };
+
+    speed.clamp(0.0, 1.0)
+}
+
+/// Get the next speed of the fan, which only slows down once the
+/// temperature dropped by the hysteresis.
+fn next_speed(curve: &[FanCurvePoint], hysteresis: f64, speed: f64, temperature: f64) -> f64 {
+    let target = curve_speed(curve, temperature);
+
+    if target >= speed {
+        target
+    } else {
+        curve_speed(curve, temperature + hysteresis).min(speed)
+    }
+}
+
+#[cfg(test)]
+mod tests;
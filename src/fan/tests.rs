@@ -0,0 +1,33 @@
+//! Tests of the case fan.
+
+use super::*;
+
+#[test]
+fn the_curve_is_interpolated() {
+    let curve = FanConfig::default_curve();
+
+    assert_eq!(curve_speed(&curve, 40.0), 0.0);
+    assert_eq!(curve_speed(&curve, 55.0), 0.0);
+    assert!((curve_speed(&curve, 67.5) - 0.7).abs() < 1e-9);
+    assert_eq!(curve_speed(&curve, 90.0), 1.0);
+}
+
+#[test]
+fn the_fan_runs_at_full_speed_without_curve() {
+    assert_eq!(curve_speed(&[], 40.0), 1.0);
+}
+
+#[test]
+fn the_fan_slows_down_after_the_hysteresis() {
+    let curve = FanConfig::default_curve();
+
+    // Speeding up is immediate.
+    assert_eq!(next_speed(&curve, 3.0, 0.0, 75.0), 1.0);
+
+    // Slowing down waits for the temperature to drop.
+    assert_eq!(next_speed(&curve, 3.0, 1.0, 73.0), 1.0);
+    assert!((next_speed(&curve, 3.0, 1.0, 67.5) - 0.82).abs() < 1e-9);
+
+    // The fan stops once well below the curve.
+    assert_eq!(next_speed(&curve, 3.0, 0.4, 50.0), 0.0);
+}
@@ -0,0 +1,38 @@
+//! The files the panel keeps its state in.
+
+use std::{fs::File, io::Write, path::Path};
+
+use anyhow::Context;
+
+/// Write a file atomically, through a temporary file next to it which then
+/// replaces it, so that a crash or a power cut never leaves it half written.
+///
+/// The missing parent directories are created.
+pub fn write_atomic(path: &Path, content: impl AsRef<[u8]>) -> anyhow::Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create `{}`", parent.display()))?;
+    }
+
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+
+    tmp_name.push(".tmp");
+
+    let tmp_path = path.with_file_name(tmp_name);
+    let write = || {
+        let mut file = File::create(&tmp_path)?;
+
+        file.write_all(content.as_ref())?;
+        file.sync_all()
+    };
+
+    write().with_context(|| format!("failed to write `{}`", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, path)
+        .with_context(|| format!("failed to replace `{}`", path.display()))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,23 @@
+//! Tests of the state files.
+
+use super::*;
+
+#[test]
+fn files_are_replaced_through_a_temporary_file() {
+    let dir = std::env::temp_dir().join(format!("write-atomic-{}", std::process::id()));
+    let path = dir.join("state").join("usage.json");
+
+    write_atomic(&path, "{}").unwrap();
+    write_atomic(&path, "{\"uses\": 1}").unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let files: Vec<_> = std::fs::read_dir(path.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(content, "{\"uses\": 1}");
+    assert_eq!(files, ["usage.json"]);
+}
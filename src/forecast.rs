@@ -0,0 +1,210 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local, Utc};
+use log::debug;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::{
+    home_assistant::{State, WeatherForecast},
+    Result,
+};
+
+/// The cache of the forecast computed from the weather entity.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ForecastCacheConfig {
+    /// For how long a forecast is served without computing it again, unless
+    /// the weather entity changes.
+    #[serde(default = "ForecastCacheConfig::default_ttl")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub ttl: Duration,
+
+    /// For how long the last forecast is served, flagged as stale, while it
+    /// cannot be computed again, like while the weather entity is
+    /// unavailable.
+    #[serde(default = "ForecastCacheConfig::default_max_stale")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub max_stale: Duration,
+}
+
+impl Default for ForecastCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Self::default_ttl(),
+            max_stale: Self::default_max_stale(),
+        }
+    }
+}
+
+impl ForecastCacheConfig {
+    fn default_ttl() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_max_stale() -> Duration {
+        Duration::from_secs(60 * 60)
+    }
+}
+
+/// A forecast, and whether it is the last known one rather than a current
+/// one.
+#[derive(Debug, Clone)]
+pub struct Forecast<T> {
+    pub value: T,
+    pub stale: bool,
+}
+
+struct CachedForecast<T> {
+    value: T,
+    computed_at: Instant,
+
+    /// The last update of the weather entity the forecast was computed from.
+    last_updated: DateTime<Utc>,
+}
+
+/// Caches the forecast computed from the weather entity, so that it does not
+/// blank out while the entity is briefly unavailable.
+pub struct ForecastCache<T> {
+    config: ForecastCacheConfig,
+    cached: Mutex<Option<CachedForecast<T>>>,
+}
+
+impl<T: Clone> ForecastCache<T> {
+    pub fn new(config: ForecastCacheConfig) -> Self {
+        Self {
+            config,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Get the forecast for a state of the weather entity, if known.
+    ///
+    /// The cached forecast is served while fresh, and computed again once the
+    /// entity changed or the TTL elapsed. If it cannot be, the cached one is
+    /// served as stale until too old.
+    pub fn get(
+        &self,
+        state: Option<&State>,
+        compute: impl FnOnce() -> Result<T>,
+    ) -> Result<Forecast<T>> {
+        let mut cached = self.cached.lock().unwrap();
+        let last_updated = state.map(|state| state.last_updated);
+
+        if let Some(cached) = &*cached {
+            if Some(cached.last_updated) == last_updated
+                && cached.computed_at.elapsed() < self.config.ttl
+            {
+                return Ok(Forecast {
+                    value: cached.value.clone(),
+                    stale: false,
+                });
+            }
+        }
+
+        match (compute(), last_updated) {
+            (Ok(value), Some(last_updated)) => {
+                *cached = Some(CachedForecast {
+                    value: value.clone(),
+                    computed_at: Instant::now(),
+                    last_updated,
+                });
+
+                Ok(Forecast {
+                    value,
+                    stale: false,
+                })
+            }
+            (Ok(value), None) => Ok(Forecast {
+                value,
+                stale: false,
+            }),
+            (Err(err), _) => match self.stale(&cached) {
+                Some(forecast) => {
+                    debug!("Serving the last forecast: {}", err);
+
+                    Ok(forecast)
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Get the last forecast, as stale, unless too old, like while
+    /// Home-Assistant is disconnected.
+    pub fn last(&self) -> Option<Forecast<T>> {
+        self.stale(&self.cached.lock().unwrap())
+    }
+
+    fn stale(&self, cached: &Option<CachedForecast<T>>) -> Option<Forecast<T>> {
+        cached
+            .as_ref()
+            .filter(|cached| cached.computed_at.elapsed() < self.config.max_stale)
+            .map(|cached| Forecast {
+                value: cached.value.clone(),
+                stale: true,
+            })
+    }
+}
+
+/// The summary of the forecast for the rest of the day.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TodaySummary {
+    pub temperature_min: f64,
+    pub temperature_max: f64,
+
+    /// The highest probability of precipitation, in percent, if the weather
+    /// integration reports any.
+    pub precipitation_probability: Option<f64>,
+
+    /// The total of the forecast precipitation.
+    pub precipitation: f64,
+}
+
+impl TodaySummary {
+    /// Summarize the forecast entries of the day of `now`, either daily or
+    /// hourly ones.
+    pub fn new(forecast: &[WeatherForecast], now: DateTime<Local>) -> Option<Self> {
+        let today = now.date_naive();
+        let mut entries = forecast
+            .iter()
+            .filter(|entry| entry.datetime.with_timezone(&Local).date_naive() == today)
+            .peekable();
+
+        entries.peek()?;
+
+        let mut summary = Self {
+            temperature_min: f64::INFINITY,
+            temperature_max: f64::NEG_INFINITY,
+            precipitation_probability: None,
+            precipitation: 0.0,
+        };
+
+        for entry in entries {
+            // Only the daily entries have a low temperature.
+            summary.temperature_min = summary
+                .temperature_min
+                .min(entry.templow.unwrap_or(entry.temperature));
+            summary.temperature_max = summary.temperature_max.max(entry.temperature);
+            summary.precipitation += entry.precipitation;
+
+            if let Some(probability) = entry.precipitation_probability {
+                summary.precipitation_probability = Some(
+                    summary
+                        .precipitation_probability
+                        .map_or(probability, |max| max.max(probability)),
+                );
+            }
+        }
+
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,59 @@
+//! Tests of the forecast.
+
+use super::*;
+
+fn state(last_updated: DateTime<Utc>) -> State {
+    serde_json::from_value(serde_json::json!({
+        "entity_id": "weather.home",
+        "state": "sunny",
+        "last_changed": last_updated,
+        "last_updated": last_updated,
+    }))
+    .unwrap()
+}
+
+fn unavailable() -> Result<u32> {
+    Err(crate::Error::InvalidConfig("unavailable".to_string()))
+}
+
+#[test]
+fn the_forecast_is_computed_again_once_the_entity_changed() {
+    let cache = ForecastCache::new(ForecastCacheConfig::default());
+    let now = Utc::now();
+
+    assert_eq!(cache.get(Some(&state(now)), || Ok(1)).unwrap().value, 1);
+    assert_eq!(cache.get(Some(&state(now)), || Ok(2)).unwrap().value, 1);
+    assert_eq!(
+        cache
+            .get(Some(&state(now + chrono::Duration::seconds(1))), || Ok(3))
+            .unwrap()
+            .value,
+        3
+    );
+}
+
+#[test]
+fn the_last_forecast_is_served_as_stale() {
+    let cache = ForecastCache::new(ForecastCacheConfig::default());
+
+    assert!(cache.get(None, unavailable).is_err());
+
+    cache.get(Some(&state(Utc::now())), || Ok(1)).unwrap();
+
+    let forecast = cache.get(None, unavailable).unwrap();
+
+    assert_eq!(forecast.value, 1);
+    assert!(forecast.stale);
+}
+
+#[test]
+fn the_stale_forecast_expires() {
+    let cache = ForecastCache::new(ForecastCacheConfig {
+        ttl: Duration::ZERO,
+        max_stale: Duration::ZERO,
+    });
+
+    cache.get(Some(&state(Utc::now())), || Ok(1)).unwrap();
+
+    assert!(cache.get(None, unavailable).is_err());
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    context::AppContext,
+    home_assistant::{State, Status, WeatherState},
+    tasks,
+};
+
+/// The event fired in Home-Assistant when frost is likely.
+const FROST_EVENT: &str = "home_control_frost_warning";
+
+/// The interval between two checks of the time.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The conditions of the nights clear enough for the windshields to cool
+/// below the air.
+const CLEAR_CONDITIONS: &[&str] = &["clear-night", "sunny", "partlycloudy"];
+
+/// The windshield frost warning configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FrostConfig {
+    /// How much colder than the air the windshields get on clear nights, in
+    /// °C.
+    #[serde(default = "FrostConfig::default_radiative_cooling")]
+    pub radiative_cooling: f64,
+
+    /// When to check the forecast of the coming night, every evening.
+    #[serde(default = "FrostConfig::default_check_at")]
+    pub check_at: NaiveTime,
+
+    /// Whether to fire a `home_control_frost_warning` event in Home-Assistant
+    /// when frost is likely.
+    #[serde(default)]
+    pub fire_event: bool,
+}
+
+impl FrostConfig {
+    fn default_radiative_cooling() -> f64 {
+        3.0
+    }
+
+    fn default_check_at() -> NaiveTime {
+        NaiveTime::from_hms_opt(18, 0, 0).unwrap_or_default()
+    }
+
+    /// Get the frost forecast for the coming night, from the weather entity.
+    pub fn forecast(
+        &self,
+        entities: &HashMap<String, State>,
+        weather_entity: &str,
+        now: DateTime<Local>,
+    ) -> Option<FrostForecast> {
+        let weather: WeatherState = entities.get(weather_entity).cloned()?.try_into().ok()?;
+
+        FrostForecast::new(self, &weather, now)
+    }
+}
+
+/// Whether frost is likely on the car windshields over a night.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FrostForecast {
+    /// The evening the night starts.
+    pub night_of: NaiveDate,
+
+    /// The lowest forecast temperature, in the unit of the weather entity.
+    pub temperature_low: f64,
+
+    /// The humidity around the lowest temperature, in percent, or the
+    /// current one if not forecast.
+    pub humidity: f64,
+    pub likely: bool,
+}
+
+impl FrostForecast {
+    /// Forecast the frost of the coming night, or of the current one before
+    /// the morning.
+    pub fn new(config: &FrostConfig, weather: &WeatherState, now: DateTime<Local>) -> Option<Self> {
+        let evening = NaiveTime::from_hms_opt(18, 0, 0)?;
+        let morning = NaiveTime::from_hms_opt(9, 0, 0)?;
+        let night_of = if now.time() < morning {
+            now.date_naive().pred_opt()?
+        } else {
+            now.date_naive()
+        };
+        let day_after = night_of.succ_opt()?;
+        let start = Local
+            .from_local_datetime(&night_of.and_time(evening))
+            .earliest()?;
+        let end = Local
+            .from_local_datetime(&day_after.and_time(morning))
+            .earliest()?;
+        let forecast = &weather.attributes.forecast;
+
+        // The hourly entries of the night, or else the daily entry of the day
+        // after, whose low is in the morning.
+        let entries: Vec<_> = forecast
+            .iter()
+            .filter(|entry| {
+                let datetime = entry.datetime.with_timezone(&Local);
+
+                start <= datetime && datetime < end
+            })
+            .collect();
+        let entries = if entries.is_empty() {
+            forecast
+                .iter()
+                .filter(|entry| {
+                    entry.templow.is_some()
+                        && entry.datetime.with_timezone(&Local).date_naive() == day_after
+                })
+                .collect()
+        } else {
+            entries
+        };
+
+        let coldest = entries.into_iter().min_by(|a, b| {
+            a.templow
+                .unwrap_or(a.temperature)
+                .total_cmp(&b.templow.unwrap_or(b.temperature))
+        })?;
+        let temperature_low = coldest.templow.unwrap_or(coldest.temperature);
+        let humidity = coldest.humidity.unwrap_or(weather.attributes.humidity);
+        let celsius = match weather.attributes.temperature_unit.as_deref() {
+            Some("°F") => (temperature_low - 32.0) * 5.0 / 9.0,
+            _ => temperature_low,
+        };
+        let windshield = if CLEAR_CONDITIONS.contains(&coldest.condition.as_str()) {
+            celsius - config.radiative_cooling
+        } else {
+            celsius
+        };
+
+        // The frost forms when the windshields freeze and the air condenses
+        // on them.
+        let likely = windshield <= 0.0 && windshield <= dew_point(celsius, humidity);
+
+        Some(Self {
+            night_of,
+            temperature_low,
+            humidity,
+            likely,
+        })
+    }
+}
+
+/// Get the dew point of the air, in °C, with the Magnus formula.
+fn dew_point(celsius: f64, humidity: f64) -> f64 {
+    const B: f64 = 17.62;
+    const C: f64 = 243.12;
+
+    let gamma = (humidity.clamp(1.0, 100.0) / 100.0).ln() + B * celsius / (C + celsius);
+
+    C * gamma / (B - gamma)
+}
+
+/// Check the forecast every evening forever, and warn Home-Assistant when
+/// frost is likely.
+pub async fn run(config: &FrostConfig, context: &AppContext) -> anyhow::Result<()> {
+    let mut last_check = Local::now().naive_local();
+
+    loop {
+        tokio::time::sleep(CHECK_INTERVAL).await;
+        tasks::heartbeat();
+
+        let now = Local::now();
+        let at = now.date_naive().and_time(config.check_at);
+        let previous_check = std::mem::replace(&mut last_check, now.naive_local());
+
+        if !(previous_check < at && at <= last_check) {
+            continue;
+        }
+
+        let entities = match context.home_assistant.status().await {
+            Status::Connected { entities } => entities,
+            Status::Disconnected => continue,
+        };
+        let forecast = match config.forecast(&entities, &context.config.weather_entity, now) {
+            Some(forecast) => forecast,
+            None => {
+                warn!("No forecast to check the frost against.");
+                continue;
+            }
+        };
+
+        if !forecast.likely {
+            continue;
+        }
+
+        info!(
+            "Frost is likely tonight, with a low of {}.",
+            forecast.temperature_low
+        );
+
+        if config.fire_event {
+            if let Err(err) = context
+                .home_assistant
+                .fire_event(
+                    FROST_EVENT,
+                    &json!({
+                        "night_of": forecast.night_of,
+                        "temperature_low": forecast.temperature_low,
+                        "humidity": forecast.humidity,
+                    }),
+                )
+                .await
+            {
+                warn!("Failed to fire the frost warning event: {}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::{actions::Action, context::AppContext};
+
+/// The gestures detected over the distance sensor.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct GesturesConfig {
+    /// The distance under which the hand is near the sensor.
+    #[serde(default = "GesturesConfig::default_near_cm")]
+    pub near_cm: f64,
+
+    /// The distance above which the hand is away from the sensor.
+    #[serde(default = "GesturesConfig::default_far_cm")]
+    pub far_cm: f64,
+
+    /// The number of near/far transitions of a wave.
+    #[serde(default = "GesturesConfig::default_transitions")]
+    pub transitions: usize,
+
+    /// How long the transitions of a wave may take at most.
+    #[serde(default = "GesturesConfig::default_window")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub window: Duration,
+
+    /// How long to ignore the readings after a gesture, for the hand to go
+    /// away.
+    #[serde(default = "GesturesConfig::default_cooldown")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub cooldown: Duration,
+
+    /// The interval between two readings of the distance sensor, which
+    /// replaces the one of the presence detection when shorter: waves are
+    /// only caught with fast readings.
+    #[serde(default = "GesturesConfig::default_poll_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub poll_interval: Duration,
+
+    /// The actions triggered by a wave.
+    #[serde(default)]
+    pub on_wave: Vec<Action>,
+}
+
+impl GesturesConfig {
+    fn default_near_cm() -> f64 {
+        15.0
+    }
+
+    fn default_far_cm() -> f64 {
+        30.0
+    }
+
+    fn default_transitions() -> usize {
+        4
+    }
+
+    fn default_window() -> Duration {
+        Duration::from_millis(1500)
+    }
+
+    fn default_cooldown() -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn default_poll_interval() -> Duration {
+        Duration::from_millis(60)
+    }
+
+    /// Run the actions of a gesture.
+    pub async fn trigger(&self, gesture: Gesture, context: &AppContext) {
+        let actions = match gesture {
+            Gesture::Wave => &self.on_wave,
+        };
+
+        for action in actions {
+            if let Err(err) = action.run(context).await {
+                warn!(
+                    "Failed to run the action of a {:?} gesture: {}",
+                    gesture, err
+                );
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Gesture {
+    /// Rapid near/far transitions of a hand in front of the sensor.
+    Wave,
+}
+
+/// A detected gesture, as streamed to the frontend.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectedGesture {
+    pub gesture: Gesture,
+    pub at: DateTime<Utc>,
+}
+
+/// Detects the gestures over the distance readings.
+pub struct GestureDetector {
+    config: GesturesConfig,
+    near: Option<bool>,
+    transitions: VecDeque<Instant>,
+    cooldown_until: Option<Instant>,
+}
+
+impl GestureDetector {
+    pub fn new(config: GesturesConfig) -> Self {
+        Self {
+            config,
+            near: None,
+            transitions: VecDeque::new(),
+            cooldown_until: None,
+        }
+    }
+
+    pub fn config(&self) -> &GesturesConfig {
+        &self.config
+    }
+
+    /// Feed a distance reading, and get the gesture it completes, if any.
+    ///
+    /// The distances between the near and far bounds do not change the
+    /// position of the hand.
+    pub fn update(&mut self, cm: f64, at: Instant) -> Option<Gesture> {
+        if matches!(self.cooldown_until, Some(until) if at < until) {
+            return None;
+        }
+
+        let near = if cm <= self.config.near_cm {
+            true
+        } else if cm >= self.config.far_cm {
+            false
+        } else {
+            return None;
+        };
+
+        if self.near.replace(near) == Some(!near) {
+            self.transitions.push_back(at);
+        }
+
+        // Only the transitions within the window count.
+        while let Some(first) = self.transitions.front() {
+            if at.duration_since(*first) <= self.config.window {
+                break;
+            }
+
+            self.transitions.pop_front();
+        }
+
+        if self.transitions.len() < self.config.transitions {
+            return None;
+        }
+
+        info!("Wave gesture detected.");
+
+        self.transitions.clear();
+        self.near = None;
+        self.cooldown_until = Some(at + self.config.cooldown);
+
+        Some(Gesture::Wave)
+    }
+}
@@ -1,6 +1,13 @@
 use anyhow::Result;
-use log::info;
-use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 #[cfg(feature = "gpio")]
 use rppal::{
@@ -8,15 +15,137 @@ use rppal::{
     system::DeviceInfo,
 };
 
-use crate::config::GpioConfig;
+use crate::{config::GpioConfig, red_led::RedLed};
+
+/// The device giving access to the GPIO registers without root privileges.
+#[cfg(feature = "gpio")]
+const GPIOMEM: &str = "/dev/gpiomem";
+
+/// How long a distance measurement may take before it is considered hung.
+const DISTANCE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long sending pulses may take before it is considered hung. Long IR
+/// codes take a few hundred milliseconds.
+const PULSES_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct GpioController {
-    #[cfg(feature = "gpio")]
     config: GpioConfig,
+
+    /// The GPIO, or `None` when simulated without the hardware.
+    #[cfg(feature = "gpio")]
+    gpio: Option<Gpio>,
+
+    /// The output pins, kept so that they hold their level.
+    #[cfg(feature = "gpio")]
+    outputs: Mutex<HashMap<u8, OutputPin>>,
     #[cfg(feature = "gpio")]
-    gpio: Gpio,
+    inputs: Mutex<HashMap<u8, InputPin>>,
+    levels: Mutex<HashMap<u8, PinLevel>>,
+    health: Mutex<GpioHealth>,
+
+    /// The echo of the distance sensor, as simulated.
+    simulated_echo: Mutex<Duration>,
+
+    /// The claims of the subsystems signaling on the red LED.
+    red_led: RedLed,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PinLevel {
+    high: bool,
+    last_change: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum PinMode {
+    Input,
+    Output,
+}
+
+/// The pull resistor of an input pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pull {
+    #[default]
+    None,
+    Up,
+    Down,
+}
+
+/// The state of a pin, as last seen by the controller.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PinStatus {
+    pub name: String,
+    pub pin: u8,
+    pub mode: PinMode,
+
+    /// Whether the pin is high, or `None` if it was never used.
+    pub high: Option<bool>,
+    pub last_change: Option<DateTime<Utc>>,
+}
+
+/// The health of the blocking GPIO operations.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GpioHealth {
+    /// Whether the GPIO is simulated, without the hardware.
+    pub simulated: bool,
+
+    /// The total number of failed operations.
+    pub failures: u64,
+
+    /// The number of operations that failed since the last success.
+    pub consecutive_failures: u64,
+    pub last_error: Option<String>,
+}
+
+/// The temperature assumed when the ambient temperature is unknown.
+const DEFAULT_TEMPERATURE_C: f64 = 20.0;
+
+/// A distance measured by the ultrasonic sensor.
+#[derive(Debug, Clone, Copy)]
+pub struct Distance {
+    /// The duration of the echo, for debugging.
+    pub echo: Duration,
+
+    /// The ambient temperature the distance was computed for.
+    pub temperature_c: f64,
+    pub cm: f64,
+}
+
+impl Distance {
+    fn from_echo(echo: Duration, temperature_c: f64) -> Self {
+        // Sound travels at about 331.3 m/s at 0°C, and 0.606 m/s faster for
+        // every degree. Divide by 2 because the echo is a round trip.
+        //
+        // Yay for physics!
+        let speed_cm_per_us = (331.3 + 0.606 * temperature_c) * 1e-4;
+
+        Self {
+            echo,
+            temperature_c,
+            cm: echo.as_secs_f64() * 1e6 * speed_cm_per_us / 2.0,
+        }
+    }
+
+    /// Get the echo of a distance, the other way around.
+    fn echo_for(cm: f64, temperature_c: f64) -> Duration {
+        let speed_cm_per_us = (331.3 + 0.606 * temperature_c) * 1e-4;
+
+        Duration::from_secs_f64((cm * 2.0 / speed_cm_per_us / 1e6).max(0.0))
+    }
+}
+
+/// A carrier modulating pulses, like the 38 kHz carrier of IR remotes.
+#[derive(Debug, Clone, Copy)]
+pub struct Carrier {
+    pub frequency: f64,
+    pub duty_cycle: f64,
 }
 
+#[derive(Debug, Clone, Copy)]
 pub enum GpioPin {
     RedLed,
     GreenLed,
@@ -25,8 +154,32 @@ pub enum GpioPin {
     Echo,
 }
 
-#[cfg(feature = "gpio")]
 impl GpioPin {
+    const ALL: [GpioPin; 5] = [
+        GpioPin::RedLed,
+        GpioPin::GreenLed,
+        GpioPin::Buzzer,
+        GpioPin::Trigger,
+        GpioPin::Echo,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            GpioPin::RedLed => "red_led",
+            GpioPin::GreenLed => "green_led",
+            GpioPin::Buzzer => "buzzer",
+            GpioPin::Trigger => "trigger",
+            GpioPin::Echo => "echo",
+        }
+    }
+
+    fn mode(self) -> PinMode {
+        match self {
+            GpioPin::Echo => PinMode::Input,
+            _ => PinMode::Output,
+        }
+    }
+
     fn into_pin_number(self, config: &GpioConfig) -> u8 {
         match self {
             GpioPin::RedLed => config.red_led_pin,
@@ -38,50 +191,125 @@ impl GpioPin {
     }
 }
 
+/// The operations on the GPIO hardware.
 #[cfg(feature = "gpio")]
 impl GpioController {
-    pub fn new(config: GpioConfig) -> Result<GpioController> {
+    /// Open the GPIO, or fall back to simulating it without the hardware,
+    /// like on a development machine.
+    fn open_gpio() -> Result<Option<Gpio>> {
         use anyhow::Context;
 
+        // Containers do not get the device unless explicitly passed through,
+        // which otherwise fails with an obscure permission error.
+        if !std::path::Path::new(GPIOMEM).exists() {
+            warn!(
+                "`{}` is missing: simulating the GPIO. When running in a container on a Pi, pass the device through, like with `devices: [\"{}:{}\"]` in the compose file.",
+                GPIOMEM,
+                GPIOMEM,
+                GPIOMEM
+            );
+
+            return Ok(None);
+        }
+
         let model = DeviceInfo::new()
             .context("failed to query Raspberry Pi model")?
             .model();
 
         info!("Raspberry Pi model: {}", model);
 
-        let gpio = Gpio::new().context("failed to initialize GPIO")?;
-
-        Ok(GpioController { config, gpio })
+        Ok(Some(Gpio::new().context("failed to initialize GPIO")?))
     }
 
-    fn get_output_pin(&self, pin: GpioPin) -> anyhow::Result<OutputPin> {
+    fn get_input_pin(&self, gpio: &Gpio, pin: GpioPin) -> anyhow::Result<InputPin> {
         let pin = pin.into_pin_number(&self.config);
-        Ok(self.gpio.get(pin)?.into_output())
+        Ok(gpio.get(pin)?.into_input())
     }
 
-    fn get_input_pin(&self, pin: GpioPin) -> anyhow::Result<InputPin> {
-        let pin = pin.into_pin_number(&self.config);
-        Ok(self.gpio.get(pin)?.into_input())
-    }
+    fn set_hardware_output(&self, gpio: &Gpio, pin: u8, status: bool) -> anyhow::Result<()> {
+        let mut outputs = self.outputs.lock().unwrap();
+        let output = match outputs.entry(pin) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(gpio.get(pin)?.into_output())
+            }
+        };
 
-    fn set_output_pin_status(&self, pin: GpioPin, status: bool) -> anyhow::Result<()> {
-        let status: bool = status.into();
+        if status {
+            output.set_high();
+        } else {
+            output.set_low();
+        }
 
-        let mut pin = self.get_output_pin(pin)?;
+        self.record_level(pin, status);
 
-        if status {
-            pin.set_high();
+        Ok(())
+    }
+
+    fn set_hardware_pwm(
+        &self,
+        gpio: &Gpio,
+        pin: u8,
+        frequency: f64,
+        duty_cycle: f64,
+    ) -> anyhow::Result<()> {
+        let mut outputs = self.outputs.lock().unwrap();
+        let output = match outputs.entry(pin) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(gpio.get(pin)?.into_output())
+            }
+        };
+
+        if duty_cycle <= 0.0 {
+            output.clear_pwm()?;
+            output.set_low();
+        } else if duty_cycle >= 1.0 {
+            output.clear_pwm()?;
+            output.set_high();
         } else {
-            pin.set_low();
+            output.set_pwm_frequency(frequency, duty_cycle)?;
         }
 
+        self.record_level(pin, duty_cycle > 0.0);
+
         Ok(())
     }
 
-    fn compute_distance(&self) -> anyhow::Result<f64> {
+    fn read_hardware_input(&self, gpio: &Gpio, pin: u8, pull: Pull) -> anyhow::Result<bool> {
+        let mut inputs = self.inputs.lock().unwrap();
+        let input = match inputs.entry(pin) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                let gpio_pin = gpio.get(pin)?;
+
+                entry.insert(match pull {
+                    Pull::None => gpio_pin.into_input(),
+                    Pull::Up => gpio_pin.into_input_pullup(),
+                    Pull::Down => gpio_pin.into_input_pulldown(),
+                })
+            }
+        };
+        let high = input.is_high();
+
+        self.record_level(pin, high);
+
+        Ok(high)
+    }
+
+    /// Get the level of an output pin, from its handle.
+    fn hardware_level(&self, pin: u8) -> Option<bool> {
+        self.outputs
+            .lock()
+            .unwrap()
+            .get(&pin)
+            .map(|output| output.is_set_high())
+    }
+
+    fn measure_hardware_echo(&self, gpio: &Gpio) -> anyhow::Result<Duration> {
         use anyhow::Context;
 
-        let mut echo_pin = self.get_input_pin(GpioPin::Echo)?;
+        let mut echo_pin = self.get_input_pin(gpio, GpioPin::Echo)?;
 
         // Set an interrupt *before* the trigger pin is set high.
         echo_pin
@@ -98,76 +326,379 @@ impl GpioController {
             .poll_interrupt(false, Some(std::time::Duration::from_millis(10)))?
             .ok_or_else(|| anyhow::anyhow!("polling for rising edge timed out"))?;
         let start = std::time::Instant::now();
+        self.record_level(self.config.echo_pin, true);
 
         echo_pin
             .poll_interrupt(false, Some(std::time::Duration::from_millis(10)))?
             .ok_or_else(|| anyhow::anyhow!("polling for falling edge timed out"))?;
         let stop = std::time::Instant::now();
+        self.record_level(self.config.echo_pin, false);
 
-        // Now compute the distance.
-        let elapsed = stop.duration_since(start);
+        Ok(stop.duration_since(start))
+    }
 
-        // Sound travels at 343 m/s, which equates to 34300 * 10^-6 = 0.0343 cm/us.
-        // Also divide by 2 because this is the duration of the round trip.
-        //
-        // Yay for physics!
-        Ok(elapsed.as_micros() as f64 * 0.0343 / 2.0)
+    /// Send alternating mark/space pulses, optionally modulated by a carrier.
+    ///
+    /// The pulses and the carrier are bit-banged with busy waits, as software
+    /// PWM and sleeps are too imprecise for IR and RF receivers.
+    fn send_hardware_pulses(
+        &self,
+        gpio: &Gpio,
+        pin: u8,
+        carrier: Option<Carrier>,
+        timings: &[u32],
+    ) -> anyhow::Result<()> {
+        use std::time::{Duration, Instant};
+
+        fn busy_wait_until(deadline: Instant) {
+            while Instant::now() < deadline {
+                std::hint::spin_loop();
+            }
+        }
+
+        let mut pin = gpio.get(pin)?.into_output_low();
+        let mut deadline = Instant::now();
+
+        for (i, timing) in timings.iter().enumerate() {
+            let end = deadline + Duration::from_micros((*timing).into());
+
+            match carrier {
+                _ if i % 2 == 1 => busy_wait_until(end),
+                Some(carrier) => {
+                    let period = Duration::from_secs_f64(1.0 / carrier.frequency);
+                    let high = period.mul_f64(carrier.duty_cycle);
+
+                    while deadline < end {
+                        pin.set_high();
+                        busy_wait_until(deadline + high);
+                        pin.set_low();
+                        deadline += period;
+                        busy_wait_until(deadline);
+                    }
+                }
+                None => {
+                    pin.set_high();
+                    busy_wait_until(end);
+                    pin.set_low();
+                }
+            }
+
+            deadline = end;
+        }
+
+        pin.set_low();
+
+        Ok(())
     }
+}
 
-    pub fn set_red_led(&self, status: bool) -> anyhow::Result<()> {
-        info!("Setting red led to {}", status);
+impl GpioController {
+    /// Open the GPIO, which is simulated when built without GPIO support or
+    /// when the hardware is missing.
+    pub fn new(config: GpioConfig) -> Result<GpioController> {
+        #[cfg(feature = "gpio")]
+        let gpio = Self::open_gpio()?;
+        #[cfg(feature = "gpio")]
+        let simulated = gpio.is_none();
+        #[cfg(not(feature = "gpio"))]
+        let simulated = true;
+
+        #[cfg(not(feature = "gpio"))]
+        info!("Running without GPIO support");
 
-        self.set_output_pin_status(GpioPin::RedLed, status)
+        Ok(GpioController {
+            config,
+            #[cfg(feature = "gpio")]
+            gpio,
+            #[cfg(feature = "gpio")]
+            outputs: Default::default(),
+            #[cfg(feature = "gpio")]
+            inputs: Default::default(),
+            levels: Default::default(),
+            health: Mutex::new(GpioHealth {
+                simulated,
+                ..Default::default()
+            }),
+            simulated_echo: Mutex::new(Duration::ZERO),
+            red_led: RedLed::new(),
+        })
     }
 
-    pub fn set_green_led(&self, status: bool) -> anyhow::Result<()> {
-        info!("Setting green led to {}", status);
+    /// Get the GPIO hardware, if not simulated.
+    #[cfg(feature = "gpio")]
+    fn hardware(&self) -> Option<&Gpio> {
+        self.gpio.as_ref()
+    }
 
-        self.set_output_pin_status(GpioPin::GreenLed, status)
+    /// Fail unless the GPIO is simulated, as the simulated readings would
+    /// otherwise be overwritten by the real ones.
+    fn ensure_simulated(&self) -> anyhow::Result<()> {
+        #[cfg(feature = "gpio")]
+        if self.hardware().is_some() {
+            anyhow::bail!("simulated readings require the GPIO to be simulated");
+        }
+
+        Ok(())
     }
 
-    pub fn set_buzzer(&self, status: bool) -> anyhow::Result<()> {
-        info!("Setting buzzer to {}", status);
+    /// Set the level of an output pin, configuring it on first use.
+    pub fn set_output(&self, pin: u8, status: bool) -> anyhow::Result<()> {
+        #[cfg(feature = "gpio")]
+        if let Some(gpio) = self.hardware() {
+            return self.set_hardware_output(gpio, pin, status);
+        }
 
-        self.set_output_pin_status(GpioPin::Buzzer, status)
+        self.record_level(pin, status);
+
+        Ok(())
     }
-}
 
-#[cfg(not(feature = "gpio"))]
-impl GpioController {
-    pub fn new(_config: GpioConfig) -> Result<GpioController> {
-        info!("Running without GPIO support");
+    /// Drive an output pin with a software PWM, configuring it on first use.
+    ///
+    /// The duty cycles of 0 and 1 hold the pin low and high.
+    #[cfg_attr(not(feature = "gpio"), allow(unused_variables))]
+    pub fn set_pwm(&self, pin: u8, frequency: f64, duty_cycle: f64) -> anyhow::Result<()> {
+        #[cfg(feature = "gpio")]
+        if let Some(gpio) = self.hardware() {
+            return self.set_hardware_pwm(gpio, pin, frequency, duty_cycle);
+        }
+
+        self.record_level(pin, duty_cycle > 0.0);
+
+        Ok(())
+    }
+
+    /// Simulate the distance sensor reading a distance, in cm.
+    pub fn simulate_distance(&self, cm: f64) -> anyhow::Result<()> {
+        self.ensure_simulated()?;
+
+        *self.simulated_echo.lock().unwrap() = Distance::echo_for(cm, DEFAULT_TEMPERATURE_C);
+
+        Ok(())
+    }
+
+    /// Simulate the level of an input pin.
+    pub fn simulate_input(&self, pin: u8, status: bool) -> anyhow::Result<()> {
+        self.ensure_simulated()?;
+
+        self.record_level(pin, status);
+
+        Ok(())
+    }
+
+    /// Get the level of a pin, from its handle if it is an output.
+    fn level(&self, pin: u8) -> Option<bool> {
+        #[cfg(feature = "gpio")]
+        if let Some(high) = self.hardware_level(pin) {
+            return Some(high);
+        }
+
+        self.levels
+            .lock()
+            .unwrap()
+            .get(&pin)
+            .map(|level| level.high)
+    }
 
-        Ok(GpioController {})
+    /// Read the level of an input pin, configuring it on first use.
+    pub fn read_input(&self, pin: u8, pull: Pull) -> anyhow::Result<bool> {
+        crate::chaos::check_gpio_read()?;
+
+        #[cfg(feature = "gpio")]
+        if let Some(gpio) = self.hardware() {
+            return self.read_hardware_input(gpio, pin, pull);
+        }
+
+        // Pulled-up inputs are high when left alone.
+        Ok(self.level(pin).unwrap_or(pull == Pull::Up))
+    }
+
+    fn measure_echo(&self) -> anyhow::Result<Duration> {
+        #[cfg(feature = "gpio")]
+        if let Some(gpio) = self.hardware() {
+            return self.measure_hardware_echo(gpio);
+        }
+
+        Ok(*self.simulated_echo.lock().unwrap())
+    }
+
+    #[cfg_attr(not(feature = "gpio"), allow(unused_variables))]
+    fn send_pulses(
+        &self,
+        pin: u8,
+        carrier: Option<Carrier>,
+        timings: &[u32],
+    ) -> anyhow::Result<()> {
+        #[cfg(feature = "gpio")]
+        if let Some(gpio) = self.hardware() {
+            return self.send_hardware_pulses(gpio, pin, carrier, timings);
+        }
+
+        info!("Sending {} pulses on pin {}", timings.len(), pin);
+
+        Ok(())
+    }
+
+    fn set_output_pin_status(&self, pin: GpioPin, status: bool) -> anyhow::Result<()> {
+        self.set_output(pin.into_pin_number(&self.config), status)
+    }
+
+    /// Get the arbiter of the red LED, which the subsystems signal through
+    /// rather than with [`GpioController::set_red_led`].
+    pub fn red_led(&self) -> &RedLed {
+        &self.red_led
     }
 
     pub fn set_red_led(&self, status: bool) -> anyhow::Result<()> {
         info!("Setting red led to {}", status);
 
-        Ok(())
+        self.set_output_pin_status(GpioPin::RedLed, status)
     }
 
     pub fn set_green_led(&self, status: bool) -> anyhow::Result<()> {
         info!("Setting green led to {}", status);
 
-        Ok(())
+        self.set_output_pin_status(GpioPin::GreenLed, status)
     }
 
     pub fn set_buzzer(&self, status: bool) -> anyhow::Result<()> {
         info!("Setting buzzer to {}", status);
 
-        Ok(())
+        self.set_output_pin_status(GpioPin::Buzzer, status)
     }
 
-    fn compute_distance(&self) -> anyhow::Result<f64> {
-        Ok(0.0)
+    /// Measure the distance, compensating for the ambient temperature in °C
+    /// if it is known.
+    pub async fn get_distance(
+        self: &Arc<Self>,
+        temperature_c: Option<f64>,
+    ) -> anyhow::Result<Distance> {
+        crate::chaos::check_gpio_read()?;
+
+        let echo = self
+            .run_blocking("distance measurement", DISTANCE_TIMEOUT, |this| {
+                this.measure_echo()
+            })
+            .await?;
+
+        Ok(Distance::from_echo(
+            echo,
+            temperature_c.unwrap_or(DEFAULT_TEMPERATURE_C),
+        ))
     }
-}
 
-impl GpioController {
-    /// Get the distance in cm.
-    pub async fn get_distance_cm(self: &Arc<Self>) -> anyhow::Result<f64> {
+    /// Send alternating mark/space pulses on a pin, optionally modulated by a
+    /// carrier.
+    ///
+    /// The timings are in microseconds and start with a mark.
+    pub async fn send_pulses_async(
+        self: &Arc<Self>,
+        pin: u8,
+        carrier: Option<Carrier>,
+        timings: Vec<u32>,
+    ) -> anyhow::Result<()> {
+        self.run_blocking("sending pulses", PULSES_TIMEOUT, move |this| {
+            this.send_pulses(pin, carrier, &timings)?;
+
+            // Pins are left low after pulsing: record the change anyway.
+            this.record_level(pin, true);
+            this.record_level(pin, false);
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Get the state of the pins the controller drives.
+    pub fn pins(&self) -> Vec<PinStatus> {
+        GpioPin::ALL
+            .into_iter()
+            .map(|pin| self.pin_status(pin.name(), pin.into_pin_number(&self.config), pin.mode()))
+            .collect()
+    }
+
+    /// Get the state of a pin.
+    pub fn pin_status(&self, name: &str, pin: u8, mode: PinMode) -> PinStatus {
+        PinStatus {
+            name: name.to_string(),
+            pin,
+            mode,
+            high: self.level(pin),
+            last_change: self
+                .levels
+                .lock()
+                .unwrap()
+                .get(&pin)
+                .map(|level| level.last_change),
+        }
+    }
+
+    fn record_level(&self, pin: u8, high: bool) {
+        let mut levels = self.levels.lock().unwrap();
+
+        match levels.get(&pin) {
+            Some(level) if level.high == high => {}
+            _ => {
+                levels.insert(
+                    pin,
+                    PinLevel {
+                        high,
+                        last_change: Utc::now(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Get the health of the blocking GPIO operations.
+    pub fn health(&self) -> GpioHealth {
+        self.health.lock().unwrap().clone()
+    }
+
+    /// Run a blocking GPIO operation on the blocking thread pool.
+    ///
+    /// Panics and hangs are turned into errors, so that a misbehaving pin
+    /// cannot take its caller down. A hung operation keeps its thread busy
+    /// until it returns.
+    async fn run_blocking<T, F>(
+        self: &Arc<Self>,
+        operation: &str,
+        timeout: Duration,
+        f: F,
+    ) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Self) -> anyhow::Result<T> + Send + 'static,
+    {
         let this = Arc::clone(self);
-        tokio::task::spawn_blocking(move || this.compute_distance()).await?
+        let task = tokio::task::spawn_blocking(move || f(&this));
+
+        let result = match tokio::time::timeout(timeout, task).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(err)) if err.is_panic() => Err(anyhow::anyhow!("{} panicked", operation)),
+            Ok(Err(err)) => Err(anyhow::anyhow!("{} was cancelled: {}", operation, err)),
+            Err(_) => Err(anyhow::anyhow!(
+                "{} timed out after {:.2?}",
+                operation,
+                timeout
+            )),
+        };
+
+        let mut health = self.health.lock().unwrap();
+
+        match &result {
+            Ok(_) => health.consecutive_failures = 0,
+            Err(err) => {
+                health.failures += 1;
+                health.consecutive_failures += 1;
+                health.last_error = Some(err.to_string());
+
+                if health.consecutive_failures == 1 {
+                    warn!("GPIO {} failed: {}", operation, err);
+                }
+            }
+        }
+
+        result
     }
 }
@@ -0,0 +1,213 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{
+    sync::{broadcast::error::RecvError, watch},
+    task::JoinHandle,
+};
+
+use crate::{
+    context::AppContext,
+    home_assistant::Update,
+    outputs::{Output, OutputPattern},
+    tasks,
+};
+
+/// The leak and smoke sensors, which are acted upon as soon as Home-Assistant
+/// reports them, without waiting for the periodic watchers.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HazardsConfig {
+    /// The sensors, like `binary_sensor.kitchen_leak`.
+    pub sensors: Vec<HazardSensorConfig>,
+
+    /// The buzzer pattern while a hazard is detected.
+    #[serde(default = "HazardsConfig::default_buzzer")]
+    pub buzzer: OutputPattern,
+
+    /// The script to call when a hazard is detected, like
+    /// `script.hazard_alert`, with the `entity_id` and the `kind` of the
+    /// sensor as variables.
+    #[serde(default)]
+    pub script: Option<String>,
+}
+
+impl HazardsConfig {
+    fn default_buzzer() -> OutputPattern {
+        OutputPattern::Blink {
+            period: Duration::from_millis(400),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HazardSensorConfig {
+    pub entity_id: String,
+    pub kind: HazardKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HazardKind {
+    Leak,
+    Smoke,
+}
+
+/// A hazard detected by a sensor, as pushed to the frontend.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HazardAlert {
+    pub entity_id: String,
+    pub friendly_name: Option<String>,
+    pub kind: HazardKind,
+    pub raised_at: DateTime<Utc>,
+}
+
+pub struct Hazards {
+    config: HazardsConfig,
+    alerts: watch::Sender<Vec<HazardAlert>>,
+}
+
+impl Hazards {
+    pub fn new(config: HazardsConfig) -> Self {
+        Self {
+            config,
+            alerts: watch::channel(Vec::new()).0,
+        }
+    }
+
+    /// Get the hazards currently detected.
+    pub fn alerts(&self) -> Vec<HazardAlert> {
+        self.alerts.borrow().clone()
+    }
+
+    /// Watch the hazards currently detected.
+    pub fn watch(&self) -> watch::Receiver<Vec<HazardAlert>> {
+        self.alerts.subscribe()
+    }
+
+    /// Watch the sensors forever.
+    pub async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        let mut updates = context.home_assistant.subscribe_updates();
+        let mut buzzer: Option<JoinHandle<()>> = None;
+
+        // The sensors may already be on.
+        self.check_all(context, &mut buzzer).await;
+
+        loop {
+            match updates.recv().await {
+                Ok(Update::Event(event)) => {
+                    for entity_id in event.entity_ids() {
+                        if let Some(sensor) = self.sensor(entity_id) {
+                            self.check(context, sensor, &mut buzzer).await;
+                        }
+                    }
+                }
+                Ok(Update::Connected) => self.check_all(context, &mut buzzer).await,
+
+                // The sensors keep their last known state while disconnected.
+                Ok(Update::Disconnected) => {}
+                Err(RecvError::Lagged(_)) => self.check_all(context, &mut buzzer).await,
+                Err(RecvError::Closed) => return Ok(()),
+            }
+
+            tasks::heartbeat();
+        }
+    }
+
+    fn sensor(&self, entity_id: &str) -> Option<&HazardSensorConfig> {
+        self.config
+            .sensors
+            .iter()
+            .find(|sensor| sensor.entity_id == entity_id)
+    }
+
+    async fn check_all(&self, context: &AppContext, buzzer: &mut Option<JoinHandle<()>>) {
+        for sensor in &self.config.sensors {
+            self.check(context, sensor, buzzer).await;
+        }
+    }
+
+    /// Raise or clear the hazard of a sensor, after its state.
+    async fn check(
+        &self,
+        context: &AppContext,
+        sensor: &HazardSensorConfig,
+        buzzer: &mut Option<JoinHandle<()>>,
+    ) {
+        let state = context.home_assistant.entity(&sensor.entity_id).await;
+        let detected = matches!(&state, Some(state) if state.state == "on");
+        let active = self
+            .alerts
+            .borrow()
+            .iter()
+            .any(|alert| alert.entity_id == sensor.entity_id);
+
+        if detected && !active {
+            // The local alerts come first, as Home-Assistant may be slow.
+            if buzzer.is_none() {
+                let gpio = context.gpio.clone();
+                let pattern = self.config.buzzer;
+
+                *buzzer = Some(tokio::spawn(async move {
+                    if let Err(err) = pattern.drive(&gpio, Output::Buzzer).await {
+                        warn!("Failed to sound the hazard buzzer: {}", err);
+                    }
+                }));
+            }
+
+            if let Err(err) = context.screen.force_on(true) {
+                warn!("Failed to turn the screen on: {}", err);
+            }
+
+            warn!("{:?} detected by `{}`.", sensor.kind, sensor.entity_id);
+
+            self.alerts.send_modify(|alerts| {
+                alerts.push(HazardAlert {
+                    entity_id: sensor.entity_id.clone(),
+                    friendly_name: state.and_then(|state| state.attributes.get("friendly_name")),
+                    kind: sensor.kind,
+                    raised_at: Utc::now(),
+                })
+            });
+
+            if let Some(script) = &self.config.script {
+                self.call_script(context, script, sensor).await;
+            }
+        } else if !detected && active {
+            info!(
+                "{:?} no longer detected by `{}`.",
+                sensor.kind, sensor.entity_id
+            );
+
+            self.alerts
+                .send_modify(|alerts| alerts.retain(|alert| alert.entity_id != sensor.entity_id));
+
+            if self.alerts.borrow().is_empty() {
+                if let Some(buzzer) = buzzer.take() {
+                    buzzer.abort();
+                }
+
+                if let Err(err) = Output::Buzzer.set(&context.gpio, false) {
+                    warn!("Failed to silence the hazard buzzer: {}", err);
+                }
+            }
+        }
+    }
+
+    async fn call_script(&self, context: &AppContext, script: &str, sensor: &HazardSensorConfig) {
+        let script = script.strip_prefix("script.").unwrap_or(script);
+        let variables = json!({ "entity_id": sensor.entity_id, "kind": sensor.kind });
+
+        if let Err(err) = context
+            .home_assistant
+            .call_service("script", script, Some(&variables), None)
+            .await
+        {
+            warn!("Failed to call the hazard script `{}`: {}", script, err);
+        }
+    }
+}
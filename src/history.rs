@@ -0,0 +1,271 @@
+use std::path::PathBuf;
+#[cfg(feature = "storage")]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(feature = "storage")]
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+#[cfg(feature = "storage")]
+use log::warn;
+#[cfg(feature = "storage")]
+use rusqlite::{params, Connection};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Result;
+
+#[cfg(feature = "storage")]
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS executions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    source TEXT NOT NULL,
+    trigger TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    duration REAL NOT NULL,
+    conditions TEXT NOT NULL,
+    actions TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS executions_source ON executions (source, started_at);
+";
+
+/// The execution history configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryConfig {
+    /// The path to the SQLite database. To spare the SD card, it can be on a
+    /// tmpfs, at the cost of the history across reboots, or on an external
+    /// drive.
+    #[serde(default = "HistoryConfig::default_database_path")]
+    pub database_path: PathBuf,
+
+    /// For how many days the executions are kept.
+    #[serde(default = "HistoryConfig::default_retention_days")]
+    pub retention_days: u32,
+}
+
+impl HistoryConfig {
+    fn default_database_path() -> PathBuf {
+        "/var/lib/home-control/history.sqlite".into()
+    }
+
+    fn default_retention_days() -> u32 {
+        30
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionResult {
+    pub condition: Value,
+    pub matched: bool,
+
+    /// The state or the time the condition was evaluated against, if any.
+    pub actual: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionResult {
+    pub action: Value,
+
+    /// Why the action failed, if it did.
+    pub error: Option<String>,
+}
+
+/// An execution of a rule or a schedule, to be recorded.
+#[derive(Debug, Clone)]
+pub struct NewExecution {
+    /// The rule id, or `irrigation` and `wakeup` for the schedules.
+    pub source: String,
+
+    /// What triggered the execution, like a change of state.
+    pub trigger: String,
+    pub started_at: DateTime<Utc>,
+    pub duration: Duration,
+    pub conditions: Vec<ConditionResult>,
+    pub actions: Vec<ActionResult>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Execution {
+    pub id: i64,
+    pub source: String,
+    pub trigger: String,
+    pub started_at: DateTime<Utc>,
+
+    /// The duration in seconds.
+    pub duration: f64,
+    pub conditions: Vec<ConditionResult>,
+    pub actions: Vec<ActionResult>,
+
+    /// Whether all the actions succeeded.
+    pub success: bool,
+}
+
+/// The history of the executions of the rules and the schedules, stored
+/// locally in SQLite.
+#[cfg(feature = "storage")]
+#[derive(Clone)]
+pub struct History {
+    connection: Arc<Mutex<Connection>>,
+    retention_days: u32,
+}
+
+/// An execution history, which cannot exist without storage support.
+#[cfg(not(feature = "storage"))]
+#[derive(Clone)]
+pub enum History {}
+
+#[cfg(feature = "storage")]
+impl History {
+    pub fn new(config: &HistoryConfig) -> anyhow::Result<Self> {
+        if let Some(parent) = config.database_path.parent() {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!(
+                    "failed to create the history directory `{}`",
+                    parent.display()
+                )
+            })?;
+        }
+
+        let connection = Connection::open(&config.database_path).with_context(|| {
+            format!(
+                "failed to open the history database `{}`",
+                config.database_path.display()
+            )
+        })?;
+
+        connection
+            .execute_batch(SCHEMA)
+            .context("failed to initialize the history database")?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            retention_days: config.retention_days,
+        })
+    }
+
+    /// Run a function on the connection in a blocking task.
+    async fn with_connection<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> anyhow::Result<T> + Send + 'static,
+    {
+        let connection = Arc::clone(&self.connection);
+
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|_| anyhow::anyhow!("the history database lock is poisoned"))?;
+
+            f(&mut connection)
+        })
+        .await
+        .context("the history database task failed")?
+        .map_err(Into::into)
+    }
+
+    /// Record an execution, and forget the ones past the retention.
+    ///
+    /// Failing to record is only logged, as it must not fail the execution.
+    pub async fn record(&self, execution: NewExecution) {
+        let source = execution.source.clone();
+        let expired_before = Utc::now() - chrono::Duration::days(i64::from(self.retention_days));
+
+        let result = self
+            .with_connection(move |connection| {
+                let transaction = connection.transaction()?;
+
+                transaction.execute(
+                    "INSERT INTO executions
+                         (source, trigger, started_at, duration, conditions, actions)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        execution.source,
+                        execution.trigger,
+                        execution.started_at,
+                        execution.duration.as_secs_f64(),
+                        serde_json::to_string(&execution.conditions)?,
+                        serde_json::to_string(&execution.actions)?,
+                    ],
+                )?;
+                transaction.execute(
+                    "DELETE FROM executions WHERE started_at < ?1",
+                    params![expired_before],
+                )?;
+                transaction.commit()?;
+
+                Ok(())
+            })
+            .await;
+
+        if let Err(err) = result {
+            warn!("Failed to record an execution of `{}`: {}", source, err);
+        }
+    }
+
+    /// List the latest executions of a rule or a schedule, the latest first.
+    pub async fn list(&self, source: String, limit: usize) -> Result<Vec<Execution>> {
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT id, source, trigger, started_at, duration, conditions, actions
+                 FROM executions
+                 WHERE source = ?1
+                 ORDER BY started_at DESC, id DESC
+                 LIMIT ?2",
+            )?;
+
+            let rows = statement
+                .query_map(params![source, limit as i64], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, DateTime<Utc>>(3)?,
+                        row.get::<_, f64>(4)?,
+                        row.get::<_, String>(5)?,
+                        row.get::<_, String>(6)?,
+                    ))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            rows.into_iter()
+                .map(
+                    |(id, source, trigger, started_at, duration, conditions, actions)| {
+                        let actions: Vec<ActionResult> = serde_json::from_str(&actions)?;
+
+                        Ok(Execution {
+                            id,
+                            source,
+                            trigger,
+                            started_at,
+                            duration,
+                            conditions: serde_json::from_str(&conditions)?,
+                            success: actions.iter().all(|action| action.error.is_none()),
+                            actions,
+                        })
+                    },
+                )
+                .collect()
+        })
+        .await
+    }
+}
+
+#[cfg(not(feature = "storage"))]
+impl History {
+    pub fn new(_config: &HistoryConfig) -> anyhow::Result<Self> {
+        anyhow::bail!("the history is configured but storage support was not compiled in")
+    }
+
+    pub async fn record(&self, _execution: NewExecution) {
+        match *self {}
+    }
+
+    pub async fn list(&self, _source: String, _limit: usize) -> Result<Vec<Execution>> {
+        match *self {}
+    }
+}
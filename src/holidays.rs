@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Local, NaiveDate, Weekday};
+use serde::Deserialize;
+
+use crate::{
+    context::AppContext,
+    home_assistant::{State, Status},
+};
+
+/// The days off, on which the schedules configured so are skipped.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HolidaysConfig {
+    /// The public holidays and days off, like `2024-12-25`.
+    #[serde(default)]
+    pub dates: Vec<NaiveDate>,
+
+    /// The Home-Assistant workday sensor, like `binary_sensor.workday`,
+    /// which is `off` on the holidays it knows of.
+    #[serde(default)]
+    pub workday_entity: Option<String>,
+}
+
+impl HolidaysConfig {
+    /// Check whether a day is a holiday.
+    ///
+    /// The workday sensor only tells about the current day, and is `off` on
+    /// the weekends too: it only makes a holiday of the days it would
+    /// otherwise count as workdays.
+    pub fn is_holiday(
+        &self,
+        date: NaiveDate,
+        today: NaiveDate,
+        entities: &HashMap<String, State>,
+    ) -> bool {
+        if self.dates.contains(&date) {
+            return true;
+        }
+
+        if date != today {
+            return false;
+        }
+
+        let workday = match self
+            .workday_entity
+            .as_ref()
+            .and_then(|entity_id| entities.get(entity_id))
+        {
+            Some(workday) => workday,
+            None => return false,
+        };
+        let workdays = workday
+            .attributes
+            .get::<Vec<String>>("workdays")
+            .map(|workdays| {
+                workdays
+                    .iter()
+                    .filter_map(|workday| workday.parse::<Weekday>().ok())
+                    .collect()
+            })
+            .unwrap_or_else(|| {
+                vec![
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                ]
+            });
+
+        workday.state == "off" && workdays.contains(&date.weekday())
+    }
+}
+
+/// Check whether a day is a holiday, as configured in the context.
+pub async fn is_holiday(context: &AppContext, date: NaiveDate) -> bool {
+    let holidays = match &context.config.holidays {
+        Some(holidays) => holidays,
+        None => return false,
+    };
+    let entities = match context.home_assistant.status().await {
+        Status::Connected { entities } => entities,
+        Status::Disconnected => Default::default(),
+    };
+
+    holidays.is_holiday(date, Local::now().date_naive(), &entities)
+}
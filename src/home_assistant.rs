@@ -1,19 +1,40 @@
-use std::{collections::HashMap, fmt::Display, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use anyhow::Context as _;
 use chrono::{DateTime, Utc};
 use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use ha_ws_client::{parse_messages, Features, Message, Pending};
 use log::{debug, error, info, warn};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::sync::RwLock;
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::{broadcast, watch, RwLock};
 use tokio_tungstenite::{
     connect_async,
     tungstenite::{Error as WsError, Message as WsMessage},
 };
 use url::Url;
+use warp::hyper::body::Bytes;
+
+use crate::{
+    chaos,
+    config::HOME_ASSISTANT_TOKEN_CREDENTIAL,
+    latency::{self, LatencySource},
+    request_id, secrets, tasks, usage, Result,
+};
+
+pub use self::traffic::{Traffic, TrafficDirection, TrafficMessage};
+pub use ha_ws_client::{
+    Attributes, ComponentLoadedData, Context, Error, Event, State, StateChangedData,
+};
 
-use crate::Result;
+mod traffic;
 
 trait WebSocket<Item = WsMessage, Error = WsError>:
     Sink<Item, Error = Error> + Stream<Item = Result<Item, Error>> + Unpin
@@ -25,32 +46,291 @@ impl<T> WebSocket for T where
 {
 }
 
+/// How long calls wait for Home-Assistant to finish starting.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What to do when Home-Assistant rejects the token, like once it expired,
+/// rather than trying it again right away.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthFailurePolicy {
+    #[serde(default)]
+    pub action: AuthFailureAction,
+
+    /// The file to reload the token from, in plain text. The `ha_token`
+    /// systemd credential if unspecified.
+    #[serde(default)]
+    pub token_file: Option<PathBuf>,
+
+    /// The delay before the first retry, doubled after every failure.
+    #[serde(default = "AuthFailurePolicy::default_initial_delay")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub initial_delay: Duration,
+
+    #[serde(default = "AuthFailurePolicy::default_max_delay")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub max_delay: Duration,
+}
+
+impl Default for AuthFailurePolicy {
+    fn default() -> Self {
+        Self {
+            action: AuthFailureAction::default(),
+            token_file: None,
+            initial_delay: Self::default_initial_delay(),
+            max_delay: Self::default_max_delay(),
+        }
+    }
+}
+
+impl AuthFailurePolicy {
+    fn default_initial_delay() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn default_max_delay() -> Duration {
+        Duration::from_secs(60 * 60)
+    }
+
+    fn next_delay(&self, delay: Duration) -> Duration {
+        (delay * 2).min(self.max_delay)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthFailureAction {
+    /// Stay disconnected until restarted.
+    Stop,
+
+    /// Retry the same token, with an exponential backoff.
+    #[default]
+    Retry,
+
+    /// Wait for the token to change, checking it with an exponential
+    /// backoff.
+    Reload,
+}
+
 type Sender = tokio::sync::oneshot::Sender<Result<serde_json::Value>>;
 type MessageAndSender = (Message, Sender);
 
+/// The connection status, with the entity states when connected.
+///
+/// The states are shared, so that getting the status is cheap even with
+/// thousands of entities: they are only copied when they change while still
+/// being read.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "status", rename_all = "snake_case")]
 pub enum Status {
-    Connected { entities: HashMap<String, State> },
+    Connected {
+        entities: Arc<HashMap<String, State>>,
+    },
     Disconnected,
 }
 
+/// A change received from Home-Assistant, as fanned out to the subscribers.
+#[derive(Debug, Clone)]
+pub enum Update {
+    /// The connection was established, and the states fetched.
+    Connected,
+    Disconnected,
+
+    /// An event, once applied to the states.
+    Event(Arc<Event>),
+}
+
 pub struct Client {
-    access_token: String,
+    access_token: Arc<Mutex<String>>,
+    auth_failure_policy: AuthFailurePolicy,
     ws_url: Url,
     events_subscription: Vec<Option<String>>,
+
+    /// The only entities to keep track of, if not all of them.
+    entity_allowlist: Option<BTreeSet<String>>,
     tx: tokio::sync::mpsc::Sender<MessageAndSender>,
     rx: tokio::sync::mpsc::Receiver<MessageAndSender>,
     status: Arc<RwLock<Status>>,
+    integrations: Arc<RwLock<Integrations>>,
+    info: Arc<RwLock<Info>>,
+    ready_tx: watch::Sender<bool>,
+    ready_rx: watch::Receiver<bool>,
+    states_tx: watch::Sender<u64>,
+    states_rx: watch::Receiver<u64>,
+    updates: broadcast::Sender<Update>,
+    rest_url: Url,
+    http_client: reqwest::Client,
+    call_stats: Arc<Mutex<CallStats>>,
+    disconnected_since: Arc<Mutex<Option<Instant>>>,
+    traffic: Traffic,
 }
 
+#[derive(Clone)]
 pub struct Controller {
     tx: tokio::sync::mpsc::Sender<MessageAndSender>,
     status: Arc<RwLock<Status>>,
+    integrations: Arc<RwLock<Integrations>>,
+    info: Arc<RwLock<Info>>,
+    ready: watch::Receiver<bool>,
+    states: watch::Receiver<u64>,
+    updates: broadcast::Sender<Update>,
+    access_token: Arc<Mutex<String>>,
+    rest_url: Url,
+    http_client: reqwest::Client,
+    call_stats: Arc<Mutex<CallStats>>,
+    disconnected_since: Arc<Mutex<Option<Instant>>>,
+    traffic: Traffic,
+}
+
+/// The response of the Assist conversation agent.
+#[derive(Debug, Clone)]
+pub struct ConversationResponse {
+    /// The id to continue the conversation with.
+    pub conversation_id: Option<String>,
+
+    /// What the agent says back, if anything.
+    pub speech: Option<String>,
+}
+
+/// The loaded components and the integrations providing the entities.
+#[derive(Debug, Default)]
+struct Integrations {
+    components: HashSet<String>,
+    entity_platforms: HashMap<String, String>,
+}
+
+/// The availability of an integration providing some entities.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrationStatus {
+    pub integration: String,
+    pub loaded: bool,
+    pub entities: Vec<String>,
+}
+
+/// How long a call may wait for its result before a warning is logged.
+const STALE_CALL_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// The statistics of the calls made to Home-Assistant.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CallStats {
+    /// The calls waiting for their result.
+    pub in_flight: usize,
+    pub completed: u64,
+
+    /// The calls that waited longer than expected for their result.
+    pub stale: u64,
+}
+
+/// The memory held by the cached entity states, in bytes.
+///
+/// This only counts the content of the states, not the overhead of the
+/// allocator.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityCacheMemory {
+    pub entities: usize,
+    pub entity_ids: usize,
+    pub states: usize,
+    pub attributes: usize,
+    pub contexts: usize,
+
+    /// The fixed size of the states and of the cache slots.
+    pub overhead: usize,
+}
+
+/// A call waiting for its result.
+struct InFlightCall {
+    description: String,
+    sender: Sender,
+    sent_at: Instant,
+    warned: bool,
+}
+
+/// Tracks the calls of a connection waiting for their result, with their
+/// statistics.
+struct InFlight {
+    calls: Pending<InFlightCall>,
+    stats: Arc<Mutex<CallStats>>,
+}
+
+impl InFlight {
+    fn new(stats: Arc<Mutex<CallStats>>) -> Self {
+        stats.lock().unwrap().in_flight = 0;
+
+        Self {
+            calls: Pending::new(),
+            stats,
+        }
+    }
+
+    fn allocate(&mut self) -> u64 {
+        self.calls.allocate()
+    }
+
+    fn insert(&mut self, id: u64, description: String, sender: Sender) {
+        self.calls.insert(
+            id,
+            InFlightCall {
+                description,
+                sender,
+                sent_at: Instant::now(),
+                warned: false,
+            },
+        );
+        self.stats.lock().unwrap().in_flight = self.calls.len();
+    }
+
+    fn complete(&mut self, id: u64) -> Option<InFlightCall> {
+        let call = self.calls.complete(id)?;
+        let mut stats = self.stats.lock().unwrap();
+
+        stats.in_flight = self.calls.len();
+        stats.completed += 1;
+
+        // The type of the call, without its details like the service.
+        latency::record(
+            LatencySource::HomeAssistant,
+            call.description.split(' ').next().unwrap_or_default(),
+            call.sent_at.elapsed(),
+        );
+
+        Some(call)
+    }
+
+    /// Warn about the calls waiting for too long, once per call.
+    fn warn_stale(&mut self) {
+        for (id, call) in self.calls.iter_mut() {
+            if !call.warned && call.sent_at.elapsed() > STALE_CALL_THRESHOLD {
+                call.warned = true;
+                self.stats.lock().unwrap().stale += 1;
+
+                warn!(
+                    "Call #{} ({}) has been waiting for its result for {:.0}s.",
+                    id,
+                    call.description,
+                    call.sent_at.elapsed().as_secs_f64()
+                );
+            }
+        }
+    }
 }
 
 impl Client {
-    pub async fn new(endpoint: &str, access_token: String) -> Result<Self> {
+    /// Create a client, which keeps track of all the entities unless an
+    /// allowlist is specified, in which case only the changes of those are
+    /// subscribed to.
+    ///
+    /// The messages exchanged with Home-Assistant are only recorded if
+    /// `record_traffic` is set, as in debug mode.
+    pub async fn new(
+        endpoint: &str,
+        access_token: String,
+        auth_failure_policy: AuthFailurePolicy,
+        entity_allowlist: Option<BTreeSet<String>>,
+        record_traffic: bool,
+    ) -> Result<Self> {
         info!("Using Home-Assistant instance at: {}", endpoint);
 
         let ws_url = Url::parse(&format!("wss://{}/api/websocket", endpoint))
@@ -58,17 +338,45 @@ impl Client {
 
         info!("Will establish Home-Assistant web-socket at: {}", ws_url);
 
+        let rest_url = Url::parse(&format!("https://{}/api/", endpoint))
+            .context("failed to parse home-assistant endpoint")?;
+
         let (tx, rx) = tokio::sync::mpsc::channel(1);
 
-        let events_subscription = vec![Some("state_changed".to_string())];
+        let events_subscription = [
+            "state_changed",
+            "homeassistant_started",
+            "core_config_updated",
+            "component_loaded",
+        ]
+        .into_iter()
+        .filter(|event_type| entity_allowlist.is_none() || *event_type != "state_changed")
+        .map(|event_type| Some(event_type.to_string()))
+        .collect();
+        let (ready_tx, ready_rx) = watch::channel(false);
+        let (states_tx, states_rx) = watch::channel(0);
 
         Ok(Self {
-            access_token,
+            access_token: Arc::new(Mutex::new(access_token)),
+            auth_failure_policy,
             ws_url,
             events_subscription,
+            entity_allowlist,
             tx,
             rx,
             status: Arc::new(RwLock::new(Status::Disconnected)),
+            integrations: Default::default(),
+            info: Default::default(),
+            ready_tx,
+            ready_rx,
+            states_tx,
+            states_rx,
+            updates: broadcast::channel(64).0,
+            rest_url,
+            http_client: reqwest::Client::new(),
+            call_stats: Default::default(),
+            disconnected_since: Arc::new(Mutex::new(Some(Instant::now()))),
+            traffic: Traffic::new(record_traffic),
         })
     }
 
@@ -97,6 +405,32 @@ impl Client {
         Ok(())
     }
 
+    /// Subscribe to the state changes of some entities only, instead of all
+    /// the `state_changed` events.
+    async fn subscribe_to_entities(
+        tx: &mut tokio::sync::mpsc::Sender<MessageAndSender>,
+        entity_ids: Vec<String>,
+    ) -> Result<()> {
+        info!(
+            "Subscribing to the states of {} Home-Assistant entities...",
+            entity_ids.len()
+        );
+
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+
+        tx.send((Message::SubscribeEntities { id: 0, entity_ids }, sender))
+            .await
+            .context("failed to send the subscribe entities message")?;
+
+        let result = receiver
+            .await
+            .context("failed to receive the subscribe entities response")??;
+
+        debug!("Subscribe entities result: {:?}", result);
+
+        Ok(())
+    }
+
     async fn _subscribe_to_trigger(
         tx: &mut tokio::sync::mpsc::Sender<MessageAndSender>,
         trigger: serde_json::Value,
@@ -116,6 +450,42 @@ impl Client {
         Ok(())
     }
 
+    async fn get_config(tx: &mut tokio::sync::mpsc::Sender<MessageAndSender>) -> Result<Config> {
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+
+        tx.send((Message::GetConfig { id: 0 }, sender))
+            .await
+            .context("failed to send the `get config` message")?;
+
+        let result = receiver
+            .await
+            .context("failed to receive the `get config` response")??;
+
+        debug!("Get config result: {:?}", result);
+
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Get the integration providing each entity of the entity registry.
+    async fn get_entity_platforms(
+        tx: &mut tokio::sync::mpsc::Sender<MessageAndSender>,
+    ) -> Result<HashMap<String, String>> {
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+
+        tx.send((Message::EntityRegistryList { id: 0 }, sender))
+            .await
+            .context("failed to send the `entity registry list` message")?;
+
+        let result = receiver
+            .await
+            .context("failed to receive the `entity registry list` response")??;
+
+        Ok(serde_json::from_value::<Vec<EntityRegistryEntry>>(result)?
+            .into_iter()
+            .map(|entry| (entry.entity_id, entry.platform))
+            .collect())
+    }
+
     async fn get_states(
         tx: &mut tokio::sync::mpsc::Sender<MessageAndSender>,
     ) -> Result<HashMap<String, State>> {
@@ -131,9 +501,27 @@ impl Client {
 
         debug!("Get states result: {:?}", result);
 
-        Ok(serde_json::from_value::<Vec<State>>(result)?
+        // A single malformed entity must not prevent the others from being
+        // displayed.
+        Ok(serde_json::from_value::<Vec<serde_json::Value>>(result)?
             .into_iter()
-            .map(|s| (s.entity_id.to_string(), s))
+            .filter_map(
+                |state| match serde_json::from_value::<State>(state.clone()) {
+                    Ok(state) => Some((state.entity_id.to_string(), state)),
+                    Err(err) => {
+                        warn!(
+                            "Ignoring malformed state of `{}`: {}",
+                            state
+                                .get("entity_id")
+                                .and_then(serde_json::Value::as_str)
+                                .unwrap_or("?"),
+                            err
+                        );
+
+                        None
+                    }
+                },
+            )
             .collect())
     }
 
@@ -142,12 +530,24 @@ impl Client {
         Controller {
             tx: self.tx.clone(),
             status: Arc::clone(&self.status),
+            integrations: Arc::clone(&self.integrations),
+            info: Arc::clone(&self.info),
+            ready: self.ready_rx.clone(),
+            states: self.states_rx.clone(),
+            updates: self.updates.clone(),
+            access_token: Arc::clone(&self.access_token),
+            rest_url: self.rest_url.clone(),
+            http_client: self.http_client.clone(),
+            call_stats: Arc::clone(&self.call_stats),
+            disconnected_since: Arc::clone(&self.disconnected_since),
+            traffic: self.traffic.clone(),
         }
     }
 
     /// Run the client and consumes it.
     pub async fn run(mut self) -> Result<()> {
         let retry_delay = Duration::from_secs(5);
+        let mut auth_retry_delay = self.auth_failure_policy.initial_delay;
 
         loop {
             match connect_async(&self.ws_url).await {
@@ -160,6 +560,12 @@ impl Client {
                 Ok((ws, _)) => {
                     if let Err(err) = self.run_with_ws(ws).await {
                         *self.status.write().await = Status::Disconnected;
+                        *self.disconnected_since.lock().unwrap() = Some(Instant::now());
+                        self.ready_tx.send_replace(false);
+                        notify_states(&self.states_tx);
+
+                        // Nobody may be listening.
+                        let _ = self.updates.send(Update::Disconnected);
 
                         warn!(
                             "Home-Assistant web-socket connection was interuppted: {}",
@@ -168,48 +574,296 @@ impl Client {
                     }
                 }
             }
+
+            auth_retry_delay = match self.info.read().await.auth_failure {
+                Some(_) => self.wait_after_auth_failure(auth_retry_delay).await,
+                None => self.auth_failure_policy.initial_delay,
+            };
+        }
+    }
+
+    /// Wait before authenticating again once Home-Assistant rejected the
+    /// token, as the policy says, so as not to flood its logs.
+    ///
+    /// Returns the delay to wait after the next failure.
+    async fn wait_after_auth_failure(&self, mut delay: Duration) -> Duration {
+        let policy = &self.auth_failure_policy;
+
+        match policy.action {
+            AuthFailureAction::Stop => {
+                error!("Home-Assistant rejected the token: not connecting again until restarted.");
+
+                tasks::idle().await
+            }
+            AuthFailureAction::Retry => {
+                error!(
+                    "Home-Assistant rejected the token: retrying in {:.0}s...",
+                    delay.as_secs_f64()
+                );
+
+                tokio::time::sleep(delay).await;
+
+                policy.next_delay(delay)
+            }
+            AuthFailureAction::Reload => {
+                error!("Home-Assistant rejected the token: waiting for a new one...");
+
+                loop {
+                    tokio::time::sleep(delay).await;
+                    tasks::heartbeat();
+
+                    match self.reload_token() {
+                        Ok(true) => {
+                            info!("Reloaded the Home-Assistant token.");
+
+                            return policy.initial_delay;
+                        }
+                        Ok(false) => debug!("The Home-Assistant token did not change."),
+                        Err(err) => warn!("Failed to reload the Home-Assistant token: {:#}", err),
+                    }
+
+                    delay = policy.next_delay(delay);
+                }
+            }
+        }
+    }
+
+    /// Reload the token, and tell whether it changed.
+    fn reload_token(&self) -> anyhow::Result<bool> {
+        let token = match &self.auth_failure_policy.token_file {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read `{}`", path.display()))?
+                .trim()
+                .to_string(),
+            None => secrets::credential(HOME_ASSISTANT_TOKEN_CREDENTIAL)?
+                .context("no `ha_token` credential")?,
+        };
+
+        anyhow::ensure!(
+            !secrets::is_encrypted(&token),
+            "the reloaded token must be in plain text"
+        );
+
+        let mut access_token = self.access_token.lock().unwrap();
+
+        if token.is_empty() || *access_token == token {
+            return Ok(false);
+        }
+
+        *access_token = token;
+
+        Ok(true)
+    }
+
+    /// Run the client against simulated states instead of Home-Assistant,
+    /// and consumes it.
+    ///
+    /// Service calls are logged and succeed without effect, while the other
+    /// calls fail.
+    pub async fn run_simulated(
+        mut self,
+        mut states: tokio::sync::mpsc::Receiver<State>,
+    ) -> Result<()> {
+        info!("Simulating Home-Assistant.");
+
+        *self.status.write().await = Status::Connected {
+            entities: Default::default(),
+        };
+        *self.disconnected_since.lock().unwrap() = None;
+        self.info.write().await.version = Some("simulated".to_string());
+        self.ready_tx.send_replace(true);
+        notify_states(&self.states_tx);
+
+        let _ = self.updates.send(Update::Connected);
+        let mut replaying = true;
+
+        loop {
+            tokio::select! {
+                state = states.recv(), if replaying => match state {
+                    Some(state) => {
+                        debug!("Simulating state of `{}`: {}", state.entity_id, state.state);
+
+                        let old_state = match &mut *self.status.write().await {
+                            Status::Connected { entities } => {
+                                Arc::make_mut(entities).insert(state.entity_id.clone(), state.clone())
+                            }
+                            Status::Disconnected => None,
+                        };
+
+                        notify_states(&self.states_tx);
+
+                        // The subscribers see the simulated states as the
+                        // events of a real server.
+                        let _ = self.updates.send(Update::Event(Arc::new(Event::StateChanged {
+                            context: Context {
+                                id: String::new(),
+                                parent_id: None,
+                                user_id: None,
+                            },
+                            data: StateChangedData {
+                                entity_id: state.entity_id.clone(),
+                                old_state,
+                                new_state: Some(state.clone()),
+                            },
+                            origin: "LOCAL".to_string(),
+                            time_fired: state.last_changed,
+                        })));
+                    }
+                    None => replaying = false,
+                },
+                pair = self.rx.recv() => match pair {
+                    Some((message, sender)) => {
+                        let result = match message {
+                            Message::CallService { .. } => {
+                                info!("Simulating call: {}", message.describe());
+
+                                if let Ok(text) = serde_json::to_string(&message) {
+                                    self.traffic.record(TrafficDirection::Sent, &text);
+                                }
+
+                                Ok(serde_json::Value::Null)
+                            }
+                            message => Err(anyhow::anyhow!(
+                                "`{}` is not available in simulation",
+                                message.describe()
+                            )
+                            .into()),
+                        };
+
+                        if sender.send(result).is_err() {
+                            warn!("Failed to send simulated result to sender");
+                        }
+                    }
+                    None => return Err(anyhow::anyhow!("channel closed").into()),
+                },
+            }
+
+            tasks::heartbeat();
         }
     }
 
     async fn run_with_ws(&mut self, mut ws: impl WebSocket) -> Result<()> {
         let mut authenticated = false;
         let mut init_done = false;
-        let mut id: u64 = 1;
-        let mut senders_by_id = HashMap::new();
+        let mut features_id = None;
+        let mut pending = VecDeque::new();
+        let mut in_flight = InFlight::new(Arc::clone(&self.call_stats));
+        let traffic = self.traffic.clone();
         let tx = &mut self.tx;
         let rx = &mut self.rx;
 
+        // Also used to refresh the states once Home-Assistant restarted, in
+        // which case there is nothing to subscribe to.
         async fn init_fn(
-            tx: &mut tokio::sync::mpsc::Sender<MessageAndSender>,
+            mut tx: tokio::sync::mpsc::Sender<MessageAndSender>,
             event_types: Vec<Option<String>>,
-        ) -> Result<HashMap<String, State>> {
-            Client::subscribe_to_events(tx, event_types).await?;
+            entity_allowlist: Option<BTreeSet<String>>,
+            subscribe: bool,
+        ) -> Result<(
+            Option<HashMap<String, State>>,
+            Config,
+            HashMap<String, String>,
+        )> {
+            if subscribe {
+                Client::subscribe_to_events(&mut tx, event_types).await?;
+
+                if let Some(entity_ids) = &entity_allowlist {
+                    Client::subscribe_to_entities(&mut tx, entity_ids.iter().cloned().collect())
+                        .await?;
+                }
+            }
+
+            let config = Client::get_config(&mut tx).await?;
 
-            Client::get_states(tx).await
+            // Listing the entity registry requires an administrator token.
+            let entity_platforms = match Client::get_entity_platforms(&mut tx).await {
+                Ok(entity_platforms) => entity_platforms,
+                Err(err) => {
+                    warn!("Failed to list the Home-Assistant entity registry: {}", err);
+
+                    HashMap::new()
+                }
+            };
+
+            // The entities subscription sends the full states of the
+            // entities first, then only their differences.
+            let states = match entity_allowlist {
+                Some(_) => None,
+                None => Some(Client::get_states(&mut tx).await?),
+            };
+
+            Ok((states, config, entity_platforms))
         }
 
-        let init = init_fn(tx, self.events_subscription.clone());
+        let init = init_fn(
+            tx.clone(),
+            self.events_subscription.clone(),
+            self.entity_allowlist.clone(),
+            true,
+        );
 
         tokio::pin!(init);
 
+        // The states the entities subscription sends before the end of the
+        // initialization.
+        let mut early_entities = HashMap::new();
         let mut last_ping = tokio::time::Instant::now();
-        let mut last_ping_id = id;
+        let mut last_ping_id = None;
         let ping_interval = Duration::from_secs(10);
 
         loop {
             tokio::select! {
-                states = &mut init, if authenticated && !init_done => {
+                result = &mut init, if authenticated && !init_done => {
+                    let (states, config, entity_platforms) = result?;
+                    let running = config.is_running();
+
                     init_done = true;
-                    *self.status.write().await = Status::Connected{entities: states?};
+                    {
+                        let mut status = self.status.write().await;
+                        let entities = match (states, &*status) {
+                            (Some(states), _) => Arc::new(states),
+                            (None, Status::Connected { entities }) => Arc::clone(entities),
+                            (None, Status::Disconnected) => {
+                                Arc::new(std::mem::take(&mut early_entities))
+                            }
+                        };
+
+                        *status = Status::Connected { entities };
+                    }
+                    *self.disconnected_since.lock().unwrap() = None;
+                    notify_states(&self.states_tx);
+                    let _ = self.updates.send(Update::Connected);
+                    {
+                        let mut info = self.info.write().await;
+
+                        info.version = config.version.clone().or_else(|| info.version.take());
+                        info.location_name = config.location_name.clone();
+                        info.unit_system = config.unit_system.clone();
+                        info.time_zone = config.time_zone.clone();
+                    }
+
+                    *self.integrations.write().await = Integrations {
+                        components: config.components.into_iter().collect(),
+                        entity_platforms,
+                    };
+
+                    if running {
+                        info!("Home-Assistant is running.");
+                    } else {
+                        info!("Home-Assistant is starting: holding calls until it is ready...");
+                    }
+
+                    self.ready_tx.send_replace(running);
                 }
                 pair = rx.recv(), if authenticated =>
                     if let Some((mut message, sender)) = pair {
+                        let id = in_flight.allocate();
+
                         if message.inject_id(id) {
-                            senders_by_id.insert(id, sender);
-                            id += 1;
+                            in_flight.insert(id, message.describe(), sender);
 
                             debug!("Sending message: {:?}", message);
-                            Self::send_message(&mut ws, message).await?;
+                            Self::send_message(&mut ws, &traffic, message).await?;
                         } else {
                             warn!("Failed to inject message ID: not sending message: {:?}", message);
                         }
@@ -218,30 +872,64 @@ impl Client {
                     },
                 _ = tokio::time::sleep_until(last_ping + ping_interval), if authenticated => {
                     last_ping = tokio::time::Instant::now();
+                    in_flight.warn_stale();
 
-                    id += 1;
-                    last_ping_id = id;
-                    Self::send_message(&mut ws, Message::Ping { id }).await?;
+                    let id = in_flight.allocate();
+
+                    last_ping_id = Some(id);
+                    Self::send_message(&mut ws, &traffic, Message::Ping { id }).await?;
+                },
+                _ = chaos::home_assistant_dropped() => {
+                    return Err(anyhow::anyhow!("the web-socket was dropped on purpose").into());
                 },
-                message = Self::read_message(&mut ws) => match message? {
+                message = Self::read_message(&mut ws, &traffic, &mut pending) => match message? {
                     Message::AuthRequired { ha_version } => {
                         info!(
                             "Authenticating with Home-Assistant version {}...",
                             ha_version
                         );
 
-                        Self::send_message(&mut ws, Message::Auth {
-                            access_token: self.access_token.clone(),
-                        })
+                        let access_token = self.access_token.lock().unwrap().clone();
+
+                        Self::send_message(&mut ws, &traffic, Message::Auth { access_token })
                         .await?;
                     }
                     Message::AuthOk { ha_version } => {
                         authenticated = true;
                         info!("Authenticated with Home-Assistant version {}", ha_version);
+
+                        {
+                            let mut info = self.info.write().await;
+
+                            info.version = Some(ha_version);
+                            info.auth_failure = None;
+                        }
+
+                        // Must be the first message after the authentication.
+                        let id = in_flight.allocate();
+
+                        features_id = Some(id);
+                        Self::send_message(&mut ws, &traffic, Message::SupportedFeatures {
+                            id,
+                            features: Features { coalesce_messages: 1 },
+                        })
+                        .await?;
                     }
                     Message::AuthInvalid { message } => {
+                        self.info.write().await.auth_failure = Some(message.clone());
+
                         return Err(anyhow::anyhow!("authentication failed: {}", message)).map_err(Into::into);
                     }
+                    Message::Result { id, success, error, .. } if Some(id) == features_id => {
+                        if success {
+                            debug!("Home-Assistant accepted the supported features.");
+                        } else {
+                            debug!(
+                                "Home-Assistant rejected the supported features: {}",
+                                error.unwrap_or_default()
+                            );
+                        }
+                    }
                     Message::Result { id, success, result, error } => {
                         let result = if success {
                             Ok(result)
@@ -249,8 +937,15 @@ impl Client {
                             Err(error.unwrap_or_default().into())
                         };
 
-                        if let Some(sender) = senders_by_id.remove(&id) {
-                            if sender.send(result).is_err() {
+                        if let Some(call) = in_flight.complete(id) {
+                            debug!(
+                                "Call #{} ({}) completed in {}ms.",
+                                id,
+                                call.description,
+                                call.sent_at.elapsed().as_millis()
+                            );
+
+                            if call.sender.send(result).is_err() {
                                 warn!("Failed to send result to sender for call #{}", id);
                             }
                         } else {
@@ -258,29 +953,65 @@ impl Client {
                         }
                     }
                     Message::Pong { id } => {
-                        if id == last_ping_id  {
+                        if Some(id) == last_ping_id  {
                             let duration = last_ping.elapsed();
 
                             debug!("Ping duration: {}ms", duration.as_millis());
+                            tasks::heartbeat();
                         } else {
-                            warn!("Discarding unexpected pong with id `{}` when `{}` was expected", id, last_ping_id);
+                            warn!("Discarding unexpected pong with id `{}` when `{:?}` was expected", id, last_ping_id);
                         }
                     }
                     Message::Event { id, event } => {
                         debug!("Received event {}: {}", id, event);
 
-                        if let Event::StateChanged {
+                        match event.as_ref() {
+                            Event::StateChanged {
                                 data: StateChangedData {
                                     entity_id,
                                     new_state: Some(new_state),
                                     ..
                                 },
-                            ..
-                        } = event.as_ref() {
-                            if let Status::Connected{entities} = &mut *self.status.write().await {
-                                entities.insert(entity_id.clone(), new_state.clone());
+                                ..
+                            } => {
+                                if let Status::Connected{entities} = &mut *self.status.write().await {
+                                    Arc::make_mut(entities).insert(entity_id.clone(), new_state.clone());
+                                }
+
+                                notify_states(&self.states_tx);
+                            }
+                            Event::StateChanged { .. } => {}
+                            Event::Entities(changes) => {
+                                match &mut *self.status.write().await {
+                                    Status::Connected{entities} => changes.apply(Arc::make_mut(entities)),
+                                    Status::Disconnected => changes.apply(&mut early_entities),
+                                }
+
+                                notify_states(&self.states_tx);
+                            }
+                            Event::ComponentLoaded { data: ComponentLoadedData { component }, .. } => {
+                                info!("Home-Assistant loaded component `{}`.", component);
+
+                                self.integrations.write().await.components.insert(component.clone());
+                            }
+                            Event::HomeassistantStarted { .. } | Event::CoreConfigUpdated { .. } => {
+                                info!("Home-Assistant reported `{}`: refreshing states...", event);
+
+                                init_done = false;
+                                init.set(init_fn(
+                                    tx.clone(),
+                                    Vec::new(),
+                                    self.entity_allowlist.clone(),
+                                    false,
+                                ));
+                            }
+                            Event::Other { event_type, .. } => {
+                                debug!("Ignoring `{}` event.", event_type);
                             }
                         }
+
+                        // Nobody may be listening.
+                        let _ = self.updates.send(Update::Event(Arc::from(event)));
                     }
                     message => {
                         warn!(
@@ -293,17 +1024,25 @@ impl Client {
         }
     }
 
-    async fn read_message(mut ws: impl WebSocket) -> Result<Message> {
+    /// Read the next message, from the ones pending from a coalesced batch
+    /// first.
+    async fn read_message(
+        mut ws: impl WebSocket,
+        traffic: &Traffic,
+        pending: &mut VecDeque<Message>,
+    ) -> Result<Message> {
         loop {
+            if let Some(message) = pending.pop_front() {
+                return Ok(message);
+            }
+
             break match ws.next().await {
                 Some(Ok(message)) => match message {
-                    WsMessage::Text(text) => match serde_json::from_str::<Message>(&text) {
-                        Ok(message) => Ok(message),
-                        Err(err) => {
-                            warn!("Failed to parse message `{:?}`: {}", text, err);
-                            continue;
-                        }
-                    },
+                    WsMessage::Text(text) => {
+                        traffic.record(TrafficDirection::Received, &text);
+                        pending.extend(parse_messages(&text));
+                        continue;
+                    }
                     WsMessage::Ping(data) => {
                         ws.send(WsMessage::Pong(data))
                             .await
@@ -335,15 +1074,19 @@ impl Client {
         }
     }
 
-    async fn send_message(mut ws: impl WebSocket, message: Message) -> Result<()> {
-        ws.send(
-            serde_json::to_string(&message)
-                .context("failed to serialize the message")?
-                .into(),
-        )
-        .await
-        .context("failed to send the Web-Socket message")
-        .map_err(Into::into)
+    async fn send_message(
+        mut ws: impl WebSocket,
+        traffic: &Traffic,
+        message: Message,
+    ) -> Result<()> {
+        let text = serde_json::to_string(&message).context("failed to serialize the message")?;
+
+        traffic.record(TrafficDirection::Sent, &text);
+
+        ws.send(text.into())
+            .await
+            .context("failed to send the Web-Socket message")
+            .map_err(Into::into)
     }
 }
 
@@ -352,6 +1095,287 @@ impl Controller {
         (*self.status.read().await).clone()
     }
 
+    /// Get the information about the Home-Assistant instance, as reported
+    /// when connecting.
+    pub async fn info(&self) -> Info {
+        self.info.read().await.clone()
+    }
+
+    /// Get the memory held by the cached entity states.
+    pub async fn entity_cache_memory(&self) -> EntityCacheMemory {
+        let mut memory = EntityCacheMemory::default();
+
+        if let Status::Connected { entities } = &*self.status.read().await {
+            memory.entities = entities.len();
+            memory.overhead = entities.capacity() * std::mem::size_of::<(String, State)>();
+
+            for (entity_id, state) in entities.iter() {
+                memory.entity_ids += entity_id.capacity() + state.entity_id.capacity();
+                memory.states += state.state.capacity();
+                memory.attributes += state.attributes.size();
+                memory.contexts += state.context.id.capacity()
+                    + state.context.parent_id.as_ref().map_or(0, String::capacity)
+                    + state.context.user_id.as_ref().map_or(0, String::capacity);
+            }
+        }
+
+        memory
+    }
+
+    /// Get the recorder of the messages exchanged with Home-Assistant.
+    pub fn traffic(&self) -> &Traffic {
+        &self.traffic
+    }
+
+    /// Get the URL of the REST API of Home-Assistant.
+    pub fn rest_url(&self) -> &Url {
+        &self.rest_url
+    }
+
+    /// Get the statistics of the calls made to Home-Assistant.
+    pub fn call_stats(&self) -> CallStats {
+        self.call_stats.lock().unwrap().clone()
+    }
+
+    /// Watch the entity states: the receiver is notified whenever a state
+    /// changes, or the states are refreshed or lost.
+    pub fn watch_states(&self) -> watch::Receiver<u64> {
+        self.states.clone()
+    }
+
+    /// Subscribe to the changes received from Home-Assistant, the events
+    /// being received once applied to the states.
+    pub fn subscribe_updates(&self) -> broadcast::Receiver<Update> {
+        self.updates.subscribe()
+    }
+
+    /// Get the generation of the entity states, which is incremented
+    /// whenever they change.
+    pub fn states_generation(&self) -> u64 {
+        *self.states.borrow()
+    }
+
+    fn access_token(&self) -> String {
+        self.access_token.lock().unwrap().clone()
+    }
+
+    /// Get for how long the connection to Home-Assistant has been lost, if
+    /// it is.
+    pub fn disconnected_for(&self) -> Option<Duration> {
+        self.disconnected_since
+            .lock()
+            .unwrap()
+            .map(|since| since.elapsed())
+    }
+
+    /// Get the current state of an entity, if it is known.
+    pub async fn entity(&self, entity_id: &str) -> Option<State> {
+        match &*self.status.read().await {
+            Status::Connected { entities } => entities.get(entity_id).cloned(),
+            Status::Disconnected => None,
+        }
+    }
+
+    /// Get the availability of the integrations providing the specified
+    /// entities.
+    ///
+    /// Entities missing from the entity registry, like most entities defined
+    /// in YAML, are ignored.
+    pub async fn integrations<'a>(
+        &self,
+        entity_ids: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<IntegrationStatus> {
+        let integrations = self.integrations.read().await;
+        let mut entities_by_integration = BTreeMap::<&str, Vec<String>>::new();
+
+        for entity_id in entity_ids {
+            if let Some(platform) = integrations.entity_platforms.get(entity_id) {
+                entities_by_integration
+                    .entry(platform)
+                    .or_default()
+                    .push(entity_id.to_string());
+            }
+        }
+
+        entities_by_integration
+            .into_iter()
+            .map(|(integration, entities)| IntegrationStatus {
+                integration: integration.to_string(),
+                loaded: integrations.components.contains(integration),
+                entities,
+            })
+            .collect()
+    }
+
+    /// Suggest entities for the dashboard, optionally restricted to a domain
+    /// and an area.
+    ///
+    /// The area may be specified by id or by name. Disabled and hidden
+    /// entities are ignored.
+    pub async fn discover(
+        &self,
+        domain: Option<&str>,
+        area: Option<&str>,
+    ) -> Result<Vec<DiscoveredEntity>> {
+        let (entities, areas) = self.registries().await?;
+
+        let states = match &*self.status.read().await {
+            Status::Connected { entities } => Arc::clone(entities),
+            Status::Disconnected => {
+                return Err(anyhow::anyhow!("Home-Assistant is not connected").into())
+            }
+        };
+
+        let mut discovered: Vec<_> = states
+            .values()
+            .filter_map(|state| {
+                let entry = entities.get(&state.entity_id);
+
+                if matches!(entry, Some(entry) if entry.disabled_by.is_some() || entry.hidden_by.is_some())
+                {
+                    return None;
+                }
+
+                let area_id = areas.entities.get(&state.entity_id).cloned();
+                let area_name = area_id
+                    .as_ref()
+                    .and_then(|area_id| areas.names.get(area_id).cloned());
+                let entity_domain = state.entity_id.split('.').next()?.to_string();
+
+                if matches!(domain, Some(domain) if domain != entity_domain) {
+                    return None;
+                }
+
+                if let Some(area) = area {
+                    let matches = area_id.as_deref() == Some(area)
+                        || matches!(&area_name, Some(name) if name.eq_ignore_ascii_case(area));
+
+                    if !matches {
+                        return None;
+                    }
+                }
+
+                Some(DiscoveredEntity {
+                    friendly_name: state.attributes.get("friendly_name"),
+                    domain: entity_domain,
+                    state: state.state.clone(),
+                    area_id,
+                    area_name,
+                    integration: entry.map(|entry| entry.platform.clone()),
+                    entity_id: state.entity_id.clone(),
+                })
+            })
+            .collect();
+
+        discovered.sort_by(|a, b| a.entity_id.cmp(&b.entity_id));
+
+        Ok(discovered)
+    }
+
+    /// Get the areas of the entities.
+    pub async fn entity_areas(&self) -> Result<EntityAreas> {
+        Ok(self.registries().await?.1)
+    }
+
+    /// Get the entity registry, by entity, and the areas of the entities.
+    async fn registries(&self) -> Result<(HashMap<String, EntityRegistryEntry>, EntityAreas)> {
+        self.wait_ready().await?;
+
+        let entities: Vec<EntityRegistryEntry> = self
+            .request(
+                Message::EntityRegistryList { id: 0 },
+                "entity registry list",
+            )
+            .await?;
+        let devices: Vec<DeviceRegistryEntry> = self
+            .request(
+                Message::DeviceRegistryList { id: 0 },
+                "device registry list",
+            )
+            .await?;
+        let areas: Vec<AreaRegistryEntry> = self
+            .request(Message::AreaRegistryList { id: 0 }, "area registry list")
+            .await?;
+
+        let device_areas: HashMap<_, _> = devices
+            .into_iter()
+            .filter_map(|device| Some((device.id, device.area_id?)))
+            .collect();
+        let entities: HashMap<_, _> = entities
+            .into_iter()
+            .map(|entry| (entry.entity_id.clone(), entry))
+            .collect();
+        let areas = EntityAreas {
+            entities: entities
+                .values()
+                .filter_map(|entry| {
+                    let area_id = entry.area_id.clone().or_else(|| {
+                        entry
+                            .device_id
+                            .as_ref()
+                            .and_then(|device_id| device_areas.get(device_id).cloned())
+                    })?;
+
+                    Some((entry.entity_id.clone(), area_id))
+                })
+                .collect(),
+            names: areas
+                .into_iter()
+                .map(|area| (area.area_id, area.name))
+                .collect(),
+        };
+
+        Ok((entities, areas))
+    }
+
+    /// Send a message and parse its result.
+    async fn request<T: serde::de::DeserializeOwned>(
+        &self,
+        message: Message,
+        description: &str,
+    ) -> Result<T> {
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+
+        self.tx
+            .send((message, sender))
+            .await
+            .with_context(|| format!("failed to send the `{}` message", description))?;
+
+        let result = receiver
+            .await
+            .with_context(|| format!("failed to receive the `{}` response", description))??;
+
+        Ok(serde_json::from_value(result)
+            .with_context(|| format!("failed to parse the `{}` response", description))?)
+    }
+
+    /// Wait for Home-Assistant to be connected and done starting.
+    ///
+    /// Calls made while Home-Assistant is starting fail because the
+    /// integrations providing the services are not loaded yet.
+    pub async fn wait_ready(&self) -> Result<()> {
+        let mut ready = self.ready.clone();
+
+        if *ready.borrow_and_update() {
+            return Ok(());
+        }
+
+        debug!("Waiting for Home-Assistant to be ready...");
+
+        tokio::time::timeout(STARTUP_TIMEOUT, async {
+            while !*ready.borrow_and_update() {
+                ready.changed().await?;
+            }
+
+            Ok::<_, watch::error::RecvError>(())
+        })
+        .await
+        .context("timed out waiting for Home-Assistant to be ready")?
+        .context("the Home-Assistant client stopped")?;
+
+        Ok(())
+    }
+
     pub async fn call_service(
         &self,
         domain: &str,
@@ -359,6 +1383,8 @@ impl Controller {
         service_data: Option<&serde_json::Value>,
         target: Option<&serde_json::Value>,
     ) -> Result<()> {
+        self.wait_ready().await?;
+
         let (sender, receiver) = tokio::sync::oneshot::channel();
 
         self.tx
@@ -379,11 +1405,162 @@ impl Controller {
             .await
             .context("failed to receive the call service response")??;
 
+        chaos::delay_call().await;
+
+        if let Some(entity_id) = target.and_then(|target| target.get("entity_id")) {
+            match entity_id {
+                serde_json::Value::String(entity_id) => usage::record([entity_id.as_str()]),
+                serde_json::Value::Array(entity_ids) => {
+                    usage::record(entity_ids.iter().filter_map(serde_json::Value::as_str))
+                }
+                _ => {}
+            }
+        }
+
         debug!("Call service result: {:?}", result);
+        log_context(&format!("Called `{}.{}`", domain, service), &result);
 
         Ok(())
     }
 
+    pub async fn fire_event(&self, event_type: &str, event_data: &serde_json::Value) -> Result<()> {
+        self.wait_ready().await?;
+
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+
+        self.tx
+            .send((
+                Message::FireEvent {
+                    id: 0,
+                    event_type: event_type.to_string(),
+                    event_data: Some(event_data.clone()),
+                },
+                sender,
+            ))
+            .await
+            .context("failed to send the fire event message")?;
+
+        let result = receiver
+            .await
+            .context("failed to receive the fire event response")??;
+
+        debug!("Fire event result: {:?}", result);
+        log_context(&format!("Fired `{}`", event_type), &result);
+
+        Ok(())
+    }
+
+    /// Set the state of an entity through the REST API, creating the entity
+    /// if it does not exist.
+    ///
+    /// Such entities are not backed by an integration, and are lost when
+    /// Home-Assistant restarts until their state is set again.
+    pub async fn set_state(
+        &self,
+        entity_id: &str,
+        state: &str,
+        attributes: &serde_json::Value,
+    ) -> Result<()> {
+        let url = self
+            .rest_url
+            .join(&format!("states/{}", entity_id))
+            .context("invalid entity id")?;
+        let started = Instant::now();
+
+        self.http_client
+            .post(url)
+            .bearer_auth(self.access_token())
+            .json(&serde_json::json!({
+                "state": state,
+                "attributes": attributes,
+            }))
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("failed to set the state of `{}`", entity_id))?;
+
+        latency::record(LatencySource::HomeAssistant, "set_state", started.elapsed());
+
+        Ok(())
+    }
+
+    /// Process a sentence with the Assist conversation agent, through the REST
+    /// API.
+    ///
+    /// Passing the id of the previous response continues the conversation.
+    pub async fn conversation_process(
+        &self,
+        text: &str,
+        language: Option<&str>,
+        conversation_id: Option<&str>,
+    ) -> Result<ConversationResponse> {
+        let url = self
+            .rest_url
+            .join("conversation/process")
+            .context("invalid conversation URL")?;
+
+        let response: serde_json::Value = self
+            .http_client
+            .post(url)
+            .bearer_auth(self.access_token())
+            .json(&serde_json::json!({
+                "text": text,
+                "language": language,
+                "conversation_id": conversation_id,
+            }))
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("failed to process the conversation")?
+            .json()
+            .await
+            .context("failed to parse the conversation response")?;
+
+        Ok(ConversationResponse {
+            conversation_id: response["conversation_id"].as_str().map(str::to_string),
+            speech: response["response"]["speech"]["plain"]["speech"]
+                .as_str()
+                .map(str::to_string),
+        })
+    }
+
+    /// Download a picture referenced by an entity, like the `entity_picture`
+    /// of a media player.
+    ///
+    /// Relative URLs are served by Home-Assistant, and only those get the
+    /// access token.
+    ///
+    /// Returns the content type and the picture.
+    pub async fn fetch_picture(&self, url: &str) -> Result<(String, Bytes)> {
+        let url = self.rest_url.join(url).context("invalid picture URL")?;
+        let mut request = self.http_client.get(url.clone());
+
+        if url.origin() == self.rest_url.origin() {
+            request = request.bearer_auth(self.access_token());
+        }
+
+        let response = request
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("failed to fetch the picture")?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("image/jpeg")
+            .to_string();
+        let data = response
+            .bytes()
+            .await
+            .context("failed to read the picture")?;
+
+        Ok((content_type, data))
+    }
+
     pub async fn light_toggle(&self, entity_id: &str) -> Result<()> {
         self.call_service(
             "light",
@@ -394,6 +1571,140 @@ impl Controller {
         .await
     }
 
+    pub async fn light_turn_on_with(
+        &self,
+        entity_id: &str,
+        service_data: &serde_json::Value,
+    ) -> Result<()> {
+        self.call_service(
+            "light",
+            "turn_on",
+            Some(service_data),
+            Some(&json!({ "entity_id": entity_id })),
+        )
+        .await
+    }
+
+    pub async fn light_set_brightness(&self, entity_id: &str, brightness_pct: f64) -> Result<()> {
+        self.call_service(
+            "light",
+            "turn_on",
+            Some(&json!({ "brightness_pct": brightness_pct })),
+            Some(&json!({ "entity_id": entity_id })),
+        )
+        .await
+    }
+
+    pub async fn media_player_volume_set(&self, entity_id: &str, volume_level: f64) -> Result<()> {
+        self.call_service(
+            "media_player",
+            "volume_set",
+            Some(&json!({ "volume_level": volume_level })),
+            Some(&json!({ "entity_id": entity_id })),
+        )
+        .await
+    }
+
+    /// Set the volume of several media players at once.
+    pub async fn media_players_volume_set(
+        &self,
+        entity_ids: &[String],
+        volume_level: f64,
+    ) -> Result<()> {
+        self.call_service(
+            "media_player",
+            "volume_set",
+            Some(&json!({ "volume_level": volume_level })),
+            Some(&json!({ "entity_id": entity_ids })),
+        )
+        .await
+    }
+
+    /// Stop several media players at once.
+    pub async fn media_players_stop(&self, entity_ids: &[String]) -> Result<()> {
+        self.call_service(
+            "media_player",
+            "media_stop",
+            Some(&json!({})),
+            Some(&json!({ "entity_id": entity_ids })),
+        )
+        .await
+    }
+
+    /// Turn several media players off at once.
+    pub async fn media_players_turn_off(&self, entity_ids: &[String]) -> Result<()> {
+        self.call_service(
+            "media_player",
+            "turn_off",
+            Some(&json!({})),
+            Some(&json!({ "entity_id": entity_ids })),
+        )
+        .await
+    }
+
+    /// Make media players play in sync with a leader.
+    pub async fn media_player_join(&self, entity_id: &str, group_members: &[String]) -> Result<()> {
+        self.call_service(
+            "media_player",
+            "join",
+            Some(&json!({ "group_members": group_members })),
+            Some(&json!({ "entity_id": entity_id })),
+        )
+        .await
+    }
+
+    /// Make media players leave the groups they are in.
+    pub async fn media_players_unjoin(&self, entity_ids: &[String]) -> Result<()> {
+        self.call_service(
+            "media_player",
+            "unjoin",
+            Some(&json!({})),
+            Some(&json!({ "entity_id": entity_ids })),
+        )
+        .await
+    }
+
+    pub async fn climate_set_temperature(&self, entity_id: &str, temperature: f64) -> Result<()> {
+        self.call_service(
+            "climate",
+            "set_temperature",
+            Some(&json!({ "temperature": temperature })),
+            Some(&json!({ "entity_id": entity_id })),
+        )
+        .await
+    }
+
+    pub async fn input_number_set_value(&self, entity_id: &str, value: f64) -> Result<()> {
+        self.call_service(
+            "input_number",
+            "set_value",
+            Some(&json!({ "value": value })),
+            Some(&json!({ "entity_id": entity_id })),
+        )
+        .await
+    }
+
+    pub async fn persistent_notification_create(&self, title: &str, message: &str) -> Result<()> {
+        self.call_service(
+            "persistent_notification",
+            "create",
+            Some(&json!({ "title": title, "message": message })),
+            None,
+        )
+        .await
+    }
+
+    /// Fade several lights off at once.
+    pub async fn lights_turn_off(&self, entity_ids: &[String], transition: Duration) -> Result<()> {
+        self.call_service(
+            "light",
+            "turn_off",
+            Some(&json!({ "transition": transition.as_secs_f64() })),
+            Some(&json!({ "entity_id": entity_ids })),
+        )
+        .await
+    }
+
     pub async fn light_set(&self, entity_id: &str, status: bool) -> Result<()> {
         self.call_service(
             "light",
@@ -403,170 +1714,191 @@ impl Controller {
         )
         .await
     }
+
+    pub async fn switch_set(&self, entity_id: &str, status: bool) -> Result<()> {
+        self.call_service(
+            "switch",
+            if status { "turn_on" } else { "turn_off" },
+            None,
+            Some(&json!({ "entity_id": entity_id })),
+        )
+        .await
+    }
+
+    pub async fn alarm_arm_away(&self, entity_id: &str, code: Option<&str>) -> Result<()> {
+        self.alarm_call("alarm_arm_away", entity_id, code).await
+    }
+
+    pub async fn alarm_arm_home(&self, entity_id: &str, code: Option<&str>) -> Result<()> {
+        self.alarm_call("alarm_arm_home", entity_id, code).await
+    }
+
+    pub async fn alarm_disarm(&self, entity_id: &str, code: Option<&str>) -> Result<()> {
+        self.alarm_call("alarm_disarm", entity_id, code).await
+    }
+
+    /// Call an `alarm_control_panel` service, with the code if the panel
+    /// requires one.
+    async fn alarm_call(&self, service: &str, entity_id: &str, code: Option<&str>) -> Result<()> {
+        let service_data = match code {
+            Some(code) => json!({ "code": code }),
+            None => json!({}),
+        };
+
+        self.call_service(
+            "alarm_control_panel",
+            service,
+            Some(&service_data),
+            Some(&json!({ "entity_id": entity_id })),
+        )
+        .await
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "type", rename_all = "snake_case")]
-enum Message {
-    AuthRequired {
-        ha_version: String,
-    },
-    Auth {
-        access_token: String,
-    },
-    AuthOk {
-        ha_version: String,
-    },
-    AuthInvalid {
-        message: String,
-    },
-    CallService {
-        id: u64,
-        domain: String,
-        service: String,
-        service_data: Option<serde_json::Value>,
-        target: Option<serde_json::Value>,
-    },
-    Result {
-        id: u64,
-        success: bool,
-        #[serde(default)]
-        result: serde_json::Value,
-        error: Option<Error>,
-    },
-    SubscribeEvents {
-        id: u64,
-        event_type: Option<String>,
-    },
-    SubscribeTrigger {
-        id: u64,
-        trigger: serde_json::Value,
-    },
-    Ping {
-        id: u64,
-    },
-    Pong {
-        id: u64,
-    },
-    Event {
-        id: u64,
-        event: Box<Event>,
-    },
-    GetStates {
-        id: u64,
-    },
+/// Log the Home Assistant context of a call made while handling an API
+/// request, so that the request can be traced in the Home Assistant logbook.
+///
+/// Home Assistant does not let websocket clients set the context, but returns
+/// the one it created.
+fn log_context(action: &str, result: &serde_json::Value) {
+    if let Some(request_id) = request_id::current() {
+        let context_id = result
+            .pointer("/context/id")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("unknown");
+
+        info!(
+            "[{}] {} (Home Assistant context `{}`).",
+            request_id, action, context_id
+        );
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Error {
-    code: String,
-    message: String,
+/// Notify the watchers of the entity states of a change.
+fn notify_states(states_tx: &watch::Sender<u64>) {
+    states_tx.send_modify(|generation| *generation += 1);
 }
 
-impl Default for Error {
-    fn default() -> Self {
-        Self {
-            code: "unspecified".to_string(),
-            message: "no error information was present".to_string(),
-        }
-    }
+/// The Home-Assistant configuration, as returned by `get_config`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Config {
+    /// The state of the core, like `RUNNING`. Older versions do not report it.
+    #[serde(default)]
+    pub state: Option<String>,
+
+    /// The loaded components, like `hue` or `light.hue`.
+    #[serde(default)]
+    pub components: Vec<String>,
+
+    #[serde(default)]
+    pub version: Option<String>,
+
+    #[serde(default)]
+    pub location_name: Option<String>,
+
+    #[serde(default)]
+    pub unit_system: Option<UnitSystem>,
+
+    #[serde(default)]
+    pub time_zone: Option<String>,
 }
 
-impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "E{}: {}", self.code, self.message)
-    }
+/// The units Home-Assistant displays values in.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct UnitSystem {
+    /// Like `km` or `mi`.
+    pub length: String,
+
+    /// Like `°C` or `°F`.
+    pub temperature: String,
 }
 
-impl std::error::Error for Error {}
+impl UnitSystem {
+    /// Check whether Home-Assistant uses imperial units.
+    pub fn is_imperial(&self) -> bool {
+        self.length == "mi"
+    }
+}
 
-impl Message {
-    fn inject_id(&mut self, new_id: u64) -> bool {
-        match self {
-            Self::AuthRequired { .. }
-            | Self::Auth { .. }
-            | Self::AuthOk { .. }
-            | Self::AuthInvalid { .. } => false,
-            Self::CallService { id, .. }
-            | Self::Result { id, .. }
-            | Self::SubscribeEvents { id, .. }
-            | Self::SubscribeTrigger { id, .. }
-            | Self::Ping { id }
-            | Self::Pong { id }
-            | Self::Event { id, .. }
-            | Self::GetStates { id } => {
-                *id = new_id;
+/// The information about the Home-Assistant instance.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Info {
+    pub version: Option<String>,
+    pub location_name: Option<String>,
+    pub unit_system: Option<UnitSystem>,
+    pub time_zone: Option<String>,
+
+    /// Why Home-Assistant rejected the token, while it does.
+    pub auth_failure: Option<String>,
+}
 
-                true
-            }
-        }
+impl Config {
+    pub fn is_running(&self) -> bool {
+        matches!(self.state.as_deref(), None | Some("RUNNING"))
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "event_type", rename_all = "snake_case")]
-pub enum Event {
-    StateChanged {
-        context: Context,
-        data: StateChangedData,
-        origin: String,
-        time_fired: DateTime<Utc>,
-    },
+struct EntityRegistryEntry {
+    entity_id: String,
+    platform: String,
+    #[serde(default)]
+    area_id: Option<String>,
+    #[serde(default)]
+    device_id: Option<String>,
+    #[serde(default)]
+    disabled_by: Option<String>,
+    #[serde(default)]
+    hidden_by: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Context {
-    pub id: String,
-    pub parent_id: Option<String>,
-    pub user_id: Option<String>,
+struct DeviceRegistryEntry {
+    id: String,
+    #[serde(default)]
+    area_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct StateChangedData {
-    pub entity_id: String,
-    pub old_state: Option<State>,
-    pub new_state: Option<State>,
+struct AreaRegistryEntry {
+    area_id: String,
+    name: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct State {
-    pub entity_id: String,
-    pub attributes: serde_json::Value,
-    pub context: Context,
-    pub last_changed: DateTime<Utc>,
-    pub last_updated: DateTime<Utc>,
-    pub state: String,
+/// The areas of the entities, as set in the registries.
+#[derive(Debug, Clone, Default)]
+pub struct EntityAreas {
+    /// The area ids by entity, from the entity or else from its device.
+    pub entities: HashMap<String, String>,
+
+    /// The area names by id.
+    pub names: HashMap<String, String>,
 }
 
-impl Display for Event {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self {
-            Self::StateChanged {
-                context: _,
-                data,
-                origin: _,
-                time_fired: _,
-            } => write!(
-                f,
-                "{}: {} -> {}",
-                data.entity_id,
-                data.old_state
-                    .as_ref()
-                    .map(|s| s.state.as_str())
-                    .unwrap_or_default(),
-                data.new_state
-                    .as_ref()
-                    .map(|s| s.state.as_str())
-                    .unwrap_or_default(),
-            ),
-        }
+impl EntityAreas {
+    /// Find the id of an area, by id or by name.
+    pub fn find(&self, area: &str) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|(id, name)| *id == area || name.eq_ignore_ascii_case(area))
+            .map(|(id, _)| id.as_str())
     }
 }
 
-impl From<State> for bool {
-    fn from(s: State) -> Self {
-        matches!(s.state.as_str(), "on" | "1" | "true")
-    }
+/// An entity suggested for the dashboard.
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredEntity {
+    pub entity_id: String,
+    pub domain: String,
+    pub friendly_name: Option<String>,
+    pub state: String,
+
+    /// The area of the entity, or of its device.
+    pub area_id: Option<String>,
+    pub area_name: Option<String>,
+    pub integration: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -588,6 +1920,12 @@ pub struct WeatherAttributes {
     pub temperature: f64,
     pub wind_bearing: f64,
     pub wind_speed: f64,
+
+    /// Older versions do not report the units.
+    #[serde(default)]
+    pub temperature_unit: Option<String>,
+    #[serde(default)]
+    pub wind_speed_unit: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -595,8 +1933,19 @@ pub struct WeatherForecast {
     pub condition: String,
     pub datetime: DateTime<Utc>,
     pub precipitation: f64,
+
+    /// Not reported by all the weather integrations.
+    #[serde(default)]
+    pub precipitation_probability: Option<f64>,
     pub temperature: f64,
-    pub templow: f64,
+
+    /// Not reported by all the weather integrations.
+    #[serde(default)]
+    pub humidity: Option<f64>,
+
+    /// Only reported by the daily forecasts.
+    #[serde(default)]
+    pub templow: Option<f64>,
     pub wind_bearing: f64,
     pub wind_speed: f64,
 }
@@ -610,7 +1959,10 @@ impl TryFrom<State> for WeatherState {
             state: value.state,
             last_changed: value.last_changed,
             last_updated: value.last_updated,
-            attributes: serde_json::from_value(value.attributes)?,
+            attributes: value.attributes.parse()?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,99 @@
+//! Tests of the Home-Assistant client.
+
+use serde_json::Value;
+
+use super::*;
+
+macro_rules! fixture {
+    ($name:literal) => {
+        serde_json::from_str::<Value>(include_str!(concat!("fixtures/", $name, ".json")))
+            .expect(concat!("invalid fixture `", $name, "`"))
+    };
+}
+
+fn parse<T: serde::de::DeserializeOwned>(value: &Value) -> T {
+    serde_json::from_value(value.clone()).expect("failed to parse")
+}
+
+#[test]
+fn configs() {
+    let config: Config = parse(&fixture!("get_config_2023"));
+
+    assert!(config.is_running());
+    assert_eq!(config.version.as_deref(), Some("2023.8.4"));
+    assert!(!config
+        .unit_system
+        .expect("missing unit system")
+        .is_imperial());
+    assert!(config.components.contains(&"light.hue".to_string()));
+
+    let config: Config = parse(&fixture!("get_config_2025"));
+
+    assert!(!config.is_running());
+    assert_eq!(config.time_zone.as_deref(), Some("America/New_York"));
+    assert!(config
+        .unit_system
+        .expect("missing unit system")
+        .is_imperial());
+}
+
+#[test]
+fn traffic_redacts_the_secrets() {
+    let traffic = Traffic::new(true);
+    let auth = Message::Auth {
+        access_token: "secret-token".to_string(),
+    };
+
+    traffic.record(
+        TrafficDirection::Sent,
+        &serde_json::to_string(&auth).unwrap(),
+    );
+    traffic.record(
+        TrafficDirection::Sent,
+        r#"{"id":3,"type":"call_service","service_data":{"entity_id":"alarm_control_panel.home","code":"1234"}}"#,
+    );
+    traffic.record(TrafficDirection::Received, "not json");
+
+    let (recent, _) = traffic.subscribe();
+    let text = serde_json::to_string(&recent).unwrap();
+
+    assert_eq!(recent.len(), 3);
+    assert!(!text.contains("secret-token"), "{}", text);
+    assert!(!text.contains("1234"), "{}", text);
+    assert_eq!(
+        recent[1].message["service_data"]["entity_id"],
+        "alarm_control_panel.home"
+    );
+    assert_eq!(recent[2].message, "not json");
+}
+
+#[test]
+fn traffic_is_only_recorded_when_enabled() {
+    let traffic = Traffic::new(false);
+
+    traffic.record(TrafficDirection::Received, r#"{"type":"pong","id":1}"#);
+
+    assert!(traffic.subscribe().0.is_empty());
+}
+
+#[test]
+fn the_auth_retries_back_off_up_to_the_max_delay() {
+    let policy = AuthFailurePolicy {
+        initial_delay: Duration::from_secs(30),
+        max_delay: Duration::from_secs(100),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        policy.next_delay(policy.initial_delay),
+        Duration::from_secs(60)
+    );
+    assert_eq!(
+        policy.next_delay(Duration::from_secs(60)),
+        Duration::from_secs(100)
+    );
+    assert_eq!(
+        policy.next_delay(Duration::from_secs(100)),
+        Duration::from_secs(100)
+    );
+}
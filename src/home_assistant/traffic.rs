@@ -0,0 +1,125 @@
+//! The recent messages exchanged with Home-Assistant, to diagnose the
+//! subscriptions and the routing of the results without extra logging.
+//!
+//! Only recorded in debug mode, with the secrets redacted.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::redact::redact;
+
+/// The number of recent messages kept.
+const CAPACITY: usize = 200;
+
+/// The size above which the messages are truncated, like the full states.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TrafficDirection {
+    Sent,
+    Received,
+}
+
+/// A raw message exchanged with Home-Assistant.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficMessage {
+    pub at: DateTime<Utc>,
+    pub direction: TrafficDirection,
+
+    /// The message, or the beginning of its text if it was too large.
+    pub message: Value,
+}
+
+/// The recorder of the messages, shared by the client and its controllers.
+#[derive(Clone)]
+pub struct Traffic {
+    enabled: bool,
+    recent: Arc<Mutex<VecDeque<TrafficMessage>>>,
+    tx: broadcast::Sender<TrafficMessage>,
+}
+
+impl Traffic {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            recent: Default::default(),
+            tx: broadcast::channel(64).0,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Record the text of a message, if enabled.
+    pub fn record(&self, direction: TrafficDirection, text: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        let message = match serde_json::from_str::<Value>(text) {
+            Ok(mut value) => {
+                redact(&mut value);
+
+                let text = value.to_string();
+
+                if text.len() > MAX_MESSAGE_SIZE {
+                    Value::String(truncate(&text))
+                } else {
+                    value
+                }
+            }
+            Err(_) => Value::String(truncate(text)),
+        };
+        let message = TrafficMessage {
+            at: Utc::now(),
+            direction,
+            message,
+        };
+
+        let mut recent = self.recent.lock().unwrap();
+
+        if recent.len() == CAPACITY {
+            recent.pop_front();
+        }
+
+        recent.push_back(message.clone());
+
+        // Nobody may be listening.
+        let _ = self.tx.send(message);
+    }
+
+    /// Get the recent messages, the oldest first, and subscribe to the next
+    /// ones.
+    pub fn subscribe(&self) -> (Vec<TrafficMessage>, broadcast::Receiver<TrafficMessage>) {
+        // Subscribed under the lock, for no message to be missed or repeated.
+        let recent = self.recent.lock().unwrap();
+
+        (recent.iter().cloned().collect(), self.tx.subscribe())
+    }
+}
+
+/// Keep the beginning of a text, on a character boundary.
+fn truncate(text: &str) -> String {
+    if text.len() <= MAX_MESSAGE_SIZE {
+        return text.to_string();
+    }
+
+    let mut end = MAX_MESSAGE_SIZE;
+
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    format!("{}… ({} bytes)", &text[..end], text.len())
+}
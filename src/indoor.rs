@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::home_assistant::State;
+
+/// The indoor climate configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndoorConfig {
+    /// The entity reporting the indoor temperature, in °C.
+    pub temperature_entity: String,
+
+    /// The entity reporting the indoor relative humidity, in %.
+    pub humidity_entity: String,
+
+    /// The relative humidity above which the mold risk is moderate.
+    #[serde(default = "IndoorConfig::default_moderate_humidity")]
+    pub moderate_humidity: f64,
+
+    /// The relative humidity above which the mold risk is high.
+    #[serde(default = "IndoorConfig::default_high_humidity")]
+    pub high_humidity: f64,
+
+    /// The mold risk level from which a notification is raised and the red
+    /// LED is lit, if any.
+    #[serde(default)]
+    pub alert_level: Option<MoldRisk>,
+}
+
+impl IndoorConfig {
+    fn default_moderate_humidity() -> f64 {
+        60.0
+    }
+
+    fn default_high_humidity() -> f64 {
+        70.0
+    }
+
+    /// Compute the indoor status from the current entities.
+    ///
+    /// Returns `None` if either entity is missing or has a non-numeric state.
+    pub fn status(&self, entities: &HashMap<String, State>) -> Option<IndoorStatus> {
+        let numeric_state = |entity_id: &String| {
+            entities
+                .get(entity_id)
+                .and_then(|state| state.state.parse::<f64>().ok())
+        };
+
+        let temperature = numeric_state(&self.temperature_entity)?;
+        let humidity = numeric_state(&self.humidity_entity)?;
+
+        let mold_risk = if humidity >= self.high_humidity {
+            MoldRisk::High
+        } else if humidity >= self.moderate_humidity {
+            MoldRisk::Moderate
+        } else {
+            MoldRisk::Low
+        };
+
+        Some(IndoorStatus {
+            temperature,
+            humidity,
+            dew_point: dew_point(temperature, humidity),
+            mold_risk,
+        })
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum MoldRisk {
+    Low,
+    Moderate,
+    High,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IndoorStatus {
+    pub temperature: f64,
+    pub humidity: f64,
+    pub dew_point: f64,
+    pub mold_risk: MoldRisk,
+}
+
+/// Compute the dew point in °C using the Magnus formula.
+pub fn dew_point(temperature: f64, humidity: f64) -> f64 {
+    const A: f64 = 17.62;
+    const B: f64 = 243.12;
+
+    let gamma = (humidity.max(1.0) / 100.0).ln() + A * temperature / (B + temperature);
+
+    B * gamma / (A - gamma)
+}
@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::{actions::Action, context::AppContext, gpio_controller::Pull, tasks};
+
+pub mod usb;
+
+/// The interval between two readings of the input pins.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// An input pin reported to Home-Assistant, like a button or a reed switch.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct InputConfig {
+    /// The name of the input, used in the events and as the friendly name of
+    /// the entity.
+    pub name: String,
+
+    /// The GPIO pin the input is connected to.
+    pub pin: u8,
+
+    /// The pull resistor to enable on the pin.
+    #[serde(default)]
+    pub pull: Pull,
+
+    /// Report the input as on when the pin is low, like with pulled-up
+    /// buttons wired to the ground.
+    #[serde(default)]
+    pub invert: bool,
+
+    /// How long the pin level must be stable to be reported.
+    #[serde(default = "InputConfig::default_debounce")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub debounce: Duration,
+
+    /// How to report the input.
+    pub report: InputReport,
+}
+
+impl InputConfig {
+    fn default_debounce() -> Duration {
+        Duration::from_millis(50)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputReport {
+    /// Fire an event on every change, with the name of the input and its
+    /// `on`/`off` state.
+    Event {
+        #[serde(default = "InputReport::default_event_type")]
+        event_type: String,
+    },
+
+    /// Set the state of an entity, like `binary_sensor.enclosure_open`.
+    State { entity_id: String },
+
+    /// Run actions when the input turns on, like when a button is pressed.
+    Actions { actions: Vec<Action> },
+}
+
+impl InputReport {
+    fn default_event_type() -> String {
+        "home_control_input".to_string()
+    }
+}
+
+struct InputState<'a> {
+    config: &'a InputConfig,
+    reported: Option<bool>,
+    candidate: bool,
+    candidate_since: Instant,
+}
+
+/// Report the input pins to Home-Assistant forever.
+pub async fn run(inputs: &[InputConfig], context: &AppContext) -> anyhow::Result<()> {
+    let mut states: Vec<_> = inputs
+        .iter()
+        .map(|config| InputState {
+            config,
+            reported: None,
+            candidate: false,
+            candidate_since: Instant::now(),
+        })
+        .collect();
+
+    loop {
+        for state in &mut states {
+            let config = state.config;
+            let on = match context.gpio.read_input(config.pin, config.pull) {
+                Ok(high) => high != config.invert,
+                Err(err) => {
+                    warn!("Failed to read input `{}`: {}", config.name, err);
+                    continue;
+                }
+            };
+
+            if on != state.candidate {
+                state.candidate = on;
+                state.candidate_since = Instant::now();
+            }
+
+            if state.reported == Some(state.candidate)
+                || state.candidate_since.elapsed() < config.debounce
+            {
+                continue;
+            }
+
+            info!(
+                "Input `{}` is {}.",
+                config.name,
+                if on { "on" } else { "off" }
+            );
+
+            match report(config, on, context).await {
+                Ok(()) => state.reported = Some(on),
+                Err(err) => warn!("Failed to report input `{}`: {}", config.name, err),
+            }
+        }
+
+        tasks::heartbeat();
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn report(config: &InputConfig, on: bool, context: &AppContext) -> crate::Result<()> {
+    let state = if on { "on" } else { "off" };
+
+    match &config.report {
+        InputReport::Event { event_type } => {
+            context
+                .home_assistant
+                .fire_event(
+                    event_type,
+                    &json!({
+                        "name": config.name,
+                        "pin": config.pin,
+                        "state": state,
+                    }),
+                )
+                .await
+        }
+        InputReport::State { entity_id } => {
+            context
+                .home_assistant
+                .set_state(entity_id, state, &json!({ "friendly_name": config.name }))
+                .await
+        }
+        InputReport::Actions { actions } => {
+            // Failed actions are not retried, unlike the reports.
+            if on {
+                run_actions(actions, &config.name, context).await;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Run the actions of an input, logging their failures.
+async fn run_actions(actions: &[Action], input: &str, context: &AppContext) {
+    for action in actions {
+        if let Err(err) = action.run(context).await {
+            warn!("Failed to run the action of input `{}`: {}", input, err);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::{actions::Action, context::AppContext};
+
+/// The delay before reopening a device that failed, like when unplugged.
+#[cfg(feature = "hid")]
+const REOPEN_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// A USB input device plugged into the panel, like a foot pedal or a macro
+/// keypad, whose keys run actions.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UsbInputConfig {
+    /// The name of the device, used in the logs.
+    pub name: String,
+
+    /// The input device, like `/dev/input/by-id/usb-...-event-kbd`.
+    pub device: PathBuf,
+
+    /// The actions by key, like `KEY_F13` or `BTN_LEFT`, run when the key is
+    /// pressed.
+    pub keys: HashMap<String, Vec<Action>>,
+
+    /// Whether to keep the keys from reaching the kiosk browser.
+    #[serde(default = "UsbInputConfig::default_grab")]
+    pub grab: bool,
+}
+
+impl UsbInputConfig {
+    fn default_grab() -> bool {
+        true
+    }
+}
+
+/// Read the USB input devices forever.
+#[cfg(feature = "hid")]
+pub async fn run(devices: &[UsbInputConfig], context: &AppContext) -> anyhow::Result<()> {
+    use log::warn;
+
+    // The keys are checked upfront, to fail on typos.
+    let devices = devices
+        .iter()
+        .map(|config| {
+            let keys = config
+                .keys
+                .iter()
+                .map(|(name, actions)| {
+                    name.parse::<evdev::Key>()
+                        .map(|key| (key, actions.as_slice()))
+                        .map_err(|_| {
+                            anyhow::anyhow!("unknown key `{}` for `{}`", name, config.name)
+                        })
+                })
+                .collect::<anyhow::Result<HashMap<_, _>>>()?;
+
+            Ok((config, keys))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let reads = devices.iter().map(|(config, keys)| async move {
+        loop {
+            if let Err(err) = read(config, keys, context).await {
+                warn!("Failed to read `{}`: {:#}", config.name, err);
+            }
+
+            tokio::time::sleep(REOPEN_DELAY).await;
+        }
+    });
+
+    futures_util::future::join_all(reads).await;
+
+    Ok(())
+}
+
+#[cfg(feature = "hid")]
+async fn read(
+    config: &UsbInputConfig,
+    keys: &HashMap<evdev::Key, &[Action]>,
+    context: &AppContext,
+) -> anyhow::Result<()> {
+    use anyhow::Context;
+    use evdev::{Device, InputEventKind};
+    use log::info;
+
+    let mut device = Device::open(&config.device)
+        .with_context(|| format!("failed to open `{}`", config.device.display()))?;
+
+    if config.grab {
+        device.grab().context("failed to grab the device")?;
+    }
+
+    let mut events = device
+        .into_event_stream()
+        .context("failed to read from the device")?;
+
+    info!(
+        "Reading `{}` from `{}`.",
+        config.name,
+        config.device.display()
+    );
+
+    loop {
+        let event = events
+            .next_event()
+            .await
+            .context("failed to read from the device")?;
+
+        // Only key presses matter, not the releases nor the repeats.
+        if event.value() != 1 {
+            continue;
+        }
+
+        if let InputEventKind::Key(key) = event.kind() {
+            if let Some(actions) = keys.get(&key) {
+                info!("Key {:?} of `{}` was pressed.", key, config.name);
+                super::run_actions(actions, &config.name, context).await;
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "hid"))]
+pub async fn run(_devices: &[UsbInputConfig], _context: &AppContext) -> anyhow::Result<()> {
+    crate::tasks::unsupported("USB input", "hid").await
+}
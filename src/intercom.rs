@@ -0,0 +1,179 @@
+//! The intercom between the panels of the household, which forward messages
+//! to each other directly rather than through Home-Assistant.
+//!
+//! The panels authenticate each other with a shared token, sent as a bearer
+//! token, and the receiving panel acknowledges the messages once shown.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::info;
+use schemars::JsonSchema;
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::auth::constant_time_eq;
+
+/// The shortest shared token, as it guards the public intercom route.
+const MIN_TOKEN_LENGTH: usize = 16;
+
+/// The intercom configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct IntercomConfig {
+    /// The name of the panel, as shown by the other panels.
+    pub name: String,
+
+    /// The token shared by the panels of the household, of at least 16
+    /// characters.
+    #[serde(deserialize_with = "IntercomConfig::deserialize_token")]
+    pub token: String,
+
+    /// The other panels, by name, with the URL of their API, like
+    /// `office: https://office.local:8000/api/v1`.
+    #[serde(default)]
+    pub panels: BTreeMap<String, String>,
+
+    /// For how long to wait for the acknowledgement of a message.
+    #[serde(default = "IntercomConfig::default_timeout")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub timeout: Duration,
+}
+
+impl IntercomConfig {
+    fn default_timeout() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn deserialize_token<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        let token = String::deserialize(deserializer)?;
+
+        if token.trim().chars().count() < MIN_TOKEN_LENGTH {
+            return Err(D::Error::custom(format!(
+                "the intercom token must be at least {} characters long",
+                MIN_TOKEN_LENGTH
+            )));
+        }
+
+        Ok(token)
+    }
+}
+
+/// A message forwarded to another panel.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IntercomMessage {
+    /// The panel the message comes from, set by the sending panel.
+    #[serde(default)]
+    pub from: String,
+    pub message: String,
+
+    /// Whether to chime the buzzer first.
+    #[serde(default)]
+    pub chime: bool,
+
+    /// A WAV clip to play after the message is shown, in base64.
+    #[serde(default)]
+    pub audio: Option<String>,
+}
+
+/// The acknowledgement of a message by the panel that showed it.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IntercomAck {
+    pub panel: String,
+    pub received_at: DateTime<Utc>,
+}
+
+/// The outcome of forwarding a message to another panel.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IntercomDelivery {
+    pub target: String,
+    pub delivered: bool,
+
+    /// The acknowledgement of the target panel, once delivered.
+    pub ack: Option<IntercomAck>,
+
+    /// Why the message was not delivered, if it was not.
+    pub error: Option<String>,
+}
+
+/// Forwards the messages to the other panels.
+pub struct Intercom {
+    config: IntercomConfig,
+    http_client: reqwest::Client,
+}
+
+impl Intercom {
+    pub fn new(config: IntercomConfig) -> Self {
+        Self {
+            config,
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Check whether a message comes from a panel of the household, from the
+    /// value of its `authorization` header.
+    ///
+    /// Nothing is authorized with an empty token.
+    pub fn is_authorized(&self, authorization: Option<&str>) -> bool {
+        !self.config.token.is_empty()
+            && authorization
+                .and_then(|authorization| authorization.strip_prefix("Bearer "))
+                .is_some_and(|token| constant_time_eq(token, &self.config.token))
+    }
+
+    /// Forward a message to another panel, by name, and wait for its
+    /// acknowledgement.
+    ///
+    /// Returns `None` if the panel is unknown.
+    pub async fn send(
+        &self,
+        target: &str,
+        mut message: IntercomMessage,
+    ) -> Option<IntercomDelivery> {
+        let url = self.config.panels.get(target)?;
+
+        message.from = self.config.name.clone();
+
+        let (ack, error) = match self.post(url, &message).await {
+            Ok(ack) => {
+                info!("The panel `{}` acknowledged the message.", target);
+
+                (Some(ack), None)
+            }
+            Err(err) => (None, Some(format!("{:#}", err))),
+        };
+
+        Some(IntercomDelivery {
+            target: target.to_string(),
+            delivered: ack.is_some(),
+            ack,
+            error,
+        })
+    }
+
+    async fn post(&self, url: &str, message: &IntercomMessage) -> anyhow::Result<IntercomAck> {
+        self.http_client
+            .post(format!("{}/intercom", url.trim_end_matches('/')))
+            .bearer_auth(&self.config.token)
+            .timeout(self.config.timeout)
+            .json(message)
+            .send()
+            .await
+            .context("failed to reach the panel")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("invalid acknowledgement")
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,45 @@
+//! Tests of the intercom.
+
+use super::*;
+
+fn intercom(token: &str) -> Intercom {
+    Intercom::new(IntercomConfig {
+        name: "kitchen".to_string(),
+        token: token.to_string(),
+        panels: BTreeMap::new(),
+        timeout: Duration::from_secs(5),
+    })
+}
+
+#[test]
+fn only_the_shared_token_is_authorized() {
+    let intercom = intercom("correct-horse-battery");
+
+    assert!(intercom.is_authorized(Some("Bearer correct-horse-battery")));
+    assert!(!intercom.is_authorized(Some("Bearer correct-horse")));
+    assert!(!intercom.is_authorized(Some("correct-horse-battery")));
+    assert!(!intercom.is_authorized(Some("Bearer ")));
+    assert!(!intercom.is_authorized(None));
+}
+
+#[test]
+fn nothing_is_authorized_with_an_empty_token() {
+    let intercom = intercom("");
+
+    assert!(!intercom.is_authorized(Some("Bearer ")));
+    assert!(!intercom.is_authorized(Some("Bearer")));
+}
+
+#[test]
+fn short_tokens_are_rejected() {
+    let config = |token: &str| {
+        serde_json::from_value::<IntercomConfig>(serde_json::json!({
+            "name": "kitchen",
+            "token": token,
+        }))
+    };
+
+    assert!(config("").is_err());
+    assert!(config("hunter2").is_err());
+    assert!(config("correct-horse-battery").is_ok());
+}
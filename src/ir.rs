@@ -0,0 +1,97 @@
+use serde::Deserialize;
+
+/// The IR blaster configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IrConfig {
+    /// The GPIO pin the IR LED is connected to.
+    pub pin: u8,
+
+    /// The frequency of the carrier, in Hz.
+    #[serde(default = "IrConfig::default_carrier_frequency")]
+    pub carrier_frequency: f64,
+
+    /// The duty cycle of the carrier, between 0 and 1.
+    #[serde(default = "IrConfig::default_duty_cycle")]
+    pub duty_cycle: f64,
+
+    /// The named codes that can be sent.
+    #[serde(default)]
+    pub codes: Vec<IrCodeConfig>,
+}
+
+impl IrConfig {
+    fn default_carrier_frequency() -> f64 {
+        38000.0
+    }
+
+    fn default_duty_cycle() -> f64 {
+        0.33
+    }
+
+    pub fn code(&self, name: &str) -> Option<&IrCodeConfig> {
+        self.codes.iter().find(|code| code.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IrCodeConfig {
+    /// The name of the code, as used in the API path.
+    pub name: String,
+
+    #[serde(flatten)]
+    pub code: IrCode,
+
+    /// The number of times to send the code.
+    #[serde(default = "IrCodeConfig::default_repeat")]
+    pub repeat: u32,
+}
+
+impl IrCodeConfig {
+    fn default_repeat() -> u32 {
+        1
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "protocol", rename_all = "snake_case")]
+pub enum IrCode {
+    /// The NEC protocol, used by most consumer devices.
+    Nec { address: u8, command: u8 },
+
+    /// Raw alternating mark/space timings, in microseconds, starting with a
+    /// mark.
+    Raw { timings: Vec<u32> },
+}
+
+/// The gap between repeated codes, in microseconds.
+pub const REPEAT_GAP_US: u32 = 40000;
+
+impl IrCode {
+    /// Get the alternating mark/space timings of the code, in microseconds.
+    pub fn timings(&self) -> Vec<u32> {
+        match self {
+            Self::Nec { address, command } => {
+                const BIT_MARK: u32 = 562;
+                const ZERO_SPACE: u32 = 562;
+                const ONE_SPACE: u32 = 1687;
+
+                let mut timings = vec![9000, 4500];
+
+                for byte in [*address, !address, *command, !command] {
+                    for bit in 0..8 {
+                        timings.push(BIT_MARK);
+                        timings.push(if byte & (1 << bit) != 0 {
+                            ONE_SPACE
+                        } else {
+                            ZERO_SPACE
+                        });
+                    }
+                }
+
+                timings.push(BIT_MARK);
+                timings
+            }
+            Self::Raw { timings } => timings.clone(),
+        }
+    }
+}
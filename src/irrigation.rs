@@ -0,0 +1,449 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::{watch, Mutex, Notify, RwLock};
+
+use crate::{
+    context::AppContext,
+    history::{ActionResult, ConditionResult, NewExecution},
+    holidays,
+    home_assistant::WeatherState,
+    outputs::Relay,
+    tasks,
+};
+
+/// How many times turning a zone off is attempted, as a valve left open
+/// floods the garden.
+const STOP_ATTEMPTS: usize = 3;
+
+/// The irrigation configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IrrigationConfig {
+    pub zones: Vec<ZoneConfig>,
+
+    /// The daily programs. They can be changed at runtime, and the changes
+    /// are saved with the configuration edited from the frontend.
+    #[serde(default)]
+    pub schedules: Vec<IrrigationSchedule>,
+
+    /// Skip the scheduled programs when rain is forecast, if specified.
+    #[serde(default)]
+    pub rain_skip: Option<RainSkipConfig>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ZoneConfig {
+    /// The name of the zone, as used in the API path.
+    pub name: String,
+
+    /// The valve of the zone.
+    pub relay: Relay,
+
+    /// How long the zone is watered for, unless specified otherwise.
+    #[serde(default = "ZoneConfig::default_run_time")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub run_time: Duration,
+}
+
+impl ZoneConfig {
+    fn default_run_time() -> Duration {
+        Duration::from_secs(10 * 60)
+    }
+}
+
+/// A daily program, watering zones one after the other.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IrrigationSchedule {
+    /// The time of day the program starts at, like `06:00`.
+    pub at: NaiveTime,
+
+    /// The zones to water, in order. All the zones if empty.
+    #[serde(default)]
+    pub zones: Vec<String>,
+
+    /// Whether the program is skipped on the holidays.
+    #[serde(default)]
+    pub skip_holidays: bool,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct RainSkipConfig {
+    /// The weather entity to get the forecast from. Defaults to the weather
+    /// entity of the panel.
+    #[serde(default)]
+    pub weather_entity: Option<String>,
+
+    /// The forecast precipitation, in mm, from which the programs are
+    /// skipped.
+    #[serde(default = "RainSkipConfig::default_threshold")]
+    pub threshold: f64,
+
+    /// How far ahead the forecast precipitation is summed.
+    #[serde(default = "RainSkipConfig::default_lookahead")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub lookahead: Duration,
+}
+
+impl RainSkipConfig {
+    fn default_threshold() -> f64 {
+        2.0
+    }
+
+    fn default_lookahead() -> Duration {
+        Duration::from_secs(24 * 60 * 60)
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedRun {
+    pub zone: String,
+    #[serde_as(as = "DurationSeconds<f64>")]
+    #[schemars(with = "f64")]
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveRun {
+    pub zone: String,
+    pub started_at: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IrrigationStatus {
+    pub zones: Vec<String>,
+    pub active: Option<ActiveRun>,
+    pub queue: Vec<QueuedRun>,
+    pub schedules: Vec<IrrigationSchedule>,
+
+    /// Whether the scheduled programs are currently skipped for rain.
+    pub rain_skip: bool,
+}
+
+/// Waters the zones one at a time.
+pub struct Irrigation {
+    config: IrrigationConfig,
+    schedules: RwLock<Vec<IrrigationSchedule>>,
+    queue: Mutex<VecDeque<QueuedRun>>,
+    active: Mutex<Option<ActiveRun>>,
+    queued: Notify,
+    stops: watch::Sender<u64>,
+}
+
+impl Irrigation {
+    pub fn new(config: IrrigationConfig) -> Self {
+        Self {
+            schedules: RwLock::new(config.schedules.clone()),
+            config,
+            queue: Default::default(),
+            active: Default::default(),
+            queued: Notify::new(),
+            stops: watch::channel(0).0,
+        }
+    }
+
+    pub async fn status(&self, context: &AppContext) -> IrrigationStatus {
+        IrrigationStatus {
+            zones: self
+                .config
+                .zones
+                .iter()
+                .map(|zone| zone.name.clone())
+                .collect(),
+            active: self.active.lock().await.clone(),
+            queue: self.queue.lock().await.iter().cloned().collect(),
+            schedules: self.schedules.read().await.clone(),
+            rain_skip: self.rain_expected(context).await,
+        }
+    }
+
+    /// Queue watering a zone, for its configured run time unless specified.
+    ///
+    /// Returns `false` if the zone does not exist.
+    pub async fn start(&self, zone: &str, duration: Option<Duration>) -> bool {
+        let zone = match self.zone(zone) {
+            Some(zone) => zone,
+            None => return false,
+        };
+
+        info!("Queueing irrigation of zone `{}`.", zone.name);
+
+        self.queue.lock().await.push_back(QueuedRun {
+            zone: zone.name.clone(),
+            duration: duration.unwrap_or(zone.run_time),
+        });
+        self.queued.notify_one();
+
+        true
+    }
+
+    /// Stop watering, and clear the queue.
+    pub async fn stop(&self) {
+        info!("Stopping irrigation.");
+
+        self.queue.lock().await.clear();
+        self.stops.send_modify(|stops| *stops += 1);
+    }
+
+    pub async fn schedules(&self) -> Vec<IrrigationSchedule> {
+        self.schedules.read().await.clone()
+    }
+
+    /// Check that the daily programs only water known zones.
+    pub fn validate_schedules(&self, schedules: &[IrrigationSchedule]) -> crate::Result<()> {
+        match schedules
+            .iter()
+            .flat_map(|schedule| &schedule.zones)
+            .find(|zone| self.zone(zone).is_none())
+        {
+            Some(zone) => Err(crate::Error::InvalidConfig(format!(
+                "unknown irrigation zone `{}`",
+                zone
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Replace the daily programs.
+    pub async fn set_schedules(&self, schedules: Vec<IrrigationSchedule>) -> crate::Result<()> {
+        self.validate_schedules(&schedules)?;
+
+        *self.schedules.write().await = schedules;
+
+        Ok(())
+    }
+
+    /// Run the programs and the queued zones forever, and record the
+    /// programs in the history, if any.
+    pub async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        let mut last_check = Local::now().naive_local();
+
+        loop {
+            let now = Local::now().naive_local();
+
+            self.queue_due_schedules(context, last_check, now).await;
+            last_check = now;
+
+            let next = self.queue.lock().await.pop_front();
+
+            match next {
+                Some(run) => self.water(context, run).await,
+                None => {
+                    tokio::select! {
+                        _ = self.queued.notified() => {}
+                        _ = tokio::time::sleep(Duration::from_secs(30)) => {}
+                    }
+                }
+            }
+
+            tasks::heartbeat();
+        }
+    }
+
+    async fn queue_due_schedules(
+        &self,
+        context: &AppContext,
+        last_check: NaiveDateTime,
+        now: NaiveDateTime,
+    ) {
+        let due: Vec<_> = self
+            .schedules
+            .read()
+            .await
+            .iter()
+            .filter(|schedule| {
+                [last_check.date(), now.date()].iter().any(|date| {
+                    let at = date.and_time(schedule.at);
+
+                    last_check < at && at <= now
+                })
+            })
+            .cloned()
+            .collect();
+
+        if due.is_empty() {
+            return;
+        }
+
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let rain_expected = self.rain_expected(context).await;
+
+        // The rain forecast is the condition of all the programs.
+        let conditions: Vec<_> = self
+            .config
+            .rain_skip
+            .iter()
+            .map(|_| ConditionResult {
+                condition: json!({ "type": "no_rain_forecast" }),
+                matched: !rain_expected,
+                actual: None,
+            })
+            .collect();
+
+        let holiday = holidays::is_holiday(context, now.date()).await;
+
+        for schedule in due {
+            let mut actions = Vec::new();
+            let mut conditions = conditions.clone();
+
+            if schedule.skip_holidays {
+                conditions.push(ConditionResult {
+                    condition: json!({ "type": "no_holiday" }),
+                    matched: !holiday,
+                    actual: None,
+                });
+            }
+
+            if schedule.skip_holidays && holiday {
+                info!(
+                    "Today is a holiday: skipping the irrigation program of {}.",
+                    schedule.at
+                );
+            } else if rain_expected {
+                info!(
+                    "Rain is forecast: skipping the irrigation program of {}.",
+                    schedule.at
+                );
+            } else {
+                info!("Starting the irrigation program of {}.", schedule.at);
+
+                let zones: Vec<_> = if schedule.zones.is_empty() {
+                    self.config
+                        .zones
+                        .iter()
+                        .map(|zone| zone.name.clone())
+                        .collect()
+                } else {
+                    schedule.zones.clone()
+                };
+
+                for zone in zones {
+                    let queued = self.start(&zone, None).await;
+
+                    actions.push(ActionResult {
+                        action: json!({ "type": "water", "zone": zone }),
+                        error: (!queued).then(|| "unknown zone".to_string()),
+                    });
+                }
+            }
+
+            if let Some(history) = &context.storage.history {
+                history
+                    .record(NewExecution {
+                        source: "irrigation".to_string(),
+                        trigger: format!("schedule {}", schedule.at),
+                        started_at,
+                        duration: start.elapsed(),
+                        conditions,
+                        actions,
+                    })
+                    .await;
+            }
+        }
+    }
+
+    /// Water a zone until its run time elapses or watering is stopped.
+    async fn water(&self, context: &AppContext, run: QueuedRun) {
+        let zone = match self.zone(&run.zone) {
+            Some(zone) => zone,
+            None => return,
+        };
+        let mut stops = self.stops.subscribe();
+
+        info!(
+            "Watering zone `{}` for {:.0}s.",
+            zone.name,
+            run.duration.as_secs_f64()
+        );
+
+        if let Err(err) = zone.relay.set(context, true).await {
+            warn!("Failed to start watering zone `{}`: {}", zone.name, err);
+
+            return;
+        }
+
+        let started_at = Utc::now();
+
+        *self.active.lock().await = Some(ActiveRun {
+            zone: zone.name.clone(),
+            started_at,
+            until: started_at + chrono::Duration::from_std(run.duration).unwrap_or_default(),
+        });
+
+        tokio::select! {
+            _ = tokio::time::sleep(run.duration) => {}
+            _ = stops.changed() => {}
+        }
+
+        for attempt in 1..=STOP_ATTEMPTS {
+            match zone.relay.set(context, false).await {
+                Ok(()) => break,
+                Err(err) => {
+                    warn!(
+                        "Failed to stop watering zone `{}` (attempt {}/{}): {}",
+                        zone.name, attempt, STOP_ATTEMPTS, err
+                    );
+
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+        }
+
+        *self.active.lock().await = None;
+    }
+
+    /// Check whether rain is forecast, if rain skip is configured.
+    async fn rain_expected(&self, context: &AppContext) -> bool {
+        let rain_skip = match &self.config.rain_skip {
+            Some(rain_skip) => rain_skip,
+            None => return false,
+        };
+        let entity_id = rain_skip
+            .weather_entity
+            .as_ref()
+            .unwrap_or(&context.config.weather_entity);
+        let weather = match context.home_assistant.entity(entity_id).await {
+            Some(state) => match WeatherState::try_from(state) {
+                Ok(weather) => weather,
+                Err(err) => {
+                    warn!("Failed to parse the weather of `{}`: {}", entity_id, err);
+
+                    return false;
+                }
+            },
+            None => return false,
+        };
+        let horizon =
+            Utc::now() + chrono::Duration::from_std(rain_skip.lookahead).unwrap_or_default();
+        let precipitation: f64 = weather
+            .attributes
+            .forecast
+            .iter()
+            .filter(|forecast| forecast.datetime <= horizon)
+            .map(|forecast| forecast.precipitation)
+            .sum();
+
+        matches!(weather.state.as_str(), "rainy" | "pouring")
+            || precipitation >= rain_skip.threshold
+    }
+
+    fn zone(&self, name: &str) -> Option<&ZoneConfig> {
+        self.config.zones.iter().find(|zone| zone.name == name)
+    }
+}
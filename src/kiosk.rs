@@ -0,0 +1,263 @@
+use std::{process::Stdio, time::Duration};
+
+use anyhow::Context;
+use log::{info, warn};
+use serde::Deserialize;
+use serde_with::{serde_as, DurationSeconds};
+use tokio::{
+    process::{Child, Command},
+    sync::Notify,
+    time::{interval, sleep, MissedTickBehavior},
+};
+
+use crate::tasks;
+
+/// The kiosk browser configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct KioskConfig {
+    /// The browser command.
+    #[serde(default = "KioskConfig::default_browser")]
+    pub browser: String,
+
+    /// The arguments of the browser, before the URL.
+    #[serde(default = "KioskConfig::default_args")]
+    pub args: Vec<String>,
+
+    /// The URL of the panel.
+    #[serde(default = "KioskConfig::default_url")]
+    pub url: String,
+
+    /// The resident memory of the browser and its children, in MiB, above
+    /// which it is restarted.
+    #[serde(default)]
+    pub memory_limit_mb: Option<u64>,
+
+    /// The interval between two checks of the browser memory.
+    #[serde(default = "KioskConfig::default_check_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub check_interval: Duration,
+
+    /// The delay before restarting a browser that exited.
+    #[serde(default = "KioskConfig::default_restart_delay")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub restart_delay: Duration,
+}
+
+impl KioskConfig {
+    fn default_browser() -> String {
+        "chromium-browser".to_string()
+    }
+
+    fn default_args() -> Vec<String> {
+        [
+            "--kiosk",
+            "--noerrdialogs",
+            "--disable-infobars",
+            "--disable-session-crashed-bubble",
+            "--check-for-update-interval=31536000",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+    }
+
+    fn default_url() -> String {
+        "http://localhost:8000/".to_string()
+    }
+
+    fn default_check_interval() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn default_restart_delay() -> Duration {
+        Duration::from_secs(5)
+    }
+}
+
+/// Why the browser stopped.
+enum Exit {
+    Exited,
+    Reload,
+    OutOfMemory(u64),
+}
+
+/// Launches the kiosk browser and supervises it.
+///
+/// The browser is restarted when it exits, when it uses too much memory, or on
+/// request.
+pub struct Kiosk {
+    config: KioskConfig,
+    reload: Notify,
+}
+
+impl Kiosk {
+    pub fn new(config: KioskConfig) -> Self {
+        Self {
+            config,
+            reload: Notify::new(),
+        }
+    }
+
+    /// Restart the browser, which reloads the panel from scratch.
+    pub fn reload(&self) {
+        self.reload.notify_one();
+    }
+
+    /// Run the browser forever.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        loop {
+            match self.supervise().await {
+                Ok(Exit::Reload) => info!("Reloading the kiosk browser."),
+                Ok(Exit::OutOfMemory(resident)) => {
+                    warn!(
+                        "The kiosk browser uses {} MiB of memory: restarting it.",
+                        resident / (1024 * 1024)
+                    );
+
+                    sleep(self.config.restart_delay).await;
+                }
+                Ok(Exit::Exited) => {
+                    warn!("The kiosk browser exited: restarting it.");
+
+                    sleep(self.config.restart_delay).await;
+                }
+                Err(err) => {
+                    warn!("The kiosk browser failed: {}", err);
+
+                    sleep(self.config.restart_delay).await;
+                }
+            }
+        }
+    }
+
+    async fn supervise(&self) -> anyhow::Result<Exit> {
+        let mut child = Command::new(&self.config.browser)
+            .args(&self.config.args)
+            .arg(&self.config.url)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .context("failed to run the kiosk browser")?;
+        let pid = child.id().context("the kiosk browser exited immediately")?;
+
+        info!("Started the kiosk browser (pid {}).", pid);
+
+        let mut check = interval(self.config.check_interval);
+        check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // The first tick is immediate: let the browser start first.
+        check.tick().await;
+
+        let exit = loop {
+            tokio::select! {
+                status = child.wait() => {
+                    let status = status.context("failed to wait for the kiosk browser")?;
+
+                    info!("The kiosk browser exited with {}.", status);
+
+                    return Ok(Exit::Exited);
+                }
+                _ = self.reload.notified() => break Exit::Reload,
+                _ = check.tick() => {
+                    tasks::heartbeat();
+
+                    if let Some(limit) = self.config.memory_limit_mb {
+                        let resident = tree_resident_memory(pid).await;
+
+                        if resident > limit * 1024 * 1024 {
+                            break Exit::OutOfMemory(resident);
+                        }
+                    }
+                }
+            }
+        };
+
+        stop(&mut child).await;
+
+        Ok(exit)
+    }
+}
+
+/// Stop the browser, gracefully first so that it does not complain about
+/// having crashed on its next start.
+async fn stop(child: &mut Child) {
+    if let Some(pid) = child.id() {
+        let _ = Command::new("kill").arg(pid.to_string()).status().await;
+
+        if tokio::time::timeout(Duration::from_secs(5), child.wait())
+            .await
+            .is_ok()
+        {
+            return;
+        }
+    }
+
+    if let Err(err) = child.kill().await {
+        warn!("Failed to kill the kiosk browser: {}", err);
+    }
+}
+
+/// The resident memory of a process and all its descendants, in bytes.
+///
+/// Browsers run their tabs in child processes, which is where the leaks are.
+async fn tree_resident_memory(pid: u32) -> u64 {
+    let mut processes = Vec::new();
+
+    if let Ok(mut entries) = tokio::fs::read_dir("/proc").await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let id = match entry
+                .file_name()
+                .to_str()
+                .and_then(|s| s.parse::<u32>().ok())
+            {
+                Some(id) => id,
+                None => continue,
+            };
+
+            if let Ok(stat) = tokio::fs::read_to_string(entry.path().join("stat")).await {
+                if let Some((parent, resident)) = parse_stat(&stat) {
+                    processes.push((id, parent, resident));
+                }
+            }
+        }
+    }
+
+    let mut tree = vec![pid];
+    let mut total = 0;
+    let mut index = 0;
+
+    while let Some(&current) = tree.get(index) {
+        for (id, parent, resident) in &processes {
+            if *id == current {
+                total += resident;
+            } else if *parent == current {
+                tree.push(*id);
+            }
+        }
+
+        index += 1;
+    }
+
+    total
+}
+
+/// Parse the parent id and the resident memory, in bytes, from a
+/// `/proc/<pid>/stat` line.
+///
+/// The command name is parenthesized and may contain spaces: the other fields
+/// come after its closing parenthesis.
+fn parse_stat(stat: &str) -> Option<(u32, u64)> {
+    let (_, fields) = stat.rsplit_once(')')?;
+    let fields: Vec<_> = fields.split_whitespace().collect();
+
+    // The fields after the command name start with the state (3rd field): the
+    // parent id is the 4th, and the resident pages the 24th.
+    let parent = fields.get(1)?.parse().ok()?;
+    let pages: u64 = fields.get(21)?.parse().ok()?;
+
+    Some((parent, pages * PAGE_SIZE))
+}
+
+/// The page size of the supported platforms.
+const PAGE_SIZE: u64 = 4096;
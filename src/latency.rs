@@ -0,0 +1,288 @@
+//! The latency of the API endpoints and of the calls to Home-Assistant, to
+//! tell a slow Home-Assistant from an overloaded panel when the panel feels
+//! sluggish.
+//!
+//! The 95th percentiles are computed over a rolling window, and a warning is
+//! raised while some exceed their budget.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::{
+    notifications::{Notifications, Severity},
+    tasks,
+};
+
+/// The id of the notification raised while some budgets are exceeded.
+const NOTIFICATION_ID: &str = "latency_budget";
+
+/// The number of latest samples kept by endpoint.
+const MAX_SAMPLES: usize = 500;
+
+/// The number of endpoints tracked, so that unexpected paths cannot grow the
+/// samples without bounds.
+const MAX_ENDPOINTS: usize = 200;
+
+/// The number of samples below which a budget is not enforced, so that a
+/// single slow request does not raise a warning.
+const MIN_SAMPLES: usize = 5;
+
+/// The latest durations, with when they were recorded, by endpoint.
+type Samples = BTreeMap<(LatencySource, String), VecDeque<(Instant, Duration)>>;
+
+static SAMPLES: Mutex<Samples> = Mutex::new(BTreeMap::new());
+
+/// The latency budgets configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct LatencyConfig {
+    /// The budget of the 95th percentile of the API endpoints.
+    #[serde(default = "LatencyConfig::default_api_budget")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub api_budget: Duration,
+
+    /// The budget of the 95th percentile of the calls to Home-Assistant.
+    #[serde(default = "LatencyConfig::default_home_assistant_budget")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub home_assistant_budget: Duration,
+
+    /// The budgets of specific endpoints, like `GET /api/v1/status`, or of
+    /// specific calls, like `get_states`.
+    #[serde(default)]
+    #[serde_as(as = "HashMap<_, DurationSeconds<f64>>")]
+    pub budgets: HashMap<String, Duration>,
+
+    /// The window the percentiles are computed over.
+    #[serde(default = "LatencyConfig::default_window")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub window: Duration,
+
+    /// The interval between checks of the budgets.
+    #[serde(default = "LatencyConfig::default_check_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub check_interval: Duration,
+}
+
+impl Default for LatencyConfig {
+    fn default() -> Self {
+        Self {
+            api_budget: Self::default_api_budget(),
+            home_assistant_budget: Self::default_home_assistant_budget(),
+            budgets: HashMap::new(),
+            window: Self::default_window(),
+            check_interval: Self::default_check_interval(),
+        }
+    }
+}
+
+impl LatencyConfig {
+    fn default_api_budget() -> Duration {
+        Duration::from_millis(500)
+    }
+
+    fn default_home_assistant_budget() -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn default_window() -> Duration {
+        Duration::from_secs(5 * 60)
+    }
+
+    fn default_check_interval() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn budget(&self, source: LatencySource, name: &str) -> Duration {
+        match self.budgets.get(name) {
+            Some(budget) => *budget,
+            None => match source {
+                LatencySource::Api => self.api_budget,
+                LatencySource::HomeAssistant => self.home_assistant_budget,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencySource {
+    Api,
+    HomeAssistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStatus {
+    pub source: LatencySource,
+
+    /// The endpoint, like `GET /api/v1/lights/*`, or the type of call, like
+    /// `call_service`.
+    pub name: String,
+
+    /// The number of samples in the window.
+    pub count: usize,
+
+    /// The 95th percentile, in seconds.
+    pub p95: f64,
+
+    /// The budget of the 95th percentile, in seconds.
+    pub budget: f64,
+
+    /// Whether the 95th percentile exceeds the budget, over enough samples.
+    pub exceeded: bool,
+}
+
+/// Record how long a request or a call took.
+pub fn record(source: LatencySource, name: &str, duration: Duration) {
+    let mut samples = SAMPLES.lock().unwrap();
+    let key = (source, name.to_string());
+
+    if !samples.contains_key(&key) && samples.len() >= MAX_ENDPOINTS {
+        return;
+    }
+
+    let endpoint_samples = samples.entry(key).or_default();
+
+    endpoint_samples.push_back((Instant::now(), duration));
+
+    if endpoint_samples.len() > MAX_SAMPLES {
+        endpoint_samples.pop_front();
+    }
+}
+
+/// Get the endpoint of a request, with the segments that look like
+/// identifiers replaced by `*`, so that the entities and the ids of a route
+/// are counted together.
+pub fn endpoint(method: &str, path: &str) -> String {
+    let path: Vec<_> = path
+        .split('/')
+        .map(|segment| {
+            // Like the entity ids, the numeric ids and the tokens.
+            if segment.contains(['.', '%'])
+                || segment.starts_with(|c: char| c.is_ascii_digit())
+                || segment.len() >= 16
+            {
+                "*"
+            } else {
+                segment
+            }
+        })
+        .collect();
+
+    format!("{} {}", method, path.join("/"))
+}
+
+pub struct Latency {
+    config: LatencyConfig,
+}
+
+impl Latency {
+    pub fn new(config: LatencyConfig) -> Self {
+        Self { config }
+    }
+
+    /// Get the latency of the endpoints and of the calls, within the window.
+    pub fn statuses(&self) -> Vec<LatencyStatus> {
+        let mut samples = SAMPLES.lock().unwrap();
+        let now = Instant::now();
+
+        samples.retain(|_, endpoint_samples| {
+            endpoint_samples.retain(|(at, _)| now.duration_since(*at) <= self.config.window);
+
+            !endpoint_samples.is_empty()
+        });
+
+        samples
+            .iter()
+            .map(|((source, name), endpoint_samples)| {
+                let p95 = percentile(endpoint_samples.iter().map(|(_, duration)| *duration), 0.95);
+                let budget = self.config.budget(*source, name);
+
+                LatencyStatus {
+                    source: *source,
+                    name: name.clone(),
+                    count: endpoint_samples.len(),
+                    p95: p95.as_secs_f64(),
+                    budget: budget.as_secs_f64(),
+                    exceeded: endpoint_samples.len() >= MIN_SAMPLES && p95 > budget,
+                }
+            })
+            .collect()
+    }
+
+    /// Check the budgets forever, raising a warning while some are exceeded.
+    pub async fn run(&self, notifications: &Notifications) -> anyhow::Result<()> {
+        loop {
+            match diagnose(&self.statuses()) {
+                Some(message) => {
+                    notifications
+                        .raise(
+                            NOTIFICATION_ID,
+                            Severity::Warning,
+                            "Slow responses",
+                            message,
+                        )
+                        .await;
+                }
+                None => {
+                    notifications.clear(NOTIFICATION_ID).await;
+                }
+            }
+
+            tokio::time::sleep(self.config.check_interval).await;
+            tasks::heartbeat();
+        }
+    }
+}
+
+/// Get a percentile of durations, by nearest rank.
+fn percentile(durations: impl Iterator<Item = Duration>, ratio: f64) -> Duration {
+    let mut durations: Vec<_> = durations.collect();
+
+    if durations.is_empty() {
+        return Duration::ZERO;
+    }
+
+    durations.sort();
+
+    let rank = (ratio * durations.len() as f64).ceil() as usize;
+
+    durations[rank.clamp(1, durations.len()) - 1]
+}
+
+/// Describe the exceeded budgets, if any, telling whether Home-Assistant or
+/// the panel itself is slow.
+fn diagnose(statuses: &[LatencyStatus]) -> Option<String> {
+    let exceeded: Vec<_> = statuses.iter().filter(|status| status.exceeded).collect();
+    let slow = |source| exceeded.iter().any(|status| status.source == source);
+
+    let cause = match (slow(LatencySource::HomeAssistant), slow(LatencySource::Api)) {
+        (false, false) => return None,
+        (true, false) => "Home-Assistant is slow to answer.",
+        (true, true) => "Home-Assistant is slow to answer, which slows the panel down.",
+        (false, true) => {
+            "The panel is slow to answer while Home-Assistant is not: it may be overloaded."
+        }
+    };
+    let details: Vec<_> = exceeded
+        .iter()
+        .map(|status| {
+            format!(
+                "`{}` takes {:.2}s for a budget of {:.2}s",
+                status.name, status.p95, status.budget
+            )
+        })
+        .collect();
+
+    Some(format!("{} {}.", cause, details.join(", ")))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,81 @@
+//! Tests of the latency budgets.
+
+use super::*;
+
+fn status(source: LatencySource, name: &str, exceeded: bool) -> LatencyStatus {
+    LatencyStatus {
+        source,
+        name: name.to_string(),
+        count: 10,
+        p95: if exceeded { 3.0 } else { 0.1 },
+        budget: 2.0,
+        exceeded,
+    }
+}
+
+#[test]
+fn the_percentile_is_by_nearest_rank() {
+    let durations = (1..=20).map(Duration::from_millis);
+
+    assert_eq!(percentile(durations, 0.95), Duration::from_millis(19));
+    assert_eq!(
+        percentile([Duration::from_secs(1)].into_iter(), 0.95),
+        Duration::from_secs(1)
+    );
+    assert_eq!(percentile(std::iter::empty(), 0.95), Duration::ZERO);
+}
+
+#[test]
+fn endpoints_group_the_identifiers() {
+    assert_eq!(
+        endpoint("POST", "/api/v1/lights/light.kitchen/toggle"),
+        "POST /api/v1/lights/*/toggle"
+    );
+    assert_eq!(
+        endpoint("DELETE", "/api/v1/chores/42"),
+        "DELETE /api/v1/chores/*"
+    );
+    assert_eq!(endpoint("GET", "/api/v1/status"), "GET /api/v1/status");
+}
+
+#[test]
+fn the_budgets_can_be_overridden() {
+    let config = LatencyConfig {
+        budgets: HashMap::from([("get_states".to_string(), Duration::from_secs(10))]),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        config.budget(LatencySource::HomeAssistant, "get_states"),
+        Duration::from_secs(10)
+    );
+    assert_eq!(
+        config.budget(LatencySource::Api, "GET /api/v1/status"),
+        Duration::from_millis(500)
+    );
+}
+
+#[test]
+fn the_diagnosis_tells_the_slow_side() {
+    assert_eq!(
+        diagnose(&[status(LatencySource::HomeAssistant, "ping", false)]),
+        None
+    );
+
+    let home_assistant = diagnose(&[
+        status(LatencySource::HomeAssistant, "call_service", true),
+        status(LatencySource::Api, "GET /api/v1/status", false),
+    ])
+    .unwrap();
+
+    assert!(home_assistant.starts_with("Home-Assistant is slow"));
+    assert!(home_assistant.contains("`call_service` takes 3.00s"));
+
+    let panel = diagnose(&[
+        status(LatencySource::HomeAssistant, "call_service", false),
+        status(LatencySource::Api, "GET /api/v1/status", true),
+    ])
+    .unwrap();
+
+    assert!(panel.contains("overloaded"));
+}
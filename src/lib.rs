@@ -1,8 +1,107 @@
+pub mod actions;
+pub mod air_quality;
+pub mod alarm_indicator;
+pub mod ambient_light;
+pub mod announcements;
 pub mod api;
+pub mod apparent_temperature;
+pub mod appliances;
+pub mod artwork;
+pub mod assets;
+pub mod astronomy;
+pub mod audio;
+pub mod auth;
+pub mod barcode;
+pub mod bug_report;
+pub mod camera;
+pub mod changelog;
+pub mod chaos;
+pub mod chores;
+pub mod circadian;
+pub mod client_ip;
+pub mod climate;
+pub mod clock_skew;
+pub mod comfort;
 pub mod config;
+pub mod context;
+pub mod control_action;
+pub mod dashboard;
+pub mod debounce;
+pub mod departures;
+pub mod digest;
+pub mod disk;
+pub mod docker;
+pub mod energy_meter;
 mod error;
+pub mod error_policy;
+pub mod esphome;
+pub mod extra_sensors;
+pub mod fan;
+pub mod files;
+pub mod forecast;
+pub mod frost;
+pub mod gestures;
 pub mod gpio_controller;
+pub mod hazards;
+pub mod history;
+pub mod holidays;
 pub mod home_assistant;
+pub mod indoor;
+pub mod inputs;
+pub mod intercom;
+pub mod ir;
+pub mod irrigation;
+pub mod kiosk;
+pub mod latency;
+pub mod lockout;
 pub mod log;
+pub mod media_groups;
+pub mod melody;
+pub mod memory;
+pub mod migrations;
+pub mod mirrors;
+pub mod mqtt;
+pub mod negotiation;
+pub mod net_check;
+pub mod network;
+pub mod notifications;
+pub mod nowcast;
+pub mod outputs;
+pub mod overrides;
+pub mod panic;
+pub mod polling;
+pub mod presence;
+pub mod recording;
+pub mod red_led;
+pub mod redact;
+pub mod reminders;
+pub mod request_id;
+pub mod rf;
+pub mod rfid;
+pub mod rules;
+pub mod screen;
+pub mod screensaver;
+pub mod secrets;
+pub mod self_check;
+pub mod serial;
+pub mod serial_devices;
+pub mod server;
+pub mod shutdown;
+pub mod simulation;
+pub mod sleep_timer;
+pub mod sound_level;
+pub mod startup;
+pub mod tasks;
+pub mod templates;
+pub mod theme;
+pub mod thermostat;
+pub mod ups;
+pub mod usage;
+pub mod users;
+pub mod voice;
+pub mod wakeup;
+pub mod weather_alerts;
+pub mod weather_conditions;
+pub mod windows;
 
 pub use error::{Error, Result};
@@ -0,0 +1,142 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::Deserialize;
+use serde_with::{serde_as, DurationSeconds};
+use tokio::{sync::Mutex, time::Instant};
+
+/// The brute-force protection configuration for PIN checks.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct LockoutConfig {
+    /// The number of failed attempts allowed before locking out.
+    #[serde(default = "LockoutConfig::default_max_attempts")]
+    pub max_attempts: u32,
+
+    /// The duration of the first lockout. Each further failure doubles it.
+    #[serde(default = "LockoutConfig::default_lockout")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub lockout: Duration,
+
+    /// The maximum duration of a lockout.
+    #[serde(default = "LockoutConfig::default_max_lockout")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub max_lockout: Duration,
+
+    /// The number of consecutive failed attempts after which Home Assistant
+    /// is notified.
+    #[serde(default = "LockoutConfig::default_notify_after")]
+    pub notify_after: u32,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: Self::default_max_attempts(),
+            lockout: Self::default_lockout(),
+            max_lockout: Self::default_max_lockout(),
+            notify_after: Self::default_notify_after(),
+        }
+    }
+}
+
+impl LockoutConfig {
+    fn default_max_attempts() -> u32 {
+        3
+    }
+
+    fn default_lockout() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn default_max_lockout() -> Duration {
+        Duration::from_secs(60 * 60)
+    }
+
+    fn default_notify_after() -> u32 {
+        5
+    }
+}
+
+#[derive(Debug, Default)]
+struct Attempts {
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+/// The outcome of a failed attempt.
+#[derive(Debug, Clone, Copy)]
+pub struct Failure {
+    /// The number of consecutive failed attempts.
+    pub failures: u32,
+
+    /// The duration of the lockout that started, if any.
+    pub locked_for: Option<Duration>,
+
+    /// Whether Home Assistant should be notified.
+    pub notify: bool,
+}
+
+/// Counts failed PIN attempts per scope, like `login` or `alarm`, and locks
+/// the scope out with an exponential backoff.
+pub struct Lockout {
+    config: LockoutConfig,
+    attempts: Mutex<HashMap<String, Attempts>>,
+}
+
+impl Lockout {
+    pub fn new(config: LockoutConfig) -> Self {
+        Self {
+            config,
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check whether a scope is locked out, returning the remaining lockout
+    /// duration if it is.
+    pub async fn locked_for(&self, scope: &str) -> Option<Duration> {
+        self.attempts
+            .lock()
+            .await
+            .get(scope)
+            .and_then(|attempts| attempts.locked_until)
+            .and_then(|locked_until| locked_until.checked_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Record a successful attempt, resetting the failure count.
+    pub async fn succeeded(&self, scope: &str) {
+        self.attempts.lock().await.remove(scope);
+    }
+
+    /// Record a failed attempt.
+    pub async fn failed(&self, scope: &str) -> Failure {
+        let mut attempts = self.attempts.lock().await;
+        let attempts = attempts.entry(scope.to_string()).or_default();
+
+        attempts.failures += 1;
+
+        let locked_for = attempts
+            .failures
+            .checked_sub(self.config.max_attempts)
+            .map(|excess| {
+                self.config
+                    .lockout
+                    .checked_mul(2u32.saturating_pow(excess))
+                    .unwrap_or(self.config.max_lockout)
+                    .min(self.config.max_lockout)
+            });
+
+        if let Some(locked_for) = locked_for {
+            attempts.locked_until = Some(Instant::now() + locked_for);
+        }
+
+        Failure {
+            failures: attempts.failures,
+            locked_for,
+            notify: attempts.failures >= self.config.notify_after,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
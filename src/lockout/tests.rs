@@ -0,0 +1,102 @@
+//! Tests of the PIN lockout.
+
+use super::*;
+
+fn lockout() -> Lockout {
+    Lockout::new(LockoutConfig {
+        max_attempts: 3,
+        lockout: Duration::from_secs(30),
+        max_lockout: Duration::from_secs(100),
+        notify_after: 5,
+    })
+}
+
+#[tokio::test(start_paused = true)]
+async fn lockouts_double_with_each_failure() {
+    let lockout = lockout();
+    let mut locked_for = Vec::new();
+
+    for _ in 0..5 {
+        locked_for.push(lockout.failed("login").await.locked_for);
+    }
+
+    assert_eq!(
+        locked_for,
+        [
+            None,
+            None,
+            Some(Duration::from_secs(30)),
+            Some(Duration::from_secs(60)),
+            Some(Duration::from_secs(100)),
+        ]
+    );
+    assert_eq!(
+        lockout.locked_for("login").await,
+        Some(Duration::from_secs(100))
+    );
+
+    tokio::time::advance(Duration::from_secs(99)).await;
+    assert_eq!(
+        lockout.locked_for("login").await,
+        Some(Duration::from_secs(1))
+    );
+
+    tokio::time::advance(Duration::from_secs(1)).await;
+    assert_eq!(lockout.locked_for("login").await, None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn lockouts_are_capped() {
+    let lockout = lockout();
+    let mut failure = lockout.failed("login").await;
+
+    for _ in 0..100 {
+        failure = lockout.failed("login").await;
+    }
+
+    assert_eq!(failure.failures, 101);
+    assert_eq!(failure.locked_for, Some(Duration::from_secs(100)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn successes_reset_the_failures() {
+    let lockout = lockout();
+
+    for _ in 0..3 {
+        lockout.failed("login").await;
+    }
+
+    lockout.succeeded("login").await;
+
+    assert_eq!(lockout.locked_for("login").await, None);
+
+    let failure = lockout.failed("login").await;
+
+    assert_eq!(failure.failures, 1);
+    assert_eq!(failure.locked_for, None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn scopes_are_locked_out_separately() {
+    let lockout = lockout();
+
+    for _ in 0..3 {
+        lockout.failed("login").await;
+    }
+
+    assert!(lockout.locked_for("login").await.is_some());
+    assert_eq!(lockout.locked_for("alarm").await, None);
+    assert_eq!(lockout.failed("alarm").await.failures, 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn home_assistant_is_notified_after_enough_failures() {
+    let lockout = lockout();
+    let mut notify = Vec::new();
+
+    for _ in 0..6 {
+        notify.push(lockout.failed("login").await.notify);
+    }
+
+    assert_eq!(notify, [false, false, false, false, true, true]);
+}
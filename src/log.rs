@@ -1,16 +1,184 @@
-use log::LevelFilter;
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+};
+
+use chrono::Utc;
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::json;
 use simplelog::{ColorChoice, Config, TermLogger, TerminalMode};
 
-pub fn init(debug: bool) {
-    TermLogger::init(
-        if debug {
-            LevelFilter::Debug
-        } else {
-            LevelFilter::Info
-        },
-        Config::default(),
-        TerminalMode::Mixed,
-        ColorChoice::Auto,
-    )
-    .unwrap();
+use crate::client_ip;
+
+/// The number of recent log lines kept, for the bug reports.
+const RECENT_CAPACITY: usize = 1000;
+
+/// The target of the audit log lines, like the PIN attempts.
+const AUDIT_TARGET: &str = "audit";
+
+/// The target of the log lines whose message is a JSON record, like the
+/// startup report, which the JSON logs keep structured.
+pub const REPORT_TARGET: &str = "report";
+
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Initialize the logs, as JSON lines on stdout when `json` is set, like in
+/// containers.
+///
+/// The recent lines are also kept in memory, whatever the output, and
+/// appended to a file if specified.
+pub fn init(debug: bool, json: bool, file: Option<&Path>) {
+    let level = if debug {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    };
+    let inner: Box<dyn Log> = if json {
+        Box::new(JsonLogger { level })
+    } else {
+        TermLogger::new(
+            level,
+            Config::default(),
+            TerminalMode::Mixed,
+            ColorChoice::Auto,
+        )
+    };
+
+    let (file, error) = match file.map(|path| (path, open(path))) {
+        Some((_, Ok(file))) => (Some(Mutex::new(file)), None),
+        Some((path, Err(err))) => (None, Some((path, err))),
+        None => (None, None),
+    };
+
+    log::set_boxed_logger(Box::new(RecentLogger { inner, file })).unwrap();
+    log::set_max_level(level);
+
+    if let Some((path, err)) = error {
+        log::warn!("Failed to open the log file `{}`: {}", path.display(), err);
+    }
+}
+
+fn open(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Get the recent log lines, the oldest first.
+pub fn recent() -> Vec<String> {
+    RECENT.lock().unwrap().iter().cloned().collect()
+}
+
+/// Keeps the recent lines logged by another logger.
+struct RecentLogger {
+    inner: Box<dyn Log>,
+    file: Option<Mutex<File>>,
+}
+
+impl Log for RecentLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        // The audit lines tell who did it, through the API.
+        if record.target() == AUDIT_TARGET {
+            if let Some(client_ip) = client_ip::current() {
+                return self.keep(
+                    &Record::builder()
+                        .args(format_args!("[from {}] {}", client_ip, record.args()))
+                        .metadata(record.metadata().clone())
+                        .module_path(record.module_path())
+                        .file(record.file())
+                        .line(record.line())
+                        .build(),
+                );
+            }
+        }
+
+        self.keep(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+impl RecentLogger {
+    fn keep(&self, record: &Record) {
+        let line = format!(
+            "{} [{}] {}: {}",
+            Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+
+        {
+            let mut recent = RECENT.lock().unwrap();
+
+            if recent.len() == RECENT_CAPACITY {
+                recent.pop_front();
+            }
+
+            recent.push_back(line.clone());
+        }
+
+        if let Some(file) = &self.file {
+            let _ = writeln!(file.lock().unwrap(), "{}", line);
+        }
+
+        self.inner.log(record);
+    }
+}
+
+/// Logs JSON lines to stdout, for the log collectors of the containers.
+struct JsonLogger {
+    level: LevelFilter,
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let message = record.args().to_string();
+        let report = (record.target() == REPORT_TARGET)
+            .then(|| serde_json::from_str::<serde_json::Value>(&message).ok())
+            .flatten();
+        let line = match report {
+            Some(report) => json!({
+                "time": Utc::now(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "report": report,
+            }),
+            None => json!({
+                "time": Utc::now(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": message,
+            }),
+        };
+
+        let _ = writeln!(std::io::stdout().lock(), "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stdout().flush();
+    }
 }
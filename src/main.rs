@@ -3,29 +3,133 @@ use std::sync::Arc;
 use anyhow::Context;
 use log::info;
 
-use home_control::{api::Api, gpio_controller::GpioController, home_assistant::Client};
-use rust_embed::RustEmbed;
-use warp::Filter;
+use home_control::{
+    api::{path_prefix, Api},
+    context::AppContext,
+    docker::InitDocker,
+    gpio_controller::GpioController,
+    home_assistant::Client,
+    overrides::EditableConfig,
+    secrets::SecretsCommand,
+    server,
+    startup::{self, Listening},
+    tasks,
+};
+use warp::{filters::BoxedFilter, http::Response, hyper::Body, Filter};
 use warp_reverse_proxy::reverse_proxy_filter;
 
-#[derive(RustEmbed)]
+/// The frontend, of which the text assets are only embedded pre-compressed.
+#[cfg(feature = "frontend")]
+#[derive(rust_embed::RustEmbed)]
 #[folder = "frontend/build"]
+#[exclude = "*.html"]
+#[exclude = "*.js"]
+#[exclude = "*.json"]
+#[exclude = "*.css"]
+#[exclude = "*.svg"]
+#[exclude = "*.xml"]
 struct Data;
 
+#[cfg(feature = "frontend")]
+fn static_files(api_prefix: &str) -> anyhow::Result<BoxedFilter<(Response<Body>,)>> {
+    Ok(home_control::assets::embedded::<Data>(api_prefix))
+}
+
+#[cfg(not(feature = "frontend"))]
+fn static_files(_api_prefix: &str) -> anyhow::Result<BoxedFilter<(Response<Body>,)>> {
+    anyhow::bail!("built without the `frontend` feature: a reverse proxy URL is required")
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    if let Some(init_docker) = InitDocker::from_args() {
+        return init_docker.run();
+    }
+
+    if let Some(secrets_command) = SecretsCommand::from_args() {
+        return secrets_command.run();
+    }
+
     let config = home_control::config::Config::new()?;
-    home_control::log::init(config.debug);
+    home_control::log::init(
+        config.debug,
+        config.docker,
+        config.home_control_config.disk.log_file.as_deref(),
+    );
 
     info!("Home-control, version {}", env!("CARGO_PKG_VERSION"));
 
     let gpio_controller =
         Arc::new(GpioController::new(config.gpio_config).context("failed to create GPIO")?);
-    let ha_client =
-        Client::new(&config.home_assistant_endpoint, config.home_assistant_token).await?;
+    let editable = EditableConfig::new(
+        config.overrides_file,
+        &config.home_control_config,
+        config.overrides,
+    );
+    let ha_client = Client::new(
+        &config.home_assistant_endpoint,
+        config.home_assistant_token,
+        config.home_control_config.auth_failure.clone(),
+        editable.tracked_entities().cloned(),
+        config.debug,
+    )
+    .await?;
     let ha_controller = ha_client.new_controller();
-    let api = Api::new(gpio_controller, ha_controller, config.home_control_config)?;
-    let routes = api.routes();
+    let context = AppContext::new(
+        config.home_control_config,
+        config.redacted_config,
+        editable,
+        gpio_controller,
+        ha_controller,
+    )?;
+    let api = Api::new(context.clone())?;
+    let routes = api.routes(&config.api_prefix, &config.api_v2_prefix);
+
+    tokio::spawn(startup::log_report(
+        context.clone(),
+        config.config_sources,
+        config.docker,
+        Listening {
+            endpoint: config.listen_endpoint,
+            tls: context.config.server.tls.is_some(),
+            api_prefixes: vec![config.api_prefix.clone(), config.api_v2_prefix.clone()],
+            static_prefix: config.static_prefix.clone(),
+            reverse_proxy_url: config.reverse_proxy_url.clone(),
+        },
+    ));
+    let home_assistant = {
+        let simulation = config.simulation;
+        let gpio = Arc::clone(&context.gpio);
+
+        async move {
+            match simulation {
+                Some(simulation) => simulation.run(ha_client, gpio).await,
+                None => Ok(ha_client.run().await?),
+            }
+        }
+    };
+
+    // Only losing Home-Assistant or the server is fatal: the other tasks are
+    // restarted when they fail, unless they ask for the process to exit.
+    api.run();
+    context.tasks.spawn("recording", {
+        let recorder = config.recorder.map(Arc::new);
+        let context = context.clone();
+        let api = Arc::clone(&api);
+
+        move || {
+            let recorder = recorder.clone();
+            let context = context.clone();
+            let distance_readings = api.distance_readings();
+
+            async move {
+                match recorder {
+                    Some(recorder) => recorder.run(&context, distance_readings).await,
+                    None => tasks::idle().await,
+                }
+            }
+        }
+    });
 
     if let Some(reverse_proxy_url) = config.reverse_proxy_url {
         info!(
@@ -34,19 +138,30 @@ async fn main() -> anyhow::Result<()> {
         );
 
         tokio::select! {
-            r = ha_client.run() => r?,
-            r = api.run() => r?,
-            _ = warp::serve(routes.or(reverse_proxy_filter("".to_string(), reverse_proxy_url)))
-                .run(config.listen_endpoint) => {},
+            r = context.tasks.run("home_assistant", home_assistant) => r?,
+            err = context.tasks.exited() => return Err(err),
+            r = context.tasks.run("server", server::serve(
+                routes.or(reverse_proxy_filter(
+                    config.static_prefix.trim_matches('/').to_string(),
+                    reverse_proxy_url,
+                )),
+                config.listen_endpoint,
+                &context.config.server,
+            )) => r?,
         }
     } else {
+        let static_files = static_files(&config.api_prefix)?;
+
         info!("Serving static files.",);
 
         tokio::select! {
-            r = ha_client.run() => r?,
-            r = api.run() => r?,
-            _ = warp::serve(routes.or(warp_embed::embed(&Data)))
-                .run(config.listen_endpoint) => {},
+            r = context.tasks.run("home_assistant", home_assistant) => r?,
+            err = context.tasks.exited() => return Err(err),
+            r = context.tasks.run("server", server::serve(
+                routes.or(path_prefix(&config.static_prefix).and(static_files)),
+                config.listen_endpoint,
+                &context.config.server,
+            )) => r?,
         }
     };
 
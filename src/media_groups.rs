@@ -0,0 +1,131 @@
+use log::info;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::home_assistant::Controller;
+
+/// A group of speakers that can play in sync, like the whole ground floor.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MediaGroupConfig {
+    /// The name of the group, as used in the API path.
+    pub name: String,
+
+    /// The media player the others follow, like `media_player.kitchen`.
+    pub leader: String,
+
+    /// The media players that join the leader.
+    pub members: Vec<String>,
+}
+
+impl MediaGroupConfig {
+    /// Get the leader and the members.
+    pub fn entity_ids(&self) -> Vec<String> {
+        std::iter::once(self.leader.clone())
+            .chain(self.members.iter().cloned())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaGroupStatus {
+    pub name: String,
+    pub leader: String,
+    pub members: Vec<String>,
+
+    /// The members currently playing in sync with the leader.
+    pub joined: Vec<String>,
+
+    /// The volume of the leader, between 0 and 1, if known.
+    pub volume_level: Option<f64>,
+}
+
+/// Groups and ungroups the configured speakers.
+pub struct MediaGroups {
+    groups: Vec<MediaGroupConfig>,
+
+    /// Serializes the joins and unjoins, whose outcome would otherwise depend
+    /// on the order in which Home-Assistant runs them.
+    operations: Mutex<()>,
+}
+
+impl MediaGroups {
+    pub fn new(groups: Vec<MediaGroupConfig>) -> Self {
+        Self {
+            groups,
+            operations: Mutex::new(()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MediaGroupConfig> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    pub async fn status(&self, ha_controller: &Controller) -> Vec<MediaGroupStatus> {
+        let mut statuses = Vec::with_capacity(self.groups.len());
+
+        for group in &self.groups {
+            let leader = ha_controller.entity(&group.leader).await;
+
+            // The leader lists itself among its group members.
+            let group_members = leader
+                .as_ref()
+                .and_then(|state| state.attributes.get::<Vec<String>>("group_members"))
+                .unwrap_or_default();
+
+            statuses.push(MediaGroupStatus {
+                name: group.name.clone(),
+                leader: group.leader.clone(),
+                members: group.members.clone(),
+                joined: group
+                    .members
+                    .iter()
+                    .filter(|member| group_members.contains(member))
+                    .cloned()
+                    .collect(),
+                volume_level: leader.and_then(|state| state.attributes.get("volume_level")),
+            });
+        }
+
+        statuses
+    }
+
+    /// Make the members of a group play in sync with its leader.
+    ///
+    /// Returns `false` if the group does not exist.
+    pub async fn join(&self, name: &str, ha_controller: &Controller) -> crate::Result<bool> {
+        let group = match self.get(name) {
+            Some(group) => group,
+            None => return Ok(false),
+        };
+        let _operation = self.operations.lock().await;
+
+        info!("Joining the speakers of `{}`.", name);
+
+        ha_controller
+            .media_player_join(&group.leader, &group.members)
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Make the speakers of a group play on their own again.
+    ///
+    /// Returns `false` if the group does not exist.
+    pub async fn unjoin(&self, name: &str, ha_controller: &Controller) -> crate::Result<bool> {
+        let group = match self.get(name) {
+            Some(group) => group,
+            None => return Ok(false),
+        };
+        let _operation = self.operations.lock().await;
+
+        info!("Unjoining the speakers of `{}`.", name);
+
+        // The leader is left alone rather than unjoined, as some integrations
+        // hand the group over to a member then.
+        ha_controller.media_players_unjoin(&group.members).await?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,57 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::Mutex;
+
+use crate::gpio_controller::GpioController;
+
+/// A melody for the buzzer, as alternating on and off durations in
+/// milliseconds, starting with a beep.
+#[derive(Debug, Clone, Copy)]
+pub struct Melody(pub &'static [u64]);
+
+impl Melody {
+    /// A single short chirp.
+    pub const SUCCESS: Melody = Melody(&[40]);
+
+    /// Three quick beeps.
+    pub const FAILURE: Melody = Melody(&[60, 60, 60, 60, 60]);
+
+    /// Two short beeps and a long one, to call for attention.
+    pub const CHIME: Melody = Melody(&[120, 80, 120, 80, 400]);
+}
+
+/// Plays melodies on the buzzer, one at a time.
+pub struct MelodyPlayer {
+    gpio: Arc<GpioController>,
+    playing: Mutex<()>,
+}
+
+impl MelodyPlayer {
+    pub fn new(gpio: Arc<GpioController>) -> Self {
+        Self {
+            gpio,
+            playing: Mutex::new(()),
+        }
+    }
+
+    /// Play a melody, after the melodies already playing.
+    ///
+    /// The buzzer is turned off even if playing fails midway.
+    pub async fn play(&self, melody: Melody) -> anyhow::Result<()> {
+        let _playing = self.playing.lock().await;
+
+        let result = async {
+            for (i, duration) in melody.0.iter().enumerate() {
+                self.gpio.set_buzzer(i % 2 == 0)?;
+                tokio::time::sleep(Duration::from_millis(*duration)).await;
+            }
+
+            Ok(())
+        }
+        .await;
+
+        self.gpio.set_buzzer(false)?;
+
+        result
+    }
+}
@@ -0,0 +1,65 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::home_assistant::{Controller, EntityCacheMemory};
+
+/// The memory used by the process, in bytes.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStatus {
+    /// The memory of the process as reported by the kernel, when available.
+    pub process: Option<ProcessMemory>,
+    pub entity_cache: EntityCacheMemory,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessMemory {
+    pub resident: u64,
+    pub peak_resident: u64,
+    pub swapped: u64,
+    pub heap: u64,
+}
+
+impl MemoryStatus {
+    pub async fn read(home_assistant: &Controller) -> Self {
+        let process = match tokio::fs::read_to_string("/proc/self/status").await {
+            Ok(status) => parse_status(&status),
+            Err(_) => None,
+        };
+
+        Self {
+            process,
+            entity_cache: home_assistant.entity_cache_memory().await,
+        }
+    }
+}
+
+/// Parse the memory of the process from `/proc/self/status`.
+///
+/// Lines look like `VmRSS:    38412 kB`.
+fn parse_status(status: &str) -> Option<ProcessMemory> {
+    let mut memory = ProcessMemory::default();
+    let mut found = false;
+
+    for (name, value) in status.lines().filter_map(|line| line.split_once(':')) {
+        let field = match name {
+            "VmRSS" => &mut memory.resident,
+            "VmHWM" => &mut memory.peak_resident,
+            "VmSwap" => &mut memory.swapped,
+            "VmData" => &mut memory.heap,
+            _ => continue,
+        };
+
+        *field = value
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse::<u64>()
+            .ok()?
+            * 1024;
+        found = true;
+    }
+
+    found.then_some(memory)
+}
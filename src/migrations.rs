@@ -0,0 +1,130 @@
+//! The migrations of the configuration file, for the keys to be renamed or
+//! restructured by new releases without breaking the panels that update on
+//! their own.
+//!
+//! A migration only applies to the configuration file: the configuration
+//! given through the environment must be upgraded by hand.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use log::{info, warn};
+use serde_yaml::{Mapping, Value};
+
+use crate::files::write_atomic;
+
+/// A migration of the configuration from a version to the next.
+type Migration = fn(&mut Mapping) -> anyhow::Result<()>;
+
+/// The migrations, by order: the first one upgrades from the version 1, the
+/// version of the files without one.
+const MIGRATIONS: &[Migration] = &[];
+
+/// The current version of the configuration.
+pub const CONFIG_VERSION: u64 = MIGRATIONS.len() as u64 + 1;
+
+/// The key of the version in the configuration.
+const VERSION_KEY: &str = "version";
+
+/// Upgrade the configuration file to the current version, if needed, and
+/// return the upgraded configuration.
+///
+/// In place, the upgraded file loses its comments: the original is kept next
+/// to it, as `<file>.v<version>.bak`. Otherwise, as on a read-only root
+/// filesystem, the file is left alone and upgraded again on every start.
+pub fn migrate_file(path: &Path, in_place: bool) -> anyhow::Result<Option<String>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read `{}`", path.display()))
+        }
+    };
+
+    // The invalid files are left for the configuration loading to report.
+    let mut config = match serde_yaml::from_str::<Value>(&content) {
+        Ok(Value::Mapping(config)) => config,
+        _ => return Ok(None),
+    };
+
+    let version = match migrate(&mut config, MIGRATIONS)
+        .with_context(|| format!("failed to migrate `{}`", path.display()))?
+    {
+        Some(version) => version,
+        None => return Ok(None),
+    };
+    let content =
+        serde_yaml::to_string(&config).context("failed to serialize the configuration")?;
+
+    if !in_place {
+        warn!(
+            "Upgraded `{}` from the version {} to {} in memory only: upgrade it by hand to stop \
+             migrating it on every start.",
+            path.display(),
+            version,
+            CONFIG_VERSION
+        );
+
+        return Ok(Some(content));
+    }
+
+    let backup_path = backup_path(path, version);
+
+    std::fs::copy(path, &backup_path)
+        .with_context(|| format!("failed to back `{}` up", path.display()))?;
+    write_atomic(path, &content)?;
+
+    info!(
+        "Upgraded `{}` from the version {} to {}: the original is kept as `{}`.",
+        path.display(),
+        version,
+        CONFIG_VERSION,
+        backup_path.display()
+    );
+
+    Ok(Some(content))
+}
+
+/// Run the migrations the configuration needs, and return the version it was
+/// upgraded from, if it was.
+fn migrate(config: &mut Mapping, migrations: &[Migration]) -> anyhow::Result<Option<u64>> {
+    let current = migrations.len() as u64 + 1;
+    let version = match config.get(VERSION_KEY) {
+        None => 1,
+        Some(version) => version
+            .as_u64()
+            .filter(|version| *version >= 1)
+            .ok_or_else(|| anyhow::anyhow!("invalid configuration version: {:?}", version))?,
+    };
+
+    if version > current {
+        anyhow::bail!(
+            "the configuration version {} is newer than the supported version {}",
+            version,
+            current
+        );
+    }
+
+    if version == current {
+        return Ok(None);
+    }
+
+    for (i, migration) in migrations.iter().enumerate().skip(version as usize - 1) {
+        migration(config)
+            .with_context(|| format!("failed to migrate from the version {}", i + 1))?;
+    }
+
+    config.insert(VERSION_KEY.into(), current.into());
+
+    Ok(Some(version))
+}
+
+fn backup_path(path: &Path, version: u64) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+
+    file_name.push(format!(".v{}.bak", version));
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,88 @@
+//! Tests of the migrations of the configuration, against example ones.
+
+use super::*;
+
+/// Rename `weather_entity` to `weather.entity`, as a version 2 could.
+fn nest_weather(config: &mut Mapping) -> anyhow::Result<()> {
+    if let Some(entity) = config.remove("weather_entity") {
+        let mut weather = Mapping::new();
+
+        weather.insert("entity".into(), entity);
+        config.insert("weather".into(), weather.into());
+    }
+
+    Ok(())
+}
+
+/// Rename `location` to `name`, as a version 3 could.
+fn rename_location(config: &mut Mapping) -> anyhow::Result<()> {
+    if let Some(location) = config.remove("location") {
+        config.insert("name".into(), location);
+    }
+
+    Ok(())
+}
+
+const EXAMPLE_MIGRATIONS: &[Migration] = &[nest_weather, rename_location];
+
+fn config(yaml: &str) -> Mapping {
+    serde_yaml::from_str(yaml).expect("invalid configuration")
+}
+
+#[test]
+fn migrate_runs_the_migrations_from_the_version() {
+    let mut unversioned = config("{location: Home, weather_entity: weather.home}");
+
+    assert_eq!(
+        migrate(&mut unversioned, EXAMPLE_MIGRATIONS).unwrap(),
+        Some(1)
+    );
+    assert_eq!(
+        unversioned,
+        config("{name: Home, weather: {entity: weather.home}, version: 3}")
+    );
+
+    // Only the last migration applies: the key is left as is.
+    let mut version_2 = config("{version: 2, location: Home, weather_entity: weather.home}");
+
+    assert_eq!(
+        migrate(&mut version_2, EXAMPLE_MIGRATIONS).unwrap(),
+        Some(2)
+    );
+    assert_eq!(
+        version_2,
+        config("{version: 3, name: Home, weather_entity: weather.home}")
+    );
+}
+
+#[test]
+fn migrate_leaves_the_current_version_alone() {
+    let mut current = config("{version: 3, location: Home}");
+
+    assert_eq!(migrate(&mut current, EXAMPLE_MIGRATIONS).unwrap(), None);
+    assert_eq!(current, config("{version: 3, location: Home}"));
+
+    let mut unversioned = config("{location: Home}");
+
+    assert_eq!(migrate(&mut unversioned, MIGRATIONS).unwrap(), None);
+    assert_eq!(unversioned, config("{location: Home}"));
+}
+
+#[test]
+fn migrate_rejects_the_unsupported_versions() {
+    for yaml in ["{version: 4}", "{version: 0}", "{version: latest}"] {
+        assert!(
+            migrate(&mut config(yaml), EXAMPLE_MIGRATIONS).is_err(),
+            "{}",
+            yaml
+        );
+    }
+}
+
+#[test]
+fn backup_path_appends_the_version() {
+    assert_eq!(
+        backup_path(Path::new("/etc/home-control/config.yaml"), 2),
+        Path::new("/etc/home-control/config.yaml.v2.bak")
+    );
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::{
+    context::AppContext,
+    home_assistant::State,
+    outputs::{DisconnectPolicy, Output},
+    tasks,
+};
+
+/// Mirrors the state of an entity on a local output, like an indicator light.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MirrorConfig {
+    /// The entity to mirror, like `binary_sensor.garage_open`.
+    pub entity_id: String,
+
+    /// The output to drive.
+    pub output: Output,
+
+    /// The states that turn the output on. Defaults to `on`-like states.
+    #[serde(default)]
+    pub on_states: Vec<String>,
+
+    /// Turn the output on when the entity is off, instead.
+    #[serde(default)]
+    pub invert: bool,
+
+    /// What to do with the output when the connection to Home Assistant is
+    /// lost for too long.
+    #[serde(default = "MirrorConfig::default_on_disconnect")]
+    pub on_disconnect: DisconnectPolicy,
+}
+
+impl MirrorConfig {
+    fn default_on_disconnect() -> DisconnectPolicy {
+        DisconnectPolicy::Off
+    }
+
+    /// Get the output status for a state of the entity.
+    ///
+    /// Outputs are off while the entity is unavailable, regardless of
+    /// `invert`.
+    fn output_status(&self, state: Option<State>) -> bool {
+        let on = match state {
+            Some(state) if !state.is_available() => return false,
+            Some(state) if !self.on_states.is_empty() => self.on_states.contains(&state.state),
+            Some(state) => Option::<bool>::from(state).unwrap_or_default(),
+            None => return false,
+        };
+
+        on != self.invert
+    }
+}
+
+/// Keep the outputs in sync with the mirrored entities forever.
+///
+/// The outputs are held while the connection to Home Assistant is lost, until
+/// the disconnect timeout elapses and their `on_disconnect` policy applies.
+pub async fn run(mirrors: &[MirrorConfig], context: &AppContext) -> anyhow::Result<()> {
+    let timeout = context.config.disconnect_timeout;
+    let mut states = context.home_assistant.watch_states();
+    let mut current = HashMap::new();
+
+    loop {
+        match context.home_assistant.disconnected_for() {
+            None => {
+                for (i, mirror) in mirrors.iter().enumerate() {
+                    let status = mirror
+                        .output_status(context.home_assistant.entity(&mirror.entity_id).await);
+
+                    set(context, &mut current, i, mirror, status);
+                }
+            }
+            Some(elapsed) if elapsed >= timeout => {
+                for (i, mirror) in mirrors.iter().enumerate() {
+                    if let Some(status) = mirror.on_disconnect.status() {
+                        set(context, &mut current, i, mirror, status);
+                    }
+                }
+            }
+            Some(elapsed) => {
+                tokio::select! {
+                    r = states.changed() => r?,
+                    _ = tokio::time::sleep(timeout - elapsed) => {}
+                }
+
+                continue;
+            }
+        }
+
+        states.changed().await?;
+        tasks::heartbeat();
+    }
+}
+
+fn set(
+    context: &AppContext,
+    current: &mut HashMap<usize, bool>,
+    i: usize,
+    mirror: &MirrorConfig,
+    status: bool,
+) {
+    if current.insert(i, status) == Some(status) {
+        return;
+    }
+
+    info!(
+        "Mirroring `{}` on {:?}: {}.",
+        mirror.entity_id, mirror.output, status
+    );
+
+    if let Err(err) = mirror.output.set(&context.gpio, status) {
+        warn!("Failed to set {:?}: {}", mirror.output, err);
+
+        current.remove(&i);
+    }
+}
@@ -0,0 +1,377 @@
+pub mod zigbee2mqtt;
+
+#[cfg(feature = "mqtt")]
+use std::time::Duration;
+
+#[cfg(feature = "mqtt")]
+use log::{info, warn};
+#[cfg(feature = "mqtt")]
+use rumqttc::{AsyncClient, Event, EventLoop, LastWill, MqttOptions, Packet, QoS};
+use serde::Deserialize;
+#[cfg(feature = "mqtt")]
+use serde_json::json;
+#[cfg(feature = "mqtt")]
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+
+use self::zigbee2mqtt::{Zigbee2Mqtt, Zigbee2MqttConfig};
+#[cfg(feature = "mqtt")]
+use crate::screen::ScreenState;
+use crate::{context::AppContext, tasks};
+
+/// The MQTT configuration, to expose the panel to Home-Assistant through
+/// MQTT discovery.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MqttConfig {
+    pub host: String,
+
+    #[serde(default = "MqttConfig::default_port")]
+    pub port: u16,
+
+    #[serde(default)]
+    pub username: Option<String>,
+
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// The discovery prefix Home-Assistant listens to.
+    #[serde(default = "MqttConfig::default_discovery_prefix")]
+    pub discovery_prefix: String,
+
+    /// The unique id of the panel, which must differ between panels.
+    #[serde(default = "MqttConfig::default_node_id")]
+    pub node_id: String,
+
+    /// The name of the panel device in Home-Assistant.
+    #[serde(default = "MqttConfig::default_name")]
+    pub name: String,
+
+    /// The Zigbee2MQTT instance on the broker, whose devices are then
+    /// controlled directly.
+    #[serde(default)]
+    pub zigbee2mqtt: Option<Zigbee2MqttConfig>,
+}
+
+impl MqttConfig {
+    fn default_port() -> u16 {
+        1883
+    }
+
+    fn default_discovery_prefix() -> String {
+        "homeassistant".to_string()
+    }
+
+    fn default_node_id() -> String {
+        "home_control".to_string()
+    }
+
+    fn default_name() -> String {
+        "Home control".to_string()
+    }
+}
+
+#[cfg(feature = "mqtt")]
+impl MqttConfig {
+    fn topic(&self, path: &str) -> String {
+        format!("home-control/{}/{}", self.node_id, path)
+    }
+
+    /// Get the discovery messages of the screen, the buzzer and the presence
+    /// sensor.
+    fn discovery_messages(&self) -> Vec<(String, serde_json::Value)> {
+        let device = json!({
+            "identifiers": [self.node_id],
+            "name": self.name,
+            "manufacturer": "home-control",
+            "sw_version": env!("CARGO_PKG_VERSION"),
+        });
+        let availability_topic = self.topic("status");
+
+        [
+            (
+                "light",
+                "screen",
+                json!({
+                    "name": "Screen",
+                    "schema": "json",
+                    "brightness": true,
+                    "command_topic": self.topic("screen/set"),
+                    "state_topic": self.topic("screen/state"),
+                }),
+            ),
+            (
+                "siren",
+                "buzzer",
+                json!({
+                    "name": "Buzzer",
+                    "command_topic": self.topic("buzzer/set"),
+                    "state_topic": self.topic("buzzer/state"),
+                }),
+            ),
+            (
+                "binary_sensor",
+                "presence",
+                json!({
+                    "name": "Presence",
+                    "device_class": "occupancy",
+                    "state_topic": self.topic("presence/state"),
+                }),
+            ),
+        ]
+        .into_iter()
+        .map(|(component, object_id, mut payload)| {
+            payload["unique_id"] = json!(format!("{}_{}", self.node_id, object_id));
+            payload["availability_topic"] = json!(availability_topic);
+            payload["device"] = device.clone();
+
+            (
+                format!(
+                    "{}/{}/{}/{}/config",
+                    self.discovery_prefix, component, self.node_id, object_id
+                ),
+                payload,
+            )
+        })
+        .collect()
+    }
+
+    /// Register the panel to Home-Assistant and keep its state up to date
+    /// forever, and track the Zigbee2MQTT devices, if any.
+    pub async fn run(
+        &self,
+        context: &AppContext,
+        presence: watch::Receiver<bool>,
+        zigbee: Option<&Zigbee2Mqtt>,
+    ) -> anyhow::Result<()> {
+        let mut options = MqttOptions::new(&self.node_id, &self.host, self.port);
+
+        options
+            .set_keep_alive(Duration::from_secs(30))
+            .set_last_will(LastWill::new(
+                self.topic("status"),
+                "offline",
+                QoS::AtLeastOnce,
+                true,
+            ));
+
+        if let Some(username) = &self.username {
+            options.set_credentials(username, self.password.as_deref().unwrap_or_default());
+        }
+
+        let (client, event_loop) = AsyncClient::new(options, 16);
+        let (connected_tx, connected) = watch::channel(false);
+        let (buzzer_tx, buzzer) = watch::channel(false);
+
+        // The event loop must keep being polled for the publications to go
+        // out, so commands are handled apart from the states publication.
+        tokio::select! {
+            r = self.poll(context, event_loop, connected_tx, buzzer_tx, zigbee) => r,
+            r = self.publish_states(&client, connected, context.screen.watch(), buzzer, presence, zigbee) => r,
+            r = Self::forward_publications(&client, zigbee) => r,
+        }
+    }
+
+    /// Poll the connection, handling the commands.
+    async fn poll(
+        &self,
+        context: &AppContext,
+        mut event_loop: EventLoop,
+        connected: watch::Sender<bool>,
+        buzzer: watch::Sender<bool>,
+        zigbee: Option<&Zigbee2Mqtt>,
+    ) -> anyhow::Result<()> {
+        loop {
+            match event_loop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    info!("Connected to the MQTT broker at `{}`.", self.host);
+
+                    connected.send_replace(true);
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    tasks::heartbeat();
+
+                    if let Some(zigbee) = zigbee {
+                        if zigbee.handle(&publish.topic, &publish.payload).await {
+                            continue;
+                        }
+                    }
+
+                    if let Err(err) =
+                        self.handle_command(context, &buzzer, &publish.topic, &publish.payload)
+                    {
+                        warn!(
+                            "Failed to handle the MQTT command on `{}`: {}",
+                            publish.topic, err
+                        );
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    warn!("MQTT connection error: {}", err);
+
+                    connected.send_replace(false);
+
+                    // The event loop reconnects on the next poll.
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+        }
+    }
+
+    /// Register the entities on every connection, and publish their states
+    /// whenever they change.
+    async fn publish_states(
+        &self,
+        client: &AsyncClient,
+        mut connected: watch::Receiver<bool>,
+        mut screen: watch::Receiver<ScreenState>,
+        mut buzzer: watch::Receiver<bool>,
+        mut presence: watch::Receiver<bool>,
+        zigbee: Option<&Zigbee2Mqtt>,
+    ) -> anyhow::Result<()> {
+        loop {
+            if !*connected.borrow_and_update() {
+                connected.changed().await?;
+
+                continue;
+            }
+
+            for (topic, payload) in self.discovery_messages() {
+                client
+                    .publish(topic, QoS::AtLeastOnce, true, payload.to_string())
+                    .await?;
+            }
+
+            for topic in ["screen/set", "buzzer/set"] {
+                client
+                    .subscribe(self.topic(topic), QoS::AtLeastOnce)
+                    .await?;
+            }
+
+            if let Some(zigbee) = zigbee {
+                client
+                    .subscribe(zigbee.topic_filter(), QoS::AtLeastOnce)
+                    .await?;
+            }
+
+            client
+                .publish(self.topic("status"), QoS::AtLeastOnce, true, "online")
+                .await?;
+
+            while *connected.borrow() {
+                let screen_state = *screen.borrow_and_update();
+                let states = [
+                    (
+                        "screen/state",
+                        json!({
+                            "state": on_off(screen_state.on),
+                            "brightness": screen_state.brightness,
+                        })
+                        .to_string(),
+                    ),
+                    (
+                        "buzzer/state",
+                        on_off(*buzzer.borrow_and_update()).to_string(),
+                    ),
+                    (
+                        "presence/state",
+                        on_off(*presence.borrow_and_update()).to_string(),
+                    ),
+                ];
+
+                for (topic, payload) in states {
+                    client
+                        .publish(self.topic(topic), QoS::AtLeastOnce, true, payload)
+                        .await?;
+                }
+
+                tokio::select! {
+                    r = connected.changed() => r?,
+                    r = screen.changed() => r?,
+                    r = buzzer.changed() => r?,
+                    r = presence.changed() => r?,
+                }
+            }
+        }
+    }
+
+    /// Publish the commands of the Zigbee2MQTT devices, if any.
+    async fn forward_publications(
+        client: &AsyncClient,
+        zigbee: Option<&Zigbee2Mqtt>,
+    ) -> anyhow::Result<()> {
+        let mut publications = match zigbee {
+            Some(zigbee) => zigbee.subscribe_publications(),
+            None => return std::future::pending().await,
+        };
+
+        loop {
+            match publications.recv().await {
+                Ok((topic, payload)) => {
+                    client
+                        .publish(topic, QoS::AtLeastOnce, false, payload)
+                        .await?
+                }
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+
+    fn handle_command(
+        &self,
+        context: &AppContext,
+        buzzer: &watch::Sender<bool>,
+        topic: &str,
+        payload: &[u8],
+    ) -> anyhow::Result<()> {
+        if topic == self.topic("screen/set") {
+            let command: LightCommand = serde_json::from_slice(payload)?;
+
+            if let Some(brightness) = command.brightness {
+                context.screen.set_brightness(brightness)?;
+            }
+
+            // Like from the API, the presence detection must not undo it.
+            context.screen.force_on(command.state == "ON")?;
+        } else if topic == self.topic("buzzer/set") {
+            let on = payload == b"ON";
+
+            context.gpio.set_buzzer(on)?;
+            buzzer.send_replace(on);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "mqtt"))]
+impl MqttConfig {
+    pub async fn run(
+        &self,
+        _context: &AppContext,
+        _presence: watch::Receiver<bool>,
+        _zigbee: Option<&Zigbee2Mqtt>,
+    ) -> anyhow::Result<()> {
+        tasks::unsupported("MQTT", "mqtt").await
+    }
+}
+
+/// A command of a light using the JSON schema.
+#[cfg(feature = "mqtt")]
+#[derive(Debug, Deserialize)]
+struct LightCommand {
+    state: String,
+
+    #[serde(default)]
+    brightness: Option<u8>,
+}
+
+#[cfg(feature = "mqtt")]
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
@@ -0,0 +1,235 @@
+//! The devices of Zigbee2MQTT, read and controlled through the broker
+//! directly, so that they keep working while Home-Assistant is down.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, RwLock};
+
+/// The Zigbee2MQTT configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Zigbee2MqttConfig {
+    /// The base topic of Zigbee2MQTT.
+    #[serde(default = "Zigbee2MqttConfig::default_base_topic")]
+    pub base_topic: String,
+}
+
+impl Zigbee2MqttConfig {
+    fn default_base_topic() -> String {
+        "zigbee2mqtt".to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ZigbeeDevice {
+    /// The name of the device in Zigbee2MQTT, like `kitchen/lamp`.
+    pub friendly_name: String,
+
+    /// The address of the device, like `0x00158d0001a2b3c4`, as used in the
+    /// API path.
+    pub ieee_address: String,
+    pub model: Option<String>,
+
+    /// Whether the device is reachable, if Zigbee2MQTT tracks it.
+    pub available: Option<bool>,
+
+    /// The last state reported, like `{"state": "ON", "brightness": 254}`.
+    pub state: Value,
+    pub last_update: Option<DateTime<Utc>>,
+}
+
+impl ZigbeeDevice {
+    /// Get whether the device is on, if it reports so and is reachable.
+    pub fn is_on(&self) -> Option<bool> {
+        if self.available == Some(false) {
+            return None;
+        }
+
+        match self.state.get("state")?.as_str()? {
+            "ON" => Some(true),
+            "OFF" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// A device of the bridge list, as published by Zigbee2MQTT.
+#[derive(Debug, Deserialize)]
+struct BridgeDevice {
+    friendly_name: String,
+    ieee_address: String,
+
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+
+    #[serde(default)]
+    definition: Option<BridgeDefinition>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BridgeDefinition {
+    #[serde(default)]
+    model: Option<String>,
+}
+
+/// The devices of Zigbee2MQTT, as last reported on the broker.
+pub struct Zigbee2Mqtt {
+    config: Zigbee2MqttConfig,
+    devices: RwLock<BTreeMap<String, ZigbeeDevice>>,
+
+    /// The publications to make, by topic.
+    publications: broadcast::Sender<(String, String)>,
+}
+
+impl Zigbee2Mqtt {
+    pub fn new(config: Zigbee2MqttConfig) -> Self {
+        Self {
+            config,
+            devices: RwLock::default(),
+            publications: broadcast::channel(16).0,
+        }
+    }
+
+    /// The topics to subscribe to.
+    pub fn topic_filter(&self) -> String {
+        format!("{}/#", self.config.base_topic)
+    }
+
+    pub async fn devices(&self) -> Vec<ZigbeeDevice> {
+        self.devices.read().await.values().cloned().collect()
+    }
+
+    /// Get a device, by friendly name or IEEE address.
+    pub async fn device(&self, id: &str) -> Option<ZigbeeDevice> {
+        self.devices
+            .read()
+            .await
+            .values()
+            .find(|device| device.friendly_name == id || device.ieee_address == id)
+            .cloned()
+    }
+
+    /// Watch the publications to make, while connected.
+    pub fn subscribe_publications(&self) -> broadcast::Receiver<(String, String)> {
+        self.publications.subscribe()
+    }
+
+    /// Send a command to a device, by friendly name or IEEE address, like
+    /// `{"state": "ON"}`.
+    pub async fn set(&self, id: &str, command: &Value) -> anyhow::Result<()> {
+        let device = self
+            .device(id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("unknown Zigbee device `{}`", id))?;
+
+        self.publications
+            .send((
+                format!("{}/{}/set", self.config.base_topic, device.friendly_name),
+                command.to_string(),
+            ))
+            .map_err(|_| anyhow::anyhow!("the MQTT connection is not running"))?;
+
+        Ok(())
+    }
+
+    /// Turn a device on or off.
+    pub async fn set_on(&self, id: &str, on: bool) -> anyhow::Result<()> {
+        self.set(id, &json!({ "state": if on { "ON" } else { "OFF" } }))
+            .await
+    }
+
+    /// Handle a publication, returning `false` if it is not a topic of
+    /// Zigbee2MQTT.
+    pub async fn handle(&self, topic: &str, payload: &[u8]) -> bool {
+        let path = match topic
+            .strip_prefix(&self.config.base_topic)
+            .and_then(|path| path.strip_prefix('/'))
+        {
+            Some(path) => path,
+            None => return false,
+        };
+
+        if let Err(err) = self.handle_path(path, payload).await {
+            warn!("Invalid Zigbee2MQTT message on `{}`: {}", topic, err);
+        }
+
+        true
+    }
+
+    async fn handle_path(&self, path: &str, payload: &[u8]) -> anyhow::Result<()> {
+        let mut devices = self.devices.write().await;
+
+        if path == "bridge/devices" {
+            let listed: Vec<BridgeDevice> = serde_json::from_slice(payload)?;
+            let mut previous = std::mem::take(&mut *devices);
+
+            // The coordinator is not a device of the household.
+            for device in listed
+                .into_iter()
+                .filter(|device| device.kind.as_deref() != Some("Coordinator"))
+            {
+                let known = previous.remove(&device.friendly_name);
+
+                devices.insert(
+                    device.friendly_name.clone(),
+                    ZigbeeDevice {
+                        friendly_name: device.friendly_name,
+                        ieee_address: device.ieee_address,
+                        model: device.definition.and_then(|definition| definition.model),
+                        available: known.as_ref().and_then(|known| known.available),
+                        state: known
+                            .as_ref()
+                            .map(|known| known.state.clone())
+                            .unwrap_or(Value::Null),
+                        last_update: known.and_then(|known| known.last_update),
+                    },
+                );
+            }
+
+            debug!("Zigbee2MQTT lists {} devices.", devices.len());
+
+            return Ok(());
+        }
+
+        // The other messages of the bridge, like its logs, are left alone.
+        if path.starts_with("bridge/") {
+            return Ok(());
+        }
+
+        if let Some(device) = path
+            .strip_suffix("/availability")
+            .and_then(|friendly_name| devices.get_mut(friendly_name))
+        {
+            // Either `online`, or `{"state": "online"}` since 1.32.
+            let state = match serde_json::from_slice::<Value>(payload) {
+                Ok(Value::Object(object)) => object
+                    .get("state")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+                _ => String::from_utf8_lossy(payload).into_owned(),
+            };
+
+            device.available = Some(state == "online");
+        } else if let Some(device) = devices.get_mut(path) {
+            let state: Value = serde_json::from_slice(payload)?;
+
+            if !state.is_object() {
+                anyhow::bail!("the state is not an object");
+            }
+
+            device.state = state;
+            device.last_update = Some(Utc::now());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
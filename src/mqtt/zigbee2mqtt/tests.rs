@@ -0,0 +1,108 @@
+//! Tests of the tracking of the Zigbee2MQTT devices.
+
+use super::*;
+
+async fn bridge() -> Zigbee2Mqtt {
+    let zigbee = Zigbee2Mqtt::new(Zigbee2MqttConfig {
+        base_topic: "zigbee2mqtt".to_string(),
+    });
+
+    assert!(
+        zigbee
+            .handle(
+                "zigbee2mqtt/bridge/devices",
+                json!([
+                    { "friendly_name": "Coordinator", "ieee_address": "0x00", "type": "Coordinator" },
+                    {
+                        "friendly_name": "kitchen/lamp",
+                        "ieee_address": "0x01",
+                        "type": "Router",
+                        "definition": { "model": "LED1623G12" },
+                    },
+                ])
+                .to_string()
+                .as_bytes(),
+            )
+            .await
+    );
+
+    zigbee
+}
+
+#[tokio::test]
+async fn lists_the_devices_but_the_coordinator() {
+    let zigbee = bridge().await;
+    let devices = zigbee.devices().await;
+
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].friendly_name, "kitchen/lamp");
+    assert_eq!(devices[0].model.as_deref(), Some("LED1623G12"));
+}
+
+#[tokio::test]
+async fn tracks_the_states_and_the_availability() {
+    let zigbee = bridge().await;
+
+    zigbee
+        .handle(
+            "zigbee2mqtt/kitchen/lamp",
+            br#"{"state": "ON", "brightness": 254}"#,
+        )
+        .await;
+
+    assert_eq!(
+        zigbee.device("kitchen/lamp").await.unwrap().is_on(),
+        Some(true)
+    );
+
+    zigbee
+        .handle(
+            "zigbee2mqtt/kitchen/lamp/availability",
+            br#"{"state": "offline"}"#,
+        )
+        .await;
+
+    assert_eq!(zigbee.device("kitchen/lamp").await.unwrap().is_on(), None);
+
+    zigbee
+        .handle("zigbee2mqtt/kitchen/lamp/availability", b"online")
+        .await;
+
+    assert_eq!(
+        zigbee.device("kitchen/lamp").await.unwrap().is_on(),
+        Some(true)
+    );
+}
+
+#[tokio::test]
+async fn keeps_the_states_when_listed_again() {
+    let zigbee = bridge().await;
+
+    zigbee
+        .handle("zigbee2mqtt/kitchen/lamp", br#"{"state": "OFF"}"#)
+        .await;
+
+    zigbee
+        .handle(
+            "zigbee2mqtt/bridge/devices",
+            br#"[{"friendly_name": "kitchen/lamp", "ieee_address": "0x01"}]"#,
+        )
+        .await;
+
+    assert_eq!(
+        zigbee.device("kitchen/lamp").await.unwrap().is_on(),
+        Some(false)
+    );
+}
+
+#[tokio::test]
+async fn ignores_the_other_topics() {
+    let zigbee = bridge().await;
+
+    assert!(!zigbee.handle("home-control/panel/screen/set", b"{}").await);
+    assert!(zigbee.handle("zigbee2mqtt/bridge/logging", b"{}").await);
+    assert!(zigbee.set_on("unknown", true).await.is_err());
+
+    // Nothing publishes them without the MQTT connection.
+    assert!(zigbee.set_on("kitchen/lamp", true).await.is_err());
+}
@@ -0,0 +1,139 @@
+use chrono::Utc;
+use warp::{
+    http::{header, HeaderValue},
+    hyper,
+};
+
+/// The field of the JSON objects holding the time of the response.
+const GENERATED_AT_FIELD: &str = "generatedAt";
+
+/// The layout of the JSON responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonLayout {
+    Compact,
+    Pretty,
+}
+
+impl JsonLayout {
+    /// Negotiate the layout of the responses to a request.
+    ///
+    /// The `pretty` query parameter, like `?pretty=true`, wins. Otherwise, the
+    /// clients asking for text rather than JSON, like the browsers showing an
+    /// endpoint, get indented JSON, and the others, like the frontend,
+    /// compact JSON.
+    pub fn negotiate<B>(request: &hyper::Request<B>) -> Self {
+        let pretty = request.uri().query().and_then(|query| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.strip_prefix("pretty"))
+                .find_map(|value| match value {
+                    "" | "=true" | "=1" => Some(true),
+                    "=false" | "=0" => Some(false),
+                    _ => None,
+                })
+        });
+
+        let pretty = pretty.unwrap_or_else(|| {
+            let accepted: Vec<&str> = request
+                .headers()
+                .get_all(header::ACCEPT)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .filter_map(|range| range.split(';').next())
+                .map(str::trim)
+                .collect();
+
+            !accepted
+                .iter()
+                .any(|range| range.eq_ignore_ascii_case("application/json"))
+                && accepted
+                    .iter()
+                    .any(|range| range.len() > 5 && range[..5].eq_ignore_ascii_case("text/"))
+        });
+
+        if pretty {
+            Self::Pretty
+        } else {
+            Self::Compact
+        }
+    }
+}
+
+/// Lay the JSON responses out, and stamp their objects with the time they
+/// were generated at, as `generatedAt`.
+///
+/// The other responses, like the streams of events, are left alone.
+pub async fn format(
+    response: hyper::Response<hyper::Body>,
+    layout: JsonLayout,
+) -> hyper::Response<hyper::Body> {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match hyper::body::to_bytes(body).await {
+        Ok(bytes) => stamp(&bytes),
+        Err(_) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+
+            return hyper::Response::from_parts(parts, hyper::Body::empty());
+        }
+    };
+
+    let bytes = match layout {
+        JsonLayout::Compact => bytes,
+        JsonLayout::Pretty => {
+            let mut pretty = serde_json::Serializer::pretty(Vec::new());
+
+            match serde_transcode::transcode(
+                &mut serde_json::Deserializer::from_slice(&bytes),
+                &mut pretty,
+            ) {
+                Ok(()) => pretty.into_inner(),
+                Err(_) => bytes,
+            }
+        }
+    };
+
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(bytes.len()));
+
+    hyper::Response::from_parts(parts, bytes.into())
+}
+
+/// Add the `generatedAt` field first to a JSON object, as is otherwise.
+fn stamp(json: &[u8]) -> Vec<u8> {
+    let start = json.iter().position(|b| !b.is_ascii_whitespace());
+
+    let rest = match start {
+        Some(start) if json[start] == b'{' => &json[start + 1..],
+        _ => return json.to_vec(),
+    };
+
+    let is_empty = rest
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_none_or(|b| *b == b'}');
+    let mut stamped = format!(
+        "{{\"{}\":\"{}\"{}",
+        GENERATED_AT_FIELD,
+        Utc::now().to_rfc3339(),
+        if is_empty { "" } else { "," }
+    )
+    .into_bytes();
+
+    stamped.extend_from_slice(rest);
+    stamped
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,102 @@
+//! Tests of the negotiation and the formatting of the JSON responses.
+
+use super::*;
+
+fn request(uri: &str, accept: Option<&'static str>) -> hyper::Request<()> {
+    let mut request = hyper::Request::builder().uri(uri);
+
+    if let Some(accept) = accept {
+        request = request.header(header::ACCEPT, accept);
+    }
+
+    request.body(()).unwrap()
+}
+
+fn json_response(body: &'static str) -> hyper::Response<hyper::Body> {
+    hyper::Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::CONTENT_LENGTH, body.len())
+        .body(body.into())
+        .unwrap()
+}
+
+async fn body(response: hyper::Response<hyper::Body>) -> String {
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[test]
+fn negotiate_prefers_the_query() {
+    for (uri, accept, layout) in [
+        ("/api/v1/status?pretty=true", None, JsonLayout::Pretty),
+        ("/api/v1/status?a=b&pretty", None, JsonLayout::Pretty),
+        (
+            "/api/v1/status?pretty=false",
+            Some("text/html"),
+            JsonLayout::Compact,
+        ),
+        ("/api/v1/status?prettier=1", None, JsonLayout::Compact),
+    ] {
+        assert_eq!(
+            JsonLayout::negotiate(&request(uri, accept)),
+            layout,
+            "{}",
+            uri
+        );
+    }
+}
+
+#[test]
+fn negotiate_pretty_prints_for_the_text_clients() {
+    for (accept, layout) in [
+        (None, JsonLayout::Compact),
+        (Some("*/*"), JsonLayout::Compact),
+        (Some("Application/json"), JsonLayout::Compact),
+        (
+            Some("text/html,application/xhtml+xml,*/*;q=0.8"),
+            JsonLayout::Pretty,
+        ),
+        (Some("text/plain, application/json"), JsonLayout::Compact),
+    ] {
+        assert_eq!(
+            JsonLayout::negotiate(&request("/api/v1/status", accept)),
+            layout,
+            "{:?}",
+            accept
+        );
+    }
+}
+
+#[tokio::test]
+async fn format_stamps_the_objects() {
+    let response = format(json_response(r#"{"on":true}"#), JsonLayout::Compact).await;
+    let value: serde_json::Value = serde_json::from_str(&body(response).await).unwrap();
+
+    assert_eq!(value["on"], true);
+    assert!(value[GENERATED_AT_FIELD].is_string());
+
+    let response = format(json_response("[1,2]"), JsonLayout::Pretty).await;
+
+    assert_eq!(body(response).await, "[\n  1,\n  2\n]");
+
+    let response = format(json_response(r#"{"b":1,"a":2}"#), JsonLayout::Compact).await;
+
+    assert!(body(response).await.ends_with(r#","b":1,"a":2}"#));
+
+    let response = format(json_response(" {} "), JsonLayout::Compact).await;
+    let value: serde_json::Value = serde_json::from_str(&body(response).await).unwrap();
+
+    assert_eq!(value.as_object().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn format_leaves_the_other_responses_alone() {
+    let response = format(
+        hyper::Response::new(hyper::Body::from("data: {}\n\n")),
+        JsonLayout::Pretty,
+    )
+    .await;
+
+    assert_eq!(body(response).await, "data: {}\n\n");
+}
@@ -0,0 +1,250 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::net::{lookup_host, TcpStream, UdpSocket};
+
+use crate::{
+    home_assistant::Controller,
+    network,
+    self_check::{check, CheckResult},
+};
+
+/// The number of seconds between the NTP epoch (1900) and the Unix one.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// The network diagnostics configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetCheckConfig {
+    /// The NTP server to measure the clock offset against, as `host:port`.
+    #[serde(default = "NetCheckConfig::default_ntp_server")]
+    pub ntp_server: String,
+
+    /// The clock offset above which the NTP check fails.
+    #[serde(default = "NetCheckConfig::default_max_clock_offset")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub max_clock_offset: Duration,
+
+    /// The timeout of each check.
+    #[serde(default = "NetCheckConfig::default_timeout")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub timeout: Duration,
+}
+
+impl Default for NetCheckConfig {
+    fn default() -> Self {
+        Self {
+            ntp_server: Self::default_ntp_server(),
+            max_clock_offset: Self::default_max_clock_offset(),
+            timeout: Self::default_timeout(),
+        }
+    }
+}
+
+impl NetCheckConfig {
+    fn default_ntp_server() -> String {
+        "pool.ntp.org:123".to_string()
+    }
+
+    fn default_max_clock_offset() -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn default_timeout() -> Duration {
+        Duration::from_secs(3)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NetCheckReport {
+    pub finished_at: DateTime<Utc>,
+    pub passed: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+/// Run the network diagnostics, from the gateway to Home-Assistant.
+pub async fn run(
+    config: &NetCheckConfig,
+    interface: &str,
+    home_assistant: &Controller,
+) -> NetCheckReport {
+    info!("Running the network diagnostics...");
+
+    let url = home_assistant.rest_url();
+    let (gateway, home_assistant_checks, ntp) = tokio::join!(
+        timeout(config, check_gateway(interface)),
+        check_home_assistant(config, url),
+        timeout(config, check_ntp(config)),
+    );
+
+    let mut checks = vec![check("gateway", gateway)];
+    checks.extend(home_assistant_checks);
+    checks.push(check("ntp", ntp));
+
+    let report = NetCheckReport {
+        finished_at: Utc::now(),
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+    };
+
+    for check in report.checks.iter().filter(|check| !check.passed) {
+        warn!(
+            "Network check `{}` failed: {}",
+            check.name,
+            check.details.as_deref().unwrap_or("unknown error")
+        );
+    }
+
+    report
+}
+
+async fn timeout<T>(
+    config: &NetCheckConfig,
+    check: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    tokio::time::timeout(config.timeout, check)
+        .await
+        .map_err(|_| anyhow::anyhow!("timed out after {:?}", config.timeout))?
+}
+
+async fn check_gateway(interface: &str) -> anyhow::Result<Option<String>> {
+    let gateway = network::default_gateway(interface)
+        .await
+        .with_context(|| format!("no default gateway on interface `{}`", interface))?;
+
+    anyhow::ensure!(
+        network::ping(gateway).await,
+        "gateway {} is not reachable",
+        gateway
+    );
+
+    Ok(Some(gateway.to_string()))
+}
+
+/// Check the resolution of the Home-Assistant host, the connection to it
+/// and the TLS handshake, in that order as each depends on the previous one.
+pub(crate) async fn check_home_assistant(
+    config: &NetCheckConfig,
+    url: &url::Url,
+) -> Vec<CheckResult> {
+    let dns = timeout(config, async {
+        let host = url.host_str().context("no host in the endpoint")?;
+        let port = url.port_or_known_default().unwrap_or(443);
+        let addresses: Vec<SocketAddr> = lookup_host((host, port))
+            .await
+            .with_context(|| format!("failed to resolve `{}`", host))?
+            .collect();
+
+        anyhow::ensure!(!addresses.is_empty(), "no address for `{}`", host);
+
+        Ok(addresses)
+    })
+    .await;
+
+    let addresses = match dns {
+        Ok(addresses) => addresses,
+        Err(err) => return vec![check("dns", Err(err))],
+    };
+
+    let mut checks = vec![check(
+        "dns",
+        Ok(Some(
+            addresses
+                .iter()
+                .map(|address| address.ip().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )),
+    )];
+
+    let tcp = timeout(config, async {
+        let started = Instant::now();
+
+        TcpStream::connect(addresses.as_slice())
+            .await
+            .context("failed to connect")?;
+
+        Ok(Some(format!("{} ms", started.elapsed().as_millis())))
+    })
+    .await;
+    let connected = tcp.is_ok();
+
+    checks.push(check("home_assistant_tcp", tcp));
+
+    if connected {
+        // Any response, even unauthorized, proves the handshake succeeded.
+        let tls = reqwest::Client::new()
+            .head(url.clone())
+            .timeout(config.timeout)
+            .send()
+            .await
+            .map(|response| Some(format!("HTTP {}", response.status())))
+            .context("TLS handshake failed");
+
+        checks.push(check("home_assistant_tls", tls));
+    }
+
+    checks
+}
+
+/// Measure the offset of the local clock with a SNTP request.
+async fn check_ntp(config: &NetCheckConfig) -> anyhow::Result<Option<String>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+
+    socket
+        .connect(&config.ntp_server)
+        .await
+        .with_context(|| format!("failed to resolve `{}`", config.ntp_server))?;
+
+    // Version 3, client mode.
+    let mut packet = [0u8; 48];
+    packet[0] = 0x1b;
+
+    let sent = unix_seconds(Utc::now());
+    socket.send(&packet).await?;
+
+    let size = socket.recv(&mut packet).await?;
+    let received = unix_seconds(Utc::now());
+
+    anyhow::ensure!(size == packet.len(), "truncated NTP response");
+
+    let server_received = parse_ntp_timestamp(&packet[32..40]);
+    let server_sent = parse_ntp_timestamp(&packet[40..48]);
+    let offset = ((server_received - sent) + (server_sent - received)) / 2.0;
+
+    anyhow::ensure!(
+        offset.abs() <= config.max_clock_offset.as_secs_f64(),
+        "the clock is off by {:.3} s",
+        offset
+    );
+
+    Ok(Some(format!("{:.3} s", offset)))
+}
+
+fn unix_seconds(time: DateTime<Utc>) -> f64 {
+    time.timestamp() as f64 + f64::from(time.timestamp_subsec_nanos()) * 1e-9
+}
+
+/// Parse a NTP timestamp, as seconds since the Unix epoch.
+///
+/// Timestamps are 32 bits of seconds since 1900 followed by 32 bits of
+/// fraction, in network order.
+fn parse_ntp_timestamp(bytes: &[u8]) -> f64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+
+    (u64::from(seconds) as f64 - NTP_UNIX_OFFSET as f64) + f64::from(fraction) / 4_294_967_296.0
+}
+
+#[cfg(test)]
+mod tests;
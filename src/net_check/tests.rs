@@ -0,0 +1,13 @@
+//! Tests of the parsing of the NTP responses.
+
+use super::*;
+
+#[test]
+fn parses_ntp_timestamps() {
+    // 2024-01-01T00:00:00.5Z
+    let seconds = (1_704_067_200 + NTP_UNIX_OFFSET) as u32;
+    let mut bytes = seconds.to_be_bytes().to_vec();
+    bytes.extend(0x8000_0000u32.to_be_bytes());
+
+    assert_eq!(parse_ntp_timestamp(&bytes), 1_704_067_200.5);
+}
@@ -0,0 +1,229 @@
+use std::{net::Ipv4Addr, process::Stdio, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::{process::Command, sync::RwLock};
+
+use crate::tasks;
+
+/// The network monitoring configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetworkConfig {
+    /// The network interface to monitor.
+    #[serde(default = "NetworkConfig::default_interface")]
+    pub interface: String,
+
+    /// The interval between refreshes.
+    #[serde(default = "NetworkConfig::default_refresh_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub refresh_interval: Duration,
+
+    /// The `input_number` entity to report the Wi-Fi signal strength to, if
+    /// any.
+    #[serde(default)]
+    pub ha_input_number: Option<String>,
+}
+
+impl NetworkConfig {
+    pub(crate) fn default_interface() -> String {
+        "wlan0".to_string()
+    }
+
+    fn default_refresh_interval() -> Duration {
+        Duration::from_secs(30)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+    pub interface: String,
+    pub ssid: Option<String>,
+
+    /// The Wi-Fi signal level, in dBm.
+    pub signal_dbm: Option<f64>,
+
+    /// The Wi-Fi link quality, as reported by the driver (usually out of 70).
+    pub link_quality: Option<f64>,
+    pub ip_address: Option<String>,
+    pub gateway: Option<Ipv4Addr>,
+    pub gateway_reachable: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Periodically collects the status of the network interface.
+pub struct Network {
+    config: NetworkConfig,
+    status: RwLock<Option<NetworkStatus>>,
+}
+
+impl Network {
+    pub fn new(config: NetworkConfig) -> Self {
+        Self {
+            config,
+            status: RwLock::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &NetworkConfig {
+        &self.config
+    }
+
+    /// Get the last network status, if any.
+    pub async fn status(&self) -> Option<NetworkStatus> {
+        self.status.read().await.clone()
+    }
+
+    /// Refresh the network status forever.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        info!("Monitoring network interface `{}`.", self.config.interface);
+
+        loop {
+            let status = self.refresh().await;
+
+            if !status.gateway_reachable {
+                warn!(
+                    "The gateway is not reachable on network interface `{}`.",
+                    self.config.interface
+                );
+            }
+
+            *self.status.write().await = Some(status);
+
+            tokio::time::sleep(self.config.refresh_interval).await;
+            tasks::heartbeat();
+        }
+    }
+
+    async fn refresh(&self) -> NetworkStatus {
+        let interface = &self.config.interface;
+
+        let ssid = command_output("iwgetid", &["-r", interface])
+            .await
+            .ok()
+            .filter(|ssid| !ssid.is_empty());
+
+        let (link_quality, signal_dbm) = match tokio::fs::read_to_string("/proc/net/wireless").await
+        {
+            Ok(wireless) => match parse_wireless(&wireless, interface) {
+                Some((link_quality, signal_dbm)) => (Some(link_quality), Some(signal_dbm)),
+                None => (None, None),
+            },
+            Err(_) => (None, None),
+        };
+
+        let ip_address = command_output("ip", &["-4", "-o", "addr", "show", "dev", interface])
+            .await
+            .ok()
+            .and_then(|addresses| parse_ip_address(&addresses));
+
+        let gateway = default_gateway(interface).await;
+
+        let gateway_reachable = match gateway {
+            Some(gateway) => ping(gateway).await,
+            None => false,
+        };
+
+        NetworkStatus {
+            interface: interface.clone(),
+            ssid,
+            signal_dbm,
+            link_quality,
+            ip_address,
+            gateway,
+            gateway_reachable,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+async fn command_output(program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .with_context(|| format!("failed to run `{}`", program))?;
+
+    if !output.status.success() {
+        anyhow::bail!("`{}` failed: {}", program, output.status);
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Get the default gateway of an interface.
+pub(crate) async fn default_gateway(interface: &str) -> Option<Ipv4Addr> {
+    match tokio::fs::read_to_string("/proc/net/route").await {
+        Ok(routes) => parse_gateway(&routes, interface),
+        Err(_) => None,
+    }
+}
+
+pub(crate) async fn ping(address: Ipv4Addr) -> bool {
+    Command::new("ping")
+        .args(["-c", "1", "-W", "2", &address.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .map(|status| status.success())
+        .unwrap_or_default()
+}
+
+/// Parse the link quality and signal level of an interface from
+/// `/proc/net/wireless`.
+///
+/// Lines look like `wlan0: 0000   54.  -56.  -256  0  0  0  0  0  0`.
+fn parse_wireless(wireless: &str, interface: &str) -> Option<(f64, f64)> {
+    wireless.lines().find_map(|line| {
+        let (name, values) = line.trim().split_once(':')?;
+
+        if name != interface {
+            return None;
+        }
+
+        let mut values = values
+            .split_whitespace()
+            .skip(1)
+            .map(|value| value.trim_end_matches('.').parse::<f64>());
+
+        Some((values.next()?.ok()?, values.next()?.ok()?))
+    })
+}
+
+/// Parse the first IPv4 address from the output of `ip -4 -o addr show`.
+fn parse_ip_address(addresses: &str) -> Option<String> {
+    let mut words = addresses.split_whitespace();
+
+    words.find(|word| *word == "inet")?;
+
+    words
+        .next()
+        .map(|address| address.split('/').next().unwrap_or(address).to_string())
+}
+
+/// Parse the default gateway of an interface from `/proc/net/route`.
+///
+/// Addresses are the hexadecimal representation of the network-order bytes
+/// read as a native integer.
+fn parse_gateway(routes: &str, interface: &str) -> Option<Ipv4Addr> {
+    routes.lines().skip(1).find_map(|line| {
+        let mut columns = line.split_whitespace();
+
+        let (name, destination, gateway) = (columns.next()?, columns.next()?, columns.next()?);
+
+        if name != interface || destination != "00000000" {
+            return None;
+        }
+
+        let gateway = u32::from_str_radix(gateway, 16).ok()?;
+
+        Some(Ipv4Addr::from(gateway.to_ne_bytes()))
+    })
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{watch, RwLock};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub id: String,
+    pub severity: Severity,
+    pub title: String,
+    pub message: String,
+    pub raised_at: DateTime<Utc>,
+}
+
+/// The set of currently active notifications.
+///
+/// Notifications are keyed by id, so raising the same notification twice is a
+/// no-op until it gets cleared.
+pub struct Notifications {
+    active: RwLock<BTreeMap<String, Notification>>,
+
+    /// The number of changes, which count in the generation of the status.
+    changes: watch::Sender<u64>,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            active: RwLock::default(),
+            changes: watch::channel(0).0,
+        }
+    }
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the number of changes so far.
+    pub fn generation(&self) -> u64 {
+        *self.changes.borrow()
+    }
+
+    /// Watch the changes of the notifications.
+    pub fn watch(&self) -> watch::Receiver<u64> {
+        self.changes.subscribe()
+    }
+
+    /// Raise a notification.
+    ///
+    /// Returns `true` if the notification was not already active.
+    pub async fn raise(
+        &self,
+        id: impl Into<String>,
+        severity: Severity,
+        title: impl Into<String>,
+        message: impl Into<String>,
+    ) -> bool {
+        let id = id.into();
+        let mut active = self.active.write().await;
+
+        if active.contains_key(&id) {
+            return false;
+        }
+
+        let notification = Notification {
+            id: id.clone(),
+            severity,
+            title: title.into(),
+            message: message.into(),
+            raised_at: Utc::now(),
+        };
+
+        match severity {
+            Severity::Info => info!("{}: {}", notification.title, notification.message),
+            Severity::Warning | Severity::Critical => {
+                warn!("{}: {}", notification.title, notification.message)
+            }
+        }
+
+        active.insert(id, notification);
+        self.changes.send_modify(|changes| *changes += 1);
+
+        true
+    }
+
+    /// Clear a notification.
+    ///
+    /// Returns `true` if the notification was active.
+    pub async fn clear(&self, id: &str) -> bool {
+        let cleared = self.active.write().await.remove(id).is_some();
+
+        if cleared {
+            self.changes.send_modify(|changes| *changes += 1);
+        }
+
+        cleared
+    }
+
+    /// Get the active notifications, most severe first.
+    pub async fn list(&self) -> Vec<Notification> {
+        let mut notifications: Vec<_> = self.active.read().await.values().cloned().collect();
+
+        notifications.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then(a.raised_at.cmp(&b.raised_at))
+        });
+
+        notifications
+    }
+}
@@ -0,0 +1,223 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::RwLock;
+use warp::hyper::body::Bytes;
+
+use crate::{home_assistant::State, tasks};
+
+/// How far ahead the nowcast looks.
+const NOWCAST_HORIZON: Duration = Duration::from_secs(60 * 60);
+
+/// The precipitation nowcast configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct NowcastConfig {
+    /// Where the nowcast comes from.
+    #[serde(flatten)]
+    pub source: NowcastSource,
+
+    /// The URL of a radar image to proxy, if any.
+    #[serde(default)]
+    pub radar_url: Option<String>,
+
+    /// The precipitation, in mm/h, above which rain is expected.
+    #[serde(default = "NowcastConfig::default_rain_threshold")]
+    pub rain_threshold: f64,
+
+    /// The interval at which the remote nowcast and the radar image are
+    /// refreshed.
+    #[serde(default = "NowcastConfig::default_refresh_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub refresh_interval: Duration,
+}
+
+impl NowcastConfig {
+    fn default_rain_threshold() -> f64 {
+        0.1
+    }
+
+    fn default_refresh_interval() -> Duration {
+        Duration::from_secs(5 * 60)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NowcastSource {
+    /// A Home Assistant entity with a `forecast` attribute, whose items have
+    /// a `datetime` and a `precipitation` in mm/h.
+    Entity(String),
+
+    /// A REST endpoint returning a JSON array of `time` and `precipitation`
+    /// items.
+    Url(String),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NowcastPoint {
+    #[serde(alias = "datetime")]
+    pub time: DateTime<Utc>,
+
+    /// The precipitation, in mm/h.
+    pub precipitation: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NowcastStatus {
+    /// The precipitation for the next hour.
+    pub points: Vec<NowcastPoint>,
+
+    /// When it is expected to rain next within the hour, if it is.
+    pub next_rain: Option<DateTime<Utc>>,
+}
+
+/// A radar image, as last fetched.
+#[derive(Debug, Clone)]
+pub struct RadarImage {
+    pub content_type: String,
+    pub data: Bytes,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Tracks the precipitation nowcast.
+pub struct Nowcast {
+    config: NowcastConfig,
+    http_client: reqwest::Client,
+    remote: RwLock<Vec<NowcastPoint>>,
+    radar: RwLock<Option<RadarImage>>,
+}
+
+impl Nowcast {
+    pub fn new(config: NowcastConfig) -> Self {
+        Self {
+            config,
+            http_client: reqwest::Client::new(),
+            remote: Default::default(),
+            radar: Default::default(),
+        }
+    }
+
+    /// Get the precipitation for the next hour.
+    pub async fn next_hour(&self, entities: &HashMap<String, State>) -> NowcastStatus {
+        let now = Utc::now();
+        let horizon = now + chrono::Duration::from_std(NOWCAST_HORIZON).unwrap_or_default();
+        let mut points = match &self.config.source {
+            NowcastSource::Entity(entity_id) => entities
+                .get(entity_id)
+                .and_then(|state| state.attributes.get::<Vec<NowcastPoint>>("forecast"))
+                .unwrap_or_default(),
+            NowcastSource::Url(_) => self.remote.read().await.clone(),
+        };
+
+        // Keep the point in progress, which starts before now.
+        points.sort_by_key(|point| point.time);
+
+        let first = points
+            .iter()
+            .rposition(|point| point.time <= now)
+            .unwrap_or_default();
+
+        points.drain(..first);
+        points.retain(|point| point.time < horizon);
+
+        let next_rain = points
+            .iter()
+            .find(|point| point.precipitation >= self.config.rain_threshold)
+            .map(|point| point.time.max(now));
+
+        NowcastStatus { points, next_rain }
+    }
+
+    /// Get the radar image, fetching it if the cached one is stale.
+    ///
+    /// Returns `None` if no radar is configured.
+    pub async fn radar(&self) -> anyhow::Result<Option<RadarImage>> {
+        let url = match &self.config.radar_url {
+            Some(url) => url,
+            None => return Ok(None),
+        };
+        let mut radar = self.radar.write().await;
+        let max_age = chrono::Duration::from_std(self.config.refresh_interval).unwrap_or_default();
+
+        match &*radar {
+            Some(image) if Utc::now() - image.fetched_at < max_age => {}
+            _ => match self.fetch_radar(url).await {
+                Ok(image) => *radar = Some(image),
+                // A stale image is better than none.
+                Err(err) if radar.is_some() => warn!("Failed to refresh the radar image: {}", err),
+                Err(err) => return Err(err),
+            },
+        }
+
+        Ok(radar.clone())
+    }
+
+    /// Refresh the remote nowcast periodically.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let url = match &self.config.source {
+            NowcastSource::Url(url) => url,
+            NowcastSource::Entity(_) => return tasks::idle().await,
+        };
+
+        loop {
+            match self.fetch(url).await {
+                Ok(points) => {
+                    debug!("Fetched {} nowcast points", points.len());
+
+                    *self.remote.write().await = points;
+                }
+                Err(err) => warn!("Failed to fetch the nowcast: {}", err),
+            }
+
+            tokio::time::sleep(self.config.refresh_interval).await;
+            tasks::heartbeat();
+        }
+    }
+
+    async fn fetch(&self, url: &str) -> anyhow::Result<Vec<NowcastPoint>> {
+        self.http_client
+            .get(url)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .context("failed to query the nowcast endpoint")?
+            .error_for_status()?
+            .json()
+            .await
+            .context("failed to parse the nowcast")
+    }
+
+    async fn fetch_radar(&self, url: &str) -> anyhow::Result<RadarImage> {
+        let response = self
+            .http_client
+            .get(url)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .context("failed to query the radar image")?
+            .error_for_status()?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("image/png")
+            .to_string();
+
+        Ok(RadarImage {
+            content_type,
+            data: response
+                .bytes()
+                .await
+                .context("failed to read the radar image")?,
+            fetched_at: Utc::now(),
+        })
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::{context::AppContext, gpio_controller::GpioController, red_led::RedLedOwner};
+
+/// A local output of the panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Output {
+    RedLed,
+    GreenLed,
+    Buzzer,
+}
+
+impl Output {
+    pub fn set(self, gpio: &GpioController, status: bool) -> anyhow::Result<()> {
+        match self {
+            Output::RedLed => gpio.set_red_led(status),
+            Output::GreenLed => gpio.set_green_led(status),
+            Output::Buzzer => gpio.set_buzzer(status),
+        }
+    }
+}
+
+/// A switchable load, like a valve or a heater.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relay {
+    /// A Home Assistant switch, like `switch.garden_valve`.
+    Switch(String),
+
+    /// A relay wired to a local output pin.
+    Pin(u8),
+}
+
+impl Relay {
+    pub async fn set(&self, context: &AppContext, status: bool) -> anyhow::Result<()> {
+        match self {
+            Relay::Switch(entity_id) => {
+                Ok(context.home_assistant.switch_set(entity_id, status).await?)
+            }
+            Relay::Pin(pin) => context.gpio.set_output(*pin, status),
+        }
+    }
+}
+
+/// What to do with an output driven from Home Assistant when the connection
+/// to it is lost for too long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisconnectPolicy {
+    /// Leave the output as it is.
+    #[default]
+    Keep,
+    Off,
+    On,
+}
+
+impl DisconnectPolicy {
+    /// Get the status to force on the output, if any.
+    pub fn status(self) -> Option<bool> {
+        match self {
+            DisconnectPolicy::Keep => None,
+            DisconnectPolicy::Off => Some(false),
+            DisconnectPolicy::On => Some(true),
+        }
+    }
+}
+
+/// How to drive an output.
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(tag = "pattern", rename_all = "snake_case")]
+pub enum OutputPattern {
+    #[default]
+    Off,
+    On,
+
+    /// Alternate between on and off, each for half the period.
+    Blink {
+        #[serde_as(as = "DurationSeconds<f64>")]
+        period: Duration,
+    },
+
+    /// Turn on briefly at every interval, like countdown beeps.
+    Beep {
+        #[serde_as(as = "DurationSeconds<f64>")]
+        interval: Duration,
+    },
+}
+
+/// The duration of a beep.
+const BEEP_DURATION: Duration = Duration::from_millis(80);
+
+impl OutputPattern {
+    /// Whether the pattern keeps switching the output, unlike the steady ones.
+    pub fn is_repeating(self) -> bool {
+        matches!(
+            self,
+            OutputPattern::Blink { .. } | OutputPattern::Beep { .. }
+        )
+    }
+
+    /// Drive an output with the pattern for a subsystem, which claims the red
+    /// LED through its arbiter rather than writing it.
+    ///
+    /// Never returns for the red LED: drop the future to release it.
+    pub async fn drive_for(
+        self,
+        gpio: &GpioController,
+        output: Output,
+        owner: RedLedOwner,
+    ) -> anyhow::Result<()> {
+        match output {
+            Output::RedLed => gpio.red_led().hold(owner, self).await,
+            Output::GreenLed | Output::Buzzer => self.drive(gpio, output).await,
+        }
+    }
+
+    /// Drive an output with the pattern.
+    ///
+    /// Never returns for the repeating patterns: drop the future to stop.
+    pub async fn drive(self, gpio: &GpioController, output: Output) -> anyhow::Result<()> {
+        let (on, off) = match self {
+            OutputPattern::Off => return output.set(gpio, false),
+            OutputPattern::On => return output.set(gpio, true),
+            OutputPattern::Blink { period } => (period / 2, period / 2),
+            OutputPattern::Beep { interval } => {
+                (BEEP_DURATION, interval.saturating_sub(BEEP_DURATION))
+            }
+        };
+
+        loop {
+            output.set(gpio, true)?;
+            tokio::time::sleep(on).await;
+            output.set(gpio, false)?;
+            tokio::time::sleep(off).await;
+        }
+    }
+}
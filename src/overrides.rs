@@ -0,0 +1,263 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use log::info;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use crate::{
+    config::HomeControlConfig,
+    dashboard::{self, DashboardConfig, LightConfig},
+    files::write_atomic,
+    irrigation::IrrigationSchedule,
+    wakeup::WakeupAlarm,
+};
+
+/// The configuration sections edited from the frontend.
+///
+/// They are persisted to a separate file, so that the main configuration
+/// file is never rewritten and keeps its comments.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ConfigOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dashboard: Option<DashboardConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lights: Option<Vec<LightConfig>>,
+
+    /// The irrigation programs, as edited or imported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub irrigation_schedules: Option<Vec<IrrigationSchedule>>,
+
+    /// The wake-up alarms, as edited or imported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wakeup_alarms: Option<Vec<WakeupAlarm>>,
+}
+
+impl ConfigOverrides {
+    /// Load the overrides, if the file exists.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_yaml::from_str(&content)
+                .with_context(|| format!("failed to parse `{}`", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("failed to read `{}`", path.display())),
+        }
+    }
+
+    /// Replace the configured schedules with the edited ones, before the
+    /// subsystems running them are created.
+    pub fn apply_schedules(&self, config: &mut HomeControlConfig) {
+        if let (Some(irrigation), Some(schedules)) =
+            (&mut config.irrigation, &self.irrigation_schedules)
+        {
+            irrigation.schedules = schedules.clone();
+        }
+
+        if let (Some(wakeup), Some(alarms)) = (&mut config.wakeup, &self.wakeup_alarms) {
+            wakeup.alarms = alarms.clone();
+        }
+    }
+
+    /// Save the overrides, replacing the file atomically.
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let content = serde_yaml::to_string(self).context("failed to serialize the overrides")?;
+
+        write_atomic(path, content)
+    }
+}
+
+/// The configuration sections that can be edited at runtime.
+///
+/// Changes are persisted to the overrides file and apply right away, or only
+/// last until the restart without one.
+pub struct EditableConfig {
+    path: Option<PathBuf>,
+    overrides: RwLock<ConfigOverrides>,
+    dashboard: RwLock<DashboardConfig>,
+    lights: RwLock<Vec<LightConfig>>,
+
+    /// The entities the rest of the configuration refers to.
+    config_entity_ids: BTreeSet<String>,
+
+    /// The entities the configuration refers to, edited sections included.
+    entity_ids: RwLock<BTreeSet<String>>,
+
+    /// The only entities Home-Assistant is subscribed to, if an allowlist is
+    /// specified: the edits can't refer to others until the restart.
+    tracked_entities: Option<BTreeSet<String>>,
+}
+
+/// Get the entities the edited sections refer to.
+fn section_entity_ids(dashboard: &DashboardConfig, lights: &[LightConfig]) -> BTreeSet<String> {
+    dashboard
+        .panels
+        .iter()
+        .flat_map(|panel| panel.entities.iter().cloned())
+        .chain(lights.iter().map(|light| format!("light.{}", light.name)))
+        .collect()
+}
+
+impl EditableConfig {
+    /// Apply the overrides on top of the configuration.
+    pub fn new(
+        path: Option<PathBuf>,
+        config: &HomeControlConfig,
+        overrides: ConfigOverrides,
+    ) -> Self {
+        let dashboard = overrides
+            .dashboard
+            .clone()
+            .unwrap_or_else(|| config.dashboard.clone());
+        let lights = overrides
+            .lights
+            .clone()
+            .unwrap_or_else(|| config.lights.clone());
+        let config_entity_ids = config.entity_ids();
+        let mut entity_ids = config_entity_ids.clone();
+
+        entity_ids.extend(section_entity_ids(&dashboard, &lights));
+
+        let tracked_entities = config.entity_allowlist.clone().map(|mut allowlist| {
+            allowlist.extend(entity_ids.iter().cloned());
+            allowlist
+        });
+
+        Self {
+            path,
+            dashboard: RwLock::new(dashboard),
+            lights: RwLock::new(lights),
+            overrides: RwLock::new(overrides),
+            config_entity_ids,
+            entity_ids: RwLock::new(entity_ids),
+            tracked_entities,
+        }
+    }
+
+    /// Get the only Home Assistant entities to keep track of, if an allowlist
+    /// is specified.
+    pub fn tracked_entities(&self) -> Option<&BTreeSet<String>> {
+        self.tracked_entities.as_ref()
+    }
+
+    /// Get the Home Assistant entities the configuration refers to, with the
+    /// edited sections as they are now.
+    pub async fn entity_ids(&self) -> RwLockReadGuard<'_, BTreeSet<String>> {
+        self.entity_ids.read().await
+    }
+
+    pub async fn dashboard(&self) -> DashboardConfig {
+        self.dashboard.read().await.clone()
+    }
+
+    pub async fn lights(&self) -> Vec<LightConfig> {
+        self.lights.read().await.clone()
+    }
+
+    pub async fn set_dashboard(&self, dashboard: DashboardConfig) -> crate::Result<()> {
+        dashboard.validate().map_err(crate::Error::InvalidConfig)?;
+
+        let lights = self.lights.read().await.clone();
+        let entity_ids = self.checked_entity_ids(&dashboard, &lights)?;
+
+        self.persist(|overrides| overrides.dashboard = Some(dashboard.clone()))
+            .await?;
+        *self.dashboard.write().await = dashboard;
+        *self.entity_ids.write().await = entity_ids;
+
+        info!("Updated the dashboard configuration.");
+
+        Ok(())
+    }
+
+    pub async fn set_lights(&self, lights: Vec<LightConfig>) -> crate::Result<()> {
+        dashboard::validate_lights(&lights).map_err(crate::Error::InvalidConfig)?;
+
+        let dashboard = self.dashboard.read().await.clone();
+        let entity_ids = self.checked_entity_ids(&dashboard, &lights)?;
+
+        self.persist(|overrides| overrides.lights = Some(lights.clone()))
+            .await?;
+        *self.lights.write().await = lights;
+        *self.entity_ids.write().await = entity_ids;
+
+        info!("Updated the lights configuration.");
+
+        Ok(())
+    }
+
+    /// Save the edited schedules, for them to replace the configured ones on
+    /// the next start. The sections left out are kept as they are.
+    ///
+    /// The subsystems running the schedules are updated separately.
+    pub async fn save_schedules(
+        &self,
+        irrigation_schedules: Option<Vec<IrrigationSchedule>>,
+        wakeup_alarms: Option<Vec<WakeupAlarm>>,
+    ) -> crate::Result<()> {
+        self.persist(|overrides| {
+            if irrigation_schedules.is_some() {
+                overrides.irrigation_schedules = irrigation_schedules;
+            }
+
+            if wakeup_alarms.is_some() {
+                overrides.wakeup_alarms = wakeup_alarms;
+            }
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the entities the configuration refers to with the edited sections,
+    /// rejecting the ones Home-Assistant is not subscribed to.
+    fn checked_entity_ids(
+        &self,
+        dashboard: &DashboardConfig,
+        lights: &[LightConfig],
+    ) -> crate::Result<BTreeSet<String>> {
+        let sections = section_entity_ids(dashboard, lights);
+
+        if let Some(tracked_entities) = &self.tracked_entities {
+            let untracked: Vec<_> = sections
+                .difference(tracked_entities)
+                .map(String::as_str)
+                .collect();
+
+            if !untracked.is_empty() {
+                return Err(crate::Error::InvalidConfig(format!(
+                    "not in the entity allowlist, so not tracked until added to it and restarted: {}",
+                    untracked.join(", ")
+                )));
+            }
+        }
+
+        let mut entity_ids = self.config_entity_ids.clone();
+
+        entity_ids.extend(sections);
+
+        Ok(entity_ids)
+    }
+
+    /// Update the overrides and save them, leaving them untouched if saving
+    /// fails.
+    async fn persist(&self, f: impl FnOnce(&mut ConfigOverrides)) -> anyhow::Result<()> {
+        let mut overrides = self.overrides.write().await;
+        let mut updated = overrides.clone();
+
+        f(&mut updated);
+
+        if let Some(path) = &self.path {
+            updated.save(path)?;
+        }
+
+        *overrides = updated;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,113 @@
+//! Tests of the configuration edited from the frontend.
+
+use serde_json::json;
+
+use super::*;
+
+fn config(entity_allowlist: Option<&[&str]>) -> HomeControlConfig {
+    serde_json::from_value(json!({
+        "location": "Home",
+        "weather_entity": "weather.home",
+        "entity_allowlist": entity_allowlist,
+        "lights": [{"name": "desk"}],
+    }))
+    .unwrap()
+}
+
+fn dashboard(entities: &[&str]) -> DashboardConfig {
+    serde_json::from_value(json!({
+        "panels": [{"title": "Living room", "entities": entities}],
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn the_overrides_are_tracked_from_the_start() {
+    let overrides = ConfigOverrides {
+        dashboard: Some(dashboard(&["sensor.living_room_temperature"])),
+        ..Default::default()
+    };
+    let editable = EditableConfig::new(None, &config(Some(&["sun.sun"])), overrides);
+    let tracked = editable.tracked_entities().unwrap();
+    let entity_ids = editable.entity_ids().await;
+
+    for entity_id in [
+        "weather.home",
+        "light.desk",
+        "sensor.living_room_temperature",
+    ] {
+        assert!(tracked.contains(entity_id), "{}", entity_id);
+        assert!(entity_ids.contains(entity_id), "{}", entity_id);
+    }
+
+    assert!(tracked.contains("sun.sun"));
+}
+
+#[tokio::test]
+async fn the_edited_entities_are_configured_right_away() {
+    let editable = EditableConfig::new(None, &config(None), ConfigOverrides::default());
+
+    assert!(editable.tracked_entities().is_none());
+
+    editable
+        .set_dashboard(dashboard(&["sensor.kitchen_temperature"]))
+        .await
+        .unwrap();
+
+    assert!(editable
+        .entity_ids()
+        .await
+        .contains("sensor.kitchen_temperature"));
+}
+
+#[tokio::test]
+async fn the_untracked_entities_are_rejected_with_an_allowlist() {
+    let editable = EditableConfig::new(None, &config(Some(&[])), ConfigOverrides::default());
+
+    let err = editable
+        .set_dashboard(dashboard(&["sensor.kitchen_temperature"]))
+        .await
+        .unwrap_err();
+
+    assert!(
+        err.to_string().contains("sensor.kitchen_temperature"),
+        "{}",
+        err
+    );
+    assert!(editable.dashboard().await.panels.is_empty());
+
+    editable
+        .set_lights(serde_json::from_value(json!([{"name": "desk"}])).unwrap())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn the_saved_schedules_replace_the_configured_ones_on_restart() {
+    let path = std::env::temp_dir().join(format!("overrides-{}.yaml", std::process::id()));
+    let mut config: HomeControlConfig = serde_json::from_value(json!({
+        "location": "Home",
+        "weather_entity": "weather.home",
+        "wakeup": {"lights": ["light.bedroom"], "alarms": [{"at": "07:00:00"}]},
+    }))
+    .unwrap();
+    let alarms: Vec<WakeupAlarm> =
+        serde_json::from_value(json!([{"at": "06:30:00", "weekdays": ["Mon"]}])).unwrap();
+    let editable = EditableConfig::new(Some(path.clone()), &config, ConfigOverrides::default());
+
+    editable
+        .save_schedules(None, Some(alarms.clone()))
+        .await
+        .unwrap();
+
+    let overrides = ConfigOverrides::load(&path).unwrap();
+
+    std::fs::remove_file(&path).unwrap();
+    overrides.apply_schedules(&mut config);
+
+    assert_eq!(
+        serde_json::to_value(&config.wakeup.unwrap().alarms).unwrap(),
+        serde_json::to_value(&alarms).unwrap()
+    );
+    assert!(overrides.irrigation_schedules.is_none());
+}
@@ -0,0 +1,412 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use futures_util::future::try_join_all;
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::watch;
+
+use crate::{
+    auth::constant_time_eq,
+    context::AppContext,
+    gpio_controller::Pull,
+    outputs::{Output, OutputPattern},
+    red_led::RedLedOwner,
+    tasks,
+};
+
+/// The HA event fired when a panic is raised or cancelled.
+const PANIC_EVENT: &str = "home_control_panic";
+
+/// The interval between two readings of the panic button.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The panic action, raised by a long press of a button or from the API, and
+/// cancelled with the PIN.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct PanicConfig {
+    /// The PIN to cancel a panic with.
+    pub pin: String,
+
+    /// The sirens to turn on, like `siren.hallway` or `switch.outdoor_siren`.
+    #[serde(default)]
+    pub sirens: Vec<String>,
+
+    /// The lights to flash, which are restored once the panic is cancelled.
+    #[serde(default)]
+    pub lights: Vec<String>,
+
+    /// The period of the flashing of the lights.
+    #[serde(default = "PanicConfig::default_flash_period")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub flash_period: Duration,
+
+    /// The local output patterns during a panic.
+    ///
+    /// Defaults to the buzzer and the red LED blinking.
+    #[serde(default = "PanicConfig::default_outputs")]
+    pub outputs: HashMap<Output, OutputPattern>,
+
+    /// The notification services to call, like `notify.mobile_app_phone`.
+    #[serde(default)]
+    pub notify: Vec<String>,
+
+    /// The message of the notifications.
+    #[serde(default = "PanicConfig::default_message")]
+    pub message: String,
+
+    /// The panic button, if any.
+    #[serde(default)]
+    pub button: Option<PanicButtonConfig>,
+}
+
+impl PanicConfig {
+    fn default_flash_period() -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn default_outputs() -> HashMap<Output, OutputPattern> {
+        let blink = OutputPattern::Blink {
+            period: Duration::from_millis(500),
+        };
+
+        HashMap::from([(Output::Buzzer, blink), (Output::RedLed, blink)])
+    }
+
+    fn default_message() -> String {
+        "Panic raised on the panel.".to_string()
+    }
+
+    /// Check the PIN to cancel a panic with.
+    pub fn check_pin(&self, pin: &str) -> bool {
+        constant_time_eq(&self.pin, pin)
+    }
+}
+
+/// A button which raises the panic when held.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct PanicButtonConfig {
+    /// The GPIO pin the button is connected to.
+    pub pin: u8,
+
+    /// The pull resistor to enable on the pin.
+    #[serde(default)]
+    pub pull: Pull,
+
+    /// Whether the button is pressed when the pin is low, like with pulled-up
+    /// buttons wired to the ground.
+    #[serde(default)]
+    pub invert: bool,
+
+    /// For how long the button must be held, so that it is not raised by
+    /// accident.
+    #[serde(default = "PanicButtonConfig::default_hold")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub hold: Duration,
+}
+
+impl PanicButtonConfig {
+    fn default_hold() -> Duration {
+        Duration::from_secs(3)
+    }
+}
+
+/// What raised a panic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PanicSource {
+    Button,
+    Api,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PanicStatus {
+    pub active: bool,
+    pub source: Option<PanicSource>,
+    pub raised_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Raised {
+    source: PanicSource,
+    at: DateTime<Utc>,
+}
+
+pub struct Panic {
+    config: PanicConfig,
+    raised: watch::Sender<Option<Raised>>,
+}
+
+impl Panic {
+    pub fn new(config: PanicConfig) -> Self {
+        Self {
+            config,
+            raised: watch::channel(None).0,
+        }
+    }
+
+    pub fn config(&self) -> &PanicConfig {
+        &self.config
+    }
+
+    pub fn status(&self) -> PanicStatus {
+        let raised = *self.raised.borrow();
+
+        PanicStatus {
+            active: raised.is_some(),
+            source: raised.map(|raised| raised.source),
+            raised_at: raised.map(|raised| raised.at),
+        }
+    }
+
+    /// Raise a panic, unless one is already raised.
+    ///
+    /// Returns whether it was raised.
+    pub fn raise(&self, source: PanicSource) -> bool {
+        let raised = self.raised.send_if_modified(|raised| match raised {
+            Some(_) => false,
+            None => {
+                *raised = Some(Raised {
+                    source,
+                    at: Utc::now(),
+                });
+                true
+            }
+        });
+
+        if raised {
+            warn!(target: "audit", "Panic raised from the {:?}.", source);
+        }
+
+        raised
+    }
+
+    /// Cancel the panic, if any.
+    ///
+    /// Returns whether one was raised.
+    pub fn cancel(&self) -> bool {
+        let cancelled = self
+            .raised
+            .send_if_modified(|raised| raised.take().is_some());
+
+        if cancelled {
+            info!(target: "audit", "Panic cancelled.");
+        }
+
+        cancelled
+    }
+
+    /// Watch the panic button and respond to the panics forever.
+    pub async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        tokio::select! {
+            r = self.respond(context) => r,
+            r = self.watch_button(context) => r,
+        }
+    }
+
+    async fn respond(&self, context: &AppContext) -> anyhow::Result<()> {
+        let mut raised = self.raised.subscribe();
+
+        loop {
+            let current = *raised.borrow_and_update();
+            let source = match current {
+                Some(current) => current.source,
+                None => {
+                    raised.changed().await?;
+                    continue;
+                }
+            };
+
+            let lights = self.lights_on(context).await;
+
+            // Alert until the panic is cancelled.
+            tokio::select! {
+                r = self.alert(context, source) => r?,
+                r = async {
+                    while raised.borrow_and_update().is_some() {
+                        raised.changed().await?;
+                    }
+
+                    anyhow::Ok(())
+                } => r?,
+            }
+
+            self.stop(context, &lights).await;
+        }
+    }
+
+    /// Get the lights which are on, to restore them afterwards.
+    async fn lights_on(&self, context: &AppContext) -> Vec<String> {
+        let mut lights = Vec::new();
+
+        for entity_id in &self.config.lights {
+            if let Some(state) = context.home_assistant.entity(entity_id).await {
+                if state.state == "on" {
+                    lights.push(entity_id.clone());
+                }
+            }
+        }
+
+        lights
+    }
+
+    /// Turn on the sirens and send the notifications.
+    async fn start(&self, context: &AppContext, source: PanicSource) {
+        self.set_sirens(context, true).await;
+        self.notify(context, &self.config.message).await;
+
+        let event = json!({ "state": "raised", "source": source });
+
+        if let Err(err) = context.home_assistant.fire_event(PANIC_EVENT, &event).await {
+            warn!("Failed to fire the panic event: {}", err);
+        }
+    }
+
+    /// Drive the outputs right away, then reach Home-Assistant and flash the
+    /// lights, forever.
+    async fn alert(&self, context: &AppContext, source: PanicSource) -> anyhow::Result<()> {
+        let drives =
+            self.config.outputs.iter().map(|(output, pattern)| {
+                pattern.drive_for(&context.gpio, *output, RedLedOwner::Panic)
+            });
+
+        let flash = async {
+            self.start(context, source).await;
+
+            if self.config.lights.is_empty() {
+                return futures_util::future::pending().await;
+            }
+
+            let mut on = true;
+
+            loop {
+                for entity_id in &self.config.lights {
+                    if let Err(err) = context.home_assistant.light_set(entity_id, on).await {
+                        warn!("Failed to flash `{}`: {}", entity_id, err);
+                    }
+                }
+
+                on = !on;
+                tokio::time::sleep(self.config.flash_period / 2).await;
+                tasks::heartbeat();
+            }
+        };
+
+        tokio::select! {
+            // The steady patterns are set once.
+            r = async {
+                try_join_all(drives).await?;
+                futures_util::future::pending().await
+            } => r,
+            r = flash => r,
+        }
+    }
+
+    /// Turn off the sirens and the outputs, and restore the lights.
+    ///
+    /// The red LED was released with the alert.
+    async fn stop(&self, context: &AppContext, lights: &[String]) {
+        for output in self
+            .config
+            .outputs
+            .keys()
+            .filter(|output| **output != Output::RedLed)
+        {
+            if let Err(err) = output.set(&context.gpio, false) {
+                warn!("Failed to turn off the panic output {:?}: {}", output, err);
+            }
+        }
+
+        self.set_sirens(context, false).await;
+
+        for entity_id in &self.config.lights {
+            let on = lights.contains(entity_id);
+
+            if let Err(err) = context.home_assistant.light_set(entity_id, on).await {
+                warn!("Failed to restore `{}`: {}", entity_id, err);
+            }
+        }
+
+        self.notify(context, "The panic was cancelled on the panel.")
+            .await;
+
+        let event = json!({ "state": "cancelled" });
+
+        if let Err(err) = context.home_assistant.fire_event(PANIC_EVENT, &event).await {
+            warn!("Failed to fire the panic event: {}", err);
+        }
+    }
+
+    async fn set_sirens(&self, context: &AppContext, status: bool) {
+        for entity_id in &self.config.sirens {
+            if let Err(err) = context
+                .home_assistant
+                .call_service(
+                    "homeassistant",
+                    if status { "turn_on" } else { "turn_off" },
+                    None,
+                    Some(&json!({ "entity_id": entity_id })),
+                )
+                .await
+            {
+                warn!("Failed to switch the siren `{}`: {}", entity_id, err);
+            }
+        }
+    }
+
+    async fn notify(&self, context: &AppContext, message: &str) {
+        for service in &self.config.notify {
+            let service = service.strip_prefix("notify.").unwrap_or(service);
+            let data = json!({ "title": "Panic", "message": message });
+
+            if let Err(err) = context
+                .home_assistant
+                .call_service("notify", service, Some(&data), None)
+                .await
+            {
+                warn!("Failed to notify through `{}`: {}", service, err);
+            }
+        }
+    }
+
+    /// Raise the panic when the button is held long enough.
+    async fn watch_button(&self, context: &AppContext) -> anyhow::Result<()> {
+        let button = match &self.config.button {
+            Some(button) => button,
+            None => return futures_util::future::pending().await,
+        };
+
+        let mut pressed_since: Option<Instant> = None;
+        let mut fired = false;
+
+        loop {
+            match context.gpio.read_input(button.pin, button.pull) {
+                Ok(high) if high != button.invert => {
+                    let since = *pressed_since.get_or_insert_with(Instant::now);
+
+                    if !fired && since.elapsed() >= button.hold {
+                        fired = true;
+                        self.raise(PanicSource::Button);
+                    }
+                }
+                Ok(_) => {
+                    pressed_since = None;
+                    fired = false;
+                }
+                Err(err) => warn!("Failed to read the panic button: {}", err),
+            }
+
+            tasks::heartbeat();
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! The refresh of the entities of the integrations that only update when
+//! polled, like some cloud sensors, so that their values do not go stale for
+//! hours.
+
+use std::time::Duration;
+
+use log::{debug, warn};
+use serde::{de::Error, Deserialize, Deserializer};
+use serde_json::json;
+use serde_with::{DeserializeAs, DurationSeconds};
+use tokio::time::Instant;
+
+use crate::{context::AppContext, tasks};
+
+/// The shortest interval between refreshes, so that a mistyped interval does
+/// not flood Home-Assistant.
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An entity to refresh periodically, with `homeassistant.update_entity`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PollConfig {
+    /// The entity, like `sensor.cloud_weather_temperature`.
+    pub entity_id: String,
+
+    /// The interval between refreshes, of at least a second.
+    #[serde(
+        default = "PollConfig::default_interval",
+        deserialize_with = "PollConfig::deserialize_interval"
+    )]
+    pub interval: Duration,
+}
+
+impl PollConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(15 * 60)
+    }
+
+    fn deserialize_interval<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Duration, D::Error> {
+        let interval = DurationSeconds::<f64>::deserialize_as(deserializer)?;
+
+        if interval < MIN_INTERVAL {
+            return Err(D::Error::custom(format!(
+                "the polling interval must be at least {}s",
+                MIN_INTERVAL.as_secs()
+            )));
+        }
+
+        Ok(interval)
+    }
+}
+
+/// Refresh the entities forever, each at its interval.
+///
+/// The entities due at once are refreshed in a single call, and the refreshes
+/// due while Home-Assistant is disconnected are skipped.
+pub async fn run(polls: &[PollConfig], context: &AppContext) -> anyhow::Result<()> {
+    let start = Instant::now();
+    let mut next: Vec<_> = polls.iter().map(|poll| start + poll.interval).collect();
+
+    loop {
+        let due = next.iter().min().copied().unwrap_or(start);
+
+        tokio::time::sleep_until(due).await;
+        tasks::heartbeat();
+
+        let now = Instant::now();
+        let entity_ids = due_entities(polls, &mut next, now);
+
+        if context.home_assistant.disconnected_for().is_some() {
+            debug!(
+                "Not refreshing {} while disconnected from Home-Assistant.",
+                entity_ids.join(", ")
+            );
+
+            continue;
+        }
+
+        debug!("Refreshing {}...", entity_ids.join(", "));
+
+        if let Err(err) = context
+            .home_assistant
+            .call_service(
+                "homeassistant",
+                "update_entity",
+                None,
+                Some(&json!({ "entity_id": entity_ids })),
+            )
+            .await
+        {
+            warn!("Failed to refresh {}: {}", entity_ids.join(", "), err);
+        }
+    }
+}
+
+/// Get the entities due for a refresh, and schedule their next one.
+fn due_entities<'a>(polls: &'a [PollConfig], next: &mut [Instant], now: Instant) -> Vec<&'a str> {
+    polls
+        .iter()
+        .zip(next.iter_mut())
+        .filter(|(_, next)| **next <= now)
+        .map(|(poll, next)| {
+            *next = now + poll.interval;
+
+            poll.entity_id.as_str()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
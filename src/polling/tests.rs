@@ -0,0 +1,50 @@
+//! Tests of the entity polling.
+
+use super::*;
+
+fn poll(entity_id: &str, interval: u64) -> PollConfig {
+    PollConfig {
+        entity_id: entity_id.to_string(),
+        interval: Duration::from_secs(interval),
+    }
+}
+
+#[test]
+fn the_due_entities_are_refreshed_together() {
+    let polls = [
+        poll("sensor.a", 60),
+        poll("sensor.b", 60),
+        poll("sensor.c", 300),
+    ];
+    let start = Instant::now();
+    let mut next: Vec<_> = polls.iter().map(|poll| start + poll.interval).collect();
+
+    assert!(due_entities(&polls, &mut next, start).is_empty());
+
+    let now = start + Duration::from_secs(60);
+
+    assert_eq!(
+        due_entities(&polls, &mut next, now),
+        ["sensor.a", "sensor.b"]
+    );
+    assert_eq!(next[0], now + Duration::from_secs(60));
+    assert_eq!(next[2], start + Duration::from_secs(300));
+}
+
+#[test]
+fn short_intervals_are_rejected() {
+    let config = |interval: &str| {
+        serde_yaml::from_str::<PollConfig>(&format!("entity_id: sensor.a\ninterval: {}", interval))
+    };
+
+    assert_eq!(config("90").unwrap().interval, Duration::from_secs(90));
+
+    assert!(config("0").unwrap_err().to_string().contains("at least 1s"));
+}
+
+#[test]
+fn the_interval_defaults_to_a_quarter_of_an_hour() {
+    let config: PollConfig = serde_yaml::from_str("entity_id: sensor.a").unwrap();
+
+    assert_eq!(config.interval, Duration::from_secs(15 * 60));
+}
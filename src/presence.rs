@@ -0,0 +1,307 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+/// The presence detection configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct PresenceConfig {
+    /// The interval between two readings of the presence sensors.
+    #[serde(default = "PresenceConfig::default_poll_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub poll_interval: Duration,
+
+    /// The number of consecutive readings that must agree for the presence
+    /// to change, to ignore spurious readings.
+    #[serde(default = "PresenceConfig::default_consecutive_readings")]
+    pub consecutive_readings: u32,
+
+    /// Poll faster for a while after the presence changed, if specified.
+    #[serde(default)]
+    pub adaptive: Option<AdaptivePollingConfig>,
+
+    /// The time windows during which presence only wakes the screen once it
+    /// persisted, like at night.
+    #[serde(default)]
+    pub quiet_hours: Vec<QuietHoursConfig>,
+
+    /// The entity reporting the ambient temperature in °C, to compensate
+    /// the distance measurements for the speed of sound.
+    #[serde(default)]
+    pub temperature_entity: Option<String>,
+
+    /// The distance buckets, for the frontend to adapt its layout.
+    #[serde(default)]
+    pub proximity: ProximityConfig,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Self::default_poll_interval(),
+            consecutive_readings: Self::default_consecutive_readings(),
+            adaptive: None,
+            quiet_hours: Vec::new(),
+            temperature_entity: None,
+            proximity: ProximityConfig::default(),
+        }
+    }
+}
+
+impl PresenceConfig {
+    fn default_poll_interval() -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn default_consecutive_readings() -> u32 {
+        1
+    }
+}
+
+/// The adaptive polling configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdaptivePollingConfig {
+    /// The interval between two readings while polling faster.
+    #[serde(default = "AdaptivePollingConfig::default_poll_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub poll_interval: Duration,
+
+    /// How long to poll faster after the presence changed.
+    #[serde(default = "AdaptivePollingConfig::default_duration")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub duration: Duration,
+}
+
+impl AdaptivePollingConfig {
+    fn default_poll_interval() -> Duration {
+        Duration::from_millis(250)
+    }
+
+    fn default_duration() -> Duration {
+        Duration::from_secs(10)
+    }
+}
+
+/// The bounds of the distance buckets, in cm.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProximityConfig {
+    /// The distance under which the user is near enough to touch the screen.
+    #[serde(default = "ProximityConfig::default_near_cm")]
+    pub near_cm: f64,
+
+    /// The distance above which the user can only glance at the screen.
+    #[serde(default = "ProximityConfig::default_far_cm")]
+    pub far_cm: f64,
+
+    /// How far past a bound the distance must go to change the bucket, so
+    /// that the layout does not flap around the bounds.
+    #[serde(default = "ProximityConfig::default_hysteresis_cm")]
+    pub hysteresis_cm: f64,
+}
+
+impl Default for ProximityConfig {
+    fn default() -> Self {
+        Self {
+            near_cm: Self::default_near_cm(),
+            far_cm: Self::default_far_cm(),
+            hysteresis_cm: Self::default_hysteresis_cm(),
+        }
+    }
+}
+
+impl ProximityConfig {
+    fn default_near_cm() -> f64 {
+        60.0
+    }
+
+    fn default_far_cm() -> f64 {
+        150.0
+    }
+
+    fn default_hysteresis_cm() -> f64 {
+        10.0
+    }
+
+    /// Get the bucket of a distance, given the previous bucket.
+    pub fn bucket(&self, cm: f64, previous: Option<Proximity>) -> Proximity {
+        let (near_cm, far_cm) = match previous {
+            Some(Proximity::Near) => (self.near_cm + self.hysteresis_cm, self.far_cm),
+            Some(Proximity::Mid) => (
+                self.near_cm - self.hysteresis_cm,
+                self.far_cm + self.hysteresis_cm,
+            ),
+            Some(Proximity::Far) => (self.near_cm, self.far_cm - self.hysteresis_cm),
+            None => (self.near_cm, self.far_cm),
+        };
+
+        if cm <= near_cm {
+            Proximity::Near
+        } else if cm <= far_cm {
+            Proximity::Mid
+        } else {
+            Proximity::Far
+        }
+    }
+}
+
+/// How far the user is from the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Proximity {
+    /// Close enough to touch the screen: the detailed layout.
+    Near,
+    Mid,
+
+    /// Only glancing at the screen: the large-type layout.
+    Far,
+}
+
+/// A time window during which presence does not wake the screen right away.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuietHoursConfig {
+    /// The start of the window, like `23:00`.
+    pub start: NaiveTime,
+
+    /// The end of the window, like `06:30`. Windows may span midnight.
+    pub end: NaiveTime,
+
+    /// How long the presence must persist to wake the screen.
+    #[serde(default = "QuietHoursConfig::default_persistence")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub persistence: Duration,
+}
+
+impl QuietHoursConfig {
+    fn default_persistence() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    /// Check whether the window contains the specified time.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+/// A distance unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceUnit {
+    #[default]
+    Cm,
+    In,
+}
+
+impl DistanceUnit {
+    /// Convert a distance in cm to the unit.
+    pub fn from_cm(self, cm: f64) -> f64 {
+        match self {
+            Self::Cm => cm,
+            Self::In => cm / 2.54,
+        }
+    }
+}
+
+/// A reading of the distance sensor, for calibration.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DistanceReading {
+    pub at: DateTime<Utc>,
+    pub unit: DistanceUnit,
+
+    /// The measured distance, if the measurement succeeded.
+    pub distance: Option<f64>,
+
+    /// The distance under which presence is detected.
+    pub activation_distance: f64,
+
+    /// The raw duration of the echo, in microseconds.
+    pub echo_us: Option<f64>,
+
+    /// The ambient temperature the distance was compensated for, in °C.
+    pub temperature_c: Option<f64>,
+}
+
+impl DistanceReading {
+    /// Convert the reading, which must be in cm, to the specified unit.
+    pub fn in_unit(self, unit: DistanceUnit) -> Self {
+        Self {
+            unit,
+            distance: self.distance.map(|distance| unit.from_cm(distance)),
+            activation_distance: unit.from_cm(self.activation_distance),
+            ..self
+        }
+    }
+}
+
+/// Filters the raw presence readings.
+pub struct PresenceFilter {
+    config: PresenceConfig,
+    present: bool,
+    streak: u32,
+    last_change: Option<Instant>,
+}
+
+impl PresenceFilter {
+    pub fn new(config: PresenceConfig) -> Self {
+        Self {
+            config,
+            present: false,
+            streak: 0,
+            last_change: None,
+        }
+    }
+
+    /// Feed a reading, and get the filtered presence.
+    pub fn update(&mut self, reading: bool) -> bool {
+        if reading == self.present {
+            self.streak = 0;
+        } else {
+            self.streak += 1;
+
+            if self.streak >= self.config.consecutive_readings {
+                self.present = reading;
+                self.streak = 0;
+                self.last_change = Some(Instant::now());
+            }
+        }
+
+        self.present
+    }
+
+    /// Check whether the filtered presence may wake the screen at the
+    /// specified time of day.
+    pub fn wakes_screen(&self, time: NaiveTime) -> bool {
+        let present_for = match self.last_change {
+            Some(at) if self.present => at.elapsed(),
+            _ => return false,
+        };
+
+        self.config
+            .quiet_hours
+            .iter()
+            .filter(|quiet_hours| quiet_hours.contains(time))
+            .all(|quiet_hours| present_for >= quiet_hours.persistence)
+    }
+
+    /// Get the interval to wait for before the next reading.
+    pub fn poll_interval(&self) -> Duration {
+        match &self.config.adaptive {
+            Some(adaptive)
+                if self.streak > 0
+                    || matches!(self.last_change, Some(at) if at.elapsed() < adaptive.duration) =>
+            {
+                adaptive.poll_interval
+            }
+            _ => self.config.poll_interval,
+        }
+    }
+}
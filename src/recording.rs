@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::broadcast::{self, error::RecvError},
+};
+
+use crate::{
+    context::AppContext, gpio_controller::PinMode, home_assistant, presence::DistanceReading,
+    simulation::SimulatedEventKind, tasks,
+};
+
+/// The size from which the events file is rotated.
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// How many rotated events files are kept, as `<path>.1` to `<path>.N`.
+const ROTATED_FILES: usize = 5;
+
+/// The interval at which the input pins are checked for changes.
+const INPUTS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A line of the events file, which can be replayed by the simulation.
+#[derive(Serialize)]
+struct RecordedEvent<'a> {
+    /// The Unix timestamp of the event, in seconds.
+    at: f64,
+
+    /// The same, for humans.
+    time: DateTime<Utc>,
+
+    #[serde(flatten)]
+    kind: &'a SimulatedEventKind,
+}
+
+/// Appends the received Home Assistant states and the local sensor readings
+/// to a JSON lines file, for later replay.
+pub struct Recorder {
+    path: PathBuf,
+}
+
+impl Recorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Record the events forever.
+    pub async fn run(
+        &self,
+        context: &AppContext,
+        mut distance_readings: broadcast::Receiver<DistanceReading>,
+    ) -> anyhow::Result<()> {
+        info!("Recording the events to `{}`.", self.path.display());
+
+        let mut file = self.open().await?;
+        let mut size = file.metadata().await?.len();
+        let mut states = context.home_assistant.watch_states();
+        let mut recorded_states: HashMap<String, DateTime<Utc>> = HashMap::new();
+        let mut recorded_inputs: HashMap<u8, DateTime<Utc>> = HashMap::new();
+        let mut inputs_interval = tokio::time::interval(INPUTS_INTERVAL);
+
+        loop {
+            let mut events = Vec::new();
+
+            tokio::select! {
+                r = states.changed() => {
+                    r?;
+
+                    if let home_assistant::Status::Connected { entities } =
+                        context.home_assistant.status().await
+                    {
+                        for state in entities.values() {
+                            if recorded_states.get(&state.entity_id) == Some(&state.last_updated) {
+                                continue;
+                            }
+
+                            recorded_states.insert(state.entity_id.clone(), state.last_updated);
+                            events.push((
+                                state.last_updated,
+                                SimulatedEventKind::State {
+                                    entity_id: state.entity_id.clone(),
+                                    state: state.state.clone(),
+                                    attributes: state.attributes.parse()?,
+                                },
+                            ));
+                        }
+                    }
+                }
+                r = distance_readings.recv() => match r {
+                    Ok(reading) => {
+                        if let Some(cm) = reading.distance {
+                            events.push((reading.at, SimulatedEventKind::Distance { cm }));
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Skipped recording {} distance readings.", skipped);
+                    }
+                    Err(RecvError::Closed) => return Err(anyhow::anyhow!("distance readings closed")),
+                },
+                _ = inputs_interval.tick() => {
+                    for input in &context.config.inputs {
+                        let status = context.gpio.pin_status(&input.name, input.pin, PinMode::Input);
+
+                        if let (Some(high), Some(last_change)) = (status.high, status.last_change) {
+                            if recorded_inputs.insert(input.pin, last_change) != Some(last_change) {
+                                events.push((
+                                    last_change,
+                                    SimulatedEventKind::Input {
+                                        pin: input.pin,
+                                        status: high,
+                                    },
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+
+            events.sort_by_key(|(time, _)| *time);
+
+            for (time, kind) in &events {
+                let mut line = serde_json::to_vec(&RecordedEvent {
+                    at: time.timestamp_millis() as f64 / 1000.0,
+                    time: *time,
+                    kind,
+                })?;
+
+                line.push(b'\n');
+
+                if size > 0 && size + line.len() as u64 > MAX_FILE_SIZE {
+                    file = self.rotate().await?;
+                    size = 0;
+                }
+
+                if let Err(err) = file.write_all(&line).await {
+                    warn!("Failed to record an event: {}", err);
+                } else {
+                    size += line.len() as u64;
+                }
+            }
+
+            tasks::heartbeat();
+        }
+    }
+
+    async fn open(&self) -> anyhow::Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("failed to open `{}`", self.path.display()))
+    }
+
+    /// Shift the rotated files, dropping the oldest one, and start a new
+    /// file.
+    async fn rotate(&self) -> anyhow::Result<File> {
+        info!("Rotating `{}`.", self.path.display());
+
+        for i in (1..ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, i);
+
+            if tokio::fs::try_exists(&from).await.unwrap_or_default() {
+                tokio::fs::rename(&from, rotated_path(&self.path, i + 1)).await?;
+            }
+        }
+
+        tokio::fs::rename(&self.path, rotated_path(&self.path, 1))
+            .await
+            .with_context(|| format!("failed to rotate `{}`", self.path.display()))?;
+
+        self.open().await
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+
+    path.push(format!(".{}", index));
+
+    path.into()
+}
@@ -0,0 +1,126 @@
+//! The arbiter of the red LED, which several subsystems signal on: each one
+//! claims it with a pattern and releases it, instead of writing the pin, so
+//! that one turning it off does not wipe the signal of another.
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+use log::warn;
+use tokio::sync::watch;
+
+use crate::{
+    gpio_controller::GpioController,
+    outputs::{Output, OutputPattern},
+};
+
+/// A subsystem signaling on the red LED, by decreasing priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RedLedOwner {
+    Panic,
+    Alarm,
+    ErrorPolicy,
+    AuthFailure,
+    WeatherAlerts,
+    MoldRisk,
+}
+
+/// The claims on the red LED.
+pub struct RedLed {
+    claims: Mutex<BTreeMap<RedLedOwner, OutputPattern>>,
+    pattern: watch::Sender<OutputPattern>,
+}
+
+impl RedLed {
+    pub fn new() -> Self {
+        Self {
+            claims: Default::default(),
+            pattern: watch::channel(OutputPattern::Off).0,
+        }
+    }
+
+    /// Claim the red LED with a pattern, replacing the previous claim of the
+    /// owner. Claiming it off releases it.
+    pub fn claim(&self, owner: RedLedOwner, pattern: OutputPattern) {
+        let mut claims = self.claims.lock().unwrap();
+
+        if pattern == OutputPattern::Off {
+            claims.remove(&owner);
+        } else {
+            claims.insert(owner, pattern);
+        }
+
+        self.pattern.send_if_modified(|current| {
+            let pattern = winning_pattern(&claims);
+
+            std::mem::replace(current, pattern) != pattern
+        });
+    }
+
+    /// Release the claim of the owner, if any.
+    pub fn release(&self, owner: RedLedOwner) {
+        self.claim(owner, OutputPattern::Off);
+    }
+
+    /// Claim the red LED until the future is dropped.
+    ///
+    /// Never returns.
+    pub async fn hold(&self, owner: RedLedOwner, pattern: OutputPattern) -> anyhow::Result<()> {
+        struct Claim<'a>(&'a RedLed, RedLedOwner);
+
+        impl Drop for Claim<'_> {
+            fn drop(&mut self) {
+                self.0.release(self.1);
+            }
+        }
+
+        self.claim(owner, pattern);
+
+        let _claim = Claim(self, owner);
+
+        futures_util::future::pending().await
+    }
+
+    /// Get the pattern the red LED is driven with.
+    pub fn pattern(&self) -> OutputPattern {
+        *self.pattern.borrow()
+    }
+
+    /// Drive the red LED with the winning claim forever.
+    pub async fn run(&self, gpio: &GpioController) -> anyhow::Result<()> {
+        let mut patterns = self.pattern.subscribe();
+
+        loop {
+            let pattern = *patterns.borrow_and_update();
+
+            tokio::select! {
+                r = pattern.drive(gpio, Output::RedLed) => {
+                    // A broken LED must not stop the signals.
+                    if let Err(err) = r {
+                        warn!("Failed to drive the red LED: {}", err);
+                    }
+
+                    patterns.changed().await?;
+                }
+                r = patterns.changed() => r?,
+            }
+        }
+    }
+}
+
+impl Default for RedLed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get the pattern of the winning claim: the repeating patterns, which signal
+/// something happening, win over the steady ones, then the highest priority.
+fn winning_pattern(claims: &BTreeMap<RedLedOwner, OutputPattern>) -> OutputPattern {
+    claims
+        .iter()
+        .min_by_key(|(owner, pattern)| (!pattern.is_repeating(), **owner))
+        .map(|(_, pattern)| *pattern)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,73 @@
+//! Tests of the red LED arbiter.
+
+use std::time::Duration;
+
+use super::*;
+
+const BLINK: OutputPattern = OutputPattern::Blink {
+    period: Duration::from_secs(1),
+};
+
+#[test]
+fn the_red_led_is_off_without_claims() {
+    let red_led = RedLed::new();
+
+    assert_eq!(red_led.pattern(), OutputPattern::Off);
+
+    red_led.claim(RedLedOwner::MoldRisk, OutputPattern::On);
+    red_led.release(RedLedOwner::MoldRisk);
+
+    assert_eq!(red_led.pattern(), OutputPattern::Off);
+}
+
+#[test]
+fn releasing_a_claim_restores_the_others() {
+    let red_led = RedLed::new();
+
+    red_led.claim(RedLedOwner::Alarm, OutputPattern::On);
+    red_led.claim(RedLedOwner::WeatherAlerts, BLINK);
+
+    assert_eq!(red_led.pattern(), BLINK);
+
+    red_led.release(RedLedOwner::WeatherAlerts);
+
+    assert_eq!(red_led.pattern(), OutputPattern::On);
+
+    red_led.claim(RedLedOwner::MoldRisk, OutputPattern::On);
+    red_led.release(RedLedOwner::MoldRisk);
+
+    assert_eq!(red_led.pattern(), OutputPattern::On);
+}
+
+#[test]
+fn the_highest_priority_wins_among_the_repeating_patterns() {
+    let red_led = RedLed::new();
+    let fast = OutputPattern::Blink {
+        period: Duration::from_millis(500),
+    };
+
+    red_led.claim(RedLedOwner::WeatherAlerts, BLINK);
+    red_led.claim(RedLedOwner::Panic, fast);
+    red_led.claim(RedLedOwner::Alarm, OutputPattern::On);
+
+    assert_eq!(red_led.pattern(), fast);
+}
+
+#[tokio::test]
+async fn dropping_a_hold_releases_the_claim() {
+    let red_led = RedLed::new();
+
+    red_led.claim(RedLedOwner::MoldRisk, OutputPattern::On);
+
+    tokio::select! {
+        _ = red_led.hold(RedLedOwner::ErrorPolicy, BLINK) => unreachable!(),
+        _ = async {
+            tokio::task::yield_now().await;
+            tokio::task::yield_now().await;
+
+            assert_eq!(red_led.pattern(), BLINK);
+        } => {}
+    }
+
+    assert_eq!(red_led.pattern(), OutputPattern::On);
+}
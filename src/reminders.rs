@@ -0,0 +1,283 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::home_assistant::State;
+
+/// The number of days to look ahead when searching for the next occurrence.
+const LOOKAHEAD_DAYS: i64 = 366;
+
+/// A recurring reminder, like the waste collection.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReminderConfig {
+    /// The name of the reminder, as displayed in the UI.
+    pub name: String,
+
+    /// When the reminder occurs.
+    #[serde(flatten)]
+    pub schedule: ReminderSchedule,
+
+    /// How long before an occurrence the reminder becomes due.
+    ///
+    /// The default makes all-day reminders due the evening before.
+    #[serde(default = "ReminderConfig::default_lead_time")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub lead_time: Duration,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderSchedule {
+    /// The reminder occurs every week on the specified days.
+    Weekdays(Vec<Weekday>),
+
+    /// The reminder occurs according to a recurrence rule.
+    Rrule(Rrule),
+
+    /// The reminder occurs on the next event of a Home Assistant calendar.
+    Calendar(String),
+}
+
+impl ReminderConfig {
+    fn default_lead_time() -> Duration {
+        Duration::from_secs(6 * 3600)
+    }
+
+    /// Get the next occurrence of the reminder that has not ended yet, if any.
+    pub fn next_occurrence(
+        &self,
+        now: DateTime<Local>,
+        entities: &HashMap<String, State>,
+    ) -> Option<UpcomingReminder> {
+        let (start, end) = match &self.schedule {
+            ReminderSchedule::Weekdays(weekdays) => Rrule {
+                frequency: Frequency::Weekly,
+                interval: 1,
+                by_day: weekdays.clone(),
+                start: None,
+            }
+            .next_occurrence(now.date_naive())
+            .and_then(all_day)?,
+            ReminderSchedule::Rrule(rrule) => {
+                rrule.next_occurrence(now.date_naive()).and_then(all_day)?
+            }
+            ReminderSchedule::Calendar(entity_id) => {
+                let state = entities.get(entity_id)?;
+                let attribute = |name: &str| {
+                    state
+                        .attributes
+                        .get::<String>(name)
+                        .and_then(|s| NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S").ok())
+                        .and_then(|dt| Local.from_local_datetime(&dt).earliest())
+                };
+
+                (attribute("start_time")?, attribute("end_time")?)
+            }
+        };
+
+        if end <= now {
+            return None;
+        }
+
+        let lead_time = chrono::Duration::from_std(self.lead_time).ok()?;
+
+        Some(UpcomingReminder {
+            name: self.name.clone(),
+            start: start.with_timezone(&Utc),
+            end: end.with_timezone(&Utc),
+            due: now >= start - lead_time,
+        })
+    }
+}
+
+fn all_day(date: NaiveDate) -> Option<(DateTime<Local>, DateTime<Local>)> {
+    let midnight = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .and_then(|dt| Local.from_local_datetime(&dt).earliest())
+    };
+
+    Some((midnight(date)?, midnight(date.succ_opt()?)?))
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpcomingReminder {
+    pub name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+
+    /// Whether the reminder is within its lead time.
+    pub due: bool,
+}
+
+/// Get the next occurrence of every reminder, soonest first.
+pub fn upcoming(
+    reminders: &[ReminderConfig],
+    entities: &HashMap<String, State>,
+) -> Vec<UpcomingReminder> {
+    let now = Local::now();
+    let mut upcoming: Vec<_> = reminders
+        .iter()
+        .filter_map(|reminder| reminder.next_occurrence(now, entities))
+        .collect();
+
+    upcoming.sort_by_key(|reminder| reminder.start);
+
+    upcoming
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+}
+
+/// A subset of the iCalendar recurrence rules.
+///
+/// Only the `FREQ` (`DAILY` or `WEEKLY`), `INTERVAL` and `BYDAY` parts are
+/// supported, along with a non-standard `DTSTART` part that anchors the
+/// interval, as in `FREQ=WEEKLY;INTERVAL=2;BYDAY=TU;DTSTART=20220104`.
+///
+/// `DTSTART` is required when the rule depends on it: with an `INTERVAL`
+/// above 1, or a weekly rule without `BYDAY`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rrule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<Weekday>,
+    pub start: Option<NaiveDate>,
+}
+
+impl Rrule {
+    /// Get the first date matching the rule, starting from the specified date.
+    ///
+    /// Never matches when the rule requires a start date but has none, as it
+    /// would match every day.
+    pub fn next_occurrence(&self, from: NaiveDate) -> Option<NaiveDate> {
+        let start = match self.start {
+            Some(start) => start,
+            None if self.requires_start() => return None,
+            None => from,
+        };
+
+        (0..LOOKAHEAD_DAYS)
+            .map(|days| from + chrono::Duration::days(days))
+            .filter(|date| *date >= start)
+            .find(|date| self.matches(start, *date))
+    }
+
+    /// Whether the occurrences depend on the start date, rather than only on
+    /// the weekdays.
+    fn requires_start(&self) -> bool {
+        self.interval > 1 || (self.frequency == Frequency::Weekly && self.by_day.is_empty())
+    }
+
+    fn matches(&self, start: NaiveDate, date: NaiveDate) -> bool {
+        let interval = i64::from(self.interval.max(1));
+
+        match self.frequency {
+            Frequency::Daily => {
+                (date - start).num_days() % interval == 0
+                    && (self.by_day.is_empty() || self.by_day.contains(&date.weekday()))
+            }
+            Frequency::Weekly => {
+                let week_start = |date: NaiveDate| {
+                    date - chrono::Duration::days(date.weekday().num_days_from_monday().into())
+                };
+                let weeks = (week_start(date) - week_start(start)).num_weeks();
+                let on_day = if self.by_day.is_empty() {
+                    date.weekday() == start.weekday()
+                } else {
+                    self.by_day.contains(&date.weekday())
+                };
+
+                weeks % interval == 0 && on_day
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Rrule {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl FromStr for Rrule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut by_day = Vec::new();
+        let mut start = None;
+
+        for part in s.trim().trim_start_matches("RRULE:").split(';') {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("invalid recurrence rule part `{}`", part))?;
+
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        _ => anyhow::bail!("unsupported recurrence frequency `{}`", value),
+                    })
+                }
+                "INTERVAL" => interval = value.parse()?,
+                "BYDAY" => {
+                    by_day = value
+                        .split(',')
+                        .map(parse_ical_weekday)
+                        .collect::<anyhow::Result<_>>()?
+                }
+                "DTSTART" => start = Some(NaiveDate::parse_from_str(value, "%Y%m%d")?),
+                _ => anyhow::bail!("unsupported recurrence rule part `{}`", key),
+            }
+        }
+
+        let rrule = Self {
+            frequency: frequency.ok_or_else(|| anyhow::anyhow!("missing recurrence frequency"))?,
+            interval,
+            by_day,
+            start,
+        };
+
+        if rrule.interval == 0 {
+            anyhow::bail!("the recurrence interval must be at least 1");
+        }
+
+        if rrule.start.is_none() && rrule.requires_start() {
+            anyhow::bail!(
+                "the recurrence rule `{}` requires a `DTSTART`, with an `INTERVAL` above 1 or a weekly rule without `BYDAY`",
+                s.trim()
+            );
+        }
+
+        Ok(rrule)
+    }
+}
+
+fn parse_ical_weekday(s: &str) -> anyhow::Result<Weekday> {
+    Ok(match s.to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => anyhow::bail!("invalid recurrence weekday `{}`", s),
+    })
+}
+
+#[cfg(test)]
+mod tests;
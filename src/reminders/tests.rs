@@ -0,0 +1,124 @@
+//! Tests of the reminders.
+
+use super::*;
+
+fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
+
+fn rrule(s: &str) -> Rrule {
+    s.parse().unwrap()
+}
+
+/// Get the first occurrences of the rule, starting from the specified date.
+fn occurrences(rrule: &Rrule, from: &str, count: usize) -> Vec<NaiveDate> {
+    let mut from = date(from);
+
+    (0..count)
+        .map(|_| {
+            let next = rrule.next_occurrence(from).unwrap();
+
+            from = next.succ_opt().unwrap();
+
+            next
+        })
+        .collect()
+}
+
+#[test]
+fn rules_are_parsed() {
+    let rrule = rrule("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,fr;DTSTART=20220104");
+
+    assert_eq!(rrule.frequency, Frequency::Weekly);
+    assert_eq!(rrule.interval, 2);
+    assert_eq!(rrule.by_day, vec![Weekday::Tue, Weekday::Fri]);
+    assert_eq!(rrule.start, Some(date("2022-01-04")));
+}
+
+#[test]
+fn invalid_rules_are_rejected() {
+    for s in [
+        "INTERVAL=2",
+        "FREQ=MONTHLY",
+        "FREQ=DAILY;COUNT=3",
+        "FREQ=DAILY;BYDAY=XX",
+        "FREQ=DAILY;INTERVAL=0",
+        "FREQ=DAILY;DTSTART=2022-01-04",
+    ] {
+        assert!(s.parse::<Rrule>().is_err(), "{}", s);
+    }
+}
+
+#[test]
+fn rules_depending_on_the_start_require_one() {
+    for s in [
+        "FREQ=WEEKLY",
+        "FREQ=DAILY;INTERVAL=2",
+        "FREQ=WEEKLY;INTERVAL=2;BYDAY=TU",
+    ] {
+        let err = s.parse::<Rrule>().unwrap_err();
+
+        assert!(err.to_string().contains("requires a `DTSTART`"), "{}", err);
+    }
+
+    rrule("FREQ=DAILY");
+    rrule("FREQ=WEEKLY;BYDAY=MO");
+}
+
+#[test]
+fn daily_rules_follow_their_interval() {
+    assert_eq!(
+        occurrences(
+            &rrule("FREQ=DAILY;INTERVAL=2;DTSTART=20220104"),
+            "2022-01-01",
+            3
+        ),
+        vec![date("2022-01-04"), date("2022-01-06"), date("2022-01-08")]
+    );
+    assert_eq!(
+        occurrences(
+            &rrule("FREQ=DAILY;INTERVAL=2;DTSTART=20220104"),
+            "2022-01-05",
+            1
+        ),
+        vec![date("2022-01-06")]
+    );
+}
+
+#[test]
+fn weekly_rules_without_days_repeat_the_start_weekday() {
+    // The 4th of January 2022 is a Tuesday.
+    assert_eq!(
+        occurrences(&rrule("FREQ=WEEKLY;DTSTART=20220104"), "2022-01-05", 2),
+        vec![date("2022-01-11"), date("2022-01-18")]
+    );
+}
+
+#[test]
+fn weekly_rules_follow_their_interval_and_days() {
+    assert_eq!(
+        occurrences(
+            &rrule("FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,FR;DTSTART=20220104"),
+            "2022-01-01",
+            4
+        ),
+        vec![
+            date("2022-01-04"),
+            date("2022-01-07"),
+            date("2022-01-18"),
+            date("2022-01-21"),
+        ]
+    );
+}
+
+#[test]
+fn rules_without_a_required_start_never_match() {
+    let rrule = Rrule {
+        frequency: Frequency::Weekly,
+        interval: 1,
+        by_day: Vec::new(),
+        start: None,
+    };
+
+    assert_eq!(rrule.next_occurrence(date("2022-01-04")), None);
+}
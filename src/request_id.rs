@@ -0,0 +1,112 @@
+use std::{convert::Infallible, future::Future, time::Instant};
+
+use log::{debug, warn};
+use rand::Rng;
+use tower_service::Service;
+use warp::{http::HeaderValue, hyper};
+
+use crate::{
+    client_ip,
+    latency::{self, LatencySource},
+};
+
+/// The header carrying the request id, both in requests and responses.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Get the id of the API request being handled, if any.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Run a future within the scope of a request id.
+pub async fn scope<F: Future>(request_id: String, f: F) -> F::Output {
+    REQUEST_ID.scope(request_id, f).await
+}
+
+/// Handle a request with the service, assigning it an id.
+///
+/// The id is taken from the `x-request-id` request header when it is sane, is
+/// available through [`current`] while the request is handled, and is
+/// returned in the `x-request-id` response header.
+pub async fn handle<S>(
+    mut service: S,
+    request: hyper::Request<hyper::Body>,
+) -> Result<hyper::Response<hyper::Body>, Infallible>
+where
+    S: Service<
+        hyper::Request<hyper::Body>,
+        Response = hyper::Response<hyper::Body>,
+        Error = Infallible,
+    >,
+{
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| is_valid(value))
+        .map(ToString::to_string)
+        .unwrap_or_else(generate);
+
+    let client_ip = client_ip::current()
+        .map(|client_ip| client_ip.to_string())
+        .unwrap_or_else(|| "-".to_string());
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let start = Instant::now();
+
+    scope(request_id.clone(), async move {
+        let mut response = service.call(request).await?;
+        let status = response.status();
+
+        latency::record(
+            LatencySource::Api,
+            &latency::endpoint(method.as_str(), &path),
+            start.elapsed(),
+        );
+
+        if status.is_server_error() {
+            warn!(
+                "[{}] {} {} {} -> {} in {:.2?}",
+                request_id,
+                client_ip,
+                method,
+                path,
+                status,
+                start.elapsed()
+            );
+        } else {
+            debug!(
+                "[{}] {} {} {} -> {} in {:.2?}",
+                request_id,
+                client_ip,
+                method,
+                path,
+                status,
+                start.elapsed()
+            );
+        }
+
+        if let Ok(value) = HeaderValue::from_str(&request_id) {
+            response.headers_mut().insert(REQUEST_ID_HEADER, value);
+        }
+
+        Ok(response)
+    })
+    .await
+}
+
+fn generate() -> String {
+    format!("{:016x}", rand::thread_rng().gen::<u64>())
+}
+
+fn is_valid(request_id: &str) -> bool {
+    !request_id.is_empty()
+        && request_id.len() <= 64
+        && request_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
@@ -0,0 +1,123 @@
+use serde::Deserialize;
+
+/// The 433 MHz RF transmitter configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RfConfig {
+    /// The GPIO pin the transmitter data line is connected to.
+    pub pin: u8,
+
+    /// The named codes that can be sent.
+    #[serde(default)]
+    pub codes: Vec<RfCodeConfig>,
+}
+
+impl RfConfig {
+    pub fn code(&self, name: &str) -> Option<&RfCodeConfig> {
+        self.codes.iter().find(|code| code.name == name)
+    }
+}
+
+/// A code, as understood by the rc-switch library.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RfCodeConfig {
+    /// The name of the code, as used in the API path.
+    pub name: String,
+
+    /// The decimal value of the code.
+    pub code: u64,
+
+    /// The number of bits of the code.
+    #[serde(default = "RfCodeConfig::default_bits")]
+    pub bits: u8,
+
+    /// The rc-switch protocol number, from 1 to 5.
+    #[serde(default = "RfCodeConfig::default_protocol")]
+    pub protocol: u8,
+
+    /// Overrides the pulse length of the protocol, in microseconds.
+    #[serde(default)]
+    pub pulse_length: Option<u32>,
+
+    /// The number of times to send the code.
+    #[serde(default = "RfCodeConfig::default_repeat")]
+    pub repeat: u32,
+}
+
+impl RfCodeConfig {
+    fn default_bits() -> u8 {
+        24
+    }
+
+    fn default_protocol() -> u8 {
+        1
+    }
+
+    fn default_repeat() -> u32 {
+        10
+    }
+
+    /// Get the alternating high/low timings of the code, in microseconds.
+    pub fn timings(&self) -> anyhow::Result<Vec<u32>> {
+        if self.bits == 0 || self.bits > 64 {
+            anyhow::bail!("invalid number of bits {}: must be from 1 to 64", self.bits);
+        }
+
+        if self.bits < 64 && self.code >> self.bits != 0 {
+            anyhow::bail!("the code {} does not fit in {} bits", self.code, self.bits);
+        }
+
+        let protocol = RcSwitchProtocol::get(self.protocol)
+            .ok_or_else(|| anyhow::anyhow!("unknown rc-switch protocol {}", self.protocol))?;
+        let pulse_length = self.pulse_length.unwrap_or(protocol.pulse_length);
+        let mut timings = Vec::new();
+
+        for _ in 0..self.repeat.max(1) {
+            for bit in (0..self.bits).rev() {
+                let (high, low) = if self.code & (1u64 << bit) != 0 {
+                    protocol.one
+                } else {
+                    protocol.zero
+                };
+
+                timings.push(high * pulse_length);
+                timings.push(low * pulse_length);
+            }
+
+            timings.push(protocol.sync.0 * pulse_length);
+            timings.push(protocol.sync.1 * pulse_length);
+        }
+
+        Ok(timings)
+    }
+}
+
+/// The timings of an rc-switch protocol, in pulses.
+struct RcSwitchProtocol {
+    pulse_length: u32,
+    sync: (u32, u32),
+    zero: (u32, u32),
+    one: (u32, u32),
+}
+
+impl RcSwitchProtocol {
+    fn get(protocol: u8) -> Option<Self> {
+        let (pulse_length, sync, zero, one) = match protocol {
+            1 => (350, (1, 31), (1, 3), (3, 1)),
+            2 => (650, (1, 10), (1, 2), (2, 1)),
+            3 => (100, (30, 71), (4, 11), (9, 6)),
+            4 => (380, (1, 6), (1, 3), (3, 1)),
+            5 => (500, (6, 14), (1, 2), (2, 1)),
+            _ => return None,
+        };
+
+        Some(Self {
+            pulse_length,
+            sync,
+            zero,
+            one,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,56 @@
+//! Tests of the RF codes.
+
+use super::*;
+
+fn code(code: u64, bits: u8) -> RfCodeConfig {
+    RfCodeConfig {
+        name: "socket".to_string(),
+        code,
+        bits,
+        protocol: 1,
+        pulse_length: None,
+        repeat: 1,
+    }
+}
+
+#[test]
+fn protocol_1_codes_are_sent_most_significant_bit_first() {
+    assert_eq!(
+        code(0b101, 3).timings().unwrap(),
+        vec![1050, 350, 350, 1050, 1050, 350, 350, 10850]
+    );
+}
+
+#[test]
+fn the_codes_are_repeated_with_the_pulse_length() {
+    let code = RfCodeConfig {
+        pulse_length: Some(100),
+        repeat: 2,
+        ..code(0b1, 1)
+    };
+
+    assert_eq!(
+        code.timings().unwrap(),
+        vec![300, 100, 100, 3100, 300, 100, 100, 3100]
+    );
+}
+
+#[test]
+fn all_the_64_bits_can_be_sent() {
+    let timings = code(u64::MAX, 64).timings().unwrap();
+
+    assert_eq!(timings.len(), 2 * 64 + 2);
+    assert!(timings[..128].chunks(2).all(|pulse| pulse == [1050, 350]));
+}
+
+#[test]
+fn invalid_bit_counts_are_rejected() {
+    assert!(code(0, 0).timings().is_err());
+    assert!(code(0, 65).timings().is_err());
+}
+
+#[test]
+fn codes_larger_than_their_bits_are_rejected() {
+    assert!(code(0b1000, 3).timings().is_err());
+    assert!(code(0b111, 3).timings().is_ok());
+}
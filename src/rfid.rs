@@ -0,0 +1,249 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::RwLock;
+
+#[cfg(feature = "rfid")]
+use evdev::{Device, InputEventKind, Key};
+
+use crate::home_assistant::Controller;
+
+/// The HA event fired when a registered tag is scanned.
+const TAG_SCANNED_EVENT: &str = "home_control_tag_scanned";
+
+/// The RFID reader configuration.
+///
+/// Only USB readers that emulate a keyboard (typing the tag id followed by
+/// `Enter`) are supported.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RfidConfig {
+    /// The input device of the reader, like `/dev/input/by-id/usb-...-event-kbd`.
+    pub device: PathBuf,
+
+    /// The alarm control panel entity to disarm, if any.
+    #[serde(default)]
+    pub alarm_entity: Option<String>,
+
+    /// The registered tags.
+    #[serde(default)]
+    pub tags: Vec<TagConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TagConfig {
+    /// The tag id, as typed by the reader.
+    pub id: String,
+
+    /// The user the tag identifies.
+    pub user: String,
+
+    /// The dashboard page to switch to, if any.
+    #[serde(default)]
+    pub page: Option<String>,
+
+    /// Whether the tag may disarm the alarm.
+    #[serde(default)]
+    pub disarm_alarm: bool,
+
+    /// Whether to fire a `home_control_tag_scanned` event in Home Assistant.
+    #[serde(default = "TagConfig::default_fire_event")]
+    pub fire_event: bool,
+}
+
+impl TagConfig {
+    fn default_fire_event() -> bool {
+        true
+    }
+}
+
+/// The last user identified by a tag.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentifiedUser {
+    pub user: String,
+    pub page: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+pub struct Rfid {
+    config: RfidConfig,
+    identified_user: RwLock<Option<IdentifiedUser>>,
+}
+
+impl Rfid {
+    pub fn new(config: RfidConfig) -> Self {
+        Self {
+            config,
+            identified_user: RwLock::new(None),
+        }
+    }
+
+    /// Get the last user identified by a tag, if any.
+    pub async fn identified_user(&self) -> Option<IdentifiedUser> {
+        self.identified_user.read().await.clone()
+    }
+
+    /// Handle a scanned tag.
+    pub async fn on_tag(&self, tag_id: &str, ha_controller: &Controller) {
+        let tag = match self.config.tags.iter().find(|tag| tag.id == tag_id) {
+            Some(tag) => tag,
+            None => {
+                warn!("Unknown RFID tag `{}` was scanned.", tag_id);
+                return;
+            }
+        };
+
+        info!("RFID tag of `{}` was scanned.", tag.user);
+
+        *self.identified_user.write().await = Some(IdentifiedUser {
+            user: tag.user.clone(),
+            page: tag.page.clone(),
+            timestamp: Utc::now(),
+        });
+
+        if tag.fire_event {
+            if let Err(err) = ha_controller
+                .fire_event(
+                    TAG_SCANNED_EVENT,
+                    &json!({ "tag_id": tag.id, "user": tag.user }),
+                )
+                .await
+            {
+                warn!("Failed to fire the tag scanned event: {}", err);
+            }
+        }
+
+        if tag.disarm_alarm {
+            if let Some(alarm_entity) = &self.config.alarm_entity {
+                info!("Disarming `{}` for `{}`.", alarm_entity, tag.user);
+
+                if let Err(err) = ha_controller.alarm_disarm(alarm_entity, None).await {
+                    warn!("Failed to disarm `{}`: {}", alarm_entity, err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rfid")]
+impl Rfid {
+    /// Read tags from the reader forever, reopening it when it fails, like
+    /// after it was unplugged.
+    pub async fn run(&self, ha_controller: &Controller) -> anyhow::Result<()> {
+        use tokio::time::{sleep, Duration, Instant};
+
+        const MIN_BACKOFF: Duration = Duration::from_secs(1);
+        const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            let opened_at = Instant::now();
+
+            if let Err(err) = self.read_tags(ha_controller).await {
+                // A reader that worked for a while is retried promptly.
+                if opened_at.elapsed() > MAX_BACKOFF {
+                    backoff = MIN_BACKOFF;
+                }
+
+                warn!(
+                    "{:#}: reopening it in {:.0}s...",
+                    err,
+                    backoff.as_secs_f64()
+                );
+            }
+
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Read tags from the reader, until it fails.
+    async fn read_tags(&self, ha_controller: &Controller) -> anyhow::Result<()> {
+        use anyhow::Context;
+
+        let mut device = Device::open(&self.config.device).with_context(|| {
+            format!(
+                "failed to open the RFID reader `{}`",
+                self.config.device.display()
+            )
+        })?;
+
+        // Prevent the tag ids from being typed into the kiosk browser.
+        device.grab().context("failed to grab the RFID reader")?;
+
+        let mut events = device
+            .into_event_stream()
+            .context("failed to read from the RFID reader")?;
+        let mut tag_id = String::new();
+
+        info!("Reading RFID tags from `{}`.", self.config.device.display());
+
+        loop {
+            let event = events
+                .next_event()
+                .await
+                .context("failed to read from the RFID reader")?;
+
+            // Only key presses matter.
+            if event.value() != 1 {
+                continue;
+            }
+
+            if let InputEventKind::Key(key) = event.kind() {
+                match key {
+                    Key::KEY_ENTER | Key::KEY_KPENTER => {
+                        if !tag_id.is_empty() {
+                            self.on_tag(&tag_id, ha_controller).await;
+                            tag_id.clear();
+                        }
+                    }
+                    key => {
+                        if let Some(c) = key_to_char(key) {
+                            tag_id.push(c);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rfid")]
+fn key_to_char(key: Key) -> Option<char> {
+    Some(match key {
+        Key::KEY_0 => '0',
+        Key::KEY_1 => '1',
+        Key::KEY_2 => '2',
+        Key::KEY_3 => '3',
+        Key::KEY_4 => '4',
+        Key::KEY_5 => '5',
+        Key::KEY_6 => '6',
+        Key::KEY_7 => '7',
+        Key::KEY_8 => '8',
+        Key::KEY_9 => '9',
+        Key::KEY_A => 'A',
+        Key::KEY_B => 'B',
+        Key::KEY_C => 'C',
+        Key::KEY_D => 'D',
+        Key::KEY_E => 'E',
+        Key::KEY_F => 'F',
+        _ => return None,
+    })
+}
+
+#[cfg(not(feature = "rfid"))]
+impl Rfid {
+    pub async fn run(&self, _ha_controller: &Controller) -> anyhow::Result<()> {
+        warn!(
+            "RFID reader `{}` is configured but RFID support was not compiled in",
+            self.config.device.display()
+        );
+
+        futures_util::future::pending().await
+    }
+}
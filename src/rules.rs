@@ -0,0 +1,6 @@
+//! Reusable detectors, which turn the states of entities into events, and
+//! the declarative rules.
+
+pub mod cycle_detector;
+pub mod engine;
+pub mod preview;
@@ -0,0 +1,152 @@
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_with::{serde_as, DurationSeconds};
+
+/// How to tell the cycles of an appliance from its power draw.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct CycleDetectorConfig {
+    /// The power above which the appliance is running, in watts.
+    #[serde(default = "CycleDetectorConfig::default_threshold")]
+    pub threshold: f64,
+
+    /// For how long the power must stay above the threshold for a cycle to
+    /// start, to ignore the brief draws like the display turning on.
+    #[serde(default = "CycleDetectorConfig::default_start_after")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub start_after: Duration,
+
+    /// For how long the power must stay below the threshold for a cycle to
+    /// finish, to ignore the pauses like a washing machine soaking.
+    #[serde(default = "CycleDetectorConfig::default_finish_after")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub finish_after: Duration,
+}
+
+impl CycleDetectorConfig {
+    fn default_threshold() -> f64 {
+        5.0
+    }
+
+    fn default_start_after() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_finish_after() -> Duration {
+        Duration::from_secs(180)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleEvent {
+    Started,
+
+    /// The cycle finished, after running for the duration, excluding the
+    /// final wait below the threshold.
+    Finished {
+        duration: Duration,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Idle,
+    Starting {
+        since: Instant,
+    },
+    Running {
+        started_at: Instant,
+        below_since: Option<Instant>,
+    },
+}
+
+/// Detects the cycles of an appliance from its power readings.
+#[derive(Debug, Clone)]
+pub struct CycleDetector {
+    config: CycleDetectorConfig,
+    phase: Phase,
+}
+
+impl CycleDetector {
+    pub fn new(config: CycleDetectorConfig) -> Self {
+        Self {
+            config,
+            phase: Phase::Idle,
+        }
+    }
+
+    /// Get when the current cycle started, if running.
+    pub fn running_since(&self) -> Option<Instant> {
+        match self.phase {
+            Phase::Running { started_at, .. } => Some(started_at),
+            Phase::Idle | Phase::Starting { .. } => None,
+        }
+    }
+
+    /// Feed a power reading, in watts.
+    ///
+    /// The readings must be fed periodically, even when unchanged, for the
+    /// durations to elapse.
+    pub fn update(&mut self, power: f64, now: Instant) -> Option<CycleEvent> {
+        let above = power > self.config.threshold;
+
+        match (self.phase, above) {
+            (Phase::Idle, true) => {
+                self.phase = Phase::Starting { since: now };
+
+                None
+            }
+            (Phase::Idle, false) => None,
+            (Phase::Starting { .. }, false) => {
+                self.phase = Phase::Idle;
+
+                None
+            }
+            (Phase::Starting { since }, true) => {
+                if now.duration_since(since) < self.config.start_after {
+                    return None;
+                }
+
+                self.phase = Phase::Running {
+                    started_at: since,
+                    below_since: None,
+                };
+
+                Some(CycleEvent::Started)
+            }
+            (Phase::Running { started_at, .. }, true) => {
+                self.phase = Phase::Running {
+                    started_at,
+                    below_since: None,
+                };
+
+                None
+            }
+            (
+                Phase::Running {
+                    started_at,
+                    below_since,
+                },
+                false,
+            ) => {
+                let below_since = below_since.unwrap_or(now);
+
+                if now.duration_since(below_since) < self.config.finish_after {
+                    self.phase = Phase::Running {
+                        started_at,
+                        below_since: Some(below_since),
+                    };
+
+                    return None;
+                }
+
+                self.phase = Phase::Idle;
+
+                Some(CycleEvent::Finished {
+                    duration: below_since.duration_since(started_at),
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, Utc, Weekday};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    actions::Action,
+    context::AppContext,
+    history::{ActionResult, ConditionResult, NewExecution},
+    holidays::HolidaysConfig,
+    home_assistant::{Attributes, State, Status, Update},
+    tasks,
+};
+
+/// The interval between two evaluations of the rules, for the time and
+/// duration conditions to apply without a change of state.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The Home-Assistant entity of the position of the sun.
+const SUN_ENTITY: &str = "sun.sun";
+
+/// A declarative rule, which runs its actions once all its conditions become
+/// true.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleConfig {
+    /// The identifier of the rule, as used in the API path.
+    pub id: String,
+
+    /// The conditions, which must all be true. The rule always matches if
+    /// there are none.
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    pub actions: Vec<Action>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    /// An entity is in a state, like `light.hallway` being `on`, optionally
+    /// for at least a while.
+    State {
+        entity_id: String,
+        state: String,
+
+        /// For how long the entity must have been in the state, in seconds.
+        #[serde(default, rename = "for", skip_serializing_if = "Option::is_none")]
+        #[serde_as(as = "Option<DurationSeconds<f64>>")]
+        #[schemars(rename = "for", with = "Option<f64>")]
+        held_for: Option<Duration>,
+    },
+
+    /// The numeric state or attribute of an entity is within bounds, both
+    /// excluded.
+    ///
+    /// Once matched, the bounds are widened by the hysteresis, for the
+    /// condition not to flap around them.
+    NumericState {
+        entity_id: String,
+
+        /// The attribute to compare rather than the state, like
+        /// `temperature`.
+        #[serde(default)]
+        attribute: Option<String>,
+
+        #[serde(default)]
+        above: Option<f64>,
+
+        #[serde(default)]
+        below: Option<f64>,
+
+        #[serde(default)]
+        hysteresis: f64,
+    },
+
+    /// The time of day is within bounds, possibly over midnight, like from
+    /// `22:00` to `06:00`, on some days of the week.
+    Time {
+        #[serde(default)]
+        after: Option<NaiveTime>,
+
+        #[serde(default)]
+        before: Option<NaiveTime>,
+
+        /// The days, like `Mon`. Every day if empty.
+        #[serde(default)]
+        weekdays: Vec<Weekday>,
+    },
+
+    /// The elevation of the sun is within bounds, in degrees, as reported by
+    /// the `sun.sun` entity.
+    SunElevation {
+        #[serde(default)]
+        above: Option<f64>,
+
+        #[serde(default)]
+        below: Option<f64>,
+    },
+
+    /// The day is a holiday, or is not one if `holiday` is `false`.
+    Holiday {
+        #[serde(default = "Condition::default_holiday")]
+        holiday: bool,
+    },
+}
+
+/// How a condition was evaluated.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionTrace {
+    pub condition: Condition,
+    pub matched: bool,
+
+    /// The value the condition was evaluated against, like a state or a
+    /// time, if any.
+    pub actual: Option<String>,
+}
+
+/// How a rule was evaluated.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleTrace {
+    pub id: String,
+    pub matched: bool,
+    pub conditions: Vec<ConditionTrace>,
+
+    /// The actions that would run, if the rule just started matching.
+    pub actions: Vec<Action>,
+}
+
+/// The state of an entity, as seen by the conditions.
+#[derive(Debug, Clone)]
+pub struct EntitySnapshot {
+    pub state: String,
+    pub last_changed: DateTime<Utc>,
+    pub attributes: Attributes,
+}
+
+impl From<&State> for EntitySnapshot {
+    fn from(state: &State) -> Self {
+        Self {
+            state: state.state.clone(),
+            last_changed: state.last_changed,
+            attributes: state.attributes.clone(),
+        }
+    }
+}
+
+/// What the conditions are evaluated against.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// The states of the entities, by id.
+    pub states: HashMap<String, EntitySnapshot>,
+    pub now: DateTime<Utc>,
+
+    /// The local time, for the time conditions.
+    pub local: NaiveDateTime,
+
+    /// Whether the local day is a holiday.
+    pub holiday: bool,
+}
+
+impl Snapshot {
+    /// Take the states of some entities, at a time.
+    pub fn new<'a>(
+        entities: &HashMap<String, State>,
+        entity_ids: impl IntoIterator<Item = &'a str>,
+        now: DateTime<Local>,
+        holidays: Option<&HolidaysConfig>,
+    ) -> Self {
+        let today = Local::now().date_naive();
+
+        Self {
+            states: entity_ids
+                .into_iter()
+                .filter_map(|entity_id| {
+                    entities
+                        .get(entity_id)
+                        .map(|state| (entity_id.to_string(), state.into()))
+                })
+                .collect(),
+            now: now.with_timezone(&Utc),
+            local: now.naive_local(),
+            holiday: holidays
+                .is_some_and(|holidays| holidays.is_holiday(now.date_naive(), today, entities)),
+        }
+    }
+}
+
+/// Check whether conditions all match the current states, as the ones of
+/// the actions do.
+pub async fn check(conditions: &[Condition], context: &AppContext) -> anyhow::Result<bool> {
+    let snapshot = match context.home_assistant.status().await {
+        Status::Connected { entities } => Snapshot::new(
+            &entities,
+            conditions.iter().filter_map(Condition::entity_id),
+            Local::now(),
+            context.config.holidays.as_ref(),
+        ),
+        Status::Disconnected => anyhow::bail!("Home-Assistant is disconnected"),
+    };
+
+    Ok(conditions
+        .iter()
+        .all(|condition| condition.evaluate(&snapshot, false).matched))
+}
+
+/// Check that a value is within bounds, both excluded.
+fn within(value: f64, above: Option<f64>, below: Option<f64>) -> bool {
+    above.is_none_or(|above| value > above) && below.is_none_or(|below| value < below)
+}
+
+/// Check whether a local time is within a time window, possibly over
+/// midnight, on some days of the week.
+pub(super) fn in_time_window(
+    after: Option<NaiveTime>,
+    before: Option<NaiveTime>,
+    weekdays: &[Weekday],
+    local: NaiveDateTime,
+) -> bool {
+    let time = local.time();
+    let in_window = match (after, before) {
+        (Some(after), Some(before)) if after > before => after <= time || time < before,
+        _ => after.is_none_or(|after| after <= time) && before.is_none_or(|before| time < before),
+    };
+
+    in_window && (weekdays.is_empty() || weekdays.contains(&local.weekday()))
+}
+
+impl Condition {
+    fn default_holiday() -> bool {
+        true
+    }
+
+    pub fn entity_id(&self) -> Option<&str> {
+        match self {
+            Self::State { entity_id, .. } | Self::NumericState { entity_id, .. } => Some(entity_id),
+            Self::SunElevation { .. } => Some(SUN_ENTITY),
+            Self::Time { .. } | Self::Holiday { .. } => None,
+        }
+    }
+
+    /// Evaluate the condition against a snapshot, knowing whether it matched
+    /// the previous time.
+    pub fn evaluate(&self, snapshot: &Snapshot, previous: bool) -> ConditionTrace {
+        let (matched, actual) = match self {
+            Self::State {
+                entity_id,
+                state,
+                held_for,
+            } => match snapshot.states.get(entity_id) {
+                Some(entity) => {
+                    let held = (snapshot.now - entity.last_changed)
+                        .to_std()
+                        .unwrap_or_default();
+                    let matched =
+                        entity.state == *state && held_for.is_none_or(|held_for| held >= held_for);
+
+                    (
+                        matched,
+                        Some(format!("{} for {}s", entity.state, held.as_secs())),
+                    )
+                }
+                None => (false, None),
+            },
+            Self::NumericState {
+                entity_id,
+                attribute,
+                above,
+                below,
+                hysteresis,
+            } => {
+                let value = snapshot
+                    .states
+                    .get(entity_id)
+                    .and_then(|entity| match attribute {
+                        Some(attribute) => entity.attributes.get::<f64>(attribute),
+                        None => entity.state.parse::<f64>().ok(),
+                    });
+                let (above, below) = if previous {
+                    (
+                        above.map(|above| above - hysteresis),
+                        below.map(|below| below + hysteresis),
+                    )
+                } else {
+                    (*above, *below)
+                };
+
+                (
+                    value.is_some_and(|value| within(value, above, below)),
+                    value.map(|value| value.to_string()),
+                )
+            }
+            Self::Time {
+                after,
+                before,
+                weekdays,
+            } => (
+                in_time_window(*after, *before, weekdays, snapshot.local),
+                Some(format!(
+                    "{} {}",
+                    snapshot.local.weekday(),
+                    snapshot.local.time().format("%H:%M:%S")
+                )),
+            ),
+            Self::SunElevation { above, below } => {
+                let elevation = snapshot
+                    .states
+                    .get(SUN_ENTITY)
+                    .and_then(|sun| sun.attributes.get::<f64>("elevation"));
+
+                (
+                    elevation.is_some_and(|elevation| within(elevation, *above, *below)),
+                    elevation.map(|elevation| elevation.to_string()),
+                )
+            }
+            Self::Holiday { holiday } => (
+                snapshot.holiday == *holiday,
+                Some(
+                    if snapshot.holiday {
+                        "holiday"
+                    } else {
+                        "workday"
+                    }
+                    .to_string(),
+                ),
+            ),
+        };
+
+        ConditionTrace {
+            condition: self.clone(),
+            matched,
+            actual,
+        }
+    }
+}
+
+impl RuleConfig {
+    /// Get the entities the conditions of the rule depend on.
+    pub fn entity_ids(&self) -> impl Iterator<Item = &str> {
+        self.conditions.iter().filter_map(Condition::entity_id)
+    }
+
+    /// Evaluate the conditions of the rule, without running its actions,
+    /// knowing which of them matched the previous time, if any.
+    pub fn trace(&self, snapshot: &Snapshot, previous: &[bool]) -> RuleTrace {
+        let conditions: Vec<_> = self
+            .conditions
+            .iter()
+            .enumerate()
+            .map(|(i, condition)| {
+                condition.evaluate(snapshot, previous.get(i).copied().unwrap_or_default())
+            })
+            .collect();
+        let matched = conditions.iter().all(|condition| condition.matched);
+
+        RuleTrace {
+            id: self.id.clone(),
+            matched,
+            conditions,
+            actions: if matched {
+                self.actions.clone()
+            } else {
+                Vec::new()
+            },
+        }
+    }
+}
+
+/// The last evaluation of a rule.
+#[derive(Debug, Clone, Default)]
+struct Evaluation {
+    matched: bool,
+    conditions: Vec<bool>,
+}
+
+/// Run the actions of the rules whenever they start matching, forever, and
+/// record their executions in the history, if any.
+///
+/// The rules already matching on start are left alone, as they did not just
+/// start to.
+pub async fn run(rules: &[RuleConfig], context: &AppContext) -> anyhow::Result<()> {
+    let mut updates = context.home_assistant.subscribe_updates();
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    let mut evaluations: Vec<Option<Evaluation>> = vec![None; rules.len()];
+    let entity_ids: BTreeSet<&str> = rules.iter().flat_map(RuleConfig::entity_ids).collect();
+
+    loop {
+        let trigger = tokio::select! {
+            _ = interval.tick() => "periodic check".to_string(),
+            update = updates.recv() => match update {
+                Ok(Update::Event(event)) => event.to_string(),
+                Err(RecvError::Lagged(_)) => "missed changes".to_string(),
+
+                // The rules are not evaluated against outdated states.
+                Ok(Update::Connected | Update::Disconnected) => continue,
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        };
+
+        tasks::heartbeat();
+
+        let snapshot = match context.home_assistant.status().await {
+            Status::Connected { entities } => Snapshot::new(
+                &entities,
+                entity_ids.iter().copied(),
+                Local::now(),
+                context.config.holidays.as_ref(),
+            ),
+            Status::Disconnected => continue,
+        };
+
+        for (rule, evaluation) in rules.iter().zip(&mut evaluations) {
+            let previous = evaluation.take();
+            let trace = rule.trace(
+                &snapshot,
+                previous
+                    .as_ref()
+                    .map(|previous| previous.conditions.as_slice())
+                    .unwrap_or_default(),
+            );
+
+            *evaluation = Some(Evaluation {
+                matched: trace.matched,
+                conditions: trace
+                    .conditions
+                    .iter()
+                    .map(|condition| condition.matched)
+                    .collect(),
+            });
+
+            // The actions run on their own, as their delays must not hold
+            // the other rules back.
+            if trace.matched && previous.is_some_and(|previous| !previous.matched) {
+                let rule = rule.clone();
+                let trigger = trigger.clone();
+                let context = context.clone();
+                tokio::spawn(async move {
+                    execute(&rule, trace, &trigger, &context).await;
+                });
+            }
+        }
+    }
+}
+
+/// Run the actions of a rule that just matched.
+async fn execute(rule: &RuleConfig, trace: RuleTrace, trigger: &str, context: &AppContext) {
+    let started_at = Utc::now();
+    let start = Instant::now();
+    let mut actions = Vec::with_capacity(trace.actions.len());
+
+    info!("The rule `{}` matched: running its actions.", rule.id);
+
+    for action in &trace.actions {
+        let error = match action.run(context).await {
+            Ok(()) => None,
+            Err(err) => {
+                warn!("Failed to run an action of the rule `{}`: {}", rule.id, err);
+
+                Some(err.to_string())
+            }
+        };
+
+        actions.push(ActionResult {
+            action: serde_json::to_value(action).unwrap_or_default(),
+            error,
+        });
+    }
+
+    if let Some(history) = &context.storage.history {
+        history
+            .record(NewExecution {
+                source: rule.id.clone(),
+                trigger: trigger.to_string(),
+                started_at,
+                duration: start.elapsed(),
+                conditions: trace
+                    .conditions
+                    .into_iter()
+                    .map(|condition| ConditionResult {
+                        condition: serde_json::to_value(condition.condition).unwrap_or_default(),
+                        matched: condition.matched,
+                        actual: condition.actual,
+                    })
+                    .collect(),
+                actions,
+            })
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests;
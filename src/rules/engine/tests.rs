@@ -0,0 +1,302 @@
+//! Tests of the conditions of the rules, as configured.
+
+use chrono::TimeZone;
+use serde_json::{json, Value};
+
+use super::*;
+
+/// Parse a condition from its configuration.
+fn condition(yaml: &str) -> Condition {
+    serde_yaml::from_str(yaml).expect("invalid condition")
+}
+
+/// Get a snapshot without states at a local time, like
+/// `2024-05-06T23:30:00` (a Monday), the local time zone being UTC.
+fn snapshot(local: &str) -> Snapshot {
+    let local: NaiveDateTime = local.parse().expect("invalid time");
+
+    Snapshot {
+        states: HashMap::new(),
+        now: Utc.from_utc_datetime(&local),
+        local,
+        holiday: false,
+    }
+}
+
+impl Snapshot {
+    /// Add the state of an entity, which changed some seconds ago.
+    fn with(mut self, entity_id: &str, state: &str, seconds_ago: i64, attributes: Value) -> Self {
+        self.states.insert(
+            entity_id.to_string(),
+            EntitySnapshot {
+                state: state.to_string(),
+                last_changed: self.now - chrono::Duration::seconds(seconds_ago),
+                attributes: attributes.into(),
+            },
+        );
+
+        self
+    }
+}
+
+fn matches(condition: &Condition, snapshot: &Snapshot) -> bool {
+    condition.evaluate(snapshot, false).matched
+}
+
+#[test]
+fn state_matches_the_state() {
+    let condition = condition("{type: state, entity_id: light.hallway, state: 'on'}");
+    let snapshot = snapshot("2024-05-06T12:00:00");
+
+    assert!(matches(
+        &condition,
+        &snapshot.clone().with("light.hallway", "on", 0, json!({}))
+    ));
+    assert!(!matches(
+        &condition,
+        &snapshot.clone().with("light.hallway", "off", 0, json!({}))
+    ));
+    assert!(!matches(&condition, &snapshot));
+}
+
+#[test]
+fn state_for_requires_the_state_held_long_enough() {
+    let condition = condition("{type: state, entity_id: door.front, state: open, for: 300}");
+    let snapshot = snapshot("2024-05-06T12:00:00");
+
+    assert!(!matches(
+        &condition,
+        &snapshot.clone().with("door.front", "open", 299, json!({}))
+    ));
+    assert!(matches(
+        &condition,
+        &snapshot.clone().with("door.front", "open", 300, json!({}))
+    ));
+    assert!(!matches(
+        &condition,
+        &snapshot.with("door.front", "closed", 3600, json!({}))
+    ));
+}
+
+#[test]
+fn numeric_state_excludes_the_bounds() {
+    let condition = condition("{type: numeric_state, entity_id: sensor.lux, above: 10, below: 20}");
+    let snapshot = snapshot("2024-05-06T12:00:00");
+
+    for (state, expected) in [
+        ("10", false),
+        ("10.5", true),
+        ("19.9", true),
+        ("20", false),
+        ("unavailable", false),
+    ] {
+        assert_eq!(
+            matches(
+                &condition,
+                &snapshot.clone().with("sensor.lux", state, 0, json!({}))
+            ),
+            expected,
+            "{}",
+            state
+        );
+    }
+}
+
+#[test]
+fn numeric_state_compares_an_attribute() {
+    let condition = condition(
+        "{type: numeric_state, entity_id: climate.living_room, attribute: current_temperature, below: 18}",
+    );
+    let snapshot = snapshot("2024-05-06T12:00:00");
+
+    assert!(matches(
+        &condition,
+        &snapshot.clone().with(
+            "climate.living_room",
+            "heat",
+            0,
+            json!({ "current_temperature": 17.5 })
+        )
+    ));
+    assert!(!matches(
+        &condition,
+        &snapshot.clone().with(
+            "climate.living_room",
+            "heat",
+            0,
+            json!({ "current_temperature": 19 })
+        )
+    ));
+    assert!(!matches(
+        &condition,
+        &snapshot.with("climate.living_room", "heat", 0, json!({}))
+    ));
+}
+
+#[test]
+fn numeric_state_hysteresis_widens_the_bounds_once_matched() {
+    let condition = condition(
+        "{type: numeric_state, entity_id: sensor.humidity, above: 60, below: 90, hysteresis: 5}",
+    );
+    let snapshot = snapshot("2024-05-06T12:00:00");
+    let at = |humidity: &str| {
+        snapshot
+            .clone()
+            .with("sensor.humidity", humidity, 0, json!({}))
+    };
+
+    assert!(!condition.evaluate(&at("58"), false).matched);
+    assert!(condition.evaluate(&at("58"), true).matched);
+    assert!(!condition.evaluate(&at("55"), true).matched);
+    assert!(!condition.evaluate(&at("92"), false).matched);
+    assert!(condition.evaluate(&at("92"), true).matched);
+}
+
+#[test]
+fn time_matches_the_window() {
+    let condition = condition("{type: time, after: '08:00', before: '18:00'}");
+
+    assert!(!matches(&condition, &snapshot("2024-05-06T07:59:59")));
+    assert!(matches(&condition, &snapshot("2024-05-06T08:00:00")));
+    assert!(matches(&condition, &snapshot("2024-05-06T17:59:59")));
+    assert!(!matches(&condition, &snapshot("2024-05-06T18:00:00")));
+}
+
+#[test]
+fn time_matches_the_window_over_midnight() {
+    let condition = condition("{type: time, after: '22:00', before: '06:00'}");
+
+    assert!(matches(&condition, &snapshot("2024-05-06T23:30:00")));
+    assert!(matches(&condition, &snapshot("2024-05-07T05:59:00")));
+    assert!(!matches(&condition, &snapshot("2024-05-07T06:00:00")));
+    assert!(!matches(&condition, &snapshot("2024-05-07T12:00:00")));
+}
+
+#[test]
+fn time_matches_the_weekdays() {
+    let condition = condition("{type: time, weekdays: [Sat, Sun]}");
+
+    // A Monday, then a Saturday.
+    assert!(!matches(&condition, &snapshot("2024-05-06T12:00:00")));
+    assert!(matches(&condition, &snapshot("2024-05-11T12:00:00")));
+}
+
+#[test]
+fn sun_elevation_reads_the_sun_entity() {
+    let condition = condition("{type: sun_elevation, below: -6}");
+    let snapshot = snapshot("2024-05-06T21:00:00");
+
+    assert_eq!(condition.entity_id(), Some("sun.sun"));
+    assert!(matches(
+        &condition,
+        &snapshot
+            .clone()
+            .with("sun.sun", "below_horizon", 0, json!({ "elevation": -8.2 }))
+    ));
+    assert!(!matches(
+        &condition,
+        &snapshot
+            .clone()
+            .with("sun.sun", "below_horizon", 0, json!({ "elevation": -3.0 }))
+    ));
+    assert!(!matches(&condition, &snapshot));
+}
+
+#[test]
+fn holiday_matches_the_holidays_by_default() {
+    let holiday = condition("{type: holiday}");
+    let workday = condition("{type: holiday, holiday: false}");
+    let snapshot = snapshot("2024-12-25T07:00:00");
+
+    assert!(!matches(&holiday, &snapshot));
+    assert!(matches(&workday, &snapshot));
+
+    let snapshot = Snapshot {
+        holiday: true,
+        ..snapshot
+    };
+
+    assert!(matches(&holiday, &snapshot));
+    assert!(!matches(&workday, &snapshot));
+}
+
+#[test]
+fn rule_matches_when_all_conditions_match() {
+    let rule: RuleConfig = serde_yaml::from_str(
+        "
+id: night-light
+conditions:
+  - {type: state, entity_id: binary_sensor.motion, state: 'on'}
+  - {type: numeric_state, entity_id: sensor.lux, below: 10, hysteresis: 2}
+actions:
+  - {type: toggle, entity_id: light.hallway}
+",
+    )
+    .expect("invalid rule");
+    let snapshot = snapshot("2024-05-06T23:30:00")
+        .with("binary_sensor.motion", "on", 0, json!({}))
+        .with("sensor.lux", "11", 0, json!({}));
+
+    let trace = rule.trace(&snapshot, &[]);
+
+    assert!(!trace.matched);
+    assert!(trace.conditions[0].matched);
+    assert!(!trace.conditions[1].matched);
+    assert!(trace.actions.is_empty());
+
+    // The lux condition matched before, and holds within its hysteresis.
+    let trace = rule.trace(&snapshot, &[true, true]);
+
+    assert!(trace.matched);
+    assert_eq!(trace.actions.len(), 1);
+    assert_eq!(
+        rule.entity_ids().collect::<Vec<_>>(),
+        ["binary_sensor.motion", "sensor.lux"]
+    );
+}
+
+#[test]
+fn rule_actions_compose() {
+    let rule: RuleConfig = serde_yaml::from_str(
+        "
+id: arrival
+actions:
+  - type: parallel
+    actions:
+      - {type: toggle, entity_id: light.hallway}
+      - type: sequence
+        actions:
+          - {type: delay, duration: 2}
+          - {type: call_service, service: script.welcome}
+  - type: choose
+    choices:
+      - conditions: [{type: sun_elevation, below: 0}]
+        actions: [{type: toggle, entity_id: light.porch}]
+    default: [{type: call_service, service: cover.open_cover}]
+  - type: repeat
+    until: [{type: state, entity_id: cover.garage, state: closed}]
+    actions: [{type: delay, duration: 10}]
+",
+    )
+    .expect("invalid rule");
+
+    let Action::Parallel { actions } = &rule.actions[0] else {
+        panic!("expected a parallel action");
+    };
+    let Action::Sequence { actions } = &actions[1] else {
+        panic!("expected a sequence action");
+    };
+
+    assert!(matches!(
+        actions[0],
+        Action::Delay { duration } if duration == Duration::from_secs(2)
+    ));
+    assert!(matches!(
+        &rule.actions[1],
+        Action::Choose { choices, default } if choices.len() == 1 && default.len() == 1
+    ));
+    assert!(matches!(
+        &rule.actions[2],
+        Action::Repeat { count: None, until, .. } if until.len() == 1
+    ));
+}
@@ -0,0 +1,264 @@
+//! The upcoming firings of the schedules and the rules, to check the changes
+//! of the UTC offset and the sun-relative rules ahead of time.
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Utc,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::engine::{in_time_window, Condition, RuleConfig};
+use crate::{
+    astronomy::AstronomyConfig, holidays::HolidaysConfig, irrigation::IrrigationSchedule,
+    wakeup::WakeupAlarm,
+};
+
+/// How a change of the UTC offset affects a firing.
+///
+/// The schedules compare naive local times, so they fire once the clocks
+/// are past the local time, however they got there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum DstShift {
+    /// The local time is skipped when the clocks go forward: it fires once
+    /// they did.
+    Postponed,
+
+    /// The local time is skipped when the clocks go forward, and so is the
+    /// firing.
+    Skipped,
+
+    /// The local time occurs twice when the clocks go back, and so does the
+    /// firing.
+    Repeated,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Firing {
+    /// When it fires, in local time.
+    pub at: DateTime<FixedOffset>,
+
+    /// What fires: `irrigation`, `wakeup` or `rule`.
+    pub source: &'static str,
+    pub rule_id: Option<String>,
+
+    /// What fires it, like `06:00` or `sun above 5°`.
+    pub trigger: String,
+    pub dst: Option<DstShift>,
+}
+
+/// The schedules and the rules to preview.
+pub struct Schedules<'a> {
+    pub irrigation: &'a [IrrigationSchedule],
+    pub wakeup: &'a [WakeupAlarm],
+    pub rules: &'a [RuleConfig],
+
+    /// The location of the panel, without which the sun-relative rules are
+    /// left out.
+    pub astronomy: Option<&'a AstronomyConfig>,
+
+    /// The holidays, of which only the listed dates are known ahead.
+    pub holidays: Option<&'a HolidaysConfig>,
+}
+
+impl Schedules<'_> {
+    /// List the firings after `from`, up to `until`, in order.
+    ///
+    /// The rules are evaluated periodically, so they fire up to half a minute
+    /// later, and only if their conditions on the entity states hold then.
+    pub fn preview<Tz: TimeZone>(
+        &self,
+        tz: &Tz,
+        from: &DateTime<Tz>,
+        until: &DateTime<Tz>,
+    ) -> Vec<Firing> {
+        // The day before, for the sun crossings of the solar days.
+        let dates: Vec<_> = from
+            .naive_local()
+            .date()
+            .pred_opt()
+            .into_iter()
+            .flat_map(|date| date.iter_days())
+            .take_while(|date| *date <= until.naive_local().date())
+            .collect();
+        let mut firings = Vec::new();
+
+        for date in &dates {
+            let holiday = self.is_holiday(*date);
+
+            for schedule in self.irrigation {
+                if schedule.skip_holidays && holiday {
+                    continue;
+                }
+
+                firings.extend(
+                    local_firings(tz, date.and_time(schedule.at), DstShift::Postponed)
+                        .into_iter()
+                        .map(|(at, dst)| Firing {
+                            at: at.fixed_offset(),
+                            source: "irrigation",
+                            rule_id: None,
+                            trigger: schedule.at.format("%H:%M").to_string(),
+                            dst,
+                        }),
+                );
+            }
+
+            // The wake-up gives up on the alarms it cannot place in time.
+            for alarm in self.wakeup {
+                if alarm.rings_on(date.weekday()) && !(alarm.skip_holidays && holiday) {
+                    firings.extend(
+                        local_firings(tz, date.and_time(alarm.at), DstShift::Skipped)
+                            .into_iter()
+                            .map(|(at, dst)| Firing {
+                                at: at.fixed_offset(),
+                                source: "wakeup",
+                                rule_id: None,
+                                trigger: alarm.at.format("%H:%M").to_string(),
+                                dst,
+                            }),
+                    );
+                }
+            }
+        }
+
+        for rule in self.rules {
+            let mut candidates = Vec::new();
+
+            for condition in &rule.conditions {
+                match condition {
+                    Condition::Time { after, .. } => {
+                        let after = after.unwrap_or(NaiveTime::MIN);
+
+                        for date in &dates {
+                            candidates.extend(
+                                local_firings(tz, date.and_time(after), DstShift::Postponed)
+                                    .into_iter()
+                                    .map(|(at, dst)| (at, after.format("%H:%M").to_string(), dst)),
+                            );
+                        }
+                    }
+                    Condition::SunElevation { above, below } => {
+                        let astronomy = match self.astronomy {
+                            Some(astronomy) => astronomy,
+                            None => continue,
+                        };
+                        let crossings = [(above, true, "above"), (below, false, "below")];
+
+                        for date in &dates {
+                            for (altitude, rising, direction) in crossings {
+                                if let Some(at) = altitude.and_then(|altitude| {
+                                    astronomy.sun_crossing(*date, altitude, rising)
+                                }) {
+                                    candidates.push((
+                                        at.with_timezone(tz),
+                                        format!(
+                                            "sun {} {}°",
+                                            direction,
+                                            altitude.unwrap_or_default()
+                                        ),
+                                        None,
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                    Condition::Holiday { .. } => {
+                        for date in &dates {
+                            candidates.extend(
+                                local_firings(
+                                    tz,
+                                    date.and_time(NaiveTime::MIN),
+                                    DstShift::Postponed,
+                                )
+                                .into_iter()
+                                .map(|(at, dst)| (at, "midnight".to_string(), dst)),
+                            );
+                        }
+                    }
+                    Condition::State { .. } | Condition::NumericState { .. } => {}
+                }
+            }
+
+            candidates.sort_by(|a, b| a.0.cmp(&b.0));
+            candidates.dedup_by(|a, b| a.0 == b.0);
+
+            // The rules fire when they start matching only.
+            firings.extend(
+                candidates
+                    .into_iter()
+                    .filter(|(at, _, _)| {
+                        self.matches(rule, at)
+                            && !self.matches(rule, &(at.clone() - Duration::minutes(1)))
+                    })
+                    .map(|(at, trigger, dst)| Firing {
+                        at: at.fixed_offset(),
+                        source: "rule",
+                        rule_id: Some(rule.id.clone()),
+                        trigger,
+                        dst,
+                    }),
+            );
+        }
+
+        let (from, until) = (from.fixed_offset(), until.fixed_offset());
+
+        firings.retain(|firing| from < firing.at && firing.at <= until);
+        firings.sort_by_key(|firing| firing.at);
+        firings
+    }
+
+    /// Check whether the time and sun conditions of a rule match at a time.
+    fn matches<Tz: TimeZone>(&self, rule: &RuleConfig, at: &DateTime<Tz>) -> bool {
+        let utc = at.with_timezone(&Utc);
+
+        rule.conditions.iter().all(|condition| match condition {
+            Condition::Time {
+                after,
+                before,
+                weekdays,
+            } => in_time_window(*after, *before, weekdays, at.naive_local()),
+            Condition::SunElevation { above, below } => match self.astronomy {
+                Some(astronomy) => {
+                    above.is_none_or(|above| astronomy.sun_above(utc, above))
+                        && below.is_none_or(|below| !astronomy.sun_above(utc, below))
+                }
+                None => true,
+            },
+            Condition::Holiday { holiday } => self.is_holiday(at.naive_local().date()) == *holiday,
+            Condition::State { .. } | Condition::NumericState { .. } => true,
+        })
+    }
+
+    fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.holidays
+            .is_some_and(|holidays| holidays.dates.contains(&date))
+    }
+}
+
+/// Place a naive local time, the way the schedules fire at it.
+fn local_firings<Tz: TimeZone>(
+    tz: &Tz,
+    at: NaiveDateTime,
+    gap: DstShift,
+) -> Vec<(DateTime<Tz>, Option<DstShift>)> {
+    match tz.from_local_datetime(&at) {
+        LocalResult::Single(at) => vec![(at, None)],
+        LocalResult::Ambiguous(first, second) => vec![
+            (first, Some(DstShift::Repeated)),
+            (second, Some(DstShift::Repeated)),
+        ],
+        LocalResult::None => (1..=24 * 60)
+            .find_map(|minutes| {
+                tz.from_local_datetime(&(at + Duration::minutes(minutes)))
+                    .earliest()
+            })
+            .map(|at| vec![(at, Some(gap))])
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests;
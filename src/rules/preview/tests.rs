@@ -0,0 +1,103 @@
+//! Tests of the preview of the schedules and the rules, in UTC.
+
+use super::*;
+
+fn at(time: &str) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&time.parse().expect("invalid time"))
+}
+
+fn rule(yaml: &str) -> RuleConfig {
+    serde_yaml::from_str(yaml).expect("invalid rule")
+}
+
+fn preview(schedules: &Schedules, from: &str, until: &str) -> Vec<(String, String)> {
+    schedules
+        .preview(&Utc, &at(from), &at(until))
+        .into_iter()
+        .map(|firing| (firing.at.naive_local().to_string(), firing.trigger))
+        .collect()
+}
+
+#[test]
+fn lists_the_daily_schedules_within_the_window() {
+    let irrigation = [IrrigationSchedule {
+        at: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+        zones: Vec::new(),
+        skip_holidays: false,
+    }];
+    let wakeup = [WakeupAlarm {
+        at: NaiveTime::from_hms_opt(7, 30, 0).unwrap(),
+        weekdays: vec![chrono::Weekday::Tue],
+        skip_holidays: false,
+    }];
+    let schedules = Schedules {
+        irrigation: &irrigation,
+        wakeup: &wakeup,
+        rules: &[],
+        astronomy: None,
+        holidays: None,
+    };
+
+    // From a Monday noon.
+    assert_eq!(
+        preview(&schedules, "2024-05-06T12:00:00", "2024-05-08T06:00:00"),
+        [
+            ("2024-05-07 06:00:00".to_string(), "06:00".to_string()),
+            ("2024-05-07 07:30:00".to_string(), "07:30".to_string()),
+            ("2024-05-08 06:00:00".to_string(), "06:00".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn lists_the_rules_when_they_start_matching() {
+    let rules = [
+        rule("{id: night, conditions: [{type: time, after: '22:00:00', before: '06:00:00'}], actions: []}"),
+        rule("{id: monday, conditions: [{type: time, weekdays: [Mon, Tue]}], actions: []}"),
+    ];
+    let schedules = Schedules {
+        irrigation: &[],
+        wakeup: &[],
+        rules: &rules,
+        astronomy: None,
+        holidays: None,
+    };
+
+    // From a Sunday noon: Tuesday is within the same window as Monday.
+    assert_eq!(
+        preview(&schedules, "2024-05-05T12:00:00", "2024-05-07T12:00:00"),
+        [
+            ("2024-05-05 22:00:00".to_string(), "22:00".to_string()),
+            ("2024-05-06 00:00:00".to_string(), "00:00".to_string()),
+            ("2024-05-06 22:00:00".to_string(), "22:00".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn lists_the_sun_relative_rules_at_the_crossings() {
+    let astronomy = AstronomyConfig {
+        latitude: 48.86,
+        longitude: 2.35,
+    };
+    let rules = [rule(
+        "{id: dusk, conditions: [{type: time, after: '18:00:00'}, {type: sun_elevation, below: 0.0}], actions: []}",
+    )];
+    let schedules = Schedules {
+        irrigation: &[],
+        wakeup: &[],
+        rules: &rules,
+        astronomy: Some(&astronomy),
+        holidays: None,
+    };
+
+    // The sun sets after 18:00 in May in Paris, but before in December.
+    let may = schedules.preview(&Utc, &at("2024-05-06T12:00:00"), &at("2024-05-07T12:00:00"));
+    let december = schedules.preview(&Utc, &at("2024-12-06T12:00:00"), &at("2024-12-07T12:00:00"));
+
+    assert_eq!(may.len(), 1);
+    assert_eq!(may[0].trigger, "sun below 0°");
+    assert!(may[0].at.time() > NaiveTime::from_hms_opt(18, 0, 0).unwrap());
+    assert_eq!(december.len(), 1);
+    assert_eq!(december[0].trigger, "18:00");
+}
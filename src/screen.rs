@@ -0,0 +1,469 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::info;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::{process::Command, sync::watch};
+
+/// The sysfs directory of the DRM connectors.
+const DRM_CLASS: &str = "/sys/class/drm";
+
+/// The screen configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScreenConfig {
+    /// How the display is powered. The screen state is only tracked if
+    /// unspecified.
+    #[serde(default)]
+    pub backend: Option<ScreenBackend>,
+
+    /// The sysfs directory of the backlight, like
+    /// `/sys/class/backlight/rpi_backlight`: a shorthand for the `backlight`
+    /// backend.
+    #[serde(default)]
+    pub backlight: Option<PathBuf>,
+
+    /// For how long the screen stays as forced from the API, before the
+    /// presence detection takes control again.
+    #[serde(default = "ScreenConfig::default_override_timeout")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub override_timeout: Duration,
+
+    /// The DRM connector of the display, like `HDMI-A-1` or `DSI-1`. The
+    /// first connected one if unspecified.
+    #[serde(default)]
+    pub output: Option<String>,
+
+    /// The rotation to apply to the display at startup, for portrait mounts.
+    #[serde(default)]
+    pub rotation: Option<Rotation>,
+
+    /// The command setting the rotation, which must understand the arguments
+    /// of `wlr-randr`.
+    #[serde(default = "ScreenConfig::default_rotation_command")]
+    pub rotation_command: String,
+}
+
+impl Default for ScreenConfig {
+    fn default() -> Self {
+        Self {
+            backend: None,
+            backlight: None,
+            override_timeout: Self::default_override_timeout(),
+            output: None,
+            rotation: None,
+            rotation_command: Self::default_rotation_command(),
+        }
+    }
+}
+
+impl ScreenConfig {
+    fn default_override_timeout() -> Duration {
+        Duration::from_secs(600)
+    }
+
+    fn default_rotation_command() -> String {
+        "wlr-randr".to_string()
+    }
+
+    fn controller(&self) -> Option<Box<dyn ScreenController>> {
+        let backend = self.backend.clone().or_else(|| {
+            self.backlight
+                .clone()
+                .map(|path| ScreenBackend::Backlight { path })
+        })?;
+
+        Some(match backend {
+            ScreenBackend::Backlight { path } => Box::new(Backlight { path }),
+            ScreenBackend::Vcgencmd => Box::new(PowerCommand {
+                on: vec!["vcgencmd".into(), "display_power".into(), "1".into()],
+                off: vec!["vcgencmd".into(), "display_power".into(), "0".into()],
+            }),
+            ScreenBackend::Command { on, off } => Box::new(PowerCommand { on, off }),
+        })
+    }
+}
+
+/// How the display is powered.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScreenBackend {
+    /// The sysfs directory of a backlight, like
+    /// `/sys/class/backlight/rpi_backlight`, which also sets the brightness.
+    Backlight { path: PathBuf },
+
+    /// The `vcgencmd display_power` command of the Raspberry Pi firmware,
+    /// for HDMI displays.
+    Vcgencmd,
+
+    /// The commands, and their arguments, turning the display on and off.
+    Command { on: Vec<String>, off: Vec<String> },
+}
+
+/// Drives the display hardware.
+pub trait ScreenController: Send + Sync {
+    fn set_power(&self, on: bool) -> anyhow::Result<()>;
+
+    /// Set the brightness, from 0 to 255. Ignored if unsupported.
+    fn set_brightness(&self, _brightness: u8) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// A backlight in sysfs.
+struct Backlight {
+    path: PathBuf,
+}
+
+impl ScreenController for Backlight {
+    fn set_power(&self, on: bool) -> anyhow::Result<()> {
+        // The backlight is powered when `bl_power` is 0.
+        fs::write(self.path.join("bl_power"), if on { "0" } else { "1" })
+            .context("failed to set the backlight power")
+    }
+
+    fn set_brightness(&self, brightness: u8) -> anyhow::Result<()> {
+        let max_brightness: u32 = fs::read_to_string(self.path.join("max_brightness"))
+            .context("failed to read the maximum brightness")?
+            .trim()
+            .parse()
+            .context("failed to parse the maximum brightness")?;
+        let brightness = u32::from(brightness) * max_brightness / u32::from(u8::MAX);
+
+        fs::write(self.path.join("brightness"), brightness.to_string())
+            .context("failed to set the brightness")
+    }
+}
+
+/// Commands powering the display.
+struct PowerCommand {
+    on: Vec<String>,
+    off: Vec<String>,
+}
+
+impl ScreenController for PowerCommand {
+    fn set_power(&self, on: bool) -> anyhow::Result<()> {
+        let (program, args) = if on { &self.on } else { &self.off }
+            .split_first()
+            .context("the screen power command is empty")?;
+        let status = std::process::Command::new(program)
+            .args(args)
+            .status()
+            .with_context(|| format!("failed to run `{}`", program))?;
+
+        anyhow::ensure!(status.success(), "`{}` failed with {}", program, status);
+
+        Ok(())
+    }
+}
+
+/// The clockwise rotation of the display, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(try_from = "u16", into = "u16")]
+#[schemars(with = "u16")]
+pub enum Rotation {
+    #[default]
+    Normal,
+    Right,
+    Inverted,
+    Left,
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(value: u16) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Normal),
+            90 => Ok(Self::Right),
+            180 => Ok(Self::Inverted),
+            270 => Ok(Self::Left),
+            _ => Err(format!(
+                "unsupported rotation {}: use 0, 90, 180 or 270",
+                value
+            )),
+        }
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> Self {
+        match rotation {
+            Rotation::Normal => 0,
+            Rotation::Right => 90,
+            Rotation::Inverted => 180,
+            Rotation::Left => 270,
+        }
+    }
+}
+
+/// The DPMS power state of the display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Dpms {
+    On,
+    Standby,
+    Suspend,
+    Off,
+}
+
+/// The connected display, as reported by DRM.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenInfo {
+    /// The DRM connector, like `HDMI-A-1`.
+    pub output: String,
+
+    /// The preferred resolution of the display, before the rotation.
+    pub width: u32,
+    pub height: u32,
+
+    /// The configured rotation.
+    pub rotation: Rotation,
+    pub dpms: Option<Dpms>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenState {
+    pub on: bool,
+
+    /// The brightness, from 0 to 255.
+    pub brightness: u8,
+}
+
+impl Default for ScreenState {
+    fn default() -> Self {
+        Self {
+            on: true,
+            brightness: u8::MAX,
+        }
+    }
+}
+
+/// The screen state, as reported to the frontend.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenStatus {
+    pub on: bool,
+
+    /// The brightness, from 0 to 255.
+    pub brightness: u8,
+
+    /// Until when the screen stays as forced, if it is.
+    pub overridden_until: Option<DateTime<Utc>>,
+}
+
+/// Controls the screen.
+pub struct Screen {
+    config: ScreenConfig,
+    controller: Option<Box<dyn ScreenController>>,
+    state: watch::Sender<ScreenState>,
+    overridden_until: Mutex<Option<Instant>>,
+}
+
+impl Screen {
+    pub fn new(config: ScreenConfig) -> Self {
+        Self {
+            controller: config.controller(),
+            config,
+            state: watch::channel(ScreenState::default()).0,
+            overridden_until: Mutex::new(None),
+        }
+    }
+
+    /// Read the connected display from sysfs, if any.
+    pub fn info(&self) -> anyhow::Result<Option<ScreenInfo>> {
+        let entries = match fs::read_dir(DRM_CLASS) {
+            Ok(entries) => entries,
+            // Like in containers, without access to the display.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).context("failed to list the DRM connectors"),
+        };
+        let mut connectors = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                // Connectors are named after their card, like `card0-HDMI-A-1`.
+                let name = entry.file_name().to_str()?.to_string();
+                let (_, output) = name.split_once('-')?;
+
+                Some((output.to_string(), entry.path()))
+            })
+            .filter(|(output, path)| match &self.config.output {
+                Some(configured) => output == configured,
+                None => read_attribute(path, "status").as_deref() == Some("connected"),
+            })
+            .collect::<Vec<_>>();
+
+        connectors.sort();
+
+        let (output, path) = match connectors.into_iter().next() {
+            Some(connector) => connector,
+            None => return Ok(None),
+        };
+
+        // The first mode is the preferred one.
+        let (width, height) = read_attribute(&path, "modes")
+            .as_deref()
+            .and_then(|modes| modes.lines().next())
+            .and_then(|mode| mode.split_once('x'))
+            .and_then(|(width, height)| {
+                // Interlaced modes end with `i`.
+                let height = height.trim_end_matches(|c: char| !c.is_ascii_digit());
+
+                Some((width.parse().ok()?, height.parse().ok()?))
+            })
+            .unwrap_or_default();
+        let dpms = match read_attribute(&path, "dpms").as_deref() {
+            Some("On") => Some(Dpms::On),
+            Some("Standby") => Some(Dpms::Standby),
+            Some("Suspend") => Some(Dpms::Suspend),
+            Some("Off") => Some(Dpms::Off),
+            _ => None,
+        };
+
+        Ok(Some(ScreenInfo {
+            output,
+            width,
+            height,
+            rotation: self.config.rotation.unwrap_or_default(),
+            dpms,
+        }))
+    }
+
+    /// Apply the configured rotation, if any.
+    pub async fn apply_rotation(&self) -> anyhow::Result<()> {
+        let rotation = match self.config.rotation {
+            Some(rotation) => rotation,
+            None => return Ok(()),
+        };
+        let output = match &self.config.output {
+            Some(output) => output.clone(),
+            None => match self.info()? {
+                Some(info) => info.output,
+                None => anyhow::bail!("no connected display to rotate"),
+            },
+        };
+        let transform = match rotation {
+            Rotation::Normal => "normal".to_string(),
+            rotation => u16::from(rotation).to_string(),
+        };
+
+        info!("Rotating display `{}` by {}°.", output, u16::from(rotation));
+
+        let status = Command::new(&self.config.rotation_command)
+            .args(["--output", &output, "--transform", &transform])
+            .status()
+            .await
+            .context("failed to run the rotation command")?;
+
+        anyhow::ensure!(
+            status.success(),
+            "the rotation command failed with {}",
+            status
+        );
+
+        Ok(())
+    }
+
+    pub fn state(&self) -> ScreenState {
+        *self.state.borrow()
+    }
+
+    /// Watch the screen state.
+    pub fn watch(&self) -> watch::Receiver<ScreenState> {
+        self.state.subscribe()
+    }
+
+    pub fn status(&self) -> ScreenStatus {
+        let state = self.state();
+
+        ScreenStatus {
+            on: state.on,
+            brightness: state.brightness,
+            overridden_until: self.remaining_override().map(|remaining| {
+                Utc::now()
+                    + chrono::Duration::from_std(remaining)
+                        .unwrap_or_else(|_| chrono::Duration::zero())
+            }),
+        }
+    }
+
+    pub fn set_on(&self, on: bool) -> anyhow::Result<()> {
+        self.set(ScreenState { on, ..self.state() })
+    }
+
+    /// Force the screen on or off, keeping the presence detection from
+    /// changing it for the override timeout.
+    pub fn force_on(&self, on: bool) -> anyhow::Result<()> {
+        self.set_on(on)?;
+
+        *self.overridden_until.lock().unwrap() =
+            Some(Instant::now() + self.config.override_timeout);
+
+        Ok(())
+    }
+
+    /// Check whether the screen is forced, and should be left as it is.
+    pub fn overridden(&self) -> bool {
+        self.remaining_override().is_some()
+    }
+
+    fn remaining_override(&self) -> Option<Duration> {
+        self.overridden_until
+            .lock()
+            .unwrap()
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    pub fn set_brightness(&self, brightness: u8) -> anyhow::Result<()> {
+        self.set(ScreenState {
+            brightness,
+            ..self.state()
+        })
+    }
+
+    fn set(&self, state: ScreenState) -> anyhow::Result<()> {
+        if state == self.state() {
+            return Ok(());
+        }
+
+        info!(
+            "Setting screen {} at brightness {}",
+            if state.on { "on" } else { "off" },
+            state.brightness
+        );
+
+        if let Some(controller) = &self.controller {
+            let previous = self.state();
+
+            if state.brightness != previous.brightness {
+                controller.set_brightness(state.brightness)?;
+            }
+
+            if state.on != previous.on {
+                controller.set_power(state.on)?;
+            }
+        }
+
+        self.state.send_replace(state);
+
+        Ok(())
+    }
+}
+
+/// Read a sysfs attribute of a DRM connector.
+fn read_attribute(connector: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(connector.join(name))
+        .ok()
+        .map(|value| value.trim().to_string())
+}
@@ -0,0 +1,298 @@
+//! The photo frame shown by the idle panels, from a directory of images.
+//!
+//! The images are shown in a shuffled order, kept across restarts, and
+//! shuffled again once they were all shown or the directory changed.
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use log::{info, warn};
+use rand::seq::SliceRandom;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncReadExt, sync::Mutex};
+
+use crate::files::write_atomic;
+
+/// The extensions of the images served, in lower case.
+const IMAGE_EXTENSIONS: [&str; 5] = ["jpg", "jpeg", "png", "webp", "gif"];
+
+/// How much of the start of a JPEG file is searched for its orientation.
+const EXIF_SEARCH_SIZE: u64 = 128 * 1024;
+
+/// The photo frame configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScreensaverConfig {
+    /// The directory of the images, possibly a mounted network share. Its
+    /// subdirectories are ignored.
+    pub dir: PathBuf,
+
+    /// The file to keep the shuffled order in across restarts, if any.
+    #[serde(default)]
+    pub state_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreensaverImage {
+    /// The file name of the image, as used in the API path.
+    pub name: String,
+
+    /// The clockwise rotation to apply for the image to show upright, in
+    /// degrees, from its EXIF orientation.
+    pub rotation: u16,
+
+    /// Whether the image must be mirrored horizontally, before the rotation.
+    pub mirrored: bool,
+
+    /// The position of the image in the shuffled order, from 0.
+    pub position: usize,
+    pub count: usize,
+}
+
+/// The shuffled order of the images, and how far through it the frame is.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct Shuffle {
+    order: Vec<String>,
+    position: usize,
+}
+
+pub struct Screensaver {
+    config: ScreensaverConfig,
+    shuffle: Mutex<Option<Shuffle>>,
+}
+
+impl Screensaver {
+    pub fn new(config: ScreensaverConfig) -> Self {
+        Self {
+            config,
+            shuffle: Mutex::new(None),
+        }
+    }
+
+    /// Get the next image to show, if there are any.
+    pub async fn next(&self) -> anyhow::Result<Option<ScreensaverImage>> {
+        let names = list_images(&self.config.dir).await?;
+        let mut shuffle = self.shuffle.lock().await;
+        let shuffle = match &mut *shuffle {
+            Some(shuffle) => shuffle,
+            None => shuffle.insert(self.load().await),
+        };
+
+        let changed =
+            shuffle.order.iter().collect::<BTreeSet<_>>() != names.iter().collect::<BTreeSet<_>>();
+
+        if changed || shuffle.position >= shuffle.order.len() {
+            info!("Shuffling the {} images of the screensaver.", names.len());
+
+            shuffle.order = names;
+            shuffle.order.shuffle(&mut rand::thread_rng());
+            shuffle.position = 0;
+        }
+
+        let name = match shuffle.order.get(shuffle.position) {
+            Some(name) => name.clone(),
+            None => return Ok(None),
+        };
+        let position = shuffle.position;
+
+        shuffle.position += 1;
+        self.save(shuffle).await;
+
+        let (rotation, mirrored) = match orientation(&self.config.dir.join(&name)).await {
+            Ok(orientation) => rotation(orientation.unwrap_or(1)),
+            Err(err) => {
+                warn!("Failed to read the orientation of `{}`: {:#}", name, err);
+
+                (0, false)
+            }
+        };
+
+        Ok(Some(ScreensaverImage {
+            name,
+            rotation,
+            mirrored,
+            position,
+            count: shuffle.order.len(),
+        }))
+    }
+
+    /// Read an image, with its content type, if it exists.
+    pub async fn image(&self, name: &str) -> anyhow::Result<Option<(Vec<u8>, String)>> {
+        if name.contains(['/', '\\']) || name.starts_with('.') || !is_image(Path::new(name)) {
+            return Ok(None);
+        }
+
+        let path = self.config.dir.join(name);
+
+        match tokio::fs::read(&path).await {
+            Ok(data) => Ok(Some((
+                data,
+                mime_guess::from_path(&path)
+                    .first_or_octet_stream()
+                    .to_string(),
+            ))),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("failed to read `{}`", path.display())),
+        }
+    }
+
+    async fn load(&self) -> Shuffle {
+        let path = match &self.config.state_path {
+            Some(path) => path,
+            None => return Shuffle::default(),
+        };
+
+        match tokio::fs::read(path).await {
+            Ok(content) => serde_json::from_slice(&content).unwrap_or_else(|err| {
+                warn!("Invalid screensaver order in `{}`: {}", path.display(), err);
+
+                Shuffle::default()
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Shuffle::default(),
+            Err(err) => {
+                warn!("Failed to read `{}`: {}", path.display(), err);
+
+                Shuffle::default()
+            }
+        }
+    }
+
+    async fn save(&self, shuffle: &Shuffle) {
+        let path = match &self.config.state_path {
+            Some(path) => path,
+            None => return,
+        };
+        let result = serde_json::to_vec(shuffle)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| write_atomic(path, content));
+
+        if let Err(err) = result {
+            warn!("Failed to save the screensaver order: {:#}", err);
+        }
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// List the images of a directory, by file name, in order.
+async fn list_images(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .with_context(|| format!("failed to list `{}`", dir.display()))?;
+    let mut names = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+
+        if !is_image(&path) || !entry.file_type().await?.is_file() {
+            continue;
+        }
+
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            if !name.starts_with('.') {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names.sort();
+
+    Ok(names)
+}
+
+/// Read the EXIF orientation of an image, if it is a JPEG file that has
+/// one.
+async fn orientation(path: &Path) -> anyhow::Result<Option<u16>> {
+    let file = tokio::fs::File::open(path).await?;
+    let mut data = Vec::new();
+
+    file.take(EXIF_SEARCH_SIZE).read_to_end(&mut data).await?;
+
+    Ok(jpeg_orientation(&data))
+}
+
+/// Find the orientation in the EXIF segment of a JPEG file.
+fn jpeg_orientation(data: &[u8]) -> Option<u16> {
+    if data.get(..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+
+    let mut offset = 2;
+
+    // The segments up to the image data, each with its length.
+    while data.get(offset)? == &0xFF {
+        let marker = *data.get(offset + 1)?;
+        let length = u16::from_be_bytes([*data.get(offset + 2)?, *data.get(offset + 3)?]) as usize;
+        let segment = data.get(offset + 4..offset + 2 + length)?;
+
+        if marker == 0xE1 && segment.starts_with(b"Exif\0\0") {
+            return tiff_orientation(&segment[6..]);
+        }
+
+        if marker == 0xDA {
+            return None;
+        }
+
+        offset += 2 + length;
+    }
+
+    None
+}
+
+/// Find the orientation tag in the first directory of a TIFF structure.
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let little_endian = match tiff.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |offset: usize| {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let u32_at = |offset: usize| {
+        let bytes = tiff.get(offset..offset + 4)?.try_into().ok()?;
+
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+    let directory = u32_at(4)? as usize;
+
+    (0..u16_at(directory)? as usize)
+        .map(|entry| directory + 2 + entry * 12)
+        .find(|entry| u16_at(*entry) == Some(0x0112))
+        .and_then(|entry| u16_at(entry + 8))
+}
+
+/// Get the clockwise rotation and the mirroring of an EXIF orientation.
+fn rotation(orientation: u16) -> (u16, bool) {
+    match orientation {
+        2 => (0, true),
+        3 => (180, false),
+        4 => (180, true),
+        5 => (270, true),
+        6 => (90, false),
+        7 => (90, true),
+        8 => (270, false),
+        _ => (0, false),
+    }
+}
+
+#[cfg(test)]
+mod tests;
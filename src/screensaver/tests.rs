@@ -0,0 +1,75 @@
+//! Tests of the reading of the orientation of the images.
+
+use super::*;
+
+/// A JPEG header with an EXIF segment holding only the orientation.
+fn jpeg(little_endian: bool, orientation: u16) -> Vec<u8> {
+    let u16_bytes = |value: u16| {
+        if little_endian {
+            value.to_le_bytes()
+        } else {
+            value.to_be_bytes()
+        }
+    };
+    let mut tiff = if little_endian {
+        b"II".to_vec()
+    } else {
+        b"MM".to_vec()
+    };
+
+    tiff.extend(u16_bytes(42));
+    tiff.extend(if little_endian {
+        8u32.to_le_bytes()
+    } else {
+        8u32.to_be_bytes()
+    });
+    tiff.extend(u16_bytes(1));
+    tiff.extend(u16_bytes(0x0112));
+    tiff.extend(u16_bytes(3));
+    tiff.extend(if little_endian {
+        1u32.to_le_bytes()
+    } else {
+        1u32.to_be_bytes()
+    });
+    tiff.extend(u16_bytes(orientation));
+    tiff.extend([0, 0]);
+
+    let mut segment = b"Exif\0\0".to_vec();
+
+    segment.extend(tiff);
+
+    let mut data = vec![0xFF, 0xD8];
+
+    // A JFIF segment first, as most cameras write.
+    data.extend([0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00]);
+    data.extend([0xFF, 0xE1]);
+    data.extend((segment.len() as u16 + 2).to_be_bytes());
+    data.extend(segment);
+    data.extend([0xFF, 0xDA, 0x00, 0x02]);
+    data
+}
+
+#[test]
+fn reads_the_orientation_in_both_byte_orders() {
+    assert_eq!(jpeg_orientation(&jpeg(true, 6)), Some(6));
+    assert_eq!(jpeg_orientation(&jpeg(false, 8)), Some(8));
+}
+
+#[test]
+fn ignores_the_images_without_exif() {
+    assert_eq!(
+        jpeg_orientation(&[0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02]),
+        None
+    );
+    assert_eq!(jpeg_orientation(b"\x89PNG\r\n\x1a\n"), None);
+    assert_eq!(jpeg_orientation(&jpeg(true, 6)[..20]), None);
+}
+
+#[test]
+fn maps_the_orientations_to_rotations() {
+    assert_eq!(rotation(1), (0, false));
+    assert_eq!(rotation(3), (180, false));
+    assert_eq!(rotation(6), (90, false));
+    assert_eq!(rotation(7), (90, true));
+    assert_eq!(rotation(8), (270, false));
+}
@@ -0,0 +1,241 @@
+//! The secrets of the configuration encrypted at rest, like the
+//! Home-Assistant token, so that a stolen SD card does not hand them over.
+//!
+//! The secrets are libsodium sealed boxes written as `enc:<base64>`, sealed
+//! to the public key of a key pair whose secret key is only read on startup:
+//! from a file, or from the output of a command, like `tpm2_unseal` for the
+//! key to stay in a TPM.
+
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Parser;
+use crypto_box::{aead::OsRng, PublicKey, SecretKey, KEY_SIZE};
+use serde_json::Value;
+
+/// The prefix of the encrypted values.
+const PREFIX: &str = "enc:";
+
+/// Where the secret key is read from.
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    /// A file holding the key in base64.
+    File(PathBuf),
+
+    /// A shell command printing the key in base64.
+    Command(String),
+}
+
+impl KeySource {
+    pub fn load(&self) -> anyhow::Result<SecretKey> {
+        let encoded = match self {
+            Self::File(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read the secrets key `{}`", path.display()))?,
+            Self::Command(command) => {
+                let output = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .output()
+                    .with_context(|| format!("failed to run `{}`", command))?;
+
+                if !output.status.success() {
+                    anyhow::bail!("`{}` failed with {}", command, output.status);
+                }
+
+                String::from_utf8(output.stdout)
+                    .with_context(|| format!("`{}` did not print a key", command))?
+            }
+        };
+
+        Ok(SecretKey::from_bytes(decode_key(&encoded)?))
+    }
+}
+
+fn decode_key(encoded: &str) -> anyhow::Result<[u8; KEY_SIZE]> {
+    STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("the key is not {} bytes in base64", KEY_SIZE))
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Decrypt a value if it is encrypted, or return it as is.
+pub fn decrypt(key: Option<&SecretKey>, value: &str) -> anyhow::Result<String> {
+    let sealed = match value.strip_prefix(PREFIX) {
+        Some(sealed) => sealed,
+        None => return Ok(value.to_string()),
+    };
+    let key = key.ok_or_else(|| anyhow::anyhow!("no secrets key to decrypt the secrets with"))?;
+    let sealed = STANDARD
+        .decode(sealed.trim())
+        .context("the encrypted secret is not in base64")?;
+    let secret = key
+        .unseal(&sealed)
+        .map_err(|_| anyhow::anyhow!("failed to decrypt a secret: is it for another key?"))?;
+
+    String::from_utf8(secret).context("the decrypted secret is not UTF-8")
+}
+
+pub fn encrypt(public_key: &PublicKey, secret: &str) -> anyhow::Result<String> {
+    let sealed = public_key
+        .seal(&mut OsRng, secret.as_bytes())
+        .map_err(|_| anyhow::anyhow!("failed to encrypt the secret"))?;
+
+    Ok(format!("{}{}", PREFIX, STANDARD.encode(sealed)))
+}
+
+/// Read a systemd credential, as passed with `LoadCredential=` or
+/// `SetCredential=`, if there is one by that name.
+pub fn credential(name: &str) -> anyhow::Result<Option<String>> {
+    let directory = match std::env::var_os("CREDENTIALS_DIRECTORY") {
+        Some(directory) => PathBuf::from(directory),
+        None => return Ok(None),
+    };
+
+    credential_in(&directory, name)
+}
+
+fn credential_in(directory: &Path, name: &str) -> anyhow::Result<Option<String>> {
+    let path = directory.join(name);
+
+    match std::fs::read_to_string(&path) {
+        Ok(value) => Ok(Some(value.trim_end().to_string())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => {
+            Err(err).with_context(|| format!("failed to read the credential `{}`", path.display()))
+        }
+    }
+}
+
+/// Find the encrypted values of a configuration, by their path, like
+/// `mqtt.password` or `cameras[0].url`.
+pub fn encrypted_values(config: &Value) -> Vec<(String, String)> {
+    fn walk(value: &Value, path: String, found: &mut Vec<(String, String)>) {
+        match value {
+            Value::String(s) if is_encrypted(s) => found.push((path, s.clone())),
+            Value::Object(map) => {
+                for (key, value) in map {
+                    let path = if path.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+
+                    walk(value, path, found);
+                }
+            }
+            Value::Array(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    walk(value, format!("{}[{}]", path, i), found);
+                }
+            }
+            Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {}
+        }
+    }
+
+    let mut found = Vec::new();
+
+    walk(config, String::new(), &mut found);
+
+    found
+}
+
+/// The `secrets` command, to set the encryption up.
+#[derive(Debug, Parser)]
+#[clap(name = "home-control secrets")]
+pub enum SecretsCommand {
+    /// Generate a key pair, write the secret key to a file and print the
+    /// public one.
+    GenerateKey {
+        /// The file to write the secret key to, only readable by its owner.
+        #[clap(long, default_value = "/etc/home-control/secrets.key")]
+        key_file: PathBuf,
+    },
+
+    /// Encrypt a secret read from the standard input, and print it as a value
+    /// for the configuration.
+    Encrypt {
+        /// The public key, as printed when generating the key pair.
+        #[clap(long, conflicts_with = "key-file")]
+        public_key: Option<String>,
+
+        /// The secret key file, to get the public key from.
+        #[clap(long, default_value = "/etc/home-control/secrets.key")]
+        key_file: PathBuf,
+    },
+}
+
+impl SecretsCommand {
+    /// Parse the arguments of the `secrets` command, if it is the one
+    /// invoked.
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args_os().skip(1);
+
+        if args.next()? != "secrets" {
+            return None;
+        }
+
+        Some(Self::parse_from(
+            std::iter::once("home-control secrets".into()).chain(args),
+        ))
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        match self {
+            Self::GenerateKey { key_file } => {
+                let secret_key = SecretKey::generate(&mut OsRng);
+
+                write_key(key_file, &secret_key)?;
+                println!("{}", STANDARD.encode(secret_key.public_key().as_bytes()));
+            }
+            Self::Encrypt {
+                public_key,
+                key_file,
+            } => {
+                let public_key = match public_key {
+                    Some(public_key) => PublicKey::from_bytes(decode_key(public_key)?),
+                    None => KeySource::File(key_file.clone()).load()?.public_key(),
+                };
+                let mut secret = String::new();
+
+                std::io::stdin()
+                    .read_to_string(&mut secret)
+                    .context("failed to read the secret")?;
+                println!("{}", encrypt(&public_key, secret.trim_end_matches('\n'))?);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_key(path: &Path, secret_key: &SecretKey) -> anyhow::Result<()> {
+    use std::{fs::OpenOptions, io::Write, os::unix::fs::OpenOptionsExt};
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create `{}`", parent.display()))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .with_context(|| format!("failed to create `{}`", path.display()))?;
+
+    writeln!(file, "{}", STANDARD.encode(secret_key.to_bytes()))
+        .with_context(|| format!("failed to write `{}`", path.display()))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,72 @@
+//! Tests of the encryption of the secrets of the configuration.
+
+use serde_json::json;
+
+use super::*;
+
+#[test]
+fn decrypt_reverses_encrypt() {
+    let secret_key = SecretKey::generate(&mut OsRng);
+    let encrypted = encrypt(&secret_key.public_key(), "long-lived token").unwrap();
+
+    assert!(is_encrypted(&encrypted));
+    assert_eq!(
+        decrypt(Some(&secret_key), &encrypted).unwrap(),
+        "long-lived token"
+    );
+
+    // Another key cannot decrypt it, and it needs one.
+    let other_key = SecretKey::generate(&mut OsRng);
+
+    assert!(decrypt(Some(&other_key), &encrypted).is_err());
+    assert!(decrypt(None, &encrypted).is_err());
+}
+
+#[test]
+fn decrypt_leaves_the_plain_values_alone() {
+    assert_eq!(decrypt(None, "plain token").unwrap(), "plain token");
+}
+
+#[test]
+fn encrypted_values_are_found_by_path() {
+    let config = json!({
+        "location": "Home",
+        "mqtt": { "host": "broker", "password": "enc:AAAA" },
+        "cameras": [{ "url": "http://camera" }, { "url": "enc:BBBB" }],
+    });
+
+    let mut found = encrypted_values(&config);
+
+    found.sort();
+
+    assert_eq!(
+        found,
+        [
+            ("cameras[1].url".to_string(), "enc:BBBB".to_string()),
+            ("mqtt.password".to_string(), "enc:AAAA".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn decode_key_checks_the_size() {
+    assert!(decode_key(&STANDARD.encode([1; KEY_SIZE])).is_ok());
+    assert!(decode_key(&STANDARD.encode([1; 16])).is_err());
+    assert!(decode_key("not base64").is_err());
+}
+
+#[test]
+fn credential_in_reads_the_credential_if_present() {
+    let directory = std::env::temp_dir().join(format!("credentials-{}", std::process::id()));
+
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("ha_token"), "token\n").unwrap();
+
+    assert_eq!(
+        credential_in(&directory, "ha_token").unwrap().as_deref(),
+        Some("token")
+    );
+    assert_eq!(credential_in(&directory, "other").unwrap(), None);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
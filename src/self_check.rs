@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::context::AppContext;
+
+/// The startup self-check configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SelfCheckConfig {
+    /// Whether to blink the LEDs and chirp the buzzer.
+    #[serde(default = "SelfCheckConfig::default_feedback")]
+    pub feedback: bool,
+}
+
+impl SelfCheckConfig {
+    fn default_feedback() -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub passed: bool,
+
+    /// What went wrong, or the measured value.
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfCheckReport {
+    pub finished_at: DateTime<Utc>,
+    pub passed: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+/// Run the self-check sequence, logging the results.
+pub async fn run(context: &AppContext, config: &SelfCheckConfig) -> SelfCheckReport {
+    info!("Running the self-check...");
+
+    let mut checks = Vec::new();
+
+    if config.feedback {
+        checks.push(check("leds", blink_leds(context).await));
+        checks.push(check("buzzer", chirp(context).await));
+    }
+
+    checks.push(check(
+        "distance",
+        context
+            .gpio
+            .get_distance(None)
+            .await
+            .map(|distance| Some(format!("{:.1} cm", distance.cm))),
+    ));
+    checks.push(check(
+        "home_assistant",
+        context
+            .home_assistant
+            .wait_ready()
+            .await
+            .map(|()| None)
+            .map_err(Into::into),
+    ));
+
+    let report = SelfCheckReport {
+        finished_at: Utc::now(),
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+    };
+
+    for check in &report.checks {
+        match (check.passed, &check.details) {
+            (true, Some(details)) => info!("Self-check `{}` passed: {}.", check.name, details),
+            (true, None) => info!("Self-check `{}` passed.", check.name),
+            (false, details) => error!(
+                "Self-check `{}` failed: {}",
+                check.name,
+                details.as_deref().unwrap_or("unknown error")
+            ),
+        }
+    }
+
+    report
+}
+
+pub(crate) fn check(name: &'static str, result: anyhow::Result<Option<String>>) -> CheckResult {
+    match result {
+        Ok(details) => CheckResult {
+            name,
+            passed: true,
+            details,
+        },
+        Err(err) => CheckResult {
+            name,
+            passed: false,
+            details: Some(format!("{:#}", err)),
+        },
+    }
+}
+
+async fn blink_leds(context: &AppContext) -> anyhow::Result<Option<String>> {
+    for status in [true, false] {
+        context.gpio.set_red_led(status)?;
+        context.gpio.set_green_led(status)?;
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    Ok(None)
+}
+
+async fn chirp(context: &AppContext) -> anyhow::Result<Option<String>> {
+    context.gpio.set_buzzer(true)?;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    context.gpio.set_buzzer(false)?;
+
+    Ok(None)
+}
@@ -0,0 +1,58 @@
+//! The serial ports, like those of the USB-RS485 and USB-UART dongles.
+
+use std::{path::Path, process::Stdio, time::Duration};
+
+use anyhow::Context;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncReadExt,
+    process::Command,
+};
+
+/// Open a serial port in raw mode, at the specified speed.
+///
+/// The port is configured with `stty` so that its reads return after a tenth
+/// of a second without data, rather than blocking the reading thread.
+pub async fn open(path: &Path, baud_rate: u32) -> anyhow::Result<File> {
+    let output = Command::new("stty")
+        .arg("-F")
+        .arg(path)
+        .arg(baud_rate.to_string())
+        .args(["raw", "-echo", "min", "0", "time", "1"])
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("failed to run `stty`")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "failed to configure `{}`: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .await
+        .with_context(|| format!("failed to open `{}`", path.display()))
+}
+
+/// Read exactly enough bytes to fill the buffer, within a timeout.
+pub async fn read_exact(port: &mut File, buf: &mut [u8], timeout: Duration) -> anyhow::Result<()> {
+    let read = async {
+        let mut offset = 0;
+
+        while offset < buf.len() {
+            offset += port.read(&mut buf[offset..]).await?;
+        }
+
+        anyhow::Ok(())
+    };
+
+    tokio::time::timeout(timeout, read)
+        .await
+        .context("timed out")?
+}
@@ -0,0 +1,341 @@
+//! The miscellaneous serial gadgets attached to the panel, like the UART
+//! sensors, speaking a line protocol.
+//!
+//! The values are extracted from the lines the devices send with regular
+//! expressions, and the commands are lines sent to them.
+
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures_util::future::try_join_all;
+use log::{debug, info, warn};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncWriteExt},
+    sync::{Mutex, RwLock},
+};
+
+use crate::{serial, tasks};
+
+/// For how long to wait before reopening a port.
+const REOPEN_DELAY: Duration = Duration::from_secs(10);
+
+/// The longest line accepted, as the devices only send short ones.
+const MAX_LINE_LENGTH: usize = 1024;
+
+/// A serial device.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SerialDeviceConfig {
+    /// The name of the device, as used in the API path.
+    pub name: String,
+    pub port: PathBuf,
+
+    #[serde(default = "SerialDeviceConfig::default_baud_rate")]
+    pub baud_rate: u32,
+
+    /// The end of the lines sent to the device. The lines it sends can end
+    /// with either.
+    #[serde(default)]
+    pub line_ending: LineEnding,
+
+    /// The values to extract from the lines the device sends.
+    #[serde(default)]
+    pub values: Vec<SerialValueConfig>,
+
+    /// The commands that can be sent to the device.
+    #[serde(default)]
+    pub commands: Vec<SerialCommandConfig>,
+}
+
+impl SerialDeviceConfig {
+    fn default_baud_rate() -> u32 {
+        9600
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+            Self::Cr => "\r",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SerialValueConfig {
+    pub name: String,
+
+    /// The regular expression matching the lines holding the value, like
+    /// `^T=(-?[0-9.]+)$`. The value is its first group, or the whole match
+    /// without groups.
+    pub pattern: String,
+
+    #[serde(default)]
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SerialCommandConfig {
+    /// The name of the command, as used in the API path.
+    pub name: String,
+
+    /// The line to send, without its ending.
+    pub line: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SerialValue {
+    /// The value, as sent by the device.
+    pub raw: String,
+
+    /// The value as a number, if it is one.
+    pub number: Option<f64>,
+    pub unit: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SerialDeviceStatus {
+    pub name: String,
+    pub connected: bool,
+
+    /// The last values extracted, by name.
+    pub values: BTreeMap<String, SerialValue>,
+
+    /// The names of the commands that can be sent.
+    pub commands: Vec<String>,
+}
+
+/// The serial devices.
+pub struct SerialDevices {
+    devices: Vec<SerialDevice>,
+}
+
+struct SerialDevice {
+    config: SerialDeviceConfig,
+    patterns: Vec<Regex>,
+    values: RwLock<BTreeMap<String, SerialValue>>,
+
+    /// The port to send the commands to, while open.
+    writer: Mutex<Option<File>>,
+}
+
+impl SerialDevices {
+    pub fn new(configs: Vec<SerialDeviceConfig>) -> anyhow::Result<Self> {
+        let devices = configs
+            .into_iter()
+            .map(|config| {
+                let patterns = config
+                    .values
+                    .iter()
+                    .map(|value| {
+                        Regex::new(&value.pattern).with_context(|| {
+                            format!("invalid pattern for `{}.{}`", config.name, value.name)
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+
+                Ok(SerialDevice {
+                    config,
+                    patterns,
+                    values: RwLock::default(),
+                    writer: Mutex::default(),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { devices })
+    }
+
+    pub async fn statuses(&self) -> Vec<SerialDeviceStatus> {
+        let mut statuses = Vec::new();
+
+        for device in &self.devices {
+            statuses.push(device.status().await);
+        }
+
+        statuses
+    }
+
+    pub async fn status(&self, name: &str) -> Option<SerialDeviceStatus> {
+        Some(self.device(name)?.status().await)
+    }
+
+    /// Send a command to a device.
+    ///
+    /// Returns `None` if the device or the command is unknown.
+    pub async fn send(&self, name: &str, command: &str) -> Option<anyhow::Result<()>> {
+        let device = self.device(name)?;
+        let command = device
+            .config
+            .commands
+            .iter()
+            .find(|candidate| candidate.name == command)?;
+
+        info!(
+            "Sending `{}` to the serial device `{}`.",
+            command.name, name
+        );
+
+        Some(device.send(&command.line).await)
+    }
+
+    /// Read the devices forever.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        if self.devices.is_empty() {
+            return tasks::idle().await;
+        }
+
+        try_join_all(self.devices.iter().map(SerialDevice::run)).await?;
+
+        Ok(())
+    }
+
+    fn device(&self, name: &str) -> Option<&SerialDevice> {
+        self.devices
+            .iter()
+            .find(|device| device.config.name == name)
+    }
+}
+
+impl SerialDevice {
+    async fn status(&self) -> SerialDeviceStatus {
+        SerialDeviceStatus {
+            name: self.config.name.clone(),
+            connected: self.writer.lock().await.is_some(),
+            values: self.values.read().await.clone(),
+            commands: self
+                .config
+                .commands
+                .iter()
+                .map(|command| command.name.clone())
+                .collect(),
+        }
+    }
+
+    async fn send(&self, line: &str) -> anyhow::Result<()> {
+        let mut writer = self.writer.lock().await;
+        let writer = writer.as_mut().ok_or_else(|| {
+            anyhow::anyhow!("the serial device `{}` is not connected", self.config.name)
+        })?;
+
+        writer
+            .write_all(format!("{}{}", line, self.config.line_ending.as_str()).as_bytes())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn run(&self) -> anyhow::Result<()> {
+        loop {
+            if let Err(err) = self.serve().await {
+                warn!("Lost the serial device `{}`: {:#}", self.config.name, err);
+            }
+
+            *self.writer.lock().await = None;
+
+            tasks::heartbeat();
+            tokio::time::sleep(REOPEN_DELAY).await;
+        }
+    }
+
+    /// Open the port and read the lines of the device until it fails.
+    async fn serve(&self) -> anyhow::Result<()> {
+        let mut port = serial::open(&self.config.port, self.config.baud_rate).await?;
+
+        *self.writer.lock().await = Some(port.try_clone().await?);
+
+        info!(
+            "Reading the serial device `{}` on `{}`.",
+            self.config.name,
+            self.config.port.display()
+        );
+
+        let mut line = Vec::new();
+        let mut buf = [0; 256];
+
+        loop {
+            let count = port.read(&mut buf).await?;
+
+            // The reads return empty every tenth of a second without data.
+            if count == 0 {
+                continue;
+            }
+
+            tasks::heartbeat();
+
+            for byte in &buf[..count] {
+                match byte {
+                    b'\n' | b'\r' if !line.is_empty() => {
+                        self.handle(&String::from_utf8_lossy(&line)).await;
+                        line.clear();
+                    }
+                    b'\n' | b'\r' => {}
+                    _ if line.len() < MAX_LINE_LENGTH => line.push(*byte),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    async fn handle(&self, line: &str) {
+        debug!("Serial device `{}` sent `{}`.", self.config.name, line);
+
+        let extracted = extract(&self.patterns, line);
+
+        if extracted.is_empty() {
+            return;
+        }
+
+        let mut values = self.values.write().await;
+        let now = Utc::now();
+
+        for (index, raw) in extracted {
+            let config = &self.config.values[index];
+
+            values.insert(
+                config.name.clone(),
+                SerialValue {
+                    number: raw.trim().parse().ok(),
+                    raw,
+                    unit: config.unit.clone(),
+                    updated_at: now,
+                },
+            );
+        }
+    }
+}
+
+/// Extract the values of a line, by index of their pattern.
+fn extract(patterns: &[Regex], line: &str) -> Vec<(usize, String)> {
+    patterns
+        .iter()
+        .enumerate()
+        .filter_map(|(index, pattern)| {
+            let captures = pattern.captures(line)?;
+            let value = captures.get(1).or_else(|| captures.get(0))?;
+
+            Some((index, value.as_str().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
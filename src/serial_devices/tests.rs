@@ -0,0 +1,43 @@
+//! Tests of the serial devices.
+
+use super::*;
+
+fn patterns(patterns: &[&str]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .map(|pattern| Regex::new(pattern).unwrap())
+        .collect()
+}
+
+#[test]
+fn values_are_the_first_group() {
+    let patterns = patterns(&[r"^T=(-?[0-9.]+)C$", r"^H=([0-9.]+)%$"]);
+
+    assert_eq!(extract(&patterns, "T=-3.5C"), vec![(0, "-3.5".to_string())]);
+    assert_eq!(extract(&patterns, "H=41%"), vec![(1, "41".to_string())]);
+    assert!(extract(&patterns, "BOOT OK").is_empty());
+}
+
+#[test]
+fn values_without_groups_are_the_whole_match() {
+    let patterns = patterns(&["OPEN|CLOSED"]);
+
+    assert_eq!(
+        extract(&patterns, "door CLOSED"),
+        vec![(0, "CLOSED".to_string())]
+    );
+}
+
+#[test]
+fn invalid_patterns_are_rejected() {
+    let config: SerialDeviceConfig = serde_json::from_value(serde_json::json!({
+        "name": "weather",
+        "port": "/dev/ttyAMA0",
+        "values": [{ "name": "temperature", "pattern": "T=(" }],
+    }))
+    .unwrap();
+
+    let err = SerialDevices::new(vec![config]).err().unwrap();
+
+    assert!(err.to_string().contains("weather.temperature"));
+}
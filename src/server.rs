@@ -0,0 +1,283 @@
+use std::{
+    convert::Infallible, fs::File, io::BufReader, net::SocketAddr, path::PathBuf, pin::Pin,
+    sync::Arc, time::Duration,
+};
+
+use anyhow::Context;
+use ipnet::IpNet;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_with::{serde_as, DisplayFromStr, DurationSeconds};
+use tokio::sync::Semaphore;
+use tokio_rustls::{rustls, TlsAcceptor};
+use warp::{
+    hyper::{
+        self,
+        server::{
+            accept::Accept,
+            conn::{AddrIncoming, Http},
+        },
+        service::service_fn,
+    },
+    Filter, Rejection, Reply,
+};
+
+use crate::{
+    client_ip, control_action,
+    negotiation::{self, JsonLayout},
+    request_id,
+};
+
+/// How long the clients have to complete the TLS handshake, after which their
+/// connection is dropped so that stalled ones do not hold a permit forever.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The HTTP server configuration.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerConfig {
+    /// Serve over TLS, which also enables HTTP/2 negotiation with browsers.
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+
+    /// Whether HTTP/2 is allowed. Disabling it forces HTTP/1.
+    #[serde(default = "ServerConfig::default_http2")]
+    pub http2: bool,
+
+    /// Whether HTTP/1 connections are kept alive between requests.
+    #[serde(default = "ServerConfig::default_keep_alive")]
+    pub keep_alive: bool,
+
+    /// The idle time after which TCP keep-alive probes are sent, if any.
+    #[serde(default = "ServerConfig::default_tcp_keep_alive")]
+    #[serde_as(as = "Option<DurationSeconds<f64>>")]
+    pub tcp_keep_alive: Option<Duration>,
+
+    /// The interval at which HTTP/2 pings are sent, if any.
+    ///
+    /// Pings keep long-lived streams, like server-sent events, from being
+    /// closed by idle timeouts along the way.
+    #[serde(default = "ServerConfig::default_http2_keep_alive_interval")]
+    #[serde_as(as = "Option<DurationSeconds<f64>>")]
+    pub http2_keep_alive_interval: Option<Duration>,
+
+    /// How long to wait for an HTTP/2 ping acknowledgement before closing
+    /// the connection.
+    #[serde(default = "ServerConfig::default_http2_keep_alive_timeout")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub http2_keep_alive_timeout: Duration,
+
+    /// The maximum number of simultaneous connections, if any. Further
+    /// connections wait to be accepted.
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+
+    /// The networks of the reverse proxies, like `127.0.0.1/32`, whose
+    /// `x-forwarded-for` and `x-real-ip` headers give the address of the
+    /// clients.
+    #[serde(default)]
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            tls: None,
+            http2: Self::default_http2(),
+            keep_alive: Self::default_keep_alive(),
+            tcp_keep_alive: Self::default_tcp_keep_alive(),
+            http2_keep_alive_interval: Self::default_http2_keep_alive_interval(),
+            http2_keep_alive_timeout: Self::default_http2_keep_alive_timeout(),
+            max_connections: None,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    fn default_http2() -> bool {
+        true
+    }
+
+    fn default_keep_alive() -> bool {
+        true
+    }
+
+    fn default_tcp_keep_alive() -> Option<Duration> {
+        Some(Duration::from_secs(60))
+    }
+
+    fn default_http2_keep_alive_interval() -> Option<Duration> {
+        Some(Duration::from_secs(20))
+    }
+
+    fn default_http2_keep_alive_timeout() -> Duration {
+        Duration::from_secs(20)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    /// The PEM certificate chain.
+    pub cert: PathBuf,
+
+    /// The PEM private key, in PKCS#8 or RSA format.
+    pub key: PathBuf,
+}
+
+impl TlsConfig {
+    fn acceptor(&self, http2: bool) -> anyhow::Result<TlsAcceptor> {
+        let certs = rustls_pemfile::certs(&mut BufReader::new(
+            File::open(&self.cert)
+                .with_context(|| format!("failed to open `{}`", self.cert.display()))?,
+        ))
+        .context("failed to read the TLS certificates")?
+        .into_iter()
+        .map(rustls::Certificate)
+        .collect();
+
+        let mut key_reader = BufReader::new(
+            File::open(&self.key)
+                .with_context(|| format!("failed to open `{}`", self.key.display()))?,
+        );
+        let key = std::iter::from_fn(|| rustls_pemfile::read_one(&mut key_reader).transpose())
+            .find_map(|item| match item {
+                Ok(rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key)) => {
+                    Some(Ok(rustls::PrivateKey(key)))
+                }
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            })
+            .context("no private key found")?
+            .context("failed to read the TLS private key")?;
+
+        let mut config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .context("invalid TLS certificate or key")?;
+
+        config.alpn_protocols = if http2 {
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        } else {
+            vec![b"http/1.1".to_vec()]
+        };
+
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+/// Serve the filter on the specified address.
+///
+/// Fails if the address cannot be bound, instead of panicking.
+pub async fn serve<F>(filter: F, addr: SocketAddr, config: &ServerConfig) -> anyhow::Result<()>
+where
+    F: Filter<Error = Rejection> + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    let service = warp::service(filter);
+    let tls_acceptor = config
+        .tls
+        .as_ref()
+        .map(|tls| tls.acceptor(config.http2))
+        .transpose()?;
+
+    let mut http = Http::new();
+
+    http.http1_keep_alive(config.keep_alive)
+        .http1_only(!config.http2)
+        .http2_keep_alive_interval(config.http2_keep_alive_interval)
+        .http2_keep_alive_timeout(config.http2_keep_alive_timeout);
+
+    let mut incoming =
+        AddrIncoming::bind(&addr).with_context(|| format!("failed to bind to {}", addr))?;
+
+    incoming
+        .set_nodelay(true)
+        .set_keepalive(config.tcp_keep_alive);
+
+    let trusted_proxies = Arc::new(config.trusted_proxies.clone());
+    let connections = config
+        .max_connections
+        .map(|max| Arc::new(Semaphore::new(max)));
+
+    info!(
+        "Listening on {}{}.",
+        addr,
+        if tls_acceptor.is_some() {
+            " over TLS"
+        } else {
+            ""
+        }
+    );
+
+    loop {
+        let permit = match &connections {
+            Some(connections) => Some(Arc::clone(connections).acquire_owned().await?),
+            None => None,
+        };
+
+        let stream =
+            match futures_util::future::poll_fn(|cx| Pin::new(&mut incoming).poll_accept(cx)).await
+            {
+                Some(Ok(stream)) => stream,
+                Some(Err(err)) => {
+                    warn!("Failed to accept a connection: {}", err);
+                    continue;
+                }
+                None => return Ok(()),
+            };
+
+        let remote_addr = stream.remote_addr();
+        let service = service.clone();
+        let http = http.clone();
+        let tls_acceptor = tls_acceptor.clone();
+        let trusted_proxies = Arc::clone(&trusted_proxies);
+
+        tokio::spawn(async move {
+            let _permit = permit;
+            let service = service_fn(move |request: hyper::Request<hyper::Body>| {
+                let client_ip =
+                    client_ip::resolve(remote_addr.ip(), request.headers(), &trusted_proxies);
+                let layout = JsonLayout::negotiate(&request);
+                let service = service.clone();
+
+                async move {
+                    let response = client_ip::scope(
+                        client_ip,
+                        control_action::scope(request_id::handle(service, request)),
+                    )
+                    .await?;
+
+                    Ok::<_, Infallible>(negotiation::format(response, layout).await)
+                }
+            });
+
+            let result = match tls_acceptor {
+                Some(tls_acceptor) => {
+                    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls_acceptor.accept(stream))
+                        .await
+                    {
+                        Ok(Ok(stream)) => {
+                            http.serve_connection(stream, service).with_upgrades().await
+                        }
+                        Ok(Err(err)) => {
+                            debug!("TLS handshake with {} failed: {}", remote_addr, err);
+                            return;
+                        }
+                        Err(_) => {
+                            debug!("TLS handshake with {} timed out.", remote_addr);
+                            return;
+                        }
+                    }
+                }
+                None => http.serve_connection(stream, service).with_upgrades().await,
+            };
+
+            if let Err(err) = result {
+                debug!("Connection with {} failed: {}", remote_addr, err);
+            }
+        });
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Context;
+use log::{info, warn};
+use serde::Deserialize;
+use tokio::process::Command;
+
+/// The shutdown controller configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShutdownConfig {
+    /// The command to run to shut the system down, and its arguments.
+    #[serde(default = "ShutdownConfig::default_command")]
+    pub command: Vec<String>,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            command: Self::default_command(),
+        }
+    }
+}
+
+impl ShutdownConfig {
+    fn default_command() -> Vec<String> {
+        vec!["systemctl".to_string(), "poweroff".to_string()]
+    }
+}
+
+/// Shuts the system down cleanly.
+pub struct ShutdownController {
+    config: ShutdownConfig,
+    requested: AtomicBool,
+}
+
+impl ShutdownController {
+    pub fn new(config: ShutdownConfig) -> Self {
+        Self {
+            config,
+            requested: AtomicBool::new(false),
+        }
+    }
+
+    /// Check whether a shutdown was already requested.
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Shut the system down.
+    ///
+    /// Only the first request runs the shutdown command: later requests are
+    /// ignored.
+    pub async fn shutdown(&self, reason: &str) -> anyhow::Result<()> {
+        if self.requested.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        warn!("Shutting the system down: {}", reason);
+
+        let result = self.run_command().await;
+
+        if result.is_err() {
+            // Allow retrying if the command failed.
+            self.requested.store(false, Ordering::SeqCst);
+        }
+
+        result
+    }
+
+    async fn run_command(&self) -> anyhow::Result<()> {
+        let (program, args) = self
+            .config
+            .command
+            .split_first()
+            .context("the shutdown command is empty")?;
+
+        let status = Command::new(program)
+            .args(args)
+            .status()
+            .await
+            .context("failed to run the shutdown command")?;
+
+        if !status.success() {
+            anyhow::bail!("the shutdown command failed: {}", status);
+        }
+
+        info!("System shutdown initiated.");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
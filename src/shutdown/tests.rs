@@ -0,0 +1,34 @@
+//! Tests of the shutdown controller.
+
+use super::*;
+
+fn controller(command: &[&str]) -> ShutdownController {
+    ShutdownController::new(ShutdownConfig {
+        command: command.iter().map(ToString::to_string).collect(),
+    })
+}
+
+#[tokio::test]
+async fn failed_shutdowns_can_be_retried() {
+    for command in [&["false"][..], &["/nonexistent/poweroff"], &[]] {
+        let controller = controller(command);
+
+        assert!(controller.shutdown("test").await.is_err());
+        assert!(!controller.requested());
+        assert!(controller.shutdown("test").await.is_err());
+    }
+}
+
+#[tokio::test]
+async fn shutdowns_are_only_run_once() {
+    let mut controller = controller(&["true"]);
+
+    controller.shutdown("test").await.unwrap();
+
+    assert!(controller.requested());
+
+    // Already requested: the command is not run again.
+    controller.config.command = vec!["false".to_string()];
+
+    controller.shutdown("test").await.unwrap();
+}
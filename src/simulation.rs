@@ -0,0 +1,147 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    gpio_controller::GpioController,
+    home_assistant::{self, Client, State},
+};
+
+/// An event of a recorded session, as a line of the events file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulatedEvent {
+    /// When the event happens, in seconds. The replay starts with the first
+    /// event, so this can be relative or a Unix timestamp.
+    pub at: f64,
+
+    #[serde(flatten)]
+    pub kind: SimulatedEventKind,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SimulatedEventKind {
+    /// A new state of a Home Assistant entity.
+    State {
+        entity_id: String,
+        state: String,
+        #[serde(default = "SimulatedEventKind::default_attributes")]
+        attributes: serde_json::Value,
+    },
+
+    /// A reading of the distance sensor, in cm.
+    Distance { cm: f64 },
+
+    /// A level of an input pin.
+    Input { pin: u8, status: bool },
+}
+
+impl SimulatedEventKind {
+    fn default_attributes() -> serde_json::Value {
+        serde_json::Value::Object(Default::default())
+    }
+}
+
+/// Replays a recorded session in place of Home Assistant and the sensors.
+pub struct Simulation {
+    events: Vec<SimulatedEvent>,
+    speed: f64,
+}
+
+impl Simulation {
+    /// Load the events from a JSON lines file, replayed at the specified
+    /// speed factor.
+    pub fn load(path: &Path, speed: f64) -> anyhow::Result<Self> {
+        if !(speed.is_finite() && speed > 0.0) {
+            anyhow::bail!("the simulation speed must be positive");
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read `{}`", path.display()))?;
+        let mut events = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str::<SimulatedEvent>(line).with_context(|| {
+                    format!("invalid event on line {} of `{}`", i + 1, path.display())
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        events.sort_by(|a, b| a.at.total_cmp(&b.at));
+
+        Ok(Self { events, speed })
+    }
+
+    /// Run the Home Assistant client against the replayed states, and feed
+    /// the replayed readings to the GPIO.
+    ///
+    /// Keeps running once the replay is over, with the last states.
+    pub async fn run(self, client: Client, gpio: Arc<GpioController>) -> anyhow::Result<()> {
+        let (tx, rx) = mpsc::channel(16);
+
+        tokio::try_join!(
+            async { Ok::<_, anyhow::Error>(client.run_simulated(rx).await?) },
+            self.replay(tx, &gpio),
+        )?;
+
+        Ok(())
+    }
+
+    async fn replay(&self, tx: mpsc::Sender<State>, gpio: &GpioController) -> anyhow::Result<()> {
+        info!(
+            "Replaying {} events at {}x speed.",
+            self.events.len(),
+            self.speed
+        );
+
+        let start = Instant::now();
+        let first = self.events.first().map_or(0.0, |event| event.at);
+
+        for event in &self.events {
+            let offset = Duration::from_secs_f64(((event.at - first) / self.speed).max(0.0));
+
+            tokio::time::sleep_until(start + offset).await;
+
+            match &event.kind {
+                SimulatedEventKind::State {
+                    entity_id,
+                    state,
+                    attributes,
+                } => {
+                    let now = Utc::now();
+
+                    tx.send(State {
+                        entity_id: entity_id.clone(),
+                        attributes: attributes.clone().into(),
+                        context: home_assistant::Context::default(),
+                        last_changed: now,
+                        last_updated: now,
+                        state: state.clone(),
+                    })
+                    .await
+                    .context("the simulated client stopped")?;
+                }
+                SimulatedEventKind::Distance { cm } => {
+                    if let Err(err) = gpio.simulate_distance(*cm) {
+                        warn!("Failed to simulate a distance of {}cm: {}", cm, err);
+                    }
+                }
+                SimulatedEventKind::Input { pin, status } => {
+                    if let Err(err) = gpio.simulate_input(*pin, *status) {
+                        warn!("Failed to simulate input pin {}: {}", pin, err);
+                    }
+                }
+            }
+        }
+
+        info!("The simulation is over: keeping the last states.");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::watch;
+
+use crate::{context::AppContext, tasks};
+
+/// The number of volume changes of the fade.
+const FADE_STEPS: u32 = 10;
+
+/// The sleep timer configuration.
+///
+/// Once the countdown ends, the media players fade out and stop, and the
+/// lights fade off.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct SleepTimerConfig {
+    /// The media players to stop, like `media_player.bedroom`.
+    #[serde(default)]
+    pub media_players: Vec<String>,
+
+    /// The lights to turn off, like `light.bedroom`.
+    #[serde(default)]
+    pub lights: Vec<String>,
+
+    /// How long the media players and the lights take to fade out.
+    #[serde(default = "SleepTimerConfig::default_fade")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub fade: Duration,
+
+    /// The countdown, when none is requested.
+    #[serde(default = "SleepTimerConfig::default_duration")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub default_duration: Duration,
+}
+
+impl SleepTimerConfig {
+    fn default_fade() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_duration() -> Duration {
+        Duration::from_secs(30 * 60)
+    }
+
+    pub fn entity_ids(&self) -> Vec<String> {
+        self.media_players
+            .iter()
+            .chain(&self.lights)
+            .cloned()
+            .collect()
+    }
+}
+
+/// A countdown running on the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Timer {
+    /// What the countdown is for, like `sleep`.
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// Turns the media players and the lights off after a countdown.
+///
+/// The countdown runs here rather than on the device that started it, so it
+/// ends even if that one went away meanwhile.
+pub struct SleepTimer {
+    config: SleepTimerConfig,
+    current: watch::Sender<Option<Timer>>,
+}
+
+impl SleepTimer {
+    pub fn new(config: SleepTimerConfig) -> Self {
+        Self {
+            config,
+            current: watch::channel(None).0,
+        }
+    }
+
+    /// The countdown in progress, if any.
+    pub fn current(&self) -> Option<Timer> {
+        self.current.borrow().clone()
+    }
+
+    /// Start the countdown, replacing the one in progress.
+    pub fn start(&self, duration: Option<Duration>) -> Timer {
+        let duration = duration.unwrap_or(self.config.default_duration);
+        let started_at = Utc::now();
+        let timer = Timer {
+            name: "sleep".to_string(),
+            started_at,
+            ends_at: started_at + chrono::Duration::from_std(duration).unwrap_or_default(),
+        };
+
+        info!("Starting the sleep timer for {:?}.", duration);
+
+        self.current.send_replace(Some(timer.clone()));
+
+        timer
+    }
+
+    /// Stop the countdown in progress.
+    ///
+    /// Returns `false` if there was none.
+    pub fn cancel(&self) -> bool {
+        let cancelled = self.current.send_replace(None).is_some();
+
+        if cancelled {
+            info!("Cancelling the sleep timer.");
+        }
+
+        cancelled
+    }
+
+    /// Turn the media players and the lights off when the countdowns end,
+    /// forever.
+    pub async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        let mut changes = self.current.subscribe();
+
+        loop {
+            let ends_at = changes
+                .borrow_and_update()
+                .as_ref()
+                .map(|timer| timer.ends_at);
+
+            match ends_at {
+                Some(ends_at) => {
+                    let delay = (ends_at - Utc::now()).to_std().unwrap_or_default();
+
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {
+                            // Unless it was restarted meanwhile.
+                            let ended = self.current.send_if_modified(|current| {
+                                let ended = current
+                                    .as_ref()
+                                    .is_some_and(|timer| timer.ends_at <= Utc::now());
+
+                                if ended {
+                                    *current = None;
+                                }
+
+                                ended
+                            });
+
+                            if ended {
+                                self.turn_off(context).await;
+                            }
+                        }
+                        r = changes.changed() => r?,
+                    }
+                }
+                None => changes.changed().await?,
+            }
+        }
+    }
+
+    async fn turn_off(&self, context: &AppContext) {
+        let ha = &context.home_assistant;
+
+        info!("The sleep timer ended: turning off the media players and the lights.");
+
+        if !self.config.lights.is_empty() {
+            if let Err(err) = ha
+                .lights_turn_off(&self.config.lights, self.config.fade)
+                .await
+            {
+                warn!("Failed to turn the lights off: {}", err);
+            }
+        }
+
+        if self.config.media_players.is_empty() {
+            return;
+        }
+
+        // The volumes are restored once stopped, for the next time.
+        let mut volumes = Vec::with_capacity(self.config.media_players.len());
+
+        for entity_id in &self.config.media_players {
+            let volume_level = ha
+                .entity(entity_id)
+                .await
+                .and_then(|state| state.attributes.get::<f64>("volume_level"));
+
+            if let Some(volume_level) = volume_level {
+                volumes.push((entity_id, volume_level));
+            }
+        }
+
+        for step in 1..=FADE_STEPS {
+            tokio::time::sleep(self.config.fade / FADE_STEPS).await;
+            tasks::heartbeat();
+
+            let remaining = f64::from(FADE_STEPS - step) / f64::from(FADE_STEPS);
+
+            for (entity_id, volume_level) in &volumes {
+                if let Err(err) = ha
+                    .media_player_volume_set(entity_id, volume_level * remaining)
+                    .await
+                {
+                    warn!("Failed to fade `{}` out: {}", entity_id, err);
+                }
+            }
+        }
+
+        if let Err(err) = ha.media_players_stop(&self.config.media_players).await {
+            warn!("Failed to stop the media players: {}", err);
+        }
+
+        for (entity_id, volume_level) in volumes {
+            if let Err(err) = ha.media_player_volume_set(entity_id, volume_level).await {
+                warn!("Failed to restore the volume of `{}`: {}", entity_id, err);
+            }
+        }
+
+        if let Err(err) = ha.media_players_turn_off(&self.config.media_players).await {
+            warn!("Failed to turn the media players off: {}", err);
+        }
+    }
+}
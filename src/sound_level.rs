@@ -0,0 +1,165 @@
+use std::{process::Stdio, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncReadExt, process::Command, sync::RwLock};
+
+const SAMPLE_RATE: u32 = 16000;
+
+/// The microphone-based sound level sensor configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SoundLevelConfig {
+    /// The ALSA capture device, like `plughw:1,0`. Uses the default device if
+    /// unspecified.
+    #[serde(default)]
+    pub device: Option<String>,
+
+    /// The ALSA recorder command.
+    #[serde(default = "SoundLevelConfig::default_recorder")]
+    pub recorder: String,
+
+    /// The offset to add to the measured dBFS to estimate the dB SPL.
+    ///
+    /// This depends on the microphone and its gain, and must be calibrated
+    /// against a reference sound level meter.
+    #[serde(default = "SoundLevelConfig::default_calibration_offset")]
+    pub calibration_offset_db: f64,
+
+    /// The `input_number` entity to report the sound level to, if any.
+    #[serde(default)]
+    pub ha_input_number: Option<String>,
+
+    /// The sound level from which someone is considered present, if any.
+    #[serde(default)]
+    pub presence_threshold_db: Option<f64>,
+}
+
+impl SoundLevelConfig {
+    fn default_recorder() -> String {
+        "arecord".to_string()
+    }
+
+    fn default_calibration_offset() -> f64 {
+        90.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SoundLevel {
+    /// The estimated sound level, in dB.
+    pub level_db: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Samples a microphone and tracks the ambient sound level.
+pub struct SoundLevelSensor {
+    config: SoundLevelConfig,
+    level: RwLock<Option<SoundLevel>>,
+}
+
+impl SoundLevelSensor {
+    pub fn new(config: SoundLevelConfig) -> Self {
+        Self {
+            config,
+            level: RwLock::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &SoundLevelConfig {
+        &self.config
+    }
+
+    /// Get the last measured sound level, if any.
+    pub async fn level(&self) -> Option<SoundLevel> {
+        *self.level.read().await
+    }
+
+    /// Check whether the sound level indicates someone is present.
+    pub async fn presence_detected(&self) -> bool {
+        match (self.config.presence_threshold_db, self.level().await) {
+            (Some(threshold), Some(level)) => level.level_db >= threshold,
+            _ => false,
+        }
+    }
+
+    /// Sample the microphone forever, restarting the recorder if it fails.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let retry_delay = Duration::from_secs(10);
+
+        loop {
+            if let Err(err) = self.sample().await {
+                warn!("Sound level sampling failed: {}", err);
+            }
+
+            *self.level.write().await = None;
+            tokio::time::sleep(retry_delay).await;
+        }
+    }
+
+    async fn sample(&self) -> anyhow::Result<()> {
+        let mut command = Command::new(&self.config.recorder);
+
+        command.args([
+            "-q",
+            "-f",
+            "S16_LE",
+            "-c",
+            "1",
+            "-r",
+            &SAMPLE_RATE.to_string(),
+            "-t",
+            "raw",
+        ]);
+
+        if let Some(device) = &self.config.device {
+            command.arg("-D").arg(device);
+        }
+
+        let mut child = command
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("failed to run the audio recorder")?;
+        let mut stdout = child
+            .stdout
+            .take()
+            .context("failed to open the audio recorder output")?;
+
+        info!("Sampling the ambient sound level.");
+
+        // Half a second of 16-bit samples.
+        let mut buffer = vec![0u8; SAMPLE_RATE as usize];
+
+        loop {
+            stdout
+                .read_exact(&mut buffer)
+                .await
+                .context("failed to read from the audio recorder")?;
+
+            let level_db = dbfs(&buffer) + self.config.calibration_offset_db;
+
+            *self.level.write().await = Some(SoundLevel {
+                level_db,
+                timestamp: Utc::now(),
+            });
+        }
+    }
+}
+
+/// Compute the RMS level of signed 16-bit little-endian samples, in dBFS.
+fn dbfs(buffer: &[u8]) -> f64 {
+    let (sum, count) = buffer
+        .chunks_exact(2)
+        .map(|sample| f64::from(i16::from_le_bytes([sample[0], sample[1]])) / 32768.0)
+        .fold((0.0, 0usize), |(sum, count), sample| {
+            (sum + sample * sample, count + 1)
+        });
+
+    let rms = (sum / count.max(1) as f64).sqrt();
+
+    20.0 * rms.max(1e-9).log10()
+}
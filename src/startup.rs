@@ -0,0 +1,173 @@
+//! The report of the environment logged on startup, as a single JSON record,
+//! for the provisioning logs of a fleet of panels to be parsed.
+
+use std::{collections::BTreeMap, net::SocketAddr, path::Path};
+
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::{context::AppContext, net_check, self_check::CheckResult};
+
+/// The files holding the model of the board, on the device-tree systems like
+/// the Pi.
+const MODEL_PATHS: &[&str] = &[
+    "/proc/device-tree/model",
+    "/sys/firmware/devicetree/base/model",
+];
+
+/// The buses, and the prefixes of their devices in `/dev`.
+const BUSES: &[(&str, &[&str])] = &[
+    ("gpio", &["gpiomem", "gpiochip"]),
+    ("i2c", &["i2c-"]),
+    ("spi", &["spidev"]),
+    ("serial", &["serial", "ttyAMA", "ttyUSB", "ttyACM"]),
+];
+
+/// The features the binary can be built with.
+const FEATURES: &[(&str, bool)] = &[
+    ("barcode", cfg!(feature = "barcode")),
+    ("debug", cfg!(feature = "debug")),
+    ("frontend", cfg!(feature = "frontend")),
+    ("gpio", cfg!(feature = "gpio")),
+    ("hid", cfg!(feature = "hid")),
+    ("mqtt", cfg!(feature = "mqtt")),
+    ("presence", cfg!(feature = "presence")),
+    ("rfid", cfg!(feature = "rfid")),
+    ("scheduler", cfg!(feature = "scheduler")),
+    ("storage", cfg!(feature = "storage")),
+    ("weather", cfg!(feature = "weather")),
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupReport {
+    pub version: &'static str,
+    pub hardware: Hardware,
+
+    /// The features the binary was built with.
+    pub features: Vec<&'static str>,
+
+    /// Where the configuration was read from, the ones taking precedence
+    /// last.
+    pub config_sources: Vec<String>,
+
+    /// Whether running in a container.
+    pub docker: bool,
+    pub home_assistant: HomeAssistantReport,
+    pub listening: Listening,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hardware {
+    /// The model of the board, like `Raspberry Pi 4 Model B Rev 1.4`, if
+    /// known.
+    pub model: Option<String>,
+
+    /// The devices of the available buses, by bus.
+    pub buses: BTreeMap<&'static str, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HomeAssistantReport {
+    pub endpoint: String,
+
+    /// Whether Home-Assistant could be reached.
+    pub reachable: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Listening {
+    pub endpoint: SocketAddr,
+    pub tls: bool,
+    pub api_prefixes: Vec<String>,
+    pub static_prefix: String,
+
+    /// The URL the frontend is proxied to, if not embedded.
+    pub reverse_proxy_url: Option<String>,
+}
+
+/// Check the environment, and log its report as a single record.
+pub async fn log_report(
+    context: AppContext,
+    config_sources: Vec<String>,
+    docker: bool,
+    listening: Listening,
+) {
+    let rest_url = context.home_assistant.rest_url();
+    let checks = net_check::check_home_assistant(&context.config.net_check, rest_url).await;
+    let report = StartupReport {
+        version: env!("CARGO_PKG_VERSION"),
+        hardware: Hardware::detect(),
+        features: FEATURES
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(feature, _)| *feature)
+            .collect(),
+        config_sources,
+        docker,
+        home_assistant: HomeAssistantReport {
+            endpoint: rest_url.to_string(),
+            reachable: checks.iter().all(|check| check.passed),
+            checks,
+        },
+        listening,
+    };
+
+    match serde_json::to_string(&report) {
+        Ok(report) => info!(target: crate::log::REPORT_TARGET, "{}", report),
+        Err(err) => warn!("Failed to serialize the startup report: {}", err),
+    }
+}
+
+impl Hardware {
+    fn detect() -> Self {
+        let model = MODEL_PATHS
+            .iter()
+            .find_map(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| parse_model(&content));
+        let devices: Vec<String> = match std::fs::read_dir(Path::new("/dev")) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        Self {
+            model,
+            buses: buses(&devices),
+        }
+    }
+}
+
+/// Parse the model of the board, which the device-tree terminates with a
+/// NUL.
+fn parse_model(content: &str) -> Option<String> {
+    let model = content.trim_end_matches('\0').trim();
+
+    (!model.is_empty()).then(|| model.to_string())
+}
+
+/// Group the devices by bus, sorted, omitting the buses without any.
+fn buses(devices: &[String]) -> BTreeMap<&'static str, Vec<String>> {
+    BUSES
+        .iter()
+        .filter_map(|(bus, prefixes)| {
+            let mut bus_devices: Vec<_> = devices
+                .iter()
+                .filter(|device| prefixes.iter().any(|prefix| device.starts_with(prefix)))
+                .cloned()
+                .collect();
+
+            bus_devices.sort();
+
+            (!bus_devices.is_empty()).then_some((*bus, bus_devices))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,29 @@
+//! Tests of the startup report.
+
+use super::*;
+
+#[test]
+fn the_model_is_trimmed() {
+    assert_eq!(
+        parse_model("Raspberry Pi 4 Model B Rev 1.4\0").as_deref(),
+        Some("Raspberry Pi 4 Model B Rev 1.4")
+    );
+    assert_eq!(parse_model("\0"), None);
+}
+
+#[test]
+fn the_devices_are_grouped_by_bus() {
+    let devices: Vec<String> = ["tty1", "i2c-1", "gpiomem", "ttyUSB0", "i2c-0", "null"]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    assert_eq!(
+        buses(&devices),
+        BTreeMap::from([
+            ("gpio", vec!["gpiomem".to_string()]),
+            ("i2c", vec!["i2c-0".to_string(), "i2c-1".to_string()]),
+            ("serial", vec!["ttyUSB0".to_string()]),
+        ])
+    );
+}
@@ -0,0 +1,223 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use futures_util::FutureExt;
+use log::{error, info, warn};
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::sync::Notify;
+
+/// The delay before restarting a failed task, doubled on every failure in a
+/// row.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(5 * 60);
+
+tokio::task_local! {
+    static CURRENT: (Tasks, &'static str);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskState {
+    Running,
+
+    /// The task is disabled by the configuration and does nothing.
+    Idle,
+    Finished,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskStatus {
+    pub name: &'static str,
+    pub state: TaskState,
+    pub started_at: DateTime<Utc>,
+
+    /// When the task last reported progress, if ever. Tasks waiting on
+    /// external events may not report any.
+    pub last_heartbeat: Option<DateTime<Utc>>,
+
+    /// The error the task last failed with, even if restarted since.
+    pub error: Option<String>,
+
+    /// The number of times the task was restarted after failing.
+    pub restarts: u32,
+}
+
+/// The registry of the long-running tasks.
+#[derive(Debug, Clone, Default)]
+pub struct Tasks {
+    tasks: Arc<Mutex<BTreeMap<&'static str, TaskStatus>>>,
+    exit: Arc<Exit>,
+}
+
+/// The request to end the process, which restarting a failed task can't do.
+#[derive(Debug, Default)]
+struct Exit {
+    error: Mutex<Option<String>>,
+    notify: Notify,
+}
+
+impl Tasks {
+    /// Run a task, tracking its state under the specified name.
+    ///
+    /// The task can report progress through [`heartbeat`] and [`idle`].
+    pub async fn run<F, T, E>(&self, name: &'static str, f: F) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: Display,
+    {
+        {
+            let mut tasks = self.tasks.lock().unwrap();
+            let status = tasks.entry(name).or_insert_with(|| TaskStatus {
+                name,
+                state: TaskState::Running,
+                started_at: Utc::now(),
+                last_heartbeat: None,
+                error: None,
+                restarts: 0,
+            });
+
+            status.state = TaskState::Running;
+            status.started_at = Utc::now();
+            status.last_heartbeat = None;
+        }
+
+        let result = CURRENT.scope((self.clone(), name), f).await;
+
+        match &result {
+            Ok(_) => {
+                info!("Task `{}` finished.", name);
+
+                self.update(name, |status| status.state = TaskState::Finished);
+            }
+            Err(err) => {
+                error!("Task `{}` failed: {}", name, err);
+
+                self.update(name, |status| {
+                    status.state = TaskState::Failed;
+                    status.error = Some(err.to_string());
+                });
+            }
+        }
+
+        result
+    }
+
+    /// Spawn a task, restarting it whenever it fails or panics, after a delay
+    /// growing with the failures in a row.
+    ///
+    /// The task failing does not bring the others down: it shows as failed
+    /// until restarted.
+    pub fn spawn<F, Fut>(&self, name: &'static str, f: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let tasks = self.clone();
+
+        tokio::spawn(async move {
+            let mut delay = RESTART_DELAY;
+
+            loop {
+                let started_at = Instant::now();
+                let task = AssertUnwindSafe(f())
+                    .catch_unwind()
+                    .map(|result| result.unwrap_or_else(|_| Err(anyhow::anyhow!("panicked"))));
+
+                if tasks.run(name, task).await.is_ok() {
+                    return;
+                }
+
+                // The failures of a task that ran for a while are not in a row.
+                if started_at.elapsed() >= MAX_RESTART_DELAY {
+                    delay = RESTART_DELAY;
+                }
+
+                warn!("Restarting task `{}` in {:?}...", name, delay);
+
+                tokio::time::sleep(delay).await;
+                tasks.update(name, |status| status.restarts += 1);
+                delay = (delay * 2).min(MAX_RESTART_DELAY);
+            }
+        });
+    }
+
+    /// Ask for the process to end with an error, for its supervisor to restart
+    /// it.
+    ///
+    /// Failing the task would only restart it, as done by [`Tasks::spawn`].
+    pub fn exit(&self, error: impl Display) {
+        error!("Exiting: {}", error);
+
+        self.exit
+            .error
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| error.to_string());
+        self.exit.notify.notify_waiters();
+    }
+
+    /// Wait for a task to ask for the process to end, with [`Tasks::exit`].
+    pub async fn exited(&self) -> anyhow::Error {
+        loop {
+            let notified = self.exit.notify.notified();
+
+            if let Some(error) = self.exit.error.lock().unwrap().clone() {
+                return anyhow::anyhow!(error);
+            }
+
+            notified.await;
+        }
+    }
+
+    /// Get the status of all the tasks, sorted by name.
+    pub fn status(&self) -> Vec<TaskStatus> {
+        self.tasks.lock().unwrap().values().cloned().collect()
+    }
+
+    fn update(&self, name: &'static str, f: impl FnOnce(&mut TaskStatus)) {
+        if let Some(status) = self.tasks.lock().unwrap().get_mut(name) {
+            f(status);
+        }
+    }
+}
+
+/// Report that the current task is making progress.
+///
+/// Does nothing outside of a task run by [`Tasks::run`].
+pub fn heartbeat() {
+    let _ = CURRENT.try_with(|(tasks, name)| {
+        tasks.update(name, |status| status.last_heartbeat = Some(Utc::now()))
+    });
+}
+
+/// Warn that a configured feature was not compiled in, and idle in place of
+/// its task.
+pub async fn unsupported<T>(what: &str, feature: &str) -> T {
+    warn!(
+        "{} is configured but the `{}` feature was not compiled in",
+        what, feature
+    );
+
+    idle().await
+}
+
+/// Mark the current task as idle, and never return.
+pub async fn idle<T>() -> T {
+    let _ = CURRENT
+        .try_with(|(tasks, name)| tasks.update(name, |status| status.state = TaskState::Idle));
+
+    futures_util::future::pending().await
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,94 @@
+//! Tests of the tasks.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::*;
+
+async fn wait_for(tasks: &Tasks, condition: impl Fn(&TaskStatus) -> bool) -> TaskStatus {
+    for _ in 0..100 {
+        if let Some(status) = tasks.status().into_iter().find(|status| condition(status)) {
+            return status;
+        }
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    panic!("unexpected tasks: {:?}", tasks.status());
+}
+
+#[tokio::test]
+async fn failed_tasks_are_restarted_alone() {
+    let tasks = Tasks::default();
+    let runs = Arc::new(AtomicU32::new(0));
+
+    tasks.spawn("healthy", idle);
+    tasks.spawn("flaky", {
+        let runs = Arc::clone(&runs);
+
+        move || {
+            let first = runs.fetch_add(1, Ordering::Relaxed) == 0;
+
+            async move {
+                if first {
+                    anyhow::bail!("device lost");
+                }
+
+                idle().await
+            }
+        }
+    });
+
+    let failed = wait_for(&tasks, |status| status.state == TaskState::Failed).await;
+
+    assert_eq!(failed.name, "flaky");
+    assert_eq!(failed.error.as_deref(), Some("device lost"));
+
+    let restarted = wait_for(&tasks, |status| {
+        status.restarts == 1 && status.state == TaskState::Idle
+    })
+    .await;
+
+    assert_eq!(restarted.name, "flaky");
+    assert_eq!(restarted.error.as_deref(), Some("device lost"));
+    assert_eq!(runs.load(Ordering::Relaxed), 2);
+    assert!(tasks
+        .status()
+        .iter()
+        .all(|status| status.state == TaskState::Idle));
+}
+
+#[tokio::test]
+async fn panics_fail_the_task() {
+    let tasks = Tasks::default();
+
+    tasks.spawn("panicking", || async { panic!("bug") });
+
+    let failed = wait_for(&tasks, |status| status.state == TaskState::Failed).await;
+
+    assert_eq!(failed.error.as_deref(), Some("panicked"));
+}
+
+#[tokio::test]
+async fn exiting_outlives_the_task_restarts() {
+    let tasks = Tasks::default();
+
+    tasks.spawn("fatal", {
+        let tasks = tasks.clone();
+
+        move || {
+            let tasks = tasks.clone();
+
+            async move {
+                tasks.exit("device lost for good");
+
+                anyhow::bail!("device lost")
+            }
+        }
+    });
+
+    let err = tokio::time::timeout(Duration::from_secs(5), tasks.exited())
+        .await
+        .expect("the process should exit");
+
+    assert_eq!(err.to_string(), "device lost for good");
+}
@@ -0,0 +1,119 @@
+//! Lightweight templates in the action payloads, like
+//! `Temperature is {{ states('sensor.indoor') }}°C`.
+//!
+//! Only a subset of the Home-Assistant templates is supported, evaluated
+//! against the local states rather than by Home-Assistant:
+//!
+//! - `states('sensor.indoor')`: the state of an entity, `unknown` if it is
+//!   missing;
+//! - `state_attr('climate.living_room', 'temperature')`: an attribute of an
+//!   entity, empty if it is missing.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail};
+use serde_json::Value;
+
+use crate::home_assistant::State;
+
+const OPENING: &str = "{{";
+const CLOSING: &str = "}}";
+
+/// Check whether the strings of a value contain templates.
+pub fn is_template(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s.contains(OPENING),
+        Value::Array(values) => values.iter().any(is_template),
+        Value::Object(map) => map.values().any(is_template),
+        Value::Null | Value::Bool(_) | Value::Number(_) => false,
+    }
+}
+
+/// Render the templates of the strings of a value.
+pub fn render_value(value: &Value, entities: &HashMap<String, State>) -> anyhow::Result<Value> {
+    Ok(match value {
+        Value::String(s) => Value::String(render(s, entities)?),
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| render_value(value, entities))
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| Ok((key.clone(), render_value(value, entities)?)))
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Value::Null | Value::Bool(_) | Value::Number(_) => value.clone(),
+    })
+}
+
+/// Render the templates of a string.
+pub fn render(template: &str, entities: &HashMap<String, State>) -> anyhow::Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(OPENING) {
+        let end = rest[start..]
+            .find(CLOSING)
+            .ok_or_else(|| anyhow!("unterminated template in `{}`", template))?;
+
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&evaluate(
+            rest[start + OPENING.len()..start + end].trim(),
+            entities,
+        )?);
+        rest = &rest[start + end + CLOSING.len()..];
+    }
+
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+/// Evaluate an expression, like `states('sensor.indoor')`.
+fn evaluate(expression: &str, entities: &HashMap<String, State>) -> anyhow::Result<String> {
+    let (function, arguments) = expression
+        .strip_suffix(')')
+        .and_then(|call| call.split_once('('))
+        .ok_or_else(|| anyhow!("unsupported template expression `{}`", expression))?;
+    let arguments = arguments
+        .split(',')
+        .map(|argument| {
+            let argument = argument.trim();
+
+            argument
+                .strip_prefix('\'')
+                .and_then(|argument| argument.strip_suffix('\''))
+                .or_else(|| {
+                    argument
+                        .strip_prefix('"')
+                        .and_then(|argument| argument.strip_suffix('"'))
+                })
+                .ok_or_else(|| anyhow!("the argument `{}` is not a string", argument))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    match (function.trim(), arguments.as_slice()) {
+        ("states", [entity_id]) => Ok(entities
+            .get(*entity_id)
+            .map_or_else(|| "unknown".to_string(), |state| state.state.clone())),
+        ("state_attr", [entity_id, attribute]) => Ok(entities
+            .get(*entity_id)
+            .and_then(|state| state.attributes.get::<Value>(attribute))
+            .map(|value| match value {
+                Value::String(s) => s,
+                Value::Null => String::new(),
+                value => value.to_string(),
+            })
+            .unwrap_or_default()),
+        (function, _) => bail!(
+            "unsupported template function `{}` with {} arguments",
+            function,
+            arguments.len()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,101 @@
+//! Tests of the rendering of the templates against local states.
+
+use serde_json::json;
+
+use super::*;
+
+fn entities() -> HashMap<String, State> {
+    [
+        json!({
+            "entity_id": "sensor.indoor",
+            "state": "21.5",
+            "last_changed": "2024-05-06T12:00:00Z",
+            "last_updated": "2024-05-06T12:00:00Z",
+        }),
+        json!({
+            "entity_id": "climate.living_room",
+            "state": "heat",
+            "attributes": { "temperature": 19, "hvac_action": "heating", "preset": null },
+            "last_changed": "2024-05-06T12:00:00Z",
+            "last_updated": "2024-05-06T12:00:00Z",
+        }),
+    ]
+    .into_iter()
+    .map(|state| {
+        let state: State = serde_json::from_value(state).expect("invalid state");
+
+        (state.entity_id.clone(), state)
+    })
+    .collect()
+}
+
+#[test]
+fn render_replaces_the_states() {
+    let entities = entities();
+
+    assert_eq!(
+        render("Temperature is {{ states('sensor.indoor') }}°C", &entities).unwrap(),
+        "Temperature is 21.5°C"
+    );
+    assert_eq!(
+        render("{{states(\"sensor.missing\")}}", &entities).unwrap(),
+        "unknown"
+    );
+    assert_eq!(render("No template", &entities).unwrap(), "No template");
+}
+
+#[test]
+fn render_replaces_the_attributes() {
+    let entities = entities();
+
+    assert_eq!(
+        render(
+            "{{ state_attr('climate.living_room', 'hvac_action') }} to {{ state_attr('climate.living_room', 'temperature') }}",
+            &entities
+        )
+        .unwrap(),
+        "heating to 19"
+    );
+    assert_eq!(
+        render(
+            "[{{ state_attr('climate.living_room', 'preset') }}{{ state_attr('climate.living_room', 'missing') }}]",
+            &entities
+        )
+        .unwrap(),
+        "[]"
+    );
+}
+
+#[test]
+fn render_rejects_invalid_templates() {
+    let entities = entities();
+
+    for template in [
+        "{{ states('sensor.indoor') ",
+        "{{ now() }}",
+        "{{ states(sensor.indoor) }}",
+        "{{ states('sensor.indoor') | float }}",
+        "{{ state_attr('climate.living_room') }}",
+    ] {
+        assert!(render(template, &entities).is_err(), "{}", template);
+    }
+}
+
+#[test]
+fn render_value_renders_the_nested_strings() {
+    let entities = entities();
+    let data = json!({
+        "message": "It is {{ states('sensor.indoor') }}°C",
+        "data": { "tags": ["{{ states('climate.living_room') }}", 1, null] },
+    });
+
+    assert!(is_template(&data));
+    assert!(!is_template(&json!({ "message": "Hello", "count": 2 })));
+    assert_eq!(
+        render_value(&data, &entities).unwrap(),
+        json!({
+            "message": "It is 21.5°C",
+            "data": { "tags": ["heat", 1, null] },
+        })
+    );
+}
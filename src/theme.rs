@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::astronomy::AstronomyConfig;
+
+/// The altitude of the sun at sunrise and sunset, accounting for the
+/// refraction and the radius of the sun.
+const SUNSET_ALTITUDE: f64 = -0.833;
+
+/// The theme of the panels, served to the frontend so they can be re-themed
+/// without rebuilding it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ThemeConfig {
+    /// The colors of the light theme, by name, like `primary: "#1e88e5"`.
+    /// The frontend keeps its own for the missing ones.
+    #[serde(default)]
+    pub light: BTreeMap<String, String>,
+
+    /// The colors of the dark theme, by name.
+    #[serde(default)]
+    pub dark: BTreeMap<String, String>,
+
+    /// The factor of the font sizes.
+    #[serde(default = "ThemeConfig::default_font_scale")]
+    pub font_scale: f64,
+
+    /// When the dark theme applies, like from `21:00` to `07:00`. Always the
+    /// light theme if unset.
+    #[serde(default)]
+    pub dark_schedule: Option<DarkSchedule>,
+
+    /// Whether the dark theme applies from sunset to sunrise instead of on
+    /// schedule, if the astronomy is configured.
+    #[serde(default)]
+    pub follow_sun: bool,
+
+    /// The accent colors of the rooms, by dashboard panel title.
+    #[serde(default)]
+    pub room_accents: BTreeMap<String, String>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            light: BTreeMap::new(),
+            dark: BTreeMap::new(),
+            font_scale: Self::default_font_scale(),
+            dark_schedule: None,
+            follow_sun: false,
+            room_accents: BTreeMap::new(),
+        }
+    }
+}
+
+/// The local times between which the dark theme applies, possibly over
+/// midnight.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DarkSchedule {
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Theme {
+    pub mode: ThemeMode,
+
+    /// The colors of the current mode, by name.
+    pub colors: BTreeMap<String, String>,
+    pub font_scale: f64,
+    pub room_accents: BTreeMap<String, String>,
+
+    /// When the mode switches next, if it does, for the panels to fetch the
+    /// theme again.
+    pub next_switch: Option<DateTime<Utc>>,
+}
+
+impl ThemeConfig {
+    fn default_font_scale() -> f64 {
+        1.0
+    }
+
+    /// Get the theme at a local time.
+    pub fn theme<Tz: TimeZone>(
+        &self,
+        now: &DateTime<Tz>,
+        astronomy: Option<&AstronomyConfig>,
+    ) -> Theme {
+        let utc = now.with_timezone(&Utc);
+        let (dark, next_switch) = match (astronomy, &self.dark_schedule) {
+            (Some(astronomy), _) if self.follow_sun => {
+                let today = now.naive_local().date();
+                let next_switch = [today.pred_opt(), Some(today), today.succ_opt()]
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|date| {
+                        [true, false]
+                            .map(|rising| astronomy.sun_crossing(date, SUNSET_ALTITUDE, rising))
+                    })
+                    .flatten()
+                    .filter(|at| *at > utc)
+                    .min();
+
+                (!astronomy.sun_above(utc, SUNSET_ALTITUDE), next_switch)
+            }
+            (_, Some(schedule)) => {
+                let local = now.naive_local();
+                let time = local.time();
+                let dark = if schedule.from <= schedule.to {
+                    schedule.from <= time && time < schedule.to
+                } else {
+                    schedule.from <= time || time < schedule.to
+                };
+                let next_switch = [local.date(), local.date() + Duration::days(1)]
+                    .into_iter()
+                    .flat_map(|date| [date.and_time(schedule.from), date.and_time(schedule.to)])
+                    .filter(|at| *at > local)
+                    .min()
+                    .and_then(|at| now.timezone().from_local_datetime(&at).earliest())
+                    .map(|at| at.with_timezone(&Utc));
+
+                (dark, next_switch)
+            }
+            _ => (false, None),
+        };
+
+        Theme {
+            mode: if dark {
+                ThemeMode::Dark
+            } else {
+                ThemeMode::Light
+            },
+            colors: if dark {
+                self.dark.clone()
+            } else {
+                self.light.clone()
+            },
+            font_scale: self.font_scale,
+            room_accents: self.room_accents.clone(),
+            next_switch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
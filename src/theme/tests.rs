@@ -0,0 +1,75 @@
+//! Tests of the switching between the light and the dark themes.
+
+use super::*;
+
+fn at(time: &str) -> DateTime<Utc> {
+    time.parse().expect("invalid time")
+}
+
+fn config() -> ThemeConfig {
+    ThemeConfig {
+        light: BTreeMap::from([("background".to_string(), "#ffffff".to_string())]),
+        dark: BTreeMap::from([("background".to_string(), "#121212".to_string())]),
+        dark_schedule: Some(DarkSchedule {
+            from: NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+            to: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn is_light_without_a_schedule() {
+    let theme = ThemeConfig::default().theme(&at("2024-06-21T23:00:00Z"), None);
+
+    assert_eq!(theme.mode, ThemeMode::Light);
+    assert_eq!(theme.next_switch, None);
+}
+
+#[test]
+fn follows_the_schedule_over_midnight() {
+    let config = config();
+    let theme = config.theme(&at("2024-06-21T12:00:00Z"), None);
+
+    assert_eq!(theme.mode, ThemeMode::Light);
+    assert_eq!(theme.colors["background"], "#ffffff");
+    assert_eq!(theme.next_switch, Some(at("2024-06-21T21:00:00Z")));
+
+    let theme = config.theme(&at("2024-06-21T23:00:00Z"), None);
+
+    assert_eq!(theme.mode, ThemeMode::Dark);
+    assert_eq!(theme.colors["background"], "#121212");
+    assert_eq!(theme.next_switch, Some(at("2024-06-22T07:00:00Z")));
+}
+
+#[test]
+fn follows_the_sun_if_located() {
+    let config = ThemeConfig {
+        follow_sun: true,
+        ..config()
+    };
+    let paris = AstronomyConfig {
+        latitude: 48.85,
+        longitude: 2.35,
+    };
+
+    // The sun sets after the schedule starts in the summer.
+    let theme = config.theme(&at("2024-06-21T19:30:00Z"), Some(&paris));
+
+    assert_eq!(theme.mode, ThemeMode::Light);
+    assert!(theme
+        .next_switch
+        .is_some_and(|at| at.format("%H").to_string() == "19"));
+
+    let theme = config.theme(&at("2024-06-21T23:00:00Z"), Some(&paris));
+
+    assert_eq!(theme.mode, ThemeMode::Dark);
+    assert!(theme
+        .next_switch
+        .is_some_and(|at| at.format("%m-%d %H").to_string() == "06-22 03"));
+
+    // The schedule applies without a location.
+    let theme = config.theme(&at("2024-06-21T21:30:00Z"), None);
+
+    assert_eq!(theme.mode, ThemeMode::Dark);
+}
@@ -0,0 +1,393 @@
+use std::{path::Path, time::Duration};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures_util::future::try_join_all;
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::{sync::RwLock, time::Instant};
+
+use crate::{
+    context::AppContext,
+    outputs::{DisconnectPolicy, Relay},
+    tasks,
+};
+
+/// The number of consecutive failed readings after which the relay is
+/// turned off, regardless of the minimum cycle.
+const FAILURE_THRESHOLD: u32 = 3;
+
+/// A local thermostat, like for a pool or a boiler.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ThermostatConfig {
+    /// The name of the thermostat, as used in the API path.
+    pub name: String,
+
+    /// Where the temperature is read from.
+    pub sensor: TemperatureSensor,
+
+    /// The heater.
+    pub relay: Relay,
+
+    /// The initial target temperature, in °C.
+    pub setpoint: f64,
+
+    /// The setpoints allowed through the API, as the minimum then the
+    /// maximum.
+    #[serde(
+        default = "ThermostatConfig::default_setpoint_range",
+        deserialize_with = "ThermostatConfig::deserialize_setpoint_range"
+    )]
+    pub setpoint_range: [f64; 2],
+
+    /// How far below the setpoint the temperature must drop for heating to
+    /// start, which cannot be negative. Heating stops once the setpoint is
+    /// reached.
+    #[serde(
+        default = "ThermostatConfig::default_hysteresis",
+        deserialize_with = "ThermostatConfig::deserialize_hysteresis"
+    )]
+    pub hysteresis: f64,
+
+    /// The minimum time between two switches of the relay, to protect the
+    /// heater.
+    #[serde(default = "ThermostatConfig::default_min_cycle")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub min_cycle: Duration,
+
+    /// The interval between two readings of the temperature.
+    #[serde(default = "ThermostatConfig::default_poll_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub poll_interval: Duration,
+
+    /// What to do with the heater when the connection to Home Assistant is
+    /// lost for too long. Regulation goes on locally by default.
+    #[serde(default)]
+    pub on_disconnect: DisconnectPolicy,
+}
+
+impl ThermostatConfig {
+    fn default_setpoint_range() -> [f64; 2] {
+        [5.0, 35.0]
+    }
+
+    fn default_hysteresis() -> f64 {
+        0.5
+    }
+
+    fn deserialize_setpoint_range<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<[f64; 2], D::Error> {
+        let [min, max] = <[f64; 2]>::deserialize(deserializer)?;
+
+        // The setpoints could not be clamped to NaN either.
+        if min.is_nan() || max.is_nan() || min > max {
+            return Err(D::Error::custom(format!(
+                "the setpoint range [{}, {}] must not be inverted",
+                min, max
+            )));
+        }
+
+        Ok([min, max])
+    }
+
+    fn deserialize_hysteresis<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        let hysteresis = f64::deserialize(deserializer)?;
+
+        if hysteresis.is_nan() || hysteresis < 0.0 {
+            return Err(D::Error::custom(format!(
+                "the hysteresis must not be negative, not {}",
+                hysteresis
+            )));
+        }
+
+        Ok(hysteresis)
+    }
+
+    fn default_min_cycle() -> Duration {
+        Duration::from_secs(5 * 60)
+    }
+
+    fn default_poll_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemperatureSensor {
+    /// A Home Assistant sensor, in °C.
+    Entity(String),
+
+    /// A DS18B20 1-Wire sensor, by id, like `28-3c01d607d4ab`.
+    Ds18b20(String),
+}
+
+impl TemperatureSensor {
+    async fn read(&self, context: &AppContext) -> anyhow::Result<f64> {
+        match self {
+            TemperatureSensor::Entity(entity_id) => context
+                .home_assistant
+                .entity(entity_id)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("`{}` is unknown", entity_id))?
+                .state
+                .parse()
+                .with_context(|| format!("`{}` has no temperature", entity_id)),
+            TemperatureSensor::Ds18b20(id) => {
+                let path = Path::new("/sys/bus/w1/devices").join(id).join("w1_slave");
+                let content = tokio::fs::read_to_string(&path)
+                    .await
+                    .with_context(|| format!("failed to read `{}`", path.display()))?;
+
+                parse_w1_slave(&content)
+                    .ok_or_else(|| anyhow::anyhow!("invalid reading from `{}`", id))
+            }
+        }
+    }
+}
+
+/// Parse the output of the 1-Wire driver, like:
+///
+/// ```text
+/// 72 01 4b 46 7f ff 0e 10 57 : crc=57 YES
+/// 72 01 4b 46 7f ff 0e 10 57 t=23125
+/// ```
+fn parse_w1_slave(content: &str) -> Option<f64> {
+    let mut lines = content.lines();
+
+    if !lines.next()?.trim_end().ends_with("YES") {
+        return None;
+    }
+
+    let (_, millidegrees) = lines.next()?.split_once("t=")?;
+
+    Some(millidegrees.trim().parse::<f64>().ok()? / 1000.0)
+}
+
+/// What a thermostat knows when deciding to switch its heater.
+#[derive(Debug, Clone, Copy)]
+struct Inputs {
+    /// The last reading, if it succeeded.
+    temperature: Option<f64>,
+
+    /// The number of consecutive failed readings.
+    failures: u32,
+    setpoint: f64,
+    heating: bool,
+
+    /// The status the disconnect policy forces, if Home Assistant has been
+    /// disconnected for too long.
+    failsafe: Option<bool>,
+
+    /// The time since the relay was last switched, if ever.
+    since_last_switch: Option<Duration>,
+}
+
+/// Decide what to switch the heater to, if anything.
+///
+/// Heating starts below the setpoint minus the hysteresis, and stops at the
+/// setpoint, once the minimum cycle elapsed since the last switch. Turning
+/// off for safety, when forced by the disconnect policy or after too many
+/// failed readings, does not wait for the cycle to elapse.
+fn decide(config: &ThermostatConfig, inputs: Inputs) -> Option<bool> {
+    let target = match (inputs.failsafe, inputs.temperature) {
+        (Some(status), _) => status,
+        (None, Some(temperature)) if temperature < inputs.setpoint - config.hysteresis => true,
+        (None, Some(temperature)) if temperature >= inputs.setpoint => false,
+        (None, Some(_)) => inputs.heating,
+        // Heating blindly is unsafe.
+        (None, None) if inputs.failures >= FAILURE_THRESHOLD => false,
+        (None, None) => inputs.heating,
+    };
+    let cycle_elapsed = inputs
+        .since_last_switch
+        .is_none_or(|elapsed| elapsed >= config.min_cycle);
+    let forced_off = !target && (inputs.failsafe.is_some() || inputs.temperature.is_none());
+
+    (target != inputs.heating && (cycle_elapsed || forced_off)).then_some(target)
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ThermostatStatus {
+    pub name: String,
+    pub setpoint: f64,
+    pub temperature: Option<f64>,
+    pub heating: bool,
+    pub last_switch: Option<DateTime<Utc>>,
+}
+
+struct Thermostat {
+    config: ThermostatConfig,
+    status: RwLock<ThermostatStatus>,
+}
+
+/// Drives the heaters of the local thermostats.
+pub struct Thermostats {
+    thermostats: Vec<Thermostat>,
+}
+
+impl Thermostats {
+    pub fn new(configs: Vec<ThermostatConfig>) -> Self {
+        Self {
+            thermostats: configs
+                .into_iter()
+                .map(|config| {
+                    let status = ThermostatStatus {
+                        name: config.name.clone(),
+                        setpoint: config.setpoint,
+                        temperature: None,
+                        heating: false,
+                        last_switch: None,
+                    };
+
+                    Thermostat {
+                        config,
+                        status: RwLock::new(status),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub async fn statuses(&self) -> Vec<ThermostatStatus> {
+        let mut statuses = Vec::with_capacity(self.thermostats.len());
+
+        for thermostat in &self.thermostats {
+            statuses.push(thermostat.status.read().await.clone());
+        }
+
+        statuses
+    }
+
+    pub async fn status(&self, name: &str) -> Option<ThermostatStatus> {
+        Some(self.thermostat(name)?.status.read().await.clone())
+    }
+
+    /// Set the target temperature of a thermostat, clamped to its allowed
+    /// range.
+    pub async fn set_setpoint(&self, name: &str, setpoint: f64) -> Option<ThermostatStatus> {
+        let thermostat = self.thermostat(name)?;
+        let [min, max] = thermostat.config.setpoint_range;
+        let mut status = thermostat.status.write().await;
+
+        status.setpoint = setpoint.clamp(min, max);
+
+        info!(
+            "Setting the setpoint of `{}` to {}°C.",
+            name, status.setpoint
+        );
+
+        Some(status.clone())
+    }
+
+    /// Run the thermostats forever.
+    pub async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        if self.thermostats.is_empty() {
+            return tasks::idle().await;
+        }
+
+        try_join_all(
+            self.thermostats
+                .iter()
+                .map(|thermostat| thermostat.run(context)),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    fn thermostat(&self, name: &str) -> Option<&Thermostat> {
+        self.thermostats
+            .iter()
+            .find(|thermostat| thermostat.config.name == name)
+    }
+}
+
+impl Thermostat {
+    async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        let mut failures = 0;
+        let mut last_switch: Option<Instant> = None;
+
+        // The state of the relay is unknown at startup.
+        if let Err(err) = self.config.relay.set(context, false).await {
+            warn!("Failed to turn off `{}`: {}", self.config.name, err);
+        }
+
+        loop {
+            let temperature = match self.config.sensor.read(context).await {
+                Ok(temperature) => {
+                    failures = 0;
+
+                    Some(temperature)
+                }
+                Err(err) => {
+                    failures += 1;
+
+                    if failures == 1 {
+                        warn!(
+                            "Failed to read the temperature of `{}`: {}",
+                            self.config.name, err
+                        );
+                    }
+
+                    None
+                }
+            };
+
+            let (setpoint, heating) = {
+                let mut status = self.status.write().await;
+
+                status.temperature = temperature;
+
+                (status.setpoint, status.heating)
+            };
+
+            let failsafe = context
+                .home_assistant
+                .disconnected_for()
+                .filter(|elapsed| *elapsed >= context.config.disconnect_timeout)
+                .and(self.config.on_disconnect.status());
+            let inputs = Inputs {
+                temperature,
+                failures,
+                setpoint,
+                heating,
+                failsafe,
+                since_last_switch: last_switch.map(|at| at.elapsed()),
+            };
+
+            if let Some(target) = decide(&self.config, inputs) {
+                info!(
+                    "Turning `{}` {} at {}°C for a setpoint of {}°C.",
+                    self.config.name,
+                    if target { "on" } else { "off" },
+                    temperature.map_or("?".to_string(), |temperature| temperature.to_string()),
+                    setpoint
+                );
+
+                match self.config.relay.set(context, target).await {
+                    Ok(()) => {
+                        last_switch = Some(Instant::now());
+
+                        let mut status = self.status.write().await;
+
+                        status.heating = target;
+                        status.last_switch = Some(Utc::now());
+                    }
+                    Err(err) => warn!("Failed to switch `{}`: {}", self.config.name, err),
+                }
+            }
+
+            tokio::time::sleep(self.config.poll_interval).await;
+            tasks::heartbeat();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
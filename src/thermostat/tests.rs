@@ -0,0 +1,164 @@
+//! Tests of the thermostat regulation.
+
+use serde_json::json;
+
+use super::*;
+
+fn config() -> ThermostatConfig {
+    serde_json::from_value(json!({
+        "name": "pool",
+        "sensor": {"entity": "sensor.pool_temperature"},
+        "relay": {"pin": 17},
+        "setpoint": 28.0,
+        "hysteresis": 0.5,
+        "min_cycle": 300,
+    }))
+    .unwrap()
+}
+
+/// The inputs at a temperature, while idle since long enough.
+fn inputs(temperature: f64) -> Inputs {
+    Inputs {
+        temperature: Some(temperature),
+        failures: 0,
+        setpoint: 28.0,
+        heating: false,
+        failsafe: None,
+        since_last_switch: Some(Duration::from_secs(600)),
+    }
+}
+
+#[test]
+fn heating_starts_below_the_hysteresis() {
+    assert_eq!(decide(&config(), inputs(27.4)), Some(true));
+    assert_eq!(decide(&config(), inputs(27.5)), None);
+    assert_eq!(
+        decide(
+            &config(),
+            Inputs {
+                since_last_switch: None,
+                ..inputs(20.0)
+            }
+        ),
+        Some(true)
+    );
+}
+
+#[test]
+fn heating_stops_at_the_setpoint() {
+    let heating = |temperature| Inputs {
+        heating: true,
+        ..inputs(temperature)
+    };
+
+    assert_eq!(decide(&config(), heating(28.0)), Some(false));
+    assert_eq!(decide(&config(), heating(27.9)), None);
+}
+
+#[test]
+fn the_relay_holds_until_the_minimum_cycle_elapsed() {
+    let recent = |temperature| Inputs {
+        since_last_switch: Some(Duration::from_secs(299)),
+        ..inputs(temperature)
+    };
+
+    assert_eq!(decide(&config(), recent(20.0)), None);
+    assert_eq!(
+        decide(
+            &config(),
+            Inputs {
+                heating: true,
+                ..recent(30.0)
+            }
+        ),
+        None
+    );
+}
+
+#[test]
+fn failed_readings_hold_the_relay_until_the_threshold() {
+    let failing = |failures| Inputs {
+        temperature: None,
+        failures,
+        heating: true,
+        ..inputs(0.0)
+    };
+
+    assert_eq!(decide(&config(), failing(FAILURE_THRESHOLD - 1)), None);
+    assert_eq!(decide(&config(), failing(FAILURE_THRESHOLD)), Some(false));
+}
+
+#[test]
+fn turning_off_for_safety_does_not_wait_for_the_cycle() {
+    let recent = Inputs {
+        heating: true,
+        since_last_switch: Some(Duration::ZERO),
+        ..inputs(20.0)
+    };
+
+    assert_eq!(
+        decide(
+            &config(),
+            Inputs {
+                failsafe: Some(false),
+                ..recent
+            }
+        ),
+        Some(false)
+    );
+    assert_eq!(
+        decide(
+            &config(),
+            Inputs {
+                temperature: None,
+                failures: FAILURE_THRESHOLD,
+                ..recent
+            }
+        ),
+        Some(false)
+    );
+    assert_eq!(
+        decide(
+            &config(),
+            Inputs {
+                failsafe: Some(true),
+                heating: false,
+                ..recent
+            }
+        ),
+        None
+    );
+}
+
+#[test]
+fn inverted_setpoint_ranges_are_rejected() {
+    let config = |setpoint_range| {
+        serde_json::from_value::<ThermostatConfig>(json!({
+            "name": "pool",
+            "sensor": {"entity": "sensor.pool_temperature"},
+            "relay": {"pin": 17},
+            "setpoint": 28.0,
+            "setpoint_range": setpoint_range,
+        }))
+    };
+
+    assert!(config(json!([5.0, 35.0])).is_ok());
+    assert!(config(json!([28.0, 28.0])).is_ok());
+    assert!(config(json!([35.0, 5.0])).is_err());
+}
+
+#[test]
+fn negative_hysteresis_is_rejected() {
+    let config = |hysteresis| {
+        serde_json::from_value::<ThermostatConfig>(json!({
+            "name": "pool",
+            "sensor": {"entity": "sensor.pool_temperature"},
+            "relay": {"pin": 17},
+            "setpoint": 28.0,
+            "hysteresis": hysteresis,
+        }))
+    };
+
+    assert!(config(0.0).is_ok());
+    assert!(config(-0.5).is_err());
+}
@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::RwLock;
+
+use crate::{shutdown::ShutdownController, tasks};
+
+#[cfg(feature = "gpio")]
+use rppal::i2c::I2c;
+
+/// The UPS HAT configuration, for HATs built around an INA219 power monitor.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpsConfig {
+    /// The I2C bus the INA219 is connected to.
+    #[serde(default = "UpsConfig::default_bus")]
+    pub bus: u8,
+
+    /// The I2C address of the INA219.
+    #[serde(default = "UpsConfig::default_address")]
+    pub address: u16,
+
+    /// The resistance of the shunt, in ohms.
+    #[serde(default = "UpsConfig::default_shunt_ohms")]
+    pub shunt_ohms: f64,
+
+    /// The battery voltage considered empty.
+    #[serde(default = "UpsConfig::default_empty_voltage")]
+    pub empty_voltage: f64,
+
+    /// The battery voltage considered full.
+    #[serde(default = "UpsConfig::default_full_voltage")]
+    pub full_voltage: f64,
+
+    /// The battery percentage under which the system is shut down while on
+    /// battery.
+    #[serde(default = "UpsConfig::default_critical_percentage")]
+    pub critical_percentage: f64,
+
+    /// The interval between readings.
+    #[serde(default = "UpsConfig::default_poll_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub poll_interval: Duration,
+}
+
+impl UpsConfig {
+    fn default_bus() -> u8 {
+        1
+    }
+
+    fn default_address() -> u16 {
+        0x42
+    }
+
+    fn default_shunt_ohms() -> f64 {
+        0.1
+    }
+
+    fn default_empty_voltage() -> f64 {
+        3.0
+    }
+
+    fn default_full_voltage() -> f64 {
+        4.2
+    }
+
+    fn default_critical_percentage() -> f64 {
+        5.0
+    }
+
+    fn default_poll_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpsStatus {
+    pub voltage: f64,
+    pub current_ma: f64,
+    pub percentage: f64,
+
+    /// Whether the battery is discharging.
+    pub on_battery: bool,
+    pub critical: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+pub struct Ups {
+    config: UpsConfig,
+    status: RwLock<Option<UpsStatus>>,
+}
+
+impl Ups {
+    pub fn new(config: UpsConfig) -> Self {
+        Self {
+            config,
+            status: RwLock::new(None),
+        }
+    }
+
+    /// Get the last UPS reading, if any.
+    pub async fn status(&self) -> Option<UpsStatus> {
+        self.status.read().await.clone()
+    }
+
+    /// Poll the UPS forever, and shut the system down once the battery is
+    /// critical.
+    ///
+    /// The failures are only logged, and retried on the next poll.
+    pub async fn run(&self, shutdown_controller: &ShutdownController) -> anyhow::Result<()> {
+        info!(
+            "Monitoring the UPS at address {:#04x} on I2C bus {}.",
+            self.config.address, self.config.bus
+        );
+
+        loop {
+            match self.read().await {
+                Ok(status) => {
+                    *self.status.write().await = Some(status.clone());
+
+                    if status.critical {
+                        let reason = format!(
+                            "the UPS battery is critical ({:.0}%, {:.2}V)",
+                            status.percentage, status.voltage
+                        );
+
+                        if let Err(err) = shutdown_controller.shutdown(&reason).await {
+                            warn!("Failed to shut the system down: {}", err);
+                        }
+                    }
+                }
+                Err(err) => {
+                    warn!("Failed to read the UPS status: {}", err);
+
+                    *self.status.write().await = None;
+                }
+            }
+
+            tokio::time::sleep(self.config.poll_interval).await;
+            tasks::heartbeat();
+        }
+    }
+
+    async fn read(&self) -> anyhow::Result<UpsStatus> {
+        let config = self.config.clone();
+        let (voltage, shunt_voltage) =
+            tokio::task::spawn_blocking(move || read_ina219(&config)).await??;
+
+        let current_ma = shunt_voltage / self.config.shunt_ohms * 1000.0;
+        let percentage = ((voltage - self.config.empty_voltage)
+            / (self.config.full_voltage - self.config.empty_voltage)
+            * 100.0)
+            .clamp(0.0, 100.0);
+        let on_battery = current_ma < 0.0;
+
+        Ok(UpsStatus {
+            voltage,
+            current_ma,
+            percentage,
+            on_battery,
+            critical: on_battery && percentage <= self.config.critical_percentage,
+            timestamp: Utc::now(),
+        })
+    }
+}
+
+/// Read the bus and shunt voltages of an INA219, in volts.
+#[cfg(feature = "gpio")]
+fn read_ina219(config: &UpsConfig) -> anyhow::Result<(f64, f64)> {
+    const REGISTER_SHUNT_VOLTAGE: u8 = 0x01;
+    const REGISTER_BUS_VOLTAGE: u8 = 0x02;
+
+    let mut i2c = I2c::with_bus(config.bus)?;
+    i2c.set_slave_address(config.address)?;
+
+    // Registers are big-endian.
+    let read_register = |register: u8| -> anyhow::Result<u16> {
+        let mut buffer = [0u8; 2];
+        i2c.write_read(&[register], &mut buffer)?;
+
+        Ok(u16::from_be_bytes(buffer))
+    };
+
+    // The bus voltage is in bits 15..3, with a 4 mV LSB.
+    let bus_voltage = f64::from(read_register(REGISTER_BUS_VOLTAGE)? >> 3) * 0.004;
+
+    // The shunt voltage is signed, with a 10 µV LSB.
+    let shunt_voltage = f64::from(read_register(REGISTER_SHUNT_VOLTAGE)? as i16) * 0.00001;
+
+    Ok((bus_voltage, shunt_voltage))
+}
+
+#[cfg(not(feature = "gpio"))]
+fn read_ina219(_config: &UpsConfig) -> anyhow::Result<(f64, f64)> {
+    Err(anyhow::anyhow!("UPS support requires the `gpio` feature"))
+}
@@ -0,0 +1,213 @@
+//! The entities controlled through the panels, for the dashboards to put the
+//! ones the household actually uses first.
+//!
+//! The uses are counted per panel, by address, and for the whole household,
+//! and weigh less as they age.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::Deserialize;
+
+use crate::{client_ip, files::write_atomic};
+
+/// The number of latest uses kept by entity.
+const MAX_USES: usize = 50;
+
+/// The interval at which the uses are saved, when some were recorded.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// The key of the uses of the whole household.
+const HOUSEHOLD: &str = "household";
+
+static USAGE: Mutex<Usage> = Mutex::new(Usage {
+    uses: None,
+    changed: false,
+});
+
+/// The tracking of the entities used through the panels.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UsageConfig {
+    /// The file to keep the uses in across restarts, if any.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+
+    /// The age in days at which a use weighs half.
+    #[serde(default = "UsageConfig::default_half_life_days")]
+    pub half_life_days: f64,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            half_life_days: Self::default_half_life_days(),
+        }
+    }
+}
+
+impl UsageConfig {
+    fn default_half_life_days() -> f64 {
+        14.0
+    }
+}
+
+/// The latest uses of the entities, by panel then by entity.
+type Uses = HashMap<String, HashMap<String, Vec<DateTime<Utc>>>>;
+
+#[derive(Debug)]
+struct Usage {
+    uses: Option<Uses>,
+    changed: bool,
+}
+
+/// Record the use of entities, if it is made through the API.
+pub fn record<'a>(entity_ids: impl IntoIterator<Item = &'a str>) {
+    let panel = match client_ip::current() {
+        Some(client_ip) => client_ip.to_string(),
+        None => return,
+    };
+    let now = Utc::now();
+    let mut usage = USAGE.lock().unwrap();
+    let usage = &mut *usage;
+    let uses = usage.uses.get_or_insert_with(Default::default);
+
+    for entity_id in entity_ids {
+        for key in [panel.as_str(), HOUSEHOLD] {
+            let entity_uses = uses
+                .entry(key.to_string())
+                .or_default()
+                .entry(entity_id.to_string())
+                .or_default();
+
+            entity_uses.push(now);
+
+            if entity_uses.len() > MAX_USES {
+                entity_uses.remove(0);
+            }
+        }
+
+        usage.changed = true;
+    }
+}
+
+/// Get the most used entities of a panel, by address, the most used first,
+/// or of the whole household if it has none.
+pub fn most_used(
+    panel: Option<&str>,
+    config: &UsageConfig,
+    now: DateTime<Utc>,
+) -> Vec<(String, f64)> {
+    let usage = USAGE.lock().unwrap();
+    let uses = match &usage.uses {
+        Some(uses) => uses,
+        None => return Vec::new(),
+    };
+    let entity_uses = panel
+        .and_then(|panel| uses.get(panel))
+        .filter(|entity_uses| !entity_uses.is_empty())
+        .or_else(|| uses.get(HOUSEHOLD));
+
+    entity_uses
+        .map(|entity_uses| rank(entity_uses, config.half_life_days, now))
+        .unwrap_or_default()
+}
+
+/// Rank the entities by their uses, each weighing half every half-life.
+fn rank(
+    entity_uses: &HashMap<String, Vec<DateTime<Utc>>>,
+    half_life_days: f64,
+    now: DateTime<Utc>,
+) -> Vec<(String, f64)> {
+    let half_life = half_life_days.max(f64::EPSILON) * 24.0 * 60.0 * 60.0;
+    let mut ranked: Vec<(String, f64)> = entity_uses
+        .iter()
+        .map(|(entity_id, uses)| {
+            let score = uses
+                .iter()
+                .map(|used_at| {
+                    let age = (now - *used_at).num_milliseconds().max(0) as f64 / 1000.0;
+
+                    0.5_f64.powf(age / half_life)
+                })
+                .sum();
+
+            (entity_id.clone(), score)
+        })
+        .collect();
+
+    ranked.sort_by(|(a_id, a), (b_id, b)| b.total_cmp(a).then_with(|| a_id.cmp(b_id)));
+    ranked
+}
+
+/// Load the uses kept across restarts, then save them periodically.
+pub async fn run(config: &UsageConfig) -> anyhow::Result<()> {
+    let path = match &config.path {
+        Some(path) => path,
+        None => return crate::tasks::idle().await,
+    };
+
+    match load(path) {
+        Ok(loaded) => {
+            let mut usage = USAGE.lock().unwrap();
+            let uses = usage.uses.get_or_insert_with(Default::default);
+
+            for (panel, entity_uses) in loaded {
+                let panel_uses = uses.entry(panel).or_default();
+
+                for (entity_id, mut loaded_uses) in entity_uses {
+                    let entity_uses = panel_uses.entry(entity_id).or_default();
+
+                    loaded_uses.append(entity_uses);
+                    *entity_uses = loaded_uses;
+                    entity_uses.drain(..entity_uses.len().saturating_sub(MAX_USES));
+                }
+            }
+        }
+        Err(err) => warn!("Failed to load the uses of the entities: {:#}", err),
+    }
+
+    let mut interval = tokio::time::interval(SAVE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let uses = {
+            let mut usage = USAGE.lock().unwrap();
+
+            if !usage.changed {
+                continue;
+            }
+
+            usage.changed = false;
+            usage.uses.clone().unwrap_or_default()
+        };
+
+        if let Err(err) = save(path, &uses) {
+            warn!("Failed to save the uses of the entities: {:#}", err);
+        }
+    }
+}
+
+fn load(path: &Path) -> anyhow::Result<Uses> {
+    match std::fs::read(path) {
+        Ok(content) => serde_json::from_slice(&content)
+            .with_context(|| format!("invalid uses in `{}`", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Uses::default()),
+        Err(err) => Err(err).with_context(|| format!("failed to read `{}`", path.display())),
+    }
+}
+
+fn save(path: &Path, uses: &Uses) -> anyhow::Result<()> {
+    write_atomic(path, serde_json::to_vec(uses)?)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,60 @@
+//! Tests of the ranking of the entities by their uses.
+
+use super::*;
+
+fn at(days_ago: i64) -> DateTime<Utc> {
+    "2026-10-16T12:00:00Z".parse::<DateTime<Utc>>().unwrap() - chrono::Duration::days(days_ago)
+}
+
+#[test]
+fn rank_weighs_the_recent_uses_more() {
+    let entity_uses = HashMap::from([
+        // Used a lot, but a while ago.
+        (
+            "light.attic".to_string(),
+            vec![at(60), at(60), at(60), at(60)],
+        ),
+        ("light.kitchen".to_string(), vec![at(1), at(0)]),
+        ("light.hallway".to_string(), vec![at(0)]),
+    ]);
+
+    let ranked: Vec<String> = rank(&entity_uses, 14.0, at(0))
+        .into_iter()
+        .map(|(entity_id, _)| entity_id)
+        .collect();
+
+    assert_eq!(ranked, ["light.kitchen", "light.hallway", "light.attic"]);
+
+    // A use weighs half after a half-life.
+    let entity_uses = HashMap::from([("light.hallway".to_string(), vec![at(14)])]);
+
+    assert_eq!(
+        rank(&entity_uses, 14.0, at(0)),
+        [("light.hallway".to_string(), 0.5)]
+    );
+}
+
+#[tokio::test]
+async fn most_used_falls_back_to_the_household() {
+    let config = UsageConfig::default();
+
+    // Only the uses through the API are recorded.
+    record(["switch.ignored"]);
+
+    client_ip::scope("192.0.2.1".parse().unwrap(), async {
+        record(["switch.coffee_machine", "switch.coffee_machine"]);
+    })
+    .await;
+
+    let panel = most_used(Some("192.0.2.1"), &config, Utc::now());
+    let other_panel = most_used(Some("192.0.2.2"), &config, Utc::now());
+
+    assert_eq!(panel.len(), 1);
+    assert_eq!(panel[0].0, "switch.coffee_machine");
+    assert!(other_panel
+        .iter()
+        .any(|(entity_id, _)| entity_id == "switch.coffee_machine"));
+    assert!(!other_panel
+        .iter()
+        .any(|(entity_id, _)| entity_id == "switch.ignored"));
+}
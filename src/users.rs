@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::home_assistant::State;
+
+/// A user of the panel.
+///
+/// RFID tags identify users through the `user` field of the tag, which must
+/// match the user id.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserConfig {
+    /// The unique id of the user.
+    pub id: String,
+
+    /// The name of the user, as displayed in the UI.
+    pub name: String,
+
+    /// The PIN of the user, if any.
+    #[serde(default)]
+    pub pin: Option<String>,
+
+    /// The favorite entities of the user, in display order.
+    #[serde(default)]
+    pub favorites: Vec<String>,
+
+    /// The entity domains the user may control, like `light`. All domains are
+    /// allowed if empty.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+}
+
+impl UserConfig {
+    /// Check whether the user may control the specified entity.
+    pub fn is_allowed(&self, entity_id: &str) -> bool {
+        self.allowed_domains.is_empty()
+            || entity_id
+                .split_once('.')
+                .map(|(domain, _)| self.allowed_domains.iter().any(|d| d == domain))
+                .unwrap_or_default()
+    }
+
+    /// Get the favorite entities of the user that they may control.
+    pub fn favorites(&self, entities: &HashMap<String, State>) -> Vec<Favorite> {
+        self.favorites
+            .iter()
+            .filter(|entity_id| self.is_allowed(entity_id))
+            .map(|entity_id| Favorite::new(entity_id, entities))
+            .collect()
+    }
+}
+
+/// The public view of a user, without its secrets.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub id: String,
+    pub name: String,
+    pub has_pin: bool,
+}
+
+impl From<&UserConfig> for User {
+    fn from(config: &UserConfig) -> Self {
+        Self {
+            id: config.id.clone(),
+            name: config.name.clone(),
+            has_pin: config.pin.is_some(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Favorite {
+    pub entity_id: String,
+    pub friendly_name: Option<String>,
+    pub state: Option<String>,
+
+    /// Whether the entity exists and is neither `unavailable` nor `unknown`.
+    pub available: bool,
+}
+
+impl Favorite {
+    pub fn new(entity_id: &str, entities: &HashMap<String, State>) -> Self {
+        let state = entities.get(entity_id);
+
+        Self {
+            entity_id: entity_id.to_string(),
+            friendly_name: state.and_then(|state| state.attributes.get("friendly_name")),
+            state: state
+                .filter(|state| state.is_available())
+                .map(|state| state.state.clone()),
+            available: state.map(State::is_available).unwrap_or_default(),
+        }
+    }
+}
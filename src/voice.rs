@@ -0,0 +1,287 @@
+use std::{process::Stdio, time::Duration};
+
+use anyhow::Context;
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+    sync::{broadcast, mpsc},
+    time::sleep,
+};
+
+use crate::{context::AppContext, tasks};
+
+/// The local wake word integration, which opens an Assist session when the
+/// wake word is heard.
+///
+/// Both the wake word engine and the transcription run as commands, so that
+/// any engine can be plugged in with a small wrapper script.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct VoiceConfig {
+    /// The wake word engine, which runs continuously and prints a line every
+    /// time it hears the wake word.
+    pub wake_word: VoiceCommand,
+
+    /// The transcription, which starts after the wake word, prints the
+    /// transcript so far on a new line as it goes, and exits once the user
+    /// stops talking. Its last line is the final transcript.
+    pub transcribe: VoiceCommand,
+
+    /// The language of the conversation, like `en`, if not the default one
+    /// of Home-Assistant.
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// For how long the transcription may run at most.
+    #[serde(default = "VoiceConfig::default_transcribe_timeout")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub transcribe_timeout: Duration,
+
+    /// The delay before restarting a wake word engine that exited.
+    #[serde(default = "VoiceConfig::default_restart_delay")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub restart_delay: Duration,
+}
+
+impl VoiceConfig {
+    fn default_transcribe_timeout() -> Duration {
+        Duration::from_secs(15)
+    }
+
+    fn default_restart_delay() -> Duration {
+        Duration::from_secs(5)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VoiceCommand {
+    pub program: String,
+
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl VoiceCommand {
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+
+        command
+            .args(&self.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true);
+
+        command
+    }
+}
+
+/// The progress of an Assist session, as pushed to the frontend.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum VoiceEvent {
+    /// The wake word was heard: the transcription starts.
+    Wake,
+
+    /// The transcript so far.
+    Transcript {
+        text: String,
+
+        /// Whether the user stopped talking.
+        #[serde(rename = "final")]
+        is_final: bool,
+    },
+
+    /// What Assist says back.
+    Response { text: String },
+
+    /// The session failed, or nothing was heard.
+    Error { message: String },
+}
+
+pub struct Voice {
+    config: VoiceConfig,
+    events: broadcast::Sender<VoiceEvent>,
+}
+
+impl Voice {
+    pub fn new(config: VoiceConfig) -> Self {
+        Self {
+            config,
+            events: broadcast::channel(16).0,
+        }
+    }
+
+    /// Subscribe to the progress of the sessions.
+    pub fn subscribe(&self) -> broadcast::Receiver<VoiceEvent> {
+        self.events.subscribe()
+    }
+
+    fn send(&self, event: VoiceEvent) {
+        // Nobody may be listening.
+        let _ = self.events.send(event);
+    }
+
+    /// Listen for the wake word forever.
+    pub async fn run(&self, context: &AppContext) -> anyhow::Result<()> {
+        // Only one session at a time: the wake words heard meanwhile are
+        // dropped.
+        let (wakes_tx, mut wakes_rx) = mpsc::channel(1);
+
+        let sessions = async {
+            while wakes_rx.recv().await.is_some() {
+                self.session(context).await;
+
+                while wakes_rx.try_recv().is_ok() {}
+            }
+        };
+
+        let engine = async {
+            loop {
+                if let Err(err) = self.listen(&wakes_tx).await {
+                    warn!("The wake word engine failed: {:#}", err);
+                }
+
+                sleep(self.config.restart_delay).await;
+            }
+        };
+
+        tokio::select! {
+            _ = sessions => Ok(()),
+            _ = engine => Ok(()),
+        }
+    }
+
+    /// Run the wake word engine until it exits.
+    async fn listen(&self, wakes: &mpsc::Sender<()>) -> anyhow::Result<()> {
+        let mut child = self
+            .config
+            .wake_word
+            .command()
+            .spawn()
+            .context("failed to run the wake word engine")?;
+        let stdout = child
+            .stdout
+            .take()
+            .context("failed to read from the wake word engine")?;
+        let mut lines = BufReader::new(stdout).lines();
+
+        info!("Listening for the wake word.");
+
+        while let Some(line) = lines.next_line().await? {
+            tasks::heartbeat();
+
+            if !line.trim().is_empty() {
+                let _ = wakes.try_send(());
+            }
+        }
+
+        let status = child.wait().await?;
+
+        anyhow::bail!("the wake word engine exited with {}", status)
+    }
+
+    /// Transcribe what the user says, and pass it to Assist.
+    async fn session(&self, context: &AppContext) {
+        info!("Wake word heard.");
+
+        if let Err(err) = context.screen.force_on(true) {
+            warn!("Failed to turn the screen on: {}", err);
+        }
+
+        self.send(VoiceEvent::Wake);
+
+        let transcript =
+            match tokio::time::timeout(self.config.transcribe_timeout, self.transcribe()).await {
+                Ok(Ok(transcript)) => transcript,
+                Ok(Err(err)) => {
+                    warn!("Failed to transcribe: {:#}", err);
+                    self.send(VoiceEvent::Error {
+                        message: "The transcription failed.".to_string(),
+                    });
+
+                    return;
+                }
+                Err(_) => {
+                    warn!("The transcription timed out.");
+                    self.send(VoiceEvent::Error {
+                        message: "The transcription timed out.".to_string(),
+                    });
+
+                    return;
+                }
+            };
+
+        if transcript.is_empty() {
+            self.send(VoiceEvent::Error {
+                message: "Nothing was heard.".to_string(),
+            });
+
+            return;
+        }
+
+        self.send(VoiceEvent::Transcript {
+            text: transcript.clone(),
+            is_final: true,
+        });
+
+        match context
+            .home_assistant
+            .conversation_process(&transcript, self.config.language.as_deref(), None)
+            .await
+        {
+            Ok(response) => {
+                let text = response.speech.unwrap_or_default();
+
+                info!("Assist: `{}` -> `{}`", transcript, text);
+                self.send(VoiceEvent::Response { text });
+            }
+            Err(err) => {
+                warn!("Failed to process `{}`: {}", transcript, err);
+                self.send(VoiceEvent::Error {
+                    message: "Home-Assistant could not process the request.".to_string(),
+                });
+            }
+        }
+    }
+
+    /// Run the transcription, streaming the partial transcripts, and get the
+    /// final one.
+    async fn transcribe(&self) -> anyhow::Result<String> {
+        let mut child = self
+            .config
+            .transcribe
+            .command()
+            .spawn()
+            .context("failed to run the transcription")?;
+        let stdout = child
+            .stdout
+            .take()
+            .context("failed to read from the transcription")?;
+        let mut lines = BufReader::new(stdout).lines();
+        let mut transcript = String::new();
+
+        while let Some(line) = lines.next_line().await? {
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            transcript = line.to_string();
+            self.send(VoiceEvent::Transcript {
+                text: transcript.clone(),
+                is_final: false,
+            });
+        }
+
+        let status = child.wait().await?;
+
+        anyhow::ensure!(status.success(), "the transcription exited with {}", status);
+
+        Ok(transcript)
+    }
+}
@@ -0,0 +1,381 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_with::{serde_as, DurationSeconds};
+use tokio::sync::{watch, RwLock};
+
+use crate::{
+    context::AppContext,
+    history::{ActionResult, NewExecution},
+    holidays,
+    melody::{Melody, MelodyPlayer},
+    tasks,
+};
+
+/// The interval between two checks of the alarms.
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The wake-up light configuration.
+///
+/// Before each alarm, the lights ramp up from a dim warm glow to a bright
+/// cool light, like a sunrise, and the buzzer chimes at the alarm time.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct WakeupConfig {
+    /// The lights to ramp up, like `light.bedroom`.
+    pub lights: Vec<String>,
+
+    /// The alarms. They can be changed at runtime, and the changes are saved
+    /// with the configuration edited from the frontend.
+    #[serde(default)]
+    pub alarms: Vec<WakeupAlarm>,
+
+    /// How long before the alarms the lights start ramping up.
+    #[serde(default = "WakeupConfig::default_duration")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub duration: Duration,
+
+    /// The interval between two changes of the lights.
+    #[serde(default = "WakeupConfig::default_step_interval")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub step_interval: Duration,
+
+    /// The brightness the ramp starts from, in percent.
+    #[serde(default = "WakeupConfig::default_brightness_start")]
+    pub brightness_start: f64,
+
+    /// The brightness the ramp ends at, in percent.
+    #[serde(default = "WakeupConfig::default_brightness_end")]
+    pub brightness_end: f64,
+
+    /// The color temperature the ramp starts from, in kelvins.
+    #[serde(default = "WakeupConfig::default_color_temp_start")]
+    pub color_temp_start: u32,
+
+    /// The color temperature the ramp ends at, in kelvins.
+    #[serde(default = "WakeupConfig::default_color_temp_end")]
+    pub color_temp_end: u32,
+
+    /// Whether to chime the buzzer at the alarm time.
+    #[serde(default = "WakeupConfig::default_chime")]
+    pub chime: bool,
+}
+
+impl WakeupConfig {
+    fn default_duration() -> Duration {
+        Duration::from_secs(30 * 60)
+    }
+
+    fn default_step_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_brightness_start() -> f64 {
+        1.0
+    }
+
+    fn default_brightness_end() -> f64 {
+        100.0
+    }
+
+    fn default_color_temp_start() -> u32 {
+        2000
+    }
+
+    fn default_color_temp_end() -> u32 {
+        4000
+    }
+
+    fn default_chime() -> bool {
+        true
+    }
+}
+
+/// An alarm, repeated on some days of the week.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WakeupAlarm {
+    /// The time of day of the alarm, like `07:00`.
+    pub at: NaiveTime,
+
+    /// The days of the alarm, like `Mon`. Every day if empty.
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+
+    /// Whether the alarm is skipped on the holidays.
+    #[serde(default)]
+    pub skip_holidays: bool,
+}
+
+impl WakeupAlarm {
+    /// Check whether the alarm rings on a day of the week.
+    pub fn rings_on(&self, weekday: Weekday) -> bool {
+        self.weekdays.is_empty() || self.weekdays.contains(&weekday)
+    }
+}
+
+/// A wake-up in progress.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveWakeup {
+    pub started_at: DateTime<Utc>,
+    pub alarm_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WakeupStatus {
+    pub alarms: Vec<WakeupAlarm>,
+    pub active: Option<ActiveWakeup>,
+
+    /// When the next alarm rings, if any.
+    pub next_alarm: Option<DateTime<Utc>>,
+}
+
+/// Ramps the lights up before the alarms.
+pub struct Wakeup {
+    config: WakeupConfig,
+    alarms: RwLock<Vec<WakeupAlarm>>,
+    active: watch::Sender<Option<ActiveWakeup>>,
+    cancels: watch::Sender<u64>,
+}
+
+impl Wakeup {
+    pub fn new(config: WakeupConfig) -> Self {
+        Self {
+            alarms: RwLock::new(config.alarms.clone()),
+            config,
+            active: watch::channel(None).0,
+            cancels: watch::channel(0).0,
+        }
+    }
+
+    pub async fn status(&self) -> WakeupStatus {
+        let alarms = self.alarms.read().await.clone();
+        let now = Local::now().naive_local();
+        let next_alarm = (0..=7)
+            .filter_map(|days| now.date().checked_add_days(chrono::Days::new(days)))
+            .flat_map(|date| {
+                alarms
+                    .iter()
+                    .filter(move |alarm| alarm.rings_on(date.weekday()))
+                    .map(move |alarm| date.and_time(alarm.at))
+            })
+            .filter(|at| *at > now)
+            .min()
+            .and_then(|at| Local.from_local_datetime(&at).earliest())
+            .map(|at| at.with_timezone(&Utc));
+
+        WakeupStatus {
+            alarms,
+            active: self.active.borrow().clone(),
+            next_alarm,
+        }
+    }
+
+    /// Watch the wake-up in progress.
+    pub fn watch(&self) -> watch::Receiver<Option<ActiveWakeup>> {
+        self.active.subscribe()
+    }
+
+    pub async fn alarms(&self) -> Vec<WakeupAlarm> {
+        self.alarms.read().await.clone()
+    }
+
+    /// Replace the alarms.
+    pub async fn set_alarms(&self, alarms: Vec<WakeupAlarm>) {
+        *self.alarms.write().await = alarms;
+    }
+
+    /// Stop the wake-up in progress, leaving the lights as they are.
+    ///
+    /// Returns `false` if there was none.
+    pub fn cancel(&self) -> bool {
+        if self.active.borrow().is_none() {
+            return false;
+        }
+
+        info!("Cancelling the wake-up.");
+
+        self.cancels.send_modify(|cancels| *cancels += 1);
+
+        true
+    }
+
+    /// Wake up before the alarms forever, and record the wake-ups in the
+    /// history, if any.
+    pub async fn run(
+        &self,
+        context: &AppContext,
+        melody_player: &MelodyPlayer,
+    ) -> anyhow::Result<()> {
+        let mut last_check = Local::now().naive_local();
+
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            tasks::heartbeat();
+
+            let now = Local::now().naive_local();
+            let due = self.due_alarm(context, last_check, now).await;
+
+            last_check = now;
+
+            if let Some(alarm_at) = due {
+                let started_at = Utc::now();
+                let start = Instant::now();
+                let actions = self.wake_up(context, melody_player, alarm_at).await;
+
+                if let Some(history) = &context.storage.history {
+                    history
+                        .record(NewExecution {
+                            source: "wakeup".to_string(),
+                            trigger: format!("alarm {}", alarm_at.time()),
+                            started_at,
+                            duration: start.elapsed(),
+                            conditions: Vec::new(),
+                            actions,
+                        })
+                        .await;
+                }
+
+                // The checks resume after the ramp.
+                last_check = Local::now().naive_local();
+            }
+        }
+    }
+
+    /// Get the alarm whose ramp started between the checks, if any, unless
+    /// it is skipped on the holidays.
+    async fn due_alarm(
+        &self,
+        context: &AppContext,
+        last_check: NaiveDateTime,
+        now: NaiveDateTime,
+    ) -> Option<NaiveDateTime> {
+        let duration = chrono::Duration::from_std(self.config.duration).unwrap_or_default();
+        let (alarm_at, skip_holidays) = {
+            let alarms = self.alarms.read().await;
+
+            // The ramps of the alarms of the day after start the day before.
+            [last_check.date(), now.date(), now.date().succ_opt()?]
+                .into_iter()
+                .flat_map(|date| {
+                    alarms
+                        .iter()
+                        .filter(move |alarm| alarm.rings_on(date.weekday()))
+                        .map(move |alarm| (date.and_time(alarm.at), alarm.skip_holidays))
+                })
+                .find(|(alarm_at, _)| {
+                    let start = *alarm_at - duration;
+
+                    last_check < start && start <= now
+                })?
+        };
+
+        if skip_holidays && holidays::is_holiday(context, alarm_at.date()).await {
+            info!("Skipping the alarm of {} on a holiday.", alarm_at);
+
+            return None;
+        }
+
+        Some(alarm_at)
+    }
+
+    /// Ramp the lights up and chime, unless cancelled meanwhile.
+    async fn wake_up(
+        &self,
+        context: &AppContext,
+        melody_player: &MelodyPlayer,
+        alarm_at: NaiveDateTime,
+    ) -> Vec<ActionResult> {
+        let alarm_at = match Local.from_local_datetime(&alarm_at).earliest() {
+            Some(alarm_at) => alarm_at.with_timezone(&Utc),
+            None => return Vec::new(),
+        };
+        let mut cancels = self.cancels.subscribe();
+
+        info!(
+            "Waking up for the alarm of {}.",
+            alarm_at.with_timezone(&Local)
+        );
+
+        self.active.send_replace(Some(ActiveWakeup {
+            started_at: Utc::now(),
+            alarm_at,
+        }));
+
+        let completed = tokio::select! {
+            _ = self.ramp(context) => true,
+            _ = cancels.changed() => false,
+        };
+
+        let mut actions = vec![ActionResult {
+            action: json!({ "type": "ramp", "lights": self.config.lights }),
+            error: (!completed).then(|| "cancelled".to_string()),
+        }];
+
+        if completed && self.config.chime {
+            let error = match melody_player.play(Melody::CHIME).await {
+                Ok(()) => None,
+                Err(err) => {
+                    warn!("Failed to play the chime: {}", err);
+
+                    Some(err.to_string())
+                }
+            };
+
+            actions.push(ActionResult {
+                action: json!({ "type": "chime" }),
+                error,
+            });
+        }
+
+        self.active.send_replace(None);
+
+        actions
+    }
+
+    /// Ramp the lights up over the duration.
+    async fn ramp(&self, context: &AppContext) {
+        let config = &self.config;
+        let steps = (config.duration.as_secs_f64() / config.step_interval.as_secs_f64())
+            .ceil()
+            .max(1.0) as u32;
+
+        for step in 0..=steps {
+            let progress = f64::from(step) / f64::from(steps);
+            let brightness = config.brightness_start
+                + (config.brightness_end - config.brightness_start) * progress;
+            let color_temp = f64::from(config.color_temp_start)
+                + (f64::from(config.color_temp_end) - f64::from(config.color_temp_start))
+                    * progress;
+
+            if let Err(err) = context
+                .home_assistant
+                .call_service(
+                    "light",
+                    "turn_on",
+                    Some(&json!({
+                        "brightness_pct": brightness.round(),
+                        "color_temp_kelvin": color_temp.round(),
+                        "transition": config.step_interval.as_secs_f64(),
+                    })),
+                    Some(&json!({ "entity_id": config.lights })),
+                )
+                .await
+            {
+                // The next steps catch up.
+                warn!("Failed to ramp the lights up: {}", err);
+            }
+
+            if step < steps {
+                tokio::time::sleep(config.step_interval).await;
+                tasks::heartbeat();
+            }
+        }
+    }
+}
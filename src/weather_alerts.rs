@@ -0,0 +1,203 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    context::AppContext,
+    home_assistant::{self, State},
+    notifications::{Notifications, Severity},
+    outputs::{Output, OutputPattern},
+    red_led::RedLedOwner,
+    tasks,
+};
+
+/// The blinking period of the red LED while a severe alert is active.
+const FLASH_PERIOD: Duration = Duration::from_secs(1);
+
+/// The weather alerts configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WeatherAlertsConfig {
+    /// The entities reporting the alerts, like Meteoalarm or NWS sensors.
+    ///
+    /// The alerts are read from an `alerts` attribute holding a list, or
+    /// else from the attributes of the entity while it is on.
+    pub entities: Vec<String>,
+
+    /// The severities of the alerts that are severe, compared
+    /// case-insensitively.
+    #[serde(default = "WeatherAlertsConfig::default_severe_levels")]
+    pub severe_levels: Vec<String>,
+
+    /// Whether the red LED flashes while a severe alert is active.
+    #[serde(default)]
+    pub flash_red_led: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherAlert {
+    pub entity_id: String,
+    pub title: String,
+    pub severity: Option<String>,
+    pub description: Option<String>,
+    pub expires: Option<DateTime<Utc>>,
+    pub severe: bool,
+}
+
+impl WeatherAlert {
+    fn notification_id(&self) -> String {
+        format!("weather-alert:{}:{}", self.entity_id, self.title)
+    }
+}
+
+impl WeatherAlertsConfig {
+    fn default_severe_levels() -> Vec<String> {
+        vec![
+            "severe".to_string(),
+            "extreme".to_string(),
+            "red".to_string(),
+        ]
+    }
+
+    /// Get the active alerts, the severe ones first.
+    pub fn alerts(&self, entities: &HashMap<String, State>) -> Vec<WeatherAlert> {
+        let now = Utc::now();
+        let mut alerts: Vec<_> = self
+            .entities
+            .iter()
+            .filter_map(|entity_id| entities.get(entity_id))
+            .filter(|state| state.is_available())
+            .flat_map(
+                |state| match state.attributes.get::<Vec<serde_json::Value>>("alerts") {
+                    Some(alerts) => alerts
+                        .iter()
+                        .filter_map(|alert| self.alert(&state.entity_id, alert))
+                        .collect(),
+                    _ if Option::<bool>::from(state.clone()).unwrap_or_default() => state
+                        .attributes
+                        .parse()
+                        .ok()
+                        .and_then(|attributes| self.alert(&state.entity_id, &attributes))
+                        .into_iter()
+                        .collect(),
+                    _ => Vec::new(),
+                },
+            )
+            .filter(|alert| !matches!(alert.expires, Some(expires) if expires < now))
+            .collect();
+
+        alerts.sort_by_key(|alert| !alert.severe);
+
+        alerts
+    }
+
+    fn alert(&self, entity_id: &str, attributes: &serde_json::Value) -> Option<WeatherAlert> {
+        let attribute = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| attributes.get(*name))
+                .and_then(serde_json::Value::as_str)
+                .map(ToString::to_string)
+        };
+        let severity = attribute(&["severity", "awareness_level"]);
+
+        Some(WeatherAlert {
+            entity_id: entity_id.to_string(),
+            title: attribute(&["title", "headline", "event", "awareness_type"])?,
+            severe: matches!(&severity, Some(severity) if self.is_severe(severity)),
+            severity,
+            description: attribute(&["description"]),
+            expires: attribute(&["expires", "ends"])
+                .and_then(|expires| DateTime::parse_from_rfc3339(&expires).ok())
+                .map(|expires| expires.with_timezone(&Utc)),
+        })
+    }
+
+    fn is_severe(&self, severity: &str) -> bool {
+        // Meteoalarm levels are like `4; red; Extreme`.
+        severity.split(';').any(|part| {
+            self.severe_levels
+                .iter()
+                .any(|level| part.trim().eq_ignore_ascii_case(level))
+        })
+    }
+
+    /// Raise notifications for the severe alerts, and flash the red LED while
+    /// they are active, forever.
+    pub async fn run(
+        &self,
+        context: &AppContext,
+        notifications: &Notifications,
+    ) -> anyhow::Result<()> {
+        let mut states = context.home_assistant.watch_states();
+        let mut raised = Vec::new();
+        let mut flashing = false;
+
+        loop {
+            let entities = match context.home_assistant.status().await {
+                home_assistant::Status::Connected { entities } => entities,
+                home_assistant::Status::Disconnected => Default::default(),
+            };
+            let severe: Vec<_> = self
+                .alerts(&entities)
+                .into_iter()
+                .filter(|alert| alert.severe)
+                .collect();
+
+            for alert in &severe {
+                notifications
+                    .raise(
+                        alert.notification_id(),
+                        Severity::Critical,
+                        alert.title.clone(),
+                        alert.description.clone().unwrap_or_default(),
+                    )
+                    .await;
+            }
+
+            let active: Vec<_> = severe.iter().map(WeatherAlert::notification_id).collect();
+
+            for id in raised.iter().filter(|id| !active.contains(id)) {
+                notifications.clear(id).await;
+            }
+
+            raised = active;
+
+            let flash = self.flash_red_led && !raised.is_empty();
+
+            if flash != flashing {
+                info!(
+                    "{} flashing the red LED for the severe weather alerts.",
+                    if flash { "Start" } else { "Stop" }
+                );
+
+                flashing = flash;
+            }
+
+            let drive = async {
+                if flash {
+                    OutputPattern::Blink {
+                        period: FLASH_PERIOD,
+                    }
+                    .drive_for(&context.gpio, Output::RedLed, RedLedOwner::WeatherAlerts)
+                    .await
+                } else {
+                    futures_util::future::pending().await
+                }
+            };
+
+            tokio::select! {
+                r = drive => if let Err(err) = r {
+                    warn!("Failed to flash the red LED: {}", err);
+                    states.changed().await?;
+                },
+                r = states.changed() => r?,
+            }
+
+            tasks::heartbeat();
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The Home Assistant weather conditions, with their icon and their labels
+/// in each locale.
+const CONDITIONS: &[(&str, &str, &str, &str)] = &[
+    (
+        "clear-night",
+        "mdi:weather-night",
+        "Nuit dégagée",
+        "Clear night",
+    ),
+    ("cloudy", "mdi:weather-cloudy", "Nuageux", "Cloudy"),
+    ("fog", "mdi:weather-fog", "Brumeux", "Fog"),
+    ("hail", "mdi:weather-hail", "Grêle", "Hail"),
+    ("lightning", "mdi:weather-lightning", "Orage", "Lightning"),
+    (
+        "lightning-rainy",
+        "mdi:weather-lightning-rainy",
+        "Pluie orageuse",
+        "Lightning and rain",
+    ),
+    (
+        "partlycloudy",
+        "mdi:weather-partly-cloudy",
+        "Partiellement nuageux",
+        "Partly cloudy",
+    ),
+    ("pouring", "mdi:weather-pouring", "Pluie forte", "Pouring"),
+    ("rainy", "mdi:weather-rainy", "Pluvieux", "Rainy"),
+    ("snowy", "mdi:weather-snowy", "Neigeux", "Snowy"),
+    (
+        "snowy-rainy",
+        "mdi:weather-snowy-rainy",
+        "Pluie verglaçante",
+        "Snowy and rainy",
+    ),
+    ("sunny", "mdi:weather-sunny", "Ensoleillé", "Sunny"),
+    ("windy", "mdi:weather-windy", "Venteux", "Windy"),
+    (
+        "windy-variant",
+        "mdi:weather-windy-variant",
+        "Vents variables",
+        "Windy and cloudy",
+    ),
+    (
+        "exceptional",
+        "mdi:alert-circle-outline",
+        "Inhabituel",
+        "Exceptional",
+    ),
+];
+
+/// The icon of the conditions that are neither standard nor configured.
+const UNKNOWN_ICON: &str = "mdi:weather-cloudy-alert";
+
+/// The locale of the weather condition labels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Fr,
+    En,
+}
+
+/// How to display the weather conditions.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WeatherConditionsConfig {
+    /// The locale of the built-in labels.
+    #[serde(default)]
+    pub locale: Locale,
+
+    /// The display of specific conditions, like the nonstandard ones of
+    /// custom weather integrations, by condition.
+    #[serde(default)]
+    pub conditions: HashMap<String, WeatherConditionConfig>,
+}
+
+/// The display of a weather condition, defaulting to the one of the standard
+/// condition it is like, if any.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WeatherConditionConfig {
+    /// The standard condition this condition is like, like `rainy` for a
+    /// `drizzle` condition.
+    #[serde(default)]
+    pub like: Option<String>,
+
+    #[serde(default)]
+    pub label: Option<String>,
+
+    /// An Iconify icon name, like `mdi:weather-rainy`.
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+impl WeatherConditionsConfig {
+    /// Get how to display a condition.
+    pub fn display(&self, condition: &str) -> WeatherCondition {
+        let config = self.conditions.get(condition);
+        let standard = config
+            .and_then(|config| config.like.as_deref())
+            .unwrap_or(condition);
+        let builtin = CONDITIONS.iter().find(|(name, ..)| *name == standard);
+
+        WeatherCondition {
+            label: config
+                .and_then(|config| config.label.clone())
+                .or_else(|| {
+                    builtin.map(|(_, _, fr, en)| match self.locale {
+                        Locale::Fr => fr.to_string(),
+                        Locale::En => en.to_string(),
+                    })
+                })
+                .unwrap_or_else(|| condition.to_string()),
+            icon: config
+                .and_then(|config| config.icon.clone())
+                .or_else(|| builtin.map(|(_, icon, ..)| icon.to_string()))
+                .unwrap_or_else(|| UNKNOWN_ICON.to_string()),
+            standard: builtin.map(|(name, ..)| name.to_string()),
+        }
+    }
+}
+
+/// How to display a weather condition.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeatherCondition {
+    pub label: String,
+    pub icon: String,
+
+    /// The standard condition, for the backgrounds, unless the condition is
+    /// neither standard nor like a standard one.
+    pub standard: Option<String>,
+}
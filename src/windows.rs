@@ -0,0 +1,168 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationSeconds};
+
+use crate::home_assistant::State;
+
+/// The window-open detection configuration for a room.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoomWindowsConfig {
+    /// The name of the room, as displayed in the UI.
+    pub room: String,
+
+    /// The climate entity heating the room.
+    pub climate_entity: String,
+
+    /// The door/window `binary_sensor` entities of the room.
+    pub sensors: Vec<String>,
+
+    /// The time a window must stay open while heating before warning.
+    #[serde(default = "RoomWindowsConfig::default_open_timeout")]
+    #[serde_as(as = "DurationSeconds<f64>")]
+    pub open_timeout: Duration,
+}
+
+impl RoomWindowsConfig {
+    fn default_open_timeout() -> Duration {
+        Duration::from_secs(5 * 60)
+    }
+
+    /// Get the notification id for the room.
+    pub fn notification_id(&self) -> String {
+        format!("window-open.{}", self.room)
+    }
+
+    /// Check whether the room is heated while a window has been open for too long.
+    ///
+    /// The time a window has been open is derived from the `last_changed`
+    /// timestamp of its sensor, so no local tracking is required.
+    pub fn is_heating_with_window_open(&self, entities: &HashMap<String, State>) -> bool {
+        let heating = entities
+            .get(&self.climate_entity)
+            .map(|state| {
+                state.state == "heat"
+                    || state.attributes.get::<String>("hvac_action").as_deref() == Some("heating")
+            })
+            .unwrap_or_default();
+
+        heating
+            && self.sensors.iter().any(|sensor| {
+                entities
+                    .get(sensor)
+                    .filter(|state| state.state == "on")
+                    .and_then(|state| (Utc::now() - state.last_changed).to_std().ok())
+                    .map(|open_for| open_for >= self.open_timeout)
+                    .unwrap_or_default()
+            })
+    }
+}
+
+/// Get the rooms that are heated while a window has been open for too long.
+pub fn rooms_with_window_open(
+    rooms: &[RoomWindowsConfig],
+    entities: &HashMap<String, State>,
+) -> Vec<String> {
+    rooms
+        .iter()
+        .filter(|room| room.is_heating_with_window_open(entities))
+        .map(|room| room.room.clone())
+        .collect()
+}
+
+/// A group of door and window sensors, and of door locks, summarized
+/// together.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpeningsConfig {
+    /// The door/window `binary_sensor` entities, and the `lock` entities.
+    pub sensors: Vec<String>,
+
+    /// Whether arming the alarm requires all the openings to be closed and
+    /// locked, unless forced.
+    #[serde(default)]
+    pub block_arming: bool,
+}
+
+/// Why an opening blocks arming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum BlockReason {
+    Open,
+    Unlocked,
+
+    /// The state is unknown, so it could be open.
+    Unavailable,
+}
+
+/// An opening which is not secured.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Blocker {
+    pub entity_id: String,
+    pub friendly_name: Option<String>,
+    pub reason: BlockReason,
+
+    /// When it was opened or unlocked, if known.
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// The summary of the doors and windows.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpeningsStatus {
+    pub open_count: usize,
+
+    /// The openings which are not secured.
+    pub blockers: Vec<Blocker>,
+
+    /// Whether all the openings are closed and locked.
+    pub all_closed: bool,
+}
+
+impl OpeningsConfig {
+    /// Summarize the states of the sensors.
+    pub fn status(&self, entities: &HashMap<String, State>) -> OpeningsStatus {
+        let blockers = self.blockers(entities);
+
+        OpeningsStatus {
+            open_count: blockers
+                .iter()
+                .filter(|blocker| blocker.reason == BlockReason::Open)
+                .count(),
+            all_closed: blockers.is_empty(),
+            blockers,
+        }
+    }
+
+    /// Get the openings which are not secured.
+    pub fn blockers(&self, entities: &HashMap<String, State>) -> Vec<Blocker> {
+        self.sensors
+            .iter()
+            .filter_map(|sensor| {
+                let state = entities.get(sensor);
+                let is_lock = sensor.starts_with("lock.");
+                let reason = match state.map(|state| state.state.as_str()) {
+                    Some("off") if !is_lock => return None,
+                    Some("on") if !is_lock => BlockReason::Open,
+                    Some("locked") if is_lock => return None,
+                    Some("unlocked" | "unlocking" | "open" | "opening" | "jammed") if is_lock => {
+                        BlockReason::Unlocked
+                    }
+                    _ => BlockReason::Unavailable,
+                };
+
+                Some(Blocker {
+                    entity_id: sensor.clone(),
+                    friendly_name: state.and_then(|state| state.attributes.get("friendly_name")),
+                    reason,
+                    since: state
+                        .filter(|_| reason != BlockReason::Unavailable)
+                        .map(|state| state.last_changed),
+                })
+            })
+            .collect()
+    }
+}
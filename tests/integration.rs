@@ -0,0 +1,296 @@
+//! End-to-end tests of home-control against a real Home-Assistant, running in
+//! a container with the demo integration.
+//!
+//! They need Docker, so they are ignored by default: run them with `make
+//! integration`, which removes the container afterwards.
+
+use std::{future::Future, net::TcpListener, path::PathBuf, time::Duration};
+
+use anyhow::{bail, Context};
+use serde_json::{json, Value};
+use tokio::{
+    process::{Child, Command},
+    sync::{Mutex, OnceCell},
+    time::{sleep, Instant},
+};
+
+/// The endpoint Home-Assistant is published on by the compose file.
+const HOME_ASSISTANT_ENDPOINT: &str = "127.0.0.1:18123";
+
+/// The name of the compose project, so that its container can be told from
+/// the others.
+const PROJECT: &str = "home-control-integration";
+
+/// The client the token is provisioned for, which Home-Assistant requires to
+/// be a URL.
+const CLIENT_ID: &str = "http://127.0.0.1/";
+
+/// For how long to wait for Home-Assistant to start, or for a change to show.
+const TIMEOUT: Duration = Duration::from_secs(180);
+
+/// The access token, provisioned once for all the tests.
+static TOKEN: OnceCell<String> = OnceCell::const_new();
+
+/// Runs the tests one at a time, as some restart Home-Assistant.
+static LOCK: Mutex<()> = Mutex::const_new(());
+
+/// The home-control binary, running against the Home-Assistant container.
+struct HomeControl {
+    url: String,
+    client: reqwest::Client,
+
+    /// Killed when dropped.
+    _child: Child,
+}
+
+impl HomeControl {
+    async fn start(token: &str) -> anyhow::Result<Self> {
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let config_file = std::env::temp_dir().join(format!("{}-{}.yaml", PROJECT, port));
+
+        std::fs::write(
+            &config_file,
+            "location: Integration tests\nweather_entity: weather.demo_weather_south\n",
+        )?;
+
+        let child = Command::new(env!("CARGO_BIN_EXE_home-control"))
+            .arg("--config-file")
+            .arg(&config_file)
+            .arg("--stateless")
+            .args(["-t", token])
+            .args(["-l", &format!("127.0.0.1:{}", port)])
+            .arg(HOME_ASSISTANT_ENDPOINT)
+            .kill_on_drop(true)
+            .spawn()
+            .context("failed to start home-control")?;
+
+        Ok(Self {
+            url: format!("http://127.0.0.1:{}/api/v1", port),
+            client: reqwest::Client::new(),
+            _child: child,
+        })
+    }
+
+    async fn get(&self, path: &str) -> anyhow::Result<Value> {
+        Ok(self
+            .client
+            .get(format!("{}{}", self.url, path))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Wait for the status to be `connected` or `disconnected`, and get it.
+    async fn wait_for_status(&self, expected: &str) -> anyhow::Result<Value> {
+        eventually(&format!("the status to be {}", expected), || async move {
+            let status = self.get("/status").await.ok()?;
+
+            (status["status"] == expected).then_some(status)
+        })
+        .await
+    }
+
+    async fn set_light(&self, light: &str, on: bool) -> anyhow::Result<()> {
+        self.client
+            .post(format!("{}/light/{}", self.url, light))
+            .json(&on)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Poll until a condition holds, and get its value.
+async fn eventually<T, F, Fut>(what: &str, mut condition: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    let deadline = Instant::now() + TIMEOUT;
+
+    loop {
+        if let Some(value) = condition().await {
+            return Ok(value);
+        }
+
+        if Instant::now() >= deadline {
+            bail!("timed out waiting for {}", what);
+        }
+
+        sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Run `docker compose` on the Home-Assistant container.
+async fn compose(args: &[&str]) -> anyhow::Result<()> {
+    let file =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/integration/docker-compose.yml");
+    let status = Command::new("docker")
+        .arg("compose")
+        .arg("-f")
+        .arg(file)
+        .args(["-p", PROJECT])
+        .args(args)
+        .status()
+        .await
+        .context("failed to run docker compose")?;
+
+    if !status.success() {
+        bail!("`docker compose {}` failed: {}", args.join(" "), status);
+    }
+
+    Ok(())
+}
+
+/// Get the state of an entity, straight from Home-Assistant.
+async fn entity_state(token: &str, entity_id: &str) -> anyhow::Result<String> {
+    let state: Value = reqwest::Client::new()
+        .get(format!(
+            "http://{}/api/states/{}",
+            HOME_ASSISTANT_ENDPOINT, entity_id
+        ))
+        .bearer_auth(token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    state["state"]
+        .as_str()
+        .map(ToString::to_string)
+        .context("the entity has no state")
+}
+
+/// Start a fresh Home-Assistant, once for all the tests, and get a token.
+async fn token() -> anyhow::Result<&'static str> {
+    TOKEN.get_or_try_init(provision).await.map(String::as_str)
+}
+
+/// Start a fresh Home-Assistant, and get a token by onboarding its owner.
+///
+/// The token is the short-lived access token of the onboarding, which lasts
+/// longer than the tests.
+async fn provision() -> anyhow::Result<String> {
+    compose(&["down", "-v"]).await?;
+    compose(&["up", "-d"]).await?;
+
+    let client = &reqwest::Client::new();
+    let base_url = &format!("http://{}", HOME_ASSISTANT_ENDPOINT);
+
+    eventually("Home-Assistant to start", || async move {
+        client
+            .get(format!("{}/api/onboarding", base_url))
+            .send()
+            .await
+            .ok()?
+            .error_for_status()
+            .ok()
+    })
+    .await?;
+
+    let user: Value = client
+        .post(format!("{}/api/onboarding/users", base_url))
+        .json(&json!({
+            "client_id": CLIENT_ID,
+            "name": "Integration tests",
+            "username": "integration",
+            "password": "integration",
+            "language": "en",
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let auth_code = user["auth_code"]
+        .as_str()
+        .context("the onboarding did not return an authorization code")?;
+    let token: Value = client
+        .post(format!("{}/auth/token", base_url))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", auth_code),
+            ("client_id", CLIENT_ID),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    token["access_token"]
+        .as_str()
+        .map(ToString::to_string)
+        .context("no access token was returned")
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn the_status_is_connected() -> anyhow::Result<()> {
+    let _lock = LOCK.lock().await;
+    let home_control = HomeControl::start(token().await?).await?;
+    let status = home_control.wait_for_status("connected").await?;
+
+    assert_eq!(status["location"], "Integration tests");
+    assert!(status["weatherCurrent"]["temperature"].is_number());
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn lights_are_set() -> anyhow::Result<()> {
+    let _lock = LOCK.lock().await;
+    let token = token().await?;
+    let home_control = &HomeControl::start(token).await?;
+
+    home_control.wait_for_status("connected").await?;
+
+    for (on, state) in [(true, "on"), (false, "off")] {
+        home_control.set_light("kitchen_lights", on).await?;
+
+        eventually(&format!("the light to be {}", state), || async move {
+            (entity_state(token, "light.kitchen_lights").await.ok()? == state).then_some(())
+        })
+        .await?;
+        eventually("the light status to follow", || async move {
+            let light = home_control.get("/light/kitchen_lights").await.ok()?;
+
+            (light["on"] == on).then_some(())
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn home_assistant_is_reconnected_after_a_restart() -> anyhow::Result<()> {
+    let _lock = LOCK.lock().await;
+    let token = token().await?;
+    let home_control = HomeControl::start(token).await?;
+
+    home_control.wait_for_status("connected").await?;
+
+    compose(&["stop"]).await?;
+    home_control.wait_for_status("disconnected").await?;
+
+    compose(&["start"]).await?;
+    home_control.wait_for_status("connected").await?;
+
+    // The calls go through the new connection.
+    home_control.set_light("bed_light", true).await?;
+
+    eventually("the light to be on", || async move {
+        (entity_state(token, "light.bed_light").await.ok()? == "on").then_some(())
+    })
+    .await?;
+
+    Ok(())
+}